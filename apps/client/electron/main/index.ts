@@ -45,6 +45,7 @@ import { env } from "./electron-main-env";
 import {
   getProxyCredentialsForWebContents,
   startPreviewProxy,
+  stopPreviewProxy,
 } from "./task-run-preview-proxy";
 import { normalizeBrowserUrl } from "@cmux/shared";
 import {
@@ -847,6 +848,10 @@ app.whenReady().then(async () => {
       });
       embeddedServerCleanup = null;
     }
+
+    stopPreviewProxy().catch((error) => {
+      console.error("Failed to stop preview proxy", error);
+    });
   });
   registerLogIpcHandlers();
   registerAutoUpdateIpcHandlers();
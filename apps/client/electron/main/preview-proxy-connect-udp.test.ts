@@ -0,0 +1,182 @@
+import dgram from "node:dgram";
+import net, { type AddressInfo } from "node:net";
+import { once } from "node:events";
+import { describe, expect, it } from "vitest";
+
+import {
+  CapsuleReader,
+  decodeVarint,
+  encodeCapsule,
+  encodeVarint,
+  isUdpTargetAllowed,
+  parseConnectUdpTarget,
+  relayConnectUdp,
+  resolveUdpTarget,
+} from "./preview-proxy-connect-udp";
+
+describe("parseConnectUdpTarget", () => {
+  it("reads the host and port from the masque path", () => {
+    expect(
+      parseConnectUdpTarget("/.well-known/masque/udp/Stun.Example.com/3478/")
+    ).toEqual({ host: "stun.example.com", port: 3478 });
+    expect(
+      parseConnectUdpTarget(
+        "https://proxy.local/.well-known/masque/udp/2001%3Adb8%3A%3A1/443/"
+      )
+    ).toEqual({ host: "2001:db8::1", port: 443 });
+  });
+
+  it("rejects other paths and bad ports", () => {
+    expect(parseConnectUdpTarget("/")).toBeNull();
+    expect(parseConnectUdpTarget("/.well-known/masque/udp/a/0/")).toBeNull();
+    expect(
+      parseConnectUdpTarget("/.well-known/masque/udp/a/70000/")
+    ).toBeNull();
+    expect(parseConnectUdpTarget("/.well-known/masque/udp/a/1/b/")).toBeNull();
+  });
+});
+
+describe("isUdpTargetAllowed", () => {
+  const allowlist = [
+    "stun.example.com:3478",
+    "*.turn.example.com",
+    "[::1]:5000",
+  ];
+
+  it("matches hosts, wildcards and ports", () => {
+    expect(
+      isUdpTargetAllowed({ host: "stun.example.com", port: 3478 }, allowlist)
+    ).toBe(true);
+    expect(
+      isUdpTargetAllowed({ host: "a.turn.example.com", port: 1 }, allowlist)
+    ).toBe(true);
+    expect(isUdpTargetAllowed({ host: "::1", port: 5000 }, allowlist)).toBe(
+      true
+    );
+  });
+
+  it("refuses everything else", () => {
+    expect(
+      isUdpTargetAllowed({ host: "stun.example.com", port: 53 }, allowlist)
+    ).toBe(false);
+    expect(
+      isUdpTargetAllowed({ host: "turn.example.com", port: 1 }, allowlist)
+    ).toBe(false);
+    expect(isUdpTargetAllowed({ host: "127.0.0.1", port: 53 }, [])).toBe(false);
+  });
+});
+
+describe("capsules", () => {
+  it("round-trips varints of every length", () => {
+    const values = [0, 63, 64, 16_383, 16_384, 2 ** 30 - 1, 2 ** 30, 2 ** 40];
+    for (const value of values) {
+      const encoded = encodeVarint(value);
+      expect(decodeVarint(encoded, 0)).toEqual({
+        value,
+        length: encoded.length,
+      });
+    }
+  });
+
+  it("reassembles capsules split across chunks", () => {
+    const first = encodeCapsule(0, Buffer.from("hello"));
+    const second = encodeCapsule(0x1234, Buffer.alloc(100, 1));
+    const stream = Buffer.concat([first, second]);
+    const reader = new CapsuleReader();
+
+    expect(reader.push(stream.subarray(0, 3))).toEqual([]);
+    const capsules = reader.push(stream.subarray(3));
+    expect(capsules.map((capsule) => capsule.type)).toEqual([0, 0x1234]);
+    expect(capsules[0]?.value.toString()).toBe("hello");
+    expect(capsules[1]?.value.length).toBe(100);
+  });
+});
+
+describe("relayConnectUdp", () => {
+  const quietLogger = { log: () => {}, warn: () => {} };
+
+  it("picks the socket family from the resolved address", async () => {
+    const aaaaOnly = async () => ({ address: "2001:db8::1", family: 6 });
+    await expect(
+      resolveUdpTarget({ host: "v6.example", port: 53 }, aaaaOnly)
+    ).resolves.toEqual({ address: "2001:db8::1", type: "udp6" });
+    const aOnly = async () => ({ address: "192.0.2.1", family: 4 });
+    await expect(
+      resolveUdpTarget({ host: "v4.example", port: 53 }, aOnly)
+    ).resolves.toEqual({ address: "192.0.2.1", type: "udp4" });
+  });
+
+  it("only relays datagrams from the target", async () => {
+    // The target echoes each datagram and remembers where the relay sends from.
+    const target = dgram.createSocket("udp4");
+    let relayPort = 0;
+    target.on("message", (message, rinfo) => {
+      relayPort = rinfo.port;
+      target.send(message, rinfo.port, rinfo.address);
+    });
+    target.bind(0, "127.0.0.1");
+    await once(target, "listening");
+    const targetPort = target.address().port;
+
+    const server = net.createServer((socket) => {
+      relayConnectUdp(
+        socket,
+        Buffer.alloc(0),
+        { host: "127.0.0.1", port: targetPort },
+        quietLogger
+      );
+    });
+    server.listen(0, "127.0.0.1");
+    await once(server, "listening");
+    const client = net.connect((server.address() as AddressInfo).port);
+
+    const reader = new CapsuleReader();
+    const payloads: string[] = [];
+    let sawHeaders = false;
+    const received = (count: number) =>
+      new Promise<void>((resolve) => {
+        const check = () => {
+          if (payloads.length >= count) {
+            client.off("data", check);
+            resolve();
+          }
+        };
+        client.on("data", check);
+        check();
+      });
+    client.on("data", (chunk: Buffer) => {
+      let data = chunk;
+      if (!sawHeaders) {
+        const text = chunk.toString("latin1");
+        const end = text.indexOf("\r\n\r\n");
+        expect(text.startsWith("HTTP/1.1 101")).toBe(true);
+        sawHeaders = true;
+        data = chunk.subarray(end + 4);
+      }
+      for (const capsule of reader.push(data)) {
+        payloads.push(capsule.value.subarray(1).toString());
+      }
+    });
+    const sendDatagram = (text: string) =>
+      client.write(
+        encodeCapsule(0, Buffer.concat([encodeVarint(0), Buffer.from(text)]))
+      );
+
+    sendDatagram("ping");
+    await received(1);
+
+    const intruder = dgram.createSocket("udp4");
+    await new Promise<void>((resolve) =>
+      intruder.send("injected", relayPort, "127.0.0.1", () => resolve())
+    );
+    sendDatagram("done");
+    await received(2);
+
+    expect(payloads).toEqual(["ping", "done"]);
+
+    client.destroy();
+    intruder.close();
+    target.close();
+    server.close();
+  });
+});
@@ -0,0 +1,308 @@
+import dgram from "node:dgram";
+import dns from "node:dns/promises";
+import net, { type Socket } from "node:net";
+import type { IncomingMessage } from "node:http";
+
+// Experimental CONNECT-UDP (RFC 9298) over an HTTP/1.1 upgrade. The client
+// asks for `/.well-known/masque/udp/{host}/{port}/`; after the 101 both sides
+// exchange capsules (RFC 9297) and every DATAGRAM capsule with context ID 0
+// carries one UDP payload, relayed through a UDP socket owned by the tunnel.
+
+const MASQUE_UDP_PREFIX = "/.well-known/masque/udp/";
+const DATAGRAM_CAPSULE_TYPE = 0x00;
+const UDP_PAYLOAD_CONTEXT_ID = 0;
+// Largest UDP payload plus room for the context ID.
+const MAX_CAPSULE_LENGTH = 65_527 + 8;
+
+export interface UdpTarget {
+  host: string;
+  port: number;
+}
+
+export interface Capsule {
+  type: number;
+  value: Buffer;
+}
+
+export interface ConnectUdpLogger {
+  log(event: string, data?: Record<string, unknown>): void;
+  warn(event: string, data?: Record<string, unknown>): void;
+}
+
+export function isConnectUdpUpgrade(req: IncomingMessage): boolean {
+  const upgrade = req.headers.upgrade;
+  return typeof upgrade === "string" && upgrade.toLowerCase() === "connect-udp";
+}
+
+/** Target of a CONNECT-UDP request from its default URI template path. */
+export function parseConnectUdpTarget(url: string): UdpTarget | null {
+  let path = url;
+  if (/^[a-z]+:\/\//i.test(url)) {
+    try {
+      path = new URL(url).pathname;
+    } catch {
+      return null;
+    }
+  }
+  path = path.split("?")[0] ?? "";
+  if (!path.startsWith(MASQUE_UDP_PREFIX)) {
+    return null;
+  }
+  const [rawHost, rawPort, ...rest] = path
+    .slice(MASQUE_UDP_PREFIX.length)
+    .split("/");
+  if (!rawHost || !rawPort || rest.some((segment) => segment !== "")) {
+    return null;
+  }
+  let host: string;
+  try {
+    // IPv6 literals arrive percent-encoded (`2001%3Adb8%3A%3A1`).
+    host = decodeURIComponent(rawHost).toLowerCase();
+  } catch {
+    return null;
+  }
+  if (!/^\d{1,5}$/.test(rawPort)) {
+    return null;
+  }
+  const port = Number(rawPort);
+  if (port < 1 || port > 65_535) {
+    return null;
+  }
+  return { host, port };
+}
+
+/**
+ * Whether `target` matches an allowlist entry: `host`, `host:port`,
+ * `*.domain` or `*.domain:port` (IPv6 hosts in brackets when a port is given).
+ */
+export function isUdpTargetAllowed(
+  target: UdpTarget,
+  allowlist: readonly string[]
+): boolean {
+  return allowlist.some((rawEntry) => {
+    const entry = rawEntry.trim().toLowerCase();
+    if (!entry) {
+      return false;
+    }
+    let pattern = entry;
+    let port: number | null = null;
+    const bracketed = /^\[([^\]]+)\](?::(\d+))?$/.exec(entry);
+    if (bracketed) {
+      pattern = bracketed[1] ?? "";
+      port = bracketed[2] ? Number(bracketed[2]) : null;
+    } else if (!net.isIPv6(entry)) {
+      const separator = entry.lastIndexOf(":");
+      if (separator !== -1) {
+        pattern = entry.slice(0, separator);
+        port = Number(entry.slice(separator + 1));
+      }
+    }
+    if (port !== null && port !== target.port) {
+      return false;
+    }
+    if (pattern.startsWith("*.")) {
+      return target.host.endsWith(pattern.slice(1));
+    }
+    return target.host === pattern;
+  });
+}
+
+export function encodeVarint(value: number): Buffer {
+  if (value < 0x40) {
+    return Buffer.from([value]);
+  }
+  if (value < 0x4000) {
+    const buffer = Buffer.alloc(2);
+    buffer.writeUInt16BE(value | 0x4000);
+    return buffer;
+  }
+  if (value < 0x4000_0000) {
+    const buffer = Buffer.alloc(4);
+    buffer.writeUInt32BE((value | 0x8000_0000) >>> 0);
+    return buffer;
+  }
+  const buffer = Buffer.alloc(8);
+  buffer.writeBigUInt64BE(BigInt(value) | 0xc000_0000_0000_0000n);
+  return buffer;
+}
+
+/** A varint at `offset` and its encoded length, or null if incomplete. */
+export function decodeVarint(
+  buffer: Buffer,
+  offset: number
+): { value: number; length: number } | null {
+  if (offset >= buffer.length) {
+    return null;
+  }
+  const first = buffer[offset] ?? 0;
+  const length = 1 << (first >> 6);
+  if (offset + length > buffer.length) {
+    return null;
+  }
+  let value = BigInt(first & 0x3f);
+  for (let i = 1; i < length; i += 1) {
+    value = (value << 8n) | BigInt(buffer[offset + i] ?? 0);
+  }
+  if (value > BigInt(Number.MAX_SAFE_INTEGER)) {
+    return null;
+  }
+  return { value: Number(value), length };
+}
+
+export function encodeCapsule(type: number, value: Buffer): Buffer {
+  return Buffer.concat([encodeVarint(type), encodeVarint(value.length), value]);
+}
+
+/**
+ * Incremental capsule parser. Feed it socket data; it returns every complete
+ * capsule and keeps the remainder for the next chunk.
+ */
+export class CapsuleReader {
+  private pending: Buffer = Buffer.alloc(0);
+
+  push(chunk: Buffer): Capsule[] {
+    this.pending =
+      this.pending.length === 0 ? chunk : Buffer.concat([this.pending, chunk]);
+    const capsules: Capsule[] = [];
+    let offset = 0;
+    for (;;) {
+      const type = decodeVarint(this.pending, offset);
+      if (!type) break;
+      const length = decodeVarint(this.pending, offset + type.length);
+      if (!length) break;
+      if (length.value > MAX_CAPSULE_LENGTH) {
+        throw new Error(`capsule of ${length.value} bytes is too large`);
+      }
+      const start = offset + type.length + length.length;
+      const end = start + length.value;
+      if (end > this.pending.length) break;
+      capsules.push({
+        type: type.value,
+        value: this.pending.subarray(start, end),
+      });
+      offset = end;
+    }
+    this.pending = this.pending.subarray(offset);
+    return capsules;
+  }
+}
+
+export type UdpLookup = (
+  hostname: string
+) => Promise<{ address: string; family: number }>;
+
+const defaultLookup: UdpLookup = (hostname) => dns.lookup(hostname);
+
+/**
+ * Address to relay to and the socket type that can reach it. Names are
+ * resolved first so AAAA-only targets get a `udp6` socket.
+ */
+export async function resolveUdpTarget(
+  target: UdpTarget,
+  lookup: UdpLookup = defaultLookup
+): Promise<{ address: string; type: dgram.SocketType }> {
+  const { address, family } = await lookup(target.host);
+  return { address, type: family === 6 ? "udp6" : "udp4" };
+}
+
+/**
+ * Answer a CONNECT-UDP upgrade and relay datagrams until either side closes.
+ * The caller has authenticated the request and checked the allowlist.
+ *
+ * The UDP socket is connected to the resolved target, so the kernel drops
+ * datagrams from any other source instead of letting them into the tunnel.
+ */
+export function relayConnectUdp(
+  socket: Socket,
+  head: Buffer,
+  target: UdpTarget,
+  logger: ConnectUdpLogger,
+  lookup: UdpLookup = defaultLookup
+): void {
+  const reader = new CapsuleReader();
+  let udp: dgram.Socket | null = null;
+  let closed = false;
+
+  const close = (reason: string, error?: unknown) => {
+    if (closed) return;
+    closed = true;
+    logger.log("connect-udp-closed", { target, reason, error });
+    try {
+      udp?.close();
+    } catch {
+      // already closed
+    }
+    socket.destroy();
+  };
+
+  const onData = (chunk: Buffer) => {
+    let capsules: Capsule[];
+    try {
+      capsules = reader.push(chunk);
+    } catch (error) {
+      close("invalid-capsule", error);
+      return;
+    }
+    for (const capsule of capsules) {
+      // Unknown capsule types are skipped, as RFC 9297 requires.
+      if (capsule.type !== DATAGRAM_CAPSULE_TYPE) continue;
+      const contextId = decodeVarint(capsule.value, 0);
+      if (!contextId || contextId.value !== UDP_PAYLOAD_CONTEXT_ID) continue;
+      udp?.send(capsule.value.subarray(contextId.length), (error) => {
+        if (error) {
+          logger.warn("connect-udp-send-error", { target, error });
+        }
+      });
+    }
+  };
+
+  const start = (connected: dgram.Socket) => {
+    connected.on("message", (message) => {
+      if (closed) return;
+      socket.write(
+        encodeCapsule(
+          DATAGRAM_CAPSULE_TYPE,
+          Buffer.concat([encodeVarint(UDP_PAYLOAD_CONTEXT_ID), message])
+        )
+      );
+    });
+    socket.write(
+      "HTTP/1.1 101 Switching Protocols\r\n" +
+        "Connection: Upgrade\r\n" +
+        "Upgrade: connect-udp\r\n" +
+        "Capsule-Protocol: ?1\r\n\r\n"
+    );
+    logger.log("connect-udp-open", {
+      target,
+      address: connected.remoteAddress(),
+    });
+    if (head.length > 0) {
+      onData(head);
+    }
+    socket.on("data", onData);
+    socket.resume();
+  };
+
+  // Capsules wait in the socket until the UDP side is connected.
+  socket.pause();
+  socket.on("error", (error) => close("socket-error", error));
+  socket.on("close", () => close("socket-closed"));
+
+  resolveUdpTarget(target, lookup).then(
+    ({ address, type }) => {
+      if (closed) return;
+      const created = dgram.createSocket(type);
+      udp = created;
+      created.on("error", (error) => close("udp-error", error));
+      created.connect(target.port, address, () => {
+        if (!closed) start(created);
+      });
+    },
+    (error: unknown) => {
+      if (closed) return;
+      closed = true;
+      logger.warn("connect-udp-resolve-failed", { target, error });
+      socket.end("HTTP/1.1 502 Bad Gateway\r\n\r\n");
+    }
+  );
+}
@@ -0,0 +1,34 @@
+import { describe, expect, it } from "vitest";
+
+import { isDestinationAllowed } from "./preview-proxy-destinations";
+
+describe("isDestinationAllowed", () => {
+  it("allows everything without a policy", () => {
+    expect(isDestinationAllowed({}, "example.com", 443)).toBe(true);
+  });
+
+  it("matches exact hosts and wildcard subdomains", () => {
+    const policy = { allowedHosts: ["localhost", "*.cmux.app", "[::1]"] };
+    expect(isDestinationAllowed(policy, "LOCALHOST", 3000)).toBe(true);
+    expect(isDestinationAllowed(policy, "a.b.cmux.app.", 443)).toBe(true);
+    expect(isDestinationAllowed(policy, "[::1]", 80)).toBe(true);
+    expect(isDestinationAllowed(policy, "cmux.app", 443)).toBe(false);
+    expect(isDestinationAllowed(policy, "evilcmux.app", 443)).toBe(false);
+    expect(isDestinationAllowed(policy, "example.com", 443)).toBe(false);
+  });
+
+  it("refuses blocked ports on any host", () => {
+    const policy = { allowedHosts: ["localhost"], blockedPorts: [22] };
+    expect(isDestinationAllowed(policy, "localhost", 22)).toBe(false);
+    expect(isDestinationAllowed({ blockedPorts: [22] }, "a.com", 22)).toBe(
+      false
+    );
+    expect(isDestinationAllowed(policy, "localhost", 3000)).toBe(true);
+  });
+
+  it("allows nothing with an empty host list", () => {
+    expect(isDestinationAllowed({ allowedHosts: [] }, "localhost", 80)).toBe(
+      false
+    );
+  });
+});
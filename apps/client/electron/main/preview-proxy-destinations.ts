@@ -0,0 +1,47 @@
+/**
+ * Per-view destination limits for the preview proxy, so a preview can be
+ * kept to its own workspace instead of using the proxy as an open relay.
+ * Checks apply to the host and port the page asked for, before loopback
+ * hosts are routed to the workspace.
+ */
+export interface DestinationPolicy {
+  /** Hosts the view may reach: `a.com` or `*.a.com`. Unset allows any. */
+  allowedHosts?: readonly string[];
+  /** Ports the view may never reach. */
+  blockedPorts?: readonly number[];
+}
+
+export function isDestinationAllowed(
+  policy: DestinationPolicy,
+  host: string,
+  port: number
+): boolean {
+  if (policy.blockedPorts?.includes(port)) {
+    return false;
+  }
+  if (!policy.allowedHosts) {
+    return true;
+  }
+  const normalized = normalizeHost(host);
+  return policy.allowedHosts.some((pattern) =>
+    matchesHostPattern(normalized, normalizeHost(pattern))
+  );
+}
+
+function matchesHostPattern(host: string, pattern: string): boolean {
+  if (!pattern) {
+    return false;
+  }
+  if (pattern.startsWith("*.")) {
+    return host.endsWith(pattern.slice(1));
+  }
+  return host === pattern;
+}
+
+function normalizeHost(host: string): string {
+  let normalized = host.trim().toLowerCase();
+  if (normalized.startsWith("[") && normalized.endsWith("]")) {
+    normalized = normalized.slice(1, -1);
+  }
+  return normalized.endsWith(".") ? normalized.slice(0, -1) : normalized;
+}
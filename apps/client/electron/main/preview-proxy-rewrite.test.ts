@@ -0,0 +1,100 @@
+import { Readable, type Duplex } from "node:stream";
+import zlib from "node:zlib";
+import { describe, expect, it } from "vitest";
+
+import {
+  createLoopbackRewriteStreams,
+  isRewritableContentType,
+  LoopbackOriginRewriter,
+} from "./preview-proxy-rewrite";
+
+const hostForPort = (port: number) => `cmux-abc-base-${port}.cmux.app`;
+
+async function readThrough(
+  chunks: Buffer[],
+  streams: Duplex[]
+): Promise<string> {
+  const output = streams.reduce<Readable>(
+    (input, stream) => input.pipe(stream),
+    Readable.from(chunks)
+  );
+  let text = "";
+  for await (const chunk of output) {
+    text += String(chunk);
+  }
+  return text;
+}
+
+function rewriteInChunks(input: string, size: number): Promise<string> {
+  const bytes = Buffer.from(input);
+  const chunks: Buffer[] = [];
+  for (let offset = 0; offset < bytes.length; offset += size) {
+    chunks.push(bytes.subarray(offset, offset + size));
+  }
+  return readThrough(chunks, [new LoopbackOriginRewriter(hostForPort)]);
+}
+
+describe("LoopbackOriginRewriter", () => {
+  const input = [
+    '<a href="http://localhost:3000/x">',
+    "HTTP://127.0.0.1/z",
+    "ws://[::1]:5173/hmr",
+    '"https://0.0.0.0:8443"',
+    "http://localhost.example.com/",
+    "http://localhost:3000000",
+    "ünïcode",
+  ].join(" ");
+  const expected = [
+    '<a href="https://cmux-abc-base-3000.cmux.app/x">',
+    "https://cmux-abc-base-80.cmux.app/z",
+    "wss://cmux-abc-base-5173.cmux.app/hmr",
+    '"https://cmux-abc-base-8443.cmux.app"',
+    "http://localhost.example.com/",
+    "http://localhost:3000000",
+    "ünïcode",
+  ].join(" ");
+
+  it("maps loopback origins to the cmux host for their port", async () => {
+    expect(await rewriteInChunks(input, 4096)).toBe(expected);
+  });
+
+  it("handles origins and characters split across chunks", async () => {
+    for (const size of [1, 2, 3, 5, 7, 13]) {
+      expect(await rewriteInChunks(input, size), `size ${size}`).toBe(
+        expected
+      );
+    }
+  });
+});
+
+describe("createLoopbackRewriteStreams", () => {
+  it("only rewrites HTML, JavaScript and JSON", () => {
+    expect(isRewritableContentType("text/html; charset=utf-8")).toBe(true);
+    expect(isRewritableContentType("application/javascript")).toBe(true);
+    expect(isRewritableContentType("application/manifest+json")).toBe(true);
+    expect(isRewritableContentType("image/png")).toBe(false);
+    expect(isRewritableContentType(undefined)).toBe(false);
+    expect(
+      createLoopbackRewriteStreams("text/css", undefined, hostForPort)
+    ).toBeNull();
+  });
+
+  it("decodes compressed bodies and skips unknown encodings", async () => {
+    const streams = createLoopbackRewriteStreams(
+      "application/json",
+      "gzip",
+      hostForPort
+    );
+    if (!streams) {
+      throw new Error("expected rewrite streams");
+    }
+    const output = await readThrough(
+      [zlib.gzipSync('{"url":"http://localhost:4000"}')],
+      streams
+    );
+    expect(output).toBe('{"url":"https://cmux-abc-base-4000.cmux.app"}');
+    expect(
+      createLoopbackRewriteStreams("text/html", "zstd", hostForPort)
+    ).toBeNull();
+  });
+});
@@ -0,0 +1,118 @@
+import { Transform, type Duplex, type TransformCallback } from "node:stream";
+import { StringDecoder } from "node:string_decoder";
+import zlib from "node:zlib";
+
+// Dev servers emit absolute `http://localhost:3000/...` URLs that break once
+// the page is served from a cmux host; these rewrite them on the way through.
+
+const REWRITABLE_CONTENT_TYPES = new Set([
+  "text/html",
+  "text/javascript",
+  "application/javascript",
+  "application/json",
+]);
+
+const LOOPBACK_ORIGIN =
+  /(https?|wss?):\/\/(?:localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1\])(?::(\d{1,5}))?(?![\w.:-])/gi;
+
+// A match starting before the last this-many characters of a chunk is
+// complete, including the character its lookahead checks.
+const HOLD_BACK = "https://127.0.0.1:65535".length + 1;
+
+export function isRewritableContentType(value: string | undefined): boolean {
+  const type = value?.split(";")[0]?.trim().toLowerCase();
+  if (!type) {
+    return false;
+  }
+  return REWRITABLE_CONTENT_TYPES.has(type) || type.endsWith("+json");
+}
+
+/**
+ * Streams that decode and rewrite a response body, or null when the body
+ * can't be rewritten. Callers must drop `content-length` and
+ * `content-encoding`, since the output is plain and of a different size.
+ */
+export function createLoopbackRewriteStreams(
+  contentType: string | undefined,
+  contentEncoding: string | undefined,
+  hostForPort: (port: number) => string
+): Duplex[] | null {
+  if (!isRewritableContentType(contentType)) {
+    return null;
+  }
+  const rewriter = new LoopbackOriginRewriter(hostForPort);
+  switch (contentEncoding?.trim().toLowerCase() || "identity") {
+    case "identity":
+      return [rewriter];
+    case "gzip":
+    case "x-gzip":
+      return [zlib.createGunzip(), rewriter];
+    case "deflate":
+      return [zlib.createInflate(), rewriter];
+    case "br":
+      return [zlib.createBrotliDecompress(), rewriter];
+    default:
+      return null;
+  }
+}
+
+/** Replaces loopback origins with `https://<hostForPort(port)>`. */
+export class LoopbackOriginRewriter extends Transform {
+  private readonly decoder = new StringDecoder("utf8");
+  private pending = "";
+
+  constructor(private readonly hostForPort: (port: number) => string) {
+    super();
+  }
+
+  override _transform(
+    chunk: Buffer,
+    _encoding: BufferEncoding,
+    callback: TransformCallback
+  ): void {
+    const text = this.pending + this.decoder.write(chunk);
+    this.pushText(this.rewrite(text, Math.max(0, text.length - HOLD_BACK)));
+    callback();
+  }
+
+  override _flush(callback: TransformCallback): void {
+    const text = this.pending + this.decoder.end();
+    this.pushText(this.rewrite(text, text.length));
+    callback();
+  }
+
+  private pushText(text: string): void {
+    if (text) {
+      this.push(text);
+    }
+  }
+
+  /** Rewrite `text` up to `cut`, keeping the rest for the next chunk. */
+  private rewrite(text: string, cut: number): string {
+    let output = "";
+    let last = 0;
+    for (const match of text.matchAll(LOOPBACK_ORIGIN)) {
+      const start = match.index ?? 0;
+      if (start >= cut) {
+        break;
+      }
+      const end = start + match[0].length;
+      if (end > cut) {
+        cut = start;
+        break;
+      }
+      output += text.slice(last, start) + this.replacement(match);
+      last = end;
+    }
+    this.pending = text.slice(cut);
+    return output + text.slice(last, cut);
+  }
+
+  private replacement(match: RegExpMatchArray): string {
+    const scheme = (match[1] ?? "http").toLowerCase();
+    const secure = scheme === "https" || scheme === "wss";
+    const port = match[2] ? Number(match[2]) : secure ? 443 : 80;
+    const outScheme = scheme.startsWith("ws") ? "wss" : "https";
+    return `${outScheme}://${this.hostForPort(port)}`;
+  }
+}
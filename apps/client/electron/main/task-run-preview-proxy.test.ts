@@ -0,0 +1,264 @@
+import http from "node:http";
+import net from "node:net";
+import zlib from "node:zlib";
+import type { Session } from "electron";
+import { afterEach, describe, expect, it } from "vitest";
+
+import {
+  buildPreviewProxyPac,
+  configurePreviewProxyForView,
+  getProxyCredentialsForWebContents,
+  startPreviewProxy,
+  startPreviewProxyPacServer,
+  stopPreviewProxy,
+} from "./task-run-preview-proxy";
+
+type ProxyConfig = Parameters<Session["setProxy"]>[0];
+
+const logger = { log: () => {}, warn: () => {}, error: () => {} };
+
+interface FakeViewOptions {
+  initialUrl?: string;
+  allowedHosts?: string[];
+  blockedPorts?: number[];
+  rewriteLoopbackUrls?: boolean;
+}
+
+async function configureFakeView(
+  id: number,
+  options: FakeViewOptions = {}
+): Promise<ProxyConfig[]> {
+  const proxyConfigs: ProxyConfig[] = [];
+  await configurePreviewProxyForView({
+    initialUrl: "https://cmux-abc-base-3000.cmux.app/",
+    ...options,
+    webContents: {
+      id,
+      session: {
+        setProxy: async (config: ProxyConfig) => {
+          proxyConfigs.push(config);
+        },
+      },
+      once: () => undefined,
+    },
+    logger,
+  });
+  return proxyConfigs;
+}
+
+function proxyAuthorization(id: number): string {
+  const credentials = getProxyCredentialsForWebContents(id);
+  if (!credentials) {
+    throw new Error("expected proxy credentials");
+  }
+  const token = Buffer.from(
+    `${credentials.username}:${credentials.password}`
+  ).toString("base64");
+  return `Basic ${token}`;
+}
+
+/** Send a raw request to the proxy and return its status line. */
+function proxyStatusLine(port: number, request: string): Promise<string> {
+  return new Promise((resolve, reject) => {
+    const socket = net.connect(port, "127.0.0.1", () => {
+      socket.write(request);
+    });
+    socket.once("data", (data) => {
+      resolve(data.toString("latin1").split("\r\n")[0] ?? "");
+      socket.destroy();
+    });
+    socket.once("error", reject);
+  });
+}
+
+function loadPac(port: number): (url: string, host: string) => string {
+  const dnsDomainIs = (host: string, domain: string) =>
+    host.length >= domain.length && host.endsWith(domain);
+  return new Function(
+    "dnsDomainIs",
+    `${buildPreviewProxyPac(port)}\nreturn FindProxyForURL;`
+  )(dnsDomainIs);
+}
+
+describe("buildPreviewProxyPac", () => {
+  const findProxy = loadPac(39385);
+  const route = (host: string) => findProxy(`http://${host}/`, host);
+
+  it("sends loopback hosts through the preview proxy", () => {
+    for (const host of [
+      "localhost",
+      "WORKSPACE-3000.localhost",
+      "127.0.0.1",
+      "127.18.0.5",
+      "0.0.0.0",
+      "::1",
+      "[::1]",
+      "::ffff:127.0.0.1",
+    ]) {
+      expect(route(host), host).toBe("PROXY 127.0.0.1:39385");
+    }
+  });
+
+  it("sends cmux domains through the preview proxy", () => {
+    expect(route("cmux.app")).toBe("PROXY 127.0.0.1:39385");
+    expect(route("cmux-abc-base-3000.cmux.local")).toBe(
+      "PROXY 127.0.0.1:39385"
+    );
+  });
+
+  it("connects everything else directly", () => {
+    for (const host of [
+      "example.com",
+      "notcmux.app",
+      "cmux.app.example.com",
+      "1270.0.0.1",
+      "10.0.0.1",
+    ]) {
+      expect(route(host), host).toBe("DIRECT");
+    }
+  });
+});
+
+describe("startPreviewProxyPacServer", () => {
+  it("serves the PAC on its own loopback listener", async () => {
+    const { server, url } = await startPreviewProxyPacServer(39385);
+    try {
+      const address = server.address();
+      if (!address || typeof address === "string") {
+        throw new Error("expected a TCP address");
+      }
+      expect(address.address).toBe("127.0.0.1");
+      expect(address.port).not.toBe(39385);
+      expect(url).toBe(`http://127.0.0.1:${address.port}/proxy.pac`);
+
+      const response = await fetch(url);
+      expect(response.status).toBe(200);
+      expect(response.headers.get("content-type")).toBe(
+        "application/x-ns-proxy-autoconfig"
+      );
+      expect(await response.text()).toBe(buildPreviewProxyPac(39385));
+
+      const other = await fetch(new URL("/other", url));
+      expect(other.status).toBe(404);
+      const post = await fetch(url, { method: "POST" });
+      expect(post.status).toBe(405);
+    } finally {
+      await new Promise((resolve) => server.close(resolve));
+    }
+  });
+});
+
+describe("configurePreviewProxyForView", () => {
+  afterEach(() => stopPreviewProxy());
+
+  it("points the session at the PAC until the proxy stops", async () => {
+    const [config] = await configureFakeView(1);
+    expect(config?.mode).toBe("pac_script");
+    const pacScript = config?.pacScript;
+    if (!pacScript) {
+      throw new Error("expected a PAC URL");
+    }
+    expect(new URL(pacScript).hostname).toBe("127.0.0.1");
+
+    const response = await fetch(pacScript);
+    expect(await response.text()).toMatch(/PROXY 127\.0\.0\.1:\d+/);
+
+    await stopPreviewProxy();
+    await expect(fetch(pacScript)).rejects.toThrow();
+  });
+});
+
+describe("preview proxy destination filtering", () => {
+  afterEach(() => stopPreviewProxy());
+
+  it("refuses hosts and ports outside the view's policy", async () => {
+    await configureFakeView(2, {
+      allowedHosts: ["localhost"],
+      blockedPorts: [22],
+    });
+    const port = await startPreviewProxy(logger);
+    const auth = proxyAuthorization(2);
+    const request = (line: string, host: string, extra = "") =>
+      `${line} HTTP/1.1\r\nHost: ${host}\r\n` +
+      `Proxy-Authorization: ${auth}\r\n${extra}\r\n`;
+
+    for (const raw of [
+      request("GET http://example.com/", "example.com"),
+      request("GET http://localhost:22/", "localhost:22"),
+      request("CONNECT example.com:443", "example.com:443"),
+      request("CONNECT localhost:22", "localhost:22"),
+      request(
+        "GET ws://example.com/",
+        "example.com",
+        "Connection: Upgrade\r\nUpgrade: websocket\r\n"
+      ),
+    ]) {
+      expect(await proxyStatusLine(port, raw), raw).toBe(
+        "HTTP/1.1 403 Forbidden"
+      );
+    }
+  });
+});
+
+describe("preview proxy loopback URL rewriting", () => {
+  afterEach(() => stopPreviewProxy());
+
+  it("rewrites loopback URLs for views that opt in", async () => {
+    const html = '<script src="http://localhost:3000/app.js"></script>';
+    const upstream = http.createServer((_req, res) => {
+      res.writeHead(200, {
+        "Content-Type": "text/html; charset=utf-8",
+        "Content-Encoding": "gzip",
+      });
+      res.end(zlib.gzipSync(html));
+    });
+    await new Promise<void>((resolve) => {
+      upstream.listen(0, "127.0.0.1", resolve);
+    });
+    const address = upstream.address();
+    if (!address || typeof address === "string") {
+      throw new Error("expected a TCP address");
+    }
+    process.env.TEST_CMUX_PROXY_ORIGIN = `http://127.0.0.1:${address.port}`;
+    try {
+      await configureFakeView(3, {
+        initialUrl: "https://cmux-test-base-8080.cmux.local/",
+        rewriteLoopbackUrls: true,
+      });
+      const port = await startPreviewProxy(logger);
+      const response = await new Promise<{
+        headers: http.IncomingHttpHeaders;
+        body: string;
+      }>((resolve, reject) => {
+        const req = http.request(
+          {
+            host: "127.0.0.1",
+            port,
+            path: "http://localhost:3000/",
+            headers: {
+              Host: "localhost:3000",
+              "Proxy-Authorization": proxyAuthorization(3),
+            },
+          },
+          (res) => {
+            let body = "";
+            res.setEncoding("utf8");
+            res.on("data", (chunk) => {
+              body += chunk;
+            });
+            res.on("end", () => resolve({ headers: res.headers, body }));
+          }
+        );
+        req.on("error", reject);
+        req.end();
+      });
+      expect(response.headers["content-encoding"]).toBeUndefined();
+      expect(response.body).toBe(
+        '<script src="https://cmux-test-base-3000.cmux.local/app.js"></script>'
+      );
+    } finally {
+      delete process.env.TEST_CMUX_PROXY_ORIGIN;
+      await new Promise((resolve) => upstream.close(resolve));
+    }
+  });
+});
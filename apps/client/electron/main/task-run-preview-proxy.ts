@@ -9,11 +9,20 @@ import http2 from "node:http2";
 import net, { type Socket } from "node:net";
 import tls, { type TLSSocket } from "node:tls";
 import { randomBytes, createHash } from "node:crypto";
+import type { Duplex } from "node:stream";
 import { pipeline as streamPipeline } from "node:stream/promises";
 import { URL } from "node:url";
-import type { Session, WebContents } from "electron";
+import type { Session } from "electron";
 import { isLoopbackHostname } from "@cmux/shared";
 import { CertificateManager } from "./preview-proxy-certs";
+import {
+  isConnectUdpUpgrade,
+  isUdpTargetAllowed,
+  parseConnectUdpTarget,
+  relayConnectUdp,
+} from "./preview-proxy-connect-udp";
+import { isDestinationAllowed } from "./preview-proxy-destinations";
+import { createLoopbackRewriteStreams } from "./preview-proxy-rewrite";
 import type { Logger } from "./chrome-camouflage";
 
 type ProxyServer = http.Server;
@@ -36,6 +45,13 @@ const HOP_BY_HOP_HEADERS = new Set([
 ]);
 
 const TASK_RUN_PREVIEW_PREFIX = "task-run-preview:";
+const PAC_PATH = "/proxy.pac";
+const PAC_LOOPBACK_HOSTS = [
+  "localhost",
+  "0.0.0.0",
+  "::1",
+  "::ffff:127.0.0.1",
+] as const;
 
 function envFlagEnabled(
   value: string | undefined,
@@ -97,6 +113,24 @@ const HTTPS1_KEEP_ALIVE_AGENT = new https.Agent({
   maxFreeSockets: 64,
 });
 
+// Experimental: WebRTC in previews needs UDP, which CONNECT can't carry.
+const ENABLE_CONNECT_UDP = envFlagEnabled(
+  process.env.CMUX_PREVIEW_CONNECT_UDP,
+  false
+);
+// Rewrite absolute loopback URLs in HTML, JS and JSON responses to the view's
+// cmux host; views can also opt in themselves.
+const REWRITE_LOOPBACK_URLS = envFlagEnabled(
+  process.env.CMUX_PREVIEW_REWRITE_LOOPBACK_URLS,
+  false
+);
+// Destinations every preview may reach over CONNECT-UDP (e.g. STUN/TURN
+// servers), comma-separated; views can add their own.
+const DEFAULT_UDP_ALLOWLIST = (process.env.CMUX_PREVIEW_UDP_ALLOWLIST ?? "")
+  .split(",")
+  .map((entry) => entry.trim())
+  .filter(Boolean);
+
 const ENABLE_TLS_MITM =
   process.env.CMUX_PREVIEW_TLS_MITM === undefined ||
   process.env.CMUX_PREVIEW_TLS_MITM === "" ||
@@ -114,9 +148,13 @@ interface ProxyContext {
   password: string;
   authToken: string;
   route: ProxyRoute | null;
-  session: Session;
+  session: PreviewWebContents["session"];
   webContentsId: number;
   persistKey?: string;
+  udpAllowlist: string[];
+  allowedHosts?: string[];
+  blockedPorts?: number[];
+  rewriteLoopbackUrls: boolean;
 }
 
 interface CmuxProxyMetadata {
@@ -135,17 +173,34 @@ interface ConnectTunnelOptions {
   clientResponseAlreadySent?: boolean;
 }
 
+/** The parts of a view's `WebContents` the proxy uses. */
+interface PreviewWebContents {
+  id: number;
+  session: Pick<Session, "setProxy">;
+  once(event: "destroyed", listener: () => void): unknown;
+}
+
 interface ConfigureOptions {
-  webContents: WebContents;
+  webContents: PreviewWebContents;
   initialUrl: string;
   persistKey?: string;
   logger: Logger;
+  /** Extra CONNECT-UDP destinations for this view. */
+  udpAllowlist?: string[];
+  /** Hosts this view may reach (`a.com`, `*.a.com`); unset allows any. */
+  allowedHosts?: string[];
+  /** Ports this view may never reach. */
+  blockedPorts?: number[];
+  /** Rewrite absolute loopback URLs in HTML, JS and JSON responses. */
+  rewriteLoopbackUrls?: boolean;
 }
 
 let proxyServer: ProxyServer | null = null;
 let proxyPort: number | null = null;
 let proxyLogger: Logger | null = null;
 let startingProxy: Promise<number> | null = null;
+let pacServer: http.Server | null = null;
+let pacUrl: string | null = null;
 let proxyLoggingEnabled = DEFAULT_PROXY_LOGGING_ENABLED;
 const http2Sessions = new Map<string, ClientHttp2Session>();
 const pendingHttp2Sessions = new Map<string, Promise<ClientHttp2Session>>();
@@ -260,7 +315,16 @@ export function releasePreviewProxy(webContentsId: number): void {
 export async function configurePreviewProxyForView(
   options: ConfigureOptions
 ): Promise<() => void> {
-  const { webContents, initialUrl, persistKey, logger } = options;
+  const {
+    webContents,
+    initialUrl,
+    persistKey,
+    logger,
+    udpAllowlist,
+    allowedHosts,
+    blockedPorts,
+    rewriteLoopbackUrls,
+  } = options;
   proxyLog("session-proxy-setup-called", {
     webContentsId: webContents.id,
     initialUrl,
@@ -294,6 +358,10 @@ export async function configurePreviewProxyForView(
     session: webContents.session,
     webContentsId: webContents.id,
     persistKey,
+    udpAllowlist: [...DEFAULT_UDP_ALLOWLIST, ...(udpAllowlist ?? [])],
+    allowedHosts,
+    blockedPorts,
+    rewriteLoopbackUrls: rewriteLoopbackUrls ?? REWRITE_LOOPBACK_URLS,
   };
 
   contextsByUsername.set(username, context);
@@ -301,16 +369,26 @@ export async function configurePreviewProxyForView(
   contextsByAuthToken.set(authToken, context);
 
   try {
-    await webContents.session.setProxy({
-      mode: "fixed_servers",
-      proxyRules: `http=127.0.0.1:${port};https=127.0.0.1:${port}`,
-      proxyBypassRules: "<-loopback>",
-    });
+    // With the PAC only loopback and cmux hosts go through the proxy.
+    await webContents.session.setProxy(
+      pacUrl
+        ? {
+            mode: "pac_script",
+            pacScript: pacUrl,
+            proxyBypassRules: "<-loopback>",
+          }
+        : {
+            mode: "fixed_servers",
+            proxyRules: `http=127.0.0.1:${port};https=127.0.0.1:${port}`,
+            proxyBypassRules: "<-loopback>",
+          }
+    );
     proxyLog("session-proxy-configured", {
       webContentsId: webContents.id,
       persistKey,
       route,
       port,
+      pacUrl,
     });
   } catch (error) {
     contextsByUsername.delete(username);
@@ -347,10 +425,100 @@ export async function configurePreviewProxyForView(
   return cleanup;
 }
 
+/**
+ * Proxy auto-config that sends only loopback and cmux hosts through the
+ * preview proxy on `port`; everything else connects directly. Chromium skips
+ * proxies for loopback unless the bypass rules contain `<-loopback>`, so
+ * sessions using this need that too.
+ */
+export function buildPreviewProxyPac(port: number): string {
+  return `function FindProxyForURL(url, host) {
+  var proxy = "PROXY 127.0.0.1:${port}";
+  var loopbackHosts = ${JSON.stringify(PAC_LOOPBACK_HOSTS)};
+  var cmuxDomains = ${JSON.stringify(CMUX_DOMAINS)};
+  host = host.toLowerCase();
+  if (host.charAt(0) === "[" && host.charAt(host.length - 1) === "]") {
+    host = host.substring(1, host.length - 1);
+  }
+  if (loopbackHosts.indexOf(host) !== -1 || dnsDomainIs(host, ".localhost")) {
+    return proxy;
+  }
+  if (/^(::ffff:)?127\\.\\d{1,3}\\.\\d{1,3}\\.\\d{1,3}$/.test(host)) {
+    return proxy;
+  }
+  for (var i = 0; i < cmuxDomains.length; i++) {
+    if (host === cmuxDomains[i] || dnsDomainIs(host, "." + cmuxDomains[i])) {
+      return proxy;
+    }
+  }
+  return "DIRECT";
+}
+`;
+}
+
+/**
+ * Serve the PAC for the proxy on `proxyPort` from its own listener on an
+ * ephemeral 127.0.0.1 port, so the file stays off the proxy's traffic path.
+ */
+export async function startPreviewProxyPacServer(
+  proxyPort: number
+): Promise<{ server: http.Server; url: string }> {
+  const server = http.createServer((req, res) => {
+    const path = (req.url ?? "").split("?")[0];
+    if (path !== PAC_PATH) {
+      res.writeHead(404);
+      res.end("Not Found");
+      return;
+    }
+    if (req.method !== "GET" && req.method !== "HEAD") {
+      res.writeHead(405, { Allow: "GET, HEAD" });
+      res.end();
+      return;
+    }
+    const body = buildPreviewProxyPac(proxyPort);
+    proxyLog("pac-served", {
+      proxyPort,
+      remoteAddress: req.socket.remoteAddress,
+    });
+    res.writeHead(200, {
+      "Content-Type": "application/x-ns-proxy-autoconfig",
+      "Content-Length": Buffer.byteLength(body),
+      "Cache-Control": "no-store",
+    });
+    res.end(req.method === "HEAD" ? undefined : body);
+  });
+  await listen(server, 0);
+  const address = server.address();
+  if (!address || typeof address === "string") {
+    await closeServer(server);
+    throw new Error("PAC server has no TCP address");
+  }
+  return { server, url: `http://127.0.0.1:${address.port}${PAC_PATH}` };
+}
+
 export function startPreviewProxy(logger: Logger): Promise<number> {
   return ensureProxyServer(logger);
 }
 
+/** Close the proxy and PAC listeners; a later view starts them again. */
+export async function stopPreviewProxy(): Promise<void> {
+  const servers = [proxyServer, pacServer];
+  proxyServer = null;
+  proxyPort = null;
+  pacServer = null;
+  pacUrl = null;
+  await Promise.all(
+    servers.map((server) => (server ? closeServer(server) : undefined))
+  );
+}
+
+function closeServer(server: http.Server): Promise<void> {
+  return new Promise((resolve, reject) => {
+    server.close((error) => (error ? reject(error) : resolve()));
+    server.closeAllConnections();
+  });
+}
+
 async function ensureProxyServer(logger: Logger): Promise<number> {
   if (proxyPort && proxyServer) {
     return proxyPort;
@@ -382,6 +550,7 @@ async function startProxyServer(logger: Logger): Promise<number> {
       console.log(`[cmux-preview-proxy] listening on port ${candidatePort}`);
       logger.log("Preview proxy listening", { port: candidatePort });
       proxyLog("listening", { port: candidatePort });
+      await startPacServer(candidatePort, logger);
       return candidatePort;
     } catch (error) {
       server.removeAllListeners();
@@ -400,6 +569,17 @@ async function startProxyServer(logger: Logger): Promise<number> {
   throw new Error("Unable to bind preview proxy port");
 }
 
+async function startPacServer(port: number, logger: Logger): Promise<void> {
+  try {
+    ({ server: pacServer, url: pacUrl } =
+      await startPreviewProxyPacServer(port));
+    proxyLog("pac-listening", { url: pacUrl });
+  } catch (error) {
+    // Views that don't use the PAC keep working without it.
+    logger.warn("Failed to start preview proxy PAC server", { error });
+  }
+}
+
 function listen(server: ProxyServer, port: number): Promise<void> {
   return new Promise((resolve, reject) => {
     const handleError = (error: Error) => {
@@ -521,7 +701,6 @@ function monitorHttp2Session(originKey: string, session: ClientHttp2Session) {
 }
 
 function handleHttpRequest(req: IncomingMessage, res: ServerResponse) {
-
   const context = authenticateRequest(req.headers, req.socket);
   if (!context) {
     respondProxyAuthRequired(res);
@@ -538,6 +717,11 @@ function handleHttpRequest(req: IncomingMessage, res: ServerResponse) {
     res.end("Bad Request");
     return;
   }
+  if (isForbiddenDestination(context, "http", target)) {
+    res.writeHead(403);
+    res.end("Forbidden");
+    return;
+  }
 
   const rewritten = rewriteTarget(target, context);
   proxyLog("http-request", {
@@ -589,6 +773,11 @@ function handleConnect(
 
   const targetUrl = new URL(`https://${target.hostname}`);
   targetUrl.port = String(target.port);
+  if (isForbiddenDestination(context, "connect", targetUrl)) {
+    socket.write("HTTP/1.1 403 Forbidden\r\n\r\n");
+    socket.end();
+    return;
+  }
   const rewritten = rewriteTarget(targetUrl, context);
 
   const tlsCandidate = shouldInterceptTls(target.hostname, context, head);
@@ -828,6 +1017,11 @@ function handleUpgrade(req: IncomingMessage, socket: Socket, head: Buffer) {
     origin: req.headers.origin,
   });
 
+  if (isConnectUdpUpgrade(req)) {
+    handleConnectUdp(req, socket, head, context);
+    return;
+  }
+
   const target = parseProxyRequestTarget(req);
   if (!target) {
     proxyWarn("upgrade-target-parse-failed", {
@@ -838,6 +1032,11 @@ function handleUpgrade(req: IncomingMessage, socket: Socket, head: Buffer) {
     socket.end();
     return;
   }
+  if (isForbiddenDestination(context, "upgrade", target)) {
+    socket.write("HTTP/1.1 403 Forbidden\r\n\r\n");
+    socket.end();
+    return;
+  }
 
   const rewritten = rewriteTarget(target, context);
   proxyLog("upgrade-request", {
@@ -851,6 +1050,55 @@ function handleUpgrade(req: IncomingMessage, socket: Socket, head: Buffer) {
   forwardUpgradeRequest(req, socket, head, rewritten);
 }
 
+function handleConnectUdp(
+  req: IncomingMessage,
+  socket: Socket,
+  head: Buffer,
+  context: ProxyContext
+) {
+  if (!ENABLE_CONNECT_UDP) {
+    socket.write("HTTP/1.1 501 Not Implemented\r\n\r\n");
+    socket.end();
+    return;
+  }
+  const target = parseConnectUdpTarget(req.url ?? "");
+  if (!target) {
+    proxyWarn("connect-udp-target-parse-failed", { url: req.url });
+    socket.write("HTTP/1.1 400 Bad Request\r\n\r\n");
+    socket.end();
+    return;
+  }
+  if (!isUdpTargetAllowed(target, context.udpAllowlist)) {
+    proxyWarn("connect-udp-forbidden", {
+      username: context.username,
+      target,
+      persistKey: context.persistKey,
+    });
+    socket.write("HTTP/1.1 403 Forbidden\r\n\r\n");
+    socket.end();
+    return;
+  }
+  relayConnectUdp(socket, head, target, { log: proxyLog, warn: proxyWarn });
+}
+
+function isForbiddenDestination(
+  context: ProxyContext,
+  kind: "http" | "connect" | "upgrade",
+  target: URL
+): boolean {
+  const port = determineRequestedPort(target);
+  if (isDestinationAllowed(context, target.hostname, port)) {
+    return false;
+  }
+  proxyWarn(`${kind}-destination-forbidden`, {
+    username: context.username,
+    host: target.hostname,
+    port,
+    persistKey: context.persistKey,
+  });
+  return true;
+}
+
 function authenticateRequest(
   headers: IncomingHttpHeaders,
   socket?: Socket | TLSSocket
@@ -1057,6 +1305,36 @@ function buildCmuxHost(route: ProxyRoute, port: number): string {
   return `cmux-${route.morphId}-${route.scope}-${safePort}.${route.domainSuffix}`;
 }
 
+/**
+ * Streams rewriting loopback URLs in the response body when the view opted
+ * in, dropping the headers that no longer describe the rewritten body.
+ */
+function createResponseRewrite(
+  context: ProxyContext,
+  headers: Record<string, string | string[] | undefined>
+): Duplex[] | null {
+  const route = context.route;
+  if (!context.rewriteLoopbackUrls || !route) {
+    return null;
+  }
+  const streams = createLoopbackRewriteStreams(
+    firstHeaderValue(headers["content-type"]),
+    firstHeaderValue(headers["content-encoding"]),
+    (port) => buildCmuxHost(route, port)
+  );
+  if (streams) {
+    delete headers["content-length"];
+    delete headers["content-encoding"];
+  }
+  return streams;
+}
+
+function firstHeaderValue(
+  value: string | string[] | undefined
+): string | undefined {
+  return Array.isArray(value) ? value[0] : value;
+}
+
 function shouldAttemptHttp2(target: ProxyTarget): boolean {
   if (!target.secure) {
     return false;
@@ -1120,14 +1398,16 @@ function forwardHttpRequestViaHttp1(
 
     const httpModule = secure ? https : http;
     const proxyReq = httpModule.request(requestOptions, (proxyRes) => {
+      const responseHeaders = { ...proxyRes.headers };
+      const rewrite = createResponseRewrite(context, responseHeaders);
       if (!clientRes.headersSent) {
         clientRes.writeHead(
           proxyRes.statusCode ?? 500,
           proxyRes.statusMessage ?? "",
-          proxyRes.headers
+          responseHeaders
         );
       }
-      void streamPipeline(proxyRes, clientRes)
+      void streamPipeline([proxyRes, ...(rewrite ?? []), clientRes])
         .then(() => {
           resolve();
         })
@@ -1200,6 +1480,7 @@ async function forwardHttpRequestViaHttp2(
       path: headers[":path"],
     });
 
+    let rewriteInput: Duplex | null = null;
     upstreamReq.on("response", (upstreamHeaders) => {
       const status = Number(upstreamHeaders[":status"] ?? 502);
       const responseHeaders: Record<string, string | string[]> = {};
@@ -1213,9 +1494,19 @@ async function forwardHttpRequestViaHttp2(
           responseHeaders[name] = String(value);
         }
       }
+      const rewrite = createResponseRewrite(context, responseHeaders);
       if (!clientRes.headersSent) {
         clientRes.writeHead(status, responseHeaders);
       }
+      if (rewrite) {
+        rewriteInput = rewrite[0] ?? null;
+        void streamPipeline([...rewrite, clientRes]).catch((error) => {
+          proxyWarn("http2-response-rewrite-error", {
+            error,
+            host: target.url.hostname,
+          });
+        });
+      }
       logHttp2("response-headers", {
         status,
         headerCount: Object.keys(responseHeaders).length,
@@ -1223,13 +1514,17 @@ async function forwardHttpRequestViaHttp2(
     });
 
     upstreamReq.on("data", (chunk) => {
-      if (!clientRes.writableEnded) {
+      if (rewriteInput) {
+        rewriteInput.write(chunk);
+      } else if (!clientRes.writableEnded) {
         clientRes.write(chunk);
       }
     });
 
     upstreamReq.on("end", () => {
-      if (!clientRes.writableEnded) {
+      if (rewriteInput) {
+        rewriteInput.end();
+      } else if (!clientRes.writableEnded) {
         clientRes.end();
       }
       logHttp2("response-end");
@@ -1240,6 +1535,7 @@ async function forwardHttpRequestViaHttp2(
       logHttp2("stream-error", {
         message: (error as Error).message,
       });
+      rewriteInput?.destroy(error);
       if (!clientRes.headersSent) {
         clientRes.writeHead(502);
         clientRes.end("Bad Gateway");
@@ -2,8 +2,14 @@ use anyhow::Result;
 use gix::bstr::ByteSlice;
 use gix::hash::ObjectId;
 
+use crate::error::{CoreError, ErrorCode};
 use crate::repo::cache::{ensure_repo, resolve_repo_url, swr_fetch_origin_all_path};
-use crate::types::{BranchInfo, GitListRemoteBranchesOptions};
+use crate::repo::handles;
+use crate::types::{
+    BranchInfo, GitBranchOpResult, GitCheckoutOptions, GitCreateBranchOptions,
+    GitListRemoteBranchesOptions, GitMergeFfOnlyOptions, GitStatusOptions,
+};
+use crate::util::run_git;
 
 fn refname_to_branch(name: &str) -> Option<(String /*remote*/, String /*branch*/)> {
     // Expect refs/remotes/<remote>/<branch>
@@ -33,7 +39,7 @@ pub fn list_remote_branches(opts: GitListRemoteBranchesOptions) -> Result<Vec<Br
     // Make sure remotes are fresh (this is cheap if within SWR window)
     let _ = swr_fetch_origin_all_path(&repo_path, crate::repo::cache::fetch_window_ms());
 
-    let repo = gix::open(&repo_path)?;
+    let repo = handles::open(&repo_path)?;
 
     // Iterate remote refs and assemble info
     let refs = repo.references()?;
@@ -54,6 +60,7 @@ pub fn list_remote_branches(opts: GitListRemoteBranchesOptions) -> Result<Vec<Br
     }
 
     for r in iter {
+        crate::cancel::check()?;
         let r = match r {
             Ok(v) => v,
             Err(_) => continue,
@@ -143,10 +150,200 @@ pub fn list_remote_branches(opts: GitListRemoteBranchesOptions) -> Result<Vec<Br
     Ok(out)
 }
 
+/// Failures callers are expected to handle; JS sees them by their
+/// [`ErrorCode`].
+#[derive(Debug)]
+pub enum BranchOpError {
+    DirtyTree(Vec<String>),
+    NonFastForward { head: String, target: String },
+    MissingRef(String),
+    AlreadyExists(String),
+}
+
+impl BranchOpError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BranchOpError::DirtyTree(_) => ErrorCode::DirtyTree,
+            BranchOpError::NonFastForward { .. } => ErrorCode::NonFastForward,
+            BranchOpError::MissingRef(_) => ErrorCode::RefNotFound,
+            BranchOpError::AlreadyExists(_) => ErrorCode::AlreadyExists,
+        }
+    }
+}
+
+impl std::fmt::Display for BranchOpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BranchOpError::DirtyTree(paths) => {
+                let shown: Vec<&str> = paths.iter().take(5).map(String::as_str).collect();
+                write!(
+                    f,
+                    "working tree has uncommitted changes ({})",
+                    shown.join(", ")
+                )?;
+                if paths.len() > shown.len() {
+                    write!(f, " and {} more", paths.len() - shown.len())?;
+                }
+                Ok(())
+            }
+            BranchOpError::NonFastForward { head, target } => {
+                write!(f, "{} is not a descendant of HEAD ({})", target, head)
+            }
+            BranchOpError::MissingRef(r) => write!(f, "could not resolve '{}'", r),
+            BranchOpError::AlreadyExists(b) => write!(f, "branch '{}' already exists", b),
+        }
+    }
+}
+
+impl std::error::Error for BranchOpError {}
+
+fn resolve_commit(repo: &gix::Repository, rev: &str) -> Result<ObjectId> {
+    let obj = repo
+        .rev_parse_single(rev)
+        .map_err(|_| BranchOpError::MissingRef(rev.to_string()))?
+        .object()?;
+    Ok(obj.peel_to_kind(gix::object::Kind::Commit)?.id)
+}
+
+fn ensure_clean(worktree_path: &str) -> Result<()> {
+    let status = crate::diff::status::git_status(GitStatusOptions {
+        worktreePath: worktree_path.to_string(),
+        includeUntracked: Some(false),
+        includeContents: Some(false),
+        maxBytes: None,
+    })?;
+    let mut dirty: Vec<String> = status
+        .staged
+        .iter()
+        .chain(status.unstaged.iter())
+        .map(|e| e.filePath.clone())
+        .collect();
+    dirty.sort();
+    dirty.dedup();
+    if dirty.is_empty() {
+        Ok(())
+    } else {
+        Err(BranchOpError::DirtyTree(dirty).into())
+    }
+}
+
+fn op_result(repo_path: &str, updated: bool) -> Result<GitBranchOpResult> {
+    let repo = handles::open(repo_path)?;
+    let branch = repo
+        .head_name()?
+        .map(|n| n.shorten().to_str_lossy().into_owned());
+    let head_sha = repo.head_id().ok().map(|id| oid_to_hex(id.detach()));
+    Ok(GitBranchOpResult {
+        branch,
+        headSha: head_sha,
+        updated,
+    })
+}
+
+pub fn create_branch(opts: GitCreateBranchOptions) -> Result<GitBranchOpResult> {
+    let name = opts.name.trim();
+    let full = format!("refs/heads/{}", name);
+    if name.is_empty() || gix::refs::FullName::try_from(full.as_str()).is_err() {
+        return Err(CoreError::new(
+            ErrorCode::InvalidArgument,
+            format!("invalid branch name '{}'", opts.name),
+        )
+        .into());
+    }
+    let repo = handles::open(&opts.worktreePath)?;
+    let start = opts.startPoint.as_deref().unwrap_or("HEAD");
+    let target = resolve_commit(&repo, start)?;
+    let force = opts.force.unwrap_or(false);
+    if !force && repo.find_reference(full.as_str()).is_ok() {
+        return Err(BranchOpError::AlreadyExists(name.to_string()).into());
+    }
+    let constraint = if force {
+        gix::refs::transaction::PreviousValue::Any
+    } else {
+        gix::refs::transaction::PreviousValue::MustNotExist
+    };
+    repo.reference(
+        full.as_str(),
+        target,
+        constraint,
+        format!("branch: Created from {}", start),
+    )?;
+    if opts.checkout.unwrap_or(false) {
+        return checkout(GitCheckoutOptions {
+            worktreePath: opts.worktreePath,
+            target: name.to_string(),
+        });
+    }
+    op_result(&opts.worktreePath, true)
+}
+
+pub fn checkout(opts: GitCheckoutOptions) -> Result<GitBranchOpResult> {
+    let target = opts.target.trim();
+    let repo = handles::open(&opts.worktreePath)?;
+    // Local branch, a remote branch git can DWIM into a tracking branch, or any commit-ish (detached).
+    let known = repo
+        .find_reference(format!("refs/heads/{}", target).as_str())
+        .is_ok()
+        || repo
+            .find_reference(format!("refs/remotes/origin/{}", target).as_str())
+            .is_ok()
+        || resolve_commit(&repo, target).is_ok();
+    if !known {
+        return Err(BranchOpError::MissingRef(target.to_string()).into());
+    }
+    if repo
+        .head_name()?
+        .map(|n| n.shorten().to_str_lossy().into_owned())
+        .as_deref()
+        == Some(target)
+    {
+        return op_result(&opts.worktreePath, false);
+    }
+    ensure_clean(&opts.worktreePath)?;
+    run_git(&opts.worktreePath, &["checkout", "--quiet", target])?;
+    op_result(&opts.worktreePath, true)
+}
+
+pub fn merge_ff_only(opts: GitMergeFfOnlyOptions) -> Result<GitBranchOpResult> {
+    let target = opts.target.trim();
+    let repo = handles::open(&opts.worktreePath)?;
+    let target_oid = resolve_commit(&repo, target)?;
+    let head_oid = repo
+        .head_id()
+        .map_err(|_| BranchOpError::MissingRef("HEAD".to_string()))?
+        .detach();
+    if head_oid == target_oid {
+        return op_result(&opts.worktreePath, false);
+    }
+    let base = crate::merge_base::merge_base(
+        &opts.worktreePath,
+        &repo,
+        head_oid,
+        target_oid,
+        crate::merge_base::MergeBaseStrategy::Bfs,
+    );
+    if base == Some(target_oid) {
+        // Target is already contained in HEAD.
+        return op_result(&opts.worktreePath, false);
+    }
+    if base != Some(head_oid) {
+        return Err(BranchOpError::NonFastForward {
+            head: oid_to_hex(head_oid),
+            target: target.to_string(),
+        }
+        .into());
+    }
+    ensure_clean(&opts.worktreePath)?;
+    run_git(
+        &opts.worktreePath,
+        &["merge", "--ff-only", "--quiet", &oid_to_hex(target_oid)],
+    )?;
+    op_result(&opts.worktreePath, true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::util::run_git;
     use std::fs;
     use tempfile::tempdir;
 
@@ -244,4 +441,106 @@ mod tests {
         let main_row = res.iter().find(|b| b.name == "main").unwrap();
         assert_eq!(main_row.isDefault, Some(true));
     }
+
+    fn init_repo(dir: &std::path::Path) -> String {
+        let cwd = dir.to_str().unwrap().to_string();
+        run_git(&cwd, &["init"]).unwrap();
+        run_git(&cwd, &["config", "user.name", "Test"]).unwrap();
+        run_git(&cwd, &["config", "user.email", "test@example.com"]).unwrap();
+        run_git(&cwd, &["checkout", "-b", "main"]).unwrap();
+        fs::write(dir.join("a.txt"), "one\n").unwrap();
+        run_git(&cwd, &["add", "."]).unwrap();
+        run_git(&cwd, &["commit", "-m", "initial"]).unwrap();
+        cwd
+    }
+
+    fn error_code(err: anyhow::Error) -> &'static str {
+        crate::error::code_of(&err).as_str()
+    }
+
+    #[test]
+    fn create_checkout_and_fast_forward() {
+        let tmp = tempdir().expect("tempdir");
+        let cwd = init_repo(tmp.path());
+
+        let created = create_branch(GitCreateBranchOptions {
+            worktreePath: cwd.clone(),
+            name: "feature".into(),
+            checkout: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(created.branch.as_deref(), Some("feature"));
+        assert!(created.updated);
+
+        let dup = create_branch(GitCreateBranchOptions {
+            worktreePath: cwd.clone(),
+            name: "feature".into(),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(error_code(dup), "ALREADY_EXISTS");
+
+        fs::write(tmp.path().join("a.txt"), "two\n").unwrap();
+        run_git(&cwd, &["commit", "-am", "feature work"]).unwrap();
+        let feature_sha = run_git(&cwd, &["rev-parse", "HEAD"]).unwrap();
+
+        // Uncommitted edits block switching branches
+        fs::write(tmp.path().join("a.txt"), "dirty\n").unwrap();
+        let dirty = checkout(GitCheckoutOptions {
+            worktreePath: cwd.clone(),
+            target: "main".into(),
+        })
+        .unwrap_err();
+        assert_eq!(error_code(dirty), "DIRTY_TREE");
+        run_git(&cwd, &["checkout", "--", "a.txt"]).unwrap();
+
+        let missing = checkout(GitCheckoutOptions {
+            worktreePath: cwd.clone(),
+            target: "nope".into(),
+        })
+        .unwrap_err();
+        assert_eq!(error_code(missing), "REF_NOT_FOUND");
+
+        let switched = checkout(GitCheckoutOptions {
+            worktreePath: cwd.clone(),
+            target: "main".into(),
+        })
+        .unwrap();
+        assert_eq!(switched.branch.as_deref(), Some("main"));
+
+        let merged = merge_ff_only(GitMergeFfOnlyOptions {
+            worktreePath: cwd.clone(),
+            target: "feature".into(),
+        })
+        .unwrap();
+        assert!(merged.updated);
+        assert_eq!(merged.headSha.as_deref(), Some(feature_sha.trim()));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("a.txt")).unwrap(),
+            "two\n"
+        );
+
+        let noop = merge_ff_only(GitMergeFfOnlyOptions {
+            worktreePath: cwd.clone(),
+            target: "feature".into(),
+        })
+        .unwrap();
+        assert!(!noop.updated);
+
+        // Diverge main and feature
+        fs::write(tmp.path().join("b.txt"), "main\n").unwrap();
+        run_git(&cwd, &["add", "."]).unwrap();
+        run_git(&cwd, &["commit", "-m", "main work"]).unwrap();
+        run_git(&cwd, &["checkout", "feature"]).unwrap();
+        fs::write(tmp.path().join("c.txt"), "feature\n").unwrap();
+        run_git(&cwd, &["add", "."]).unwrap();
+        run_git(&cwd, &["commit", "-m", "more feature work"]).unwrap();
+        let non_ff = merge_ff_only(GitMergeFfOnlyOptions {
+            worktreePath: cwd,
+            target: "main".into(),
+        })
+        .unwrap_err();
+        assert_eq!(error_code(non_ff), "NON_FAST_FORWARD");
+    }
 }
@@ -0,0 +1,205 @@
+//! Cancellation of long-running git operations.
+//!
+//! JS allocates an id with `createGitOperation()`, passes it to a git call and
+//! may later `cancelGitOperation(id)`. The call [`claim`]s the id and runs
+//! inside [`Operation::run`], which makes the operation's token current for
+//! the worker thread; the diff and branch walkers poll [`check`] /
+//! [`is_cancelled`] and bail out with [`Cancelled`].
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::error::{CoreError, ErrorCode};
+
+/// How long an id may wait to be passed to a git call before it is dropped.
+const UNCLAIMED_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Error returned by an operation that was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("git operation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+struct Entry {
+    token: Arc<AtomicBool>,
+    created: Instant,
+    claimed: bool,
+}
+
+fn operations() -> MutexGuard<'static, HashMap<u32, Entry>> {
+    static OPERATIONS: OnceLock<Mutex<HashMap<u32, Entry>>> = OnceLock::new();
+    // Entries are inserted and removed whole, so a panic elsewhere can't leave
+    // the map half-updated.
+    OPERATIONS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Forget ids created before `now - UNCLAIMED_TTL` that no call has claimed.
+fn prune_unclaimed(ops: &mut HashMap<u32, Entry>, now: Instant) {
+    ops.retain(|_, e| e.claimed || now.saturating_duration_since(e.created) < UNCLAIMED_TTL);
+}
+
+/// Register a new operation and return its id.
+pub fn create_operation() -> u32 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let now = Instant::now();
+    let mut ops = operations();
+    prune_unclaimed(&mut ops, now);
+    ops.insert(
+        id,
+        Entry {
+            token: Arc::new(AtomicBool::new(false)),
+            created: now,
+            claimed: false,
+        },
+    );
+    id
+}
+
+/// Cancel an operation, whether or not it has started. Returns false if the
+/// id is unknown or the operation already finished.
+pub fn cancel_operation(id: u32) -> bool {
+    match operations().get(&id) {
+        Some(entry) => {
+            entry.token.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Operation `id` (or untracked if `None`) as used by one git call. The id is
+/// released when this is dropped, whether or not the call got to run.
+pub struct Operation(Option<u32>);
+
+/// Claim `id` for a git call made now, so it is kept until the call is done.
+pub fn claim(id: Option<u32>) -> Operation {
+    if let Some(id) = id {
+        if let Some(entry) = operations().get_mut(&id) {
+            entry.claimed = true;
+        }
+    }
+    Operation(id)
+}
+
+impl Operation {
+    /// Run `f` on this thread as this operation.
+    pub fn run<T>(self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let Some(id) = self.0 else {
+            return f();
+        };
+        let token = operations()
+            .get(&id)
+            .map(|e| e.token.clone())
+            .ok_or_else(|| {
+                CoreError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("unknown git operation id {id}"),
+                )
+            })?;
+
+        struct Scope;
+        impl Drop for Scope {
+            fn drop(&mut self) {
+                CURRENT.with(|c| c.borrow_mut().take());
+            }
+        }
+
+        CURRENT.with(|c| *c.borrow_mut() = Some(token));
+        let _scope = Scope;
+        check()?;
+        f()
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            operations().remove(&id);
+        }
+    }
+}
+
+/// Whether the current thread's operation has been cancelled.
+pub fn is_cancelled() -> bool {
+    CURRENT.with(|c| {
+        c.borrow()
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    })
+}
+
+/// `Err(Cancelled)` once the current operation has been cancelled.
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        Err(Cancelled.into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_operations_fail_with_typed_error() {
+        let id = create_operation();
+        let seen = claim(Some(id))
+            .run(|| {
+                assert!(!is_cancelled());
+                assert!(cancel_operation(id));
+                Ok(check().is_err())
+            })
+            .unwrap();
+        assert!(seen);
+        // Finished operations are released.
+        assert!(!cancel_operation(id));
+        assert!(!is_cancelled());
+
+        let id = create_operation();
+        cancel_operation(id);
+        let err = claim(Some(id)).run(|| Ok(())).unwrap_err();
+        assert!(err.is::<Cancelled>());
+
+        assert!(claim(Some(u32::MAX)).run(|| Ok(())).is_err());
+        assert!(claim(None).run(check).is_ok());
+    }
+
+    #[test]
+    fn operations_that_never_run_are_released() {
+        // A call that is dropped before it runs releases its id.
+        let id = create_operation();
+        drop(claim(Some(id)));
+        assert!(!cancel_operation(id));
+
+        // Ids never passed to a call expire; claimed ones are kept.
+        let unclaimed = create_operation();
+        let claimed = create_operation();
+        let operation = claim(Some(claimed));
+        prune_unclaimed(&mut operations(), Instant::now() + UNCLAIMED_TTL);
+        assert!(!cancel_operation(unclaimed));
+        assert!(cancel_operation(claimed));
+        drop(operation);
+        assert!(!cancel_operation(claimed));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod page;
 pub mod refs;
+pub mod status;
 #[cfg(test)]
 pub mod workspace;
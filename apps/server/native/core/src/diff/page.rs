@@ -0,0 +1,166 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, OnceLock,
+};
+
+use crate::error::{CoreError, ErrorCode};
+use crate::types::{DiffEntry, GitDiffOptions, GitDiffPage, GitDiffPageRequest};
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const DEFAULT_MAX_TOTAL_BYTES: usize = 32 * 1024 * 1024;
+/// Number of computed diffs kept around for follow-up page requests.
+const MAX_CACHED_DIFFS: usize = 8;
+
+struct PagedDiff {
+    entries: Vec<DiffEntry>,
+    budget_exhausted: bool,
+}
+
+struct CachedDiff {
+    id: u64,
+    /// What the diff was computed from; a cursor is only valid with these.
+    opts: GitDiffOptions,
+    budget: usize,
+    diff: Arc<PagedDiff>,
+}
+
+static NEXT_DIFF_ID: AtomicU64 = AtomicU64::new(1);
+
+fn cache() -> &'static Mutex<VecDeque<CachedDiff>> {
+    static CACHE: OnceLock<Mutex<VecDeque<CachedDiff>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+fn cached_diff(id: u64, opts: &GitDiffOptions, budget: usize) -> Result<Option<Arc<PagedDiff>>> {
+    let Ok(guard) = cache().lock() else {
+        return Ok(None);
+    };
+    let Some(cached) = guard.iter().find(|c| c.id == id) else {
+        return Ok(None);
+    };
+    if cached.opts != *opts || cached.budget != budget {
+        return Err(CoreError::new(
+            ErrorCode::InvalidArgument,
+            format!(
+                "diff cursor {} belongs to a diff with different options",
+                id
+            ),
+        )
+        .into());
+    }
+    Ok(Some(cached.diff.clone()))
+}
+
+fn store_diff(diff: PagedDiff, opts: GitDiffOptions, budget: usize) -> (u64, Arc<PagedDiff>) {
+    let id = NEXT_DIFF_ID.fetch_add(1, Ordering::Relaxed);
+    let diff = Arc::new(diff);
+    if let Ok(mut guard) = cache().lock() {
+        while guard.len() >= MAX_CACHED_DIFFS {
+            guard.pop_front();
+        }
+        guard.push_back(CachedDiff {
+            id,
+            opts,
+            budget,
+            diff: diff.clone(),
+        });
+    }
+    (id, diff)
+}
+
+fn parse_cursor(cursor: &str) -> Result<(u64, usize)> {
+    let invalid = || {
+        CoreError::new(
+            ErrorCode::InvalidArgument,
+            format!("invalid diff cursor '{}'", cursor),
+        )
+    };
+    let (id, offset) = cursor.split_once(':').ok_or_else(invalid)?;
+    let id = id.parse::<u64>().map_err(|_| invalid())?;
+    let offset = offset.parse::<usize>().map_err(|_| invalid())?;
+    Ok((id, offset))
+}
+
+/// Walk entries in their (stable) output order and drop file contents once the
+/// cumulative payload would exceed `budget`. Every entry after the first one that
+/// does not fit is omitted too, so the cut-off point only depends on the diff
+/// itself and never on how it is paged.
+pub(crate) fn apply_byte_budget(entries: &mut [DiffEntry], budget: usize) -> bool {
+    let mut used: usize = 0;
+    let mut exhausted = false;
+    for e in entries.iter_mut() {
+        let size = e.oldContent.as_ref().map_or(0, |s| s.len())
+            + e.newContent.as_ref().map_or(0, |s| s.len());
+        if size == 0 {
+            continue;
+        }
+        if exhausted || used + size > budget {
+            exhausted = true;
+            e.oldContent = None;
+            e.newContent = None;
+            e.truncation = None;
+            e.contentOmitted = Some(true);
+            continue;
+        }
+        used += size;
+    }
+    exhausted
+}
+
+pub fn diff_page(opts: GitDiffOptions, page: GitDiffPageRequest) -> Result<GitDiffPage> {
+    let page_size = page
+        .pageSize
+        .filter(|n| *n > 0)
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let budget = page
+        .maxTotalBytes
+        .filter(|n| *n >= 0)
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_TOTAL_BYTES);
+
+    let (cursor_id, offset) = match page.cursor.as_deref().map(str::trim) {
+        Some(c) if !c.is_empty() => {
+            let (id, offset) = parse_cursor(c)?;
+            (Some(id), offset)
+        }
+        _ => (None, 0),
+    };
+
+    // Follow-up pages reuse the diff computed for the first page so that the
+    // listing stays consistent even if refs move in the meantime. If it was
+    // evicted, recompute; the ordering and budget cut-off are deterministic.
+    // A cursor is rejected with options other than those it was issued for,
+    // since its offset means nothing in another listing.
+    let hit = match cursor_id {
+        Some(id) => cached_diff(id, &opts, budget)?.map(|d| (id, d)),
+        None => None,
+    };
+    let (id, diff) = match hit {
+        Some(hit) => hit,
+        None => {
+            let mut entries = super::refs::diff_refs(opts.clone())?;
+            let budget_exhausted = apply_byte_budget(&mut entries, budget);
+            store_diff(
+                PagedDiff {
+                    entries,
+                    budget_exhausted,
+                },
+                opts,
+                budget,
+            )
+        }
+    };
+
+    let total = diff.entries.len();
+    let start = offset.min(total);
+    let end = (start + page_size).min(total);
+    Ok(GitDiffPage {
+        entries: diff.entries[start..end].to_vec(),
+        nextCursor: (end < total).then(|| format!("{}:{}", id, end)),
+        totalCount: total as i32,
+        budgetExhausted: diff.budget_exhausted,
+    })
+}
@@ -3,16 +3,21 @@ use gix::bstr::ByteSlice;
 #[cfg(test)]
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use crate::{
+    error::{CoreError, ErrorCode},
     repo::cache::{ensure_repo, resolve_repo_url},
-    types::{DiffEntry, GitDiffOptions},
+    types::{ContentTruncation, DiffEntry, GitDiffOptions, SubmoduleChange},
 };
 use gix::{hash::ObjectId, Repository};
 use similar::TextDiff;
 
-fn oid_from_rev_parse(repo: &Repository, rev: &str) -> anyhow::Result<ObjectId> {
+/// Largest file whose head and tail are returned when truncation is requested.
+const DEFAULT_MAX_TRUNCATED_SOURCE: usize = 64 * 1024 * 1024;
+
+pub(crate) fn oid_from_rev_parse(repo: &Repository, rev: &str) -> anyhow::Result<ObjectId> {
     if let Ok(oid) = ObjectId::from_hex(rev.as_bytes()) {
         return Ok(oid);
     }
@@ -34,18 +39,33 @@ fn oid_from_rev_parse(repo: &Repository, rev: &str) -> anyhow::Result<ObjectId>
             return Ok(obj.id);
         }
     }
-    Err(anyhow::anyhow!("could not resolve rev '{}'", rev))
+    Err(CoreError::new(
+        ErrorCode::RefNotFound,
+        format!("could not resolve rev '{}'", rev),
+    )
+    .into())
 }
 
-fn is_binary(data: &[u8]) -> bool {
+pub(crate) fn is_binary(data: &[u8]) -> bool {
     data.contains(&0) || std::str::from_utf8(data).is_err()
 }
 
-fn collect_tree_blobs(
+pub(crate) fn collect_tree_blobs(
     repo: &Repository,
     tree_id: ObjectId,
     prefix: &str,
     out: &mut HashMap<String, ObjectId>,
+) -> anyhow::Result<()> {
+    collect_tree_entries(repo, tree_id, prefix, out, None)
+}
+
+/// Like [`collect_tree_blobs`], but submodule commits go to `gitlinks` when given.
+fn collect_tree_entries(
+    repo: &Repository,
+    tree_id: ObjectId,
+    prefix: &str,
+    out: &mut HashMap<String, ObjectId>,
+    mut gitlinks: Option<&mut HashMap<String, ObjectId>>,
 ) -> anyhow::Result<()> {
     let obj = repo.find_object(tree_id)?;
     let tree = obj.try_into_tree()?;
@@ -60,7 +80,9 @@ fn collect_tree_blobs(
         let mode = entry.mode();
         if mode.is_tree() {
             let id = entry.oid().to_owned();
-            collect_tree_blobs(repo, id, &full, out)?;
+            collect_tree_entries(repo, id, &full, out, gitlinks.as_deref_mut())?;
+        } else if let (true, Some(links)) = (mode.is_commit(), gitlinks.as_deref_mut()) {
+            links.insert(full, entry.oid().to_owned());
         } else {
             let id = entry.oid().to_owned();
             out.insert(full, id);
@@ -85,12 +107,111 @@ fn resolve_default_base(repo: &Repository, head_oid: ObjectId) -> ObjectId {
             return id.to_owned();
         }
     }
-    if let Ok(commit) = repo.head_commit() {
+    // A linked worktree's HEAD is the branch under review; the main worktree's
+    // HEAD is what it branched from.
+    let main_repo = match repo.kind() {
+        gix::repository::Kind::WorkTree { is_linked: true } => repo.main_repo().ok(),
+        _ => None,
+    };
+    if let Ok(commit) = main_repo.as_ref().unwrap_or(repo).head_commit() {
         return commit.id;
     }
     head_oid
 }
 
+/// The worktree (or bare repository) containing `path`, so an override may
+/// point anywhere inside a checkout, including a linked worktree.
+fn containing_repo_root(path: &Path) -> PathBuf {
+    match gix::discover(path) {
+        Ok(repo) => repo
+            .work_dir()
+            .unwrap_or_else(|| repo.git_dir())
+            .to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Open the repository checked out for the submodule at `path`, looking where
+/// `git submodule update` puts it for this worktree, the main repository, and
+/// in the checkout itself.
+fn open_submodule(repo: &Repository, path: &str) -> Option<Repository> {
+    let candidates = [
+        Some(repo.git_dir().join("modules").join(path)),
+        Some(repo.common_dir().join("modules").join(path)),
+        repo.work_dir().map(|dir| dir.join(path)),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .filter(|dir| dir.exists())
+        .find_map(|dir| crate::repo::handles::open(dir).ok())
+}
+
+fn commit_tree_id(repo: &Repository, commit: ObjectId) -> Option<ObjectId> {
+    let commit = repo.find_object(commit).ok()?.try_into_commit().ok()?;
+    Some(commit.tree_id().ok()?.detach())
+}
+
+/// Entries for submodule pointer changes. While `submodule_depth` allows it
+/// and the submodule's repository has both commits, a modified pointer is
+/// followed by the submodule's own changes, prefixed with its path.
+fn diff_gitlinks(
+    repo: &Repository,
+    base: &HashMap<String, ObjectId>,
+    head: &HashMap<String, ObjectId>,
+    opts: &TreeDiffOptions,
+    stats: &mut TreeDiffStats,
+) -> Result<Vec<DiffEntry>> {
+    let mut paths: Vec<&String> = base.keys().chain(head.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    let mut out = Vec::new();
+    for path in paths {
+        crate::cancel::check()?;
+        let (old, new) = (base.get(path).copied(), head.get(path).copied());
+        let status = match (old, new) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(_), Some(_)) => "modified",
+            (None, _) => "added",
+            (_, None) => "deleted",
+        };
+        out.push(DiffEntry {
+            filePath: path.clone(),
+            status: status.into(),
+            contentOmitted: Some(false),
+            submodule: Some(SubmoduleChange {
+                oldSha: old.map(|id| id.to_string()),
+                newSha: new.map(|id| id.to_string()),
+            }),
+            ..Default::default()
+        });
+        let (Some(old), Some(new)) = (old, new) else {
+            continue;
+        };
+        if opts.submodule_depth == 0 {
+            continue;
+        }
+        let Some(sub) = open_submodule(repo, path) else {
+            continue;
+        };
+        let (Some(old_tree), Some(new_tree)) =
+            (commit_tree_id(&sub, old), commit_tree_id(&sub, new))
+        else {
+            continue;
+        };
+        let sub_opts = TreeDiffOptions {
+            submodule_depth: opts.submodule_depth - 1,
+            ..*opts
+        };
+        for mut e in diff_trees(&sub, old_tree, new_tree, &sub_opts, stats)? {
+            e.filePath = format!("{}/{}", path, e.filePath);
+            e.oldPath = e.oldPath.map(|p| format!("{}/{}", path, p));
+            out.push(e);
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
@@ -135,6 +256,9 @@ fn find_merge_parent_on_base(
     let mut seen = 0usize;
     let mut ancestor_candidate: Option<(ObjectId, ObjectId)> = None;
     while seen < limit {
+        if crate::cancel::is_cancelled() {
+            break;
+        }
         seen += 1;
         let obj = repo.find_object(base_tip).ok()?;
         let commit = obj.try_into_commit().ok()?;
@@ -165,209 +289,156 @@ fn parse_oid(hex: &str) -> Option<ObjectId> {
     ObjectId::from_hex(trimmed.as_bytes()).ok()
 }
 
-pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
-    let include = opts.includeContents.unwrap_or(true);
-    let max_bytes = opts.maxBytes.unwrap_or(950 * 1024) as usize;
-    let t_total = Instant::now();
-    #[cfg(test)]
-    LAST_DIFF_DEBUG.with(|cell| {
-        *cell.borrow_mut() = None;
-    });
-
-    let head_ref = opts.headRef.trim();
-    if head_ref.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let base_ref_input = opts
-        .baseRef
-        .as_ref()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty());
-    #[cfg(test)]
-    let base_ref_for_debug = base_ref_input.clone();
-
-    #[cfg(debug_assertions)]
-    println!(
-        "[native.refs] start headRef={} baseRef={:?} originPathOverride={:?} repoFullName={:?}",
-        head_ref, base_ref_input, opts.originPathOverride, opts.repoFullName
-    );
-
-    let t_repo_path = Instant::now();
-    let repo_path = if let Some(p) = &opts.originPathOverride {
-        std::path::PathBuf::from(p)
-    } else {
-        let url = resolve_repo_url(opts.repoFullName.as_deref(), opts.repoUrl.as_deref())?;
-        ensure_repo(&url)?
-    };
-    let _d_repo_path = t_repo_path.elapsed();
-    let cwd = repo_path.to_string_lossy().to_string();
+/// Counters and timings for the debug summary line of [`diff_refs`].
+#[derive(Default)]
+struct TreeDiffStats {
+    collect_base: Duration,
+    collect_head: Duration,
+    add_mod_loop: Duration,
+    del_loop: Duration,
+    num_added: usize,
+    num_modified: usize,
+    num_deleted: usize,
+    num_binary: usize,
+    total_scanned_bytes: usize,
+    blob_read_ns: u128,
+    textdiff_ns: u128,
+    textdiff_count: usize,
+    max_diff_ns: u128,
+    max_diff_path: Option<String>,
+}
 
-    // If a specific repo path is provided, assume the caller ensures freshness.
-    // Avoid synchronous fetch here to reduce latency.
-    let _d_fetch = if opts.originPathOverride.is_some() {
-        Duration::from_millis(0)
-    } else {
-        let t_fetch = Instant::now();
-        let _ = crate::repo::cache::swr_fetch_origin_all_path(
-            std::path::Path::new(&cwd),
-            crate::repo::cache::fetch_window_ms(),
-        );
-        t_fetch.elapsed()
-    };
+#[derive(Clone, Copy)]
+struct TreeDiffOptions {
+    include: bool,
+    max_bytes: usize,
+    /// Keep this many bytes from each end of text over `max_bytes`.
+    truncate_window: Option<usize>,
+    /// Text larger than this is omitted rather than truncated.
+    max_truncated_source: usize,
+    /// Levels of submodules whose pointer changes are expanded into file entries.
+    submodule_depth: u32,
+}
 
-    let t_open = Instant::now();
-    let repo = gix::open(&cwd)?;
-    let _d_open = t_open.elapsed();
-    let t_head = Instant::now();
-    let head_oid = match oid_from_rev_parse(&repo, head_ref) {
-        Ok(oid) => oid,
-        Err(_) => {
-            let _d_head = t_head.elapsed();
-            #[cfg(debug_assertions)]
-            println!(
-        "[cmux_native_git] git_diff timings: total={}ms resolve_head={}ms (failed to resolve); cwd={}",
-        t_total.elapsed().as_millis(),
-        _d_head.as_millis(),
-        cwd,
-      );
-            return Ok(Vec::new());
+impl TreeDiffOptions {
+    /// Head/tail windows of both sides of an entry too large for `max_bytes`,
+    /// or `None` when its content should be omitted.
+    fn truncate(&self, old: &str, new: &str) -> Option<(String, String, ContentTruncation)> {
+        let window = self.truncate_window?;
+        if old.len().max(new.len()) > self.max_truncated_source {
+            return None;
         }
-    };
-    let _d_head = t_head.elapsed();
+        let old_cut = truncate_middle(old, window);
+        let new_cut = truncate_middle(new, window);
+        let truncation = ContentTruncation {
+            windowBytes: window as i32,
+            oldTruncated: old_cut.is_some(),
+            newTruncated: new_cut.is_some(),
+        };
+        Some((
+            old_cut.unwrap_or_else(|| old.to_string()),
+            new_cut.unwrap_or_else(|| new.to_string()),
+            truncation,
+        ))
+    }
+}
 
-    let t_base = Instant::now();
-    let mut resolved_base_oid = match base_ref_input {
-        Some(ref spec) => match oid_from_rev_parse(&repo, spec) {
-            Ok(oid) => oid,
-            Err(_) => {
-                let _d_base = t_base.elapsed();
-                #[cfg(debug_assertions)]
-                println!(
-          "[cmux_native_git] git_diff timings: total={}ms resolve_head={}ms resolve_base={}ms (failed to resolve); cwd={}",
-          t_total.elapsed().as_millis(),
-          _d_head.as_millis(),
-          _d_base.as_millis(),
-          cwd,
-        );
-                return Ok(Vec::new());
-            }
-        },
-        None => resolve_default_base(&repo, head_oid),
-    };
-    let _d_base = t_base.elapsed();
-    if let Some(ref known_base) = opts.lastKnownBaseSha {
-        if let Some(candidate) = parse_oid(known_base) {
-            if repo.find_object(candidate).is_ok() && is_ancestor(&repo, candidate, head_oid) {
-                resolved_base_oid = candidate;
-            }
-        }
+/// `text` cut down to about `window` bytes from its start and from its end,
+/// joined by a `... N bytes truncated ...` line. Cuts fall on line boundaries
+/// when the window holds a newline. `None` if nothing would be cut.
+pub(crate) fn truncate_middle(text: &str, window: usize) -> Option<String> {
+    if text.len() <= window.saturating_mul(2) {
+        return None;
     }
-    let t_merge_base = Instant::now();
-    // Compute merge-base; prefer BFS (pure gix) to avoid shelling out
-    let mut compare_base_oid = crate::merge_base::merge_base(
-        &cwd,
-        &repo,
-        resolved_base_oid,
-        head_oid,
-        crate::merge_base::MergeBaseStrategy::Bfs,
-    )
-    .unwrap_or(resolved_base_oid);
-    #[cfg(test)]
-    let mut merge_commit_for_debug: Option<String> = None;
-    if let Some(ref known_merge) = opts.lastKnownMergeCommitSha {
-        if let Some(merge_oid) = parse_oid(known_merge) {
-            if let Ok(obj) = repo.find_object(merge_oid) {
-                if let Ok(commit) = obj.try_into_commit() {
-                    if let Some(parent_oid) = commit.parent_ids().next().map(|p| p.detach()) {
-                        if is_ancestor(&repo, parent_oid, head_oid) {
-                            compare_base_oid = parent_oid;
-                            #[cfg(test)]
-                            {
-                                merge_commit_for_debug = Some(merge_oid.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    } else if base_ref_input.is_none() {
-        if let Some((merge_commit_oid, parent_oid)) =
-            find_merge_parent_on_base(&repo, resolved_base_oid, head_oid, 20_000)
-        {
-            compare_base_oid = parent_oid;
-            #[cfg(test)]
-            {
-                merge_commit_for_debug = Some(merge_commit_oid.to_string());
-            }
-            let _ = merge_commit_oid;
+    let mut head_end = window;
+    while !text.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    if let Some(nl) = text[..head_end].rfind('\n') {
+        head_end = nl + 1;
+    }
+    let mut tail_start = text.len() - window;
+    while !text.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    if let Some(nl) = text[tail_start..].find('\n') {
+        if tail_start + nl + 1 < text.len() {
+            tail_start += nl + 1;
         }
     }
-    #[cfg(test)]
-    LAST_DIFF_DEBUG.with(|cell| {
-        *cell.borrow_mut() = Some(DiffComputationDebug {
-            head_oid: head_oid.to_string(),
-            resolved_base_oid: resolved_base_oid.to_string(),
-            compare_base_oid: compare_base_oid.to_string(),
-            base_ref_input: base_ref_for_debug.clone(),
-            repo_path: cwd.clone(),
-            merge_commit_oid: merge_commit_for_debug.clone(),
-        });
-    });
-    let _d_merge_base = t_merge_base.elapsed();
-    #[cfg(debug_assertions)]
-    println!(
-        "[native.refs] MB({}, {})={}",
-        resolved_base_oid, head_oid, compare_base_oid
-    );
-
-    let t_tree_ids = Instant::now();
-    let base_commit = repo.find_object(compare_base_oid)?.try_into_commit()?;
-    let base_tree_id = base_commit.tree_id()?.detach();
-    let head_commit = repo.find_object(head_oid)?.try_into_commit()?;
-    let head_tree_id = head_commit.tree_id()?.detach();
-    let _d_tree_ids = t_tree_ids.elapsed();
+    if head_end >= tail_start {
+        return None;
+    }
+    let head = &text[..head_end];
+    let mut out = String::with_capacity(head_end + text.len() - tail_start + 40);
+    out.push_str(head);
+    if !head.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "... {} bytes truncated ...\n",
+        tail_start - head_end
+    ));
+    out.push_str(&text[tail_start..]);
+    Some(out)
+}
 
+/// Compare two trees of `repo`, entries unsorted.
+fn diff_trees(
+    repo: &Repository,
+    base_tree_id: ObjectId,
+    head_tree_id: ObjectId,
+    opts: &TreeDiffOptions,
+    stats: &mut TreeDiffStats,
+) -> Result<Vec<DiffEntry>> {
     let mut base_map: HashMap<String, ObjectId> = HashMap::new();
     let mut head_map: HashMap<String, ObjectId> = HashMap::new();
+    let mut base_links: HashMap<String, ObjectId> = HashMap::new();
+    let mut head_links: HashMap<String, ObjectId> = HashMap::new();
     let t_collect_base = Instant::now();
-    collect_tree_blobs(&repo, base_tree_id, "", &mut base_map)?;
-    let _d_collect_base = t_collect_base.elapsed();
+    collect_tree_entries(repo, base_tree_id, "", &mut base_map, Some(&mut base_links))?;
+    stats.collect_base += t_collect_base.elapsed();
     let t_collect_head = Instant::now();
-    collect_tree_blobs(&repo, head_tree_id, "", &mut head_map)?;
-    let _d_collect_head = t_collect_head.elapsed();
-
-    // Utility closures to obtain blob data safely; handle submodules and non-blobs gracefully
-    let mut out: Vec<DiffEntry> = Vec::new();
-    let mut _num_added: usize = 0;
-    let mut _num_modified: usize = 0;
-    let mut _num_deleted: usize = 0;
-    let mut _num_binary: usize = 0;
-    let mut _total_scanned_bytes: usize = 0;
-    let mut _blob_read_ns: u128 = 0;
-    let mut _textdiff_ns: u128 = 0;
-    let mut _textdiff_count: usize = 0;
-    let mut _max_diff_ns: u128 = 0;
-    let mut _max_diff_path: Option<String> = None;
+    collect_tree_entries(repo, head_tree_id, "", &mut head_map, Some(&mut head_links))?;
+    stats.collect_head += t_collect_head.elapsed();
+
+    let mut out = diff_gitlinks(repo, &base_links, &head_links, opts, stats)?;
 
     let get_blob_bytes = |id: ObjectId| -> Option<Vec<u8>> {
-        if let Ok(obj) = repo.find_object(id) {
-            if let Ok(blob) = obj.try_into_blob() {
-                return Some(blob.data.to_vec());
-            }
-        }
-        None
+        let obj = repo.find_object(id).ok()?;
+        let blob = obj.try_into_blob().ok()?;
+        Some(blob.data.to_vec())
     };
+    out.extend(diff_entry_maps(
+        &base_map,
+        &head_map,
+        &get_blob_bytes,
+        opts,
+        stats,
+    )?);
+    Ok(out)
+}
 
+/// Entries between two path -> blob maps, unsorted. Deletions and additions
+/// with the same blob id are paired as renames; `load` returns a blob's
+/// content, or `None` for blobs that can't be read (reported as binary).
+fn diff_entry_maps(
+    base_map: &HashMap<String, ObjectId>,
+    head_map: &HashMap<String, ObjectId>,
+    get_blob_bytes: &dyn Fn(ObjectId) -> Option<Vec<u8>>,
+    opts: &TreeDiffOptions,
+    stats: &mut TreeDiffStats,
+) -> Result<Vec<DiffEntry>> {
+    let mut out = Vec::new();
     // Precompute path partitions
     let mut base_only: HashMap<String, ObjectId> = HashMap::new();
     let mut head_only: HashMap<String, ObjectId> = HashMap::new();
-    for (p, oid) in &base_map {
+    for (p, oid) in base_map {
         if !head_map.contains_key(p) {
             base_only.insert(p.clone(), *oid);
         }
     }
-    for (p, oid) in &head_map {
+    for (p, oid) in head_map {
         if !base_map.contains_key(p) {
             head_only.insert(p.clone(), *oid);
         }
@@ -386,23 +457,21 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
     let mut renamed_pairs: Vec<(String, String, ObjectId)> = Vec::new();
     for (oid, olds) in id_to_old.iter_mut() {
         if let Some(news) = id_to_new.get_mut(oid) {
-            let n = std::cmp::min(olds.len(), news.len());
-            for _ in 0..n {
-                let old_p = olds.pop().unwrap();
-                let new_p = news.pop().unwrap();
-                renamed_pairs.push((old_p.clone(), new_p.clone(), *oid));
+            while let (Some(old_p), Some(new_p)) = (olds.pop(), news.pop()) {
                 // Remove matched from base_only/head_only
                 base_only.remove(&old_p);
                 head_only.remove(&new_p);
+                renamed_pairs.push((old_p, new_p, *oid));
             }
         }
     }
 
     // Emit renames (content identical by OID)
     for (old_path, new_path, oid) in renamed_pairs {
+        crate::cancel::check()?;
         let t_bl = Instant::now();
         let new_data = get_blob_bytes(oid);
-        _blob_read_ns += t_bl.elapsed().as_nanos();
+        stats.blob_read_ns += t_bl.elapsed().as_nanos();
         // New content may be missing (e.g., submodule) -> treat as binary
         let bin = match &new_data {
             Some(buf) => is_binary(buf),
@@ -415,13 +484,15 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
             additions: 0,
             deletions: 0,
             isBinary: bin,
+            oldSha: Some(oid.to_string()),
+            newSha: Some(oid.to_string()),
             ..Default::default()
         };
         if let Some(buf) = &new_data {
             e.newSize = Some(buf.len() as i32);
             e.oldSize = Some(buf.len() as i32);
         }
-        if include && !bin {
+        if opts.include && !bin {
             e.contentOmitted = Some(true);
         } else {
             e.contentOmitted = Some(false);
@@ -431,7 +502,8 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
 
     // Handle modifications where the path exists in both
     let t_loop_add_mod = Instant::now();
-    for (path, new_id) in &head_map {
+    for (path, new_id) in head_map {
+        crate::cancel::check()?;
         if let Some(old_id) = base_map.get(path) {
             if old_id == new_id {
                 continue;
@@ -439,7 +511,7 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
             let t_bl1 = Instant::now();
             let old_data = get_blob_bytes(*old_id);
             let new_data = get_blob_bytes(*new_id);
-            _blob_read_ns += t_bl1.elapsed().as_nanos();
+            stats.blob_read_ns += t_bl1.elapsed().as_nanos();
             let bin = match (&old_data, &new_data) {
                 (Some(a), Some(b)) => is_binary(a) || is_binary(b),
                 _ => true,
@@ -450,16 +522,22 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
                 additions: 0,
                 deletions: 0,
                 isBinary: bin,
+                oldSha: Some(old_id.to_string()),
+                newSha: Some(new_id.to_string()),
+                oldSize: old_data.as_ref().map(|d| d.len() as i32),
+                newSize: new_data.as_ref().map(|d| d.len() as i32),
                 ..Default::default()
             };
-            if include && !bin {
-                let old_str = String::from_utf8_lossy(old_data.as_ref().unwrap()).into_owned();
-                let new_str = String::from_utf8_lossy(new_data.as_ref().unwrap()).into_owned();
+            if let (true, false, Some(old_buf), Some(new_buf)) =
+                (opts.include, bin, &old_data, &new_data)
+            {
+                let old_str = String::from_utf8_lossy(old_buf).into_owned();
+                let new_str = String::from_utf8_lossy(new_buf).into_owned();
                 let old_sz = old_str.len();
                 let new_sz = new_str.len();
                 e.oldSize = Some(old_sz as i32);
                 e.newSize = Some(new_sz as i32);
-                if old_sz + new_sz <= max_bytes {
+                if old_sz + new_sz <= opts.max_bytes {
                     let t_diff = Instant::now();
                     // Use changes grouped by operations; count per-line inserts/deletes only.
                     let diff = TextDiff::from_lines(&old_str, &new_str);
@@ -475,12 +553,12 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
                         }
                     }
                     let d_diff = t_diff.elapsed().as_nanos();
-                    _textdiff_ns += d_diff;
-                    _textdiff_count += 1;
-                    _total_scanned_bytes += old_sz + new_sz;
-                    if d_diff > _max_diff_ns {
-                        _max_diff_ns = d_diff;
-                        _max_diff_path = Some(path.clone());
+                    stats.textdiff_ns += d_diff;
+                    stats.textdiff_count += 1;
+                    stats.total_scanned_bytes += old_sz + new_sz;
+                    if d_diff > stats.max_diff_ns {
+                        stats.max_diff_ns = d_diff;
+                        stats.max_diff_path = Some(path.clone());
                     }
                     e.additions = adds;
                     e.deletions = dels;
@@ -488,6 +566,13 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
                     e.newContent = Some(new_str);
                     e.contentOmitted = Some(false);
                 } else {
+                    // Line counts need the whole text, so truncated entries report none.
+                    if let Some((old_cut, new_cut, truncation)) = opts.truncate(&old_str, &new_str)
+                    {
+                        e.oldContent = Some(old_cut);
+                        e.newContent = Some(new_cut);
+                        e.truncation = Some(truncation);
+                    }
                     e.contentOmitted = Some(true);
                 }
             } else {
@@ -495,19 +580,20 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
             }
             // Do not filter out zero-line modifications: mode changes or metadata changes should still show up.
             out.push(e);
-            _num_modified += 1;
+            stats.num_modified += 1;
             if bin {
-                _num_binary += 1;
+                stats.num_binary += 1;
             }
         }
     }
-    let _d_loop_add_mod = t_loop_add_mod.elapsed();
+    stats.add_mod_loop += t_loop_add_mod.elapsed();
 
     // Additions not matched as renames
     for (path, new_id) in &head_only {
+        crate::cancel::check()?;
         let t_bl = Instant::now();
         let new_data = get_blob_bytes(*new_id);
-        _blob_read_ns += t_bl.elapsed().as_nanos();
+        stats.blob_read_ns += t_bl.elapsed().as_nanos();
         let (bin, new_sz) = match &new_data {
             Some(buf) => (is_binary(buf), buf.len()),
             None => (true, 0),
@@ -518,37 +604,46 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
             additions: 0,
             deletions: 0,
             isBinary: bin,
+            newSha: Some(new_id.to_string()),
+            newSize: new_data.as_ref().map(|_| new_sz as i32),
             ..Default::default()
         };
-        if include && !bin {
-            let new_str = String::from_utf8_lossy(new_data.as_ref().unwrap()).into_owned();
+        if let (true, false, Some(new_buf)) = (opts.include, bin, &new_data) {
+            let new_str = String::from_utf8_lossy(new_buf).into_owned();
             e.newSize = Some(new_sz as i32);
             e.oldSize = Some(0);
-            if new_sz <= max_bytes {
+            if new_sz <= opts.max_bytes {
                 e.oldContent = Some(String::new());
                 e.newContent = Some(new_str.clone());
                 e.contentOmitted = Some(false);
                 e.additions = new_str.lines().count() as i32;
-                _total_scanned_bytes += new_sz;
+                stats.total_scanned_bytes += new_sz;
             } else {
+                if let Some((old_cut, new_cut, truncation)) = opts.truncate("", &new_str) {
+                    e.oldContent = Some(old_cut);
+                    e.newContent = Some(new_cut);
+                    e.truncation = Some(truncation);
+                    e.additions = new_str.lines().count() as i32;
+                }
                 e.contentOmitted = Some(true);
             }
         } else {
             e.contentOmitted = Some(false);
         }
         out.push(e);
-        _num_added += 1;
+        stats.num_added += 1;
         if bin {
-            _num_binary += 1;
+            stats.num_binary += 1;
         }
     }
 
     // Deletions not matched as renames
     let t_loop_del = Instant::now();
     for (path, old_id) in &base_only {
+        crate::cancel::check()?;
         let t_bl = Instant::now();
         let old_data = get_blob_bytes(*old_id);
-        _blob_read_ns += t_bl.elapsed().as_nanos();
+        stats.blob_read_ns += t_bl.elapsed().as_nanos();
         let (bin, old_sz) = match &old_data {
             Some(buf) => (is_binary(buf), buf.len()),
             None => (true, 0),
@@ -559,30 +654,287 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
             additions: 0,
             deletions: 0,
             isBinary: bin,
+            oldSha: Some(old_id.to_string()),
+            oldSize: old_data.as_ref().map(|_| old_sz as i32),
             ..Default::default()
         };
-        if include && !bin {
-            let old_str = String::from_utf8_lossy(old_data.as_ref().unwrap()).into_owned();
+        if let (true, false, Some(old_buf)) = (opts.include, bin, &old_data) {
+            let old_str = String::from_utf8_lossy(old_buf).into_owned();
             e.oldSize = Some(old_sz as i32);
-            if old_sz <= max_bytes {
+            if old_sz <= opts.max_bytes {
+                e.deletions = old_str.lines().count() as i32;
                 e.oldContent = Some(old_str);
                 e.newContent = Some(String::new());
                 e.contentOmitted = Some(false);
-                e.deletions = e.oldContent.as_ref().unwrap().lines().count() as i32;
-                _total_scanned_bytes += old_sz;
+                stats.total_scanned_bytes += old_sz;
             } else {
+                if let Some((old_cut, new_cut, truncation)) = opts.truncate(&old_str, "") {
+                    e.oldContent = Some(old_cut);
+                    e.newContent = Some(new_cut);
+                    e.truncation = Some(truncation);
+                    e.deletions = old_str.lines().count() as i32;
+                }
                 e.contentOmitted = Some(true);
             }
         } else {
             e.contentOmitted = Some(false);
         }
         out.push(e);
-        _num_deleted += 1;
+        stats.num_deleted += 1;
         if bin {
-            _num_binary += 1;
+            stats.num_binary += 1;
         }
     }
-    let _d_loop_del = t_loop_del.elapsed();
+    stats.del_loop += t_loop_del.elapsed();
+
+    Ok(out)
+}
+
+/// Entries between two trees of `repo` with contents of files up to
+/// `max_bytes`, sorted like [`diff_refs`]. Submodules are reported as
+/// pointer changes only.
+pub(crate) fn diff_tree_ids(
+    repo: &Repository,
+    base_tree_id: ObjectId,
+    head_tree_id: ObjectId,
+    max_bytes: usize,
+) -> Result<Vec<DiffEntry>> {
+    let opts = TreeDiffOptions {
+        include: true,
+        max_bytes,
+        truncate_window: None,
+        max_truncated_source: DEFAULT_MAX_TRUNCATED_SOURCE,
+        submodule_depth: 0,
+    };
+    let mut out = diff_trees(
+        repo,
+        base_tree_id,
+        head_tree_id,
+        &opts,
+        &mut TreeDiffStats::default(),
+    )?;
+    sort_entries(&mut out);
+    Ok(out)
+}
+
+/// Entries between two path -> blob maps with contents up to `max_bytes`,
+/// sorted like [`diff_refs`]. Used for index and working-tree snapshots,
+/// whose blobs `load` may read from disk rather than the object database.
+pub(crate) fn diff_blob_maps(
+    base_map: &HashMap<String, ObjectId>,
+    head_map: &HashMap<String, ObjectId>,
+    load: &dyn Fn(ObjectId) -> Option<Vec<u8>>,
+    include: bool,
+    max_bytes: usize,
+) -> Result<Vec<DiffEntry>> {
+    let opts = TreeDiffOptions {
+        include,
+        max_bytes,
+        truncate_window: None,
+        max_truncated_source: DEFAULT_MAX_TRUNCATED_SOURCE,
+        submodule_depth: 0,
+    };
+    let mut out = diff_entry_maps(
+        base_map,
+        head_map,
+        load,
+        &opts,
+        &mut TreeDiffStats::default(),
+    )?;
+    sort_entries(&mut out);
+    Ok(out)
+}
+
+fn sort_entries(entries: &mut [DiffEntry]) {
+    // Stable sort by filePath (case-insensitive)
+    entries.sort_by(|a, b| {
+        a.filePath
+            .to_lowercase()
+            .cmp(&b.filePath.to_lowercase())
+            .then_with(|| a.filePath.cmp(&b.filePath))
+    });
+}
+
+pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
+    let include = opts.includeContents.unwrap_or(true);
+    let max_bytes = opts.maxBytes.unwrap_or(950 * 1024) as usize;
+    let t_total = Instant::now();
+    #[cfg(test)]
+    LAST_DIFF_DEBUG.with(|cell| {
+        *cell.borrow_mut() = None;
+    });
+
+    let head_ref = opts.headRef.trim();
+    if head_ref.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let base_ref_input = opts
+        .baseRef
+        .as_ref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    #[cfg(test)]
+    let base_ref_for_debug = base_ref_input.clone();
+
+    #[cfg(debug_assertions)]
+    println!(
+        "[native.refs] start headRef={} baseRef={:?} originPathOverride={:?} repoFullName={:?}",
+        head_ref, base_ref_input, opts.originPathOverride, opts.repoFullName
+    );
+
+    let t_repo_path = Instant::now();
+    let repo_path = if let Some(p) = &opts.originPathOverride {
+        containing_repo_root(Path::new(p))
+    } else {
+        let url = resolve_repo_url(opts.repoFullName.as_deref(), opts.repoUrl.as_deref())?;
+        ensure_repo(&url)?
+    };
+    let _d_repo_path = t_repo_path.elapsed();
+    let cwd = repo_path.to_string_lossy().to_string();
+
+    // If a specific repo path is provided, assume the caller ensures freshness.
+    // Avoid synchronous fetch here to reduce latency.
+    let _d_fetch = if opts.originPathOverride.is_some() {
+        Duration::from_millis(0)
+    } else {
+        let t_fetch = Instant::now();
+        let _ = crate::repo::cache::swr_fetch_origin_all_path(
+            std::path::Path::new(&cwd),
+            crate::repo::cache::fetch_window_ms(),
+        );
+        t_fetch.elapsed()
+    };
+
+    let t_open = Instant::now();
+    let repo = crate::repo::handles::open(&cwd)?;
+    let _d_open = t_open.elapsed();
+    let t_head = Instant::now();
+    let head_oid = match oid_from_rev_parse(&repo, head_ref) {
+        Ok(oid) => oid,
+        Err(e) => {
+            let _d_head = t_head.elapsed();
+            #[cfg(debug_assertions)]
+            println!(
+        "[cmux_native_git] git_diff timings: total={}ms resolve_head={}ms (failed to resolve); cwd={}",
+        t_total.elapsed().as_millis(),
+        _d_head.as_millis(),
+        cwd,
+      );
+            return Err(e);
+        }
+    };
+    let _d_head = t_head.elapsed();
+
+    let t_base = Instant::now();
+    let mut resolved_base_oid = match base_ref_input {
+        Some(ref spec) => match oid_from_rev_parse(&repo, spec) {
+            Ok(oid) => oid,
+            Err(e) => {
+                let _d_base = t_base.elapsed();
+                #[cfg(debug_assertions)]
+                println!(
+          "[cmux_native_git] git_diff timings: total={}ms resolve_head={}ms resolve_base={}ms (failed to resolve); cwd={}",
+          t_total.elapsed().as_millis(),
+          _d_head.as_millis(),
+          _d_base.as_millis(),
+          cwd,
+        );
+                return Err(e);
+            }
+        },
+        None => resolve_default_base(&repo, head_oid),
+    };
+    let _d_base = t_base.elapsed();
+    if let Some(ref known_base) = opts.lastKnownBaseSha {
+        if let Some(candidate) = parse_oid(known_base) {
+            if repo.find_object(candidate).is_ok() && is_ancestor(&repo, candidate, head_oid) {
+                resolved_base_oid = candidate;
+            }
+        }
+    }
+    let t_merge_base = Instant::now();
+    // Compute merge-base; prefer BFS (pure gix) to avoid shelling out
+    let mut compare_base_oid = crate::merge_base::merge_base(
+        &cwd,
+        &repo,
+        resolved_base_oid,
+        head_oid,
+        crate::merge_base::MergeBaseStrategy::Bfs,
+    )
+    .unwrap_or(resolved_base_oid);
+    #[cfg(test)]
+    let mut merge_commit_for_debug: Option<String> = None;
+    if let Some(ref known_merge) = opts.lastKnownMergeCommitSha {
+        if let Some(merge_oid) = parse_oid(known_merge) {
+            if let Ok(obj) = repo.find_object(merge_oid) {
+                if let Ok(commit) = obj.try_into_commit() {
+                    if let Some(parent_oid) = commit.parent_ids().next().map(|p| p.detach()) {
+                        if is_ancestor(&repo, parent_oid, head_oid) {
+                            compare_base_oid = parent_oid;
+                            #[cfg(test)]
+                            {
+                                merge_commit_for_debug = Some(merge_oid.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    } else if base_ref_input.is_none() {
+        if let Some((merge_commit_oid, parent_oid)) =
+            find_merge_parent_on_base(&repo, resolved_base_oid, head_oid, 20_000)
+        {
+            compare_base_oid = parent_oid;
+            #[cfg(test)]
+            {
+                merge_commit_for_debug = Some(merge_commit_oid.to_string());
+            }
+            let _ = merge_commit_oid;
+        }
+    }
+    #[cfg(test)]
+    LAST_DIFF_DEBUG.with(|cell| {
+        *cell.borrow_mut() = Some(DiffComputationDebug {
+            head_oid: head_oid.to_string(),
+            resolved_base_oid: resolved_base_oid.to_string(),
+            compare_base_oid: compare_base_oid.to_string(),
+            base_ref_input: base_ref_for_debug.clone(),
+            repo_path: cwd.clone(),
+            merge_commit_oid: merge_commit_for_debug.clone(),
+        });
+    });
+    let _d_merge_base = t_merge_base.elapsed();
+    #[cfg(debug_assertions)]
+    println!(
+        "[native.refs] MB({}, {})={}",
+        resolved_base_oid, head_oid, compare_base_oid
+    );
+
+    crate::cancel::check()?;
+    let t_tree_ids = Instant::now();
+    let base_commit = repo.find_object(compare_base_oid)?.try_into_commit()?;
+    let base_tree_id = base_commit.tree_id()?.detach();
+    let head_commit = repo.find_object(head_oid)?.try_into_commit()?;
+    let head_tree_id = head_commit.tree_id()?.detach();
+    let _d_tree_ids = t_tree_ids.elapsed();
+
+    let mut stats = TreeDiffStats::default();
+    let tree_opts = TreeDiffOptions {
+        include,
+        max_bytes,
+        truncate_window: opts
+            .truncateWindowBytes
+            .filter(|n| *n > 0)
+            .map(|n| n as usize),
+        max_truncated_source: opts
+            .maxTruncatedSourceBytes
+            .filter(|n| *n > 0)
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_MAX_TRUNCATED_SOURCE),
+        submodule_depth: opts.recurseSubmodules.unwrap_or(0).max(0) as u32,
+    };
+    let mut out = diff_trees(&repo, base_tree_id, head_tree_id, &tree_opts, &mut stats)?;
 
     let _d_total = t_total.elapsed();
     #[cfg(debug_assertions)]
@@ -596,20 +948,20 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
     _d_base.as_millis(),
     _d_merge_base.as_millis(),
     _d_tree_ids.as_millis(),
-    _d_collect_base.as_millis(),
-    _d_collect_head.as_millis(),
-    _d_loop_add_mod.as_millis(),
-    _d_loop_del.as_millis(),
-    (_blob_read_ns as f64 / 1_000_000.0) as i64,
-    (_textdiff_ns as f64 / 1_000_000.0) as i64,
-    _textdiff_count,
-    _total_scanned_bytes,
-    _num_added,
-    _num_modified,
-    _num_deleted,
-    _num_binary,
-    _max_diff_path,
-    (_max_diff_ns as f64 / 1_000_000.0) as i64,
+    stats.collect_base.as_millis(),
+    stats.collect_head.as_millis(),
+    stats.add_mod_loop.as_millis(),
+    stats.del_loop.as_millis(),
+    (stats.blob_read_ns as f64 / 1_000_000.0) as i64,
+    (stats.textdiff_ns as f64 / 1_000_000.0) as i64,
+    stats.textdiff_count,
+    stats.total_scanned_bytes,
+    stats.num_added,
+    stats.num_modified,
+    stats.num_deleted,
+    stats.num_binary,
+    stats.max_diff_path,
+    (stats.max_diff_ns as f64 / 1_000_000.0) as i64,
     cwd,
     out.len(),
   );
@@ -815,13 +1167,7 @@ pub fn diff_refs(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
         }
     }
 
-    // Stable sort by filePath (case-insensitive)
-    out.sort_by(|a, b| {
-        a.filePath
-            .to_lowercase()
-            .cmp(&b.filePath.to_lowercase())
-            .then_with(|| a.filePath.cmp(&b.filePath))
-    });
+    sort_entries(&mut out);
 
     Ok(out)
 }
@@ -0,0 +1,276 @@
+use anyhow::{anyhow, Result};
+use gix::bstr::ByteSlice;
+use gix::status::plumbing::index_as_worktree::{traits::FastEq, Change, EntryStatus};
+use gix::status::plumbing::index_as_worktree_with_renames::{Entry, VisitEntry};
+use gix::{hash::ObjectId, Repository};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use super::refs::{collect_tree_blobs, diff_blob_maps};
+use crate::types::{DiffEntry, GitDiffWorkingTreeOptions, GitStatusOptions, GitStatusResult};
+
+/// Snapshot of HEAD, the index and the working tree, all expressed as
+/// path -> blob id maps so the same comparison can be run between any two.
+struct Snapshot {
+    head: HashMap<String, ObjectId>,
+    index: HashMap<String, ObjectId>,
+    worktree: HashMap<String, ObjectId>,
+    /// Worktree content that is not (necessarily) in the object database yet.
+    worktree_files: HashMap<ObjectId, PathBuf>,
+    conflicted: HashSet<String>,
+    branch: Option<String>,
+    head_sha: Option<String>,
+}
+
+enum WorktreeChange {
+    Removed,
+    Changed,
+}
+
+/// Collects index-vs-worktree changes from the plumbing status walk.
+///
+/// The high-level `status()` iterator swallows racily-clean modifications
+/// (same size, same mtime second) while queueing an index refresh, so we
+/// consume the plumbing entries directly instead.
+#[derive(Default)]
+struct Collect {
+    changes: Vec<(String, WorktreeChange)>,
+}
+
+impl<'index> VisitEntry<'index> for Collect {
+    type ContentChange = ();
+    type SubmoduleStatus = ();
+
+    fn visit_entry(&mut self, entry: Entry<'index, (), ()>) {
+        match entry {
+            Entry::Modification {
+                rela_path, status, ..
+            } => {
+                let change = match status {
+                    EntryStatus::Change(Change::Removed) => WorktreeChange::Removed,
+                    EntryStatus::Change(_)
+                    | EntryStatus::IntentToAdd
+                    | EntryStatus::Conflict(_) => WorktreeChange::Changed,
+                    EntryStatus::NeedsUpdate(_) => return,
+                };
+                self.changes
+                    .push((rela_path.to_str_lossy().into_owned(), change));
+            }
+            Entry::DirectoryContents { entry, .. } => {
+                if matches!(entry.status, gix::dir::entry::Status::Untracked)
+                    && !matches!(entry.disk_kind, Some(gix::dir::entry::Kind::Directory))
+                {
+                    self.changes.push((
+                        entry.rela_path.to_str_lossy().into_owned(),
+                        WorktreeChange::Changed,
+                    ));
+                }
+            }
+            // Rewrite tracking is disabled; renames are paired by content id in `diff_blob_maps`.
+            Entry::Rewrite { .. } => {}
+        }
+    }
+}
+
+/// Submodules are reported by their recorded commit only.
+#[derive(Clone)]
+struct NoSubmoduleStatus;
+
+impl gix::status::plumbing::index_as_worktree::traits::SubmoduleStatus for NoSubmoduleStatus {
+    type Output = ();
+    type Error = std::convert::Infallible;
+
+    fn status(
+        &mut self,
+        _entry: &gix::index::Entry,
+        _rela_path: &gix::bstr::BStr,
+    ) -> Result<Option<()>, Self::Error> {
+        Ok(None)
+    }
+}
+
+fn read_worktree_file(path: &Path) -> Option<Vec<u8>> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    if meta.file_type().is_symlink() {
+        // Git stores the link target as the blob content.
+        let target = fs::read_link(path).ok()?;
+        return Some(target.to_string_lossy().into_owned().into_bytes());
+    }
+    if !meta.is_file() {
+        return None;
+    }
+    fs::read(path).ok()
+}
+
+fn snapshot(repo: &Repository, include_untracked: bool) -> Result<Snapshot> {
+    let workdir = repo
+        .work_dir()
+        .ok_or_else(|| anyhow!("repository has no working tree"))?
+        .to_path_buf();
+
+    let mut head: HashMap<String, ObjectId> = HashMap::new();
+    let mut head_sha = None;
+    // An unborn HEAD simply has nothing committed yet.
+    if let Ok(commit) = repo.head_commit() {
+        head_sha = Some(commit.id.to_string());
+        collect_tree_blobs(repo, commit.tree_id()?.detach(), "", &mut head)?;
+    }
+    let branch = repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|name| name.shorten().to_str_lossy().into_owned());
+
+    let index_file = repo.index_or_empty()?;
+    let mut index: HashMap<String, ObjectId> = HashMap::new();
+    let mut conflicted: HashSet<String> = HashSet::new();
+    for entry in index_file.entries() {
+        let path = entry.path(&index_file).to_str_lossy().into_owned();
+        match entry.stage() {
+            gix::index::entry::Stage::Unconflicted => {
+                // Intent-to-add entries carry the empty blob; they only show up as unstaged additions.
+                if entry
+                    .flags
+                    .contains(gix::index::entry::Flags::INTENT_TO_ADD)
+                {
+                    continue;
+                }
+                index.insert(path, entry.id);
+            }
+            gix::index::entry::Stage::Ours => {
+                conflicted.insert(path.clone());
+                index.insert(path, entry.id);
+            }
+            _ => {
+                conflicted.insert(path);
+            }
+        }
+    }
+
+    let mut worktree = index.clone();
+    let mut worktree_files: HashMap<ObjectId, PathBuf> = HashMap::new();
+    let track_file = |rela: &str,
+                      worktree: &mut HashMap<String, ObjectId>,
+                      worktree_files: &mut HashMap<ObjectId, PathBuf>| {
+        let abs = workdir.join(rela);
+        match read_worktree_file(&abs) {
+            Some(buf) => {
+                let id = gix::objs::compute_hash(repo.object_hash(), gix::objs::Kind::Blob, &buf);
+                worktree.insert(rela.to_string(), id);
+                worktree_files.insert(id, abs);
+            }
+            None => {
+                worktree.remove(rela);
+            }
+        }
+    };
+
+    let mut collect = Collect::default();
+    let mut dirwalk = repo.dirwalk_options()?;
+    dirwalk = dirwalk.emit_untracked(gix::dir::walk::EmissionMode::Matching);
+    repo.index_worktree_status(
+        &index_file,
+        Vec::<gix::bstr::BString>::new(),
+        &mut collect,
+        FastEq,
+        NoSubmoduleStatus,
+        &mut gix::progress::Discard,
+        &AtomicBool::new(false),
+        gix::status::index_worktree::Options {
+            sorting: None,
+            dirwalk_options: include_untracked.then_some(dirwalk),
+            rewrites: None,
+            thread_limit: None,
+        },
+    )?;
+    for (rela, change) in collect.changes {
+        match change {
+            WorktreeChange::Removed => {
+                worktree.remove(&rela);
+            }
+            WorktreeChange::Changed => track_file(&rela, &mut worktree, &mut worktree_files),
+        }
+    }
+
+    Ok(Snapshot {
+        head,
+        index,
+        worktree,
+        worktree_files,
+        conflicted,
+        branch,
+        head_sha,
+    })
+}
+
+fn loader<'a>(
+    repo: &'a Repository,
+    worktree_files: &'a HashMap<ObjectId, PathBuf>,
+) -> impl Fn(ObjectId) -> Option<Vec<u8>> + 'a {
+    move |id: ObjectId| {
+        if let Some(path) = worktree_files.get(&id) {
+            return read_worktree_file(path);
+        }
+        let obj = repo.find_object(id).ok()?;
+        let blob = obj.try_into_blob().ok()?;
+        Some(blob.data.to_vec())
+    }
+}
+
+fn without_paths(
+    map: &HashMap<String, ObjectId>,
+    skip: &HashSet<String>,
+) -> HashMap<String, ObjectId> {
+    map.iter()
+        .filter(|(p, _)| !skip.contains(*p))
+        .map(|(p, id)| (p.clone(), *id))
+        .collect()
+}
+
+pub fn git_status(opts: GitStatusOptions) -> Result<GitStatusResult> {
+    let include = opts.includeContents.unwrap_or(false);
+    let max_bytes = opts.maxBytes.unwrap_or(950 * 1024) as usize;
+    let repo = crate::repo::handles::open(&opts.worktreePath)?;
+    let snap = snapshot(&repo, opts.includeUntracked.unwrap_or(true))?;
+    let load = loader(&repo, &snap.worktree_files);
+
+    // Conflicted paths have no single staged version; report them on the unstaged side only.
+    let staged = diff_blob_maps(
+        &without_paths(&snap.head, &snap.conflicted),
+        &without_paths(&snap.index, &snap.conflicted),
+        &load,
+        include,
+        max_bytes,
+    )?;
+    let mut unstaged = diff_blob_maps(&snap.index, &snap.worktree, &load, include, max_bytes)?;
+    for e in unstaged.iter_mut() {
+        if snap.conflicted.contains(&e.filePath) {
+            e.status = "conflicted".into();
+        }
+    }
+
+    Ok(GitStatusResult {
+        branch: snap.branch,
+        headSha: snap.head_sha,
+        staged,
+        unstaged,
+    })
+}
+
+pub fn diff_working_tree(opts: GitDiffWorkingTreeOptions) -> Result<Vec<DiffEntry>> {
+    let include = opts.includeContents.unwrap_or(true);
+    let max_bytes = opts.maxBytes.unwrap_or(950 * 1024) as usize;
+    let repo = crate::repo::handles::open(&opts.worktreePath)?;
+    let snap = snapshot(&repo, opts.includeUntracked.unwrap_or(true))?;
+    let load = loader(&repo, &snap.worktree_files);
+    let (old_map, new_map) = if opts.staged.unwrap_or(false) {
+        (&snap.head, &snap.index)
+    } else {
+        (&snap.head, &snap.worktree)
+    };
+    diff_blob_maps(old_map, new_map, &load, include, max_bytes)
+}
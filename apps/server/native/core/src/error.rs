@@ -0,0 +1,162 @@
+use napi::bindgen_prelude::{Env, Task, ToNapiValue, TypeName};
+use std::fmt;
+
+use crate::branches::BranchOpError;
+use crate::cancel::Cancelled;
+
+/// Stable codes JS sees as the `code` property of a rejected error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotARepo,
+    RefNotFound,
+    PathNotFound,
+    InvalidArgument,
+    GitCommandFailed,
+    DirtyTree,
+    NonFastForward,
+    AlreadyExists,
+    Cancelled,
+    Timeout,
+    ProxyNotStarted,
+    ProxyAlreadyRunning,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NotARepo => "NOT_A_REPO",
+            ErrorCode::RefNotFound => "REF_NOT_FOUND",
+            ErrorCode::PathNotFound => "PATH_NOT_FOUND",
+            ErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+            ErrorCode::GitCommandFailed => "GIT_COMMAND_FAILED",
+            ErrorCode::DirtyTree => "DIRTY_TREE",
+            ErrorCode::NonFastForward => "NON_FAST_FORWARD",
+            ErrorCode::AlreadyExists => "ALREADY_EXISTS",
+            ErrorCode::Cancelled => "CANCELLED",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::ProxyNotStarted => "PROXY_NOT_STARTED",
+            ErrorCode::ProxyAlreadyRunning => "PROXY_ALREADY_RUNNING",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+}
+
+/// An error with a code callers can match on. Wrap it in `anyhow` like any
+/// other error; context added on top keeps the code.
+#[derive(Debug)]
+pub struct CoreError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl CoreError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CoreError {}
+
+/// The code for the first cause in `err`'s chain that has one.
+pub fn code_of(err: &anyhow::Error) -> ErrorCode {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<CoreError>() {
+            return e.code;
+        }
+        if let Some(e) = cause.downcast_ref::<BranchOpError>() {
+            return e.code();
+        }
+        if cause.is::<Cancelled>() {
+            return ErrorCode::Cancelled;
+        }
+        if let Some(gix::open::Error::NotARepository { .. }) = cause.downcast_ref() {
+            return ErrorCode::NotARepo;
+        }
+    }
+    ErrorCode::Internal
+}
+
+/// A JS `Error` carrying the message and `code` of `err`.
+pub fn to_js_error(env: Env, err: &anyhow::Error) -> napi::Error {
+    let build = || -> napi::Result<napi::Error> {
+        let mut obj = env.create_error(napi::Error::from_reason(format!("{err:#}")))?;
+        obj.set_named_property("code", env.create_string(code_of(err).as_str())?)?;
+        Ok(napi::Error::from(obj.into_unknown()))
+    };
+    build().unwrap_or_else(|e| e)
+}
+
+/// Runs blocking work on the libuv pool and rejects with [`to_js_error`].
+/// Async `#[napi]` fns can only reject with a napi `Status`, so functions
+/// that report codes return `AsyncTask<CoreTask<T>>` instead.
+pub struct CoreTask<T> {
+    work: Option<Box<dyn FnOnce() -> anyhow::Result<T> + Send>>,
+    error: Option<anyhow::Error>,
+}
+
+impl<T> CoreTask<T> {
+    pub fn new(work: impl FnOnce() -> anyhow::Result<T> + Send + 'static) -> Self {
+        Self {
+            work: Some(Box::new(work)),
+            error: None,
+        }
+    }
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for CoreTask<T> {
+    type Output = T;
+    type JsValue = T;
+
+    fn compute(&mut self) -> napi::Result<T> {
+        let work = self.work.take().expect("CoreTask computed twice");
+        work().map_err(|e| {
+            let reason = format!("{e:#}");
+            self.error = Some(e);
+            napi::Error::from_reason(reason)
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: T) -> napi::Result<T> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<T> {
+        match self.error.take() {
+            Some(e) => Err(to_js_error(env, &e)),
+            None => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn code_of_walks_the_error_chain() {
+        let tagged = anyhow::Error::new(CoreError::new(ErrorCode::RefNotFound, "no such rev"))
+            .context("diff failed");
+        assert_eq!(code_of(&tagged), ErrorCode::RefNotFound);
+        assert_eq!(tagged.to_string(), "diff failed");
+
+        let missing = gix::open("/definitely/not/a/repo").context("open");
+        assert_eq!(code_of(&missing.unwrap_err()), ErrorCode::NotARepo);
+
+        let branch = anyhow::Error::new(BranchOpError::AlreadyExists("main".into()));
+        assert_eq!(code_of(&branch), ErrorCode::AlreadyExists);
+
+        assert_eq!(code_of(&Cancelled.into()), ErrorCode::Cancelled);
+        assert_eq!(code_of(&anyhow::anyhow!("boom")), ErrorCode::Internal);
+    }
+}
@@ -1,15 +1,33 @@
 #![deny(clippy::all)]
 
 mod branches;
+mod cancel;
 mod diff;
+mod error;
+mod log;
 mod merge_base;
+mod proxy;
 mod repo;
+mod show;
 mod types;
 mod util;
+mod watch;
 
+use error::{to_js_error, CoreTask};
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::JsFunction;
 use napi_derive::napi;
-use types::{BranchInfo, DiffEntry, GitDiffOptions, GitListRemoteBranchesOptions};
+use types::{
+    BranchInfo, DiffEntry, GitBranchOpResult, GitCheckoutOptions, GitCreateBranchOptions,
+    GitDiffOptions, GitDiffPage, GitDiffPageRequest, GitDiffWorkingTreeOptions,
+    GitFileAtRefOptions, GitFileContent, GitListRemoteBranchesOptions, GitLogOptions, GitLogResult,
+    GitMergeFfOnlyOptions, GitMergePreview, GitMergePreviewOptions, GitShowOptions, GitShowResult,
+    GitStatusOptions, GitStatusResult, GitWatchEvent, GitWatchOptions,
+    WorkspaceProxyAccessLogEvent, WorkspaceProxyInfo, WorkspaceProxyOptions,
+};
 
 #[napi]
 pub async fn get_time() -> String {
@@ -20,8 +38,34 @@ pub async fn get_time() -> String {
     now.as_millis().to_string()
 }
 
+/// Drop cached repository handles for `path` (and repositories inside it).
+/// Returns false if nothing was cached.
 #[napi]
-pub async fn git_diff(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
+pub fn git_cache_invalidate(path: String) -> bool {
+    repo::handles::invalidate(path)
+}
+
+/// Allocate an operation id to pass to a long git call so it can be
+/// cancelled with `cancelGitOperation`.
+#[napi]
+pub fn create_git_operation() -> u32 {
+    cancel::create_operation()
+}
+
+/// Cancel a git call started with this operation id. Returns false if the
+/// operation is unknown or already finished.
+#[napi]
+pub fn cancel_git_operation(id: u32) -> bool {
+    cancel::cancel_operation(id)
+}
+
+/// Rejects with an `Error` whose `code` is one of the `ErrorCode` strings,
+/// e.g. `REF_NOT_FOUND` or `CANCELLED`.
+#[napi(ts_return_type = "Promise<Array<DiffEntry>>")]
+pub fn git_diff(
+    opts: GitDiffOptions,
+    operation_id: Option<u32>,
+) -> AsyncTask<CoreTask<Vec<DiffEntry>>> {
     #[cfg(debug_assertions)]
     println!(
     "[cmux_native_git] git_diff headRef={} baseRef={:?} originPathOverride={:?} repoUrl={:?} repoFullName={:?} includeContents={:?} maxBytes={:?}",
@@ -33,16 +77,83 @@ pub async fn git_diff(opts: GitDiffOptions) -> Result<Vec<DiffEntry>> {
     opts.includeContents,
     opts.maxBytes
   );
-    tokio::task::spawn_blocking(move || diff::refs::diff_refs(opts))
-        .await
-        .map_err(|e| Error::from_reason(format!("Join error: {e}")))?
-        .map_err(|e| Error::from_reason(format!("{e:#}")))
+    let operation = cancel::claim(operation_id);
+    AsyncTask::new(CoreTask::new(move || {
+        operation.run(|| diff::refs::diff_refs(opts))
+    }))
 }
 
-#[napi]
-pub async fn git_list_remote_branches(
+#[napi(ts_return_type = "Promise<GitDiffPage>")]
+pub fn git_diff_page(
+    opts: GitDiffOptions,
+    page: Option<GitDiffPageRequest>,
+    operation_id: Option<u32>,
+) -> AsyncTask<CoreTask<GitDiffPage>> {
+    let page = page.unwrap_or_default();
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_diff_page headRef={} baseRef={:?} cursor={:?} pageSize={:?} maxTotalBytes={:?}",
+        opts.headRef, opts.baseRef, page.cursor, page.pageSize, page.maxTotalBytes
+    );
+    let operation = cancel::claim(operation_id);
+    AsyncTask::new(CoreTask::new(move || {
+        operation.run(|| diff::page::diff_page(opts, page))
+    }))
+}
+
+/// Rejects with `code: "PATH_NOT_FOUND"` when the file is not in that commit.
+#[napi(ts_return_type = "Promise<GitFileContent>")]
+pub fn git_file_at_ref(opts: GitFileAtRefOptions) -> AsyncTask<CoreTask<GitFileContent>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_file_at_ref ref={} path={} maxBytes={:?} originPathOverride={:?}",
+        opts.gitRef, opts.path, opts.maxBytes, opts.originPathOverride
+    );
+    AsyncTask::new(CoreTask::new(move || show::file_at_ref(opts)))
+}
+
+/// Commit details with a unified patch per changed file, against the first parent.
+#[napi(ts_return_type = "Promise<GitShowResult>")]
+pub fn git_show(
+    opts: GitShowOptions,
+    operation_id: Option<u32>,
+) -> AsyncTask<CoreTask<GitShowResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_show hash={} maxBytes={:?} contextLines={:?} originPathOverride={:?}",
+        opts.hash, opts.maxBytes, opts.contextLines, opts.originPathOverride
+    );
+    let operation = cancel::claim(operation_id);
+    AsyncTask::new(CoreTask::new(move || operation.run(|| show::show(opts))))
+}
+
+#[napi(ts_return_type = "Promise<GitStatusResult>")]
+pub fn git_status(opts: GitStatusOptions) -> AsyncTask<CoreTask<GitStatusResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_status worktreePath={} includeUntracked={:?} includeContents={:?} maxBytes={:?}",
+        opts.worktreePath, opts.includeUntracked, opts.includeContents, opts.maxBytes
+    );
+    AsyncTask::new(CoreTask::new(move || diff::status::git_status(opts)))
+}
+
+#[napi(ts_return_type = "Promise<Array<DiffEntry>>")]
+pub fn git_diff_working_tree(
+    opts: GitDiffWorkingTreeOptions,
+) -> AsyncTask<CoreTask<Vec<DiffEntry>>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_diff_working_tree worktreePath={} staged={:?} includeUntracked={:?} includeContents={:?} maxBytes={:?}",
+        opts.worktreePath, opts.staged, opts.includeUntracked, opts.includeContents, opts.maxBytes
+    );
+    AsyncTask::new(CoreTask::new(move || diff::status::diff_working_tree(opts)))
+}
+
+#[napi(ts_return_type = "Promise<Array<BranchInfo>>")]
+pub fn git_list_remote_branches(
     opts: GitListRemoteBranchesOptions,
-) -> Result<Vec<BranchInfo>> {
+    operation_id: Option<u32>,
+) -> AsyncTask<CoreTask<Vec<BranchInfo>>> {
     #[cfg(debug_assertions)]
     println!(
     "[cmux_native_git] git_list_remote_branches repoFullName={:?} repoUrl={:?} originPathOverride={:?}",
@@ -50,10 +161,146 @@ pub async fn git_list_remote_branches(
     opts.repoUrl,
     opts.originPathOverride
   );
-    tokio::task::spawn_blocking(move || branches::list_remote_branches(opts))
-        .await
-        .map_err(|e| Error::from_reason(format!("Join error: {e}")))?
-        .map_err(|e| Error::from_reason(format!("{e:#}")))
+    let operation = cancel::claim(operation_id);
+    AsyncTask::new(CoreTask::new(move || {
+        operation.run(|| branches::list_remote_branches(opts))
+    }))
+}
+
+#[napi(ts_return_type = "Promise<GitLogResult>")]
+pub fn git_log(
+    opts: GitLogOptions,
+    operation_id: Option<u32>,
+) -> AsyncTask<CoreTask<GitLogResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_log refs={:?} paths={:?} order={:?} skip={:?} maxCount={:?} originPathOverride={:?}",
+        opts.refs, opts.paths, opts.order, opts.skip, opts.maxCount, opts.originPathOverride
+    );
+    let operation = cancel::claim(operation_id);
+    AsyncTask::new(CoreTask::new(move || operation.run(|| log::git_log(opts))))
+}
+
+#[napi(ts_return_type = "Promise<GitBranchOpResult>")]
+pub fn git_create_branch(opts: GitCreateBranchOptions) -> AsyncTask<CoreTask<GitBranchOpResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_create_branch worktreePath={} name={} startPoint={:?} force={:?} checkout={:?}",
+        opts.worktreePath, opts.name, opts.startPoint, opts.force, opts.checkout
+    );
+    AsyncTask::new(CoreTask::new(move || branches::create_branch(opts)))
+}
+
+#[napi(ts_return_type = "Promise<GitBranchOpResult>")]
+pub fn git_checkout(opts: GitCheckoutOptions) -> AsyncTask<CoreTask<GitBranchOpResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_checkout worktreePath={} target={}",
+        opts.worktreePath, opts.target
+    );
+    AsyncTask::new(CoreTask::new(move || branches::checkout(opts)))
+}
+
+#[napi(ts_return_type = "Promise<GitBranchOpResult>")]
+pub fn git_merge_ff_only(opts: GitMergeFfOnlyOptions) -> AsyncTask<CoreTask<GitBranchOpResult>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_merge_ff_only worktreePath={} target={}",
+        opts.worktreePath, opts.target
+    );
+    AsyncTask::new(CoreTask::new(move || branches::merge_ff_only(opts)))
+}
+
+#[napi(ts_return_type = "Promise<GitMergePreview>")]
+pub fn git_merge_preview(opts: GitMergePreviewOptions) -> AsyncTask<CoreTask<GitMergePreview>> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_merge_preview baseRef={} headRef={} originPathOverride={:?} repoFullName={:?}",
+        opts.baseRef, opts.headRef, opts.originPathOverride, opts.repoFullName
+    );
+    AsyncTask::new(CoreTask::new(move || {
+        merge_base::preview::merge_preview(opts)
+    }))
+}
+
+/// Handle returned by `gitWatch`; call `unsubscribe()` to stop watching.
+#[napi]
+pub struct GitWatchHandle {
+    sub: Option<watch::WatchSubscription>,
+}
+
+#[napi]
+impl GitWatchHandle {
+    #[napi]
+    pub fn unsubscribe(&mut self) {
+        if let Some(sub) = self.sub.take() {
+            sub.stop();
+        }
+    }
+}
+
+#[napi(ts_args_type = "opts: GitWatchOptions, callback: (event: GitWatchEvent) => void")]
+pub fn git_watch(env: Env, opts: GitWatchOptions, callback: JsFunction) -> Result<GitWatchHandle> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_git] git_watch worktreePath={} debounceMs={:?}",
+        opts.worktreePath, opts.debounceMs
+    );
+    let tsfn: ThreadsafeFunction<GitWatchEvent, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<GitWatchEvent>| {
+            Ok(vec![ctx.value])
+        })?;
+    let sub = watch::watch_repo(opts, move |event| {
+        tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    })
+    .map_err(|e| to_js_error(env, &e))?;
+    Ok(GitWatchHandle { sub: Some(sub) })
+}
+
+/// Start the workspace proxy in-process. `listen` is `host:port` (port 0 picks
+/// a free one); `onAccessLog` receives one event per proxied request.
+#[napi(
+    ts_args_type = "listen: string, upstream: string, options?: WorkspaceProxyOptions, onAccessLog?: (event: WorkspaceProxyAccessLogEvent) => void"
+)]
+pub fn start_workspace_proxy(
+    env: Env,
+    listen: String,
+    upstream: String,
+    options: Option<WorkspaceProxyOptions>,
+    on_access_log: Option<JsFunction>,
+) -> Result<WorkspaceProxyInfo> {
+    #[cfg(debug_assertions)]
+    println!(
+        "[cmux_native_proxy] start_workspace_proxy listen={} upstream={} options={:?}",
+        listen, upstream, options
+    );
+    let tsfn = on_access_log
+        .map(|callback| {
+            callback.create_threadsafe_function(
+                0,
+                |ctx: ThreadSafeCallContext<WorkspaceProxyAccessLogEvent>| Ok(vec![ctx.value]),
+            )
+        })
+        .transpose()?;
+    let on_event = tsfn.map(
+        |tsfn: ThreadsafeFunction<WorkspaceProxyAccessLogEvent, ErrorStrategy::Fatal>| {
+            move |event| {
+                tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        },
+    );
+    within_runtime_if_available(|| {
+        proxy::start_workspace_proxy(&listen, &upstream, options.unwrap_or_default(), on_event)
+    })
+    .map_err(|e| to_js_error(env, &e))
+}
+
+/// Rejects with `code: "PROXY_NOT_STARTED"` if no proxy is running.
+#[napi(ts_return_type = "Promise<void>")]
+pub fn stop_workspace_proxy() -> AsyncTask<CoreTask<()>> {
+    #[cfg(debug_assertions)]
+    println!("[cmux_native_proxy] stop_workspace_proxy");
+    AsyncTask::new(CoreTask::new(|| block_on(proxy::stop_workspace_proxy())))
 }
 
 #[cfg(test)]
@@ -0,0 +1,530 @@
+use anyhow::Result;
+use gix::bstr::ByteSlice;
+use gix::hash::ObjectId;
+use gix::Repository;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::error::{CoreError, ErrorCode};
+use crate::repo::cache::{ensure_repo, resolve_repo_url};
+use crate::types::{CommitInfo, GitLogOptions, GitLogResult};
+
+const DEFAULT_MAX_COUNT: usize = 200;
+
+struct Node {
+    parents: Vec<ObjectId>,
+    time: i64,
+}
+
+fn resolve_rev(repo: &Repository, rev: &str) -> Result<ObjectId> {
+    let spec = repo.rev_parse_single(rev).map_err(|e| {
+        CoreError::new(
+            ErrorCode::RefNotFound,
+            format!("could not resolve rev '{}': {}", rev, e),
+        )
+    })?;
+    let commit = spec.object()?.peel_to_kind(gix::object::Kind::Commit)?;
+    Ok(commit.id)
+}
+
+/// Ref decorations keyed by the commit they point at, in `git log --decorate` style:
+/// `HEAD -> main`, `origin/main`, `tag: v1.0`.
+pub(crate) fn collect_decorations(repo: &Repository) -> HashMap<ObjectId, Vec<String>> {
+    let mut out: HashMap<ObjectId, Vec<String>> = HashMap::new();
+    let head_branch = repo
+        .head_name()
+        .ok()
+        .flatten()
+        .map(|n| n.as_bstr().to_str_lossy().into_owned());
+    if let Ok(Some(id)) = repo.head_id().map(|id| Some(id.detach())) {
+        let label = match &head_branch {
+            Some(full) => format!(
+                "HEAD -> {}",
+                full.strip_prefix("refs/heads/").unwrap_or(full)
+            ),
+            None => "HEAD".to_string(),
+        };
+        out.entry(id).or_default().push(label);
+    }
+
+    let Ok(platform) = repo.references() else {
+        return out;
+    };
+    let Ok(iter) = platform.all() else {
+        return out;
+    };
+    for r in iter.flatten() {
+        let name = r.name().as_bstr().to_str_lossy().into_owned();
+        if Some(&name) == head_branch.as_ref() || name.ends_with("/HEAD") {
+            continue;
+        }
+        let label = if let Some(s) = name.strip_prefix("refs/heads/") {
+            s.to_string()
+        } else if let Some(s) = name.strip_prefix("refs/remotes/") {
+            s.to_string()
+        } else if let Some(s) = name.strip_prefix("refs/tags/") {
+            format!("tag: {}", s)
+        } else {
+            continue;
+        };
+        let mut r = r;
+        let Ok(id) = r.peel_to_id_in_place() else {
+            continue;
+        };
+        // Annotated tags peel to the tag object; decorate the commit it points to.
+        let Ok(commit) = id
+            .object()
+            .map_err(anyhow::Error::from)
+            .and_then(|o| Ok(o.peel_to_kind(gix::object::Kind::Commit)?))
+        else {
+            continue;
+        };
+        out.entry(commit.id).or_default().push(label);
+    }
+    out
+}
+
+/// Load every commit reachable from `tips` (minus `hidden`) into memory.
+fn collect_graph(
+    repo: &Repository,
+    tips: &[ObjectId],
+    hidden: &HashSet<ObjectId>,
+) -> Result<HashMap<ObjectId, Node>> {
+    let mut graph: HashMap<ObjectId, Node> = HashMap::new();
+    let mut stack: Vec<ObjectId> = tips.to_vec();
+    while let Some(id) = stack.pop() {
+        crate::cancel::check()?;
+        if graph.contains_key(&id) || hidden.contains(&id) {
+            continue;
+        }
+        let commit = repo.find_object(id)?.try_into_commit()?;
+        let time = commit.time().map(|t| t.seconds).unwrap_or(0);
+        let parents: Vec<ObjectId> = commit.parent_ids().map(|p| p.detach()).collect();
+        for p in &parents {
+            if !graph.contains_key(p) {
+                stack.push(*p);
+            }
+        }
+        graph.insert(id, Node { parents, time });
+    }
+    Ok(graph)
+}
+
+fn reachable(repo: &Repository, tips: &[ObjectId]) -> Result<HashSet<ObjectId>> {
+    let graph = collect_graph(repo, tips, &HashSet::new())?;
+    Ok(graph.into_keys().collect())
+}
+
+/// Commits reachable from the tips (minus `hidden`) in display order, read
+/// from the object database only as far as the output has got.
+///
+/// No parent is shown before all of its children. `topo` keeps lines of
+/// history together like `git log --topo-order`; otherwise ties are broken by
+/// commit time like `--date-order`. Commits are read newest first, and one is
+/// taken to have no unread children once everything at least as new has been
+/// read, so like git without a commit-graph this trusts commit times.
+struct Walk<'a> {
+    repo: &'a Repository,
+    hidden: &'a HashSet<ObjectId>,
+    topo: bool,
+    nodes: HashMap<ObjectId, Node>,
+    /// Read commits whose parents have not been counted yet, newest first.
+    unexpanded: BinaryHeap<(i64, ObjectId)>,
+    /// Children counted so far that have not been emitted.
+    pending_children: HashMap<ObjectId, usize>,
+    /// Commits with no children left to emit; a stack for `topo`.
+    ready_stack: Vec<ObjectId>,
+    ready_heap: BinaryHeap<(i64, ObjectId)>,
+    emitted: HashSet<ObjectId>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(
+        repo: &'a Repository,
+        tips: &[ObjectId],
+        hidden: &'a HashSet<ObjectId>,
+        topo: bool,
+    ) -> Result<Self> {
+        let mut walk = Walk {
+            repo,
+            hidden,
+            topo,
+            nodes: HashMap::new(),
+            unexpanded: BinaryHeap::new(),
+            pending_children: HashMap::new(),
+            ready_stack: Vec::new(),
+            ready_heap: BinaryHeap::new(),
+            emitted: HashSet::new(),
+        };
+        for id in tips {
+            if !hidden.contains(id) {
+                walk.read(*id)?;
+            }
+        }
+        let oldest_tip = walk.nodes.values().map(|n| n.time).min();
+        if let Some(time) = oldest_tip {
+            walk.expand_down_to(time)?;
+        }
+
+        // A tip that is an ancestor of another tip is not a root.
+        let mut roots: Vec<ObjectId> = walk
+            .nodes
+            .keys()
+            .copied()
+            .filter(|id| tips.contains(id) && !walk.pending_children.contains_key(id))
+            .collect();
+        roots.sort_by(|a, b| {
+            walk.nodes[b]
+                .time
+                .cmp(&walk.nodes[a].time)
+                .then_with(|| a.cmp(b))
+        });
+        for id in roots.into_iter().rev() {
+            walk.push_ready(id);
+        }
+        Ok(walk)
+    }
+
+    fn read(&mut self, id: ObjectId) -> Result<()> {
+        if self.nodes.contains_key(&id) {
+            return Ok(());
+        }
+        let commit = self.repo.find_object(id)?.try_into_commit()?;
+        let time = commit.time().map(|t| t.seconds).unwrap_or(0);
+        let parents: Vec<ObjectId> = commit.parent_ids().map(|p| p.detach()).collect();
+        self.nodes.insert(id, Node { parents, time });
+        self.unexpanded.push((time, id));
+        Ok(())
+    }
+
+    /// Count the parents of every read commit at least as new as `time`.
+    fn expand_down_to(&mut self, time: i64) -> Result<()> {
+        while self.unexpanded.peek().is_some_and(|(t, _)| *t >= time) {
+            crate::cancel::check()?;
+            let Some((_, id)) = self.unexpanded.pop() else {
+                break;
+            };
+            let parents = self.nodes[&id].parents.clone();
+            for p in parents {
+                if self.hidden.contains(&p) {
+                    continue;
+                }
+                *self.pending_children.entry(p).or_default() += 1;
+                self.read(p)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn push_ready(&mut self, id: ObjectId) {
+        if self.topo {
+            self.ready_stack.push(id);
+        } else {
+            self.ready_heap.push((self.nodes[&id].time, id));
+        }
+    }
+
+    fn pop_ready(&mut self) -> Option<ObjectId> {
+        if self.topo {
+            self.ready_stack.pop()
+        } else {
+            self.ready_heap.pop().map(|(_, id)| id)
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<ObjectId>> {
+        let id = loop {
+            match self.pop_ready() {
+                Some(id) if self.emitted.contains(&id) => continue,
+                Some(id) => break id,
+                None => return Ok(None),
+            }
+        };
+        self.emitted.insert(id);
+        // LIFO for `topo`, like git: after a merge the merged-in line is
+        // drained before continuing down the first parent, so branches are
+        // never interleaved.
+        let parents = self.nodes[&id].parents.clone();
+        for p in parents {
+            let Some(left) = self.pending_children.get_mut(&p) else {
+                continue;
+            };
+            *left -= 1;
+            if *left > 0 {
+                continue;
+            }
+            // Read everything that could still be a child of `p` first.
+            self.expand_down_to(self.nodes[&p].time)?;
+            if self.pending_children.get(&p) == Some(&0) && !self.emitted.contains(&p) {
+                self.push_ready(p);
+            }
+        }
+        Ok(Some(id))
+    }
+}
+
+fn entry_id_at(repo: &Repository, commit: ObjectId, path: &str) -> Option<ObjectId> {
+    let tree = repo
+        .find_object(commit)
+        .ok()?
+        .try_into_commit()
+        .ok()?
+        .tree()
+        .ok()?;
+    let mut buf = Vec::new();
+    let entry = tree.lookup_entry_by_path(path, &mut buf).ok()??;
+    Some(entry.object_id())
+}
+
+/// A commit touches `paths` if any of them differs from every parent, which
+/// mirrors git's default history simplification (a merge that takes one side
+/// unchanged is hidden). Directories compare by tree id.
+fn touches_paths(repo: &Repository, id: ObjectId, node: &Node, paths: &[String]) -> bool {
+    paths.iter().any(|p| {
+        let here = entry_id_at(repo, id, p);
+        if node.parents.is_empty() {
+            return here.is_some();
+        }
+        node.parents
+            .iter()
+            .all(|pid| entry_id_at(repo, *pid, p) != here)
+    })
+}
+
+pub fn git_log(opts: GitLogOptions) -> Result<GitLogResult> {
+    let repo_path = if let Some(p) = &opts.originPathOverride {
+        std::path::PathBuf::from(p)
+    } else {
+        let url = resolve_repo_url(opts.repoFullName.as_deref(), opts.repoUrl.as_deref())?;
+        ensure_repo(&url)?
+    };
+    let repo = crate::repo::handles::open(&repo_path)?;
+
+    let revs: Vec<String> = match &opts.refs {
+        Some(r) if !r.is_empty() => r.clone(),
+        _ => vec!["HEAD".to_string()],
+    };
+    let mut tips: Vec<ObjectId> = Vec::new();
+    let mut excluded: Vec<ObjectId> = Vec::new();
+    for rev in &revs {
+        let rev = rev.trim();
+        if let Some(neg) = rev.strip_prefix('^') {
+            excluded.push(resolve_rev(&repo, neg)?);
+        } else {
+            tips.push(resolve_rev(&repo, rev)?);
+        }
+    }
+    let hidden = if excluded.is_empty() {
+        HashSet::new()
+    } else {
+        reachable(&repo, &excluded)?
+    };
+
+    let topo = opts.order.as_deref() != Some("date");
+    let mut walk = Walk::new(&repo, &tips, &hidden, topo)?;
+
+    let paths: Vec<String> = opts
+        .paths
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| p.trim_matches('/').to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let skip = opts.skip.unwrap_or(0).max(0) as usize;
+    let max_count = opts
+        .maxCount
+        .filter(|n| *n > 0)
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_COUNT);
+
+    let decorations = collect_decorations(&repo);
+    let mut commits: Vec<CommitInfo> = Vec::new();
+    let mut matched = 0usize;
+    let mut has_more = false;
+    // Stop at the first match past the page; it only tells us there is more.
+    while let Some(id) = walk.next()? {
+        let node = &walk.nodes[&id];
+        if !paths.is_empty() && !touches_paths(&repo, id, node, &paths) {
+            continue;
+        }
+        matched += 1;
+        if matched <= skip {
+            continue;
+        }
+        if commits.len() == max_count {
+            has_more = true;
+            break;
+        }
+        let commit = repo.find_object(id)?.try_into_commit()?;
+        let author = commit.author()?;
+        let committer_time = commit.time().map(|t| t.seconds * 1000).ok();
+        let subject = commit
+            .message()
+            .map(|m| m.summary().to_str_lossy().into_owned())
+            .unwrap_or_default();
+        commits.push(CommitInfo {
+            sha: id.to_string(),
+            parents: node.parents.iter().map(|p| p.to_string()).collect(),
+            authorName: author.name.to_str_lossy().into_owned(),
+            authorEmail: author.email.to_str_lossy().into_owned(),
+            authorTime: author.time.seconds * 1000,
+            committerTime: committer_time,
+            subject,
+            refs: decorations.get(&id).cloned().unwrap_or_default(),
+        });
+    }
+
+    Ok(GitLogResult {
+        commits,
+        hasMore: has_more,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::run_git;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn commit(dir: &str, msg: &str, date: &str) {
+        let out = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["commit", "-q", "-a", "-m", msg])
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .status()
+            .expect("spawn git");
+        assert!(out.success(), "commit {msg} failed");
+    }
+
+    #[test]
+    fn log_orders_topologically_filters_paths_and_pages() {
+        let tmp = tempdir().expect("tempdir");
+        let dir = tmp.path().to_str().unwrap().to_string();
+        run_git(&dir, &["init"]).unwrap();
+        run_git(&dir, &["config", "user.name", "Test"]).unwrap();
+        run_git(&dir, &["config", "user.email", "test@example.com"]).unwrap();
+        run_git(&dir, &["checkout", "-b", "main"]).unwrap();
+
+        fs::write(tmp.path().join("a.txt"), "1").unwrap();
+        run_git(&dir, &["add", "."]).unwrap();
+        commit(&dir, "root", "2024-01-01T00:00:00Z");
+
+        // Side branch commits interleave in time with main
+        run_git(&dir, &["checkout", "-b", "side"]).unwrap();
+        fs::create_dir_all(tmp.path().join("docs")).unwrap();
+        fs::write(tmp.path().join("docs/x.md"), "x").unwrap();
+        run_git(&dir, &["add", "."]).unwrap();
+        commit(&dir, "side1", "2024-01-02T00:00:00Z");
+        fs::write(tmp.path().join("docs/x.md"), "xx").unwrap();
+        commit(&dir, "side2\n\nbody", "2024-01-04T00:00:00Z");
+
+        run_git(&dir, &["checkout", "main"]).unwrap();
+        fs::write(tmp.path().join("a.txt"), "2").unwrap();
+        commit(&dir, "main1", "2024-01-03T00:00:00Z");
+        fs::write(tmp.path().join("a.txt"), "3").unwrap();
+        commit(&dir, "main2", "2024-01-05T00:00:00Z");
+        let out = std::process::Command::new("git")
+            .current_dir(&dir)
+            .args(["merge", "--no-ff", "-q", "-m", "merge", "side"])
+            .env("GIT_AUTHOR_DATE", "2024-01-06T00:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2024-01-06T00:00:00Z")
+            .status()
+            .unwrap();
+        assert!(out.success());
+        run_git(&dir, &["tag", "v1"]).unwrap();
+
+        let base = GitLogOptions {
+            originPathOverride: Some(dir.clone()),
+            ..Default::default()
+        };
+        let subjects = |res: &GitLogResult| -> Vec<String> {
+            res.commits.iter().map(|c| c.subject.clone()).collect()
+        };
+
+        let topo = git_log(base.clone()).unwrap();
+        assert_eq!(
+            subjects(&topo),
+            vec!["merge", "side2", "side1", "main2", "main1", "root"]
+        );
+        let expected = run_git(&dir, &["log", "--topo-order", "--format=%s"]).unwrap();
+        assert_eq!(
+            subjects(&topo),
+            expected.lines().map(str::to_string).collect::<Vec<_>>()
+        );
+        assert_eq!(topo.commits[0].parents.len(), 2);
+        assert_eq!(topo.commits[0].refs, vec!["HEAD -> main", "tag: v1"]);
+        assert_eq!(topo.commits[1].refs, vec!["side"]);
+        assert_eq!(topo.commits[1].authorEmail, "test@example.com");
+
+        let date = git_log(GitLogOptions {
+            order: Some("date".into()),
+            ..base.clone()
+        })
+        .unwrap();
+        assert_eq!(
+            subjects(&date),
+            vec!["merge", "main2", "side2", "main1", "side1", "root"]
+        );
+
+        let docs = git_log(GitLogOptions {
+            paths: Some(vec!["docs".into()]),
+            ..base.clone()
+        })
+        .unwrap();
+        assert_eq!(subjects(&docs), vec!["side2", "side1"]);
+
+        let page = git_log(GitLogOptions {
+            skip: Some(2),
+            maxCount: Some(2),
+            ..base.clone()
+        })
+        .unwrap();
+        assert_eq!(subjects(&page), vec!["side1", "main2"]);
+        assert!(page.hasMore);
+
+        let range = git_log(GitLogOptions {
+            refs: Some(vec!["main".into(), "^side".into()]),
+            ..base
+        })
+        .unwrap();
+        assert_eq!(subjects(&range), vec!["merge", "main2", "main1"]);
+    }
+
+    #[test]
+    fn log_reads_only_as_far_as_the_page() {
+        let tmp = tempdir().expect("tempdir");
+        let dir = tmp.path().to_str().unwrap().to_string();
+        run_git(&dir, &["init"]).unwrap();
+        run_git(&dir, &["config", "user.name", "Test"]).unwrap();
+        run_git(&dir, &["config", "user.email", "test@example.com"]).unwrap();
+        for i in 0..30 {
+            fs::write(tmp.path().join("a.txt"), i.to_string()).unwrap();
+            run_git(&dir, &["add", "."]).unwrap();
+            commit(&dir, &format!("c{i}"), &format!("2024-01-01T00:{i:02}:00Z"));
+        }
+
+        let repo = gix::open(&dir).unwrap();
+        let head = resolve_rev(&repo, "HEAD").unwrap();
+        let hidden = HashSet::new();
+        for topo in [true, false] {
+            let mut walk = Walk::new(&repo, &[head], &hidden, topo).unwrap();
+            for _ in 0..3 {
+                walk.next().unwrap().expect("commit");
+            }
+            assert!(walk.nodes.len() <= 5, "read {} commits", walk.nodes.len());
+        }
+
+        let page = git_log(GitLogOptions {
+            originPathOverride: Some(dir.clone()),
+            skip: Some(27),
+            maxCount: Some(5),
+            ..Default::default()
+        })
+        .unwrap();
+        let subjects: Vec<&str> = page.commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["c2", "c1", "c0"]);
+        assert!(!page.hasMore);
+    }
+}
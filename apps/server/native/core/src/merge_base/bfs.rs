@@ -65,6 +65,10 @@ pub fn merge_base_bfs(repo: &Repository, a: ObjectId, b: ObjectId) -> Option<Obj
 
     // Alternate expanding the smaller frontier for performance.
     loop {
+        // Give up early on cancellation; callers check the token themselves.
+        if crate::cancel::is_cancelled() {
+            break;
+        }
         let next_from_a = qa.len() <= qb.len();
         let progressed = expand(
             next_from_a,
@@ -22,6 +22,7 @@ pub fn merge_base(
 
 pub mod bfs;
 pub mod git;
+pub mod preview;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Result};
+use gix::{hash::ObjectId, Repository};
+use similar::{DiffTag, TextDiff};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::diff::refs::{collect_tree_blobs, is_binary, oid_from_rev_parse};
+use crate::repo::cache::{ensure_repo, resolve_repo_url};
+use crate::types::{GitMergePreview, GitMergePreviewOptions, MergeConflictFile};
+
+/// A contiguous change against the merge base: base lines `start..end` are
+/// replaced by `lines`.
+#[derive(Debug, PartialEq)]
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+fn hunks<'a>(base: &'a str, side: &'a str) -> Vec<Hunk<'a>> {
+    let diff = TextDiff::from_lines(base, side);
+    let new_lines = diff.new_slices();
+    let mut out: Vec<Hunk<'a>> = Vec::new();
+    for op in diff.ops() {
+        if op.tag() == DiffTag::Equal {
+            continue;
+        }
+        let old = op.old_range();
+        let new = op.new_range();
+        match out.last_mut() {
+            // Delete+Insert pairs come out as separate ops; fold them together.
+            Some(prev) if prev.end == old.start => {
+                prev.end = old.end;
+                prev.lines.extend_from_slice(&new_lines[new]);
+            }
+            _ => out.push(Hunk {
+                start: old.start,
+                end: old.end,
+                lines: new_lines[new].to_vec(),
+            }),
+        }
+    }
+    out
+}
+
+/// Count the regions where both sides changed overlapping or adjacent base
+/// lines differently. Adjacent edits conflict too, matching git's merge.
+fn count_conflict_hunks(base: &str, ours: &str, theirs: &str) -> usize {
+    let ours = hunks(base, ours);
+    let theirs = hunks(base, theirs);
+    let mut all: Vec<(usize, usize, bool)> = ours
+        .iter()
+        .map(|h| (h.start, h.end, true))
+        .chain(theirs.iter().map(|h| (h.start, h.end, false)))
+        .collect();
+    all.sort();
+
+    let mut conflicts = 0;
+    let mut i = 0;
+    while i < all.len() {
+        let (start, mut end, _) = all[i];
+        let mut j = i;
+        while j < all.len() && all[j].0 <= end {
+            end = end.max(all[j].1);
+            j += 1;
+        }
+        let has_ours = all[i..j].iter().any(|h| h.2);
+        let has_theirs = all[i..j].iter().any(|h| !h.2);
+        if has_ours && has_theirs {
+            let in_cluster = |h: &&Hunk| h.start >= start && h.start <= end;
+            let a: Vec<&Hunk> = ours.iter().filter(in_cluster).collect();
+            let b: Vec<&Hunk> = theirs.iter().filter(in_cluster).collect();
+            if a != b {
+                conflicts += 1;
+            }
+        }
+        i = j;
+    }
+    conflicts
+}
+
+fn blob(repo: &Repository, id: ObjectId) -> Option<Vec<u8>> {
+    let obj = repo.find_object(id).ok()?;
+    let blob = obj.try_into_blob().ok()?;
+    Some(blob.data.to_vec())
+}
+
+fn tree_of(repo: &Repository, commit: ObjectId) -> Result<HashMap<String, ObjectId>> {
+    let tree_id = repo
+        .find_object(commit)?
+        .try_into_commit()?
+        .tree_id()?
+        .detach();
+    let mut out = HashMap::new();
+    collect_tree_blobs(repo, tree_id, "", &mut out)?;
+    Ok(out)
+}
+
+/// Preview merging `headRef` into `baseRef` without touching any working tree
+/// or index: a three-way comparison of both tips against their merge base.
+pub fn merge_preview(opts: GitMergePreviewOptions) -> Result<GitMergePreview> {
+    let repo_path = if let Some(p) = &opts.originPathOverride {
+        std::path::PathBuf::from(p)
+    } else {
+        let url = resolve_repo_url(opts.repoFullName.as_deref(), opts.repoUrl.as_deref())?;
+        ensure_repo(&url)?
+    };
+    let cwd = repo_path.to_string_lossy().to_string();
+    let repo = crate::repo::handles::open(&repo_path)?;
+
+    let ours = oid_from_rev_parse(&repo, opts.baseRef.trim())?;
+    let theirs = oid_from_rev_parse(&repo, opts.headRef.trim())?;
+    let base = super::merge_base(&cwd, &repo, ours, theirs, super::MergeBaseStrategy::Bfs)
+        .ok_or_else(|| {
+            anyhow!(
+                "no merge base between '{}' and '{}'",
+                opts.baseRef,
+                opts.headRef
+            )
+        })?;
+
+    let mut preview = GitMergePreview {
+        mergeBaseSha: base.to_string(),
+        baseSha: ours.to_string(),
+        headSha: theirs.to_string(),
+        alreadyMerged: base == theirs,
+        fastForward: base == ours && base != theirs,
+        conflicts: Vec::new(),
+    };
+    if preview.alreadyMerged || preview.fastForward {
+        return Ok(preview);
+    }
+
+    let base_map = tree_of(&repo, base)?;
+    let ours_map = tree_of(&repo, ours)?;
+    let theirs_map = tree_of(&repo, theirs)?;
+    let paths: BTreeSet<&String> = base_map
+        .keys()
+        .chain(ours_map.keys())
+        .chain(theirs_map.keys())
+        .collect();
+
+    for path in paths {
+        let b = base_map.get(path);
+        let o = ours_map.get(path);
+        let t = theirs_map.get(path);
+        // One side unchanged, or both made the same change: resolves cleanly.
+        if o == t || o == b || t == b {
+            continue;
+        }
+        let (kind, count) = match (b, o, t) {
+            (None, Some(_), Some(_)) => ("add_add", 1),
+            (Some(_), None, Some(_)) | (Some(_), Some(_), None) => ("modify_delete", 1),
+            (Some(b), Some(o), Some(t)) => {
+                match (blob(&repo, *b), blob(&repo, *o), blob(&repo, *t)) {
+                    (Some(bb), Some(ob), Some(tb))
+                        if !is_binary(&bb) && !is_binary(&ob) && !is_binary(&tb) =>
+                    {
+                        let n = count_conflict_hunks(
+                            &String::from_utf8_lossy(&bb),
+                            &String::from_utf8_lossy(&ob),
+                            &String::from_utf8_lossy(&tb),
+                        );
+                        if n == 0 {
+                            continue;
+                        }
+                        ("content", n)
+                    }
+                    _ => ("binary", 1),
+                }
+            }
+            _ => continue,
+        };
+        preview.conflicts.push(MergeConflictFile {
+            filePath: path.clone(),
+            kind: kind.into(),
+            conflictCount: count as i32,
+        });
+    }
+
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::count_conflict_hunks;
+
+    #[test]
+    fn disjoint_edits_merge_cleanly() {
+        let base = "a\nb\nc\nd\ne\n";
+        assert_eq!(
+            count_conflict_hunks(base, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\n"),
+            0
+        );
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_edits_conflict() {
+        let base = "a\nb\nc\nd\ne\nf\ng\n";
+        // Same line changed differently, plus adjacent lines further down.
+        let ours = "A1\nb\nc\nd\nE1\nf\ng\n";
+        let theirs = "A2\nb\nc\nd\ne\nF2\ng\n";
+        assert_eq!(count_conflict_hunks(base, ours, theirs), 2);
+    }
+
+    #[test]
+    fn identical_changes_do_not_conflict() {
+        let base = "a\nb\n";
+        assert_eq!(count_conflict_hunks(base, "a\nX\n", "a\nX\n"), 0);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Context, Result};
+use cmux_proxy::access_log::{AccessLog, AccessLogEvent};
+use cmux_proxy::cors::CorsRule;
+use cmux_proxy::ports::parse_port_range;
+use cmux_proxy::throttle::BandwidthConfig;
+use cmux_proxy::ProxyConfig;
+use std::net::SocketAddr;
+use std::sync::{Mutex, MutexGuard};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::error::{CoreError, ErrorCode};
+use crate::types::{WorkspaceProxyAccessLogEvent, WorkspaceProxyInfo, WorkspaceProxyOptions};
+
+struct RunningProxy {
+    shutdown: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// The in-process workspace proxy; the app runs at most one.
+static PROXY: Mutex<Option<RunningProxy>> = Mutex::new(None);
+
+fn running_proxy() -> Result<MutexGuard<'static, Option<RunningProxy>>> {
+    PROXY.lock().map_err(|_| {
+        CoreError::new(ErrorCode::Internal, "workspace proxy state is poisoned").into()
+    })
+}
+
+fn proxy_config(
+    listen: &str,
+    upstream: &str,
+    opts: WorkspaceProxyOptions,
+    on_access_log: Option<AccessLog>,
+) -> Result<ProxyConfig> {
+    let invalid = |message: String| CoreError::new(ErrorCode::InvalidArgument, message);
+    let listen: SocketAddr = listen
+        .parse()
+        .map_err(|e| invalid(format!("invalid listen address '{listen}': {e}")))?;
+    let cors = opts
+        .cors
+        .unwrap_or_default()
+        .iter()
+        .map(|rule| {
+            rule.parse::<CorsRule>()
+                .map_err(|e| invalid(format!("invalid CORS rule '{rule}': {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut cfg = ProxyConfig {
+        listen,
+        upstream_host: upstream.to_string(),
+        allow_default_upstream: opts.allowDefaultUpstream.unwrap_or(true),
+        bandwidth: BandwidthConfig {
+            per_connection: opts.maxConnectionBandwidth.map(u64::from),
+            per_workspace: opts.maxWorkspaceBandwidth.map(u64::from),
+        },
+        cors,
+        access_log: on_access_log,
+        ..ProxyConfig::default()
+    };
+    if let Some(range) = opts.portScanRange {
+        cfg.port_scan_range = parse_port_range(&range).map_err(invalid)?;
+    }
+    Ok(cfg)
+}
+
+fn to_js_event(event: AccessLogEvent) -> WorkspaceProxyAccessLogEvent {
+    WorkspaceProxyAccessLogEvent {
+        client: event.client.to_string(),
+        method: event.method,
+        path: event.path,
+        workspace: event.workspace,
+        port: event.port.map(u32::from),
+        status: u32::from(event.status),
+        durationMs: event.duration.as_secs_f64() * 1000.0,
+    }
+}
+
+/// Start the proxy on `listen` (port 0 picks a free port). Must be called
+/// inside a tokio runtime.
+pub fn start_workspace_proxy(
+    listen: &str,
+    upstream: &str,
+    opts: WorkspaceProxyOptions,
+    on_access_log: Option<impl Fn(WorkspaceProxyAccessLogEvent) + Send + Sync + 'static>,
+) -> Result<WorkspaceProxyInfo> {
+    let mut running = running_proxy()?;
+    if running.is_some() {
+        return Err(CoreError::new(
+            ErrorCode::ProxyAlreadyRunning,
+            "workspace proxy is already running",
+        )
+        .into());
+    }
+    let access_log = on_access_log.map(|cb| AccessLog::new(move |event| cb(to_js_event(event))));
+    let cfg = proxy_config(listen, upstream, opts, access_log)?;
+
+    let (shutdown, rx) = oneshot::channel::<()>();
+    let (addr, handle) = cmux_proxy::try_spawn_proxy(
+        cfg,
+        Box::pin(async move {
+            let _ = rx.await;
+        }),
+    )
+    .with_context(|| format!("failed to start workspace proxy on {listen}"))?;
+    *running = Some(RunningProxy { shutdown, handle });
+    Ok(WorkspaceProxyInfo {
+        port: u32::from(addr.port()),
+    })
+}
+
+/// Stop accepting connections and wait for the listener to close. Tunnels
+/// already open finish on their own.
+pub async fn stop_workspace_proxy() -> Result<()> {
+    let running = running_proxy()?.take().ok_or_else(|| {
+        CoreError::new(ErrorCode::ProxyNotStarted, "workspace proxy is not running")
+    })?;
+    let _ = running.shutdown.send(());
+    running
+        .handle
+        .await
+        .map_err(|e| anyhow!("workspace proxy task failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::code_of;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn serve_one_response(listener: TcpListener) {
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn starts_forwards_access_logs_and_stops() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap();
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        serve_one_response(upstream);
+
+        let (tx, rx) = mpsc::channel();
+        let info = rt
+            .block_on(async {
+                start_workspace_proxy(
+                    "127.0.0.1:0",
+                    "127.0.0.1",
+                    WorkspaceProxyOptions::default(),
+                    Some(move |event| {
+                        let _ = tx.send(event);
+                    }),
+                )
+            })
+            .expect("start");
+        assert_ne!(info.port, 0);
+        let again = rt.block_on(async {
+            start_workspace_proxy(
+                "127.0.0.1:0",
+                "127.0.0.1",
+                WorkspaceProxyOptions::default(),
+                None::<fn(WorkspaceProxyAccessLogEvent)>,
+            )
+        });
+        assert_eq!(code_of(&again.unwrap_err()), ErrorCode::ProxyAlreadyRunning);
+
+        let mut client = TcpStream::connect(("127.0.0.1", info.port as u16)).unwrap();
+        write!(
+            client,
+            "GET /hello HTTP/1.1\r\nHost: localhost\r\nX-Cmux-Port-Internal: {upstream_port}\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+        let event = rx.recv_timeout(Duration::from_secs(5)).expect("access log");
+        assert_eq!(event.path, "/hello");
+        assert_eq!(event.status, 200);
+        assert_eq!(event.port, Some(u32::from(upstream_port)));
+
+        rt.block_on(stop_workspace_proxy()).expect("stop");
+        let stopped = rt.block_on(stop_workspace_proxy()).unwrap_err();
+        assert_eq!(code_of(&stopped), ErrorCode::ProxyNotStarted);
+        assert!(TcpStream::connect(("127.0.0.1", info.port as u16)).is_err());
+    }
+}
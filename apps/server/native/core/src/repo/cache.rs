@@ -1,15 +1,20 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use dirs_next::cache_dir;
 use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
 
-use crate::util::run_git;
+use super::handles;
+use crate::error::{CoreError, ErrorCode};
+use crate::util::{run_git, run_git_with_timeout};
 
 const MAX_CACHE_REPOS: usize = 20;
+/// A clone still running after this is assumed stuck (e.g. on a dead network).
+const CLONE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 // Default SWR window for git fetches. Lower means fetch more often.
 pub const DEFAULT_FETCH_WINDOW_MS: u128 = 5_000; // 5s
@@ -65,11 +70,12 @@ pub fn ensure_repo(url: &str) -> Result<PathBuf> {
     let git_dir = path.join(".git");
     let head = git_dir.join("HEAD");
     if path.exists() && (!git_dir.exists() || !head.exists()) {
+        handles::invalidate(&path);
         let _ = fs::remove_dir_all(&path);
     }
     if !path.exists() {
         fs::create_dir_all(&path)?;
-        run_git(
+        run_git_with_timeout(
             root.to_string_lossy().as_ref(),
             &[
                 "clone",
@@ -77,6 +83,7 @@ pub fn ensure_repo(url: &str) -> Result<PathBuf> {
                 url,
                 path.file_name().unwrap().to_str().unwrap(),
             ],
+            CLONE_TIMEOUT,
         )?;
         let _ = update_cache_index_with(&root, &path, Some(now_ms()));
     } else {
@@ -102,7 +109,11 @@ pub fn resolve_repo_url(repo_full_name: Option<&str>, repo_url: Option<&str>) ->
     if let Some(full) = repo_full_name {
         return Ok(format!("https://github.com/{}.git", full));
     }
-    Err(anyhow!("repoUrl or repoFullName required"))
+    Err(CoreError::new(
+        ErrorCode::InvalidArgument,
+        "repoUrl or repoFullName required",
+    )
+    .into())
 }
 
 fn load_index(root: &Path) -> CacheIndex {
@@ -234,6 +245,7 @@ pub fn swr_fetch_origin_all_path_bool(path: &std::path::Path, window_ms: u128) -
             let root_bg = root.clone();
             std::thread::spawn(move || {
                 let _ = run_git(&cwd_bg, &["fetch", "--all", "--tags", "--prune"]);
+                handles::invalidate(&cwd_bg);
                 let _ = update_cache_index_with(&root_bg, &PathBuf::from(&cwd_bg), Some(now_ms()));
                 set_map_last_fetch(&PathBuf::from(&cwd_bg), now_ms());
             });
@@ -242,6 +254,7 @@ pub fn swr_fetch_origin_all_path_bool(path: &std::path::Path, window_ms: u128) -
     }
 
     let _ = run_git(&cwd, &["fetch", "--all", "--tags", "--prune"]);
+    handles::invalidate(&cwd);
     let now2 = now_ms();
     let _ = update_cache_index_with(&root, &PathBuf::from(&cwd), Some(now2));
     set_map_last_fetch(&PathBuf::from(&cwd), now2);
@@ -270,6 +283,7 @@ fn enforce_cache_limit(root: &Path) -> Result<()> {
     let victims = idx.entries[MAX_CACHE_REPOS..].to_vec();
     for v in &victims {
         let p = PathBuf::from(&v.path);
+        handles::invalidate(&p);
         let _ = fs::remove_dir_all(&p);
     }
     idx.entries = survivors;
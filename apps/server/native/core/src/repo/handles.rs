@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Repositories kept open between calls. The diff panel fires bursts of
+/// diff/log/branch calls at the same few repos; reusing the handle skips
+/// config discovery and keeps the object database's pack indices loaded.
+const MAX_OPEN_REPOS: usize = 16;
+
+static HANDLES: Mutex<HandleCache> = Mutex::new(HandleCache::new(MAX_OPEN_REPOS));
+
+/// LRU of open repositories keyed by canonical path, most recent first.
+struct HandleCache {
+    capacity: usize,
+    entries: VecDeque<(PathBuf, gix::ThreadSafeRepository)>,
+}
+
+impl HandleCache {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, key: &Path) -> Option<gix::Repository> {
+        let i = self.entries.iter().position(|(path, _)| path == key)?;
+        let entry = self.entries.remove(i)?;
+        let repo = entry.1.to_thread_local();
+        self.entries.push_front(entry);
+        Some(repo)
+    }
+
+    fn insert(&mut self, key: PathBuf, repo: gix::ThreadSafeRepository) {
+        self.entries.retain(|(path, _)| path != &key);
+        self.entries.push_front((key, repo));
+        self.entries.truncate(self.capacity);
+    }
+
+    /// Drop `key` and any repository below it.
+    fn invalidate(&mut self, key: &Path) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|(path, _)| !path.starts_with(key));
+        self.entries.len() != before
+    }
+}
+
+/// The handle cache. A panic while it was locked may have cut an update
+/// short, so a poisoned cache is emptied rather than trusted.
+fn handles() -> MutexGuard<'static, HandleCache> {
+    HANDLES.lock().unwrap_or_else(|poisoned| {
+        HANDLES.clear_poison();
+        let mut cache = poisoned.into_inner();
+        cache.entries.clear();
+        cache
+    })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Open the repository at `path`, reusing a cached handle when there is one.
+pub fn open(path: impl AsRef<Path>) -> Result<gix::Repository> {
+    let key = canonical(path.as_ref());
+    if let Some(repo) = handles().get(&key) {
+        return Ok(repo);
+    }
+    let repo = gix::ThreadSafeRepository::open(&key)?;
+    let local = repo.to_thread_local();
+    handles().insert(key, repo);
+    Ok(local)
+}
+
+/// Forget cached handles for `path` (and repositories inside it) so the next
+/// [`open`] rereads config and refs from disk. Returns whether any were cached.
+pub fn invalidate(path: impl AsRef<Path>) -> bool {
+    handles().invalidate(&canonical(path.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::run_git;
+    use tempfile::tempdir;
+
+    fn init_repo(dir: &Path) -> gix::ThreadSafeRepository {
+        std::fs::create_dir_all(dir).unwrap();
+        run_git(dir.to_str().unwrap(), &["init", "--quiet"]).unwrap();
+        gix::ThreadSafeRepository::open(dir).unwrap()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_and_invalidates_by_prefix() {
+        let tmp = tempdir().unwrap();
+        let root = canonical(tmp.path());
+        let (a, b, c) = (root.join("a"), root.join("b"), root.join("c"));
+        let mut cache = HandleCache::new(2);
+        cache.insert(a.clone(), init_repo(&a));
+        cache.insert(b.clone(), init_repo(&b));
+        assert!(cache.get(&a).is_some());
+        cache.insert(c.clone(), init_repo(&c));
+        assert!(cache.get(&b).is_none(), "b was least recently used");
+        assert!(cache.get(&a).is_some());
+
+        assert!(cache.invalidate(&root));
+        assert!(cache.get(&a).is_none() && cache.get(&c).is_none());
+        assert!(!cache.invalidate(&root));
+    }
+
+    #[test]
+    fn open_shares_handles_across_path_spellings() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().join("repo");
+        init_repo(&dir);
+        let repo = open(&dir).unwrap();
+        let again = open(dir.join(".")).unwrap();
+        assert_eq!(repo.git_dir(), again.git_dir());
+
+        assert!(invalidate(&dir));
+        assert!(!invalidate(dir.join(".")));
+        assert!(open(tmp.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Single files and commits, so diff viewers can load contents on demand
+//! instead of receiving every file body in the `git_diff` payload.
+
+use anyhow::Result;
+use gix::bstr::ByteSlice;
+use gix::hash::ObjectId;
+use gix::Repository;
+use similar::TextDiff;
+
+use crate::diff::refs::{diff_tree_ids, is_binary, oid_from_rev_parse};
+use crate::error::{CoreError, ErrorCode};
+use crate::repo::cache::{ensure_repo, resolve_repo_url};
+use crate::types::{
+    CommitInfo, DiffEntry, GitFileAtRefOptions, GitFileContent, GitShowOptions, GitShowResult,
+};
+
+const DEFAULT_MAX_BYTES: usize = 950 * 1024;
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+fn open_repo(
+    origin_path_override: Option<&str>,
+    repo_full_name: Option<&str>,
+    repo_url: Option<&str>,
+) -> Result<Repository> {
+    let repo_path = if let Some(p) = origin_path_override {
+        std::path::PathBuf::from(p)
+    } else {
+        let url = resolve_repo_url(repo_full_name, repo_url)?;
+        ensure_repo(&url)?
+    };
+    crate::repo::handles::open(&repo_path)
+}
+
+/// Longest prefix of `text` that is at most `max` bytes.
+fn prefix(text: &str, max: usize) -> &str {
+    let mut end = max.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+pub fn file_at_ref(opts: GitFileAtRefOptions) -> Result<GitFileContent> {
+    let rev = opts.gitRef.trim();
+    if rev.is_empty() {
+        return Err(CoreError::new(ErrorCode::InvalidArgument, "ref is required").into());
+    }
+    let repo = open_repo(
+        opts.originPathOverride.as_deref(),
+        opts.repoFullName.as_deref(),
+        opts.repoUrl.as_deref(),
+    )?;
+    let oid = oid_from_rev_parse(&repo, rev)?;
+    let object = repo.find_object(oid).map_err(|e| {
+        CoreError::new(
+            ErrorCode::RefNotFound,
+            format!("could not find object '{}': {}", rev, e),
+        )
+    })?;
+
+    let blob = if object.kind == gix::object::Kind::Blob {
+        object.detach()
+    } else {
+        let path = opts.path.trim_matches('/');
+        let commit = object
+            .peel_to_kind(gix::object::Kind::Commit)?
+            .into_commit();
+        let tree = commit.tree()?;
+        let entry = tree
+            .lookup_entry_by_path(path, &mut Vec::new())?
+            .ok_or_else(|| {
+                CoreError::new(
+                    ErrorCode::PathNotFound,
+                    format!("'{}' does not exist at '{}'", path, rev),
+                )
+            })?;
+        if !entry.mode().is_blob() {
+            return Err(CoreError::new(
+                ErrorCode::InvalidArgument,
+                format!("'{}' at '{}' is not a file", path, rev),
+            )
+            .into());
+        }
+        entry.object()?.detach()
+    };
+
+    let data = &blob.data;
+    let binary = is_binary(data);
+    let max = opts
+        .maxBytes
+        .filter(|n| *n >= 0)
+        .map_or(data.len(), |n| n as usize);
+    // Text is valid UTF-8; anything else counts as binary.
+    let content = std::str::from_utf8(data)
+        .ok()
+        .filter(|_| !binary)
+        .map(|text| prefix(text, max).to_string());
+    Ok(GitFileContent {
+        path: opts.path,
+        sha: blob.id.to_string(),
+        size: data.len() as i32,
+        isBinary: binary,
+        truncated: content.as_ref().is_some_and(|c| c.len() < data.len()),
+        content,
+    })
+}
+
+/// Unified diff of an entry's contents in `git diff` form, or `None` when it
+/// has no text changes to show.
+fn unified_patch(entry: &DiffEntry, context: usize) -> Option<String> {
+    let (old, new) = (entry.oldContent.as_deref()?, entry.newContent.as_deref()?);
+    if old == new {
+        return None;
+    }
+    let old_path = entry.oldPath.as_deref().unwrap_or(&entry.filePath);
+    let old_header = match entry.status.as_str() {
+        "added" => "/dev/null".to_string(),
+        _ => format!("a/{}", old_path),
+    };
+    let new_header = match entry.status.as_str() {
+        "deleted" => "/dev/null".to_string(),
+        _ => format!("b/{}", entry.filePath),
+    };
+    let diff = TextDiff::from_lines(old, new);
+    let patch = diff
+        .unified_diff()
+        .context_radius(context)
+        .header(&old_header, &new_header)
+        .to_string();
+    Some(patch)
+}
+
+pub fn show(opts: GitShowOptions) -> Result<GitShowResult> {
+    let rev = opts.hash.trim();
+    if rev.is_empty() {
+        return Err(CoreError::new(ErrorCode::InvalidArgument, "hash is required").into());
+    }
+    let repo = open_repo(
+        opts.originPathOverride.as_deref(),
+        opts.repoFullName.as_deref(),
+        opts.repoUrl.as_deref(),
+    )?;
+    let oid = oid_from_rev_parse(&repo, rev)?;
+    let commit = repo
+        .find_object(oid)
+        .map_err(|e| {
+            CoreError::new(
+                ErrorCode::RefNotFound,
+                format!("could not find commit '{}': {}", rev, e),
+            )
+        })?
+        .peel_to_kind(gix::object::Kind::Commit)?
+        .into_commit();
+    let id = commit.id;
+    let parents: Vec<ObjectId> = commit.parent_ids().map(|p| p.detach()).collect();
+    let base_tree = match parents.first() {
+        Some(parent) => repo
+            .find_object(*parent)?
+            .try_into_commit()?
+            .tree_id()?
+            .detach(),
+        None => ObjectId::empty_tree(repo.object_hash()),
+    };
+    let head_tree = commit.tree_id()?.detach();
+
+    let max_bytes = opts
+        .maxBytes
+        .filter(|n| *n > 0)
+        .map_or(DEFAULT_MAX_BYTES, |n| n as usize);
+    let context = opts
+        .contextLines
+        .filter(|n| *n >= 0)
+        .map_or(DEFAULT_CONTEXT_LINES, |n| n as usize);
+    let include_contents = opts.includeContents.unwrap_or(false);
+    let mut files = diff_tree_ids(&repo, base_tree, head_tree, max_bytes)?;
+    for entry in &mut files {
+        crate::cancel::check()?;
+        if let Some(patch) = unified_patch(entry, context) {
+            entry.patchSize = Some(patch.len() as i32);
+            entry.patch = Some(patch);
+        }
+        if !include_contents {
+            entry.oldContent = None;
+            entry.newContent = None;
+        }
+    }
+
+    let author = commit.author()?;
+    let message = commit.message_raw_sloppy().to_str_lossy().into_owned();
+    let subject = commit
+        .message()
+        .map(|m| m.summary().to_str_lossy().into_owned())
+        .unwrap_or_default();
+    let info = CommitInfo {
+        sha: id.to_string(),
+        parents: parents.iter().map(|p| p.to_string()).collect(),
+        authorName: author.name.to_str_lossy().into_owned(),
+        authorEmail: author.email.to_str_lossy().into_owned(),
+        authorTime: author.time.seconds * 1000,
+        committerTime: commit.time().map(|t| t.seconds * 1000).ok(),
+        subject,
+        refs: crate::log::collect_decorations(&repo)
+            .remove(&id)
+            .unwrap_or_default(),
+    };
+    Ok(GitShowResult {
+        commit: info,
+        message,
+        files,
+    })
+}
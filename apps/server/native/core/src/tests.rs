@@ -1,7 +1,10 @@
 use crate::{
     diff::refs,
     repo::cache::{ensure_repo, resolve_repo_url},
-    types::{GitDiffOptions, GitDiffWorkspaceOptions},
+    types::{
+        GitDiffOptions, GitDiffPageRequest, GitDiffWorkingTreeOptions, GitDiffWorkspaceOptions,
+        GitStatusOptions,
+    },
     util::run_git,
 };
 #[cfg_attr(not(feature = "fuzz-tests"), allow(unused_imports))]
@@ -225,6 +228,9 @@ fn compute_diff_for_pr(pr: &PullRequestRecord) -> CachedDiff {
         maxBytes: Some(LARGE_MAX_BYTES),
         lastKnownBaseSha: None,
         lastKnownMergeCommitSha: None,
+        recurseSubmodules: None,
+        truncateWindowBytes: None,
+        maxTruncatedSourceBytes: None,
     })
     .unwrap_or_else(|err| panic!("diff_refs failed for {}#{}: {err}", pr.repo, pr.number));
 
@@ -610,12 +616,56 @@ fn refs_diff_basic_on_local_repo() {
         maxBytes: Some(1024 * 1024),
         lastKnownBaseSha: None,
         lastKnownMergeCommitSha: None,
+        recurseSubmodules: None,
+        truncateWindowBytes: None,
+        maxTruncatedSourceBytes: None,
     })
     .unwrap();
 
     assert!(out.iter().any(|e| e.filePath == "b.txt"));
 }
 
+#[test]
+fn refs_diff_stops_when_operation_is_cancelled() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    std::fs::create_dir_all(&work).unwrap();
+    run(&work, "git init");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test checkout -b main",
+    );
+    std::fs::write(work.join("a.txt"), b"a1\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m init",
+    );
+    run(&work, "git checkout -b feature");
+    std::fs::write(work.join("a.txt"), b"a2\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m change",
+    );
+
+    let opts = GitDiffOptions {
+        baseRef: Some("main".into()),
+        headRef: "feature".into(),
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let id = crate::cancel::create_operation();
+    let err = crate::cancel::claim(Some(id))
+        .run(|| {
+            // Cancelled after the call started: the walker has to notice.
+            crate::cancel::cancel_operation(id);
+            crate::diff::refs::diff_refs(opts)
+        })
+        .unwrap_err();
+    assert!(err.is::<crate::cancel::Cancelled>(), "{err:#}");
+}
+
 #[test]
 fn refs_merge_base_after_merge_is_branch_tip() {
     let tmp = tempdir().unwrap();
@@ -666,6 +716,9 @@ fn refs_merge_base_after_merge_is_branch_tip() {
         maxBytes: Some(1024 * 1024),
         lastKnownBaseSha: None,
         lastKnownMergeCommitSha: None,
+        recurseSubmodules: None,
+        truncateWindowBytes: None,
+        maxTruncatedSourceBytes: None,
     })
     .unwrap();
     assert_eq!(
@@ -723,6 +776,9 @@ fn refs_diff_numstat_matches_known_pairs() {
             maxBytes: Some(10 * 1024 * 1024),
             lastKnownBaseSha: None,
             lastKnownMergeCommitSha: None,
+            recurseSubmodules: None,
+            truncateWindowBytes: None,
+            maxTruncatedSourceBytes: None,
         })
         .expect("diff refs");
         let adds: i32 = out.iter().map(|e| e.additions).sum();
@@ -817,6 +873,9 @@ fn refs_diff_handles_binary_files() {
         maxBytes: Some(1024 * 1024),
         lastKnownBaseSha: None,
         lastKnownMergeCommitSha: None,
+        recurseSubmodules: None,
+        truncateWindowBytes: None,
+        maxTruncatedSourceBytes: None,
     })
     .expect("diff refs binary");
 
@@ -829,6 +888,260 @@ fn refs_diff_handles_binary_files() {
     assert_eq!(bin_entry.deletions, 0);
 }
 
+#[test]
+fn status_reports_staged_and_unstaged_changes() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    fs::create_dir_all(&work).unwrap();
+    run(&work, "git init");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test checkout -b main",
+    );
+    fs::write(work.join("a.txt"), b"a1\n").unwrap();
+    fs::write(work.join("b.txt"), b"b1\n").unwrap();
+    fs::write(work.join("old.txt"), b"moved\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m init",
+    );
+
+    // Staged: modify a.txt and rename old.txt -> new.txt
+    fs::write(work.join("a.txt"), b"a1\na2\n").unwrap();
+    run(&work, "git mv old.txt new.txt");
+    run(&work, "git add a.txt");
+    // Unstaged: modify b.txt, plus an untracked file
+    fs::write(work.join("b.txt"), b"b2\n").unwrap();
+    fs::write(work.join("untracked.txt"), b"u\n").unwrap();
+
+    let out = crate::diff::status::git_status(GitStatusOptions {
+        worktreePath: work.to_string_lossy().to_string(),
+        includeUntracked: Some(true),
+        includeContents: Some(true),
+        maxBytes: Some(1024 * 1024),
+    })
+    .expect("git status");
+
+    assert_eq!(out.branch.as_deref(), Some("main"));
+    assert!(out.headSha.is_some());
+
+    let staged: Vec<(String, String)> = out
+        .staged
+        .iter()
+        .map(|e| (e.status.clone(), e.filePath.clone()))
+        .collect();
+    assert_eq!(
+        staged,
+        vec![
+            ("modified".to_string(), "a.txt".to_string()),
+            ("renamed".to_string(), "new.txt".to_string()),
+        ]
+    );
+    let renamed = out.staged.iter().find(|e| e.filePath == "new.txt").unwrap();
+    assert_eq!(renamed.oldPath.as_deref(), Some("old.txt"));
+    let a = out.staged.iter().find(|e| e.filePath == "a.txt").unwrap();
+    assert_eq!(a.newContent.as_deref(), Some("a1\na2\n"));
+    assert_eq!((a.additions, a.deletions), (1, 0));
+
+    let unstaged: Vec<(String, String)> = out
+        .unstaged
+        .iter()
+        .map(|e| (e.status.clone(), e.filePath.clone()))
+        .collect();
+    assert_eq!(
+        unstaged,
+        vec![
+            ("modified".to_string(), "b.txt".to_string()),
+            ("added".to_string(), "untracked.txt".to_string()),
+        ]
+    );
+    let b = out.unstaged.iter().find(|e| e.filePath == "b.txt").unwrap();
+    assert_eq!(b.oldContent.as_deref(), Some("b1\n"));
+    assert_eq!(b.newContent.as_deref(), Some("b2\n"));
+}
+
+#[test]
+fn working_tree_diff_respects_max_bytes_and_detects_renames() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    fs::create_dir_all(&work).unwrap();
+    run(&work, "git init");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test checkout -b main",
+    );
+    fs::write(work.join("big.txt"), "x\n".repeat(100)).unwrap();
+    fs::write(work.join("keep.txt"), b"same\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m init",
+    );
+
+    // Unstaged rename (plain mv) and an oversized modification
+    fs::rename(work.join("keep.txt"), work.join("kept.txt")).unwrap();
+    fs::write(work.join("big.txt"), "y\n".repeat(100)).unwrap();
+
+    let out = crate::diff::status::diff_working_tree(GitDiffWorkingTreeOptions {
+        worktreePath: work.to_string_lossy().to_string(),
+        staged: None,
+        includeUntracked: Some(true),
+        includeContents: Some(true),
+        maxBytes: Some(64),
+    })
+    .expect("working tree diff");
+
+    let big = out.iter().find(|e| e.filePath == "big.txt").unwrap();
+    assert_eq!(big.status, "modified");
+    assert_eq!(big.contentOmitted, Some(true));
+    assert!(big.oldContent.is_none() && big.newContent.is_none());
+
+    let kept = out.iter().find(|e| e.filePath == "kept.txt").unwrap();
+    assert_eq!(kept.status, "renamed");
+    assert_eq!(kept.oldPath.as_deref(), Some("keep.txt"));
+    assert!(!out.iter().any(|e| e.filePath == "keep.txt"));
+
+    // Nothing is staged yet
+    let staged = crate::diff::status::diff_working_tree(GitDiffWorkingTreeOptions {
+        worktreePath: work.to_string_lossy().to_string(),
+        staged: Some(true),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(staged.is_empty());
+}
+
+#[test]
+fn refs_diff_page_walks_all_entries_with_byte_budget() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    fs::create_dir_all(&work).unwrap();
+    run(&work, "git init");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test checkout -b main",
+    );
+    fs::write(work.join("seed.txt"), b"seed\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m init",
+    );
+    run(&work, "git checkout -b feature");
+    for i in 0..5 {
+        fs::write(work.join(format!("f{i}.txt")), "0123456789\n".repeat(2)).unwrap();
+    }
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -m change",
+    );
+
+    let opts = GitDiffOptions {
+        baseRef: Some("main".into()),
+        headRef: "feature".into(),
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        includeContents: Some(true),
+        ..Default::default()
+    };
+    let full = crate::diff::refs::diff_refs(opts.clone()).unwrap();
+    assert_eq!(full.len(), 5);
+
+    // Refs that don't resolve are errors, not empty diffs
+    for (base, head) in [("main", "no-such-branch"), ("no-such-branch", "feature")] {
+        let err = crate::diff::refs::diff_refs(GitDiffOptions {
+            baseRef: Some(base.into()),
+            headRef: head.into(),
+            ..opts.clone()
+        })
+        .unwrap_err();
+        assert_eq!(
+            crate::error::code_of(&err),
+            crate::error::ErrorCode::RefNotFound
+        );
+    }
+
+    let mut cursor: Option<String> = None;
+    let mut paged = Vec::new();
+    let mut pages = 0;
+    loop {
+        let page = crate::diff::page::diff_page(
+            opts.clone(),
+            GitDiffPageRequest {
+                cursor: cursor.clone(),
+                pageSize: Some(2),
+                // Room for exactly two files (22 bytes each)
+                maxTotalBytes: Some(50),
+            },
+        )
+        .unwrap();
+        pages += 1;
+        assert_eq!(page.totalCount, 5);
+        assert!(page.budgetExhausted);
+        paged.extend(page.entries);
+        match page.nextCursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(pages, 3);
+    let paths: Vec<&str> = paged.iter().map(|e| e.filePath.as_str()).collect();
+    let expected: Vec<&str> = full.iter().map(|e| e.filePath.as_str()).collect();
+    assert_eq!(paths, expected);
+    let with_content: Vec<bool> = paged.iter().map(|e| e.newContent.is_some()).collect();
+    assert_eq!(with_content, vec![true, true, false, false, false]);
+    assert!(paged[2..].iter().all(|e| e.contentOmitted == Some(true)));
+    // Line counts survive truncation
+    assert!(paged.iter().all(|e| e.additions == 2));
+
+    // A cursor can't be replayed against a different diff
+    let first = crate::diff::page::diff_page(
+        opts.clone(),
+        GitDiffPageRequest {
+            pageSize: Some(2),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let next = first.nextCursor.expect("second page");
+    let err = crate::diff::page::diff_page(
+        GitDiffOptions {
+            baseRef: Some("feature".into()),
+            headRef: "main".into(),
+            ..opts.clone()
+        },
+        GitDiffPageRequest {
+            cursor: Some(next.clone()),
+            pageSize: Some(2),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        crate::error::code_of(&err),
+        crate::error::ErrorCode::InvalidArgument
+    );
+    assert!(crate::diff::page::diff_page(
+        opts.clone(),
+        GitDiffPageRequest {
+            cursor: Some(next.clone()),
+            pageSize: Some(2),
+            maxTotalBytes: Some(50),
+        },
+    )
+    .is_err());
+
+    assert!(crate::diff::page::diff_page(
+        opts,
+        GitDiffPageRequest {
+            cursor: Some("garbage".into()),
+            ..Default::default()
+        },
+    )
+    .is_err());
+}
+
 #[cfg(feature = "fuzz-tests")]
 #[test]
 #[ignore]
@@ -1046,3 +1359,308 @@ fn fuzz_diff_stats_match_github_ground_truth() {
     }
     assert!(checked > 0, "no PRs with verified merge bases");
 }
+
+#[test]
+fn refs_diff_resolves_refs_inside_linked_worktrees() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    std::fs::create_dir_all(work.join("src")).unwrap();
+    run(&work, "git init -q -b main");
+    std::fs::write(work.join("src/a.txt"), b"a1\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm init",
+    );
+    run(&work, "git worktree add -q -b feature ../wt");
+    let wt = tmp.path().join("wt");
+    std::fs::write(wt.join("b.txt"), b"b\n").unwrap();
+    run(&wt, "git add .");
+    run(
+        &wt,
+        "git -c user.email=a@b -c user.name=test commit -qm change",
+    );
+
+    // Without a base ref the main worktree's HEAD is the base, not the
+    // linked worktree's own HEAD; the override may also be a subdirectory.
+    for dir in [wt.clone(), wt.join("src")] {
+        let out = crate::diff::refs::diff_refs(GitDiffOptions {
+            headRef: "HEAD".into(),
+            originPathOverride: Some(dir.to_string_lossy().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let paths: Vec<&str> = out.iter().map(|e| e.filePath.as_str()).collect();
+        assert_eq!(paths, ["b.txt"], "override {}", dir.display());
+    }
+}
+
+#[test]
+fn refs_diff_reports_submodule_pointer_changes() {
+    let tmp = tempdir().unwrap();
+    let lib = tmp.path().join("lib");
+    std::fs::create_dir_all(&lib).unwrap();
+    run(&lib, "git init -q -b main");
+    std::fs::write(lib.join("lib.txt"), b"v1\n").unwrap();
+    run(&lib, "git add .");
+    run(
+        &lib,
+        "git -c user.email=a@b -c user.name=test commit -qm v1",
+    );
+
+    let work = tmp.path().join("repo");
+    std::fs::create_dir_all(&work).unwrap();
+    run(&work, "git init -q -b main");
+    run(
+        &work,
+        "git -c protocol.file.allow=always submodule add -q ../lib vendor/lib",
+    );
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm init",
+    );
+    let old_sha = run_git(lib.to_str().unwrap(), &["rev-parse", "HEAD"]).unwrap();
+
+    std::fs::write(lib.join("lib.txt"), b"v2\n").unwrap();
+    run(
+        &lib,
+        "git -c user.email=a@b -c user.name=test commit -qam v2",
+    );
+    let new_sha = run_git(lib.to_str().unwrap(), &["rev-parse", "HEAD"]).unwrap();
+    run(&work, "git checkout -q -b feature");
+    run(
+        &work.join("vendor/lib"),
+        "git -c protocol.file.allow=always pull -q origin main",
+    );
+    run(&work, "git add vendor/lib");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm bump",
+    );
+
+    let diff = |depth| {
+        crate::diff::refs::diff_refs(GitDiffOptions {
+            baseRef: Some("main".into()),
+            headRef: "feature".into(),
+            originPathOverride: Some(work.to_string_lossy().to_string()),
+            recurseSubmodules: depth,
+            ..Default::default()
+        })
+        .unwrap()
+    };
+
+    let out = diff(None);
+    assert_eq!(out.len(), 1, "{out:?}");
+    let pointer = &out[0];
+    assert_eq!(pointer.filePath, "vendor/lib");
+    assert_eq!(pointer.status, "modified");
+    assert!(!pointer.isBinary);
+    let change = pointer.submodule.as_ref().expect("submodule change");
+    assert_eq!(change.oldSha.as_deref(), Some(old_sha.trim()));
+    assert_eq!(change.newSha.as_deref(), Some(new_sha.trim()));
+
+    let out = diff(Some(1));
+    let paths: Vec<&str> = out.iter().map(|e| e.filePath.as_str()).collect();
+    assert_eq!(paths, ["vendor/lib", "vendor/lib/lib.txt"]);
+    assert_eq!(out[1].additions, 1);
+    assert_eq!(out[1].deletions, 1);
+    assert!(out[1].submodule.is_none());
+}
+
+#[test]
+fn truncate_middle_keeps_whole_lines_around_a_marker() {
+    let text: String = (0..100).map(|i| format!("line {i:03}\n")).collect();
+    let cut = crate::diff::refs::truncate_middle(&text, 25).unwrap();
+    assert_eq!(
+        cut,
+        "line 000\nline 001\n... 864 bytes truncated ...\nline 098\nline 099\n"
+    );
+    assert!(crate::diff::refs::truncate_middle(&text, 500).is_none());
+    // No newline in the window: cut on a character boundary instead.
+    let wide = "é".repeat(20);
+    let cut = crate::diff::refs::truncate_middle(&wide, 5).unwrap();
+    assert_eq!(cut, "éé\n... 32 bytes truncated ...\néé");
+}
+
+#[test]
+fn refs_diff_truncates_large_text_and_hashes_binaries() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    std::fs::create_dir_all(&work).unwrap();
+    run(&work, "git init -q -b main");
+    let big_old: String = (0..2000).map(|i| format!("old {i}\n")).collect();
+    std::fs::write(work.join("big.txt"), &big_old).unwrap();
+    std::fs::write(work.join("bin.dat"), [0u8, 1, 2]).unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm init",
+    );
+    run(&work, "git checkout -q -b feature");
+    let big_new: String = (0..2000).map(|i| format!("new {i}\n")).collect();
+    std::fs::write(work.join("big.txt"), &big_new).unwrap();
+    std::fs::write(work.join("bin.dat"), [0u8, 1, 2, 3]).unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm change",
+    );
+    let blob = |spec: &str| {
+        run_git(work.to_str().unwrap(), &["rev-parse", spec])
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+
+    let diff = |window| {
+        crate::diff::refs::diff_refs(GitDiffOptions {
+            baseRef: Some("main".into()),
+            headRef: "feature".into(),
+            originPathOverride: Some(work.to_string_lossy().to_string()),
+            maxBytes: Some(1024),
+            truncateWindowBytes: window,
+            ..Default::default()
+        })
+        .unwrap()
+    };
+
+    let out = diff(None);
+    let big = out.iter().find(|e| e.filePath == "big.txt").unwrap();
+    assert_eq!(big.contentOmitted, Some(true));
+    assert!(big.newContent.is_none() && big.truncation.is_none());
+    let bin = out.iter().find(|e| e.filePath == "bin.dat").unwrap();
+    assert!(bin.isBinary && bin.newContent.is_none());
+    assert_eq!((bin.oldSize, bin.newSize), (Some(3), Some(4)));
+    assert_eq!(bin.oldSha.as_deref(), Some(blob("main:bin.dat").as_str()));
+    assert_eq!(
+        bin.newSha.as_deref(),
+        Some(blob("feature:bin.dat").as_str())
+    );
+
+    let out = diff(Some(64));
+    let big = out.iter().find(|e| e.filePath == "big.txt").unwrap();
+    let truncation = big.truncation.as_ref().expect("truncated");
+    assert!(truncation.oldTruncated && truncation.newTruncated);
+    let new_content = big.newContent.as_deref().unwrap();
+    assert!(new_content.starts_with("new 0\n"));
+    assert!(new_content.contains(" bytes truncated ...\n"));
+    assert!(new_content.ends_with("new 1999\n"));
+    assert!(new_content.len() < 200);
+    assert_eq!(big.newSize, Some(big_new.len() as i32));
+
+    // The follow-up fetch for the full body, by path or by blob sha.
+    let full = crate::show::file_at_ref(crate::types::GitFileAtRefOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        gitRef: "feature".into(),
+        path: "big.txt".into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(full.content.as_deref(), Some(big_new.as_str()));
+    assert_eq!(full.sha, blob("feature:big.txt"));
+    assert!(!full.truncated);
+
+    let head = crate::show::file_at_ref(crate::types::GitFileAtRefOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        gitRef: big.oldSha.clone().unwrap(),
+        path: "big.txt".into(),
+        maxBytes: Some(10),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(head.content.as_deref(), Some("old 0\nold "));
+    assert!(head.truncated);
+    assert_eq!(head.size, big_old.len() as i32);
+
+    let missing = crate::show::file_at_ref(crate::types::GitFileAtRefOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        gitRef: "main".into(),
+        path: "nope.txt".into(),
+        ..Default::default()
+    })
+    .unwrap_err();
+    assert_eq!(
+        crate::error::code_of(&missing),
+        crate::error::ErrorCode::PathNotFound
+    );
+}
+
+#[test]
+fn show_returns_commit_and_per_file_patches() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("repo");
+    std::fs::create_dir_all(&work).unwrap();
+    run(&work, "git init -q -b main");
+    std::fs::write(work.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+    std::fs::write(work.join("gone.txt"), "bye\n").unwrap();
+    run(&work, "git add .");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm init",
+    );
+    let root = run_git(work.to_str().unwrap(), &["rev-parse", "HEAD"])
+        .unwrap()
+        .trim()
+        .to_string();
+    std::fs::write(work.join("a.txt"), "one\nTWO\nthree\n").unwrap();
+    std::fs::write(work.join("new.txt"), "hi\n").unwrap();
+    std::fs::remove_file(work.join("gone.txt")).unwrap();
+    run(&work, "git add -A");
+    run(
+        &work,
+        "git -c user.email=a@b -c user.name=test commit -qm 'change things' -m 'body text'",
+    );
+
+    let out = crate::show::show(crate::types::GitShowOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        hash: "main".into(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(out.commit.subject, "change things");
+    assert_eq!(out.commit.parents, vec![root.clone()]);
+    assert_eq!(out.commit.authorEmail, "a@b");
+    assert!(out.message.contains("body text"));
+    assert!(out.commit.refs.iter().any(|r| r == "HEAD -> main"));
+
+    let paths: Vec<&str> = out.files.iter().map(|e| e.filePath.as_str()).collect();
+    assert_eq!(paths, vec!["a.txt", "gone.txt", "new.txt"]);
+    let a = &out.files[0];
+    assert_eq!(a.status, "modified");
+    let patch = a.patch.as_deref().unwrap();
+    assert!(patch.starts_with("--- a/a.txt\n+++ b/a.txt\n@@"));
+    assert!(patch.contains("-two\n+TWO\n"));
+    assert_eq!(a.patchSize, Some(patch.len() as i32));
+    assert!(a.oldContent.is_none() && a.newContent.is_none());
+    let gone = out.files[1].patch.as_deref().unwrap();
+    assert!(gone.starts_with("--- a/gone.txt\n+++ /dev/null\n"));
+    let added = out.files[2].patch.as_deref().unwrap();
+    assert!(added.starts_with("--- /dev/null\n+++ b/new.txt\n"));
+
+    // A root commit diffs against the empty tree.
+    let first = crate::show::show(crate::types::GitShowOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        hash: root[..8].to_string(),
+        includeContents: Some(true),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(first.commit.parents.is_empty());
+    assert_eq!(first.files.len(), 2);
+    assert!(first.files.iter().all(|e| e.status == "added"));
+    assert_eq!(
+        first.files[0].newContent.as_deref(),
+        Some("one\ntwo\nthree\n")
+    );
+
+    let missing = crate::show::show(crate::types::GitShowOptions {
+        originPathOverride: Some(work.to_string_lossy().to_string()),
+        hash: "does-not-exist".into(),
+        ..Default::default()
+    })
+    .unwrap_err();
+    assert_eq!(
+        crate::error::code_of(&missing),
+        crate::error::ErrorCode::RefNotFound
+    );
+}
@@ -17,6 +17,31 @@ pub struct DiffEntry {
     pub newSize: Option<i32>,
     pub patchSize: Option<i32>,
     pub patch: Option<String>,
+    /// Set when the entry is a submodule pointer rather than a file.
+    pub submodule: Option<SubmoduleChange>,
+    /// Blob ids of each side, for fetching full contents with `gitFileAtRef`.
+    pub oldSha: Option<String>,
+    pub newSha: Option<String>,
+    /// Set when `oldContent`/`newContent` hold only the head and tail of the file.
+    pub truncation: Option<ContentTruncation>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct ContentTruncation {
+    /// Bytes kept from the start and from the end of each truncated side.
+    pub windowBytes: i32,
+    pub oldTruncated: bool,
+    pub newTruncated: bool,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct SubmoduleChange {
+    /// Commit the submodule pointed at before; unset when it was added.
+    pub oldSha: Option<String>,
+    /// Commit the submodule points at now; unset when it was removed.
+    pub newSha: Option<String>,
 }
 
 #[napi(object)]
@@ -47,7 +72,7 @@ pub struct GitDiffWorkspaceOptions {
 }
 
 #[napi(object)]
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct GitDiffOptions {
     pub headRef: String,
     pub baseRef: Option<String>,
@@ -59,4 +84,269 @@ pub struct GitDiffOptions {
     pub maxBytes: Option<i32>,
     pub lastKnownBaseSha: Option<String>,
     pub lastKnownMergeCommitSha: Option<String>,
+    /// Levels of submodules to expand into per-file entries, prefixed with the
+    /// submodule path (default 0: pointer changes only). Needs the submodules
+    /// checked out or fetched into the repository's `modules` directory.
+    pub recurseSubmodules: Option<i32>,
+    /// Instead of omitting text over `maxBytes`, return this many bytes from
+    /// its start and end around a `... N bytes truncated ...` line.
+    pub truncateWindowBytes: Option<i32>,
+    /// Files larger than this are omitted even when truncating (default 64 MiB).
+    pub maxTruncatedSourceBytes: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitFileAtRefOptions {
+    pub repoFullName: Option<String>,
+    pub repoUrl: Option<String>,
+    pub originPathOverride: Option<String>,
+    /// Commit-ish to read the file from, or a blob sha from `DiffEntry`.
+    #[napi(js_name = "ref")]
+    pub gitRef: String,
+    /// Repository-relative path; ignored when `ref` is a blob sha.
+    pub path: String,
+    /// Content past this many bytes is cut off (default: the whole file).
+    pub maxBytes: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitShowOptions {
+    pub repoFullName: Option<String>,
+    pub repoUrl: Option<String>,
+    pub originPathOverride: Option<String>,
+    /// Commit sha or any commit-ish.
+    pub hash: String,
+    /// Files larger than this get no patch (default 950 KiB).
+    pub maxBytes: Option<i32>,
+    /// Unchanged lines around each hunk (default 3).
+    pub contextLines: Option<i32>,
+    /// Also return `oldContent`/`newContent` for each file (default false).
+    pub includeContents: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitShowResult {
+    pub commit: CommitInfo,
+    /// Full commit message, including the subject line.
+    pub message: String,
+    /// Changes against the first parent, each with a unified `patch`.
+    pub files: Vec<DiffEntry>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitFileContent {
+    pub path: String,
+    /// Blob id of the file.
+    pub sha: String,
+    pub size: i32,
+    pub isBinary: bool,
+    /// UTF-8 text; unset for binary files.
+    pub content: Option<String>,
+    /// True when `content` stops at `maxBytes`.
+    pub truncated: bool,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitDiffPageRequest {
+    /// Opaque cursor returned as `nextCursor` by the previous page.
+    pub cursor: Option<String>,
+    pub pageSize: Option<i32>,
+    /// Total content bytes across the whole diff; later entries are omitted once exceeded.
+    pub maxTotalBytes: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitDiffPage {
+    pub entries: Vec<DiffEntry>,
+    pub nextCursor: Option<String>,
+    pub totalCount: i32,
+    pub budgetExhausted: bool,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitStatusOptions {
+    pub worktreePath: String,
+    pub includeUntracked: Option<bool>,
+    pub includeContents: Option<bool>,
+    pub maxBytes: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitStatusResult {
+    pub branch: Option<String>,
+    pub headSha: Option<String>,
+    pub staged: Vec<DiffEntry>,
+    pub unstaged: Vec<DiffEntry>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitDiffWorkingTreeOptions {
+    pub worktreePath: String,
+    /// Compare HEAD against the index instead of the working tree.
+    pub staged: Option<bool>,
+    pub includeUntracked: Option<bool>,
+    pub includeContents: Option<bool>,
+    pub maxBytes: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitLogOptions {
+    pub repoFullName: Option<String>,
+    pub repoUrl: Option<String>,
+    pub originPathOverride: Option<String>,
+    /// Revisions to start from (default `HEAD`); prefix with `^` to exclude.
+    pub refs: Option<Vec<String>>,
+    /// Only include commits touching any of these repository-relative paths.
+    pub paths: Option<Vec<String>>,
+    /// `"topo"` (default) or `"date"`.
+    pub order: Option<String>,
+    pub skip: Option<i32>,
+    pub maxCount: Option<i32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub parents: Vec<String>,
+    pub authorName: String,
+    pub authorEmail: String,
+    pub authorTime: i64,
+    pub committerTime: Option<i64>,
+    pub subject: String,
+    pub refs: Vec<String>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitLogResult {
+    pub commits: Vec<CommitInfo>,
+    pub hasMore: bool,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitCreateBranchOptions {
+    pub worktreePath: String,
+    pub name: String,
+    /// Commit-ish to branch from; defaults to `HEAD`.
+    pub startPoint: Option<String>,
+    /// Reset the branch if it already exists.
+    pub force: Option<bool>,
+    pub checkout: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitCheckoutOptions {
+    pub worktreePath: String,
+    pub target: String,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitMergeFfOnlyOptions {
+    pub worktreePath: String,
+    pub target: String,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitBranchOpResult {
+    pub branch: Option<String>,
+    pub headSha: Option<String>,
+    /// False when the operation was a no-op (already on the branch / up to date).
+    pub updated: bool,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitMergePreviewOptions {
+    /// Branch being merged into.
+    pub baseRef: String,
+    /// Branch being merged.
+    pub headRef: String,
+    pub repoFullName: Option<String>,
+    pub repoUrl: Option<String>,
+    pub originPathOverride: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct MergeConflictFile {
+    pub filePath: String,
+    /// `content`, `add_add`, `modify_delete` or `binary`.
+    pub kind: String,
+    pub conflictCount: i32,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitMergePreview {
+    pub mergeBaseSha: String,
+    pub baseSha: String,
+    pub headSha: String,
+    pub alreadyMerged: bool,
+    pub fastForward: bool,
+    pub conflicts: Vec<MergeConflictFile>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitWatchOptions {
+    pub worktreePath: String,
+    /// Quiet period before a batch of filesystem changes is emitted (default 200ms).
+    pub debounceMs: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct GitWatchEvent {
+    /// `head`, `index`, `refs` or `worktree`.
+    pub kind: String,
+    /// Repo-relative paths for `worktree` events; empty otherwise.
+    pub paths: Vec<String>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct WorkspaceProxyOptions {
+    /// Route requests without a workspace header to `upstream` (default true).
+    pub allowDefaultUpstream: Option<bool>,
+    /// Bytes/sec cap per client connection.
+    pub maxConnectionBandwidth: Option<u32>,
+    /// Bytes/sec cap shared by all connections to one workspace.
+    pub maxWorkspaceBandwidth: Option<u32>,
+    /// CORS rules in the proxy's `--cors` format, e.g. `*:3000;origin=https://a.test`.
+    pub cors: Option<Vec<String>>,
+    /// Ports probed by `/__cmux/ports`, as `START-END`.
+    pub portScanRange: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct WorkspaceProxyInfo {
+    pub port: u32,
+}
+
+#[napi(object)]
+#[derive(Default, Debug, Clone)]
+pub struct WorkspaceProxyAccessLogEvent {
+    pub client: String,
+    pub method: String,
+    pub path: String,
+    pub workspace: Option<String>,
+    pub port: Option<u32>,
+    pub status: u32,
+    pub durationMs: f64,
 }
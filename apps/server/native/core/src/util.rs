@@ -1,14 +1,98 @@
 use anyhow::{anyhow, Result};
-use std::process::{Command, Stdio};
+use std::io::Read;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::error::{CoreError, ErrorCode};
 
 pub fn run_git(cwd: &str, args: &[&str]) -> Result<String> {
     let mut cmd = Command::new("git");
     cmd.current_dir(cwd).args(args).stdin(Stdio::null());
     let output = cmd.output()?;
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    git_output(args, output.status, &output.stdout, &output.stderr)
+}
+
+/// Like [`run_git`], but kills git and fails with `ErrorCode::Timeout` once it
+/// has run for `timeout`. For network operations that can hang.
+pub fn run_git_with_timeout(cwd: &str, args: &[&str], timeout: Duration) -> Result<String> {
+    let mut child = Command::new("git")
+        .current_dir(cwd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain both pipes so a chatty git can't block on a full buffer.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(CoreError::new(
+                ErrorCode::Timeout,
+                format!("git {:?} timed out after {}s", args, timeout.as_secs()),
+            )
+            .into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let join = |reader: JoinHandle<std::io::Result<Vec<u8>>>| {
+        reader
+            .join()
+            .map_err(|_| anyhow!("reading git {:?} output panicked", args))?
+            .map_err(anyhow::Error::from)
+    };
+    git_output(args, status, &join(stdout)?, &join(stderr)?)
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<std::io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buf)?;
+        }
+        Ok(buf)
+    })
+}
+
+fn git_output(args: &[&str], status: ExitStatus, stdout: &[u8], stderr: &[u8]) -> Result<String> {
+    if status.success() {
+        Ok(String::from_utf8_lossy(stdout).into_owned())
     } else {
-        let err = String::from_utf8_lossy(&output.stderr);
-        Err(anyhow!("git {:?} failed: {}", args, err))
+        let err = String::from_utf8_lossy(stderr);
+        Err(CoreError::new(
+            ErrorCode::GitCommandFailed,
+            format!("git {:?} failed: {}", args, err),
+        )
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::code_of;
+
+    #[cfg(unix)]
+    #[test]
+    fn git_that_runs_too_long_times_out() {
+        let dir = std::env::temp_dir();
+        let cwd = dir.to_str().unwrap();
+        let slow = ["-c", "alias.slow=!sleep 5", "slow"];
+        let started = Instant::now();
+        let err = run_git_with_timeout(cwd, &slow, Duration::from_millis(200)).unwrap_err();
+        assert_eq!(code_of(&err), ErrorCode::Timeout);
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let version = run_git_with_timeout(cwd, &["--version"], Duration::from_secs(30)).unwrap();
+        assert!(version.starts_with("git version"));
+        let failed = run_git_with_timeout(cwd, &["no-such-command"], Duration::from_secs(30));
+        assert_eq!(code_of(&failed.unwrap_err()), ErrorCode::GitCommandFailed);
     }
 }
@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Result};
+use notify::{RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use crate::repo::handles;
+use crate::types::{GitWatchEvent, GitWatchOptions};
+
+const DEFAULT_DEBOUNCE_MS: u32 = 200;
+
+pub const KIND_HEAD: &str = "head";
+pub const KIND_INDEX: &str = "index";
+pub const KIND_REFS: &str = "refs";
+pub const KIND_WORKTREE: &str = "worktree";
+
+enum Msg {
+    Fs(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// Stops the watcher thread when `stop` is called. Dropping it without
+/// calling `stop` leaves the subscription running.
+pub struct WatchSubscription {
+    tx: Sender<Msg>,
+}
+
+impl WatchSubscription {
+    pub fn stop(&self) {
+        let _ = self.tx.send(Msg::Stop);
+    }
+}
+
+struct Layout {
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    work_dir: Option<PathBuf>,
+}
+
+enum Class {
+    Head,
+    Index,
+    Refs,
+    Worktree(String),
+}
+
+fn canonical(p: &Path) -> PathBuf {
+    p.canonicalize().unwrap_or_else(|_| p.to_path_buf())
+}
+
+fn classify(layout: &Layout, path: &Path) -> Option<Class> {
+    if path.extension().is_some_and(|ext| ext == "lock") {
+        return None;
+    }
+    if let Ok(rel) = path.strip_prefix(&layout.git_dir) {
+        if rel == Path::new("HEAD") {
+            return Some(Class::Head);
+        }
+        if rel == Path::new("index") {
+            return Some(Class::Index);
+        }
+    }
+    if let Ok(rel) = path.strip_prefix(&layout.common_dir) {
+        if rel.starts_with("refs") || rel == Path::new("packed-refs") {
+            return Some(Class::Refs);
+        }
+        // objects/, logs/, FETCH_HEAD and friends are noise for subscribers.
+        return None;
+    }
+    if path.starts_with(&layout.git_dir) {
+        return None;
+    }
+    let rel = path.strip_prefix(layout.work_dir.as_ref()?).ok()?;
+    if rel.as_os_str().is_empty() {
+        return None;
+    }
+    Some(Class::Worktree(rel.to_string_lossy().replace('\\', "/")))
+}
+
+/// `(symbolic ref, commit)` HEAD currently points at.
+fn head_state(repo_path: &Path) -> Option<(Option<String>, Option<String>)> {
+    let repo = gix::open(repo_path).ok()?;
+    let head = repo.head().ok()?;
+    let name = head.referent_name().map(|n| n.as_bstr().to_string());
+    let id = head.id().map(|id| id.to_string());
+    Some((name, id))
+}
+
+/// Drop worktree paths matched by the repository's ignore rules. The exclude
+/// stack is rebuilt per batch so `.gitignore` edits take effect immediately.
+fn filter_ignored(repo_path: &Path, paths: BTreeSet<String>) -> BTreeSet<String> {
+    let Ok(repo) = gix::open(repo_path) else {
+        return paths;
+    };
+    let Some(work_dir) = repo.work_dir().map(Path::to_path_buf) else {
+        return paths;
+    };
+    let Ok(index) = repo.index_or_empty() else {
+        return paths;
+    };
+    let Ok(mut excludes) = repo.excludes(
+        &index,
+        None,
+        gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped,
+    ) else {
+        return paths;
+    };
+    paths
+        .into_iter()
+        .filter(|rel| {
+            let mode = work_dir
+                .join(rel)
+                .is_dir()
+                .then_some(gix::index::entry::Mode::DIR);
+            !excludes
+                .at_path(rel, mode)
+                .map(|p| p.is_excluded())
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Watch a repository and call `emit` with debounced, typed events: `head`
+/// when HEAD points at a different branch or commit, `index`, `refs`, and
+/// `worktree` (with the non-ignored, repo-relative paths that changed).
+pub fn watch_repo<F>(opts: GitWatchOptions, emit: F) -> Result<WatchSubscription>
+where
+    F: Fn(GitWatchEvent) + Send + 'static,
+{
+    let repo_path = PathBuf::from(&opts.worktreePath);
+    let repo = gix::open(&repo_path)?;
+    let layout = Layout {
+        git_dir: canonical(repo.git_dir()),
+        common_dir: canonical(repo.common_dir()),
+        work_dir: repo.work_dir().map(canonical),
+    };
+    drop(repo);
+    let debounce = Duration::from_millis(opts.debounceMs.unwrap_or(DEFAULT_DEBOUNCE_MS) as u64);
+
+    let (tx, rx) = mpsc::channel::<Msg>();
+    let fs_tx = tx.clone();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = fs_tx.send(Msg::Fs(res));
+    })?;
+    let mut roots: Vec<&Path> = Vec::new();
+    if let Some(w) = &layout.work_dir {
+        roots.push(w);
+    }
+    for dir in [&layout.git_dir, &layout.common_dir] {
+        if !roots.iter().any(|r| dir.starts_with(r)) {
+            roots.push(dir);
+        }
+    }
+    for root in roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| anyhow!("failed to watch {}: {e}", root.display()))?;
+    }
+
+    std::thread::spawn(move || {
+        // Keep the watcher alive for as long as the thread runs.
+        let _watcher = watcher;
+        let mut last_head = head_state(&repo_path);
+        let mut pending: BTreeMap<&'static str, BTreeSet<String>> = BTreeMap::new();
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let msg = match deadline {
+                Some(at) => rx.recv_timeout(at.saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match msg {
+                Ok(Msg::Fs(Ok(event))) => {
+                    for path in &event.paths {
+                        let (kind, rel) = match classify(&layout, path) {
+                            Some(Class::Head) => (KIND_HEAD, None),
+                            Some(Class::Index) => (KIND_INDEX, None),
+                            Some(Class::Refs) => (KIND_REFS, None),
+                            Some(Class::Worktree(rel)) => (KIND_WORKTREE, Some(rel)),
+                            None => continue,
+                        };
+                        let set = pending.entry(kind).or_default();
+                        set.extend(rel);
+                        deadline.get_or_insert_with(|| Instant::now() + debounce);
+                    }
+                }
+                Ok(Msg::Fs(Err(_))) => {}
+                Ok(Msg::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    deadline = None;
+                    let batch = std::mem::take(&mut pending);
+                    let git_touched = batch.keys().any(|k| *k != KIND_WORKTREE);
+                    let mut head_moved = false;
+                    if git_touched {
+                        // HEAD, index or refs moved under us; reopen on the next call.
+                        handles::invalidate(&repo_path);
+                        let now = head_state(&repo_path);
+                        head_moved = now != last_head;
+                        last_head = now;
+                    }
+                    if head_moved {
+                        emit(GitWatchEvent {
+                            kind: KIND_HEAD.into(),
+                            paths: Vec::new(),
+                        });
+                    }
+                    for (kind, paths) in batch {
+                        let paths = match kind {
+                            KIND_HEAD => continue,
+                            KIND_WORKTREE => {
+                                let kept = filter_ignored(&repo_path, paths);
+                                if kept.is_empty() {
+                                    continue;
+                                }
+                                kept
+                            }
+                            _ => paths,
+                        };
+                        emit(GitWatchEvent {
+                            kind: kind.into(),
+                            paths: paths.into_iter().collect(),
+                        });
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(WatchSubscription { tx })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::run_git;
+    use std::sync::mpsc::Receiver;
+    use tempfile::tempdir;
+
+    fn drain_events(rx: &Receiver<GitWatchEvent>) -> Vec<GitWatchEvent> {
+        let mut out = Vec::new();
+        while let Ok(ev) = rx.recv_timeout(Duration::from_millis(600)) {
+            out.push(ev);
+        }
+        out
+    }
+
+    #[test]
+    fn emits_typed_events_and_stops_on_unsubscribe() {
+        let tmp = tempdir().expect("tempdir");
+        let dir = tmp.path();
+        let cwd = dir.to_str().unwrap();
+        run_git(cwd, &["init", "-q", "-b", "main"]).unwrap();
+        run_git(cwd, &["config", "user.email", "t@example.com"]).unwrap();
+        run_git(cwd, &["config", "user.name", "Test"]).unwrap();
+        std::fs::write(dir.join(".gitignore"), "build/\n").unwrap();
+        std::fs::write(dir.join("a.txt"), "one\n").unwrap();
+        run_git(cwd, &["add", "."]).unwrap();
+        run_git(cwd, &["commit", "-qm", "init"]).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let sub = watch_repo(
+            GitWatchOptions {
+                worktreePath: cwd.to_string(),
+                debounceMs: Some(50),
+            },
+            move |ev| {
+                let _ = tx.send(ev);
+            },
+        )
+        .expect("watch");
+
+        std::fs::create_dir(dir.join("build")).unwrap();
+        std::fs::write(dir.join("build/out.o"), "x").unwrap();
+        std::fs::write(dir.join("a.txt"), "two\n").unwrap();
+        let events = drain_events(&rx);
+        let worktree: Vec<_> = events.iter().filter(|e| e.kind == KIND_WORKTREE).collect();
+        assert!(!worktree.is_empty(), "events: {events:?}");
+        assert!(worktree
+            .iter()
+            .all(|e| e.paths.iter().all(|p| !p.starts_with("build"))));
+        assert!(worktree
+            .iter()
+            .any(|e| e.paths.iter().any(|p| p == "a.txt")));
+
+        run_git(cwd, &["commit", "-qam", "second"]).unwrap();
+        let kinds: BTreeSet<String> = drain_events(&rx).into_iter().map(|e| e.kind).collect();
+        assert!(kinds.contains(KIND_HEAD), "kinds: {kinds:?}");
+        assert!(kinds.contains(KIND_INDEX), "kinds: {kinds:?}");
+        assert!(kinds.contains(KIND_REFS), "kinds: {kinds:?}");
+
+        sub.stop();
+        std::thread::sleep(Duration::from_millis(100));
+        std::fs::write(dir.join("a.txt"), "three\n").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(400)).is_err());
+    }
+}
@@ -16,11 +16,246 @@ export interface GitDiffOptions {
   maxBytes?: number;
   lastKnownBaseSha?: string;
   lastKnownMergeCommitSha?: string;
+  /** Levels of submodules to expand into per-file entries (default 0). */
+  recurseSubmodules?: number;
+  /**
+   * Instead of omitting text over `maxBytes`, return this many bytes from its
+   * start and end around a `... N bytes truncated ...` line.
+   */
+  truncateWindowBytes?: number;
+  /** Files larger than this are omitted even when truncating (default 64 MiB). */
+  maxTruncatedSourceBytes?: number;
+}
+
+export interface GitFileAtRefOptions {
+  repoFullName?: string;
+  repoUrl?: string;
+  originPathOverride?: string;
+  /** Commit-ish to read the file from, or a blob sha from a diff entry. */
+  ref: string;
+  path: string;
+  maxBytes?: number;
+}
+
+export interface GitFileContent {
+  path: string;
+  sha: string;
+  size: number;
+  isBinary: boolean;
+  content?: string;
+  truncated: boolean;
+}
+
+export interface GitDiffPageRequest {
+  /** Opaque cursor returned as `nextCursor` by the previous page. */
+  cursor?: string;
+  pageSize?: number;
+  /** Total content bytes across the whole diff; later entries are omitted once exceeded. */
+  maxTotalBytes?: number;
+}
+
+export interface GitDiffPage {
+  entries: ReplaceDiffEntry[];
+  nextCursor?: string;
+  totalCount: number;
+  budgetExhausted: boolean;
+}
+
+export interface GitStatusOptions {
+  worktreePath: string;
+  includeUntracked?: boolean;
+  includeContents?: boolean;
+  maxBytes?: number;
+}
+
+export interface GitStatusResult {
+  branch?: string;
+  headSha?: string;
+  staged: ReplaceDiffEntry[];
+  unstaged: ReplaceDiffEntry[];
+}
+
+export interface GitDiffWorkingTreeOptions extends GitStatusOptions {
+  /** Compare HEAD against the index instead of the working tree. */
+  staged?: boolean;
+}
+
+export interface GitLogOptions {
+  repoFullName?: string;
+  repoUrl?: string;
+  originPathOverride?: string;
+  /** Revisions to start from (default `HEAD`); prefix with `^` to exclude. */
+  refs?: string[];
+  /** Only include commits touching any of these repository-relative paths. */
+  paths?: string[];
+  order?: "topo" | "date";
+  skip?: number;
+  maxCount?: number;
+}
+
+export interface CommitInfo {
+  sha: string;
+  parents: string[];
+  authorName: string;
+  authorEmail: string;
+  authorTime: number;
+  committerTime?: number;
+  subject: string;
+  refs: string[];
+}
+
+export interface GitLogResult {
+  commits: CommitInfo[];
+  hasMore: boolean;
+}
+
+export interface GitShowOptions {
+  repoFullName?: string;
+  repoUrl?: string;
+  originPathOverride?: string;
+  /** Commit sha or any commit-ish. */
+  hash: string;
+  /** Files larger than this get no patch (default 950 KiB). */
+  maxBytes?: number;
+  /** Unchanged lines around each hunk (default 3). */
+  contextLines?: number;
+  /** Also return `oldContent`/`newContent` for each file (default false). */
+  includeContents?: boolean;
+}
+
+export interface GitShowResult {
+  commit: CommitInfo;
+  /** Full commit message, including the subject line. */
+  message: string;
+  /** Changes against the first parent, each with a unified `patch`. */
+  files: ReplaceDiffEntry[];
+}
+
+export interface GitBranchOpResult {
+  branch?: string;
+  headSha?: string;
+  /** False when the operation was a no-op (already on the branch / up to date). */
+  updated: boolean;
+}
+
+/** The `code` property native core sets on the errors it rejects with. */
+export type NativeErrorCode =
+  | "NOT_A_REPO"
+  | "REF_NOT_FOUND"
+  | "PATH_NOT_FOUND"
+  | "INVALID_ARGUMENT"
+  | "GIT_COMMAND_FAILED"
+  | "DIRTY_TREE"
+  | "NON_FAST_FORWARD"
+  | "ALREADY_EXISTS"
+  | "CANCELLED"
+  | "TIMEOUT"
+  | "PROXY_NOT_STARTED"
+  | "PROXY_ALREADY_RUNNING"
+  | "INTERNAL";
+
+export type GitBranchErrorCode = Extract<
+  NativeErrorCode,
+  "DIRTY_TREE" | "NON_FAST_FORWARD" | "REF_NOT_FOUND" | "ALREADY_EXISTS"
+>;
+
+const BRANCH_ERROR_CODES: readonly GitBranchErrorCode[] = [
+  "DIRTY_TREE",
+  "NON_FAST_FORWARD",
+  "REF_NOT_FOUND",
+  "ALREADY_EXISTS",
+];
+
+export class GitBranchError extends Error {
+  constructor(
+    readonly code: GitBranchErrorCode,
+    message: string
+  ) {
+    super(message);
+    this.name = "GitBranchError";
+  }
+}
+
+function toBranchError(err: unknown): unknown {
+  if (!(err instanceof Error)) return err;
+  const code = (err as { code?: unknown }).code;
+  if ((BRANCH_ERROR_CODES as readonly unknown[]).includes(code)) {
+    return new GitBranchError(code as GitBranchErrorCode, err.message);
+  }
+  return err;
+}
+
+export interface GitMergePreviewOptions {
+  baseRef: string;
+  headRef: string;
+  repoFullName?: string;
+  repoUrl?: string;
+  originPathOverride?: string;
+}
+
+export interface MergeConflictFile {
+  filePath: string;
+  kind: "content" | "add_add" | "modify_delete" | "binary";
+  conflictCount: number;
+}
+
+export interface GitMergePreview {
+  mergeBaseSha: string;
+  baseSha: string;
+  headSha: string;
+  alreadyMerged: boolean;
+  fastForward: boolean;
+  conflicts: MergeConflictFile[];
+}
+
+export interface GitWatchOptions {
+  worktreePath: string;
+  debounceMs?: number;
+}
+
+export interface GitWatchEvent {
+  kind: "head" | "index" | "refs" | "worktree";
+  paths: string[];
+}
+
+export interface GitWatchHandle {
+  unsubscribe(): void;
 }
 
 type NativeGitModule = {
   // napi-rs exports as camelCase
   gitDiff?: (opts: GitDiffOptions) => Promise<ReplaceDiffEntry[]>;
+  gitDiffPage?: (
+    opts: GitDiffOptions,
+    page?: GitDiffPageRequest
+  ) => Promise<GitDiffPage>;
+  gitLog?: (opts: GitLogOptions) => Promise<GitLogResult>;
+  gitFileAtRef?: (opts: GitFileAtRefOptions) => Promise<GitFileContent>;
+  gitShow?: (opts: GitShowOptions) => Promise<GitShowResult>;
+  gitCreateBranch?: (opts: {
+    worktreePath: string;
+    name: string;
+    startPoint?: string;
+    force?: boolean;
+    checkout?: boolean;
+  }) => Promise<GitBranchOpResult>;
+  gitCheckout?: (opts: {
+    worktreePath: string;
+    target: string;
+  }) => Promise<GitBranchOpResult>;
+  gitMergeFfOnly?: (opts: {
+    worktreePath: string;
+    target: string;
+  }) => Promise<GitBranchOpResult>;
+  gitMergePreview?: (opts: GitMergePreviewOptions) => Promise<GitMergePreview>;
+  gitWatch?: (
+    opts: GitWatchOptions,
+    callback: (event: GitWatchEvent) => void
+  ) => GitWatchHandle;
+  gitStatus?: (opts: GitStatusOptions) => Promise<GitStatusResult>;
+  gitDiffWorkingTree?: (
+    opts: GitDiffWorkingTreeOptions
+  ) => Promise<ReplaceDiffEntry[]>;
   gitListRemoteBranches?: (opts: {
     repoFullName?: string;
     repoUrl?: string;
@@ -102,6 +337,138 @@ export async function gitDiff(opts: GitDiffOptions): Promise<ReplaceDiffEntry[]>
   return mod.gitDiff(opts);
 }
 
+export async function gitDiffPage(
+  opts: GitDiffOptions,
+  page?: GitDiffPageRequest
+): Promise<GitDiffPage> {
+  const mod = loadNativeGit();
+  if (!mod?.gitDiffPage) {
+    throw new Error(
+      "Native gitDiffPage not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitDiffPage(opts, page);
+}
+
+export async function gitStatus(
+  opts: GitStatusOptions
+): Promise<GitStatusResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitStatus) {
+    throw new Error("Native gitStatus not available; rebuild @cmux/native-core");
+  }
+  return mod.gitStatus(opts);
+}
+
+export async function gitDiffWorkingTree(
+  opts: GitDiffWorkingTreeOptions
+): Promise<ReplaceDiffEntry[]> {
+  const mod = loadNativeGit();
+  if (!mod?.gitDiffWorkingTree) {
+    throw new Error(
+      "Native gitDiffWorkingTree not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitDiffWorkingTree(opts);
+}
+
+export async function gitLog(opts: GitLogOptions): Promise<GitLogResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitLog) {
+    throw new Error("Native gitLog not available; rebuild @cmux/native-core");
+  }
+  return mod.gitLog(opts);
+}
+
+export async function gitFileAtRef(
+  opts: GitFileAtRefOptions
+): Promise<GitFileContent> {
+  const mod = loadNativeGit();
+  if (!mod?.gitFileAtRef) {
+    throw new Error(
+      "Native gitFileAtRef not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitFileAtRef(opts);
+}
+
+export async function gitShow(opts: GitShowOptions): Promise<GitShowResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitShow) {
+    throw new Error("Native gitShow not available; rebuild @cmux/native-core");
+  }
+  return mod.gitShow(opts);
+}
+
+export async function gitCreateBranch(opts: {
+  worktreePath: string;
+  name: string;
+  startPoint?: string;
+  force?: boolean;
+  checkout?: boolean;
+}): Promise<GitBranchOpResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitCreateBranch) {
+    throw new Error(
+      "Native gitCreateBranch not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitCreateBranch(opts).catch((err) => {
+    throw toBranchError(err);
+  });
+}
+
+export async function gitCheckout(opts: {
+  worktreePath: string;
+  target: string;
+}): Promise<GitBranchOpResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitCheckout) {
+    throw new Error("Native gitCheckout not available; rebuild @cmux/native-core");
+  }
+  return mod.gitCheckout(opts).catch((err) => {
+    throw toBranchError(err);
+  });
+}
+
+export async function gitMergeFfOnly(opts: {
+  worktreePath: string;
+  target: string;
+}): Promise<GitBranchOpResult> {
+  const mod = loadNativeGit();
+  if (!mod?.gitMergeFfOnly) {
+    throw new Error(
+      "Native gitMergeFfOnly not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitMergeFfOnly(opts).catch((err) => {
+    throw toBranchError(err);
+  });
+}
+
+export async function gitMergePreview(
+  opts: GitMergePreviewOptions
+): Promise<GitMergePreview> {
+  const mod = loadNativeGit();
+  if (!mod?.gitMergePreview) {
+    throw new Error(
+      "Native gitMergePreview not available; rebuild @cmux/native-core"
+    );
+  }
+  return mod.gitMergePreview(opts);
+}
+
+export function gitWatch(
+  opts: GitWatchOptions,
+  callback: (event: GitWatchEvent) => void
+): GitWatchHandle {
+  const mod = loadNativeGit();
+  if (!mod?.gitWatch) {
+    throw new Error("Native gitWatch not available; rebuild @cmux/native-core");
+  }
+  return mod.gitWatch(opts, callback);
+}
+
 export async function listRemoteBranches(opts: {
   repoFullName?: string;
   repoUrl?: string;
@@ -0,0 +1,132 @@
+//! Accept throughput with one accept loop versus one `SO_REUSEPORT` socket
+//! per core, under many short preview-style requests (one connection each).
+//!
+//! Run with `cargo bench --bench accept`. `CMUX_BENCH_SECS` sets how long
+//! each configuration runs, `CMUX_BENCH_CLIENTS` how many clients connect
+//! concurrently and `CMUX_BENCH_ACCEPT_TASKS` the sharded task count
+//! (default: one per core, at least 4).
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cmux_proxy::listener::ListenerOptions;
+use cmux_proxy::ProxyConfig;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Upstream answering every request with a tiny body and closing.
+async fn start_upstream() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let mut seen = Vec::new();
+                while !seen.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => seen.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .await;
+            });
+        }
+    });
+    addr
+}
+
+async fn run(accept_tasks: usize, upstream: SocketAddr, clients: u64, duration: Duration) -> f64 {
+    let (tx, rx) = oneshot::channel::<()>();
+    let cfg = ProxyConfig {
+        listener: ListenerOptions {
+            accept_tasks,
+            nodelay: true,
+            ..ListenerOptions::default()
+        },
+        ..ProxyConfig::default()
+    };
+    let (proxy, handle) = cmux_proxy::spawn_proxy(
+        cfg,
+        Box::pin(async move {
+            let _ = rx.await;
+        }),
+    );
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: bench\r\nX-Cmux-Port-Internal: {}\r\nConnection: close\r\n\r\n",
+        upstream.port()
+    );
+
+    let completed = Arc::new(AtomicU64::new(0));
+    let deadline = Instant::now() + duration;
+    let workers: Vec<_> = (0..clients)
+        .map(|_| {
+            let request = request.clone();
+            let completed = completed.clone();
+            tokio::spawn(async move {
+                let mut response = Vec::with_capacity(256);
+                while Instant::now() < deadline {
+                    let Ok(mut stream) = TcpStream::connect(proxy).await else {
+                        continue;
+                    };
+                    let _ = stream.set_nodelay(true);
+                    response.clear();
+                    if stream.write_all(request.as_bytes()).await.is_ok()
+                        && stream.read_to_end(&mut response).await.is_ok()
+                        && response.starts_with(b"HTTP/1.1 200")
+                    {
+                        completed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.await;
+    }
+
+    let _ = tx.send(());
+    let _ = handle.await;
+    completed.load(Ordering::Relaxed) as f64 / duration.as_secs_f64()
+}
+
+fn main() {
+    let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let duration = Duration::from_secs(env_or("CMUX_BENCH_SECS", 3));
+    let clients = env_or("CMUX_BENCH_CLIENTS", 64);
+    let sharded = env_or("CMUX_BENCH_ACCEPT_TASKS", cores.max(4) as u64) as usize;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(cores)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let upstream = start_upstream().await;
+        let mut baseline = None;
+        for accept_tasks in [1, sharded] {
+            let rate = run(accept_tasks, upstream, clients, duration).await;
+            let gain = baseline.map_or(String::new(), |base: f64| {
+                format!(" ({:+.1}%)", (rate / base - 1.0) * 100.0)
+            });
+            println!("accept_tasks={accept_tasks:<3} {rate:>10.0} req/s{gain}");
+            baseline.get_or_insert(rate);
+            // Let TIME_WAIT sockets from this round settle a little.
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    });
+}
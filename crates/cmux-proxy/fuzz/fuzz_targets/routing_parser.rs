@@ -0,0 +1,30 @@
+//! Feeds arbitrary bytes to the Host and port parsers.
+//!
+//! Run with `cargo +nightly fuzz run routing_parser` from `crates/cmux-proxy`.
+//! Besides not panicking, every accepted Host must describe a route that
+//! parses back to itself.
+
+#![no_main]
+
+use cmux_proxy::routing::parser::{parse_host, parse_port, split_host_port, HOST_SUFFIX};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(port) = parse_port(input) {
+        assert!(port > 0);
+        assert_eq!(parse_port(&port.to_string()), Ok(port));
+    }
+
+    if let Ok(route) = parse_host(input) {
+        assert!(split_host_port(input).is_ok());
+        assert!(route.port > 0);
+        assert!(!route.workspace.is_empty());
+        assert_eq!(route.workspace, route.workspace.to_ascii_lowercase());
+        let again = format!("{}-{}{}", route.workspace, route.port, HOST_SUFFIX);
+        assert_eq!(parse_host(&again), Ok(route));
+    }
+});
@@ -0,0 +1,46 @@
+//! Access log hook for embedding the proxy.
+//!
+//! The binary logs requests through `tracing`; an embedder (such as the
+//! Electron app's native module) can also set [`ProxyConfig::access_log`]
+//! to receive one [`AccessLogEvent`] per request.
+//!
+//! [`ProxyConfig::access_log`]: crate::ProxyConfig::access_log
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// One handled request. For WebSocket upgrades and CONNECT tunnels this is
+/// emitted once the handshake is answered, not when the tunnel closes.
+#[derive(Clone, Debug)]
+pub struct AccessLogEvent {
+    pub client: SocketAddr,
+    pub method: String,
+    pub path: String,
+    pub workspace: Option<String>,
+    pub port: Option<u16>,
+    pub status: u16,
+    pub duration: Duration,
+}
+
+/// Callback invoked for every request. Runs on the proxy's tasks, so it
+/// should hand the event off rather than block.
+#[derive(Clone)]
+pub struct AccessLog(Arc<dyn Fn(AccessLogEvent) + Send + Sync>);
+
+impl AccessLog {
+    pub fn new(callback: impl Fn(AccessLogEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn emit(&self, event: AccessLogEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AccessLog(..)")
+    }
+}
@@ -0,0 +1,252 @@
+//! Boot hold: park early requests while a workspace is starting.
+//!
+//! Right after a workspace is created its dev servers aren't listening yet, so
+//! the first requests would all get 502s. While a hold is active for a
+//! workspace, requests to it wait until their upstream port accepts TCP
+//! connections and are then sent on in arrival order. A hold ends when its
+//! time runs out or it is released; at most `max_requests` wait at once and
+//! any more are turned away with 503.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+const PROBE_INTERVAL: Duration = Duration::from_millis(200);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Defaults for holds activated without explicit limits, and the most a
+/// request activating one may ask for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootHoldConfig {
+    pub max_wait: Duration,
+    pub max_requests: usize,
+    pub max_wait_limit: Duration,
+    pub max_requests_limit: usize,
+}
+
+impl Default for BootHoldConfig {
+    fn default() -> Self {
+        Self {
+            max_wait: Duration::from_secs(30),
+            max_requests: 100,
+            max_wait_limit: Duration::from_secs(10 * 60),
+            max_requests_limit: 1000,
+        }
+    }
+}
+
+/// A hold's `max_wait` ends too far in the future for the clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WaitOutOfRange;
+
+impl fmt::Display for WaitOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("boot hold wait is out of range")
+    }
+}
+
+impl std::error::Error for WaitOutOfRange {}
+
+/// What happened to a request that arrived during a hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoldOutcome {
+    /// No hold for this workspace; proceed immediately.
+    NotHeld,
+    /// The upstream came up while the request waited.
+    Ready,
+    /// The hold ran out before the upstream came up.
+    Expired,
+    /// Too many requests are already waiting.
+    Full,
+}
+
+/// A snapshot of an active hold.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HoldStatus {
+    pub remaining: Duration,
+    pub max_requests: usize,
+    pub waiting: usize,
+    pub ready_ports: Vec<u16>,
+}
+
+struct Hold {
+    expires: Instant,
+    max_requests: usize,
+    waiting: Mutex<usize>,
+    ready_ports: Mutex<HashSet<u16>>,
+    /// Fair lock: waiters are let through in the order they arrived.
+    queue: tokio::sync::Mutex<()>,
+}
+
+/// Active holds keyed by upstream host. Clones share the same holds, so an
+/// embedder can keep one to activate holds on a running proxy.
+#[derive(Clone, Default)]
+pub struct BootHolds {
+    holds: Arc<Mutex<HashMap<String, Arc<Hold>>>>,
+}
+
+impl fmt::Debug for BootHolds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let holds = self.holds.lock().unwrap();
+        f.debug_set().entries(holds.keys()).finish()
+    }
+}
+
+/// Removes the waiter count for a request when it stops waiting.
+struct Waiting<'a>(&'a Hold);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        *self.0.waiting.lock().unwrap() -= 1;
+    }
+}
+
+impl BootHolds {
+    /// Hold requests to `upstream_host` for up to `max_wait`. Replaces any
+    /// hold already active for that host.
+    pub fn activate(
+        &self,
+        upstream_host: &str,
+        max_wait: Duration,
+        max_requests: usize,
+    ) -> Result<(), WaitOutOfRange> {
+        let expires = Instant::now().checked_add(max_wait).ok_or(WaitOutOfRange)?;
+        let hold = Arc::new(Hold {
+            expires,
+            max_requests,
+            waiting: Mutex::new(0),
+            ready_ports: Mutex::new(HashSet::new()),
+            queue: tokio::sync::Mutex::new(()),
+        });
+        self.holds
+            .lock()
+            .unwrap()
+            .insert(upstream_host.to_string(), hold);
+        Ok(())
+    }
+
+    /// End the hold for `upstream_host`. Requests already waiting keep waiting
+    /// for their upstream until the hold would have expired.
+    pub fn release(&self, upstream_host: &str) -> bool {
+        self.holds.lock().unwrap().remove(upstream_host).is_some()
+    }
+
+    fn active(&self, upstream_host: &str) -> Option<Arc<Hold>> {
+        let mut holds = self.holds.lock().unwrap();
+        let now = Instant::now();
+        holds.retain(|_, hold| hold.expires > now);
+        holds.get(upstream_host).cloned()
+    }
+
+    pub fn status(&self, upstream_host: &str) -> Option<HoldStatus> {
+        let hold = self.active(upstream_host)?;
+        let mut ready_ports: Vec<u16> = hold.ready_ports.lock().unwrap().iter().copied().collect();
+        ready_ports.sort_unstable();
+        let waiting = *hold.waiting.lock().unwrap();
+        Some(HoldStatus {
+            remaining: hold.expires.saturating_duration_since(Instant::now()),
+            max_requests: hold.max_requests,
+            waiting,
+            ready_ports,
+        })
+    }
+
+    /// Wait, if a hold is active, until `upstream_host:port` accepts
+    /// connections or the hold expires.
+    pub async fn wait(&self, upstream_host: &str, port: u16) -> HoldOutcome {
+        let Some(hold) = self.active(upstream_host) else {
+            return HoldOutcome::NotHeld;
+        };
+        if hold.ready_ports.lock().unwrap().contains(&port) {
+            return HoldOutcome::Ready;
+        }
+        {
+            let mut waiting = hold.waiting.lock().unwrap();
+            if *waiting >= hold.max_requests {
+                return HoldOutcome::Full;
+            }
+            *waiting += 1;
+        }
+        let _waiting = Waiting(&hold);
+
+        let Ok(_turn) = tokio::time::timeout_at(hold.expires, hold.queue.lock()).await else {
+            return HoldOutcome::Expired;
+        };
+        loop {
+            if hold.ready_ports.lock().unwrap().contains(&port) {
+                return HoldOutcome::Ready;
+            }
+            let probe =
+                tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect((upstream_host, port)));
+            if let Ok(Ok(_)) = probe.await {
+                hold.ready_ports.lock().unwrap().insert(port);
+                return HoldOutcome::Ready;
+            }
+            if Instant::now() + PROBE_INTERVAL >= hold.expires {
+                return HoldOutcome::Expired;
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn requests_wait_until_the_upstream_listens() {
+        let holds = BootHolds::default();
+        assert_eq!(holds.wait("127.0.0.1", 1).await, HoldOutcome::NotHeld);
+
+        // Reserve a port, then free it so nothing listens there yet.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        holds
+            .activate("127.0.0.1", Duration::from_secs(10), 10)
+            .unwrap();
+
+        let waiter = {
+            let holds = holds.clone();
+            tokio::spawn(async move { holds.wait("127.0.0.1", port).await })
+        };
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(holds.status("127.0.0.1").unwrap().waiting, 1);
+
+        let _listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        assert_eq!(waiter.await.unwrap(), HoldOutcome::Ready);
+        assert_eq!(holds.status("127.0.0.1").unwrap().ready_ports, [port]);
+        assert!(holds.release("127.0.0.1"));
+        assert!(holds.status("127.0.0.1").is_none());
+    }
+
+    #[tokio::test]
+    async fn full_and_expired_holds() {
+        let holds = BootHolds::default();
+        holds
+            .activate("127.0.0.1", Duration::from_millis(400), 1)
+            .unwrap();
+        let first = {
+            let holds = holds.clone();
+            tokio::spawn(async move { holds.wait("127.0.0.1", 1).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(holds.wait("127.0.0.1", 1).await, HoldOutcome::Full);
+        assert_eq!(first.await.unwrap(), HoldOutcome::Expired);
+
+        assert_eq!(
+            holds.activate("127.0.0.1", Duration::MAX, 1),
+            Err(WaitOutOfRange)
+        );
+    }
+}
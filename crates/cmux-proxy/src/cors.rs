@@ -0,0 +1,280 @@
+//! Opt-in CORS for workspace dev servers.
+//!
+//! Dev APIs inside a workspace rarely send CORS headers, so a preview page
+//! served from another origin can't call them. A [`CorsRule`] matched by
+//! workspace and port makes the proxy answer preflights itself and add the
+//! `Access-Control-Allow-*` headers to the upstream's responses, replacing any
+//! the upstream sent.
+
+use std::str::FromStr;
+
+use http::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use http::{HeaderMap, HeaderValue, Method};
+
+const DEFAULT_ALLOW_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS";
+const DEFAULT_MAX_AGE_SECS: u64 = 600;
+
+/// CORS policy for requests to a workspace and port. `None` matches any.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorsRule {
+    pub workspace: Option<String>,
+    pub port: Option<u16>,
+    /// Allowed origins; empty or `*` allows any.
+    pub allow_origins: Vec<String>,
+    pub allow_methods: String,
+    /// Allowed request headers; `None` echoes what the preflight asked for.
+    pub allow_headers: Option<String>,
+    pub expose_headers: Option<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: u64,
+}
+
+impl CorsRule {
+    /// A permissive rule: any origin, the common methods, any headers.
+    pub fn new(workspace: Option<String>, port: Option<u16>) -> Self {
+        Self {
+            workspace,
+            port,
+            allow_origins: Vec::new(),
+            allow_methods: DEFAULT_ALLOW_METHODS.to_string(),
+            allow_headers: None,
+            expose_headers: None,
+            allow_credentials: false,
+            max_age_secs: DEFAULT_MAX_AGE_SECS,
+        }
+    }
+
+    pub fn matches(&self, workspace: Option<&str>, port: Option<u16>) -> bool {
+        let workspace_ok = match (&self.workspace, workspace) {
+            (None, _) => true,
+            (Some(want), Some(got)) => {
+                let got = got.rsplit('/').next().unwrap_or(got);
+                want.eq_ignore_ascii_case(got)
+            }
+            (Some(_), None) => false,
+        };
+        let port_ok = self.port.is_none() || self.port == port;
+        workspace_ok && port_ok
+    }
+
+    /// The `Access-Control-Allow-Origin` value for `origin`, if it's allowed.
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        let any = self.allow_origins.is_empty() || self.allow_origins.iter().any(|o| o == "*");
+        if any {
+            // Browsers reject `*` on credentialed requests, so echo the origin instead.
+            return Some(if self.allow_credentials {
+                origin.clone()
+            } else {
+                HeaderValue::from_static("*")
+            });
+        }
+        let origin_str = origin.to_str().ok()?;
+        self.allow_origins
+            .iter()
+            .any(|o| o.eq_ignore_ascii_case(origin_str))
+            .then(|| origin.clone())
+    }
+
+    fn insert_origin_headers(&self, allow_origin: HeaderValue, headers: &mut HeaderMap) {
+        if allow_origin != "*" {
+            headers.append(VARY, HeaderValue::from_static("Origin"));
+        }
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.allow_credentials {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    /// Headers answering a preflight request, or `None` if `req_headers`
+    /// aren't a preflight from an allowed origin.
+    pub fn preflight_headers(&self, method: &Method, req_headers: &HeaderMap) -> Option<HeaderMap> {
+        if method != Method::OPTIONS || !req_headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+            return None;
+        }
+        let allow_origin = self.allow_origin(req_headers.get(ORIGIN)?)?;
+
+        let mut headers = HeaderMap::new();
+        self.insert_origin_headers(allow_origin, &mut headers);
+        if let Ok(methods) = HeaderValue::from_str(&self.allow_methods) {
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        let allow_headers = match &self.allow_headers {
+            Some(list) => HeaderValue::from_str(list).ok(),
+            None => req_headers.get(ACCESS_CONTROL_REQUEST_HEADERS).cloned(),
+        };
+        if let Some(allow_headers) = allow_headers {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+        }
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age_secs));
+        Some(headers)
+    }
+
+    /// Add CORS headers to a proxied response for a request from `origin`.
+    pub fn apply(&self, origin: Option<&HeaderValue>, resp_headers: &mut HeaderMap) {
+        let Some(allow_origin) = origin.and_then(|o| self.allow_origin(o)) else {
+            return;
+        };
+        resp_headers.remove(ACCESS_CONTROL_ALLOW_ORIGIN);
+        resp_headers.remove(ACCESS_CONTROL_ALLOW_CREDENTIALS);
+        self.insert_origin_headers(allow_origin, resp_headers);
+        if let Some(expose) = self
+            .expose_headers
+            .as_deref()
+            .and_then(|v| HeaderValue::from_str(v).ok())
+        {
+            resp_headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+        }
+    }
+}
+
+/// The first rule matching a request's workspace and port.
+pub fn find_rule<'a>(
+    rules: &'a [CorsRule],
+    workspace: Option<&str>,
+    port: Option<u16>,
+) -> Option<&'a CorsRule> {
+    rules.iter().find(|rule| rule.matches(workspace, port))
+}
+
+/// Parses `<workspace|*>:<port|*>[;key=value...]`, e.g.
+/// `workspace-1:3000;origin=https://app.example,https://cmux.local;credentials`.
+/// Keys: `origin`, `methods`, `headers`, `expose`, `credentials`, `max-age`.
+impl FromStr for CorsRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(';');
+        let target = parts.next().unwrap_or_default().trim();
+        let (workspace, port) = target
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected <workspace>:<port>, got {target:?}"))?;
+        let workspace = match workspace.trim() {
+            "" => return Err("workspace cannot be empty (use * for any)".to_string()),
+            "*" => None,
+            ws => Some(ws.to_string()),
+        };
+        let port = match port.trim() {
+            "*" => None,
+            p => Some(p.parse().map_err(|_| format!("invalid port: {p:?}"))?),
+        };
+        let mut rule = CorsRule::new(workspace, port);
+
+        for option in parts.map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), Some(v.trim())))
+                .unwrap_or((option, None));
+            let value_for = |key: &str| value.ok_or_else(|| format!("{key} needs a value"));
+            match key {
+                "origin" => {
+                    rule.allow_origins = value_for(key)?
+                        .split(',')
+                        .map(|o| o.trim().trim_end_matches('/').to_string())
+                        .filter(|o| !o.is_empty())
+                        .collect();
+                }
+                "methods" => rule.allow_methods = value_for(key)?.to_string(),
+                "headers" => rule.allow_headers = Some(value_for(key)?.to_string()),
+                "expose" => rule.expose_headers = Some(value_for(key)?.to_string()),
+                "credentials" => {
+                    rule.allow_credentials = match value.unwrap_or("true") {
+                        "true" => true,
+                        "false" => false,
+                        v => return Err(format!("invalid credentials value: {v:?}")),
+                    }
+                }
+                "max-age" => {
+                    let v = value_for(key)?;
+                    rule.max_age_secs = v.parse().map_err(|_| format!("invalid max-age: {v:?}"))?;
+                }
+                other => return Err(format!("unknown CORS option: {other:?}")),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preflight(origin: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN, origin.parse().unwrap());
+        headers.insert(ACCESS_CONTROL_REQUEST_METHOD, "POST".parse().unwrap());
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_HEADERS,
+            "content-type".parse().unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rule: CorsRule = "workspace-1:3000;origin=https://a.test/, https://b.test;credentials"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.workspace.as_deref(), Some("workspace-1"));
+        assert_eq!(rule.port, Some(3000));
+        assert_eq!(rule.allow_origins, ["https://a.test", "https://b.test"]);
+        assert!(rule.allow_credentials);
+
+        let any: CorsRule = "*:*".parse().unwrap();
+        assert_eq!(any, CorsRule::new(None, None));
+        assert!(any.matches(None, Some(8080)));
+
+        assert!("workspace-1".parse::<CorsRule>().is_err());
+        assert!("*:http".parse::<CorsRule>().is_err());
+        assert!("*:*;bogus=1".parse::<CorsRule>().is_err());
+    }
+
+    #[test]
+    fn matches_workspace_and_port() {
+        let rule = CorsRule::new(Some("workspace-1".into()), Some(3000));
+        assert!(rule.matches(Some("/root/workspace-1"), Some(3000)));
+        assert!(!rule.matches(Some("workspace-2"), Some(3000)));
+        assert!(!rule.matches(Some("workspace-1"), Some(3001)));
+        assert!(!rule.matches(None, Some(3000)));
+    }
+
+    #[test]
+    fn answers_preflight_for_allowed_origins_only() {
+        let rule: CorsRule = "*:3000;origin=https://a.test".parse().unwrap();
+        let headers = rule
+            .preflight_headers(&Method::OPTIONS, &preflight("https://a.test"))
+            .unwrap();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://a.test");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[VARY], "Origin");
+
+        assert!(rule
+            .preflight_headers(&Method::OPTIONS, &preflight("https://evil.test"))
+            .is_none());
+        assert!(rule
+            .preflight_headers(&Method::GET, &preflight("https://a.test"))
+            .is_none());
+    }
+
+    #[test]
+    fn apply_replaces_upstream_cors_headers() {
+        let rule = CorsRule::new(None, None);
+        let mut resp = HeaderMap::new();
+        resp.insert(
+            ACCESS_CONTROL_ALLOW_ORIGIN,
+            "https://other.test".parse().unwrap(),
+        );
+        rule.apply(Some(&"https://a.test".parse().unwrap()), &mut resp);
+        assert_eq!(resp[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        let mut untouched = HeaderMap::new();
+        rule.apply(None, &mut untouched);
+        assert!(untouched.is_empty());
+    }
+}
@@ -1,13 +1,26 @@
+pub mod access_log;
+pub mod boot_hold;
+pub mod cors;
+pub mod listener;
+pub mod parent_proxy;
+pub mod ports;
+pub mod routing;
+pub mod slow_client;
+pub mod telemetry;
+pub mod throttle;
+pub mod validation;
+
 use std::{
     cmp::min,
     convert::Infallible,
     future::Future,
     io,
-    net::{SocketAddr, TcpListener as StdTcpListener},
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
     pin::Pin,
     str::FromStr,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bytes::Bytes;
@@ -22,17 +35,32 @@ use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
 use std::sync::Arc;
 use tokio::io::{copy_bidirectional, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::Notify;
+use tokio::sync::watch;
 use tokio::task::{JoinHandle, JoinSet};
-use tracing::{error, info, warn};
+use tracing::{error, info, warn, Instrument, Span};
+
+use access_log::{AccessLog, AccessLogEvent};
+use boot_hold::{BootHoldConfig, BootHolds, HoldOutcome};
+use cors::CorsRule;
+use listener::ListenerOptions;
+use parent_proxy::{ParentProxy, UpstreamConnector};
+use routing::parser::{self, HostRoute};
+use slow_client::{Admission, Handshake, SlowClientConfig, SlowClientStats};
+use throttle::{Bandwidth, BandwidthConfig, Limiter, Throttled, ThrottledBody, TokenBucket};
+use validation::RequestError;
 
 use http::header::{CONNECTION, HOST, UPGRADE};
 
 type BoxBody =
     http_body_util::combinators::BoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type UpstreamClient = Client<UpstreamConnector, BoxBody>;
 const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 const HOST_OVERRIDE_HEADER: &str = "X-Cmux-Host-Override";
+const WORKSPACE_HEADER: &str = "X-Cmux-Workspace-Internal";
+const PORTS_PATH: &str = "/__cmux/ports";
+const BOOT_HOLD_PATH: &str = "/__cmux/boot-hold";
+const STATS_PATH: &str = "/__cmux/stats";
 const HTTP2_KEEP_ALIVE_INTERVAL_SECS: u64 = 30;
 const HTTP2_KEEP_ALIVE_TIMEOUT_SECS: u64 = 10;
 
@@ -104,6 +132,15 @@ fn configure_http_client_builder(builder: &mut impl ClientKeepAliveConfig) {
     builder.set_http2_keep_alive_while_idle(true);
 }
 
+/// HTTP client for upstream requests, through the parent proxy if configured.
+fn upstream_client(cfg: &ProxyConfig) -> UpstreamClient {
+    let mut connector = HttpConnector::new();
+    connector.set_connect_timeout(Some(Duration::from_secs(5)));
+    let mut client_builder = Client::builder(TokioExecutor::new());
+    configure_http_client_builder(&mut client_builder);
+    client_builder.build(UpstreamConnector::new(connector, cfg.parent_proxy.clone()))
+}
+
 fn configure_http1_server_builder(builder: &mut impl Http1ServerConfig) {
     builder.set_keep_alive(true);
     builder.set_preserve_header_case(true);
@@ -119,6 +156,36 @@ fn configure_http2_server_builder(builder: &mut impl Http2ServerConfig) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn admin_routes_need_loopback_or_the_token() {
+        let remote = SocketAddr::from(([10, 0, 0, 5], 4000));
+        let loopback = SocketAddr::from(([127, 0, 0, 1], 4000));
+        let mapped: SocketAddr = "[::ffff:127.0.0.1]:4000".parse().unwrap();
+        let none = HeaderMap::new();
+        let mut cfg = ProxyConfig::default();
+        assert!(authorize_admin(&cfg, loopback, &none).is_ok());
+        assert!(authorize_admin(&cfg, mapped, &none).is_ok());
+        let denied = authorize_admin(&cfg, remote, &none).unwrap_err();
+        assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+
+        cfg.admin_token = Some("s3cret".into());
+        let mut good = HeaderMap::new();
+        good.insert(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cret"),
+        );
+        let mut bad = HeaderMap::new();
+        bad.insert(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer s3cres"),
+        );
+        assert!(authorize_admin(&cfg, remote, &good).is_ok());
+        for headers in [&none, &bad] {
+            let resp = authorize_admin(&cfg, loopback, headers).unwrap_err();
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
     #[derive(Default)]
     struct RecordingClientBuilder {
         pool_max_idle: Option<usize>,
@@ -293,192 +360,345 @@ fn incoming_to_box(b: Incoming) -> BoxBody {
     b.map_err(|e| -> BoxError { Box::new(e) }).boxed()
 }
 
+fn throttle_body(body: BoxBody, limiter: Limiter) -> BoxBody {
+    if limiter.is_unlimited() {
+        body
+    } else {
+        ThrottledBody::new(body, limiter).boxed()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ProxyConfig {
     pub listen: SocketAddr,
     pub upstream_host: String,
     pub allow_default_upstream: bool,
+    pub bandwidth: BandwidthConfig,
+    /// CORS rules; the first one matching a request's workspace and port applies.
+    pub cors: Vec<CorsRule>,
+    /// Ports probed by `/__cmux/ports` (also filters what `/proc/net/tcp` reports).
+    pub port_scan_range: RangeInclusive<u16>,
+    /// Called once per handled request, in addition to the `tracing` logs.
+    pub access_log: Option<AccessLog>,
+    /// Workspaces whose requests wait for their upstream to come up.
+    pub boot_holds: BootHolds,
+    /// Limits for holds activated through `/__cmux/boot-hold` without their own.
+    pub boot_hold: BootHoldConfig,
+    /// Proxy that upstream connections are tunnelled through, if any.
+    pub parent_proxy: Option<ParentProxy>,
+    /// Backlog, socket options and accept tasks for each listen address.
+    pub listener: ListenerOptions,
+    /// Limits on clients that connect but are slow to send requests.
+    pub slow_clients: SlowClientConfig,
+    /// Counts of connections closed by `slow_clients`, served at `/__cmux/stats`.
+    pub slow_client_stats: SlowClientStats,
+    /// Bearer token for the `/__cmux/*` admin routes. Without one they only
+    /// answer clients connecting over loopback.
+    pub admin_token: Option<String>,
 }
 
-pub fn spawn_proxy<S>(cfg: ProxyConfig, mut shutdown: S) -> (SocketAddr, JoinHandle<()>)
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 0)),
+            upstream_host: "127.0.0.1".to_string(),
+            allow_default_upstream: true,
+            bandwidth: BandwidthConfig::default(),
+            cors: Vec::new(),
+            port_scan_range: ports::DEFAULT_SCAN_RANGE,
+            access_log: None,
+            boot_holds: BootHolds::default(),
+            boot_hold: BootHoldConfig::default(),
+            parent_proxy: None,
+            listener: ListenerOptions::default(),
+            slow_clients: SlowClientConfig::default(),
+            slow_client_stats: SlowClientStats::default(),
+            admin_token: None,
+        }
+    }
+}
+
+/// Bandwidth state for one client connection: its own bucket plus access to
+/// the per-workspace buckets shared with other connections.
+#[derive(Clone)]
+struct ConnectionThrottle {
+    bandwidth: Arc<Bandwidth>,
+    connection: Option<Arc<TokenBucket>>,
+}
+
+impl ConnectionThrottle {
+    fn new(bandwidth: Arc<Bandwidth>) -> Self {
+        let connection = bandwidth.connection_bucket();
+        Self {
+            bandwidth,
+            connection,
+        }
+    }
+
+    /// Limiter for traffic to `upstream_host`; each workspace has its own host.
+    fn limiter(&self, upstream_host: &str) -> Limiter {
+        Limiter::new(
+            self.connection
+                .iter()
+                .cloned()
+                .chain(self.bandwidth.workspace_bucket(upstream_host)),
+        )
+    }
+}
+
+pub fn spawn_proxy<S>(cfg: ProxyConfig, shutdown: S) -> (SocketAddr, JoinHandle<()>)
 where
     S: Future<Output = ()> + Send + 'static + Unpin,
 {
-    // Hyper client for proxying HTTP/1.1
-    let mut connector = HttpConnector::new();
-    connector.set_connect_timeout(Some(Duration::from_secs(5)));
-    let mut client_builder = Client::builder(TokioExecutor::new());
-    configure_http_client_builder(&mut client_builder);
-    let client: Client<HttpConnector, BoxBody> = client_builder.build(connector);
-
-    let listen = cfg.listen;
-    let std_listener = StdTcpListener::bind(listen).expect("bind");
-    std_listener.set_nonblocking(true).expect("set nonblocking");
-    let listen_addr = std_listener.local_addr().expect("local addr");
-    let listener = TcpListener::from_std(std_listener).expect("to tokio listener");
-
-    let handle = tokio::spawn(async move {
-        info!("proxy listening on {}", listen_addr);
+    try_spawn_proxy(cfg, shutdown).expect("bind")
+}
 
-        loop {
-            tokio::select! {
-                result = listener.accept() => {
-                    match result {
-                        Ok((stream, remote_addr)) => {
-                            let client = client.clone();
-                            let cfg = cfg.clone();
-                            tokio::spawn(async move {
-                                if let Err(err) = serve_client_stream(stream, remote_addr, client, cfg).await {
-                                    error!(%err, "connection error");
-                                }
-                            });
-                        }
-                        Err(e) => {
-                            error!(%e, "accept error");
-                        }
-                    }
-                }
-                _ = &mut shutdown => {
-                    info!("shutting down proxy");
-                    break;
-                }
-            }
-        }
-    });
+/// Like [`spawn_proxy`], but returns an error instead of panicking when the
+/// listen address can't be bound.
+pub fn try_spawn_proxy<S>(cfg: ProxyConfig, shutdown: S) -> io::Result<(SocketAddr, JoinHandle<()>)>
+where
+    S: Future<Output = ()> + Send + 'static + Unpin,
+{
+    // Hyper client for proxying HTTP/1.1
+    let client = upstream_client(&cfg);
+    let bandwidth = Arc::new(Bandwidth::new(cfg.bandwidth.clone()));
+    let admission = Arc::new(Admission::new(
+        &cfg.slow_clients,
+        cfg.slow_client_stats.clone(),
+    ));
+
+    let listeners = cfg.listener.bind(cfg.listen)?;
+    let listen_addr = listeners[0].local_addr()?;
+    let mut cfg = cfg;
+    cfg.listen = listen_addr;
+
+    let shutdown = shutdown_signal(shutdown);
+    let mut join_set: JoinSet<()> = JoinSet::new();
+    for listener in listeners {
+        join_set.spawn(accept_loop(
+            listener,
+            client.clone(),
+            cfg.clone(),
+            bandwidth.clone(),
+            admission.clone(),
+            shutdown.clone(),
+        ));
+    }
+    let handle = tokio::spawn(async move { while join_set.join_next().await.is_some() {} });
     // Return the actual bound address so callers can discover OS-assigned ports
-    (listen_addr, handle)
+    Ok((listen_addr, handle))
 }
 
-/// Start the proxy on multiple addresses. Returns the bound addresses actually used and a handle
-/// that completes when all servers exit (after shutdown is signaled).
+/// Start the proxy on multiple addresses. `cfg.listen` is ignored; each listener uses its own
+/// address. Returns the bound addresses actually used and a handle that completes when all
+/// servers exit (after shutdown is signaled).
 pub fn spawn_proxy_multi<S>(
     listens: Vec<SocketAddr>,
-    upstream_host: String,
-    allow_default_upstream: bool,
+    cfg: ProxyConfig,
     shutdown: S,
 ) -> (Vec<SocketAddr>, JoinHandle<()>)
 where
     S: Future<Output = ()> + Send + 'static,
 {
     // Prepare shared client and shutdown notifier
-    let mut connector = HttpConnector::new();
-    connector.set_connect_timeout(Some(Duration::from_secs(5)));
-    let mut client_builder = Client::builder(TokioExecutor::new());
-    configure_http_client_builder(&mut client_builder);
-    let client: Client<HttpConnector, BoxBody> = client_builder.build(connector);
-
-    let notify = Arc::new(Notify::new());
-    let notify_clone = notify.clone();
-    tokio::spawn(async move {
-        shutdown.await;
-        notify_clone.notify_waiters();
-    });
+    let client = upstream_client(&cfg);
+    // Shared across listeners so workspace caps hold no matter which address clients use.
+    let bandwidth = Arc::new(Bandwidth::new(cfg.bandwidth.clone()));
+    let admission = Arc::new(Admission::new(
+        &cfg.slow_clients,
+        cfg.slow_client_stats.clone(),
+    ));
+    let shutdown = shutdown_signal(shutdown);
 
     let mut join_set: JoinSet<()> = JoinSet::new();
     let mut bound_addrs = Vec::new();
 
     for addr in listens {
-        let client = client.clone();
-        let upstream = upstream_host.clone();
-        let notify = notify.clone();
-        let allow_default = allow_default_upstream;
-
-        let std_listener = match StdTcpListener::bind(addr) {
-            Ok(listener) => listener,
+        let listeners = match cfg.listener.bind(addr) {
+            Ok(listeners) => listeners,
             Err(e) => {
                 error!(%e, "failed to bind to {}", addr);
                 continue;
             }
         };
-        if let Err(e) = std_listener.set_nonblocking(true) {
-            error!(%e, "failed to set nonblocking on {}", addr);
-            continue;
-        }
-        let actual_addr = match std_listener.local_addr() {
+        let actual_addr = match listeners[0].local_addr() {
             Ok(addr) => addr,
             Err(e) => {
                 error!(%e, "failed to get local addr for {}", addr);
                 continue;
             }
         };
-        let listener = match TcpListener::from_std(std_listener) {
-            Ok(listener) => listener,
-            Err(e) => {
-                error!(%e, "failed to create tokio listener for {}", actual_addr);
-                continue;
-            }
-        };
 
         bound_addrs.push(actual_addr);
+        let mut cfg = cfg.clone();
+        cfg.listen = actual_addr;
+
+        for listener in listeners {
+            join_set.spawn(accept_loop(
+                listener,
+                client.clone(),
+                cfg.clone(),
+                bandwidth.clone(),
+                admission.clone(),
+                shutdown.clone(),
+            ));
+        }
+    }
 
-        join_set.spawn(async move {
-            info!("proxy listening on {}", actual_addr);
-
-            loop {
-                tokio::select! {
-                    result = listener.accept() => {
-                        match result {
-                            Ok((stream, remote_addr)) => {
-                                let client = client.clone();
-                                let upstream = upstream.clone();
-
-                                tokio::spawn(async move {
-                                    let cfg = ProxyConfig {
-                                        listen: actual_addr,
-                                        upstream_host: upstream.clone(),
-                                        allow_default_upstream: allow_default,
-                                    };
-                                    if let Err(err) =
-                                        serve_client_stream(stream, remote_addr, client, cfg).await
-                                    {
-                                        error!(%err, "connection error");
-                                    }
-                                });
-                            }
-                            Err(e) => {
-                                error!(%e, "accept error");
-                            }
+    let handle = tokio::spawn(async move { while let Some(_res) = join_set.join_next().await {} });
+
+    (bound_addrs, handle)
+}
+
+/// Flag set once `shutdown` completes. Unlike a `Notify`, accept loops that
+/// start after that still see it.
+fn shutdown_signal<S>(shutdown: S) -> watch::Receiver<bool>
+where
+    S: Future<Output = ()> + Send + 'static,
+{
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown.await;
+        let _ = tx.send(true);
+    });
+    rx
+}
+
+/// Accept and serve clients on `listener` until shutdown is signalled.
+async fn accept_loop(
+    listener: TcpListener,
+    client: UpstreamClient,
+    cfg: ProxyConfig,
+    bandwidth: Arc<Bandwidth>,
+    admission: Arc<Admission>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let listen_addr = cfg.listen;
+    info!("proxy listening on {}", listen_addr);
+
+    loop {
+        tokio::select! {
+            result = async {
+                admission.accept_ready().await;
+                listener.accept().await
+            } => {
+                match result {
+                    Ok((stream, remote_addr)) => {
+                        admission.accepted();
+                        let Some(handshake) = admission.begin_handshake(remote_addr.ip()) else {
+                            warn!(client = %remote_addr, "too many pending connections from client; closing");
+                            continue;
+                        };
+                        if let Err(e) = cfg.listener.configure_stream(&stream) {
+                            warn!(%e, "failed to set client socket options");
                         }
+                        let client = client.clone();
+                        let cfg = cfg.clone();
+                        let bandwidth = bandwidth.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = serve_client_stream(
+                                stream,
+                                remote_addr,
+                                client,
+                                cfg,
+                                bandwidth,
+                                handshake,
+                            )
+                            .await
+                            {
+                                error!(%err, "connection error");
+                            }
+                        });
                     }
-                    _ = notify.notified() => {
-                        info!("shutting down proxy on {}", actual_addr);
-                        break;
+                    Err(e) => {
+                        error!(%e, "accept error");
                     }
                 }
             }
-        });
+            _ = shutdown.wait_for(|stopped| *stopped) => {
+                info!("shutting down proxy on {}", listen_addr);
+                break;
+            }
+        }
     }
-
-    let handle = tokio::spawn(async move { while let Some(_res) = join_set.join_next().await {} });
-
-    (bound_addrs, handle)
 }
 
 async fn serve_client_stream(
     stream: TcpStream,
     remote_addr: SocketAddr,
-    client: Client<HttpConnector, BoxBody>,
+    client: UpstreamClient,
     cfg: ProxyConfig,
+    bandwidth: Arc<Bandwidth>,
+    handshake: Handshake,
 ) -> Result<(), BoxError> {
-    let (buffered_stream, client_prefers_http2) = sniff_http2_preface(stream).await?;
+    let header_read_timeout = cfg.slow_clients.header_read_timeout;
+    let stats = cfg.slow_client_stats.clone();
+    let sniffed = match header_read_timeout {
+        Some(limit) => match tokio::time::timeout(limit, sniff_http2_preface(stream)).await {
+            Ok(sniffed) => sniffed,
+            Err(_) => {
+                stats.record_header_timeout();
+                info!(client = %remote_addr, "client sent nothing before the header read timeout; closing");
+                return Ok(());
+            }
+        },
+        None => sniff_http2_preface(stream).await,
+    };
+    let (buffered_stream, client_prefers_http2) = sniffed?;
     let io = TokioIo::new(buffered_stream);
     let svc_client = client.clone();
     let svc_cfg = cfg.clone();
-    let service =
-        service_fn(move |req| handle(svc_client.clone(), svc_cfg.clone(), remote_addr, req));
+    let throttle = ConnectionThrottle::new(bandwidth);
+    // Released on the first request so the client stops counting against its
+    // IP's pending-handshake limit.
+    let handshake = Arc::new(std::sync::Mutex::new(Some(handshake)));
+    let svc_handshake = handshake.clone();
+    let service = service_fn(move |req| {
+        svc_handshake.lock().unwrap().take();
+        handle(
+            svc_client.clone(),
+            svc_cfg.clone(),
+            throttle.clone(),
+            remote_addr,
+            req,
+        )
+    });
 
     if client_prefers_http2 {
         let mut builder = http2::Builder::new(TokioExecutor::new());
         configure_http2_server_builder(&mut builder);
         builder.timer(TokioTimer::new());
-        builder.serve_connection(io, service).await?;
+        builder.max_header_list_size(cfg.slow_clients.header_bytes() as u32);
+        let conn = builder.serve_connection(io, service);
+        tokio::pin!(conn);
+        // hyper has no header read timeout for HTTP/2, so bound the wait for
+        // the first request instead.
+        if let Some(limit) = header_read_timeout {
+            tokio::select! {
+                result = conn.as_mut() => return Ok(result?),
+                _ = tokio::time::sleep(limit) => {}
+            }
+            if handshake.lock().unwrap().is_some() {
+                stats.record_header_timeout();
+                info!(client = %remote_addr, "no HTTP/2 request before the header read timeout; closing");
+                return Ok(());
+            }
+        }
+        conn.await?;
     } else {
         let mut builder = http1::Builder::new();
         configure_http1_server_builder(&mut builder);
-        builder
-            .serve_connection(io, service)
-            .with_upgrades()
-            .await?;
+        builder.timer(TokioTimer::new());
+        builder.header_read_timeout(header_read_timeout);
+        builder.max_buf_size(cfg.slow_clients.header_bytes());
+        let result = builder.serve_connection(io, service).with_upgrades().await;
+        match result {
+            Err(e) if e.is_timeout() => {
+                stats.record_header_timeout();
+                info!(client = %remote_addr, "request head not completed in time; closing");
+            }
+            result => result?,
+        }
     }
     Ok(())
 }
@@ -523,26 +743,18 @@ fn get_port_from_header(headers: &HeaderMap) -> Result<u16, Response<BoxBody>> {
             )
         })?;
 
-        let s = s.trim();
-        if s.is_empty() {
-            return Err(response_with(
-                StatusCode::BAD_REQUEST,
-                "header value cannot be empty".to_string(),
-            ));
-        }
-
-        let port: u16 = s.parse().map_err(|_| {
-            response_with(
-                StatusCode::BAD_REQUEST,
-                "invalid port in X-Cmux-Port-Internal".to_string(),
-            )
-        })?;
-        return Ok(port);
+        return parser::parse_port(s).map_err(|e| {
+            let msg = match e {
+                parser::PortError::Empty => "header value cannot be empty".to_string(),
+                e => format!("invalid port in {}: {}", HDR, e),
+            };
+            response_with(StatusCode::BAD_REQUEST, msg)
+        });
     }
 
     // Fallback: try parsing from Host subdomain pattern: <workspace>-<port>.localhost[:...]
-    if let Some((_ws, port)) = parse_workspace_port_from_host(headers) {
-        return Ok(port);
+    if let Some(route) = host_route(headers) {
+        return Ok(route.port);
     }
 
     Err(response_with(
@@ -591,7 +803,7 @@ fn upstream_host_from_headers(
     default_host: &str,
     allow_default_without_workspace: bool,
 ) -> Result<String, Response<BoxBody>> {
-    const HDR_WS: &str = "X-Cmux-Workspace-Internal";
+    const HDR_WS: &str = WORKSPACE_HEADER;
     if let Some(val) = headers.get(HDR_WS) {
         let v = val.to_str().map_err(|_| {
             response_with(
@@ -620,13 +832,13 @@ fn upstream_host_from_headers(
     }
 
     // Fallback: try parsing from subdomain pattern if present
-    if let Some((ws, _port)) = parse_workspace_port_from_host(headers) {
-        if let Some(ip) = workspace_ip_from_name(&ws) {
+    if let Some(HostRoute { workspace, .. }) = host_route(headers) {
+        if let Some(ip) = workspace_ip_from_name(&workspace) {
             return Ok(ip.to_string());
         } else {
             return Err(response_with(
                 StatusCode::BAD_REQUEST,
-                format!("invalid workspace name: {}", ws),
+                format!("invalid workspace name: {}", workspace),
             ));
         }
     }
@@ -665,22 +877,21 @@ fn strip_hop_by_hop_headers(h: &mut HeaderMap) {
         "x-cmux-workspace-internal",
         "x-cmux-host-override",
     ];
+    // Headers listed in Connection: <header-names> are hop-by-hop too; read
+    // them before Connection itself goes.
+    let listed: Vec<String> = h
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|token| token.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
     for name in HOP_HEADERS {
         h.remove(*name);
     }
-
-    // Also remove headers listed in Connection: <header-names>
-    if let Some(conn_val) = h
-        .get(CONNECTION)
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-    {
-        for token in conn_val.split(',') {
-            let name = token.trim().to_ascii_lowercase();
-            if !name.is_empty() {
-                h.remove(&name);
-            }
-        }
+    for name in listed {
+        h.remove(&name);
     }
 }
 
@@ -696,41 +907,11 @@ fn build_upstream_uri(
         .map_err(|_| response_with(StatusCode::BAD_GATEWAY, "invalid upstream uri".into()))
 }
 
-// Attempt to parse a pattern like: <workspace>-<port>.localhost[:...]
-// Returns (workspace, port) if found and valid.
-fn parse_workspace_port_from_host(headers: &HeaderMap) -> Option<(String, u16)> {
-    let host_val = headers.get("host")?.to_str().ok()?.trim();
-    if host_val.is_empty() {
-        return None;
-    }
-
-    // Strip optional :port from Host header
-    let host_only = host_val.split_once(':').map(|(h, _)| h).unwrap_or(host_val);
-    let host_lc = host_only.to_ascii_lowercase();
-
-    // Must end with .localhost
-    const SUFFIX: &str = ".localhost";
-    if !host_lc.ends_with(SUFFIX) {
-        return None;
-    }
-
-    // Take the label before .localhost
-    let base_len = host_only.len() - SUFFIX.len();
-    let label = &host_only[..base_len];
-
-    // Expect last '-' separates workspace and port
-    let dash_idx = label.rfind('-')?;
-    let (ws_part, port_part) = label.split_at(dash_idx);
-    // port_part still has leading '-' from split_at
-    let port_str = &port_part[1..];
-    if ws_part.is_empty() || port_str.is_empty() {
-        return None;
-    }
-    let port: u16 = match port_str.parse() {
-        Ok(p) => p,
-        Err(_) => return None,
-    };
-    Some((ws_part.to_string(), port))
+/// Workspace and port from a `<workspace>-<port>.localhost[:...]` Host
+/// header. Any other Host (or none) routes by the internal headers instead.
+fn host_route(headers: &HeaderMap) -> Option<HostRoute> {
+    let host = headers.get(HOST)?.to_str().ok()?;
+    parser::parse_host(host).ok()
 }
 
 #[allow(clippy::result_large_err)]
@@ -762,39 +943,412 @@ fn response_with(status: StatusCode, msg: String) -> Response<BoxBody> {
         .unwrap()
 }
 
+/// `400` for a request [`validation::check_request`] refused. The connection
+/// is closed afterwards since its framing can't be trusted any more (HTTP/2
+/// frames every request itself, so there it stays open).
+fn rejection_response(error: &RequestError, version: Version) -> Response<BoxBody> {
+    let mut resp = response_with(
+        StatusCode::BAD_REQUEST,
+        format!("{}: {}", error.code(), error),
+    );
+    let headers = resp.headers_mut();
+    headers.insert(
+        validation::ERROR_HEADER,
+        HeaderValue::from_static(error.code()),
+    );
+    if version < Version::HTTP_2 {
+        headers.insert(CONNECTION, HeaderValue::from_static("close"));
+    }
+    resp
+}
+
+/// Workspace and port a request is addressed to, for tracing. Either may be
+/// missing or invalid; routing reports that separately.
+fn request_route(headers: &HeaderMap) -> (Option<String>, Option<u16>) {
+    let from_host = host_route(headers);
+    let workspace = headers
+        .get(WORKSPACE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .or_else(|| from_host.map(|route| route.workspace));
+    let port = get_port_from_header(headers).ok();
+    (workspace, port)
+}
+
 async fn handle(
-    client: Client<HttpConnector, BoxBody>,
+    client: UpstreamClient,
     cfg: ProxyConfig,
+    throttle: ConnectionThrottle,
     remote_addr: SocketAddr,
     req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Infallible> {
+    let started = Instant::now();
+    let (workspace, port) = request_route(req.headers());
+    let span = telemetry::request_span(
+        req.method(),
+        req.uri().path(),
+        req.headers(),
+        workspace.as_deref(),
+        port,
+    );
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let access_log = cfg.access_log.clone();
+
+    let rejected = validation::check_request(&req).err();
+
+    // CORS covers plain HTTP only; tunnels and upgrades pass through untouched.
+    let cors = cors::find_rule(&cfg.cors, workspace.as_deref(), port)
+        .filter(|_| req.method() != Method::CONNECT && !is_upgrade_request(&req))
+        .cloned();
+    let preflight = cors
+        .as_ref()
+        .and_then(|rule| rule.preflight_headers(req.method(), req.headers()));
+    let resp = if let Some(error) = rejected {
+        warn!(client = %remote_addr, code = error.code(), "refusing request: {}", error);
+        rejection_response(&error, req.version())
+    } else if let Some(headers) = preflight {
+        let mut resp = Response::new(empty_body());
+        *resp.status_mut() = StatusCode::NO_CONTENT;
+        *resp.headers_mut() = headers;
+        resp
+    } else {
+        let origin = req.headers().get(http::header::ORIGIN).cloned();
+        let mut resp = route(client, cfg, throttle, remote_addr, req)
+            .instrument(span.clone())
+            .await;
+        if let Some(rule) = cors {
+            rule.apply(origin.as_ref(), resp.headers_mut());
+        }
+        resp
+    };
+
+    telemetry::record_status(&span, resp.status());
+    if let Some(access_log) = access_log {
+        access_log.emit(AccessLogEvent {
+            client: remote_addr,
+            method,
+            path,
+            workspace,
+            port,
+            status: resp.status().as_u16(),
+            duration: started.elapsed(),
+        });
+    }
+    Ok(resp)
+}
+
+async fn route(
+    client: UpstreamClient,
+    cfg: ProxyConfig,
+    throttle: ConnectionThrottle,
+    remote_addr: SocketAddr,
+    req: Request<Incoming>,
+) -> Response<BoxBody> {
     let method = req.method().clone();
     let is_upgrade = is_upgrade_request(&req);
 
-    match method {
-        Method::CONNECT => match handle_connect(req, &cfg, remote_addr).await {
-            Ok(resp) => Ok(resp),
-            Err(resp) => Ok(resp),
-        },
+    if method != Method::CONNECT && req.uri().path() == PORTS_PATH {
+        if let Err(resp) = authorize_admin(&cfg, remote_addr, req.headers()) {
+            return resp;
+        }
+        return handle_ports(&cfg, &req).await.unwrap_or_else(|resp| resp);
+    }
+    if method != Method::CONNECT && req.uri().path() == BOOT_HOLD_PATH {
+        if let Err(resp) = authorize_admin(&cfg, remote_addr, req.headers()) {
+            return resp;
+        }
+        return handle_boot_hold(&cfg, &req).unwrap_or_else(|resp| resp);
+    }
+    if method != Method::CONNECT && req.uri().path() == STATS_PATH {
+        if let Err(resp) = authorize_admin(&cfg, remote_addr, req.headers()) {
+            return resp;
+        }
+        return handle_stats(&cfg, &req);
+    }
+    // CONNECT tunnels aren't held: clients treat a slow CONNECT as a dead proxy.
+    if method != Method::CONNECT {
+        if let Err(resp) = wait_for_boot(&cfg, req.headers()).await {
+            return resp;
+        }
+    }
+
+    let result = match method {
+        Method::CONNECT => handle_connect(req, &cfg, &throttle, remote_addr).await,
         _ => {
             if is_upgrade {
-                match handle_upgrade(client, cfg, remote_addr, req).await {
-                    Ok(resp) => Ok(resp),
-                    Err(resp) => Ok(resp),
-                }
+                handle_upgrade(client, cfg, &throttle, remote_addr, req).await
             } else {
-                match handle_http(client, &cfg, remote_addr, req).await {
-                    Ok(resp) => Ok(resp),
-                    Err(resp) => Ok(resp),
+                handle_http(client, &cfg, &throttle, remote_addr, req).await
+            }
+        }
+    };
+    result.unwrap_or_else(|resp| resp)
+}
+
+/// Admin routes act on any workspace, so they need `cfg.admin_token` as a
+/// bearer token, or a loopback client when no token is configured.
+#[allow(clippy::result_large_err)]
+fn authorize_admin(
+    cfg: &ProxyConfig,
+    remote_addr: SocketAddr,
+    headers: &HeaderMap,
+) -> Result<(), Response<BoxBody>> {
+    let Some(token) = cfg.admin_token.as_deref() else {
+        if remote_addr.ip().to_canonical().is_loopback() {
+            return Ok(());
+        }
+        return Err(response_with(
+            StatusCode::FORBIDDEN,
+            "admin routes only answer loopback clients".into(),
+        ));
+    };
+    let presented = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    match presented {
+        Some(presented) if constant_time_eq(presented.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => {
+            let mut resp = response_with(StatusCode::UNAUTHORIZED, "admin token required".into());
+            resp.headers_mut().insert(
+                http::header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Bearer"),
+            );
+            Err(resp)
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `GET /__cmux/ports`: listening ports on the workspace the request resolves to,
+/// so the UI can offer them without the user typing one in.
+async fn handle_ports(
+    cfg: &ProxyConfig,
+    req: &Request<Incoming>,
+) -> Result<Response<BoxBody>, Response<BoxBody>> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Err(response_with(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} only supports GET", PORTS_PATH),
+        ));
+    }
+    let upstream_host = upstream_host_from_headers(
+        req.headers(),
+        &cfg.upstream_host,
+        cfg.allow_default_upstream,
+    )?;
+    let range = match req
+        .uri()
+        .query()
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|pair| pair.strip_prefix("range="))
+    {
+        Some(range) => {
+            let range = ports::parse_port_range(range)
+                .map_err(|e| response_with(StatusCode::BAD_REQUEST, e))?;
+            // A request may narrow the configured scan, never widen it.
+            if !cfg.port_scan_range.contains(range.start())
+                || !cfg.port_scan_range.contains(range.end())
+            {
+                return Err(response_with(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "range must be within {}-{}",
+                        cfg.port_scan_range.start(),
+                        cfg.port_scan_range.end()
+                    ),
+                ));
+            }
+            range
+        }
+        None => cfg.port_scan_range.clone(),
+    };
+    let ip = resolve_upstream_ip(&upstream_host).await.ok_or_else(|| {
+        response_with(
+            StatusCode::BAD_GATEWAY,
+            format!("cannot resolve upstream host: {}", upstream_host),
+        )
+    })?;
+
+    let (source, mut found) = ports::discover(ip, range).await;
+    // The proxy's own listener isn't a workspace service.
+    if ports::listener_reaches(cfg.listen.ip(), ip) {
+        found.retain(|port| *port != cfg.listen.port());
+    }
+    let (workspace, _) = request_route(req.headers());
+    info!(upstream = %upstream_host, source = source.as_str(), count = found.len(), "port discovery");
+
+    let body = serde_json::json!({
+        "workspace": workspace,
+        "host": upstream_host,
+        "source": source.as_str(),
+        "ports": found,
+    });
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(full_body(body.to_string()))
+        .map_err(|_| {
+            response_with(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to build response".into(),
+            )
+        })
+}
+
+/// Hold the request while its workspace is booting. Routing errors are left
+/// for the handler to report.
+async fn wait_for_boot(cfg: &ProxyConfig, headers: &HeaderMap) -> Result<(), Response<BoxBody>> {
+    let (Ok(port), Ok(upstream_host)) = (
+        get_port_from_header(headers),
+        upstream_host_from_headers(headers, &cfg.upstream_host, cfg.allow_default_upstream),
+    ) else {
+        return Ok(());
+    };
+    let started = Instant::now();
+    let outcome = cfg.boot_holds.wait(&upstream_host, port).await;
+    match outcome {
+        HoldOutcome::NotHeld => Ok(()),
+        HoldOutcome::Full => {
+            warn!(upstream = %upstream_host, port, "boot hold queue full");
+            let mut resp = response_with(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "workspace is starting; too many requests waiting".into(),
+            );
+            resp.headers_mut()
+                .insert(http::header::RETRY_AFTER, HeaderValue::from_static("1"));
+            Err(resp)
+        }
+        HoldOutcome::Ready | HoldOutcome::Expired => {
+            info!(
+                upstream = %upstream_host,
+                port,
+                ready = outcome == HoldOutcome::Ready,
+                waited_ms = started.elapsed().as_millis() as u64,
+                "boot hold released request"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// `/__cmux/boot-hold`: `POST` starts holding requests for the workspace the
+/// request resolves to (optionally `?seconds=N&requests=M`, capped at the
+/// configured limits), `DELETE` ends the hold and `GET` reports it.
+#[allow(clippy::result_large_err)]
+fn handle_boot_hold(
+    cfg: &ProxyConfig,
+    req: &Request<Incoming>,
+) -> Result<Response<BoxBody>, Response<BoxBody>> {
+    let upstream_host = upstream_host_from_headers(
+        req.headers(),
+        &cfg.upstream_host,
+        cfg.allow_default_upstream,
+    )?;
+    let (workspace, _) = request_route(req.headers());
+    match *req.method() {
+        Method::POST => {
+            let mut max_wait = cfg.boot_hold.max_wait;
+            let mut max_requests = cfg.boot_hold.max_requests;
+            for pair in req.uri().query().into_iter().flat_map(|q| q.split('&')) {
+                let invalid = || response_with(StatusCode::BAD_REQUEST, format!("invalid {pair}"));
+                if let Some(v) = pair.strip_prefix("seconds=") {
+                    max_wait = Duration::from_secs(v.parse().map_err(|_| invalid())?)
+                        .min(cfg.boot_hold.max_wait_limit);
+                } else if let Some(v) = pair.strip_prefix("requests=") {
+                    max_requests = v
+                        .parse::<usize>()
+                        .map_err(|_| invalid())?
+                        .min(cfg.boot_hold.max_requests_limit);
                 }
             }
+            cfg.boot_holds
+                .activate(&upstream_host, max_wait, max_requests)
+                .map_err(|e| response_with(StatusCode::BAD_REQUEST, e.to_string()))?;
+            info!(upstream = %upstream_host, secs = max_wait.as_secs(), max_requests, "boot hold activated");
+        }
+        Method::DELETE => {
+            if cfg.boot_holds.release(&upstream_host) {
+                info!(upstream = %upstream_host, "boot hold released");
+            }
+        }
+        Method::GET | Method::HEAD => {}
+        _ => {
+            return Err(response_with(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("{} supports GET, POST and DELETE", BOOT_HOLD_PATH),
+            ))
         }
     }
+
+    let status = cfg.boot_holds.status(&upstream_host);
+    let body = serde_json::json!({
+        "workspace": workspace,
+        "host": upstream_host,
+        "active": status.is_some(),
+        "remainingMs": status.as_ref().map(|s| s.remaining.as_millis() as u64),
+        "maxRequests": status.as_ref().map(|s| s.max_requests),
+        "waiting": status.as_ref().map(|s| s.waiting),
+        "readyPorts": status.map(|s| s.ready_ports).unwrap_or_default(),
+    });
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(full_body(body.to_string()))
+        .map_err(|_| {
+            response_with(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to build response".into(),
+            )
+        })
+}
+
+/// `GET /__cmux/stats`: counts of connections closed by the slow-client limits.
+fn handle_stats(cfg: &ProxyConfig, req: &Request<Incoming>) -> Response<BoxBody> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return response_with(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{} only supports GET", STATS_PATH),
+        );
+    }
+    let body = serde_json::json!({ "slowClients": cfg.slow_client_stats.to_json() });
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .header("cache-control", "no-store")
+        .body(full_body(body.to_string()))
+        .unwrap_or_else(|_| {
+            response_with(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to build response".into(),
+            )
+        })
+}
+
+async fn resolve_upstream_ip(host: &str) -> Option<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Some(ip);
+    }
+    tokio::net::lookup_host((host, 0))
+        .await
+        .ok()?
+        .next()
+        .map(|addr| addr.ip())
 }
 
 async fn handle_http(
-    client: Client<HttpConnector, BoxBody>,
+    client: UpstreamClient,
     cfg: &ProxyConfig,
+    throttle: &ConnectionThrottle,
     remote_addr: SocketAddr,
     req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
@@ -818,7 +1372,8 @@ async fn handle_http(
     parts.version = Version::HTTP_11;
 
     // Convert incoming body to BoxBody
-    let proxied_body: BoxBody = incoming_to_box(incoming);
+    let proxied_body: BoxBody =
+        throttle_body(incoming_to_box(incoming), throttle.limiter(&upstream_host));
     let mut new_req = Request::from_parts(parts, proxied_body);
 
     // Strip internal headers
@@ -833,6 +1388,7 @@ async fn handle_http(
 
     // Strip hop-by-hop headers on the proxied request
     strip_hop_by_hop_headers(new_req.headers_mut());
+    telemetry::inject_context(&Span::current(), new_req.headers_mut());
 
     info!(
         client = %remote_addr,
@@ -843,12 +1399,14 @@ async fn handle_http(
         "proxy http"
     );
 
+    let started = Instant::now();
     let upstream_resp = client.request(new_req).await.map_err(|e| {
         response_with(
             StatusCode::BAD_GATEWAY,
             format!("upstream request error: {}", e),
         )
     })?;
+    telemetry::record_upstream_latency(&Span::current(), started.elapsed());
 
     // Map upstream response back to client, stripping hop-by-hop headers
     let mut client_resp_builder = Response::builder().status(upstream_resp.status());
@@ -861,7 +1419,10 @@ async fn handle_http(
     }
     strip_hop_by_hop_headers(headers);
 
-    let body = incoming_to_box(upstream_resp.into_body());
+    let body = throttle_body(
+        incoming_to_box(upstream_resp.into_body()),
+        throttle.limiter(&upstream_host),
+    );
     let resp = client_resp_builder.body(body).map_err(|_| {
         response_with(
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -872,8 +1433,9 @@ async fn handle_http(
 }
 
 async fn handle_upgrade(
-    client: Client<HttpConnector, BoxBody>,
+    client: UpstreamClient,
     cfg: ProxyConfig,
+    throttle: &ConnectionThrottle,
     remote_addr: SocketAddr,
     req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
@@ -934,15 +1496,19 @@ async fn handle_upgrade(
         }
     }
 
+    telemetry::inject_context(&Span::current(), proxied_req.headers_mut());
+
     info!(client = %remote_addr, port = port, upstream = %upstream_host, "proxy upgrade (e.g. websocket)");
 
     // Send to upstream and get its response (should be 101)
+    let started = Instant::now();
     let upstream_resp = client.request(proxied_req).await.map_err(|e| {
         response_with(
             StatusCode::BAD_GATEWAY,
             format!("upstream upgrade error: {}", e),
         )
     })?;
+    telemetry::record_upstream_latency(&Span::current(), started.elapsed());
 
     if upstream_resp.status() != StatusCode::SWITCHING_PROTOCOLS {
         // Return upstream status (probably 4xx/5xx) to client with body
@@ -952,7 +1518,10 @@ async fn handle_upgrade(
         for (k, v) in upstream_resp.headers() {
             headers.insert(k, v.clone());
         }
-        let body = incoming_to_box(upstream_resp.into_body());
+        let body = throttle_body(
+            incoming_to_box(upstream_resp.into_body()),
+            throttle.limiter(&upstream_host),
+        );
         return builder.body(body).map_err(|_| {
             response_with(
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -983,6 +1552,10 @@ async fn handle_upgrade(
     // Reconstruct the original request for upgrade
     let original_req = Request::from_parts(parts, ());
 
+    // Both directions of the tunnel count against the same buckets
+    let client_limiter = throttle.limiter(&upstream_host);
+    let upstream_limiter = throttle.limiter(&upstream_host);
+
     // Spawn tunnel after returning the 101 to the client
     tokio::spawn(async move {
         match future::try_join(
@@ -992,8 +1565,9 @@ async fn handle_upgrade(
         .await
         {
             Ok((client_upgraded, upstream_upgraded)) => {
-                let mut client_io = TokioIo::new(client_upgraded);
-                let mut upstream_io = TokioIo::new(upstream_upgraded);
+                let mut client_io = Throttled::new(TokioIo::new(client_upgraded), client_limiter);
+                let mut upstream_io =
+                    Throttled::new(TokioIo::new(upstream_upgraded), upstream_limiter);
                 if let Err(e) = copy_bidirectional(&mut client_io, &mut upstream_io).await {
                     warn!(%e, "upgrade tunnel error");
                 }
@@ -1013,6 +1587,7 @@ async fn handle_upgrade(
 async fn handle_connect(
     req: Request<Incoming>,
     cfg: &ProxyConfig,
+    throttle: &ConnectionThrottle,
     remote_addr: SocketAddr,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
     let port = get_port_from_header(req.headers())?;
@@ -1022,7 +1597,13 @@ async fn handle_connect(
         cfg.allow_default_upstream,
     )?;
     let target = format!("{}:{}", upstream_host, port);
-    info!(client = %remote_addr, %target, "tcp tunnel via CONNECT");
+    let parent = cfg.parent_proxy.clone();
+    info!(
+        client = %remote_addr,
+        %target,
+        via = parent.as_ref().filter(|p| p.applies_to(&upstream_host, port)).map(|p| p.authority.as_str()),
+        "tcp tunnel via CONNECT"
+    );
 
     // Consume request to get parts for upgrade later
     let (parts, _incoming) = req.into_parts();
@@ -1039,13 +1620,17 @@ async fn handle_connect(
             )
         })?;
 
+    let client_limiter = throttle.limiter(&upstream_host);
+    let upstream_limiter = throttle.limiter(&upstream_host);
+
     tokio::spawn(async move {
         let original_req = Request::from_parts(parts, ());
         match hyper::upgrade::on(original_req).await {
             Ok(upgraded) => {
-                let mut client_io = TokioIo::new(upgraded);
-                match TcpStream::connect(&target).await {
-                    Ok(mut upstream) => {
+                let mut client_io = Throttled::new(TokioIo::new(upgraded), client_limiter);
+                match parent_proxy::connect(parent.as_ref(), &upstream_host, port).await {
+                    Ok(upstream) => {
+                        let mut upstream = Throttled::new(upstream, upstream_limiter);
                         if let Err(e) = copy_bidirectional(&mut client_io, &mut upstream).await {
                             warn!(%e, "tcp tunnel error");
                         }
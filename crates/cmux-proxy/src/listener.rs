@@ -0,0 +1,110 @@
+//! Listening sockets and their tuning.
+//!
+//! Preview pages open many short connections, so accept throughput matters.
+//! [`ListenerOptions`] sets the backlog and socket options, and with
+//! `accept_tasks > 1` binds one `SO_REUSEPORT` socket per accept task so the
+//! kernel spreads new connections across them instead of every task
+//! contending for a single accept queue.
+
+use std::io;
+use std::net::SocketAddr;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const DEFAULT_BACKLOG: u32 = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListenerOptions {
+    /// Length of the kernel's queue of connections waiting to be accepted.
+    pub backlog: u32,
+    /// `SO_REUSEADDR`, so a restarted proxy can bind while old connections
+    /// linger in `TIME_WAIT`.
+    pub reuse_address: bool,
+    /// `SO_REUSEPORT`, letting other sockets (and processes) bind the same
+    /// address. Implied when `accept_tasks > 1`.
+    pub reuse_port: bool,
+    /// `TCP_NODELAY` on accepted client connections.
+    pub nodelay: bool,
+    /// Accept loops per listen address, each with its own socket.
+    pub accept_tasks: usize,
+}
+
+impl Default for ListenerOptions {
+    fn default() -> Self {
+        Self {
+            backlog: DEFAULT_BACKLOG,
+            reuse_address: true,
+            reuse_port: false,
+            nodelay: false,
+            accept_tasks: 1,
+        }
+    }
+}
+
+impl ListenerOptions {
+    fn shards(&self) -> usize {
+        self.accept_tasks.max(1)
+    }
+
+    /// Bind `addr` once per accept task. With port 0 every socket shares the
+    /// port the first one was given.
+    pub(crate) fn bind(&self, addr: SocketAddr) -> io::Result<Vec<TcpListener>> {
+        let first = self.bind_one(addr)?;
+        let addr = first.local_addr()?;
+        let mut listeners = vec![first];
+        for _ in 1..self.shards() {
+            listeners.push(self.bind_one(addr)?);
+        }
+        Ok(listeners)
+    }
+
+    fn bind_one(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_reuse_address(self.reuse_address)?;
+        #[cfg(unix)]
+        if self.reuse_port || self.shards() > 1 {
+            socket.set_reuse_port(true)?;
+        }
+        socket.bind(&addr.into())?;
+        socket.listen(self.backlog.min(i32::MAX as u32) as i32)?;
+        socket.set_nonblocking(true)?;
+        TcpListener::from_std(socket.into())
+    }
+
+    /// Apply per-connection options to an accepted client stream.
+    pub(crate) fn configure_stream(&self, stream: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shards_share_one_port() {
+        let options = ListenerOptions {
+            accept_tasks: 3,
+            ..ListenerOptions::default()
+        };
+        let listeners = options.bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        assert_eq!(listeners.len(), 3);
+        let addr = listeners[0].local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        for listener in &listeners {
+            assert_eq!(listener.local_addr().unwrap(), addr);
+        }
+    }
+
+    #[tokio::test]
+    async fn single_listener_refuses_a_second_bind() {
+        let options = ListenerOptions::default();
+        let listeners = options.bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let addr = listeners[0].local_addr().unwrap();
+        assert!(options.bind(addr).is_err());
+    }
+}
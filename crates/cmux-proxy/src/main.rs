@@ -1,6 +1,16 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use cmux_proxy::boot_hold::BootHoldConfig;
+use cmux_proxy::cors::CorsRule;
+use cmux_proxy::listener::{ListenerOptions, DEFAULT_BACKLOG};
+use cmux_proxy::parent_proxy::{BypassRule, ParentProxy};
+use cmux_proxy::ports::parse_port_range;
+use cmux_proxy::slow_client::{SlowClientConfig, DEFAULT_MAX_HEADER_BYTES};
+use cmux_proxy::throttle::BandwidthConfig;
+use cmux_proxy::ProxyConfig;
 use tracing::info;
 
 #[derive(Parser, Debug, Clone)]
@@ -23,25 +33,133 @@ struct Args {
     /// Allow requests without workspace headers to route to the default upstream host.
     #[arg(long, env = "CMUX_ALLOW_DEFAULT_UPSTREAM", default_value_t = true)]
     allow_default_upstream: bool,
+
+    /// Cap each client connection to this many bytes/sec (uploads and downloads combined).
+    /// Unlimited when unset.
+    #[arg(long, env = "CMUX_MAX_CONNECTION_BANDWIDTH")]
+    max_connection_bandwidth: Option<u64>,
+
+    /// Cap all traffic to one workspace to this many bytes/sec, shared by its connections.
+    /// Unlimited when unset.
+    #[arg(long, env = "CMUX_MAX_WORKSPACE_BANDWIDTH")]
+    max_workspace_bandwidth: Option<u64>,
+
+    /// Answer CORS preflights and add Access-Control-Allow-* headers for matching requests.
+    /// Format: <workspace|*>:<port|*>[;origin=A,B][;methods=..][;headers=..][;expose=..]
+    /// [;credentials][;max-age=SECS]. Repeatable; the first matching rule wins.
+    #[arg(long = "cors", env = "CMUX_CORS", value_name = "RULE")]
+    cors: Vec<CorsRule>,
+
+    /// Ports checked by `/__cmux/ports`, as START-END.
+    #[arg(long, env = "CMUX_PORT_SCAN_RANGE", default_value = "1024-65535", value_parser = parse_port_range)]
+    port_scan_range: RangeInclusive<u16>,
+
+    /// How long a boot hold started through `/__cmux/boot-hold` keeps requests
+    /// waiting for the workspace upstream, unless the request sets `seconds`.
+    #[arg(long, env = "CMUX_BOOT_HOLD_SECS", default_value_t = 30)]
+    boot_hold_secs: u64,
+
+    /// How many requests a boot hold queues before answering 503, unless the
+    /// request sets `requests`.
+    #[arg(long, env = "CMUX_BOOT_HOLD_MAX_REQUESTS", default_value_t = 100)]
+    boot_hold_max_requests: usize,
+
+    /// Most `seconds` a `/__cmux/boot-hold` request may ask for.
+    #[arg(long, env = "CMUX_BOOT_HOLD_SECS_LIMIT", default_value_t = 600)]
+    boot_hold_secs_limit: u64,
+
+    /// Most `requests` a `/__cmux/boot-hold` request may ask for.
+    #[arg(
+        long,
+        env = "CMUX_BOOT_HOLD_MAX_REQUESTS_LIMIT",
+        default_value_t = 1000
+    )]
+    boot_hold_max_requests_limit: usize,
+
+    /// Bearer token required by the `/__cmux/*` admin routes. Without one
+    /// they only answer clients connecting over loopback.
+    #[arg(long, env = "CMUX_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// Reach upstreams through this HTTP proxy, as http://[user:password@]host[:port].
+    /// Each upstream connection becomes a CONNECT tunnel through it.
+    #[arg(long, env = "HTTPS_PROXY")]
+    parent_proxy: Option<ParentProxy>,
+
+    /// Upstreams reached directly even with --parent-proxy: `*`, host (also
+    /// covers subdomains), IP or CIDR, each optionally with :port. Comma-separated.
+    /// Loopback hosts are always reached directly.
+    #[arg(long, env = "NO_PROXY", value_delimiter = ',')]
+    proxy_bypass: Vec<String>,
+
+    /// Pending-connection queue length for each listening socket.
+    #[arg(long, env = "CMUX_LISTEN_BACKLOG", default_value_t = DEFAULT_BACKLOG)]
+    listen_backlog: u32,
+
+    /// Set SO_REUSEPORT so other processes can bind the same addresses.
+    #[arg(long, env = "CMUX_REUSE_PORT", default_value_t = false)]
+    reuse_port: bool,
+
+    /// Set TCP_NODELAY on client connections.
+    #[arg(long, env = "CMUX_TCP_NODELAY", default_value_t = false)]
+    tcp_nodelay: bool,
+
+    /// Accept loops per listen address. Above 1, each gets its own SO_REUSEPORT
+    /// socket and the kernel spreads new connections across them.
+    #[arg(long, env = "CMUX_ACCEPT_TASKS", default_value_t = 1)]
+    accept_tasks: usize,
+
+    /// Seconds a client gets to send each request head before its connection
+    /// is closed. Also closes idle keep-alive connections. 0 disables.
+    #[arg(long, env = "CMUX_HEADER_READ_TIMEOUT_SECS", default_value_t = 30)]
+    header_read_timeout_secs: u64,
+
+    /// Largest request head accepted, in bytes (at least 8192).
+    #[arg(long, env = "CMUX_MAX_HEADER_BYTES", default_value_t = DEFAULT_MAX_HEADER_BYTES)]
+    max_header_bytes: usize,
+
+    /// Connections one client IP may have open before sending its first request.
+    /// Unlimited when unset.
+    #[arg(long, env = "CMUX_MAX_HANDSHAKES_PER_IP")]
+    max_handshakes_per_ip: Option<usize>,
+
+    /// New connections accepted per second across all listeners. Unlimited when unset.
+    #[arg(long, env = "CMUX_MAX_ACCEPT_RATE")]
+    max_accept_rate: Option<u64>,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
 
-    // Init logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "cmux-proxy=info,hyper=warn".into()),
-        )
-        .compact()
-        .init();
+    // Init logging (and OpenTelemetry export when built with `otel` and configured)
+    let _telemetry = cmux_proxy::telemetry::init_tracing("cmux-proxy=info,hyper=warn");
+
+    let parent_proxy = args.parent_proxy.map(|mut parent| {
+        parent.bypass = BypassRule::loopback();
+        for rule in args.proxy_bypass.iter().filter(|r| !r.trim().is_empty()) {
+            match rule.parse() {
+                Ok(rule) => parent.bypass.push(rule),
+                Err(e) => Args::command()
+                    .error(clap::error::ErrorKind::ValueValidation, e)
+                    .exit(),
+            }
+        }
+        parent
+    });
 
     info!(
         "listen" = ?args.listen,
         "upstream_host" = %args.upstream_host,
         allow_default_upstream = args.allow_default_upstream,
+        max_connection_bandwidth = ?args.max_connection_bandwidth,
+        max_workspace_bandwidth = ?args.max_workspace_bandwidth,
+        cors_rules = args.cors.len(),
+        accept_tasks = args.accept_tasks,
+        header_read_timeout_secs = args.header_read_timeout_secs,
+        max_handshakes_per_ip = ?args.max_handshakes_per_ip,
+        max_accept_rate = ?args.max_accept_rate,
+        parent_proxy = ?parent_proxy.as_ref().map(|p| &p.authority),
         "Starting cmux-proxy"
     );
 
@@ -55,13 +173,43 @@ async fn main() {
     listens.dedup();
     let listens = dedupe_wildcard_v4(listens);
 
-    let upstream_host = args.upstream_host;
-    let allow_default_upstream = args.allow_default_upstream;
+    let cfg = ProxyConfig {
+        upstream_host: args.upstream_host,
+        allow_default_upstream: args.allow_default_upstream,
+        bandwidth: BandwidthConfig {
+            per_connection: args.max_connection_bandwidth,
+            per_workspace: args.max_workspace_bandwidth,
+        },
+        cors: args.cors,
+        port_scan_range: args.port_scan_range,
+        boot_hold: BootHoldConfig {
+            max_wait: Duration::from_secs(args.boot_hold_secs),
+            max_requests: args.boot_hold_max_requests,
+            max_wait_limit: Duration::from_secs(args.boot_hold_secs_limit),
+            max_requests_limit: args.boot_hold_max_requests_limit,
+        },
+        parent_proxy,
+        admin_token: args.admin_token.filter(|token| !token.is_empty()),
+        listener: ListenerOptions {
+            backlog: args.listen_backlog,
+            reuse_port: args.reuse_port,
+            nodelay: args.tcp_nodelay,
+            accept_tasks: args.accept_tasks,
+            ..ListenerOptions::default()
+        },
+        slow_clients: SlowClientConfig {
+            header_read_timeout: (args.header_read_timeout_secs > 0)
+                .then(|| Duration::from_secs(args.header_read_timeout_secs)),
+            max_header_bytes: args.max_header_bytes,
+            max_handshakes_per_ip: args.max_handshakes_per_ip,
+            max_accept_rate: args.max_accept_rate,
+        },
+        ..ProxyConfig::default()
+    };
 
-    let (bound, handle) =
-        cmux_proxy::spawn_proxy_multi(listens, upstream_host, allow_default_upstream, async {
-            let _ = tokio::signal::ctrl_c().await;
-        });
+    let (bound, handle) = cmux_proxy::spawn_proxy_multi(listens, cfg, async {
+        let _ = tokio::signal::ctrl_c().await;
+    });
     info!("bound_addrs" = ?bound, "proxy started");
     let _ = handle.await;
 }
@@ -0,0 +1,183 @@
+//! Listening port discovery for `/__cmux/ports`.
+//!
+//! For a loopback upstream on Linux the kernel's socket tables
+//! (`/proc/net/tcp{,6}`) list every listener directly. Anything else falls
+//! back to a TCP connect scan of the configured range.
+
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use futures_util::{stream, StreamExt};
+use tokio::net::TcpStream;
+
+/// Socket state for LISTEN in `/proc/net/tcp`.
+const TCP_LISTEN: &str = "0A";
+const SCAN_CONCURRENCY: usize = 256;
+const SCAN_CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+
+pub const DEFAULT_SCAN_RANGE: RangeInclusive<u16> = 1024..=65535;
+
+/// How the ports were found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Proc,
+    Scan,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Proc => "proc",
+            Source::Scan => "scan",
+        }
+    }
+}
+
+/// Parses `START-END` (or a single port) into an inclusive range.
+pub fn parse_port_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let s = s.trim();
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let parse = |p: &str| {
+        p.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid port: {p:?}"))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start == 0 || start > end {
+        return Err(format!("invalid port range: {s:?}"));
+    }
+    Ok(start..=end)
+}
+
+/// Listening TCP ports on `ip` within `range`.
+pub async fn discover(ip: IpAddr, range: RangeInclusive<u16>) -> (Source, Vec<u16>) {
+    if ip.is_loopback() {
+        if let Some(ports) = listening_from_proc(ip, &range).await {
+            return (Source::Proc, ports);
+        }
+    }
+    (Source::Scan, connect_scan(ip, range).await)
+}
+
+async fn listening_from_proc(ip: IpAddr, range: &RangeInclusive<u16>) -> Option<Vec<u16>> {
+    let v4 = tokio::fs::read_to_string("/proc/net/tcp").await.ok()?;
+    // IPv6 may be disabled; the IPv4 table alone is still authoritative for v4 listeners.
+    let v6 = tokio::fs::read_to_string("/proc/net/tcp6")
+        .await
+        .unwrap_or_default();
+    let ports: BTreeSet<u16> = parse_proc_net_tcp(&v4)
+        .chain(parse_proc_net_tcp(&v6))
+        .filter(|addr| listener_reaches(addr.ip(), ip) && range.contains(&addr.port()))
+        .map(|addr| addr.port())
+        .collect();
+    Some(ports.into_iter().collect())
+}
+
+/// Whether a socket bound to `bound` accepts connections to `target`.
+pub(crate) fn listener_reaches(bound: IpAddr, target: IpAddr) -> bool {
+    if bound.is_unspecified() || bound == target {
+        return true;
+    }
+    match (bound, target) {
+        (IpAddr::V6(v6), IpAddr::V4(v4)) => v6.to_ipv4_mapped() == Some(v4),
+        _ => false,
+    }
+}
+
+/// Local addresses of LISTEN sockets in a `/proc/net/tcp` or `/proc/net/tcp6` table.
+fn parse_proc_net_tcp(table: &str) -> impl Iterator<Item = SocketAddr> + '_ {
+    table.lines().skip(1).filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        let local = fields.nth(1)?;
+        let state = fields.nth(1)?;
+        if state != TCP_LISTEN {
+            return None;
+        }
+        let (ip_hex, port_hex) = local.split_once(':')?;
+        let port = u16::from_str_radix(port_hex, 16).ok()?;
+        Some(SocketAddr::new(parse_proc_ip(ip_hex)?, port))
+    })
+}
+
+/// The kernel prints addresses as native-endian 32-bit words.
+fn parse_proc_ip(hex: &str) -> Option<IpAddr> {
+    let word = |i: usize| -> Option<[u8; 4]> {
+        let chunk = hex.get(i * 8..i * 8 + 8)?;
+        Some(u32::from_str_radix(chunk, 16).ok()?.to_ne_bytes())
+    };
+    match hex.len() {
+        8 => Some(IpAddr::V4(Ipv4Addr::from(word(0)?))),
+        32 => {
+            let mut octets = [0u8; 16];
+            for i in 0..4 {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+async fn connect_scan(ip: IpAddr, range: RangeInclusive<u16>) -> Vec<u16> {
+    let mut ports: Vec<u16> = stream::iter(range)
+        .map(|port| async move {
+            let addr = SocketAddr::new(ip, port);
+            match tokio::time::timeout(SCAN_CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+                Ok(Ok(_)) => Some(port),
+                _ => None,
+            }
+        })
+        .buffer_unordered(SCAN_CONCURRENCY)
+        .filter_map(|port| async move { port })
+        .collect()
+        .await;
+    ports.sort_unstable();
+    ports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_port_ranges() {
+        assert_eq!(parse_port_range("3000-3010"), Ok(3000..=3010));
+        assert_eq!(parse_port_range("8080"), Ok(8080..=8080));
+        assert!(parse_port_range("0-10").is_err());
+        assert!(parse_port_range("9000-8000").is_err());
+        assert!(parse_port_range("http").is_err());
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn parses_listeners_from_proc_tables() {
+        let v4 = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1 1 0 100 0 0 10 0
+   1: 0100127F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 2 1 0 100 0 0 10 0
+   2: 0100007F:0BB8 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 3 1 0 20 4 30 10 -1
+";
+        let v6 = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:1538 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 4 1 0 100 0 0 10 0
+";
+        let listeners: Vec<SocketAddr> = parse_proc_net_tcp(v4)
+            .chain(parse_proc_net_tcp(v6))
+            .collect();
+        assert_eq!(
+            listeners,
+            [
+                "127.0.0.1:3000".parse().unwrap(),
+                "127.18.0.1:8080".parse().unwrap(),
+                "[::]:5432".parse().unwrap(),
+            ]
+        );
+
+        let workspace: IpAddr = "127.18.0.1".parse().unwrap();
+        assert!(listener_reaches(
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            workspace
+        ));
+        assert!(!listener_reaches("127.0.0.1".parse().unwrap(), workspace));
+    }
+}
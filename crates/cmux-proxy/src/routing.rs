@@ -0,0 +1,3 @@
+//! Working out where a request should go.
+
+pub mod parser;
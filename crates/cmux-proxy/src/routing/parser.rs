@@ -0,0 +1,297 @@
+//! Parsers for the routing parts of a request: the `Host` header's
+//! `<workspace>-<port>.localhost` form and port header values.
+//!
+//! Both take attacker-controlled input, so they never panic and never slice
+//! on positions they haven't checked. Host names are case-insensitive, so the
+//! workspace comes back lowercase. The port is everything after the last `-`
+//! of the first label, so workspace names may contain dashes themselves.
+
+use std::fmt;
+
+/// Suffix every routed host ends with.
+pub const HOST_SUFFIX: &str = ".localhost";
+
+/// A workspace and port taken from a `Host` value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostRoute {
+    pub workspace: String,
+    pub port: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PortError {
+    Empty,
+    NotNumeric,
+    /// Zero or larger than 65535.
+    OutOfRange,
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PortError::Empty => "port is empty",
+            PortError::NotNumeric => "port is not a number",
+            PortError::OutOfRange => "port is out of range",
+        })
+    }
+}
+
+impl std::error::Error for PortError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HostError {
+    Empty,
+    /// Unbalanced IPv6 brackets or a bad `:port` after the host.
+    InvalidAuthority,
+    /// Not a `*.localhost` name (IP literals included).
+    NotWorkspaceHost,
+    /// The part before the port is empty or not a single DNS label.
+    InvalidWorkspace,
+    /// No `-<port>` at the end of the label.
+    MissingPort,
+    InvalidPort(PortError),
+}
+
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostError::Empty => f.write_str("host is empty"),
+            HostError::InvalidAuthority => f.write_str("host is not a valid authority"),
+            HostError::NotWorkspaceHost => {
+                write!(f, "host is not of the form <workspace>-<port>{HOST_SUFFIX}")
+            }
+            HostError::InvalidWorkspace => f.write_str("host has an invalid workspace name"),
+            HostError::MissingPort => f.write_str("host has no -<port> after the workspace"),
+            HostError::InvalidPort(e) => write!(f, "host has an invalid port: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HostError {}
+
+impl From<PortError> for HostError {
+    fn from(e: PortError) -> Self {
+        HostError::InvalidPort(e)
+    }
+}
+
+/// Parse a port made only of ASCII digits (surrounding whitespace allowed).
+/// Unlike `str::parse`, a sign is rejected.
+pub fn parse_port(value: &str) -> Result<u16, PortError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(PortError::Empty);
+    }
+    if !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(PortError::NotNumeric);
+    }
+    match value.parse::<u16>() {
+        Ok(0) | Err(_) => Err(PortError::OutOfRange),
+        Ok(port) => Ok(port),
+    }
+}
+
+/// Split a `Host` value into the host and its optional port, handling
+/// bracketed IPv6 literals (`[::1]:8080`).
+pub fn split_host_port(authority: &str) -> Result<(&str, Option<u16>), HostError> {
+    let authority = authority.trim();
+    if authority.is_empty() {
+        return Err(HostError::Empty);
+    }
+    let (host, port) = if authority.starts_with('[') {
+        let end = authority.find(']').ok_or(HostError::InvalidAuthority)?;
+        let (host, rest) = authority.split_at(end + 1);
+        match rest {
+            "" => (host, None),
+            _ => (
+                host,
+                Some(rest.strip_prefix(':').ok_or(HostError::InvalidAuthority)?),
+            ),
+        }
+    } else {
+        match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(HostError::InvalidAuthority);
+    }
+    let port = port
+        .map(|p| parse_port(p).map_err(|_| HostError::InvalidAuthority))
+        .transpose()?;
+    Ok((host, port))
+}
+
+fn is_label(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with('-')
+        && !s.ends_with('-')
+        && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Parse `<workspace>-<port>.localhost[:port]` (any case, optional trailing
+/// dot) into its workspace and port.
+pub fn parse_host(authority: &str) -> Result<HostRoute, HostError> {
+    let (host, _) = split_host_port(authority)?;
+    let host = host.to_ascii_lowercase();
+    let host = host.strip_suffix('.').unwrap_or(&host);
+    let label = host
+        .strip_suffix(HOST_SUFFIX)
+        .ok_or(HostError::NotWorkspaceHost)?;
+    if label.contains('.') {
+        return Err(HostError::InvalidWorkspace);
+    }
+    let (workspace, port) = label.rsplit_once('-').ok_or(HostError::MissingPort)?;
+    let port = parse_port(port)?;
+    if !is_label(workspace) {
+        return Err(HostError::InvalidWorkspace);
+    }
+    Ok(HostRoute {
+        workspace: workspace.to_string(),
+        port,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parses_workspace_hosts() {
+        let route = |workspace: &str, port| {
+            Ok(HostRoute {
+                workspace: workspace.to_string(),
+                port,
+            })
+        };
+        assert_eq!(
+            parse_host("workspace-1-3000.localhost"),
+            route("workspace-1", 3000)
+        );
+        assert_eq!(parse_host("WS-8080.LocalHost:39379"), route("ws", 8080));
+        assert_eq!(parse_host("ws-80.localhost."), route("ws", 80));
+        assert_eq!(parse_host(" ws-80.localhost "), route("ws", 80));
+    }
+
+    #[test]
+    fn rejects_malformed_hosts() {
+        assert_eq!(parse_host(""), Err(HostError::Empty));
+        assert_eq!(parse_host("[::1]:3000"), Err(HostError::NotWorkspaceHost));
+        assert_eq!(parse_host("[::1"), Err(HostError::InvalidAuthority));
+        assert_eq!(parse_host("[::1]x"), Err(HostError::InvalidAuthority));
+        assert_eq!(
+            parse_host("ws-80.localhost:"),
+            Err(HostError::InvalidAuthority)
+        );
+        assert_eq!(
+            parse_host("ws-80.localhost:x"),
+            Err(HostError::InvalidAuthority)
+        );
+        assert_eq!(
+            parse_host("127.0.0.1:3000"),
+            Err(HostError::NotWorkspaceHost)
+        );
+        assert_eq!(parse_host(".localhost"), Err(HostError::MissingPort));
+        assert_eq!(parse_host("ws.localhost"), Err(HostError::MissingPort));
+        assert_eq!(
+            parse_host("-3000.localhost"),
+            Err(HostError::InvalidWorkspace)
+        );
+        assert_eq!(
+            parse_host("ws--3000.localhost"),
+            Err(HostError::InvalidWorkspace)
+        );
+        assert_eq!(
+            parse_host("a.ws-3000.localhost"),
+            Err(HostError::InvalidWorkspace)
+        );
+        assert_eq!(
+            parse_host("ws-+80.localhost"),
+            Err(HostError::InvalidPort(PortError::NotNumeric))
+        );
+        assert_eq!(
+            parse_host("ws-70000.localhost"),
+            Err(HostError::InvalidPort(PortError::OutOfRange))
+        );
+        assert_eq!(
+            parse_host("ws-.localhost"),
+            Err(HostError::InvalidPort(PortError::Empty))
+        );
+    }
+
+    #[test]
+    fn parses_ports() {
+        assert_eq!(parse_port(" 3000 "), Ok(3000));
+        assert_eq!(parse_port("65535"), Ok(65535));
+        assert_eq!(parse_port(""), Err(PortError::Empty));
+        assert_eq!(parse_port("+80"), Err(PortError::NotNumeric));
+        assert_eq!(parse_port("0"), Err(PortError::OutOfRange));
+        assert_eq!(parse_port("65536"), Err(PortError::OutOfRange));
+        assert_eq!(
+            parse_port("99999999999999999999"),
+            Err(PortError::OutOfRange)
+        );
+    }
+
+    fn workspace_name() -> impl Strategy<Value = String> {
+        "[a-zA-Z0-9]([a-zA-Z0-9-]{0,30}[a-zA-Z0-9])?"
+    }
+
+    proptest! {
+        #[test]
+        fn valid_hosts_round_trip(
+            workspace in workspace_name(),
+            port in 1u16..,
+            host_port in proptest::option::of(1u16..),
+            trailing_dot in any::<bool>(),
+            upper_suffix in any::<bool>(),
+        ) {
+            let suffix = if upper_suffix { ".LOCALHOST" } else { ".localhost" };
+            let dot = if trailing_dot { "." } else { "" };
+            let authority = match host_port {
+                Some(p) => format!("{workspace}-{port}{suffix}{dot}:{p}"),
+                None => format!("{workspace}-{port}{suffix}{dot}"),
+            };
+            let route = parse_host(&authority).unwrap();
+            prop_assert_eq!(route.workspace, workspace.to_ascii_lowercase());
+            prop_assert_eq!(route.port, port);
+        }
+
+        #[test]
+        fn any_host_parses_to_a_valid_route_or_an_error(authority in any::<String>()) {
+            if let Ok(route) = parse_host(&authority) {
+                prop_assert!(route.port > 0);
+                prop_assert!(is_label(&route.workspace));
+                prop_assert_eq!(route.workspace.to_ascii_lowercase(), route.workspace.clone());
+                let again = format!("{}-{}{}", route.workspace, route.port, HOST_SUFFIX);
+                prop_assert_eq!(parse_host(&again), Ok(route));
+            }
+        }
+
+        #[test]
+        fn ipv6_literals_are_never_workspaces(
+            segments in proptest::collection::vec(any::<u16>(), 8),
+            port in proptest::option::of(1u16..),
+        ) {
+            let ip = std::net::Ipv6Addr::from(<[u16; 8]>::try_from(segments).unwrap());
+            let authority = match port {
+                Some(p) => format!("[{ip}]:{p}"),
+                None => format!("[{ip}]"),
+            };
+            prop_assert_eq!(split_host_port(&authority).map(|(_, p)| p), Ok(port));
+            prop_assert_eq!(parse_host(&authority), Err(HostError::NotWorkspaceHost));
+        }
+
+        #[test]
+        fn ports_round_trip_and_reject_everything_else(value in any::<String>(), port in 1u16..) {
+            prop_assert_eq!(parse_port(&port.to_string()), Ok(port));
+            if let Ok(parsed) = parse_port(&value) {
+                let trimmed = value.trim();
+                prop_assert!(trimmed.bytes().all(|b| b.is_ascii_digit()));
+                prop_assert_eq!(trimmed.trim_start_matches('0'), parsed.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Protections against clients that hold connections without sending requests.
+//!
+//! Opening a connection and trickling (or never sending) a request head ties
+//! up a server task for as long as the client likes. Four limits bound that:
+//! a deadline and a size cap for each request head, a cap on connections per
+//! client IP that haven't finished their first request head yet, and a global
+//! rate at which new connections are accepted. Connections the limits close
+//! are counted in [`SlowClientStats`] and reported by `/__cmux/stats`.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::throttle::TokenBucket;
+
+pub const DEFAULT_HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_MAX_HEADER_BYTES: usize = 64 * 1024;
+/// Smallest HTTP/1 read buffer hyper accepts.
+const MIN_HEADER_BYTES: usize = 8192;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlowClientConfig {
+    /// Time a client gets to send each complete request head, including the
+    /// next one on an idle keep-alive connection. `None` disables.
+    pub header_read_timeout: Option<Duration>,
+    /// Largest request head accepted, in bytes. HTTP/1 clients going over get
+    /// 431; HTTP/2 ones a stream error. Values under 8 KiB are raised to it.
+    pub max_header_bytes: usize,
+    /// Connections per client IP still waiting on their first request head.
+    /// Further connections from that IP are closed at once. `None` is unlimited.
+    pub max_handshakes_per_ip: Option<usize>,
+    /// New connections accepted per second across all listeners, with one
+    /// second of burst. Above it, accepting waits and the kernel backlog fills.
+    pub max_accept_rate: Option<u64>,
+}
+
+impl Default for SlowClientConfig {
+    fn default() -> Self {
+        Self {
+            header_read_timeout: Some(DEFAULT_HEADER_READ_TIMEOUT),
+            max_header_bytes: DEFAULT_MAX_HEADER_BYTES,
+            max_handshakes_per_ip: None,
+            max_accept_rate: None,
+        }
+    }
+}
+
+impl SlowClientConfig {
+    pub(crate) fn header_bytes(&self) -> usize {
+        self.max_header_bytes.max(MIN_HEADER_BYTES)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    header_timeouts: AtomicU64,
+    handshake_rejections: AtomicU64,
+    accept_delays: AtomicU64,
+}
+
+/// Counts of connections the slow-client limits acted on. Clones share the
+/// counters, so an embedder can keep one to read them from a running proxy.
+#[derive(Clone, Debug, Default)]
+pub struct SlowClientStats {
+    counters: Arc<Counters>,
+}
+
+impl SlowClientStats {
+    /// Connections closed for not completing a request head in time.
+    pub fn header_timeouts(&self) -> u64 {
+        self.counters.header_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Connections closed because their IP had too many pending handshakes.
+    pub fn handshake_rejections(&self) -> u64 {
+        self.counters.handshake_rejections.load(Ordering::Relaxed)
+    }
+
+    /// Times accepting a connection waited on the accept-rate limit.
+    pub fn accept_delays(&self) -> u64 {
+        self.counters.accept_delays.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_header_timeout(&self) {
+        self.counters
+            .header_timeouts
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "headerTimeouts": self.header_timeouts(),
+            "handshakeRejections": self.handshake_rejections(),
+            "acceptDelays": self.accept_delays(),
+        })
+    }
+}
+
+/// Shared admission state for every listener of one proxy.
+pub(crate) struct Admission {
+    max_handshakes_per_ip: Option<usize>,
+    handshakes: Mutex<HashMap<IpAddr, usize>>,
+    accept_bucket: Option<TokenBucket>,
+    stats: SlowClientStats,
+}
+
+impl Admission {
+    pub(crate) fn new(config: &SlowClientConfig, stats: SlowClientStats) -> Self {
+        Self {
+            max_handshakes_per_ip: config.max_handshakes_per_ip,
+            handshakes: Mutex::new(HashMap::new()),
+            accept_bucket: config.max_accept_rate.map(TokenBucket::new),
+            stats,
+        }
+    }
+
+    /// Wait until the accept-rate limit allows another connection.
+    pub(crate) async fn accept_ready(&self) {
+        let Some(bucket) = &self.accept_bucket else {
+            return;
+        };
+        let wait = bucket.wait_time();
+        if !wait.is_zero() {
+            self.stats
+                .counters
+                .accept_delays
+                .fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Charge an accepted connection against the accept-rate limit.
+    pub(crate) fn accepted(&self) {
+        if let Some(bucket) = &self.accept_bucket {
+            bucket.consume(1);
+        }
+    }
+
+    /// Count a connection from `ip` as pending until the returned guard is
+    /// dropped, or refuse it when `ip` is at its limit.
+    pub(crate) fn begin_handshake(self: &Arc<Self>, ip: IpAddr) -> Option<Handshake> {
+        let Some(max) = self.max_handshakes_per_ip else {
+            return Some(Handshake {
+                admission: None,
+                ip,
+            });
+        };
+        let mut handshakes = self.handshakes.lock().unwrap();
+        let pending = handshakes.entry(ip).or_default();
+        if *pending >= max {
+            drop(handshakes);
+            self.stats
+                .counters
+                .handshake_rejections
+                .fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        *pending += 1;
+        Some(Handshake {
+            admission: Some(self.clone()),
+            ip,
+        })
+    }
+}
+
+/// A connection that hasn't finished its first request head yet.
+pub(crate) struct Handshake {
+    admission: Option<Arc<Admission>>,
+    ip: IpAddr,
+}
+
+impl Drop for Handshake {
+    fn drop(&mut self) {
+        let Some(admission) = &self.admission else {
+            return;
+        };
+        let mut handshakes = admission.handshakes.lock().unwrap();
+        if let Some(pending) = handshakes.get_mut(&self.ip) {
+            *pending -= 1;
+            if *pending == 0 {
+                handshakes.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn handshakes_are_capped_per_ip() {
+        let stats = SlowClientStats::default();
+        let admission = Arc::new(Admission::new(
+            &SlowClientConfig {
+                max_handshakes_per_ip: Some(2),
+                ..SlowClientConfig::default()
+            },
+            stats.clone(),
+        ));
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let first = admission.begin_handshake(a).unwrap();
+        let _second = admission.begin_handshake(a).unwrap();
+        assert!(admission.begin_handshake(a).is_none());
+        assert!(admission.begin_handshake(b).is_some());
+        assert_eq!(stats.handshake_rejections(), 1);
+
+        drop(first);
+        assert!(admission.begin_handshake(a).is_some());
+    }
+
+    #[test]
+    fn released_ips_are_forgotten() {
+        let admission = Arc::new(Admission::new(
+            &SlowClientConfig {
+                max_handshakes_per_ip: Some(1),
+                ..SlowClientConfig::default()
+            },
+            SlowClientStats::default(),
+        ));
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        drop(admission.begin_handshake(ip));
+        assert!(admission.handshakes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn accept_rate_delays_past_the_burst() {
+        let stats = SlowClientStats::default();
+        let admission = Admission::new(
+            &SlowClientConfig {
+                max_accept_rate: Some(20),
+                ..SlowClientConfig::default()
+            },
+            stats.clone(),
+        );
+        let started = std::time::Instant::now();
+        // The burst covers 20 and the 21st puts the bucket in debt, so only
+        // the 22nd waits, for 1/20 s.
+        for _ in 0..22 {
+            admission.accept_ready().await;
+            admission.accepted();
+        }
+        assert_eq!(stats.accept_delays(), 1);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn header_limit_has_a_floor() {
+        let config = SlowClientConfig {
+            max_header_bytes: 100,
+            ..SlowClientConfig::default()
+        };
+        assert_eq!(config.header_bytes(), MIN_HEADER_BYTES);
+    }
+}
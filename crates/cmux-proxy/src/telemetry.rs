@@ -0,0 +1,217 @@
+//! Logging and request tracing.
+//!
+//! Every proxied request runs inside a `proxy_request` span carrying the
+//! workspace, port, response status and upstream latency. Built with the
+//! `otel` feature and started with an OTLP endpoint in the environment
+//! (`OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`),
+//! those spans are exported over OTLP/HTTP, continue the trace from an
+//! incoming `traceparent` header and hand their own context to the upstream.
+//! Otherwise the headers pass through untouched.
+
+use std::time::Duration;
+
+use http::{HeaderMap, Method, StatusCode};
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Keeps the span exporter alive; dropping it flushes spans still queued.
+#[must_use = "dropping the guard shuts down span export"]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("cmux-proxy: failed to flush spans: {e}");
+            }
+        }
+    }
+}
+
+/// Install the global subscriber: compact logs filtered by `RUST_LOG` (or
+/// `default_filter`), plus span export when OpenTelemetry is configured.
+pub fn init_tracing(default_filter: &str) -> TelemetryGuard {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter.into());
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .compact()
+        .with_filter(filter);
+
+    #[cfg(feature = "otel")]
+    {
+        let provider = otel::provider_from_env();
+        let otel_layer = provider.as_ref().map(otel::layer);
+        tracing_subscriber::registry()
+            .with(fmt_layer)
+            .with(otel_layer)
+            .init();
+        TelemetryGuard { provider }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        tracing_subscriber::registry().with(fmt_layer).init();
+        TelemetryGuard {}
+    }
+}
+
+/// Span for one proxied request, continuing the caller's trace if it sent one.
+pub(crate) fn request_span(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    workspace: Option<&str>,
+    port: Option<u16>,
+) -> Span {
+    let span = tracing::info_span!(
+        "proxy_request",
+        otel.kind = "server",
+        http.request.method = %method,
+        url.path = %path,
+        cmux.workspace = workspace,
+        cmux.port = port,
+        http.response.status_code = Empty,
+        cmux.upstream_latency_ms = Empty,
+    );
+    #[cfg(feature = "otel")]
+    otel::set_remote_parent(&span, headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = headers;
+    span
+}
+
+pub(crate) fn record_status(span: &Span, status: StatusCode) {
+    span.record("http.response.status_code", status.as_u16());
+}
+
+/// Time until the upstream answered with response headers.
+pub(crate) fn record_upstream_latency(span: &Span, latency: Duration) {
+    span.record("cmux.upstream_latency_ms", latency.as_secs_f64() * 1000.0);
+}
+
+/// Write the span's trace context into the headers sent upstream.
+pub(crate) fn inject_context(span: &Span, headers: &mut HeaderMap) {
+    #[cfg(feature = "otel")]
+    otel::inject(span, headers);
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use http::HeaderMap;
+    use opentelemetry::global;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing::{Level, Span};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::filter::Targets;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    const SERVICE_NAME: &str = "cmux-proxy";
+
+    /// Build an OTLP/HTTP exporter if an endpoint is configured. The rest of
+    /// the exporter settings (headers, timeout, protocol) come from the
+    /// standard `OTEL_EXPORTER_OTLP_*` variables.
+    pub(super) fn provider_from_env() -> Option<SdkTracerProvider> {
+        let configured = [
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+        ]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|v| !v.trim().is_empty()));
+        if !configured {
+            return None;
+        }
+
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                eprintln!("cmux-proxy: OpenTelemetry disabled, failed to build OTLP exporter: {e}");
+                return None;
+            }
+        };
+        let service_name =
+            std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| SERVICE_NAME.to_string());
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(service_name).build())
+            .build();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+        Some(provider)
+    }
+
+    /// Exports this crate's spans; other crates' spans stay local.
+    pub(super) fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(SERVICE_NAME))
+            .with_filter(Targets::new().with_target("cmux_proxy", Level::INFO))
+    }
+
+    pub(super) fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+        let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(headers)));
+        let _ = span.set_parent(parent);
+    }
+
+    pub(super) fn inject(span: &Span, headers: &mut HeaderMap) {
+        let cx = span.context();
+        global::get_text_map_propagator(|p| p.inject_context(&cx, &mut HeaderInjector(headers)));
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use http::Method;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        #[test]
+        fn continues_incoming_trace_and_propagates_upstream() {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+            let provider = SdkTracerProvider::builder().build();
+            let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+            tracing::subscriber::with_default(subscriber, || {
+                let mut incoming = HeaderMap::new();
+                incoming.insert(
+                    "traceparent",
+                    "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                        .parse()
+                        .unwrap(),
+                );
+                let span = super::super::request_span(
+                    &Method::GET,
+                    "/api",
+                    &incoming,
+                    Some("workspace-1"),
+                    Some(3000),
+                );
+
+                let mut upstream = HeaderMap::new();
+                super::super::inject_context(&span, &mut upstream);
+                let traceparent = upstream["traceparent"].to_str().unwrap();
+                assert!(
+                    traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"),
+                    "{traceparent}"
+                );
+                assert!(!traceparent.contains("00f067aa0ba902b7"), "{traceparent}");
+            });
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Bandwidth caps for proxied traffic.
+//!
+//! Each cap is a token bucket refilled at the configured bytes/sec with one
+//! second of burst. Bytes are charged after they move, so a bucket can go
+//! into debt; the next read or body frame then waits until the debt is paid
+//! off. A connection is charged against its own bucket and, when a workspace
+//! cap is set, against the bucket shared by every connection to the same
+//! upstream host.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame, SizeHint};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+/// Bandwidth caps in bytes per second. `None` means unlimited.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BandwidthConfig {
+    /// Cap on each client connection, both directions combined.
+    pub per_connection: Option<u64>,
+    /// Cap shared by all connections to the same workspace.
+    pub per_workspace: Option<u64>,
+}
+
+/// A token bucket that refills at `rate` bytes per second.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket allowing `rate` bytes per second with one second of burst.
+    pub fn new(rate: u64) -> Self {
+        let rate = rate.max(1) as f64;
+        Self {
+            rate,
+            capacity: rate,
+            state: Mutex::new(BucketState {
+                tokens: rate,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState) {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+        state.updated = now;
+    }
+
+    /// Charge `bytes` that have already been transferred.
+    pub fn consume(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        state.tokens -= bytes as f64;
+    }
+
+    /// How long until the bucket is out of debt (zero if it isn't).
+    pub fn wait_time(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+}
+
+/// Buckets for the configured caps. Workspace buckets live as long as some
+/// connection to that workspace holds them.
+#[derive(Debug, Default)]
+pub struct Bandwidth {
+    config: BandwidthConfig,
+    workspaces: Mutex<HashMap<String, Weak<TokenBucket>>>,
+}
+
+impl Bandwidth {
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self {
+            config,
+            workspaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A fresh bucket for a new client connection, if connections are capped.
+    pub fn connection_bucket(&self) -> Option<Arc<TokenBucket>> {
+        self.config
+            .per_connection
+            .map(|rate| Arc::new(TokenBucket::new(rate)))
+    }
+
+    /// The bucket shared by traffic to `workspace`, if workspaces are capped.
+    pub fn workspace_bucket(&self, workspace: &str) -> Option<Arc<TokenBucket>> {
+        let rate = self.config.per_workspace?;
+        let mut workspaces = self.workspaces.lock().unwrap();
+        if let Some(bucket) = workspaces.get(workspace).and_then(Weak::upgrade) {
+            return Some(bucket);
+        }
+        workspaces.retain(|_, bucket| bucket.strong_count() > 0);
+        let bucket = Arc::new(TokenBucket::new(rate));
+        workspaces.insert(workspace.to_string(), Arc::downgrade(&bucket));
+        Some(bucket)
+    }
+}
+
+/// The buckets one stream of traffic is charged against.
+#[derive(Debug, Default)]
+pub struct Limiter {
+    buckets: Vec<Arc<TokenBucket>>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl Limiter {
+    pub fn new(buckets: impl IntoIterator<Item = Arc<TokenBucket>>) -> Self {
+        Self {
+            buckets: buckets.into_iter().collect(),
+            sleep: None,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Ready once every bucket is out of debt.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            let wait = self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.wait_time())
+                .max()
+                .unwrap_or_default();
+            if wait.is_zero() {
+                return Poll::Ready(());
+            }
+            self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+    }
+
+    fn consume(&self, bytes: usize) {
+        for bucket in &self.buckets {
+            bucket.consume(bytes);
+        }
+    }
+}
+
+/// An I/O stream whose reads are rate limited. In a tunnel both ends are
+/// wrapped with the same buckets, which caps traffic in both directions.
+pub struct Throttled<S> {
+    inner: S,
+    limiter: Limiter,
+}
+
+impl<S> Throttled<S> {
+    pub fn new(inner: S, limiter: Limiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Throttled<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if this.limiter.is_unlimited() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+        ready!(this.limiter.poll_ready(cx));
+
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.limiter.consume(buf.filled().len() - before);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Throttled<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, data)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// A body whose data frames are rate limited.
+pub struct ThrottledBody<B> {
+    inner: B,
+    limiter: Limiter,
+}
+
+impl<B> ThrottledBody<B> {
+    pub fn new(inner: B, limiter: Limiter) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+impl<B> Body for ThrottledBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let this = &mut *self;
+        ready!(this.limiter.poll_ready(cx));
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if let Some(data) = frame.as_ref().and_then(|f| f.as_ref().ok()?.data_ref()) {
+            this.limiter.consume(data.len());
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn reads_are_paced_to_the_rate() {
+        let data = vec![7u8; 48 * 1024];
+        let limiter = Limiter::new([Arc::new(TokenBucket::new(16 * 1024))]);
+        let mut reader = Throttled::new(&data[..], limiter);
+        let mut buf = [0u8; 8 * 1024];
+
+        let started = Instant::now();
+        let mut out = Vec::new();
+        loop {
+            let n = reader.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+
+        assert_eq!(out, data);
+        // 16 KiB of burst, then 32 KiB at 16 KiB/s.
+        let elapsed = started.elapsed();
+        assert!(
+            elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(3),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn workspace_buckets_are_shared_until_dropped() {
+        let bandwidth = Bandwidth::new(BandwidthConfig {
+            per_connection: None,
+            per_workspace: Some(1000),
+        });
+        assert!(bandwidth.connection_bucket().is_none());
+
+        let a = bandwidth.workspace_bucket("127.18.0.1").unwrap();
+        let b = bandwidth.workspace_bucket("127.18.0.1").unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        a.consume(1500);
+        assert_eq!(b.wait_time(), Duration::from_millis(500));
+
+        drop((a, b));
+        let fresh = bandwidth.workspace_bucket("127.18.0.1").unwrap();
+        assert_eq!(fresh.wait_time(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,181 @@
+//! Request checks that run before anything is proxied.
+//!
+//! The upstream and the proxy must agree on where a request ends and which
+//! host it is for. Requests that are ambiguous about either (both framing
+//! headers, repeated `Content-Length` or `Host`, or an absolute-form target
+//! naming a different host than `Host`) are the classic request smuggling
+//! vectors, so they are refused with `400` instead of being passed on for the
+//! upstream to interpret differently. Each refusal carries a stable code in
+//! the [`ERROR_HEADER`] response header and at the start of the body.
+//!
+//! hyper's HTTP/1 parser gets to some of these first: it answers differing
+//! `Content-Length` values with its own `400`, keeps one of several equal
+//! ones, and ignores a `Content-Length` that follows `Transfer-Encoding`. In
+//! every case the body is re-framed towards the upstream, so it never sees
+//! both.
+
+use std::fmt;
+use std::str::FromStr;
+
+use http::header::{CONTENT_LENGTH, HOST, TRANSFER_ENCODING};
+use http::uri::Authority;
+use http::{Method, Request, Uri};
+
+/// Response header naming why a request was refused.
+pub const ERROR_HEADER: &str = "X-Cmux-Error";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequestError {
+    /// Both `Content-Length` and `Transfer-Encoding` are present.
+    ConflictingFraming,
+    /// More than one `Content-Length` header field.
+    DuplicateContentLength,
+    /// More than one `Host` header field.
+    DuplicateHost,
+    /// An absolute-form target whose authority differs from `Host`.
+    HostMismatch,
+}
+
+impl RequestError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RequestError::ConflictingFraming => "conflicting_framing",
+            RequestError::DuplicateContentLength => "duplicate_content_length",
+            RequestError::DuplicateHost => "duplicate_host",
+            RequestError::HostMismatch => "host_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RequestError::ConflictingFraming => {
+                "request has both Content-Length and Transfer-Encoding"
+            }
+            RequestError::DuplicateContentLength => "request has more than one Content-Length",
+            RequestError::DuplicateHost => "request has more than one Host",
+            RequestError::HostMismatch => "request target and Host name different hosts",
+        })
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// Refuse requests whose framing or target host is ambiguous.
+pub fn check_request<B>(req: &Request<B>) -> Result<(), RequestError> {
+    let headers = req.headers();
+    if headers.get_all(CONTENT_LENGTH).iter().count() > 1 {
+        return Err(RequestError::DuplicateContentLength);
+    }
+    if headers.contains_key(CONTENT_LENGTH) && headers.contains_key(TRANSFER_ENCODING) {
+        return Err(RequestError::ConflictingFraming);
+    }
+    let mut hosts = headers.get_all(HOST).iter();
+    let host = hosts.next();
+    if hosts.next().is_some() {
+        return Err(RequestError::DuplicateHost);
+    }
+    // CONNECT targets are always an authority; they route by their own rules.
+    if req.method() != Method::CONNECT {
+        if let (Some(target), Some(host)) = (req.uri().authority(), host) {
+            let same = host
+                .to_str()
+                .ok()
+                .and_then(|h| Authority::from_str(h.trim()).ok())
+                .is_some_and(|host| same_authority(req.uri(), target, &host));
+            if !same {
+                return Err(RequestError::HostMismatch);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compare host names case-insensitively and ports with the scheme's default
+/// filled in, so `http://a.localhost/` matches `Host: a.localhost:80`.
+fn same_authority(uri: &Uri, target: &Authority, host: &Authority) -> bool {
+    let default_port = match uri.scheme_str() {
+        Some(scheme) if scheme.eq_ignore_ascii_case("https") => 443,
+        _ => 80,
+    };
+    target.host().eq_ignore_ascii_case(host.host())
+        && target.port_u16().unwrap_or(default_port) == host.port_u16().unwrap_or(default_port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, uri: &str, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn accepts_unambiguous_requests() {
+        let ok = |method, uri, headers: &[(&str, &str)]| {
+            assert_eq!(check_request(&request(method, uri, headers)), Ok(()));
+        };
+        ok(Method::GET, "/", &[("host", "ws-3000.localhost")]);
+        ok(Method::POST, "/", &[("host", "a"), ("content-length", "3")]);
+        ok(Method::POST, "/", &[("transfer-encoding", "chunked")]);
+        ok(
+            Method::GET,
+            "http://WS-3000.localhost/x",
+            &[("host", "ws-3000.localhost:80")],
+        );
+        ok(Method::GET, "http://ws-3000.localhost:8080/x", &[]);
+        ok(
+            Method::CONNECT,
+            "127.0.0.1:5432",
+            &[("host", "ws-3000.localhost")],
+        );
+    }
+
+    #[test]
+    fn refuses_ambiguous_requests() {
+        let err = |method, uri, headers: &[(&str, &str)]| {
+            check_request(&request(method, uri, headers)).unwrap_err()
+        };
+        assert_eq!(
+            err(
+                Method::POST,
+                "/",
+                &[("content-length", "3"), ("transfer-encoding", "chunked")]
+            ),
+            RequestError::ConflictingFraming
+        );
+        assert_eq!(
+            err(
+                Method::POST,
+                "/",
+                &[("content-length", "3"), ("content-length", "3")]
+            ),
+            RequestError::DuplicateContentLength
+        );
+        assert_eq!(
+            err(Method::GET, "/", &[("host", "a"), ("host", "b")]),
+            RequestError::DuplicateHost
+        );
+        assert_eq!(
+            err(
+                Method::GET,
+                "http://a-3000.localhost/",
+                &[("host", "b-3000.localhost")]
+            ),
+            RequestError::HostMismatch
+        );
+        assert_eq!(
+            err(
+                Method::GET,
+                "https://a.localhost/",
+                &[("host", "a.localhost:80")]
+            ),
+            RequestError::HostMismatch
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! What the proxy forwards, checked on both sides with the in-process
+//! upstreams from `support`.
+
+mod support;
+
+use std::net::Ipv4Addr;
+
+use cmux_proxy::ProxyConfig;
+use http::{Method, StatusCode, Version};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_tungstenite::tungstenite::Message;
+
+use support::{
+    assert_header, assert_no_header, connect_tunnel, h1, h2, raw, request, websocket, ws_roundtrip,
+    EchoUpstream, TcpEchoUpstream, TestProxy, WsEchoUpstream, ECHO_RESPONSE_HEADER,
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn port_header_routes_and_internal_headers_stay_behind() {
+    let upstream = EchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+    let port = upstream.port().to_string();
+
+    let reply = h1(
+        proxy.addr,
+        request(
+            Method::POST,
+            "/submit?x=1",
+            &[
+                ("X-Cmux-Port-Internal", &port),
+                ("X-Cmux-Host-Override", "app.internal"),
+                ("X-Request-Id", "abc"),
+            ],
+            "payload",
+        ),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.text(), "payload");
+    assert_eq!(reply.header("x-echo-method"), Some("POST"));
+    assert_eq!(reply.header("x-echo-path"), Some("/submit?x=1"));
+
+    let seen = upstream.last_request();
+    assert_eq!(seen.body, "payload");
+    assert_header(&seen.headers, "host", "app.internal");
+    assert_header(&seen.headers, "x-request-id", "abc");
+    assert_no_header(&seen.headers, "x-cmux-port-internal");
+    assert_no_header(&seen.headers, "x-cmux-host-override");
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn subdomain_host_routes_to_its_port() {
+    let upstream = EchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+    let host = format!("workspace-1-{}.localhost", upstream.port());
+
+    let reply = h1(
+        proxy.addr,
+        request(Method::GET, "/from-host", &[("Host", &host)], ""),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.header("x-echo-path"), Some("/from-host"));
+    assert_eq!(upstream.requests().len(), 1);
+
+    proxy.stop().await;
+}
+
+/// Workspaces live on their own loopback address, which only Linux routes
+/// without extra setup.
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn workspace_header_routes_to_the_workspace_address() {
+    let ip = cmux_proxy::workspace_ip_from_name("workspace-7").unwrap();
+    let workspace = EchoUpstream::start_on(ip.into()).await;
+    let proxy = TestProxy::start().await;
+    let port = workspace.port().to_string();
+
+    let reply = h1(
+        proxy.addr,
+        request(
+            Method::GET,
+            "/ws-route",
+            &[
+                ("X-Cmux-Workspace-Internal", "workspace-7"),
+                ("X-Cmux-Port-Internal", &port),
+            ],
+            "",
+        ),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_no_header(
+        &workspace.last_request().headers,
+        "x-cmux-workspace-internal",
+    );
+
+    // Another workspace's address has nothing on that port.
+    let reply = h1(
+        proxy.addr,
+        request(
+            Method::GET,
+            "/",
+            &[
+                ("X-Cmux-Workspace-Internal", "workspace-8"),
+                ("X-Cmux-Port-Internal", &port),
+            ],
+            "",
+        ),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::BAD_GATEWAY);
+    assert_eq!(workspace.requests().len(), 1);
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn hop_by_hop_headers_are_stripped_both_ways() {
+    let upstream = EchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+
+    let request = format!(
+        "GET /hop HTTP/1.1\r\n\
+         Host: localhost\r\n\
+         X-Cmux-Port-Internal: {}\r\n\
+         Connection: close, X-Client-Private\r\n\
+         X-Client-Private: 1\r\n\
+         Keep-Alive: timeout=5\r\n\
+         Proxy-Connection: keep-alive\r\n\
+         TE: trailers\r\n\
+         X-End-To-End: kept\r\n\
+         {h}: Keep-Alive: timeout=1\r\n\
+         {h}: Connection: X-Upstream-Private\r\n\
+         {h}: X-Upstream-Private: secret\r\n\
+         {h}: X-Upstream-Public: shown\r\n\
+         \r\n",
+        upstream.port(),
+        h = ECHO_RESPONSE_HEADER,
+    );
+    let response = raw(proxy.addr, &request).await;
+
+    let seen = upstream.last_request();
+    assert_header(&seen.headers, "x-end-to-end", "kept");
+    for name in ["x-client-private", "keep-alive", "proxy-connection", "te"] {
+        assert_no_header(&seen.headers, name);
+    }
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(body.is_empty(), "{response}");
+    let head = head.to_ascii_lowercase();
+    assert!(head.contains("x-upstream-public: shown"), "{head}");
+    assert!(!head.contains("x-upstream-private"), "{head}");
+    assert!(!head.contains("keep-alive"), "{head}");
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http2_clients_reach_http1_upstreams() {
+    let upstream = EchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+    let port = upstream.port().to_string();
+
+    let reply = h2(
+        proxy.addr,
+        request(
+            Method::PUT,
+            "/h2",
+            &[("x-cmux-port-internal", &port)],
+            "over h2",
+        ),
+    )
+    .await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.version, Version::HTTP_2);
+    assert_eq!(reply.text(), "over h2");
+
+    let seen = upstream.last_request();
+    assert_eq!(seen.version, Version::HTTP_11);
+    assert_eq!(seen.method, Method::PUT);
+    assert_eq!(seen.body, "over h2");
+    assert_no_header(&seen.headers, "x-cmux-port-internal");
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn websocket_upgrades_tunnel_frames() {
+    let upstream = WsEchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+    let port = upstream.port().to_string();
+
+    let mut ws = websocket(
+        proxy.addr,
+        "/socket?room=1",
+        &[("X-Cmux-Port-Internal", &port)],
+    )
+    .await;
+    assert_eq!(
+        ws_roundtrip(&mut ws, Message::Text("hello".into())).await,
+        Message::Text("hello".into())
+    );
+    let blob = vec![0u8, 1, 2, 255];
+    assert_eq!(
+        ws_roundtrip(&mut ws, Message::Binary(blob.clone())).await,
+        Message::Binary(blob)
+    );
+    ws.close(None).await.unwrap();
+
+    let handshake = upstream.last_handshake();
+    assert_eq!(handshake.uri.path(), "/socket");
+    assert_eq!(handshake.uri.query(), Some("room=1"));
+    assert_header(&handshake.headers, "upgrade", "websocket");
+    assert_no_header(&handshake.headers, "x-cmux-port-internal");
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn connect_tunnels_raw_tcp() {
+    let upstream = TcpEchoUpstream::start().await;
+    let proxy = TestProxy::start().await;
+    let port = upstream.port().to_string();
+
+    let mut tunnel = connect_tunnel(proxy.addr, &[("X-Cmux-Port-Internal", &port)]).await;
+    for payload in [&b"first"[..], &b"\x00binary\xff"[..]] {
+        tunnel.write_all(payload).await.unwrap();
+        let mut echoed = vec![0u8; payload.len()];
+        tunnel.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, payload);
+    }
+
+    proxy.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn connect_without_a_port_is_refused() {
+    let proxy = TestProxy::start_with(ProxyConfig {
+        listen: (Ipv4Addr::LOCALHOST, 0).into(),
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    let response = raw(
+        proxy.addr,
+        "CONNECT upstream HTTP/1.1\r\nHost: upstream\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+    proxy.stop().await;
+}
@@ -4,6 +4,10 @@ use std::net::{Ipv4Addr, SocketAddr};
 use std::time::Duration;
 
 use bytes::Bytes;
+use cmux_proxy::access_log::AccessLog;
+use cmux_proxy::boot_hold::BootHoldConfig;
+use cmux_proxy::slow_client::{SlowClientConfig, SlowClientStats};
+use cmux_proxy::throttle::BandwidthConfig;
 use cmux_proxy::ProxyConfig;
 use futures_util::{FutureExt, SinkExt, StreamExt};
 use http_body_util::BodyExt;
@@ -103,9 +107,11 @@ async fn start_upstream_real_ws_echo_multi() -> (SocketAddr, tokio::task::JoinHa
 }
 
 async fn start_upstream_http() -> SocketAddr {
-    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
-        .await
-        .unwrap();
+    start_upstream_http_at(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await
+}
+
+async fn start_upstream_http_at(addr: SocketAddr) -> SocketAddr {
+    let listener = TcpListener::bind(addr).await.unwrap();
     let local = listener.local_addr().unwrap();
     tokio::spawn(async move {
         loop {
@@ -272,7 +278,14 @@ async fn start_proxy(
         listen,
         upstream_host: upstream_host.to_string(),
         allow_default_upstream,
+        ..ProxyConfig::default()
     };
+    start_proxy_with_config(cfg).await
+}
+
+async fn start_proxy_with_config(
+    cfg: ProxyConfig,
+) -> (SocketAddr, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
     let (tx, rx) = oneshot::channel::<()>();
     let (bound, handle) = cmux_proxy::spawn_proxy(
         cfg,
@@ -772,3 +785,644 @@ async fn test_missing_host_override_keeps_host_header() {
     let _ = shutdown.send(());
     let _ = handle.await;
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_connection_bandwidth_cap_slows_tunnel() {
+    let (echo_addr, _echo_handle) = start_upstream_tcp_echo().await;
+    let cfg = ProxyConfig {
+        listen: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        upstream_host: "127.0.0.1".to_string(),
+        allow_default_upstream: false,
+        bandwidth: BandwidthConfig {
+            per_connection: Some(32 * 1024),
+            per_workspace: None,
+        },
+        ..ProxyConfig::default()
+    };
+    let (proxy_addr, tx, handle) = start_proxy_with_config(cfg).await;
+
+    let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+    let req = format!(
+        "CONNECT foo HTTP/1.1\r\nHost: foo\r\nX-Cmux-Port-Internal: {}\r\n\r\n",
+        echo_addr.port()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut resp_buf = Vec::new();
+    let mut tmp = [0u8; 1];
+    while !resp_buf.ends_with(b"\r\n\r\n") {
+        let n = timeout(Duration::from_secs(5), stream.read(&mut tmp))
+            .await
+            .expect("read timeout")
+            .unwrap();
+        assert!(n > 0);
+        resp_buf.push(tmp[0]);
+    }
+    assert!(resp_buf.starts_with(b"HTTP/1.1 200"));
+
+    // 32 KiB each way is 64 KiB through a 32 KiB/s bucket with 32 KiB of burst.
+    let payload = vec![b'x'; 32 * 1024];
+    let started = std::time::Instant::now();
+    let (mut reader, mut writer) = stream.into_split();
+    let writer_payload = payload.clone();
+    let write = tokio::spawn(async move { writer.write_all(&writer_payload).await });
+    let mut recv = vec![0u8; payload.len()];
+    timeout(Duration::from_secs(10), reader.read_exact(&mut recv))
+        .await
+        .expect("echo timeout")
+        .unwrap();
+    write.await.unwrap().unwrap();
+    assert_eq!(recv, payload);
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(700), "{elapsed:?}");
+
+    let _ = tx.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cors_rule_answers_preflight_and_injects_headers() {
+    let upstream_addr = start_upstream_http().await;
+    let rule = format!("*:{};origin=https://preview.test", upstream_addr.port());
+    let cfg = ProxyConfig {
+        upstream_host: "127.0.0.1".to_string(),
+        allow_default_upstream: true,
+        cors: vec![rule.parse().unwrap()],
+        ..ProxyConfig::default()
+    };
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(cfg).await;
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let url = format!("http://{}/api", proxy_addr);
+
+    // Preflight is answered by the proxy without reaching the upstream
+    let req = Request::builder()
+        .method("OPTIONS")
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .header("Origin", "https://preview.test")
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .body(Empty::new())
+        .unwrap();
+    let resp = timeout(Duration::from_secs(5), client.request(req))
+        .await
+        .expect("resp timeout")
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        resp.headers()["access-control-allow-origin"],
+        "https://preview.test"
+    );
+    assert_eq!(
+        resp.headers()["access-control-allow-headers"],
+        "content-type"
+    );
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.is_empty());
+
+    // Proxied responses get the allow header; other origins don't
+    for (origin, expected) in [
+        ("https://preview.test", Some("https://preview.test")),
+        ("https://other.test", None),
+    ] {
+        let req = Request::builder()
+            .method("GET")
+            .uri(&url)
+            .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+            .header("Origin", origin)
+            .body(Empty::new())
+            .unwrap();
+        let resp = timeout(Duration::from_secs(5), client.request(req))
+            .await
+            .expect("resp timeout")
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let allow = resp
+            .headers()
+            .get("access-control-allow-origin")
+            .map(|v| v.to_str().unwrap());
+        assert_eq!(allow, expected);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"ok:GET:/api");
+    }
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_ports_endpoint_lists_listening_upstreams() {
+    let upstream_addr = start_upstream_http().await;
+    let (proxy_addr, shutdown, handle) = start_proxy(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        true,
+    )
+    .await;
+
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let port = upstream_addr.port();
+    let url = format!(
+        "http://{}/__cmux/ports?range={}-{}",
+        proxy_addr,
+        port - 1,
+        port + 1
+    );
+    let req = Request::builder()
+        .method("GET")
+        .uri(url)
+        .body(Empty::new())
+        .unwrap();
+    let resp = timeout(Duration::from_secs(10), client.request(req))
+        .await
+        .expect("resp timeout")
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["host"], "127.0.0.1");
+    let ports: Vec<u64> = json["ports"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p.as_u64().unwrap())
+        .collect();
+    assert!(ports.contains(&u64::from(port)), "{json}");
+
+    // Requests can't scan outside the configured range.
+    let req = Request::builder()
+        .method("GET")
+        .uri(format!("http://{}/__cmux/ports?range=1-1023", proxy_addr))
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_access_log_reports_each_request() {
+    let upstream_addr = start_upstream_http().await;
+    let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let cfg = ProxyConfig {
+        upstream_host: "127.0.0.1".to_string(),
+        allow_default_upstream: true,
+        access_log: Some(AccessLog::new(move |event| {
+            let _ = events_tx.send(event);
+        })),
+        ..ProxyConfig::default()
+    };
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(cfg).await;
+
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let req = Request::builder()
+        .method("GET")
+        .uri(format!("http://{}/logged", proxy_addr))
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .body(Empty::new())
+        .unwrap();
+    let resp = timeout(Duration::from_secs(5), client.request(req))
+        .await
+        .expect("resp timeout")
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let event = timeout(Duration::from_secs(5), events_rx.recv())
+        .await
+        .expect("access log timeout")
+        .unwrap();
+    assert_eq!(event.method, "GET");
+    assert_eq!(event.path, "/logged");
+    assert_eq!(event.port, Some(upstream_addr.port()));
+    assert_eq!(event.status, 200);
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_boot_hold_replays_requests_once_upstream_listens() {
+    let (proxy_addr, shutdown, handle) = start_proxy(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        true,
+    )
+    .await;
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+
+    let hold = Request::builder()
+        .method("POST")
+        .uri(format!("http://{}/__cmux/boot-hold?seconds=10", proxy_addr))
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(hold).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["active"], true, "{json}");
+
+    // Nothing listens on this port until after the request is sent.
+    let port = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let early = {
+        let client = client.clone();
+        let req = Request::builder()
+            .method("GET")
+            .uri(format!("http://{}/early", proxy_addr))
+            .header("X-Cmux-Port-Internal", port.to_string())
+            .body(Empty::new())
+            .unwrap();
+        tokio::spawn(async move { client.request(req).await })
+    };
+    sleep(Duration::from_millis(400)).await;
+    assert!(!early.is_finished(), "request should be held");
+
+    start_upstream_http_at(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).await;
+    let resp = timeout(Duration::from_secs(5), early)
+        .await
+        .expect("held request timeout")
+        .unwrap()
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"ok:GET:/early");
+
+    let release = Request::builder()
+        .method("DELETE")
+        .uri(format!("http://{}/__cmux/boot-hold", proxy_addr))
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(release).await.unwrap();
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["active"], false, "{json}");
+
+    // Requested limits are capped at the configured maximums.
+    let huge = Request::builder()
+        .method("POST")
+        .uri(format!(
+            "http://{}/__cmux/boot-hold?seconds={}&requests={}",
+            proxy_addr,
+            u64::MAX,
+            usize::MAX
+        ))
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(huge).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let limits = BootHoldConfig::default();
+    assert_eq!(json["maxRequests"], limits.max_requests_limit, "{json}");
+    assert!(json["remainingMs"].as_u64().unwrap() <= limits.max_wait_limit.as_millis() as u64);
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+/// Write `request` on a fresh connection and read until the proxy closes it.
+async fn send_raw(proxy_addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut resp = Vec::new();
+    timeout(Duration::from_secs(5), stream.read_to_end(&mut resp))
+        .await
+        .expect("proxy should close the connection")
+        .unwrap();
+    String::from_utf8_lossy(&resp).into_owned()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_ambiguous_requests_are_refused() {
+    let upstream_addr = start_upstream_http().await;
+    let (proxy_addr, shutdown, handle) = start_proxy(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        false,
+    )
+    .await;
+    let port = upstream_addr.port();
+
+    let cases = [
+        (
+            format!(
+                "POST /cl-te HTTP/1.1\r\nHost: a\r\nX-Cmux-Port-Internal: {port}\r\n\
+                 Content-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n"
+            ),
+            "conflicting_framing",
+        ),
+        (
+            format!(
+                "GET /host-host HTTP/1.1\r\nHost: a\r\nHost: b\r\n\
+                 X-Cmux-Port-Internal: {port}\r\n\r\n"
+            ),
+            "duplicate_host",
+        ),
+        (
+            format!(
+                "GET http://a-{port}.localhost/absolute HTTP/1.1\r\n\
+                 Host: b-{port}.localhost\r\nX-Cmux-Port-Internal: {port}\r\n\r\n"
+            ),
+            "host_mismatch",
+        ),
+    ];
+    for (request, code) in cases {
+        let resp = send_raw(proxy_addr, &request).await;
+        assert!(resp.starts_with("HTTP/1.1 400"), "{code}: {resp}");
+        assert!(
+            resp.to_ascii_lowercase()
+                .contains(&format!("x-cmux-error: {code}\r\n")),
+            "{code}: {resp}"
+        );
+        assert!(!resp.contains("ok:"), "{code} reached the upstream: {resp}");
+    }
+
+    // A matching absolute-form target is proxied as usual.
+    let resp = send_raw(
+        proxy_addr,
+        &format!(
+            "GET http://A.localhost:80/absolute HTTP/1.1\r\nHost: a.localhost\r\n\
+             X-Cmux-Port-Internal: {port}\r\nConnection: close\r\n\r\n"
+        ),
+    )
+    .await;
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    assert!(resp.ends_with("ok:GET:/absolute"), "{resp}");
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+/// Minimal parent proxy: answers `CONNECT` with 200 when the expected
+/// credentials are sent, then tunnels. Reports each CONNECT target.
+async fn start_parent_proxy(
+    authorization: &'static str,
+) -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let local = listener.local_addr().unwrap();
+    let (targets, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let targets = targets.clone();
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    head.push(client.read_u8().await.unwrap());
+                }
+                let head = String::from_utf8(head).unwrap();
+                let target = head
+                    .strip_prefix("CONNECT ")
+                    .and_then(|rest| rest.split(' ').next())
+                    .unwrap()
+                    .to_string();
+                let authorized = head.lines().any(|line| {
+                    line.eq_ignore_ascii_case(&format!("proxy-authorization: {authorization}"))
+                });
+                let _ = targets.send(target.clone());
+                if !authorized {
+                    let _ = client
+                        .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                        .await;
+                    return;
+                }
+                let mut upstream = TcpStream::connect(&target).await.unwrap();
+                client
+                    .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                    .await
+                    .unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+            });
+        }
+    });
+    (local, rx)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upstreams_are_reached_through_parent_proxy() {
+    use cmux_proxy::parent_proxy::ParentProxy;
+
+    let upstream_addr = start_upstream_http().await;
+    let (echo_addr, _echo_handle) = start_upstream_tcp_echo().await;
+    // base64("user:secret")
+    let (parent_addr, mut targets) = start_parent_proxy("Basic dXNlcjpzZWNyZXQ=").await;
+    let parent: ParentProxy = format!("http://user:secret@{parent_addr}").parse().unwrap();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        parent_proxy: Some(parent.clone()),
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    // Plain HTTP goes through a tunnel.
+    let req = Request::builder()
+        .method("GET")
+        .uri(format!("http://{}/hello", proxy_addr))
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let resp = new_test_client().request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(targets.recv().await.unwrap(), upstream_addr.to_string());
+
+    // So does a client's CONNECT, as CONNECT-over-CONNECT.
+    let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+    let req = format!(
+        "CONNECT foo HTTP/1.1\r\nHost: foo\r\nX-Cmux-Port-Internal: {}\r\n\r\n",
+        echo_addr.port()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    assert!(head.starts_with(b"HTTP/1.1 200"));
+    stream.write_all(b"ping").await.unwrap();
+    let mut echoed = [0u8; 4];
+    timeout(Duration::from_secs(5), stream.read_exact(&mut echoed))
+        .await
+        .expect("echo timeout")
+        .unwrap();
+    assert_eq!(&echoed, b"ping");
+    assert_eq!(targets.recv().await.unwrap(), echo_addr.to_string());
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+
+    // Bypassed upstreams are dialled directly.
+    let mut bypassing = parent;
+    bypassing.bypass = vec!["127.0.0.1".parse().unwrap()];
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        parent_proxy: Some(bypassing),
+        ..ProxyConfig::default()
+    })
+    .await;
+    let req = Request::builder()
+        .method("GET")
+        .uri(format!("http://{}/hello", proxy_addr))
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let resp = new_test_client().request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(targets.try_recv().is_err());
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_parent_proxy_refusal_is_a_bad_gateway() {
+    let upstream_addr = start_upstream_http().await;
+    let (parent_addr, mut targets) = start_parent_proxy("Basic dXNlcjpzZWNyZXQ=").await;
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        parent_proxy: Some(format!("http://user:wrong@{parent_addr}").parse().unwrap()),
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    let req = Request::builder()
+        .method("GET")
+        .uri(format!("http://{}/hello", proxy_addr))
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let resp = new_test_client().request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(targets.recv().await.unwrap(), upstream_addr.to_string());
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sharded_accept_loops_serve_every_connection() {
+    use cmux_proxy::listener::ListenerOptions;
+
+    let upstream_addr = start_upstream_http().await;
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        listener: ListenerOptions {
+            accept_tasks: 4,
+            nodelay: true,
+            backlog: 64,
+            ..ListenerOptions::default()
+        },
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    // A fresh connection per request, so connections land on different shards.
+    let requests = (0..32).map(|i| async move {
+        let raw = format!(
+            "GET /r{i} HTTP/1.1\r\nHost: a\r\nX-Cmux-Port-Internal: {}\r\nConnection: close\r\n\r\n",
+            upstream_addr.port()
+        );
+        send_raw(proxy_addr, &raw).await
+    });
+    for resp in futures_util::future::join_all(requests).await {
+        assert!(resp.starts_with("HTTP/1.1 200"), "resp: {resp}");
+    }
+
+    let _ = shutdown.send(());
+    timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("all accept loops stop")
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_slow_request_heads_are_closed_and_counted() {
+    let stats = SlowClientStats::default();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        listen: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        slow_clients: SlowClientConfig {
+            header_read_timeout: Some(Duration::from_millis(300)),
+            ..SlowClientConfig::default()
+        },
+        slow_client_stats: stats.clone(),
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    // One connection sends nothing, the other stops halfway through its head.
+    let silent = TcpStream::connect(proxy_addr).await.unwrap();
+    let mut trickle = TcpStream::connect(proxy_addr).await.unwrap();
+    trickle
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n")
+        .await
+        .unwrap();
+    for mut stream in [silent, trickle] {
+        let mut buf = Vec::new();
+        timeout(Duration::from_secs(3), stream.read_to_end(&mut buf))
+            .await
+            .expect("proxy should close slow connections")
+            .ok();
+    }
+    assert_eq!(stats.header_timeouts(), 2);
+
+    let resp = send_raw(
+        proxy_addr,
+        "GET /__cmux/stats HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    assert!(resp.contains("\"headerTimeouts\":2"), "{resp}");
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_pending_handshakes_are_capped_per_ip() {
+    let stats = SlowClientStats::default();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_config(ProxyConfig {
+        listen: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        slow_clients: SlowClientConfig {
+            max_handshakes_per_ip: Some(1),
+            ..SlowClientConfig::default()
+        },
+        slow_client_stats: stats.clone(),
+        ..ProxyConfig::default()
+    })
+    .await;
+
+    let mut pending = TcpStream::connect(proxy_addr).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+    let mut refused = TcpStream::connect(proxy_addr).await.unwrap();
+    let mut buf = Vec::new();
+    timeout(Duration::from_secs(3), refused.read_to_end(&mut buf))
+        .await
+        .expect("second pending connection should be closed")
+        .ok();
+    assert!(buf.is_empty());
+    assert_eq!(stats.handshake_rejections(), 1);
+
+    // The first connection is still served, and once it has sent a request
+    // the IP may open more.
+    pending
+        .write_all(b"GET /__cmux/stats HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut resp = Vec::new();
+    timeout(Duration::from_secs(3), pending.read_to_end(&mut resp))
+        .await
+        .unwrap()
+        .unwrap();
+    let resp = String::from_utf8_lossy(&resp);
+    assert!(resp.contains("\"handshakeRejections\":1"), "{resp}");
+    let resp = send_raw(
+        proxy_addr,
+        "GET /__cmux/stats HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
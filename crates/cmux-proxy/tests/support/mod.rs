@@ -0,0 +1,474 @@
+//! In-process upstreams and a proxy for integration tests.
+//!
+//! Upstreams record what reached them so tests can check both sides of the
+//! proxy: [`EchoUpstream`] (HTTP/1.1 and h2c), [`WsEchoUpstream`] and
+//! [`TcpEchoUpstream`]. [`TestProxy`] runs the proxy itself, and the client
+//! helpers send HTTP/1.1, HTTP/2 and raw requests through it.
+
+#![allow(dead_code)]
+
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use cmux_proxy::ProxyConfig;
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use http::header::HeaderName;
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri, Version};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder as AutoBuilder;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    Request as WsRequest, Response as WsResponse,
+};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Request header naming a header [`EchoUpstream`] adds to its response, as
+/// `name: value`. Repeatable.
+pub const ECHO_RESPONSE_HEADER: &str = "x-echo-response-header";
+
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A request as an upstream received it.
+#[derive(Clone, Debug)]
+pub struct SeenRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+type Seen = Arc<Mutex<Vec<SeenRequest>>>;
+
+fn latest(seen: &Seen) -> SeenRequest {
+    seen.lock()
+        .unwrap()
+        .last()
+        .cloned()
+        .expect("upstream received no requests")
+}
+
+/// HTTP server answering each request with its body, method and path (in
+/// `x-echo-method` and `x-echo-path`). Speaks HTTP/1.1 and prior-knowledge h2c.
+pub struct EchoUpstream {
+    pub addr: SocketAddr,
+    seen: Seen,
+    task: JoinHandle<()>,
+}
+
+impl EchoUpstream {
+    pub async fn start() -> Self {
+        Self::start_on(IpAddr::V4(Ipv4Addr::LOCALHOST)).await
+    }
+
+    pub async fn start_on(ip: IpAddr) -> Self {
+        let listener = TcpListener::bind(SocketAddr::new(ip, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let seen = Seen::default();
+        let task_seen = seen.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let seen = task_seen.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| echo(seen.clone(), req));
+                    let _ = AutoBuilder::new(TokioExecutor::new())
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        Self { addr, seen, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn requests(&self) -> Vec<SeenRequest> {
+        self.seen.lock().unwrap().clone()
+    }
+
+    pub fn last_request(&self) -> SeenRequest {
+        latest(&self.seen)
+    }
+}
+
+impl Drop for EchoUpstream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn echo(seen: Seen, req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes())
+        .unwrap_or_default();
+    let mut resp = Response::new(Full::new(body.clone()));
+    let headers = resp.headers_mut();
+    headers.insert("x-echo-method", parts.method.as_str().parse().unwrap());
+    if let Some(path) = parts.uri.path_and_query() {
+        headers.insert("x-echo-path", path.as_str().parse().unwrap());
+    }
+    for spec in parts.headers.get_all(ECHO_RESPONSE_HEADER) {
+        let (name, value) = spec.to_str().unwrap().split_once(':').unwrap();
+        headers.append(
+            HeaderName::from_bytes(name.trim().as_bytes()).unwrap(),
+            HeaderValue::from_str(value.trim()).unwrap(),
+        );
+    }
+    seen.lock().unwrap().push(SeenRequest {
+        method: parts.method,
+        uri: parts.uri,
+        version: parts.version,
+        headers: parts.headers,
+        body,
+    });
+    Ok(resp)
+}
+
+/// WebSocket server echoing text and binary frames; pings are answered by
+/// tungstenite. Records each handshake request.
+pub struct WsEchoUpstream {
+    pub addr: SocketAddr,
+    seen: Seen,
+    task: JoinHandle<()>,
+}
+
+impl WsEchoUpstream {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let seen = Seen::default();
+        let task_seen = seen.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let seen = task_seen.clone();
+                tokio::spawn(async move {
+                    // The callback signature (and its large error type) is tungstenite's.
+                    #[allow(clippy::result_large_err)]
+                    let record = |req: &WsRequest, resp: WsResponse| {
+                        seen.lock().unwrap().push(SeenRequest {
+                            method: req.method().clone(),
+                            uri: req.uri().clone(),
+                            version: req.version(),
+                            headers: req.headers().clone(),
+                            body: Bytes::new(),
+                        });
+                        Ok(resp)
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, record).await
+                    else {
+                        return;
+                    };
+                    while let Some(Ok(msg)) = ws.next().await {
+                        if msg.is_text() || msg.is_binary() {
+                            if ws.send(msg).await.is_err() {
+                                break;
+                            }
+                        } else if msg.is_close() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Self { addr, seen, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn last_handshake(&self) -> SeenRequest {
+        latest(&self.seen)
+    }
+}
+
+impl Drop for WsEchoUpstream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// TCP server writing back whatever each connection sends.
+pub struct TcpEchoUpstream {
+    pub addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TcpEchoUpstream {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut read, mut write) = stream.split();
+                    let _ = tokio::io::copy(&mut read, &mut write).await;
+                });
+            }
+        });
+        Self { addr, task }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+}
+
+impl Drop for TcpEchoUpstream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A running proxy, shut down when dropped or through [`TestProxy::stop`].
+pub struct TestProxy {
+    pub addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TestProxy {
+    /// Proxy on an OS-assigned loopback port with otherwise default settings.
+    pub async fn start() -> Self {
+        Self::start_with(ProxyConfig::default()).await
+    }
+
+    /// Proxy with `cfg`; a zero `cfg.listen` port is replaced by the bound one.
+    pub async fn start_with(cfg: ProxyConfig) -> Self {
+        let (tx, rx) = oneshot::channel::<()>();
+        let (addr, handle) = cmux_proxy::spawn_proxy(
+            cfg,
+            async move {
+                let _ = rx.await;
+            }
+            .boxed(),
+        );
+        sleep(Duration::from_millis(25)).await;
+        Self {
+            addr,
+            shutdown: Some(tx),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub async fn stop(mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = timeout(IO_TIMEOUT, handle).await;
+        }
+    }
+}
+
+impl Drop for TestProxy {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// A response with its body read in full.
+#[derive(Debug)]
+pub struct Reply {
+    pub status: StatusCode,
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl Reply {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+}
+
+async fn read_reply(resp: Response<Incoming>) -> Reply {
+    let (parts, body) = resp.into_parts();
+    let body = timeout(IO_TIMEOUT, body.collect())
+        .await
+        .expect("response body timed out")
+        .unwrap()
+        .to_bytes();
+    Reply {
+        status: parts.status,
+        version: parts.version,
+        headers: parts.headers,
+        body,
+    }
+}
+
+/// `GET`/`POST`/... request for `path` with `headers`, ready for [`h1`] or [`h2`].
+pub fn request(
+    method: Method,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Request<Full<Bytes>> {
+    let mut builder = Request::builder().method(method).uri(path);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+/// Send `req` over a new HTTP/1.1 connection to `addr`, with `Host:
+/// localhost` unless it sets its own.
+pub async fn h1(addr: SocketAddr, mut req: Request<Full<Bytes>>) -> Reply {
+    req.headers_mut()
+        .entry(http::header::HOST)
+        .or_insert(HeaderValue::from_static("localhost"));
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+    let resp = timeout(IO_TIMEOUT, sender.send_request(req))
+        .await
+        .expect("HTTP/1.1 request timed out")
+        .unwrap();
+    read_reply(resp).await
+}
+
+/// Send `req` over a new prior-knowledge HTTP/2 connection to `addr`.
+pub async fn h2(addr: SocketAddr, req: Request<Full<Bytes>>) -> Reply {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+    tokio::spawn(conn);
+    let (mut parts, body) = req.into_parts();
+    // HTTP/2 requests carry the authority in the URI.
+    parts.uri = format!("http://{}{}", addr, parts.uri).parse().unwrap();
+    parts.version = Version::HTTP_2;
+    let resp = timeout(
+        IO_TIMEOUT,
+        sender.send_request(Request::from_parts(parts, body)),
+    )
+    .await
+    .expect("HTTP/2 request timed out")
+    .unwrap();
+    read_reply(resp).await
+}
+
+/// Write `request` on a new connection and read until the peer closes it.
+pub async fn raw(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut resp = Vec::new();
+    timeout(IO_TIMEOUT, stream.read_to_end(&mut resp))
+        .await
+        .expect("peer should close the connection")
+        .unwrap();
+    String::from_utf8_lossy(&resp).into_owned()
+}
+
+/// Open a `CONNECT` tunnel through the proxy at `addr` with `headers`,
+/// returning the stream once the proxy has answered 200.
+pub async fn connect_tunnel(addr: SocketAddr, headers: &[(&str, &str)]) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut head = String::from("CONNECT upstream HTTP/1.1\r\nHost: upstream\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await.unwrap();
+
+    // Read byte by byte so nothing past the response head is consumed.
+    let mut resp = Vec::new();
+    while !resp.ends_with(b"\r\n\r\n") {
+        let byte = timeout(IO_TIMEOUT, stream.read_u8())
+            .await
+            .expect("CONNECT response timed out")
+            .expect("proxy closed the CONNECT connection");
+        resp.push(byte);
+    }
+    let resp = String::from_utf8_lossy(&resp);
+    assert!(resp.starts_with("HTTP/1.1 200"), "CONNECT refused: {resp}");
+    stream
+}
+
+pub type WsClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Open a WebSocket to `path` on `addr` with extra handshake `headers`.
+pub async fn websocket(addr: SocketAddr, path: &str, headers: &[(&str, &str)]) -> WsClient {
+    let mut req = format!("ws://{}{}", addr, path)
+        .into_client_request()
+        .unwrap();
+    for (name, value) in headers {
+        req.headers_mut().insert(
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            HeaderValue::from_str(value).unwrap(),
+        );
+    }
+    let (ws, _) = timeout(IO_TIMEOUT, tokio_tungstenite::connect_async(req))
+        .await
+        .expect("WebSocket handshake timed out")
+        .unwrap();
+    ws
+}
+
+/// Send `msg` and return the next data frame.
+pub async fn ws_roundtrip(ws: &mut WsClient, msg: Message) -> Message {
+    ws.send(msg).await.unwrap();
+    loop {
+        let next = timeout(IO_TIMEOUT, ws.next())
+            .await
+            .expect("WebSocket echo timed out")
+            .expect("WebSocket closed")
+            .unwrap();
+        if next.is_text() || next.is_binary() {
+            return next;
+        }
+    }
+}
+
+pub fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[track_caller]
+pub fn assert_header(headers: &HeaderMap, name: &str, expected: &str) {
+    assert_eq!(
+        header(headers, name),
+        Some(expected),
+        "header {name} in {headers:?}"
+    );
+}
+
+#[track_caller]
+pub fn assert_no_header(headers: &HeaderMap, name: &str) {
+    assert!(
+        !headers.contains_key(name),
+        "header {name} should have been removed: {headers:?}"
+    );
+}
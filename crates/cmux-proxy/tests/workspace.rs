@@ -85,6 +85,7 @@ async fn start_proxy(
         listen,
         upstream_host: upstream_host.to_string(),
         allow_default_upstream,
+        ..ProxyConfig::default()
     };
     let (tx, rx) = oneshot::channel::<()>();
     let (bound, handle) = cmux_proxy::spawn_proxy(
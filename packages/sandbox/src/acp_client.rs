@@ -1,19 +1,38 @@
+mod attachments;
+mod branching;
 mod client;
+mod commands;
 mod config;
 mod connection;
 mod demo;
 mod demo_content;
+mod diff;
 mod events;
+mod headless;
+mod history;
+mod keymap;
 mod logging;
 mod markdown;
+mod mentions;
+mod pane;
+mod permissions;
 mod provider;
+mod reconnect;
+mod review;
 mod runner;
 mod state;
+mod tabs;
+mod terminal;
 mod ui;
+mod usage;
 mod workspace_sync;
+mod wrap;
 
 pub use config::load_last_provider;
 pub use demo::run_demo_tui;
+pub use headless::{run_headless, stop_reason_exit_code, HeadlessOptions};
 pub use provider::AcpProvider;
 pub use runner::{run_chat_tui, run_chat_tui_with_workspace_status};
 pub use workspace_sync::WorkspaceSyncStatus;
+
+pub(crate) use pane::ChatPane;
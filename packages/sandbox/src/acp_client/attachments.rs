@@ -0,0 +1,203 @@
+//! Image and resource link content blocks.
+//!
+//! Images are written to a temp file so they can be linked from the chat and
+//! opened later. Terminals that speak the iTerm2 inline image protocol
+//! (iTerm2, WezTerm) can preview them without leaving the client; elsewhere,
+//! including sixel-only terminals, the file is opened with the system viewer.
+
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use agent_client_protocol::{ImageContent, ResourceLink};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::acp_client::state::{App, ChatEntry};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum AttachmentKind {
+    Image,
+    Resource,
+}
+
+/// An image or linked resource shown in the chat.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Attachment {
+    pub(crate) kind: AttachmentKind,
+    pub(crate) name: String,
+    /// File path or URI the attachment opens.
+    pub(crate) target: String,
+    pub(crate) mime_type: Option<String>,
+    pub(crate) description: Option<String>,
+}
+
+impl Attachment {
+    pub(crate) fn from_image(image: &ImageContent) -> std::io::Result<Self> {
+        let target = match image.uri {
+            Some(ref uri) if image.data.is_empty() => uri.clone(),
+            _ => save_image(image)?.display().to_string(),
+        };
+        let name = Path::new(&target)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "image".to_string());
+        Ok(Self {
+            kind: AttachmentKind::Image,
+            name,
+            target,
+            mime_type: Some(image.mime_type.clone()),
+            description: None,
+        })
+    }
+
+    pub(crate) fn from_resource_link(link: &ResourceLink) -> Self {
+        Self {
+            kind: AttachmentKind::Resource,
+            name: link.title.clone().unwrap_or_else(|| link.name.clone()),
+            target: link.uri.clone(),
+            mime_type: link.mime_type.clone(),
+            description: link.description.clone(),
+        }
+    }
+
+    /// Local file behind the attachment, if it has one.
+    pub(crate) fn local_path(&self) -> Option<PathBuf> {
+        let path = match self.target.strip_prefix("file://") {
+            Some(path) => PathBuf::from(path),
+            None if self.target.contains("://") => return None,
+            None => PathBuf::from(&self.target),
+        };
+        path.exists().then_some(path)
+    }
+}
+
+fn image_extension(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        _ => "img",
+    }
+}
+
+/// Write an image's data to the temp directory, named by content so repeated
+/// chunks of the same image share a file.
+fn save_image(image: &ImageContent) -> std::io::Result<PathBuf> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(image.data.trim())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+
+    let dir = std::env::temp_dir().join("cmux-acp-images");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{:016x}.{}",
+        hasher.finish(),
+        image_extension(&image.mime_type)
+    ));
+    if !path.exists() {
+        std::fs::write(&path, &bytes)?;
+    }
+    Ok(path)
+}
+
+/// Whether the terminal understands the iTerm2 inline image protocol.
+pub(crate) fn inline_images_supported() -> bool {
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    matches!(term_program.as_str(), "iTerm.app" | "WezTerm")
+        || std::env::var("LC_TERMINAL").is_ok_and(|t| t == "iTerm2")
+}
+
+/// Print an image with the iTerm2 inline image protocol.
+pub(crate) fn write_inline_image(out: &mut impl Write, path: &Path) -> std::io::Result<()> {
+    let bytes = std::fs::read(path)?;
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let name = engine.encode(path.file_name().unwrap_or_default().as_encoded_bytes());
+    write!(
+        out,
+        "\x1b]1337;File=name={};size={};inline=1;preserveAspectRatio=1:{}\x07",
+        name,
+        bytes.len(),
+        engine.encode(&bytes)
+    )?;
+    out.flush()
+}
+
+impl App<'_> {
+    /// Attachments in the history, most recent first.
+    pub(crate) fn attachments(&self) -> Vec<&Attachment> {
+        self.history
+            .iter()
+            .rev()
+            .filter_map(|entry| match entry {
+                ChatEntry::Attachment(attachment) => Some(attachment),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Open an attachment: preview images inline where the terminal supports
+    /// it, otherwise hand the file or URI to the system opener.
+    pub(crate) fn open_attachment(&mut self, attachment: &Attachment) {
+        if attachment.kind == AttachmentKind::Image && inline_images_supported() {
+            if let Some(path) = attachment.local_path() {
+                self.image_preview = Some(path);
+                return;
+            }
+        }
+        let target = attachment
+            .local_path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| attachment.target.clone());
+        if let Err(e) = open::that(&target) {
+            self.push_entry(ChatEntry::Message {
+                role: "Error".to_string(),
+                text: format!("Failed to open {}: {}", target, e),
+                normalized_markdown: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_saved_and_links_resolve() {
+        let image = ImageContent {
+            annotations: None,
+            data: base64::engine::general_purpose::STANDARD.encode(b"not really a png"),
+            mime_type: "image/png".to_string(),
+            uri: None,
+            meta: None,
+        };
+        let attachment = Attachment::from_image(&image).unwrap();
+        assert_eq!(attachment.kind, AttachmentKind::Image);
+        assert!(attachment.name.ends_with(".png"));
+        let path = attachment.local_path().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"not really a png");
+
+        let link = Attachment::from_resource_link(&ResourceLink {
+            annotations: None,
+            description: None,
+            mime_type: None,
+            name: "spec.md".to_string(),
+            size: None,
+            title: Some("Spec".to_string()),
+            uri: "https://example.com/spec.md".to_string(),
+            meta: None,
+        });
+        assert_eq!(link.name, "Spec");
+        assert_eq!(link.local_path(), None);
+
+        let mut out = Vec::new();
+        write_inline_image(&mut out, &path).unwrap();
+        assert!(out.starts_with(b"\x1b]1337;File=name="));
+    }
+}
@@ -0,0 +1,272 @@
+//! Edit-and-resend of earlier user messages.
+//!
+//! Up on an empty input (or the "Edit Previous Message" command) selects one of
+//! the user's messages; Enter loads it into the input. Sending it drops that
+//! message and everything after it from the chat and continues in a new
+//! session on the same connection, since ACP agents can't truncate a session's
+//! history. The new session gets the kept turns as context with the first
+//! prompt, and the branch is saved as its own history file so the original
+//! conversation stays intact.
+
+use agent_client_protocol::{
+    Agent, CancelNotification, ContentBlock, PromptRequest, SessionId, SessionModelState,
+    SetSessionModelRequest, TextContent,
+};
+
+use crate::acp_client::connection::start_session;
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::logging::log_debug;
+use crate::acp_client::mentions::prompt_blocks;
+use crate::acp_client::state::{chat_textarea, run_prompt, App, ChatEntry, UiMode};
+
+/// The kept turns of a branched conversation, for the new session's first
+/// prompt. `None` when branching from the first message.
+fn branch_context(history: &[ChatEntry]) -> Option<String> {
+    let turns: Vec<String> = history
+        .iter()
+        .filter_map(|entry| match entry {
+            ChatEntry::Message { role, text, .. } if matches!(role.as_str(), "User" | "Agent") => {
+                Some(format!("{role}: {}", text.trim()))
+            }
+            _ => None,
+        })
+        .collect();
+    if turns.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "This conversation continues an earlier session whose later messages were discarded. \
+         The turns so far were:\n\n{}\n\nReply to the next message only.",
+        turns.join("\n\n")
+    ))
+}
+
+fn is_user_message(entry: &ChatEntry) -> bool {
+    matches!(entry, ChatEntry::Message { role, .. } if role == "User")
+}
+
+impl App<'_> {
+    fn user_message_indices(&self) -> Vec<usize> {
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| is_user_message(entry))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Select the most recent user message for editing.
+    pub(crate) fn start_message_selection(&mut self) {
+        if let Some(last) = self.user_message_indices().last() {
+            self.selected_message = Some(*last);
+            self.ui_mode = UiMode::MessageSelect;
+        }
+    }
+
+    /// Move the message selection; negative `delta` moves towards older messages.
+    pub(crate) fn move_message_selection(&mut self, delta: isize) {
+        let indices = self.user_message_indices();
+        let Some(current) = self.selected_message else {
+            return;
+        };
+        let pos = indices.iter().position(|i| *i == current).unwrap_or(0);
+        let target = pos
+            .saturating_add_signed(delta)
+            .min(indices.len().saturating_sub(1));
+        self.selected_message = indices.get(target).copied();
+    }
+
+    pub(crate) fn exit_message_selection(&mut self) {
+        self.selected_message = None;
+        self.ui_mode = UiMode::Chat;
+    }
+
+    /// Put the selected message in the input; sending it resends from there.
+    pub(crate) fn edit_selected_message(&mut self) {
+        let Some(index) = self.selected_message.take() else {
+            return;
+        };
+        self.ui_mode = UiMode::Chat;
+        let Some(ChatEntry::Message { text, .. }) = self.history.get(index) else {
+            return;
+        };
+        let mut textarea = chat_textarea();
+        textarea.insert_str(text);
+        self.textarea = textarea;
+        self.editing_from = Some(index);
+    }
+
+    /// Stop editing and clear the input.
+    pub(crate) fn cancel_edit(&mut self) {
+        if self.editing_from.take().is_some() {
+            self.textarea = chat_textarea();
+        }
+    }
+
+    /// Replace the history from `index` on with `text` and send it in a new
+    /// session, keeping the current model.
+    pub(crate) fn resend_from(&mut self, index: usize, text: String) {
+        let Some(conn) = self.client_connection.clone() else {
+            return;
+        };
+        // The original conversation keeps its file; the branch gets a new one.
+        self.flush_history();
+        self.history.truncate(index);
+        self.history_file = None;
+        self.history_dirty = (0..self.history.len()).collect();
+
+        let mut prompt: Vec<ContentBlock> = branch_context(&self.history)
+            .map(|context| {
+                ContentBlock::Text(TextContent {
+                    text: context,
+                    annotations: None,
+                    meta: None,
+                })
+            })
+            .into_iter()
+            .collect();
+        prompt.extend(prompt_blocks(&text, self.indexed_files()));
+
+        self.push_entry(ChatEntry::Message {
+            role: "User".to_string(),
+            text,
+            normalized_markdown: None,
+        });
+        self.textarea = chat_textarea();
+        self.scroll_to_bottom();
+        self.usage.start_turn();
+
+        let old_session = self.session_id.take();
+        self.available_commands.clear();
+        let model = self
+            .model_state
+            .as_ref()
+            .map(|state| state.current_model_id.clone());
+        let tx = self.event_tx.clone();
+        let tab = self.tab_id;
+
+        tokio::task::spawn_local(async move {
+            if let Some(session_id) = old_session {
+                // Stop a turn that may still be running in the old session.
+                let _ = Agent::cancel(
+                    &*conn,
+                    CancelNotification {
+                        session_id,
+                        meta: None,
+                    },
+                )
+                .await;
+            }
+            let (session_id, mut model_state) = match start_session(&conn).await {
+                Ok(session) => session,
+                Err(error) => {
+                    log_debug(&format!("Branched session failed: {}", error));
+                    let _ = tx.send(AppEvent::for_tab(
+                        tab,
+                        AppEvent::RequestError {
+                            error: format!("Couldn't start a new session: {}", error),
+                        },
+                    ));
+                    return;
+                }
+            };
+            if let (Some(model), Some(state)) = (model, model_state.as_mut()) {
+                if state.current_model_id != model
+                    && state.available_models.iter().any(|m| m.model_id == model)
+                {
+                    let request = SetSessionModelRequest {
+                        session_id: session_id.clone(),
+                        model_id: model.clone(),
+                        meta: None,
+                    };
+                    match Agent::set_session_model(&*conn, request).await {
+                        Ok(_) => state.current_model_id = model,
+                        Err(error) => {
+                            log_debug(&format!("Keeping model after branch failed: {}", error))
+                        }
+                    }
+                }
+            }
+            let _ = tx.send(AppEvent::for_tab(
+                tab,
+                AppEvent::SessionBranched {
+                    session_id: session_id.clone(),
+                    model_state,
+                },
+            ));
+            let request = PromptRequest {
+                session_id,
+                prompt,
+                meta: None,
+            };
+            run_prompt(conn, request, tab, tx).await;
+        });
+    }
+
+    pub(crate) fn on_session_branched(
+        &mut self,
+        session_id: SessionId,
+        model_state: Option<SessionModelState>,
+    ) {
+        self.session_id = Some(session_id);
+        if model_state.is_some() {
+            self.model_state = model_state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp_client::provider::AcpProvider;
+    use tokio::sync::mpsc;
+
+    fn message(role: &str, text: &str) -> ChatEntry {
+        ChatEntry::Message {
+            role: role.to_string(),
+            text: text.to_string(),
+            normalized_markdown: None,
+        }
+    }
+
+    #[test]
+    fn context_keeps_user_and_agent_turns() {
+        let history = [
+            message("System", "Restored previous session."),
+            message("User", "hi"),
+            message("Agent", "hello\n"),
+        ];
+        let context = branch_context(&history).unwrap();
+        assert!(context.contains("User: hi\n\nAgent: hello\n\n"));
+        assert!(!context.contains("Restored"));
+        assert!(branch_context(&history[..1]).is_none());
+    }
+
+    #[test]
+    fn selecting_a_message_loads_it_for_editing() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut app = App::new(AcpProvider::default(), tx, String::new(), String::new());
+        app.history = vec![
+            message("User", "first"),
+            message("Agent", "one"),
+            message("User", "second"),
+            message("Agent", "two"),
+        ];
+
+        app.start_message_selection();
+        assert_eq!(app.selected_message, Some(2));
+        app.move_message_selection(-1);
+        assert_eq!(app.selected_message, Some(0));
+        app.move_message_selection(-1);
+        assert_eq!(app.selected_message, Some(0));
+
+        app.edit_selected_message();
+        assert!(app.ui_mode == UiMode::Chat);
+        assert_eq!(app.editing_from, Some(0));
+        assert_eq!(app.textarea.lines(), ["first"]);
+
+        app.cancel_edit();
+        assert_eq!(app.editing_from, None);
+        assert_eq!(app.textarea.lines(), [""]);
+    }
+}
@@ -7,13 +7,48 @@ use agent_client_protocol::{
     WriteTextFileResponse,
 };
 use anyhow::Result;
-use tokio::sync::mpsc;
+use std::path::Path;
+use tokio::sync::{mpsc, oneshot};
 
 use crate::acp_client::events::AppEvent;
 use crate::acp_client::logging::log_debug;
+use crate::acp_client::permissions::{
+    terminal_permission_request, terminal_permitted, PendingPermission,
+};
+use crate::acp_client::terminal::Terminals;
 
 pub(crate) struct AppClient {
     pub(crate) tx: mpsc::UnboundedSender<AppEvent>,
+    pub(crate) terminals: Terminals,
+}
+
+/// Write a file for the agent's `fs/write_text_file` requests, and for
+/// anything else that changes files on its behalf.
+pub(crate) async fn write_text_file(path: &Path, content: &str) -> std::io::Result<()> {
+    tokio::fs::write(path, content).await
+}
+
+fn unknown_terminal(id: &agent_client_protocol::TerminalId) -> Error {
+    Error::invalid_params().with_data(format!("Unknown terminal: {}", id))
+}
+
+impl AppClient {
+    /// Answer `request` through the permission policy or the user's prompt.
+    async fn ask_permission(&self, request: RequestPermissionRequest) -> RequestPermissionOutcome {
+        log_debug(&format!("RequestPermission: {:?}", request));
+        let (responder, response) = oneshot::channel();
+        let _ = self.tx.send(AppEvent::PermissionRequest(PendingPermission {
+            request,
+            responder,
+        }));
+
+        // The UI dropping the request (e.g. on exit) counts as cancelling it.
+        let outcome = response
+            .await
+            .unwrap_or(RequestPermissionOutcome::Cancelled);
+        log_debug(&format!("RequestPermission outcome: {:?}", outcome));
+        outcome
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -22,15 +57,8 @@ impl Client for AppClient {
         &self,
         request: RequestPermissionRequest,
     ) -> Result<RequestPermissionResponse, Error> {
-        log_debug(&format!("RequestPermission: {:?}", request));
-        let option_id = request
-            .options
-            .first()
-            .map(|o| o.id.clone())
-            .unwrap_or(agent_client_protocol::PermissionOptionId("allow".into()));
-
         Ok(RequestPermissionResponse {
-            outcome: RequestPermissionOutcome::Selected { option_id },
+            outcome: self.ask_permission(request).await,
             meta: None,
         })
     }
@@ -57,7 +85,7 @@ impl Client for AppClient {
         request: WriteTextFileRequest,
     ) -> Result<WriteTextFileResponse, Error> {
         log_debug(&format!("WriteTextFile: {:?}", request.path));
-        match tokio::fs::write(&request.path, &request.content).await {
+        match write_text_file(&request.path, &request.content).await {
             Ok(_) => Ok(WriteTextFileResponse::default()),
             Err(e) => Err(Error::internal_error().with_data(e.to_string())),
         }
@@ -65,37 +93,92 @@ impl Client for AppClient {
 
     async fn create_terminal(
         &self,
-        _request: CreateTerminalRequest,
+        request: CreateTerminalRequest,
     ) -> Result<CreateTerminalResponse, Error> {
-        Err(Error::internal_error().with_data("Terminal not supported yet"))
+        log_debug(&format!(
+            "CreateTerminal: {} {:?} (cwd: {:?})",
+            request.command, request.args, request.cwd
+        ));
+        let outcome = self
+            .ask_permission(terminal_permission_request(&request))
+            .await;
+        if !terminal_permitted(&outcome) {
+            return Err(Error::invalid_request().with_data(format!(
+                "Permission to run `{}` was denied",
+                request.command
+            )));
+        }
+        match self.terminals.create(
+            &request.command,
+            &request.args,
+            &request.env,
+            request.cwd,
+            request.output_byte_limit,
+        ) {
+            Ok(terminal_id) => Ok(CreateTerminalResponse {
+                terminal_id,
+                meta: None,
+            }),
+            Err(e) => {
+                log_debug(&format!("CreateTerminal Error: {}", e));
+                Err(Error::internal_error().with_data(e.to_string()))
+            }
+        }
     }
 
     async fn terminal_output(
         &self,
-        _request: TerminalOutputRequest,
+        request: TerminalOutputRequest,
     ) -> Result<TerminalOutputResponse, Error> {
-        Err(Error::internal_error().with_data("Terminal not supported yet"))
+        let (output, truncated, exit_status) = self
+            .terminals
+            .output(&request.terminal_id)
+            .ok_or_else(|| unknown_terminal(&request.terminal_id))?;
+        Ok(TerminalOutputResponse {
+            output,
+            truncated,
+            exit_status,
+            meta: None,
+        })
     }
 
     async fn release_terminal(
         &self,
-        _request: ReleaseTerminalRequest,
+        request: ReleaseTerminalRequest,
     ) -> Result<ReleaseTerminalResponse, Error> {
-        Err(Error::internal_error().with_data("Terminal not supported yet"))
+        log_debug(&format!("ReleaseTerminal: {}", request.terminal_id));
+        if self.terminals.release(&request.terminal_id) {
+            Ok(ReleaseTerminalResponse::default())
+        } else {
+            Err(unknown_terminal(&request.terminal_id))
+        }
     }
 
     async fn wait_for_terminal_exit(
         &self,
-        _request: WaitForTerminalExitRequest,
+        request: WaitForTerminalExitRequest,
     ) -> Result<WaitForTerminalExitResponse, Error> {
-        Err(Error::internal_error().with_data("Terminal not supported yet"))
+        let exit_status = self
+            .terminals
+            .wait_for_exit(&request.terminal_id)
+            .await
+            .ok_or_else(|| unknown_terminal(&request.terminal_id))?;
+        Ok(WaitForTerminalExitResponse {
+            exit_status,
+            meta: None,
+        })
     }
 
     async fn kill_terminal_command(
         &self,
-        _request: KillTerminalCommandRequest,
+        request: KillTerminalCommandRequest,
     ) -> Result<KillTerminalCommandResponse, Error> {
-        Err(Error::internal_error().with_data("Terminal not supported yet"))
+        log_debug(&format!("KillTerminalCommand: {}", request.terminal_id));
+        match self.terminals.kill(&request.terminal_id) {
+            Some(Ok(())) => Ok(KillTerminalCommandResponse::default()),
+            Some(Err(e)) => Err(Error::internal_error().with_data(e.to_string())),
+            None => Err(unknown_terminal(&request.terminal_id)),
+        }
     }
 
     async fn session_notification(&self, notification: SessionNotification) -> Result<(), Error> {
@@ -106,3 +189,55 @@ impl Client for AppClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::SessionId;
+
+    fn create_request() -> CreateTerminalRequest {
+        CreateTerminalRequest {
+            session_id: SessionId("s".into()),
+            command: "sh".into(),
+            args: vec!["-c".into(), "exit 0".into()],
+            env: Vec::new(),
+            cwd: None,
+            output_byte_limit: None,
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn terminals_need_permission() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let client = AppClient {
+            tx,
+            terminals: Terminals::default(),
+        };
+
+        // Whoever answers, only an explicit allow runs the command.
+        let answers = tokio::spawn(async move {
+            let mut outcomes = vec![
+                None,
+                Some(RequestPermissionOutcome::Cancelled),
+                Some(RequestPermissionOutcome::Selected {
+                    option_id: agent_client_protocol::PermissionOptionId("allow".into()),
+                }),
+            ]
+            .into_iter();
+            while let Some(AppEvent::PermissionRequest(pending)) = rx.recv().await {
+                match outcomes.next().flatten() {
+                    Some(outcome) => pending.respond(outcome),
+                    // Dropped without an answer, like the UI shutting down.
+                    None => drop(pending),
+                }
+            }
+        });
+        assert!(client.create_terminal(create_request()).await.is_err());
+        assert!(client.create_terminal(create_request()).await.is_err());
+        let created = client.create_terminal(create_request()).await.unwrap();
+        assert!(client.terminals.release(&created.terminal_id));
+        drop(client);
+        answers.await.unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+//! Slash commands typed in the chat input.
+//!
+//! Built-in commands run in the client. Commands advertised by the agent via
+//! `AvailableCommandsUpdate` are sent as an ordinary prompt (`/name args`),
+//! which is how ACP agents expect them to be invoked.
+
+use agent_client_protocol::{AvailableCommand, AvailableCommandInput};
+
+use crate::acp_client::state::{chat_textarea, App};
+
+/// Rows shown in the completion popup.
+pub(crate) const MAX_COMPLETIONS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BuiltinCommand {
+    Clear,
+    Provider,
+    Debug,
+}
+
+impl BuiltinCommand {
+    fn all() -> &'static [BuiltinCommand] {
+        &[
+            BuiltinCommand::Clear,
+            BuiltinCommand::Provider,
+            BuiltinCommand::Debug,
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BuiltinCommand::Clear => "clear",
+            BuiltinCommand::Provider => "provider",
+            BuiltinCommand::Debug => "debug",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            BuiltinCommand::Clear => "Clear the conversation",
+            BuiltinCommand::Provider => "Switch provider or model",
+            BuiltinCommand::Debug => "Toggle raw ACP message view",
+        }
+    }
+}
+
+/// An entry in the completion popup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SlashCommand {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    /// Hint for the command's input, if it takes one.
+    pub(crate) input_hint: Option<String>,
+    pub(crate) builtin: bool,
+}
+
+impl SlashCommand {
+    fn from_builtin(command: BuiltinCommand) -> Self {
+        Self {
+            name: command.name().to_string(),
+            description: command.description().to_string(),
+            input_hint: None,
+            builtin: true,
+        }
+    }
+
+    fn from_available(command: &AvailableCommand) -> Self {
+        Self {
+            name: command.name.clone(),
+            description: command.description.clone(),
+            input_hint: command.input.as_ref().map(|input| match input {
+                AvailableCommandInput::Unstructured { hint } => hint.clone(),
+            }),
+            builtin: false,
+        }
+    }
+}
+
+/// The command name being typed, if the input is `/` followed by a partial name.
+fn typed_command_name(input: &str) -> Option<&str> {
+    let name = input.strip_prefix('/')?;
+    if name.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(name)
+}
+
+/// Commands whose name starts with `prefix`, built-ins first. Agent commands
+/// that share a built-in's name are hidden since the built-in takes precedence.
+pub(crate) fn complete(prefix: &str, available: &[AvailableCommand]) -> Vec<SlashCommand> {
+    let prefix = prefix.to_lowercase();
+    let builtins = BuiltinCommand::all()
+        .iter()
+        .map(|c| SlashCommand::from_builtin(*c));
+    let provided = available
+        .iter()
+        .filter(|c| !BuiltinCommand::all().iter().any(|b| b.name() == c.name))
+        .map(SlashCommand::from_available);
+    builtins
+        .chain(provided)
+        .filter(|c| c.name.to_lowercase().starts_with(&prefix))
+        .collect()
+}
+
+/// The built-in command a submitted message invokes, if any.
+pub(crate) fn parse_builtin(input: &str) -> Option<BuiltinCommand> {
+    let name = input.trim().strip_prefix('/')?;
+    BuiltinCommand::all()
+        .iter()
+        .copied()
+        .find(|c| c.name() == name)
+}
+
+impl App<'_> {
+    /// Completions for the current input; empty when the popup is hidden.
+    pub(crate) fn command_completions(&self) -> Vec<SlashCommand> {
+        let lines = self.textarea.lines();
+        if lines.len() != 1 {
+            return vec![];
+        }
+        match typed_command_name(&lines[0]) {
+            Some(prefix) => complete(prefix, &self.available_commands),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn command_popup_visible(&self) -> bool {
+        !self.command_completions().is_empty()
+    }
+
+    pub(crate) fn command_selection_up(&mut self) {
+        self.command_selection = self.command_selection.saturating_sub(1);
+    }
+
+    pub(crate) fn command_selection_down(&mut self) {
+        let count = self.command_completions().len();
+        if self.command_selection + 1 < count {
+            self.command_selection += 1;
+        }
+    }
+
+    /// Replace the input with the selected completion and return it, so the
+    /// caller can wait for input if the command takes any.
+    pub(crate) fn accept_command_completion(&mut self) -> Option<SlashCommand> {
+        let completions = self.command_completions();
+        let selected = completions
+            .get(
+                self.command_selection
+                    .min(completions.len().saturating_sub(1)),
+            )?
+            .clone();
+        self.textarea = chat_textarea();
+        self.textarea.insert_str(format!("/{}", selected.name));
+        self.command_selection = 0;
+        Some(selected)
+    }
+
+    /// Take the input if it invokes a built-in command.
+    pub(crate) fn take_builtin_command(&mut self) -> Option<BuiltinCommand> {
+        let command = parse_builtin(&self.textarea.lines().join("\n"))?;
+        self.textarea = chat_textarea();
+        Some(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available(name: &str) -> AvailableCommand {
+        AvailableCommand {
+            name: name.to_string(),
+            description: String::new(),
+            input: Some(AvailableCommandInput::Unstructured {
+                hint: "topic".to_string(),
+            }),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn completes_builtins_and_agent_commands() {
+        let agent = [available("create_plan"), available("clear")];
+
+        let names: Vec<String> = complete("", &agent).into_iter().map(|c| c.name).collect();
+        assert_eq!(names, ["clear", "provider", "debug", "create_plan"]);
+
+        let matches = complete("CR", &agent);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].input_hint.as_deref(), Some("topic"));
+        assert!(!matches[0].builtin);
+
+        assert_eq!(typed_command_name("/pl"), Some("pl"));
+        assert_eq!(typed_command_name("/plan now"), None);
+        assert_eq!(typed_command_name("plan"), None);
+
+        assert_eq!(parse_builtin(" /debug "), Some(BuiltinCommand::Debug));
+        assert_eq!(parse_builtin("/debug now"), None);
+        assert_eq!(parse_builtin("/create_plan"), None);
+    }
+}
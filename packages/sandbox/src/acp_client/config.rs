@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use crate::acp_client::permissions::PermissionPolicy;
 use crate::acp_client::provider::AcpProvider;
 
 /// Get the cmux config directory (~/.cmux)
@@ -52,3 +53,21 @@ pub(crate) fn save_last_model(provider: AcpProvider, model_id: &str) {
     let path = dir.join(format!("last_model_{}", provider.short_name()));
     let _ = std::fs::write(path, model_id);
 }
+
+/// Load the permission auto-approve policy
+pub(crate) fn load_permission_policy() -> PermissionPolicy {
+    std::fs::read_to_string(get_config_dir().join("acp_permission_policy"))
+        .ok()
+        .and_then(|s| PermissionPolicy::from_short_name(s.trim()))
+        .unwrap_or_default()
+}
+
+/// Save the permission auto-approve policy
+pub(crate) fn save_permission_policy(policy: PermissionPolicy) {
+    let dir = get_config_dir();
+    if !dir.exists() {
+        let _ = std::fs::create_dir_all(&dir);
+    }
+    let path = dir.join("acp_permission_policy");
+    let _ = std::fs::write(path, policy.short_name());
+}
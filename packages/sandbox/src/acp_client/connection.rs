@@ -2,8 +2,8 @@ use std::io;
 use std::sync::Arc;
 
 use agent_client_protocol::{
-    Agent, ClientCapabilities, ClientSideConnection, FileSystemCapability, InitializeRequest,
-    NewSessionRequest, SessionId, SessionModelState, V1,
+    Agent, AgentCapabilities, ClientCapabilities, ClientSideConnection, FileSystemCapability,
+    InitializeRequest, LoadSessionRequest, NewSessionRequest, SessionId, SessionModelState, V1,
 };
 use anyhow::Result;
 use futures::{SinkExt, StreamExt};
@@ -13,6 +13,8 @@ use crate::acp_client::client::AppClient;
 use crate::acp_client::events::AppEvent;
 use crate::acp_client::logging::log_debug;
 use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::terminal::Terminals;
+use crate::client_auth::websocket_request;
 
 /// WebSocket reader wrapper for ACP protocol
 struct WsRead {
@@ -175,6 +177,50 @@ pub(crate) async fn connect_to_provider(
     SessionId,
     Option<SessionModelState>,
 )> {
+    let (client_conn, _) = open_connection(base_url, sandbox_id, provider, tx).await?;
+    let (session_id, model_state) = start_session(&client_conn).await?;
+    Ok((client_conn, session_id, model_state))
+}
+
+/// Reconnect to a provider, resuming `session_id` with `session/load` when the
+/// agent supports it and starting a new session otherwise. The returned flag
+/// says whether the session was resumed.
+pub(crate) async fn resume_session(
+    base_url: &str,
+    sandbox_id: &str,
+    provider: AcpProvider,
+    session_id: Option<SessionId>,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) -> Result<(
+    Arc<ClientSideConnection>,
+    SessionId,
+    Option<SessionModelState>,
+    bool,
+)> {
+    let (client_conn, capabilities) = open_connection(base_url, sandbox_id, provider, tx).await?;
+    if let Some(session_id) = session_id.filter(|_| capabilities.load_session) {
+        log_debug(&format!("Loading session {}", session_id));
+        let response = client_conn
+            .load_session(LoadSessionRequest {
+                mcp_servers: vec![],
+                cwd: std::path::PathBuf::from("/workspace"),
+                session_id: session_id.clone(),
+                meta: None,
+            })
+            .await?;
+        return Ok((client_conn, session_id, response.models, true));
+    }
+    let (session_id, model_state) = start_session(&client_conn).await?;
+    Ok((client_conn, session_id, model_state, false))
+}
+
+/// Open the websocket and initialize the ACP connection.
+async fn open_connection(
+    base_url: &str,
+    sandbox_id: &str,
+    provider: AcpProvider,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) -> Result<(Arc<ClientSideConnection>, AgentCapabilities)> {
     log_debug(&format!(
         "Connecting to provider: {}",
         provider.display_name()
@@ -196,13 +242,16 @@ pub(crate) async fn connect_to_provider(
     );
     log_debug(&format!("Connecting to: {}", url));
 
-    let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
+    let (ws_stream, _) = tokio_tungstenite::connect_async(websocket_request(&url)?).await?;
     log_debug("WebSocket connected");
 
     let (write, read) = ws_stream.split();
 
     let (client_conn, io_task) = ClientSideConnection::new(
-        Arc::new(AppClient { tx: tx.clone() }),
+        Arc::new(AppClient {
+            tx: tx.clone(),
+            terminals: Terminals::default(),
+        }),
         TokioCompatWrite(WsWrite {
             sink: write,
             tx: tx.clone(),
@@ -217,16 +266,25 @@ pub(crate) async fn connect_to_provider(
     );
     let client_conn = Arc::new(client_conn);
 
+    // Only report the drop if someone still holds the connection; connections
+    // closed on purpose (provider switch, model discovery) are gone by then.
+    let watched = Arc::downgrade(&client_conn);
+    let lost_tx = tx.clone();
     tokio::task::spawn_local(async move {
         if let Err(e) = io_task.await {
             log_debug(&format!("IO Task Error: {}", e));
         } else {
             log_debug("IO Task Finished");
         }
+        if watched.upgrade().is_some() {
+            let _ = lost_tx.send(AppEvent::ConnectionLost {
+                connection: watched,
+            });
+        }
     });
 
     log_debug("Sending Initialize...");
-    client_conn
+    let initialize_res = client_conn
         .initialize(InitializeRequest {
             protocol_version: V1,
             client_capabilities: ClientCapabilities {
@@ -235,7 +293,7 @@ pub(crate) async fn connect_to_provider(
                     write_text_file: true,
                     meta: None,
                 },
-                terminal: false,
+                terminal: true,
                 meta: None,
             },
             client_info: None,
@@ -244,6 +302,13 @@ pub(crate) async fn connect_to_provider(
         .await?;
     log_debug("Initialize complete");
 
+    Ok((client_conn, initialize_res.agent_capabilities))
+}
+
+/// Start a new session on an initialized connection.
+pub(crate) async fn start_session(
+    client_conn: &ClientSideConnection,
+) -> Result<(SessionId, Option<SessionModelState>)> {
     log_debug("Starting New Session...");
     let new_session_res = client_conn
         .new_session(NewSessionRequest {
@@ -257,11 +322,7 @@ pub(crate) async fn connect_to_provider(
         new_session_res.models
     ));
 
-    Ok((
-        client_conn,
-        new_session_res.session_id,
-        new_session_res.models,
-    ))
+    Ok((new_session_res.session_id, new_session_res.models))
 }
 
 /// Fetch models from a provider without keeping the connection.
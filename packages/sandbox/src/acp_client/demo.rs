@@ -11,7 +11,8 @@ use tokio::sync::mpsc;
 
 use crate::acp_client::demo_content::{DEMO_CODE_EXAMPLES, DEMO_MARKDOWN_CONTENT};
 use crate::acp_client::markdown::normalize_code_fences;
-use crate::acp_client::state::{App, ChatEntry, ConnectionState};
+use crate::acp_client::runner::handle_tool_key;
+use crate::acp_client::state::{App, ChatEntry, ConnectionState, ToolCallDetails, UiMode};
 use crate::acp_client::ui::ui;
 use crate::terminal_guard;
 
@@ -101,12 +102,17 @@ async fn run_demo_loop<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>)
 
 fn process_demo_event(app: &mut App, event: Event, scroll_delta: &mut i32) -> Option<bool> {
     match event {
+        Event::Key(key) if matches!(app.ui_mode, UiMode::ToolSelect | UiMode::ToolDetail) => {
+            handle_tool_key(app, key);
+            Some(false)
+        }
         Event::Key(key) => {
             if key.modifiers.contains(KeyModifiers::CONTROL) {
                 match key.code {
                     KeyCode::Char('q') | KeyCode::Char('c') | KeyCode::Char('d') => {
                         return Some(true);
                     }
+                    KeyCode::Char('t') => app.start_tool_selection(),
                     _ => {}
                 }
             } else {
@@ -190,60 +196,86 @@ fn create_demo_chat_entries() -> Vec<ChatEntry> {
             title: "Read src/auth/mod.rs".to_string(),
             kind: agent_client_protocol::ToolKind::Read,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-2".to_string(),
             title: "Edit src/auth/jwt.rs - add token validation".to_string(),
             kind: agent_client_protocol::ToolKind::Edit,
             status: agent_client_protocol::ToolCallStatus::InProgress,
+            details: ToolCallDetails {
+                content: vec![agent_client_protocol::ToolCallContent::Diff {
+                    diff: agent_client_protocol::Diff {
+                        path: "src/auth/jwt.rs".into(),
+                        old_text: Some(
+                            "pub fn decode(token: &str) -> Claims {\n    parse(token)\n}\n"
+                                .to_string(),
+                        ),
+                        new_text: "pub fn decode(token: &str) -> Result<Claims, AuthError> {\n    let claims = parse(token)?;\n    validate(&claims)?;\n    Ok(claims)\n}\n"
+                            .to_string(),
+                        meta: None,
+                    },
+                }],
+                locations: vec![],
+                raw_input: None,
+                raw_output: None,
+            },
         },
         ChatEntry::ToolCall {
             id: "tool-3".to_string(),
             title: "Delete src/auth/deprecated.rs".to_string(),
             kind: agent_client_protocol::ToolKind::Delete,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-4".to_string(),
             title: "Move src/utils/hash.rs → src/auth/hash.rs".to_string(),
             kind: agent_client_protocol::ToolKind::Move,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-5".to_string(),
             title: "Search for \"password\" in src/".to_string(),
             kind: agent_client_protocol::ToolKind::Search,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-6".to_string(),
             title: "Execute: cargo test auth::tests".to_string(),
             kind: agent_client_protocol::ToolKind::Execute,
             status: agent_client_protocol::ToolCallStatus::Failed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-7".to_string(),
             title: "Analyzing authentication flow".to_string(),
             kind: agent_client_protocol::ToolKind::Think,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-8".to_string(),
             title: "Fetch https://docs.rs/jsonwebtoken".to_string(),
             kind: agent_client_protocol::ToolKind::Fetch,
             status: agent_client_protocol::ToolCallStatus::Pending,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-9".to_string(),
             title: "Switch to code-review mode".to_string(),
             kind: agent_client_protocol::ToolKind::SwitchMode,
             status: agent_client_protocol::ToolCallStatus::Completed,
+            details: Default::default(),
         },
         ChatEntry::ToolCall {
             id: "tool-10".to_string(),
             title: "Custom: generate-schema".to_string(),
             kind: agent_client_protocol::ToolKind::Other,
             status: agent_client_protocol::ToolCallStatus::InProgress,
+            details: Default::default(),
         },
         ChatEntry::Message {
             role: "User".to_string(),
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use similar::{ChangeTag, TextDiff};
+
+use crate::acp_client::markdown::highlight_for_path;
+
+/// Unchanged lines shown around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+/// Render a file edit as a unified diff with syntax highlighting. A missing
+/// `old_text` means the file is new, so every line is an addition.
+pub(crate) fn diff_to_lines(
+    path: &Path,
+    old_text: Option<&str>,
+    new_text: &str,
+) -> Vec<Line<'static>> {
+    let old_text = old_text.unwrap_or_default();
+    let old_lines = highlight_for_path(old_text, path);
+    let new_lines = highlight_for_path(new_text, path);
+
+    let hunk_style = Style::default().fg(Color::Cyan);
+    let removed_bg = Color::Rgb(70, 20, 20);
+    let added_bg = Color::Rgb(20, 60, 20);
+
+    let diff = TextDiff::from_lines(old_text, new_text);
+    let mut lines = Vec::new();
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        lines.push(Line::styled(
+            format!(
+                "@@ -{},{} +{},{} @@",
+                old_range.start + 1,
+                old_range.len(),
+                new_range.start + 1,
+                new_range.len()
+            ),
+            hunk_style,
+        ));
+
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let (marker, source, index, bg) = match change.tag() {
+                    ChangeTag::Delete => ("-", &old_lines, change.old_index(), Some(removed_bg)),
+                    ChangeTag::Insert => ("+", &new_lines, change.new_index(), Some(added_bg)),
+                    ChangeTag::Equal => (" ", &old_lines, change.old_index(), None),
+                };
+                let highlighted = index
+                    .and_then(|i| source.get(i))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        Line::from(change.value().trim_end_matches('\n').to_owned())
+                    });
+
+                let mut spans = vec![Span::raw(marker)];
+                spans.extend(highlighted.spans);
+                let mut line = Line::from(spans);
+                if let Some(bg) = bg {
+                    line = line.style(Style::default().bg(bg));
+                }
+                lines.push(line);
+            }
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(Line::styled(
+            "(no changes)",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers(lines: &[Line<'_>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| {
+                l.spans
+                    .first()
+                    .map(|s| s.content.to_string())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_hunks_with_change_markers() {
+        let old = "fn main() {\n    println!(\"hi\");\n}\n";
+        let new = "fn main() {\n    println!(\"hello\");\n}\n";
+        let lines = diff_to_lines(Path::new("src/main.rs"), Some(old), new);
+        assert_eq!(markers(&lines), ["@@ -1,3 +1,3 @@", " ", "-", "+", " "]);
+
+        let created = diff_to_lines(Path::new("notes.txt"), None, "a\nb\n");
+        assert_eq!(markers(&created), ["@@ -1,0 +1,2 @@", "+", "+"]);
+    }
+}
@@ -1,8 +1,10 @@
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 use agent_client_protocol::{ModelId, SessionId, SessionModelState, SessionNotification};
 
+use crate::acp_client::permissions::PendingPermission;
 use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::usage::TurnUsage;
 use crate::acp_client::workspace_sync::WorkspaceSyncStatus;
 
 pub(crate) enum AppEvent {
@@ -23,6 +25,18 @@ pub(crate) enum AppEvent {
         provider: AcpProvider,
         error: String,
     },
+    /// A new session replaced the tab's session after an earlier message was edited
+    SessionBranched {
+        session_id: SessionId,
+        model_state: Option<SessionModelState>,
+    },
+    /// A file from the changes review was put back, or why it couldn't be
+    ChangeReverted {
+        path: std::path::PathBuf,
+        /// The agent's content the revert replaced
+        new_text: String,
+        result: Result<(), String>,
+    },
     /// Model switch completed successfully
     ModelSwitchComplete {
         model_id: ModelId,
@@ -46,4 +60,43 @@ pub(crate) enum AppEvent {
         provider: AcpProvider,
     },
     WorkspaceSyncStatus(WorkspaceSyncStatus),
+    /// Workspace files for `@` mentions, or why they couldn't be listed
+    WorkspaceIndexed(Result<Vec<String>, String>),
+    /// Agent asked for permission; answered via the pending request's responder
+    PermissionRequest(PendingPermission),
+    /// A connection's websocket closed while it was still in use
+    ConnectionLost {
+        connection: Weak<agent_client_protocol::ClientSideConnection>,
+    },
+    /// Starting reconnect attempt `attempt`
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Reconnected; `resumed` if the previous session was loaded
+    Reconnected {
+        connection: Arc<agent_client_protocol::ClientSideConnection>,
+        session_id: SessionId,
+        model_state: Option<SessionModelState>,
+        resumed: bool,
+    },
+    /// Every reconnect attempt failed
+    ReconnectFailed {
+        error: String,
+    },
+    /// Usage reported in a prompt response's `_meta`
+    UsageReported(TurnUsage),
+    /// An event for a specific chat tab, which may not be the active one
+    ForTab {
+        tab: u64,
+        event: Box<AppEvent>,
+    },
+}
+
+impl AppEvent {
+    pub(crate) fn for_tab(tab: u64, event: AppEvent) -> AppEvent {
+        AppEvent::ForTab {
+            tab,
+            event: Box::new(event),
+        }
+    }
 }
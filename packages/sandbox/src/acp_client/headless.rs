@@ -0,0 +1,237 @@
+//! Non-interactive ACP runs (`cmux acp run`).
+//!
+//! Sends a single prompt over the same connection the TUI uses and streams
+//! session updates to stdout, either as JSON lines or as plain agent text,
+//! until the agent stops. There's nobody to answer permission requests, so
+//! they're decided by a policy up front.
+
+use std::io::Write;
+
+use agent_client_protocol::{
+    Agent, ContentBlock, ModelId, PromptRequest, RequestPermissionOutcome, SessionNotification,
+    SessionUpdate, SetSessionModelRequest, StopReason, TextContent,
+};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::acp_client::connection::connect_to_provider;
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::permissions::{PendingPermission, PermissionPolicy};
+use crate::acp_client::provider::{registry_error, AcpProvider};
+
+pub struct HeadlessOptions {
+    pub prompt: String,
+    pub provider: AcpProvider,
+    /// Emit every event as a JSON line instead of plain agent text.
+    pub json: bool,
+    /// Permission policy short name (`ask`, `reads`, `all`). Requests the
+    /// policy doesn't approve are rejected.
+    pub permissions: String,
+    /// Bearer token sent to the sandbox daemon.
+    pub auth_token: Option<String>,
+}
+
+/// Process exit code for a stop reason; 0 only when the agent finished its turn.
+pub fn stop_reason_exit_code(reason: StopReason) -> i32 {
+    match reason {
+        StopReason::EndTurn => 0,
+        StopReason::MaxTokens => 2,
+        StopReason::MaxTurnRequests => 3,
+        StopReason::Refusal => 4,
+        StopReason::Cancelled => 5,
+    }
+}
+
+/// Run one prompt against a sandbox and return the agent's stop reason.
+pub async fn run_headless(
+    base_url: String,
+    sandbox_id: String,
+    options: HeadlessOptions,
+) -> Result<StopReason> {
+    let policy = PermissionPolicy::from_short_name(&options.permissions)
+        .ok_or_else(|| anyhow!("unknown permission policy: {}", options.permissions))?;
+    crate::client_auth::set_auth_token(options.auth_token.clone());
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(run(base_url, sandbox_id, options, policy))
+        .await
+}
+
+async fn run(
+    base_url: String,
+    sandbox_id: String,
+    options: HeadlessOptions,
+    policy: PermissionPolicy,
+) -> Result<StopReason> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if let Some(error) = registry_error() {
+        eprintln!("{}", error);
+    }
+    let (connection, session_id, model_state) =
+        connect_to_provider(&base_url, &sandbox_id, options.provider, tx).await?;
+
+    let default_model = options.provider.default_model().filter(|model| {
+        model_state.as_ref().is_some_and(|state| {
+            *state.current_model_id.0 != **model
+                && state
+                    .available_models
+                    .iter()
+                    .any(|m| *m.model_id.0 == **model)
+        })
+    });
+    if let Some(model) = default_model {
+        let request = SetSessionModelRequest {
+            session_id: session_id.clone(),
+            model_id: ModelId::from(model.to_string()),
+            meta: None,
+        };
+        Agent::set_session_model(&*connection, request).await?;
+    }
+
+    let request = PromptRequest {
+        session_id,
+        prompt: vec![ContentBlock::Text(TextContent {
+            text: options.prompt,
+            annotations: None,
+            meta: None,
+        })],
+        meta: None,
+    };
+    let prompt = Agent::prompt(&*connection, request);
+    tokio::pin!(prompt);
+
+    let mut out = Output::new(options.json);
+    let response = loop {
+        tokio::select! {
+            response = &mut prompt => break response?,
+            Some(event) = rx.recv() => out.event(event, policy)?,
+        }
+    };
+    // Updates sent just before the response may still be queued.
+    while let Ok(event) = rx.try_recv() {
+        out.event(event, policy)?;
+    }
+    out.stop(response.stop_reason)?;
+    Ok(response.stop_reason)
+}
+
+struct Output {
+    json: bool,
+    stdout: std::io::Stdout,
+    /// Whether plain output ended mid-line, so the next line starts fresh.
+    mid_line: bool,
+}
+
+impl Output {
+    fn new(json: bool) -> Self {
+        Self {
+            json,
+            stdout: std::io::stdout(),
+            mid_line: false,
+        }
+    }
+
+    fn event(&mut self, event: AppEvent, policy: PermissionPolicy) -> Result<()> {
+        match event {
+            AppEvent::SessionUpdate(notification) => self.session_update(*notification),
+            AppEvent::PermissionRequest(pending) => self.permission(pending, policy),
+            AppEvent::RequestError { error } => Err(anyhow!(error)),
+            _ => Ok(()),
+        }
+    }
+
+    fn session_update(&mut self, notification: SessionNotification) -> Result<()> {
+        if self.json {
+            return self.line(&json!({"type": "session_update", "update": notification}));
+        }
+        match notification.update {
+            SessionUpdate::AgentMessageChunk(chunk) => {
+                if let ContentBlock::Text(text) = chunk.content {
+                    write!(self.stdout, "{}", text.text)?;
+                    self.mid_line = !text.text.ends_with('\n');
+                    self.stdout.flush()?;
+                }
+            }
+            SessionUpdate::ToolCall(tool_call) => {
+                self.end_line()?;
+                eprintln!("[tool] {} ({:?})", tool_call.title, tool_call.status);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn permission(&mut self, pending: PendingPermission, policy: PermissionPolicy) -> Result<()> {
+        let auto = policy.auto_option(&pending.request);
+        let allowed = auto.is_some();
+        let outcome = match auto {
+            Some(option_id) => RequestPermissionOutcome::Selected { option_id },
+            None => pending.reject_outcome(),
+        };
+        if self.json {
+            self.line(&json!({
+                "type": "permission",
+                "toolCall": pending.request.tool_call,
+                "outcome": outcome,
+            }))?;
+        } else {
+            self.end_line()?;
+            let title = pending
+                .request
+                .tool_call
+                .fields
+                .title
+                .clone()
+                .unwrap_or_else(|| pending.request.tool_call.id.to_string());
+            let verdict = if allowed { "allowed" } else { "rejected" };
+            eprintln!("[permission] {} {}", verdict, title);
+        }
+        pending.respond(outcome);
+        Ok(())
+    }
+
+    fn stop(&mut self, reason: StopReason) -> Result<()> {
+        if self.json {
+            return self.line(&json!({"type": "stop", "stopReason": reason}));
+        }
+        self.end_line()?;
+        eprintln!(
+            "[stop] {}",
+            serde_json::to_value(reason)?.as_str().unwrap_or("unknown")
+        );
+        Ok(())
+    }
+
+    fn line(&mut self, value: &serde_json::Value) -> Result<()> {
+        writeln!(self.stdout, "{}", value)?;
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<()> {
+        if self.mid_line {
+            writeln!(self.stdout)?;
+            self.mid_line = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_finished_turn_exits_zero() {
+        assert_eq!(stop_reason_exit_code(StopReason::EndTurn), 0);
+        for reason in [
+            StopReason::MaxTokens,
+            StopReason::MaxTurnRequests,
+            StopReason::Refusal,
+            StopReason::Cancelled,
+        ] {
+            assert_ne!(stop_reason_exit_code(reason), 0);
+        }
+    }
+}
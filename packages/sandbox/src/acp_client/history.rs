@@ -0,0 +1,176 @@
+//! On-disk chat history for the ACP client.
+//!
+//! Each chat session is a JSONL file under `<history dir>/<sandbox id>/`. Lines
+//! are `{"index": n, "entry": ...}` records; an entry that changes after it was
+//! written (a streamed message, a tool call status, a plan) is appended again
+//! with the same index and the last record wins when loading.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::acp_client::config::get_config_dir;
+use crate::acp_client::state::ChatEntry;
+
+/// Overrides the history directory (defaults to `~/.cmux/chat-history`).
+const HISTORY_DIR_ENV: &str = "CMUX_CHAT_HISTORY_DIR";
+/// How many sessions "Load previous session" offers.
+pub(crate) const RECENT_SESSION_LIMIT: usize = 20;
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    index: usize,
+    entry: ChatEntry,
+}
+
+/// A saved session as listed in the session picker.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct SessionSummary {
+    pub(crate) path: PathBuf,
+    pub(crate) modified: SystemTime,
+    pub(crate) preview: String,
+}
+
+pub(crate) fn history_dir() -> PathBuf {
+    std::env::var_os(HISTORY_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_config_dir().join("chat-history"))
+}
+
+fn sandbox_dir(root: &Path, sandbox_id: &str) -> PathBuf {
+    let safe: String = sandbox_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    root.join(safe)
+}
+
+/// Path for a new session file, named by start time so files sort chronologically.
+pub(crate) fn new_session_file(root: &Path, sandbox_id: &str) -> PathBuf {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    sandbox_dir(root, sandbox_id).join(format!("{}.jsonl", stamp))
+}
+
+/// Append `(index, entry)` records to a session file, creating it if needed.
+pub(crate) fn append_entries<'a>(
+    path: &Path,
+    entries: impl IntoIterator<Item = (usize, &'a ChatEntry)>,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = String::new();
+    for (index, entry) in entries {
+        let line = serde_json::to_string(&Record {
+            index,
+            entry: entry.clone(),
+        })?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    if out.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(out.as_bytes())
+}
+
+/// Load a session file. Unreadable lines (e.g. a torn final write) are skipped.
+pub(crate) fn load_session(path: &Path) -> std::io::Result<Vec<ChatEntry>> {
+    let file = std::fs::File::open(path)?;
+    let mut entries = BTreeMap::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if let Ok(record) = serde_json::from_str::<Record>(&line) {
+            entries.insert(record.index, record.entry);
+        }
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// Saved sessions for a sandbox, most recent first.
+pub(crate) fn list_sessions(root: &Path, sandbox_id: &str, limit: usize) -> Vec<SessionSummary> {
+    let Ok(read_dir) = std::fs::read_dir(sandbox_dir(root, sandbox_id)) else {
+        return vec![];
+    };
+    let mut sessions: Vec<SessionSummary> = read_dir
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some(SessionSummary {
+                preview: session_preview(&path),
+                path,
+                modified,
+            })
+        })
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+    sessions.truncate(limit);
+    sessions
+}
+
+/// First user message of a session, for display in the picker.
+fn session_preview(path: &Path) -> String {
+    let entries = load_session(path).unwrap_or_default();
+    let first_user = entries.iter().find_map(|entry| match entry {
+        ChatEntry::Message { role, text, .. } if role == "User" => Some(text.as_str()),
+        _ => None,
+    });
+    match first_user {
+        Some(text) => text.lines().next().unwrap_or_default().to_string(),
+        None => format!("({} entries)", entries.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, text: &str) -> ChatEntry {
+        ChatEntry::Message {
+            role: role.to_string(),
+            text: text.to_string(),
+            normalized_markdown: None,
+        }
+    }
+
+    #[test]
+    fn later_records_replace_earlier_ones_with_the_same_index() {
+        let root = tempfile::tempdir().unwrap();
+        let path = new_session_file(root.path(), "sb/1");
+
+        append_entries(
+            &path,
+            [
+                (0, &message("User", "fix the build")),
+                (1, &message("Agent", "Look")),
+            ],
+        )
+        .unwrap();
+        append_entries(&path, [(1, &message("Agent", "Looking now"))]).unwrap();
+
+        let entries = load_session(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[1], ChatEntry::Message { text, .. } if text == "Looking now"));
+
+        let sessions = list_sessions(root.path(), "sb/1", RECENT_SESSION_LIMIT);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].preview, "fix the build");
+        assert!(list_sessions(root.path(), "other", RECENT_SESSION_LIMIT).is_empty());
+    }
+}
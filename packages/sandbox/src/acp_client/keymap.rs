@@ -0,0 +1,539 @@
+//! Remappable key bindings for the chat TUI.
+//!
+//! Named actions are bound to key sequences per context (the chat input or a
+//! palette). `~/.config/cmux/keybindings.toml` can rebind any of them:
+//!
+//! ```toml
+//! [chat]
+//! command_palette = "ctrl+p"
+//! review_changes = ["ctrl+x r", "f2"]
+//! tool_calls = []            # unbind
+//!
+//! [palette]
+//! palette_down = ["ctrl+n", "down"]
+//! ```
+//!
+//! A binding is one or more space-separated chords; multi-chord bindings wait
+//! for the rest of the sequence. Actions the file names replace their default
+//! bindings; the rest keep them. Keys that aren't bound keep their built-in
+//! meaning (typing, Enter to send, arrows to navigate).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeyContext {
+    Chat,
+    Palette,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    NewLine,
+    CommandPalette,
+    SwitchProvider,
+    ToolCalls,
+    EditPreviousMessage,
+    ReviewChanges,
+    NewTab,
+    CloseTab,
+    ShowKeybindings,
+    PaletteUp,
+    PaletteDown,
+    PaletteClose,
+}
+
+impl Action {
+    const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::NewLine,
+        Action::CommandPalette,
+        Action::SwitchProvider,
+        Action::ToolCalls,
+        Action::EditPreviousMessage,
+        Action::ReviewChanges,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::ShowKeybindings,
+        Action::PaletteUp,
+        Action::PaletteDown,
+        Action::PaletteClose,
+    ];
+
+    /// The name used in `keybindings.toml`.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NewLine => "new_line",
+            Action::CommandPalette => "command_palette",
+            Action::SwitchProvider => "switch_provider",
+            Action::ToolCalls => "tool_calls",
+            Action::EditPreviousMessage => "edit_previous_message",
+            Action::ReviewChanges => "review_changes",
+            Action::NewTab => "new_tab",
+            Action::CloseTab => "close_tab",
+            Action::ShowKeybindings => "show_keybindings",
+            Action::PaletteUp => "palette_up",
+            Action::PaletteDown => "palette_down",
+            Action::PaletteClose => "palette_close",
+        }
+    }
+
+    fn context(&self) -> KeyContext {
+        match self {
+            Action::PaletteUp | Action::PaletteDown | Action::PaletteClose => KeyContext::Palette,
+            _ => KeyContext::Chat,
+        }
+    }
+
+    fn default_bindings(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["ctrl+q", "ctrl+c", "ctrl+d"],
+            Action::NewLine => &["ctrl+j"],
+            Action::CommandPalette => &["ctrl+o"],
+            Action::SwitchProvider => &["ctrl+m"],
+            Action::ToolCalls => &["ctrl+t"],
+            Action::PaletteUp => &["ctrl+p", "ctrl+k"],
+            Action::PaletteDown => &["ctrl+n", "ctrl+j"],
+            Action::PaletteClose => &["ctrl+c", "ctrl+g"],
+            Action::EditPreviousMessage
+            | Action::ReviewChanges
+            | Action::NewTab
+            | Action::CloseTab
+            | Action::ShowKeybindings => &[],
+        }
+    }
+}
+
+/// One key press with its modifiers. Shifted characters are stored as the
+/// character they produce, without `SHIFT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = modifiers
+            & (KeyModifiers::CONTROL
+                | KeyModifiers::ALT
+                | KeyModifiers::SUPER
+                | KeyModifiers::SHIFT);
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Self {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // "+" and "ctrl++" bind the plus key.
+        if text == "+" || text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let Some((key, mods)) = parts.split_last() else {
+            return Err(format!("empty key in {text:?}"));
+        };
+        for modifier in mods {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                "super" | "cmd" => KeyModifiers::SUPER,
+                other => return Err(format!("unknown modifier {other:?} in {text:?}")),
+            };
+        }
+        let lower = key.to_ascii_lowercase();
+        let code = match lower.as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('f'), Some(_)) => lower[1..]
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=24).contains(n))
+                        .map(KeyCode::F)
+                        .ok_or_else(|| format!("unknown key {key:?} in {text:?}"))?,
+                    _ => return Err(format!("unknown key {key:?} in {text:?}")),
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+            (KeyModifiers::SUPER, "Super+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) if c.is_ascii_uppercase() => write!(f, "Shift+{c}"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Enter => f.write_str("Enter"),
+            KeyCode::Esc => f.write_str("Esc"),
+            KeyCode::Tab => f.write_str("Tab"),
+            KeyCode::Backspace => f.write_str("Backspace"),
+            KeyCode::Delete => f.write_str("Del"),
+            KeyCode::Insert => f.write_str("Ins"),
+            KeyCode::Home => f.write_str("Home"),
+            KeyCode::End => f.write_str("End"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::Left => f.write_str("←"),
+            KeyCode::Right => f.write_str("→"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            _ => f.write_str("?"),
+        }
+    }
+}
+
+/// A sequence of chords pressed one after another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeySequence(Vec<KeyChord>);
+
+impl KeySequence {
+    fn parse(text: &str) -> Result<Self, String> {
+        let chords = text
+            .split_whitespace()
+            .map(KeyChord::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if chords.is_empty() {
+            return Err("empty key binding".to_string());
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, chord) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{chord}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a key press means given the chords pressed before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeyResolution {
+    Action(Action),
+    /// The key starts or continues a longer binding.
+    Pending,
+    /// Not bound; the key keeps its built-in meaning.
+    Unbound,
+}
+
+pub(crate) struct Keymap {
+    bindings: Vec<(Action, Vec<KeySequence>)>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeybindingsFile {
+    #[serde(default)]
+    chat: BTreeMap<String, Bindings>,
+    #[serde(default)]
+    palette: BTreeMap<String, Bindings>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Bindings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let sequences = action
+                    .default_bindings()
+                    .iter()
+                    .map(|text| KeySequence::parse(text).expect("valid default binding"))
+                    .collect();
+                (*action, sequences)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    fn from_toml(contents: &str) -> Result<Self, String> {
+        let file: KeybindingsFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let mut keymap = Self::default();
+        for (context, section, entries) in [
+            (KeyContext::Chat, "chat", file.chat),
+            (KeyContext::Palette, "palette", file.palette),
+        ] {
+            for (name, bindings) in entries {
+                let (action, sequences) = keymap
+                    .bindings
+                    .iter_mut()
+                    .find(|(action, _)| action.name() == name && action.context() == context)
+                    .ok_or_else(|| format!("unknown action {name:?} in [{section}]"))?;
+                let texts = match bindings {
+                    Bindings::One(text) => vec![text],
+                    Bindings::Many(texts) => texts,
+                };
+                *sequences = texts
+                    .iter()
+                    .map(|text| KeySequence::parse(text))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("{} ({})", e, action.name()))?;
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Resolve `key` in `context`, given the chords in `pending` that were
+    /// pressed before it. `pending` is updated for the next key.
+    pub(crate) fn resolve(
+        &self,
+        context: KeyContext,
+        pending: &mut Vec<KeyChord>,
+        key: KeyEvent,
+    ) -> KeyResolution {
+        let chord = KeyChord::from(key);
+        let had_pending = !pending.is_empty();
+        pending.push(chord);
+        let mut prefix = false;
+        for (action, sequences) in &self.bindings {
+            if action.context() != context {
+                continue;
+            }
+            for sequence in sequences {
+                if sequence.0 == *pending {
+                    pending.clear();
+                    return KeyResolution::Action(*action);
+                }
+                prefix |= sequence.0.starts_with(pending);
+            }
+        }
+        if prefix {
+            return KeyResolution::Pending;
+        }
+        pending.clear();
+        if had_pending {
+            // A broken sequence: the last key counts on its own.
+            return self.resolve(context, pending, key);
+        }
+        KeyResolution::Unbound
+    }
+
+    /// Whether the single key `key` triggers `action`.
+    pub(crate) fn is_bound(&self, action: Action, key: KeyEvent) -> bool {
+        let chord = KeyChord::from(key);
+        self.sequences(action).iter().any(|s| s.0 == [chord])
+    }
+
+    pub(crate) fn sequences(&self, action: Action) -> &[KeySequence] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, sequences)| sequences.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The first binding of `action` for hints, if it has one.
+    pub(crate) fn hint(&self, action: Action) -> Option<String> {
+        self.sequences(action).first().map(ToString::to_string)
+    }
+
+    /// Every action with its bindings, as shown by "Show Keybindings".
+    pub(crate) fn describe(&self) -> String {
+        let mut text = format!("Key bindings ({}):\n", keybindings_path().display());
+        for (context, title) in [(KeyContext::Chat, "chat"), (KeyContext::Palette, "palette")] {
+            text.push_str(&format!("\n[{title}]\n"));
+            for (action, sequences) in &self.bindings {
+                if action.context() != context {
+                    continue;
+                }
+                let keys = if sequences.is_empty() {
+                    "(unbound)".to_string()
+                } else {
+                    sequences
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                text.push_str(&format!("{:<24}{}\n", action.name(), keys));
+            }
+        }
+        text
+    }
+}
+
+fn keybindings_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".config/cmux/keybindings.toml")
+}
+
+struct Loaded {
+    keymap: Keymap,
+    /// Why `keybindings.toml` was ignored, if it was.
+    error: Option<String>,
+}
+
+fn loaded() -> &'static Loaded {
+    static KEYMAP: OnceLock<Loaded> = OnceLock::new();
+    KEYMAP.get_or_init(|| {
+        let path = keybindings_path();
+        let result = match std::fs::read_to_string(&path) {
+            Ok(contents) => Keymap::from_toml(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Keymap::default()),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(keymap) => Loaded {
+                keymap,
+                error: None,
+            },
+            Err(e) => Loaded {
+                keymap: Keymap::default(),
+                error: Some(format!("Ignoring {}: {}", path.display(), e)),
+            },
+        }
+    })
+}
+
+/// The key bindings in effect, loaded once per process.
+pub(crate) fn keymap() -> &'static Keymap {
+    &loaded().keymap
+}
+
+/// Why the key bindings file couldn't be used, if it couldn't.
+pub(crate) fn keymap_error() -> Option<&'static str> {
+    loaded().error.as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_and_formats_chords() {
+        let sequence = KeySequence::parse("ctrl+x shift+r F5").unwrap();
+        assert_eq!(sequence.to_string(), "Ctrl+X Shift+R F5");
+        assert_eq!(
+            KeyChord::parse("Ctrl+O").unwrap(),
+            KeyChord::from(key(KeyCode::Char('o'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            KeyChord::parse("shift+g").unwrap(),
+            KeyChord::from(key(KeyCode::Char('G'), KeyModifiers::SHIFT))
+        );
+        assert!(KeyChord::parse("hyper+x").is_err());
+        assert!(KeyChord::parse("ctrl+nope").is_err());
+    }
+
+    #[test]
+    fn file_overrides_defaults_and_resolves_sequences() {
+        let keymap = Keymap::from_toml(
+            "[chat]\ncommand_palette = \"ctrl+p\"\nreview_changes = [\"ctrl+x r\"]\ntool_calls = []\n",
+        )
+        .unwrap();
+        let ctrl = |c| key(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let mut pending = Vec::new();
+
+        assert_eq!(
+            keymap.resolve(KeyContext::Chat, &mut pending, ctrl('p')),
+            KeyResolution::Action(Action::CommandPalette)
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::Chat, &mut pending, ctrl('o')),
+            KeyResolution::Unbound
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::Chat, &mut pending, ctrl('t')),
+            KeyResolution::Unbound
+        );
+        assert_eq!(
+            keymap.resolve(KeyContext::Chat, &mut pending, ctrl('x')),
+            KeyResolution::Pending
+        );
+        assert_eq!(
+            keymap.resolve(
+                KeyContext::Chat,
+                &mut pending,
+                key(KeyCode::Char('r'), KeyModifiers::NONE)
+            ),
+            KeyResolution::Action(Action::ReviewChanges)
+        );
+        // A broken sequence resolves its last key alone.
+        keymap.resolve(KeyContext::Chat, &mut pending, ctrl('x'));
+        assert_eq!(
+            keymap.resolve(KeyContext::Chat, &mut pending, ctrl('q')),
+            KeyResolution::Action(Action::Quit)
+        );
+        assert!(pending.is_empty());
+        // Palette bindings stay separate.
+        assert_eq!(
+            keymap.resolve(KeyContext::Palette, &mut pending, ctrl('j')),
+            KeyResolution::Action(Action::PaletteDown)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_actions() {
+        let error = Keymap::from_toml("[palette]\nquit = \"q\"\n")
+            .err()
+            .unwrap();
+        assert!(error.contains("unknown action \"quit\""), "{error}");
+    }
+}
@@ -8,6 +8,8 @@ use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
 use syntect::util::LinesWithEndings;
 
+use crate::acp_client::wrap::wrap_line;
+
 // Use two-face's extended syntax set which includes TypeScript, Kotlin, Swift, etc.
 static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(two_face::syntax::extra_newlines);
 static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
@@ -30,8 +32,12 @@ static HIGHLIGHT_THEME: LazyLock<Theme> = LazyLock::new(|| {
         .unwrap_or_default()
 });
 
-/// Convert markdown text to ratatui Lines with syntax highlighting for code blocks
-pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
+/// Columns taken by the gutter drawn in front of code block lines.
+const CODE_GUTTER_WIDTH: usize = 2;
+
+/// Convert markdown text to ratatui Lines with syntax highlighting for code blocks,
+/// wrapped to `width` columns
+pub(crate) fn markdown_to_lines(source: &str, width: usize) -> Vec<Line<'static>> {
     let mut lines: Vec<Line<'static>> = Vec::new();
     let mut current_spans: Vec<Span<'static>> = Vec::new();
 
@@ -45,7 +51,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
         match event {
             MdEvent::Start(Tag::CodeBlock(kind)) => {
                 if !current_spans.is_empty() {
-                    lines.push(Line::from(std::mem::take(&mut current_spans)));
+                    push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                 }
                 lines.push(Line::from(""));
                 in_code_block = true;
@@ -64,7 +70,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             }
             MdEvent::End(TagEnd::CodeBlock) => {
                 let highlighted_lines = highlight_code(&code_content, code_lang.as_deref());
-                lines.extend(highlighted_lines);
+                lines.extend(with_code_gutter(highlighted_lines, width));
                 lines.push(Line::from(""));
                 in_code_block = false;
                 code_lang = None;
@@ -81,7 +87,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
                             current_spans.push(Span::raw(part.to_owned()));
                         }
                         if parts.peek().is_some() {
-                            lines.push(Line::from(std::mem::take(&mut current_spans)));
+                            push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                         }
                     }
                 }
@@ -95,12 +101,12 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             MdEvent::Start(Tag::Paragraph) => {}
             MdEvent::End(TagEnd::Paragraph) => {
                 if !current_spans.is_empty() {
-                    lines.push(Line::from(std::mem::take(&mut current_spans)));
+                    push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                 }
             }
             MdEvent::SoftBreak | MdEvent::HardBreak => {
                 if !current_spans.is_empty() {
-                    lines.push(Line::from(std::mem::take(&mut current_spans)));
+                    push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                 }
             }
             MdEvent::Start(Tag::Heading { level, .. }) => {
@@ -112,7 +118,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             }
             MdEvent::End(TagEnd::Heading(_)) => {
                 if !current_spans.is_empty() {
-                    lines.push(Line::from(std::mem::take(&mut current_spans)));
+                    push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                 }
             }
             MdEvent::Start(Tag::Item) => {
@@ -120,7 +126,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             }
             MdEvent::End(TagEnd::Item) => {
                 if !current_spans.is_empty() {
-                    lines.push(Line::from(std::mem::take(&mut current_spans)));
+                    push_wrapped(&mut lines, std::mem::take(&mut current_spans), width);
                 }
             }
             _ => {}
@@ -128,12 +134,38 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
     }
 
     if !current_spans.is_empty() {
-        lines.push(Line::from(current_spans));
+        push_wrapped(&mut lines, current_spans, width);
     }
 
     lines
 }
 
+/// Push a prose line, wrapped. List items continue under their text, not the bullet.
+fn push_wrapped(lines: &mut Vec<Line<'static>>, spans: Vec<Span<'static>>, width: usize) {
+    let indent = match spans.first() {
+        Some(span) if span.content == "• " => 2,
+        _ => 0,
+    };
+    lines.extend(wrap_line(&Line::from(spans), width, indent));
+}
+
+/// Prefix code lines with a gutter, wrapping long lines without reflowing them.
+/// Continuation rows get a dashed gutter so they read as one source line.
+fn with_code_gutter(code_lines: Vec<Line<'static>>, width: usize) -> Vec<Line<'static>> {
+    let gutter_style = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+    let code_width = width.saturating_sub(CODE_GUTTER_WIDTH).max(1);
+    let mut out = Vec::with_capacity(code_lines.len());
+    for line in code_lines {
+        for (i, row) in wrap_line(&line, code_width, 0).into_iter().enumerate() {
+            let gutter = if i == 0 { "│ " } else { "┆ " };
+            let mut spans = vec![Span::styled(gutter, gutter_style)];
+            spans.extend(row.spans);
+            out.push(Line::from(spans));
+        }
+    }
+    out
+}
+
 /// Normalize code fence language tokens to syntect-compatible format.
 /// Uses two-face's extended syntax set which includes TypeScript, Kotlin, Swift, etc.
 pub(crate) fn normalize_code_fences(content: &str) -> String {
@@ -160,6 +192,15 @@ pub(crate) fn normalize_code_fences(content: &str) -> String {
     normalized
 }
 
+/// Highlight a file's contents, picking the syntax from its extension.
+pub(crate) fn highlight_for_path(code: &str, path: &std::path::Path) -> Vec<Line<'static>> {
+    let lang = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(canonical_language_token);
+    highlight_code(code, lang.as_deref())
+}
+
 fn highlight_code(code: &str, lang: Option<&str>) -> Vec<Line<'static>> {
     let mut lines = Vec::new();
 
@@ -0,0 +1,319 @@
+//! `@file` mentions in the chat input.
+//!
+//! Typing `@` opens a fuzzy finder over the sandbox workspace. The file list
+//! is fetched once in the background through the exec API, using git's ignore
+//! rules when the workspace is a repository. When the prompt is sent, every
+//! `@path` naming an indexed file becomes a `resource_link` content block
+//! pointing at the file instead of staying in the text.
+
+use agent_client_protocol::{ContentBlock, ResourceLink, TextContent};
+use tokio::sync::mpsc;
+
+use crate::acp_client::commands::MAX_COMPLETIONS;
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::logging::log_debug;
+use crate::acp_client::state::App;
+use crate::client_auth::http_client;
+use crate::models::{ExecRequest, ExecResponse};
+use crate::palette::fuzzy_score;
+
+/// Where the agent's session runs inside the sandbox.
+const WORKSPACE_ROOT: &str = "/workspace";
+/// Files kept in the index; larger workspaces are cut off.
+const MAX_INDEXED_FILES: usize = 20_000;
+
+/// State of the workspace file index, shared by every tab.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) enum WorkspaceIndex {
+    #[default]
+    NotLoaded,
+    Loading,
+    Loaded(Vec<String>),
+    Failed(String),
+}
+
+/// Lists workspace files relative to the root: tracked and untracked but not
+/// ignored files in a git repository, every file outside `.git` otherwise.
+fn list_files_request() -> ExecRequest {
+    let script = format!(
+        "cd {WORKSPACE_ROOT} && {{ git ls-files -co --exclude-standard 2>/dev/null \
+         || find . -type f -not -path './.git/*' | sed 's|^\\./||'; }} | head -n {MAX_INDEXED_FILES}"
+    );
+    ExecRequest {
+        command: vec!["/bin/sh".to_string(), "-c".to_string(), script],
+        workdir: None,
+        env: Vec::new(),
+    }
+}
+
+/// Index the sandbox workspace in the background; the result arrives as
+/// [`AppEvent::WorkspaceIndexed`].
+pub(crate) fn spawn_workspace_index(
+    base_url: String,
+    sandbox_id: String,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) {
+    tokio::spawn(async move {
+        let url = format!(
+            "{}/sandboxes/{}/exec",
+            base_url.trim_end_matches('/'),
+            sandbox_id
+        );
+        let result = async {
+            let response: ExecResponse = http_client()
+                .post(&url)
+                .json(&list_files_request())
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok::<_, reqwest::Error>(response)
+        }
+        .await;
+        let files = match result {
+            Ok(response) if response.exit_code == 0 => Ok(response
+                .stdout
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()),
+            Ok(response) => Err(response.stderr.trim().to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        if let Err(ref error) = files {
+            log_debug(&format!("Workspace index failed: {}", error));
+        }
+        let _ = tx.send(AppEvent::WorkspaceIndexed(files));
+    });
+}
+
+/// The query after `@` of the mention being typed before the cursor. A
+/// mention starts the line or follows whitespace.
+fn typed_mention(before_cursor: &str) -> Option<&str> {
+    let start = before_cursor
+        .rfind(char::is_whitespace)
+        .map(|i| i + before_cursor[i..].chars().next().unwrap().len_utf8())
+        .unwrap_or(0);
+    before_cursor[start..].strip_prefix('@')
+}
+
+/// Indexed files matching `query`, best first.
+pub(crate) fn complete(query: &str, files: &[String]) -> Vec<String> {
+    if query.is_empty() {
+        return files.iter().take(MAX_COMPLETIONS).cloned().collect();
+    }
+    let mut scored: Vec<(i64, &String)> = files
+        .iter()
+        .filter_map(|file| fuzzy_score(query, file).map(|score| (score, file)))
+        .collect();
+    // Shorter paths first among equal scores: `src/lib.rs` before `vendor/x/src/lib.rs`.
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.len().cmp(&b.1.len())));
+    scored
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(|(_, file)| file.clone())
+        .collect()
+}
+
+fn resource_link(path: &str) -> ContentBlock {
+    let name = path.rsplit('/').next().unwrap_or(path).to_string();
+    ContentBlock::ResourceLink(ResourceLink {
+        annotations: None,
+        description: None,
+        mime_type: None,
+        name,
+        size: None,
+        title: Some(path.to_string()),
+        uri: format!("file://{WORKSPACE_ROOT}/{path}"),
+        meta: None,
+    })
+}
+
+fn text_block(text: String) -> ContentBlock {
+    ContentBlock::Text(TextContent {
+        text,
+        annotations: None,
+        meta: None,
+    })
+}
+
+/// Split a prompt into content blocks, turning `@path` mentions of files in
+/// `files` into resource links. Anything else stays text.
+pub(crate) fn prompt_blocks(text: &str, files: &[String]) -> Vec<ContentBlock> {
+    let mut blocks = Vec::new();
+    let mut pending = String::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let starts_word = rest[..at]
+            .chars()
+            .next_back()
+            .or_else(|| pending.chars().next_back())
+            .is_none_or(char::is_whitespace);
+        let after = &rest[at + 1..];
+        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+        let path = &after[..end];
+        if starts_word && !path.is_empty() && files.iter().any(|f| f == path) {
+            pending.push_str(&rest[..at]);
+            if !pending.is_empty() {
+                blocks.push(text_block(std::mem::take(&mut pending)));
+            }
+            blocks.push(resource_link(path));
+            rest = &after[end..];
+        } else {
+            pending.push_str(&rest[..=at]);
+            rest = after;
+        }
+    }
+    pending.push_str(rest);
+    if !pending.is_empty() {
+        blocks.push(text_block(pending));
+    }
+    blocks
+}
+
+impl App<'_> {
+    /// The `@` query before the cursor, if the cursor is in a mention.
+    fn current_mention(&self) -> Option<String> {
+        let (row, col) = self.textarea.cursor();
+        let line = self.textarea.lines().get(row)?;
+        let byte = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+        typed_mention(&line[..byte]).map(str::to_string)
+    }
+
+    /// Start indexing the workspace the first time a mention is typed.
+    pub(crate) fn ensure_workspace_index(&mut self) {
+        if self.workspace_index == WorkspaceIndex::NotLoaded && self.current_mention().is_some() {
+            self.workspace_index = WorkspaceIndex::Loading;
+            spawn_workspace_index(
+                self.base_url.clone(),
+                self.sandbox_id.clone(),
+                self.event_tx.clone(),
+            );
+        }
+    }
+
+    pub(crate) fn on_workspace_indexed(&mut self, files: Result<Vec<String>, String>) {
+        self.workspace_index = match files {
+            Ok(files) => WorkspaceIndex::Loaded(files),
+            Err(error) => WorkspaceIndex::Failed(error),
+        };
+        self.mention_selection = 0;
+    }
+
+    pub(crate) fn indexed_files(&self) -> &[String] {
+        match &self.workspace_index {
+            WorkspaceIndex::Loaded(files) => files,
+            _ => &[],
+        }
+    }
+
+    /// Files offered for the mention being typed; empty when there is none.
+    pub(crate) fn mention_completions(&self) -> Vec<String> {
+        match self.current_mention() {
+            Some(query) => complete(&query, self.indexed_files()),
+            None => vec![],
+        }
+    }
+
+    /// Whether the file finder is shown: a mention is being typed and the
+    /// index is loading or has files to offer.
+    pub(crate) fn mention_popup_visible(&self) -> bool {
+        self.current_mention().is_some()
+            && match &self.workspace_index {
+                WorkspaceIndex::Loading => true,
+                WorkspaceIndex::Loaded(_) => !self.mention_completions().is_empty(),
+                WorkspaceIndex::NotLoaded | WorkspaceIndex::Failed(_) => false,
+            }
+    }
+
+    pub(crate) fn mention_selection_up(&mut self) {
+        self.mention_selection = self.mention_selection.saturating_sub(1);
+    }
+
+    pub(crate) fn mention_selection_down(&mut self) {
+        let count = self.mention_completions().len();
+        if self.mention_selection + 1 < count {
+            self.mention_selection += 1;
+        }
+    }
+
+    /// Replace the query with the selected file and a trailing space.
+    pub(crate) fn accept_mention(&mut self) -> bool {
+        let Some(query) = self.current_mention() else {
+            return false;
+        };
+        let completions = self.mention_completions();
+        let Some(path) = completions.get(
+            self.mention_selection
+                .min(completions.len().saturating_sub(1)),
+        ) else {
+            return false;
+        };
+        for _ in 0..query.chars().count() {
+            self.textarea.delete_char();
+        }
+        self.textarea.insert_str(format!("{path} "));
+        self.mention_selection = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<String> {
+        [
+            "src/main.rs",
+            "src/lib.rs",
+            "vendor/x/src/lib.rs",
+            "README.md",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    #[test]
+    fn finds_the_mention_before_the_cursor() {
+        assert_eq!(typed_mention("@sr"), Some("sr"));
+        assert_eq!(typed_mention("look at @src/li"), Some("src/li"));
+        assert_eq!(typed_mention("look at @"), Some(""));
+        assert_eq!(typed_mention("mail me@example"), None);
+        assert_eq!(typed_mention("@src/lib.rs and"), None);
+    }
+
+    #[test]
+    fn completes_shorter_paths_first() {
+        let matches = complete("lib.rs", &files());
+        assert_eq!(matches, ["src/lib.rs", "vendor/x/src/lib.rs"]);
+        assert_eq!(complete("", &files()).len(), 4);
+        assert!(complete("zzz", &files()).is_empty());
+    }
+
+    #[test]
+    fn mentions_of_indexed_files_become_resource_links() {
+        let blocks = prompt_blocks("explain @src/lib.rs vs @nope and me@src/lib.rs", &files());
+        let kinds: Vec<String> = blocks
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text(text) => format!("text:{}", text.text),
+                ContentBlock::ResourceLink(link) => format!("link:{}", link.uri),
+                _ => "other".to_string(),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "text:explain ",
+                "link:file:///workspace/src/lib.rs",
+                "text: vs @nope and me@src/lib.rs",
+            ]
+        );
+
+        let blocks = prompt_blocks("@README.md", &files());
+        assert!(
+            matches!(&blocks[..], [ContentBlock::ResourceLink(link)] if link.name == "README.md")
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! The chat view as a pane inside another TUI (the mux).
+//!
+//! The owner draws it into a rect, forwards key/paste/mouse events while it
+//! has focus, and polls [`ChatPane::update`] so it sees its connections' events. Connections are
+//! spawned with `spawn_local`, so panes have to live inside a `LocalSet`.
+
+use crossterm::event::Event;
+use ratatui::layout::Rect;
+use ratatui::Frame;
+use tokio::sync::mpsc;
+
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::runner::{
+    handle_app_event, handle_terminal_event, new_chat_app, spawn_provider_tasks, EventOutcome,
+};
+use crate::acp_client::state::{App, UiMode};
+use crate::acp_client::tabs::INITIAL_TAB_ID;
+use crate::acp_client::ui::render_chat;
+
+pub(crate) struct ChatPane {
+    app: App<'static>,
+    rx: mpsc::UnboundedReceiver<AppEvent>,
+}
+
+impl ChatPane {
+    /// Start a chat with `provider` in `sandbox_id`.
+    pub(crate) fn start(base_url: String, sandbox_id: String, provider: AcpProvider) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        spawn_provider_tasks(
+            tx.clone(),
+            INITIAL_TAB_ID,
+            base_url.clone(),
+            sandbox_id.clone(),
+            provider,
+        );
+        Self {
+            app: new_chat_app(tx, base_url, sandbox_id, provider),
+            rx,
+        }
+    }
+
+    pub(crate) fn render(&mut self, f: &mut Frame, area: Rect) {
+        // Inline images need the whole terminal; the mux can't hand it over.
+        if self.app.image_preview.take().is_some() {
+            self.app.ui_mode = UiMode::Chat;
+        }
+        render_chat(f, &mut self.app, area);
+    }
+
+    /// Apply input while the pane has focus. Returns false once the user asks
+    /// to leave the chat.
+    pub(crate) fn handle_input(&mut self, event: Event) -> bool {
+        handle_terminal_event(&mut self.app, event) != EventOutcome::Quit
+    }
+
+    /// Wait for the next event from the chat's connections and apply it.
+    /// Cancel-safe; pends forever once every connection has gone away.
+    pub(crate) async fn update(&mut self) {
+        match self.rx.recv().await {
+            Some(event) => handle_app_event(&mut self.app, event),
+            None => std::future::pending().await,
+        }
+    }
+
+    pub(crate) fn flush_history(&mut self) {
+        self.app.flush_all_history();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[tokio::test]
+    async fn renders_inside_its_area_and_quits_on_ctrl_c() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                // Nothing listens here; the connection fails in the background.
+                let mut pane = ChatPane::start(
+                    "http://127.0.0.1:9".to_string(),
+                    "sandbox".to_string(),
+                    AcpProvider::default(),
+                );
+                pane.app.history_dir = None;
+
+                let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+                let area = Rect::new(30, 5, 30, 15);
+                terminal.draw(|f| pane.render(f, area)).unwrap();
+                let buffer = terminal.backend().buffer();
+                assert!((0..30).all(|x| buffer[(x, 0)].symbol() == " "));
+
+                assert!(pane.handle_input(Event::Paste("hello".to_string())));
+                assert_eq!(pane.app.textarea.lines(), ["hello"]);
+                let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+                assert!(!pane.handle_input(Event::Key(ctrl_c)));
+            })
+            .await;
+    }
+}
@@ -0,0 +1,236 @@
+use agent_client_protocol::{
+    CreateTerminalRequest, PermissionOption, PermissionOptionId, PermissionOptionKind,
+    RequestPermissionOutcome, RequestPermissionRequest, ToolCallId, ToolCallUpdate,
+    ToolCallUpdateFields, ToolKind,
+};
+use tokio::sync::oneshot;
+
+const TERMINAL_ALLOW: &str = "allow";
+const TERMINAL_REJECT: &str = "reject";
+
+/// How `session/request_permission` calls are answered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum PermissionPolicy {
+    /// Prompt for every request.
+    #[default]
+    AlwaysAsk,
+    /// Approve reads and searches, prompt for everything else.
+    AllowReads,
+    /// Approve everything without prompting.
+    AllowAll,
+}
+
+impl PermissionPolicy {
+    pub(crate) fn short_name(&self) -> &'static str {
+        match self {
+            PermissionPolicy::AlwaysAsk => "ask",
+            PermissionPolicy::AllowReads => "reads",
+            PermissionPolicy::AllowAll => "all",
+        }
+    }
+
+    pub(crate) fn from_short_name(name: &str) -> Option<Self> {
+        match name {
+            "ask" => Some(PermissionPolicy::AlwaysAsk),
+            "reads" => Some(PermissionPolicy::AllowReads),
+            "all" => Some(PermissionPolicy::AllowAll),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn display_name(&self) -> &'static str {
+        match self {
+            PermissionPolicy::AlwaysAsk => "always ask",
+            PermissionPolicy::AllowReads => "allow reads",
+            PermissionPolicy::AllowAll => "allow all",
+        }
+    }
+
+    /// The option to pick without asking, if this policy covers the request.
+    pub(crate) fn auto_option(
+        &self,
+        request: &RequestPermissionRequest,
+    ) -> Option<PermissionOptionId> {
+        let covered = match self {
+            PermissionPolicy::AlwaysAsk => false,
+            PermissionPolicy::AllowReads => matches!(
+                request.tool_call.fields.kind,
+                Some(ToolKind::Read | ToolKind::Search)
+            ),
+            PermissionPolicy::AllowAll => true,
+        };
+        if !covered {
+            return None;
+        }
+        // Grant once rather than always so a later policy change still applies.
+        [
+            PermissionOptionKind::AllowOnce,
+            PermissionOptionKind::AllowAlways,
+        ]
+        .iter()
+        .find_map(|kind| request.options.iter().find(|o| o.kind == *kind))
+        .map(|o| o.id.clone())
+    }
+}
+
+/// The prompt for an agent's `terminal/create`, which runs a command on this
+/// machine: an execute tool call the policy approves or the user answers.
+pub(crate) fn terminal_permission_request(
+    request: &CreateTerminalRequest,
+) -> RequestPermissionRequest {
+    let command = std::iter::once(request.command.as_str())
+        .chain(request.args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let option = |id: &str, name: &str, kind| PermissionOption {
+        id: PermissionOptionId(id.into()),
+        name: name.to_string(),
+        kind,
+        meta: None,
+    };
+    RequestPermissionRequest {
+        session_id: request.session_id.clone(),
+        tool_call: ToolCallUpdate {
+            id: ToolCallId("terminal/create".into()),
+            fields: ToolCallUpdateFields {
+                kind: Some(ToolKind::Execute),
+                title: Some(format!("Run `{}`", command)),
+                raw_input: Some(serde_json::json!({
+                    "command": request.command,
+                    "args": request.args,
+                    "cwd": request.cwd,
+                })),
+                ..Default::default()
+            },
+            meta: None,
+        },
+        options: vec![
+            option(TERMINAL_ALLOW, "Allow", PermissionOptionKind::AllowOnce),
+            option(TERMINAL_REJECT, "Reject", PermissionOptionKind::RejectOnce),
+        ],
+        meta: None,
+    }
+}
+
+/// Whether the answer to [`terminal_permission_request`] allows the command.
+/// Anything but an explicit allow, including a dismissed prompt, denies it.
+pub(crate) fn terminal_permitted(outcome: &RequestPermissionOutcome) -> bool {
+    matches!(
+        outcome,
+        RequestPermissionOutcome::Selected { option_id } if option_id.0.as_ref() == TERMINAL_ALLOW
+    )
+}
+
+/// A permission request waiting for the user, answered through `responder`.
+pub(crate) struct PendingPermission {
+    pub(crate) request: RequestPermissionRequest,
+    pub(crate) responder: oneshot::Sender<RequestPermissionOutcome>,
+}
+
+impl PendingPermission {
+    pub(crate) fn respond(self, outcome: RequestPermissionOutcome) {
+        let _ = self.responder.send(outcome);
+    }
+
+    /// The outcome for dismissing the prompt: the first reject-once option,
+    /// falling back to cancelling the request.
+    pub(crate) fn reject_outcome(&self) -> RequestPermissionOutcome {
+        self.request
+            .options
+            .iter()
+            .find(|o| o.kind == PermissionOptionKind::RejectOnce)
+            .map(|o| RequestPermissionOutcome::Selected {
+                option_id: o.id.clone(),
+            })
+            .unwrap_or(RequestPermissionOutcome::Cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::{
+        PermissionOption, SessionId, ToolCallId, ToolCallUpdate, ToolCallUpdateFields,
+    };
+
+    fn request(kind: ToolKind) -> RequestPermissionRequest {
+        let option = |id: &'static str, kind| PermissionOption {
+            id: PermissionOptionId(id.into()),
+            name: id.to_string(),
+            kind,
+            meta: None,
+        };
+        RequestPermissionRequest {
+            session_id: SessionId("s".into()),
+            tool_call: ToolCallUpdate {
+                id: ToolCallId("t".into()),
+                fields: ToolCallUpdateFields {
+                    kind: Some(kind),
+                    ..Default::default()
+                },
+                meta: None,
+            },
+            options: vec![
+                option("always", PermissionOptionKind::AllowAlways),
+                option("once", PermissionOptionKind::AllowOnce),
+                option("reject", PermissionOptionKind::RejectOnce),
+            ],
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn policies_only_approve_what_they_cover() {
+        let once = Some(PermissionOptionId("once".into()));
+        assert_eq!(
+            PermissionPolicy::AlwaysAsk.auto_option(&request(ToolKind::Read)),
+            None
+        );
+        assert_eq!(
+            PermissionPolicy::AllowReads.auto_option(&request(ToolKind::Read)),
+            once
+        );
+        assert_eq!(
+            PermissionPolicy::AllowReads.auto_option(&request(ToolKind::Edit)),
+            None
+        );
+        assert_eq!(
+            PermissionPolicy::AllowAll.auto_option(&request(ToolKind::Execute)),
+            once
+        );
+
+        let terminal = terminal_permission_request(&CreateTerminalRequest {
+            session_id: SessionId("s".into()),
+            command: "rm".into(),
+            args: vec!["-rf".into(), "/tmp/x".into()],
+            env: Vec::new(),
+            cwd: None,
+            output_byte_limit: None,
+            meta: None,
+        });
+        assert_eq!(
+            terminal.tool_call.fields.title.as_deref(),
+            Some("Run `rm -rf /tmp/x`")
+        );
+        assert_eq!(PermissionPolicy::AllowReads.auto_option(&terminal), None);
+        let allowed = PermissionPolicy::AllowAll.auto_option(&terminal).unwrap();
+        assert!(terminal_permitted(&RequestPermissionOutcome::Selected {
+            option_id: allowed
+        }));
+        assert!(!terminal_permitted(&RequestPermissionOutcome::Selected {
+            option_id: PermissionOptionId(TERMINAL_REJECT.into())
+        }));
+        assert!(!terminal_permitted(&RequestPermissionOutcome::Cancelled));
+
+        for policy in [
+            PermissionPolicy::AlwaysAsk,
+            PermissionPolicy::AllowReads,
+            PermissionPolicy::AllowAll,
+        ] {
+            assert_eq!(
+                PermissionPolicy::from_short_name(policy.short_name()),
+                Some(policy)
+            );
+        }
+    }
+}
@@ -1,71 +1,277 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use clap::builder::PossibleValue;
 use clap::ValueEnum;
+use serde::Deserialize;
+
+/// An ACP (Agent Client Protocol) provider: one of the built-in agents, or one
+/// added in `~/.config/cmux/providers.toml`. Providers are handles into a
+/// registry that's loaded once per process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AcpProvider(usize);
+
+/// How to launch a provider inside the sandbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProviderSpec {
+    name: String,
+    display_name: String,
+    command: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    /// Model to select after connecting when none was picked before.
+    default_model: Option<String>,
+}
+
+/// Every command is wrapped with stdbuf for unbuffered I/O.
+const STDBUF: &str = "/usr/bin/stdbuf -i0 -o0 -e0";
+
+/// The built-in providers. Codex comes first and is the default.
+fn builtin_specs() -> Vec<ProviderSpec> {
+    let spec = |name: &str, display_name: &str, command: &str, args: &[&str]| ProviderSpec {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        command: command.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        env: BTreeMap::new(),
+        default_model: None,
+    };
+    vec![
+        spec(
+            "codex",
+            "Codex CLI",
+            "/usr/local/bin/codex-acp",
+            &[
+                "-c",
+                "approval_policy=never",
+                "-c",
+                "sandbox_mode=danger-full-access",
+                "-c",
+                "model=gpt-5.1-codex-max",
+            ],
+        ),
+        spec("opencode", "OpenCode", "opencode", &["acp"]),
+        spec("claude", "Claude Code", "claude-code-acp", &[]),
+        spec("gemini", "Gemini CLI", "gemini", &["--experimental-acp"]),
+    ]
+}
+
+/// `providers.toml`: a list of `[[provider]]` tables. An entry named after a
+/// built-in overrides the fields it sets; any other name adds a provider.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProvidersFile {
+    #[serde(default)]
+    provider: Vec<ProviderEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderEntry {
+    name: String,
+    display_name: Option<String>,
+    command: Option<String>,
+    args: Option<Vec<String>>,
+    env: Option<BTreeMap<String, String>>,
+    default_model: Option<String>,
+}
+
+fn merge_providers(
+    mut specs: Vec<ProviderSpec>,
+    entries: Vec<ProviderEntry>,
+) -> Result<Vec<ProviderSpec>, String> {
+    for entry in entries {
+        let name = entry.name.trim().to_string();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("invalid provider name {:?}", entry.name));
+        }
+        let index = match specs.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                let command = entry
+                    .command
+                    .clone()
+                    .ok_or_else(|| format!("provider {:?} has no command", name))?;
+                specs.push(ProviderSpec {
+                    display_name: name.clone(),
+                    name,
+                    command,
+                    args: Vec::new(),
+                    env: BTreeMap::new(),
+                    default_model: None,
+                });
+                specs.len() - 1
+            }
+        };
+        let spec = &mut specs[index];
+        if let Some(display_name) = entry.display_name {
+            spec.display_name = display_name;
+        }
+        if let Some(command) = entry.command {
+            spec.command = command;
+        }
+        if let Some(args) = entry.args {
+            spec.args = args;
+        }
+        if let Some(env) = entry.env {
+            spec.env = env;
+        }
+        if entry.default_model.is_some() {
+            spec.default_model = entry.default_model;
+        }
+    }
+    Ok(specs)
+}
+
+fn providers_config_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".config/cmux/providers.toml")
+}
 
-/// Available ACP (Agent Client Protocol) providers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
-pub enum AcpProvider {
-    /// OpenAI Codex CLI ACP - `codex-acp`
-    #[default]
-    Codex,
-    /// OpenCode ACP - `opencode acp`
-    Opencode,
-    /// Claude Code ACP - `claude-code-acp`
-    Claude,
-    /// Gemini CLI ACP - `gemini --experimental-acp`
-    Gemini,
+struct Registry {
+    specs: Vec<ProviderSpec>,
+    providers: Vec<AcpProvider>,
+    /// Why `providers.toml` was ignored, if it was.
+    error: Option<String>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let path = providers_config_path();
+        let loaded = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str::<ProvidersFile>(&contents)
+                .map_err(|e| e.to_string())
+                .and_then(|file| merge_providers(builtin_specs(), file.provider)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(builtin_specs()),
+            Err(e) => Err(e.to_string()),
+        };
+        let (specs, error) = match loaded {
+            Ok(specs) => (specs, None),
+            Err(e) => (
+                builtin_specs(),
+                Some(format!("Ignoring {}: {}", path.display(), e)),
+            ),
+        };
+        Registry {
+            providers: (0..specs.len()).map(AcpProvider).collect(),
+            specs,
+            error,
+        }
+    })
+}
+
+/// Why the provider config file couldn't be used, if it couldn't.
+pub(crate) fn registry_error() -> Option<&'static str> {
+    registry().error.as_deref()
+}
+
+/// Quote a word for `/bin/sh -c` unless it's plainly safe.
+fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+%".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
 }
 
 impl AcpProvider {
+    fn spec(&self) -> &'static ProviderSpec {
+        &registry().specs[self.0]
+    }
+
     /// Get all available providers for display in the command palette
     pub fn all() -> &'static [AcpProvider] {
-        &[
-            AcpProvider::Codex,
-            AcpProvider::Opencode,
-            AcpProvider::Claude,
-            AcpProvider::Gemini,
-        ]
+        &registry().providers
     }
 
     /// Get the display name for this provider
     pub fn display_name(&self) -> &'static str {
-        match self {
-            AcpProvider::Codex => "Codex CLI",
-            AcpProvider::Opencode => "OpenCode",
-            AcpProvider::Claude => "Claude Code",
-            AcpProvider::Gemini => "Gemini CLI",
-        }
+        &self.spec().display_name
     }
 
-    /// Get the command to execute for this provider
-    /// Commands are wrapped with stdbuf for unbuffered I/O
-    pub fn command(&self) -> &'static str {
-        match self {
-            AcpProvider::Codex => {
-                "/usr/bin/stdbuf -i0 -o0 -e0 /usr/local/bin/codex-acp -c approval_policy=\"never\" -c sandbox_mode=\"danger-full-access\" -c model=\"gpt-5.1-codex-max\""
-            }
-            AcpProvider::Opencode => "/usr/bin/stdbuf -i0 -o0 -e0 opencode acp",
-            AcpProvider::Claude => "/usr/bin/stdbuf -i0 -o0 -e0 claude-code-acp",
-            AcpProvider::Gemini => "/usr/bin/stdbuf -i0 -o0 -e0 gemini --experimental-acp",
+    /// Get the shell command to execute for this provider
+    pub fn command(&self) -> String {
+        let spec = self.spec();
+        let mut words = vec![STDBUF.to_string()];
+        if !spec.env.is_empty() {
+            words.push("env".to_string());
+            words.extend(
+                spec.env
+                    .iter()
+                    .map(|(key, value)| shell_quote(&format!("{}={}", key, value))),
+            );
         }
+        words.push(shell_quote(&spec.command));
+        words.extend(spec.args.iter().map(|a| shell_quote(a)));
+        words.join(" ")
+    }
+
+    /// Model to select when the user hasn't picked one for this provider
+    pub fn default_model(&self) -> Option<&'static str> {
+        self.spec().default_model.as_deref()
     }
 
     /// Get a short identifier for this provider
     pub fn short_name(&self) -> &'static str {
-        match self {
-            AcpProvider::Codex => "codex",
-            AcpProvider::Opencode => "opencode",
-            AcpProvider::Claude => "claude",
-            AcpProvider::Gemini => "gemini",
-        }
+        &self.spec().name
     }
 
     /// Parse a short name back to AcpProvider
     pub fn from_short_name(name: &str) -> Option<AcpProvider> {
-        match name {
-            "codex" => Some(AcpProvider::Codex),
-            "opencode" => Some(AcpProvider::Opencode),
-            "claude" => Some(AcpProvider::Claude),
-            "gemini" => Some(AcpProvider::Gemini),
-            _ => None,
-        }
+        Self::all().iter().copied().find(|p| p.short_name() == name)
+    }
+}
+
+impl ValueEnum for AcpProvider {
+    fn value_variants<'a>() -> &'a [Self] {
+        Self::all()
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.short_name()).help(self.display_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_overrides_builtins_and_adds_providers() {
+        let file: ProvidersFile = toml::from_str(
+            r#"
+            [[provider]]
+            name = "codex"
+            default_model = "gpt-5"
+
+            [[provider]]
+            name = "my-agent"
+            display_name = "My Agent"
+            command = "my-agent"
+            args = ["--acp", "it's"]
+            env = { API_KEY = "abc" }
+            "#,
+        )
+        .unwrap();
+        let specs = merge_providers(builtin_specs(), file.provider).unwrap();
+        assert_eq!(specs.len(), builtin_specs().len() + 1);
+        assert_eq!(specs[0].default_model.as_deref(), Some("gpt-5"));
+        assert_eq!(specs[0].args, builtin_specs()[0].args);
+        let custom = specs.last().unwrap();
+        assert_eq!(custom.display_name, "My Agent");
+        assert_eq!(custom.env.get("API_KEY").map(String::as_str), Some("abc"));
+
+        let missing_command: ProvidersFile =
+            toml::from_str("[[provider]]\nname = \"other\"\n").unwrap();
+        assert!(merge_providers(builtin_specs(), missing_command.provider).is_err());
+
+        assert_eq!(shell_quote("model=gpt-5"), "model=gpt-5");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
     }
 }
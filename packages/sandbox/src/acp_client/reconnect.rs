@@ -0,0 +1,165 @@
+//! Reconnecting a tab after its sandbox websocket drops.
+//!
+//! The tab keeps its history and input while a background task retries with
+//! exponential backoff. Session updates that arrive while reconnecting are the
+//! agent replaying a loaded session and are ignored, since the history already
+//! has them.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use agent_client_protocol::{ClientSideConnection, SessionId, SessionModelState};
+
+use crate::acp_client::connection::resume_session;
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::logging::log_debug;
+use crate::acp_client::state::{App, ChatEntry, ConnectionState};
+
+/// Overrides how many reconnect attempts are made before giving up.
+const RECONNECT_ATTEMPTS_ENV: &str = "CMUX_ACP_RECONNECT_ATTEMPTS";
+const DEFAULT_RECONNECT_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub(crate) fn max_reconnect_attempts() -> u32 {
+    parse_attempts(std::env::var(RECONNECT_ATTEMPTS_ENV).ok().as_deref())
+}
+
+fn parse_attempts(value: Option<&str>) -> u32 {
+    value
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_RECONNECT_ATTEMPTS)
+}
+
+/// Delay before attempt `attempt` (1-based): 0.5s, 1s, 2s, ... capped at 30s.
+fn backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    INITIAL_BACKOFF
+        .saturating_mul(1 << exponent)
+        .min(MAX_BACKOFF)
+}
+
+impl App<'_> {
+    /// Drop the current connection and reconnect in the background. Ignored
+    /// while a connection attempt is already in progress.
+    pub(crate) fn start_reconnect(&mut self) {
+        if !matches!(
+            self.connection_state,
+            ConnectionState::Connected | ConnectionState::Disconnected
+        ) {
+            return;
+        }
+        let max = max_reconnect_attempts();
+        if max == 0 {
+            self.on_reconnect_failed("reconnecting is disabled".to_string());
+            return;
+        }
+        self.client_connection = None;
+        self.connection_state = ConnectionState::Reconnecting { attempt: 1, max };
+
+        let tab = self.tab_id;
+        let tx = self.event_tx.clone();
+        let base_url = self.base_url.clone();
+        let sandbox_id = self.sandbox_id.clone();
+        let provider = self.current_provider;
+        let session_id = self.session_id.clone();
+        tokio::task::spawn_local(async move {
+            let mut last_error = String::new();
+            for attempt in 1..=max {
+                let _ = tx.send(AppEvent::for_tab(tab, AppEvent::Reconnecting { attempt }));
+                tokio::time::sleep(backoff(attempt)).await;
+                match resume_session(
+                    &base_url,
+                    &sandbox_id,
+                    provider,
+                    session_id.clone(),
+                    tx.clone(),
+                )
+                .await
+                {
+                    Ok((connection, session_id, model_state, resumed)) => {
+                        let _ = tx.send(AppEvent::for_tab(
+                            tab,
+                            AppEvent::Reconnected {
+                                connection,
+                                session_id,
+                                model_state,
+                                resumed,
+                            },
+                        ));
+                        return;
+                    }
+                    Err(e) => {
+                        log_debug(&format!("Reconnect attempt {} failed: {}", attempt, e));
+                        last_error = e.to_string();
+                    }
+                }
+            }
+            let _ = tx.send(AppEvent::for_tab(
+                tab,
+                AppEvent::ReconnectFailed { error: last_error },
+            ));
+        });
+    }
+
+    pub(crate) fn on_reconnecting(&mut self, attempt: u32) {
+        if let ConnectionState::Reconnecting { max, .. } = self.connection_state {
+            self.connection_state = ConnectionState::Reconnecting { attempt, max };
+        }
+    }
+
+    pub(crate) fn on_reconnected(
+        &mut self,
+        connection: Arc<ClientSideConnection>,
+        session_id: SessionId,
+        model_state: Option<SessionModelState>,
+        resumed: bool,
+    ) {
+        self.client_connection = Some(connection);
+        self.session_id = Some(session_id);
+        if model_state.is_some() {
+            self.model_state = model_state;
+        }
+        self.connection_state = ConnectionState::Connected;
+        let text = if resumed {
+            "Reconnected and resumed the session."
+        } else {
+            "Reconnected with a new session; the agent no longer has the earlier context."
+        };
+        self.push_entry(ChatEntry::Message {
+            role: "System".to_string(),
+            text: text.to_string(),
+            normalized_markdown: None,
+        });
+    }
+
+    pub(crate) fn on_reconnect_failed(&mut self, error: String) {
+        self.connection_state = ConnectionState::Disconnected;
+        self.push_entry(ChatEntry::Message {
+            role: "Error".to_string(),
+            text: format!(
+                "Connection lost and could not be restored: {}. Run \"Reconnect\" from the command palette to try again.",
+                error
+            ),
+            normalized_markdown: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(4), Duration::from_secs(4));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+
+        assert_eq!(parse_attempts(None), DEFAULT_RECONNECT_ATTEMPTS);
+        assert_eq!(parse_attempts(Some(" 12 ")), 12);
+        assert_eq!(parse_attempts(Some("0")), 0);
+        assert_eq!(parse_attempts(Some("many")), DEFAULT_RECONNECT_ATTEMPTS);
+    }
+}
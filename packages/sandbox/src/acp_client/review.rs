@@ -0,0 +1,310 @@
+//! Reviewing the files the agent changed.
+//!
+//! Every diff an edit tool call reported is folded into one change per file:
+//! the text before the agent's first edit against the text after its latest.
+//! "Review Changes" browses them full screen; each file can be accepted as is
+//! or reverted, which writes the original text back the same way the agent's
+//! `fs/write_text_file` requests are served (a file the agent created is
+//! removed instead).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use agent_client_protocol::{ToolCallContent, ToolCallStatus};
+
+use crate::acp_client::client::write_text_file;
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::logging::log_debug;
+use crate::acp_client::state::{App, ChatEntry, UiMode};
+
+/// A file's accumulated change over the session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ChangedFile {
+    pub(crate) path: PathBuf,
+    /// `None` when the agent created the file.
+    pub(crate) old_text: Option<String>,
+    pub(crate) new_text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ReviewStatus {
+    Pending,
+    Accepted,
+    Reverting,
+    Reverted,
+    Failed(String),
+}
+
+impl ReviewStatus {
+    pub(crate) fn marker(&self) -> &'static str {
+        match self {
+            ReviewStatus::Pending => "•",
+            ReviewStatus::Accepted => "✓",
+            ReviewStatus::Reverting => "…",
+            ReviewStatus::Reverted => "↺",
+            ReviewStatus::Failed(_) => "✗",
+        }
+    }
+}
+
+/// Review progress of a tab. A decision holds only while the file still has
+/// the content it was made for; another edit makes the file pending again.
+#[derive(Default)]
+pub(crate) struct ChangeReview {
+    decisions: HashMap<PathBuf, (String, ReviewStatus)>,
+    pub(crate) selected: usize,
+    pub(crate) scroll: u16,
+}
+
+impl ChangeReview {
+    pub(crate) fn status(&self, file: &ChangedFile) -> ReviewStatus {
+        match self.decisions.get(&file.path) {
+            Some((new_text, status)) if *new_text == file.new_text => status.clone(),
+            _ => ReviewStatus::Pending,
+        }
+    }
+
+    fn decide(&mut self, file: &ChangedFile, status: ReviewStatus) {
+        self.decisions
+            .insert(file.path.clone(), (file.new_text.clone(), status));
+    }
+}
+
+/// Files changed by edit tool calls in `history`, in the order they were
+/// first changed. Failed tool calls are skipped.
+pub(crate) fn changed_files(history: &[ChatEntry]) -> Vec<ChangedFile> {
+    let mut files: Vec<ChangedFile> = Vec::new();
+    for entry in history {
+        let ChatEntry::ToolCall {
+            status, details, ..
+        } = entry
+        else {
+            continue;
+        };
+        if *status == ToolCallStatus::Failed {
+            continue;
+        }
+        for content in &details.content {
+            let ToolCallContent::Diff { diff } = content else {
+                continue;
+            };
+            match files.iter_mut().find(|f| f.path == diff.path) {
+                Some(file) => file.new_text = diff.new_text.clone(),
+                None => files.push(ChangedFile {
+                    path: diff.path.clone(),
+                    old_text: diff.old_text.clone(),
+                    new_text: diff.new_text.clone(),
+                }),
+            }
+        }
+    }
+    files.retain(|f| f.old_text.as_deref() != Some(f.new_text.as_str()));
+    files
+}
+
+/// Put `file` back the way it was before the agent touched it.
+async fn revert_file(file: &ChangedFile) -> std::io::Result<()> {
+    match &file.old_text {
+        Some(text) => write_text_file(&file.path, text).await,
+        None => tokio::fs::remove_file(&file.path).await,
+    }
+}
+
+impl App<'_> {
+    pub(crate) fn open_review(&mut self) {
+        if changed_files(&self.history).is_empty() {
+            self.push_entry(ChatEntry::Message {
+                role: "System".to_string(),
+                text: "The agent hasn't changed any files yet.".to_string(),
+                normalized_markdown: None,
+            });
+            self.scroll_to_bottom();
+            return;
+        }
+        self.review.scroll = 0;
+        self.ui_mode = UiMode::Review;
+    }
+
+    pub(crate) fn close_review(&mut self) {
+        self.ui_mode = UiMode::Chat;
+    }
+
+    /// The changed file under the cursor.
+    pub(crate) fn selected_change(&self) -> Option<ChangedFile> {
+        let files = changed_files(&self.history);
+        let index = self.review.selected.min(files.len().saturating_sub(1));
+        files.into_iter().nth(index)
+    }
+
+    /// Move the file selection; negative `delta` moves up the list.
+    pub(crate) fn move_review_selection(&mut self, delta: isize) {
+        let count = changed_files(&self.history).len();
+        self.review.selected = self
+            .review
+            .selected
+            .saturating_add_signed(delta)
+            .min(count.saturating_sub(1));
+        self.review.scroll = 0;
+    }
+
+    pub(crate) fn scroll_review_up(&mut self, lines: u16) {
+        self.review.scroll = self.review.scroll.saturating_sub(lines);
+    }
+
+    pub(crate) fn scroll_review_down(&mut self, lines: u16) {
+        self.review.scroll = self.review.scroll.saturating_add(lines);
+    }
+
+    /// Keep the selected file as the agent left it.
+    pub(crate) fn accept_selected_change(&mut self) {
+        if let Some(file) = self.selected_change() {
+            if self.review.status(&file) != ReviewStatus::Reverting {
+                self.review.decide(&file, ReviewStatus::Accepted);
+                self.move_review_selection(1);
+            }
+        }
+    }
+
+    /// Restore the selected file's original content in the background.
+    pub(crate) fn revert_selected_change(&mut self) {
+        let Some(file) = self.selected_change() else {
+            return;
+        };
+        if matches!(
+            self.review.status(&file),
+            ReviewStatus::Reverting | ReviewStatus::Reverted
+        ) {
+            return;
+        }
+        self.review.decide(&file, ReviewStatus::Reverting);
+        let tx = self.event_tx.clone();
+        let tab = self.tab_id;
+        tokio::task::spawn_local(async move {
+            let result = revert_file(&file).await.map_err(|e| {
+                log_debug(&format!("Revert of {} failed: {}", file.path.display(), e));
+                e.to_string()
+            });
+            let _ = tx.send(AppEvent::for_tab(
+                tab,
+                AppEvent::ChangeReverted {
+                    path: file.path,
+                    new_text: file.new_text,
+                    result,
+                },
+            ));
+        });
+    }
+
+    pub(crate) fn on_change_reverted(
+        &mut self,
+        path: &Path,
+        new_text: String,
+        result: Result<(), String>,
+    ) {
+        let status = match result {
+            Ok(()) => ReviewStatus::Reverted,
+            Err(error) => ReviewStatus::Failed(error),
+        };
+        self.review
+            .decisions
+            .insert(path.to_path_buf(), (new_text, status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acp_client::state::ToolCallDetails;
+    use agent_client_protocol::{Diff, ToolKind};
+
+    fn edit(path: &str, old_text: Option<&str>, new_text: &str) -> ChatEntry {
+        ChatEntry::ToolCall {
+            id: path.to_string(),
+            title: format!("Edit {path}"),
+            kind: ToolKind::Edit,
+            status: ToolCallStatus::Completed,
+            details: ToolCallDetails {
+                content: vec![ToolCallContent::Diff {
+                    diff: Diff {
+                        path: PathBuf::from(path),
+                        old_text: old_text.map(str::to_string),
+                        new_text: new_text.to_string(),
+                        meta: None,
+                    },
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn edits_fold_into_one_change_per_file() {
+        let history = [
+            edit("/workspace/a.rs", Some("one"), "two"),
+            edit("/workspace/new.txt", None, "hello"),
+            edit("/workspace/a.rs", Some("two"), "three"),
+            edit("/workspace/same.txt", Some("x"), "y"),
+            edit("/workspace/same.txt", Some("y"), "x"),
+        ];
+        let files = changed_files(&history);
+        assert_eq!(
+            files,
+            [
+                ChangedFile {
+                    path: "/workspace/a.rs".into(),
+                    old_text: Some("one".into()),
+                    new_text: "three".into(),
+                },
+                ChangedFile {
+                    path: "/workspace/new.txt".into(),
+                    old_text: None,
+                    new_text: "hello".into(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn revert_restores_or_removes() {
+        let dir = tempfile::tempdir().unwrap();
+        let edited = dir.path().join("edited.txt");
+        let created = dir.path().join("created.txt");
+        std::fs::write(&edited, "after").unwrap();
+        std::fs::write(&created, "new").unwrap();
+
+        revert_file(&ChangedFile {
+            path: edited.clone(),
+            old_text: Some("before".into()),
+            new_text: "after".into(),
+        })
+        .await
+        .unwrap();
+        revert_file(&ChangedFile {
+            path: created.clone(),
+            old_text: None,
+            new_text: "new".into(),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "before");
+        assert!(!created.exists());
+    }
+
+    #[test]
+    fn a_new_edit_reopens_a_decided_file() {
+        let mut review = ChangeReview::default();
+        let file = ChangedFile {
+            path: "/workspace/a.rs".into(),
+            old_text: None,
+            new_text: "v1".into(),
+        };
+        review.decide(&file, ReviewStatus::Accepted);
+        assert_eq!(review.status(&file), ReviewStatus::Accepted);
+        let edited_again = ChangedFile {
+            new_text: "v2".into(),
+            ..file
+        };
+        assert_eq!(review.status(&edited_again), ReviewStatus::Pending);
+    }
+}
@@ -3,28 +3,41 @@ use anyhow::Result;
 use crossterm::{
     event::{
         DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event, EventStream, KeyCode, KeyModifiers, MouseEventKind,
+        Event, EventStream, KeyCode, KeyEvent, KeyModifiers, MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::StreamExt;
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::io::Write;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
-use crate::acp_client::config::{load_last_model, save_last_model, save_last_provider};
+use crate::acp_client::attachments::write_inline_image;
+use crate::acp_client::commands::BuiltinCommand;
+use crate::acp_client::config::{
+    load_last_model, load_permission_policy, save_last_model, save_last_provider,
+    save_permission_policy,
+};
 use crate::acp_client::connection::{connect_to_provider, fetch_provider_models};
 use crate::acp_client::events::AppEvent;
+use crate::acp_client::history::history_dir;
+use crate::acp_client::keymap::{keymap, keymap_error, Action, KeyContext, KeyResolution};
 use crate::acp_client::logging::log_debug;
-use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::permissions::PermissionPolicy;
+use crate::acp_client::provider::{registry_error, AcpProvider};
 use crate::acp_client::state::{App, ConnectionState, PaletteCommand, UiMode};
+use crate::acp_client::tabs::{INITIAL_TAB_ID, MAX_NUMBERED_TABS};
 use crate::acp_client::ui::ui;
+use crate::acp_client::usage::UsageStats;
 use crate::acp_client::workspace_sync::WorkspaceSyncStatus;
 use crate::terminal_guard;
 
-fn spawn_provider_tasks(
+pub(crate) fn spawn_provider_tasks(
     tx: mpsc::UnboundedSender<AppEvent>,
+    tab: u64,
     base_url: String,
     sandbox_id: String,
     initial_provider: AcpProvider,
@@ -46,19 +59,25 @@ fn spawn_provider_tasks(
                 .await
                 {
                     Ok((connection, session_id, model_state)) => {
-                        let _ = tx_clone.send(AppEvent::ProviderSwitchComplete {
-                            provider,
-                            connection,
-                            session_id,
-                            model_state,
-                        });
+                        let _ = tx_clone.send(AppEvent::for_tab(
+                            tab,
+                            AppEvent::ProviderSwitchComplete {
+                                provider,
+                                connection,
+                                session_id,
+                                model_state,
+                            },
+                        ));
                     }
                     Err(e) => {
                         log_debug(&format!("Initial provider connection failed: {}", e));
-                        let _ = tx_clone.send(AppEvent::ProviderSwitchFailed {
-                            provider,
-                            error: e.to_string(),
-                        });
+                        let _ = tx_clone.send(AppEvent::for_tab(
+                            tab,
+                            AppEvent::ProviderSwitchFailed {
+                                provider,
+                                error: e.to_string(),
+                            },
+                        ));
                     }
                 }
             });
@@ -74,16 +93,21 @@ pub async fn run_chat_tui(
     base_url: String,
     sandbox_id: String,
     provider: AcpProvider,
+    auth_token: Option<String>,
 ) -> Result<()> {
-    run_chat_tui_with_workspace_status(base_url, sandbox_id, provider, None).await
+    run_chat_tui_with_workspace_status(base_url, sandbox_id, provider, None, auth_token).await
 }
 
+/// `auth_token` is sent on every request to the daemon at `base_url`.
 pub async fn run_chat_tui_with_workspace_status(
     base_url: String,
     sandbox_id: String,
     provider: AcpProvider,
     workspace_status_rx: Option<mpsc::UnboundedReceiver<WorkspaceSyncStatus>>,
+    auth_token: Option<String>,
 ) -> Result<()> {
+    crate::client_auth::set_auth_token(auth_token);
+
     let mut stdout = std::io::stdout();
     execute!(
         stdout,
@@ -179,6 +203,7 @@ async fn run_main_loop<B: ratatui::backend::Backend>(
                     tasks_started = true;
                     spawn_provider_tasks(
                         tx_clone.clone(),
+                        INITIAL_TAB_ID,
                         base_url_clone.clone(),
                         sandbox_id_clone.clone(),
                         initial_provider_clone,
@@ -188,6 +213,7 @@ async fn run_main_loop<B: ratatui::backend::Backend>(
             if !tasks_started {
                 spawn_provider_tasks(
                     tx_clone,
+                    INITIAL_TAB_ID,
                     base_url_clone,
                     sandbox_id_clone,
                     initial_provider_clone,
@@ -196,21 +222,17 @@ async fn run_main_loop<B: ratatui::backend::Backend>(
         });
     }
 
-    let mut app = App::new(
-        initial_provider,
+    let app = new_chat_app(
         tx.clone(),
         base_url.clone(),
         sandbox_id.clone(),
+        initial_provider,
     );
-    app.connection_state = ConnectionState::Connecting;
-
-    for provider in AcpProvider::all() {
-        app.providers_loading.push(*provider);
-    }
 
     if provider_tasks_started {
         spawn_provider_tasks(
             tx.clone(),
+            INITIAL_TAB_ID,
             base_url.clone(),
             sandbox_id.clone(),
             initial_provider,
@@ -223,245 +245,644 @@ async fn run_main_loop<B: ratatui::backend::Backend>(
     Ok(())
 }
 
-async fn run_app<B: ratatui::backend::Backend>(
-    terminal: &mut Terminal<B>,
-    mut app: App<'_>,
-    mut rx: mpsc::UnboundedReceiver<AppEvent>,
-) -> std::io::Result<()> {
-    let mut reader = EventStream::new();
+/// A chat for `sandbox_id` with saved history and settings loaded, waiting
+/// for its provider connection.
+pub(crate) fn new_chat_app<'a>(
+    tx: mpsc::UnboundedSender<AppEvent>,
+    base_url: String,
+    sandbox_id: String,
+    provider: AcpProvider,
+) -> App<'a> {
+    let mut app = App::new(provider, tx, base_url, sandbox_id);
+    app.connection_state = ConnectionState::Connecting;
+    app.enable_history(history_dir());
+    app.permission_policy = load_permission_policy();
+    for error in [registry_error(), keymap_error()].into_iter().flatten() {
+        app.push_entry(crate::acp_client::state::ChatEntry::Message {
+            role: "Error".to_string(),
+            text: error.to_string(),
+            normalized_markdown: None,
+        });
+    }
 
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+    for provider in AcpProvider::all() {
+        app.providers_loading.push(*provider);
+    }
+    app
+}
 
-        tokio::select! {
-            Some(event) = rx.recv() => {
-                match event {
-                    AppEvent::SessionUpdate(notification) => app.on_session_update(*notification),
-                    AppEvent::DebugMessage { direction, message } => {
-                        app.add_debug_message(&direction, &message);
+/// Keys for navigating tool calls and their detail view.
+pub(crate) fn handle_tool_key(app: &mut App, key: KeyEvent) {
+    if app.ui_mode == UiMode::ToolDetail {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Backspace => app.close_tool_detail(),
+            KeyCode::Up | KeyCode::Char('k') => app.scroll_tool_detail_up(1),
+            KeyCode::Down | KeyCode::Char('j') => app.scroll_tool_detail_down(1),
+            KeyCode::PageUp => app.scroll_tool_detail_up(10),
+            KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_tool_detail_down(10),
+            KeyCode::Home | KeyCode::Char('g') => app.tool_detail_scroll = 0,
+            _ => {}
+        }
+        return;
+    }
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.exit_tool_selection(),
+        KeyCode::Up | KeyCode::Char('k') => app.move_tool_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.move_tool_selection(1),
+        KeyCode::Home | KeyCode::Char('g') => app.move_tool_selection(isize::MIN),
+        KeyCode::End | KeyCode::Char('G') => app.move_tool_selection(isize::MAX),
+        KeyCode::Enter => app.open_tool_detail(),
+        _ => {}
+    }
+}
+
+/// Apply an event from a connection or background task to the app.
+/// Leave the alternate screen and print an image inline. The next key press
+/// (handled in `UiMode::ImagePreview`) returns to the TUI.
+fn show_image_preview(path: &std::path::Path) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    execute!(stdout, LeaveAlternateScreen)?;
+    write!(stdout, "\r\n")?;
+    if let Err(e) = write_inline_image(&mut stdout, path) {
+        write!(stdout, "Failed to show {}: {}", path.display(), e)?;
+    }
+    write!(
+        stdout,
+        "\r\n{}\r\nPress any key to return\r\n",
+        path.display()
+    )?;
+    stdout.flush()
+}
+
+fn run_builtin_command(app: &mut App, command: BuiltinCommand) {
+    match command {
+        BuiltinCommand::Clear => app.clear_history(),
+        BuiltinCommand::Provider => app.open_switch_palette(),
+        BuiltinCommand::Debug => app.toggle_debug_mode(),
+    }
+}
+
+/// Run a chat action from a key binding.
+fn run_chat_action(app: &mut App, action: Action) -> EventOutcome {
+    match action {
+        Action::Quit => {
+            app.flush_all_history();
+            return EventOutcome::Quit;
+        }
+        Action::NewLine => app.textarea.insert_newline(),
+        Action::CommandPalette => app.open_main_palette(),
+        Action::SwitchProvider => app.open_switch_palette(),
+        Action::ToolCalls => app.start_tool_selection(),
+        Action::EditPreviousMessage => app.start_message_selection(),
+        Action::ReviewChanges => app.open_review(),
+        Action::NewTab => app.new_tab(),
+        Action::CloseTab => app.close_tab(),
+        Action::ShowKeybindings => app.show_keybindings(),
+        Action::PaletteUp | Action::PaletteDown | Action::PaletteClose => {}
+    }
+    EventOutcome::Continue
+}
+
+/// Bound palette keys: navigation, closing, and the binding that opened the
+/// palette (`toggle`) closing it again. `false` if `key` isn't one of them.
+fn handle_palette_binding(app: &mut App, key: KeyEvent, toggle: Action) -> bool {
+    match keymap().resolve(KeyContext::Palette, &mut app.pending_keys, key) {
+        KeyResolution::Action(Action::PaletteUp) => app.palette_up(),
+        KeyResolution::Action(Action::PaletteDown) => app.palette_down(),
+        KeyResolution::Action(_) => app.close_palette(),
+        KeyResolution::Pending => {}
+        KeyResolution::Unbound if keymap().is_bound(toggle, key) => app.close_palette(),
+        KeyResolution::Unbound => return false,
+    }
+    true
+}
+
+/// Ctrl/Alt + 1-9 jumps to that tab.
+fn switch_numbered_tab(app: &mut App, digit: char) {
+    if let Some(n) = digit.to_digit(10) {
+        let index = n as usize - 1;
+        if index < MAX_NUMBERED_TABS {
+            app.switch_tab(index);
+        }
+    }
+}
+
+pub(crate) fn handle_app_event(app: &mut App, event: AppEvent) {
+    match event {
+        AppEvent::ForTab { tab, event } => {
+            // Events for a closed tab are dropped.
+            app.with_tab(tab, |app| handle_app_event(app, *event));
+        }
+        AppEvent::SessionUpdate(notification) => {
+            // Updates can arrive before the tab has recorded its session id.
+            let tab = app
+                .tab_for_session(&notification.session_id)
+                .unwrap_or(app.tab_id);
+            app.with_tab(tab, |app| app.on_session_update(*notification));
+        }
+        AppEvent::DebugMessage { direction, message } => {
+            app.add_debug_message(&direction, &message);
+        }
+        AppEvent::WorkspaceSyncStatus(status) => {
+            app.update_workspace_sync_state(status);
+        }
+        AppEvent::WorkspaceIndexed(files) => {
+            app.on_workspace_indexed(files);
+        }
+        AppEvent::ProviderSwitchComplete {
+            provider,
+            connection,
+            session_id,
+            model_state,
+        } => {
+            log_debug(&format!(
+                "Provider switch complete: {}",
+                provider.display_name()
+            ));
+            let was_initial_connection = app.connection_state == ConnectionState::Connecting;
+            app.current_provider = provider;
+            app.client_connection = Some(connection);
+            app.session_id = Some(session_id);
+            app.model_state = model_state.clone();
+            app.connection_state = ConnectionState::Connected;
+
+            if let Some(ref state) = model_state {
+                let models: Vec<(String, String)> = state
+                    .available_models
+                    .iter()
+                    .map(|m| (m.model_id.0.to_string(), m.name.clone()))
+                    .collect();
+                app.provider_models.insert(provider, Some(models));
+            }
+            app.providers_loading.retain(|p| *p != provider);
+
+            if !was_initial_connection {
+                app.clear_history();
+                app.usage = UsageStats::default();
+                app.available_commands.clear();
+            }
+
+            save_last_provider(provider);
+
+            if let Some(pending_model) = app.pending_model_switch.take() {
+                app.model_switching = true;
+                app.start_model_switch(pending_model);
+            } else if let Some(ref state) = model_state {
+                let preferred_model = load_last_model(provider)
+                    .or_else(|| provider.default_model().map(str::to_string));
+                if let Some(last_model_id) = preferred_model {
+                    if state
+                        .available_models
+                        .iter()
+                        .any(|m| *m.model_id.0 == last_model_id)
+                        && *state.current_model_id.0 != last_model_id
+                    {
+                        app.model_switching = true;
+                        app.start_model_switch(ModelId::from(last_model_id));
                     }
-                    AppEvent::WorkspaceSyncStatus(status) => {
-                        app.update_workspace_sync_state(status);
+                }
+            }
+        }
+        AppEvent::ProviderSwitchFailed { provider, error } => {
+            log_debug(&format!(
+                "Provider switch failed for {}: {}",
+                provider.display_name(),
+                error
+            ));
+            let was_initial_connection = app.connection_state == ConnectionState::Connecting;
+            if let ConnectionState::SwitchingProvider(old_provider) = app.connection_state {
+                app.current_provider = old_provider;
+                app.connection_state = ConnectionState::Connected;
+            } else if was_initial_connection {
+                app.connection_state = ConnectionState::Connected;
+            }
+            app.providers_loading.retain(|p| *p != provider);
+            app.provider_models.insert(provider, Some(vec![]));
+            app.pending_model_switch = None;
+            if provider == app.current_provider {
+                app.push_entry(crate::acp_client::state::ChatEntry::Message {
+                    role: "System".to_string(),
+                    text: format!(
+                        "Failed to connect to {}: {}",
+                        provider.display_name(),
+                        error
+                    ),
+                    normalized_markdown: None,
+                });
+            }
+        }
+        AppEvent::SessionBranched {
+            session_id,
+            model_state,
+        } => {
+            app.on_session_branched(session_id, model_state);
+        }
+        AppEvent::ChangeReverted {
+            path,
+            new_text,
+            result,
+        } => {
+            app.on_change_reverted(&path, new_text, result);
+        }
+        AppEvent::ModelSwitchComplete { model_id } => {
+            log_debug(&format!("Model switch complete: {}", model_id));
+            app.model_switching = false;
+            if let Some(ref mut model_state) = app.model_state {
+                model_state.current_model_id = model_id.clone();
+            }
+            save_last_model(app.current_provider, &model_id.0);
+        }
+        AppEvent::ModelSwitchFailed { error } => {
+            log_debug(&format!("Model switch failed: {}", error));
+            app.model_switching = false;
+            app.push_entry(crate::acp_client::state::ChatEntry::Message {
+                role: "System".to_string(),
+                text: format!("Failed to switch model: {}", error),
+                normalized_markdown: None,
+            });
+        }
+        AppEvent::ConnectionLost { connection } => {
+            log_debug("Connection lost");
+            // Tabs share a connection when opened from a connected tab.
+            for id in app.tab_ids() {
+                app.with_tab(id, |app| {
+                    let uses_connection = app
+                        .client_connection
+                        .as_ref()
+                        .is_some_and(|c| std::ptr::eq(Arc::as_ptr(c), connection.as_ptr()));
+                    if uses_connection {
+                        app.start_reconnect();
                     }
-                    AppEvent::ProviderSwitchComplete { provider, connection, session_id, model_state } => {
-                        log_debug(&format!("Provider switch complete: {}", provider.display_name()));
-                        let was_initial_connection = app.connection_state == ConnectionState::Connecting;
-                        app.current_provider = provider;
-                        app.client_connection = Some(connection);
-                        app.session_id = Some(session_id);
-                        app.model_state = model_state.clone();
-                        app.connection_state = ConnectionState::Connected;
-
-                        if let Some(ref state) = model_state {
-                            let models: Vec<(String, String)> = state
-                                .available_models
-                                .iter()
-                                .map(|m| (m.model_id.0.to_string(), m.name.clone()))
-                                .collect();
-                            app.provider_models.insert(provider, Some(models));
-                        }
-                        app.providers_loading.retain(|p| *p != provider);
+                });
+            }
+        }
+        AppEvent::Reconnecting { attempt } => {
+            app.on_reconnecting(attempt);
+        }
+        AppEvent::Reconnected {
+            connection,
+            session_id,
+            model_state,
+            resumed,
+        } => {
+            app.on_reconnected(connection, session_id, model_state, resumed);
+        }
+        AppEvent::ReconnectFailed { error } => {
+            log_debug(&format!("Reconnect failed: {}", error));
+            app.on_reconnect_failed(error);
+        }
+        AppEvent::UsageReported(usage) => {
+            app.usage.record(usage);
+        }
+        AppEvent::RequestError { error } => {
+            log_debug(&format!("Request error: {}", error));
+            app.push_entry(crate::acp_client::state::ChatEntry::Message {
+                role: "Error".to_string(),
+                text: error,
+                normalized_markdown: None,
+            });
+        }
+        AppEvent::ProviderModelsLoaded { provider, models } => {
+            log_debug(&format!(
+                "Loaded {} models for {}",
+                models.len(),
+                provider.display_name()
+            ));
+            app.provider_models.insert(provider, Some(models));
+            app.providers_loading.retain(|p| *p != provider);
+        }
+        AppEvent::PermissionRequest(pending) => {
+            app.on_permission_request(pending);
+        }
+        AppEvent::ProviderModelsLoadFailed { provider } => {
+            log_debug(&format!(
+                "Failed to load models for {}",
+                provider.display_name()
+            ));
+            app.provider_models.insert(provider, Some(vec![]));
+            app.providers_loading.retain(|p| *p != provider);
+        }
+    }
+}
 
-                        if !was_initial_connection {
-                            app.history.clear();
-                        }
+/// What the owner of the terminal should do after [`handle_terminal_event`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum EventOutcome {
+    Continue,
+    /// The user asked to leave the chat.
+    Quit,
+    /// An inline image preview was dismissed; the alternate screen needs restoring.
+    LeftImagePreview,
+}
 
-                        save_last_provider(provider);
-
-                        if let Some(pending_model) = app.pending_model_switch.take() {
-                            app.model_switching = true;
-                            app.start_model_switch(pending_model);
-                        } else if let Some(ref state) = model_state {
-                            if let Some(last_model_id) = load_last_model(provider) {
-                                if state.available_models.iter().any(|m| *m.model_id.0 == last_model_id)
-                                    && *state.current_model_id.0 != last_model_id
-                                {
-                                    app.model_switching = true;
-                                    app.start_model_switch(ModelId::from(last_model_id));
-                                }
-                            }
+/// Apply a key, paste or mouse event to the chat.
+pub(crate) fn handle_terminal_event(app: &mut App, event: Event) -> EventOutcome {
+    match app.ui_mode {
+        UiMode::MainPalette => {
+            if let Event::Key(key) = event {
+                if handle_palette_binding(app, key, Action::CommandPalette) {
+                } else if key.modifiers.contains(KeyModifiers::CONTROL) {
+                    match key.code {
+                        KeyCode::Char('u')
+                        | KeyCode::Char('r')
+                        | KeyCode::Char('w')
+                        | KeyCode::Char('a')
+                        | KeyCode::Char('e')
+                        | KeyCode::Char('h')
+                        | KeyCode::Char('d') => {
+                            app.palette_handle_input(key);
                         }
+                        _ => {}
                     }
-                    AppEvent::ProviderSwitchFailed { provider, error } => {
-                        log_debug(&format!("Provider switch failed for {}: {}", provider.display_name(), error));
-                        let was_initial_connection = app.connection_state == ConnectionState::Connecting;
-                        if let ConnectionState::SwitchingProvider(old_provider) = app.connection_state {
-                            app.current_provider = old_provider;
-                            app.connection_state = ConnectionState::Connected;
-                        } else if was_initial_connection {
-                            app.connection_state = ConnectionState::Connected;
+                } else {
+                    match key.code {
+                        KeyCode::Esc => app.close_palette(),
+                        KeyCode::Up => app.palette_up(),
+                        KeyCode::Down => app.palette_down(),
+                        KeyCode::Enter => {
+                            if let Some(cmd) = app.execute_main_palette_selection() {
+                                match cmd {
+                                    PaletteCommand::ToggleDebugMode => {
+                                        app.toggle_debug_mode();
+                                    }
+                                    PaletteCommand::SwitchProviderModel => {
+                                        app.open_switch_palette();
+                                    }
+                                    PaletteCommand::LoadPreviousSession => {
+                                        app.open_session_palette();
+                                    }
+                                    PaletteCommand::NewTab => app.new_tab(),
+                                    PaletteCommand::CloseTab => app.close_tab(),
+                                    PaletteCommand::ShowUsage => app.show_usage(),
+                                    PaletteCommand::OpenAttachment => app.open_attachment_palette(),
+                                    PaletteCommand::EditPreviousMessage => {
+                                        app.start_message_selection()
+                                    }
+                                    PaletteCommand::ReviewChanges => app.open_review(),
+                                    PaletteCommand::ShowKeybindings => app.show_keybindings(),
+                                    PaletteCommand::Reconnect => app.start_reconnect(),
+                                    PaletteCommand::PermissionsAlwaysAsk
+                                    | PaletteCommand::PermissionsAllowReads
+                                    | PaletteCommand::PermissionsAllowAll => {
+                                        let policy = match cmd {
+                                            PaletteCommand::PermissionsAllowReads => {
+                                                PermissionPolicy::AllowReads
+                                            }
+                                            PaletteCommand::PermissionsAllowAll => {
+                                                PermissionPolicy::AllowAll
+                                            }
+                                            _ => PermissionPolicy::AlwaysAsk,
+                                        };
+                                        app.permission_policy = policy;
+                                        save_permission_policy(policy);
+                                    }
+                                }
+                            }
                         }
-                        app.providers_loading.retain(|p| *p != provider);
-                        app.provider_models.insert(provider, Some(vec![]));
-                        app.pending_model_switch = None;
-                        if provider == app.current_provider {
-                            app.history.push(crate::acp_client::state::ChatEntry::Message {
-                                role: "System".to_string(),
-                                text: format!("Failed to connect to {}: {}", provider.display_name(), error),
-                                normalized_markdown: None,
-                            });
+                        _ => {
+                            app.palette_handle_input(key);
                         }
                     }
-                    AppEvent::ModelSwitchComplete { model_id } => {
-                        log_debug(&format!("Model switch complete: {}", model_id));
-                        app.model_switching = false;
-                        if let Some(ref mut model_state) = app.model_state {
-                            model_state.current_model_id = model_id.clone();
+                }
+            }
+        }
+        UiMode::SwitchPalette | UiMode::SessionPalette | UiMode::AttachmentPalette => {
+            if let Event::Key(key) = event {
+                if handle_palette_binding(app, key, Action::SwitchProvider) {
+                } else if key.modifiers.contains(KeyModifiers::CONTROL) {
+                    match key.code {
+                        KeyCode::Char('u')
+                        | KeyCode::Char('r')
+                        | KeyCode::Char('w')
+                        | KeyCode::Char('a')
+                        | KeyCode::Char('e')
+                        | KeyCode::Char('h')
+                        | KeyCode::Char('d') => {
+                            app.palette_handle_input(key);
                         }
-                        save_last_model(app.current_provider, &model_id.0);
-                    }
-                    AppEvent::ModelSwitchFailed { error } => {
-                        log_debug(&format!("Model switch failed: {}", error));
-                        app.model_switching = false;
-                        app.history.push(crate::acp_client::state::ChatEntry::Message {
-                            role: "System".to_string(),
-                            text: format!("Failed to switch model: {}", error),
-                            normalized_markdown: None,
-                        });
+                        _ => {}
                     }
-                    AppEvent::RequestError { error } => {
-                        log_debug(&format!("Request error: {}", error));
-                        app.history.push(crate::acp_client::state::ChatEntry::Message {
-                            role: "Error".to_string(),
-                            text: error,
-                            normalized_markdown: None,
-                        });
+                } else {
+                    match key.code {
+                        KeyCode::Esc => app.close_palette(),
+                        KeyCode::Up => app.palette_up(),
+                        KeyCode::Down => app.palette_down(),
+                        KeyCode::Enter => match app.ui_mode {
+                            UiMode::SessionPalette => app.execute_session_palette_selection(),
+                            UiMode::AttachmentPalette => app.execute_attachment_palette_selection(),
+                            _ => app.execute_switch_palette_selection(),
+                        },
+                        _ => {
+                            app.palette_handle_input(key);
+                        }
                     }
-                    AppEvent::ProviderModelsLoaded { provider, models } => {
-                        log_debug(&format!("Loaded {} models for {}", models.len(), provider.display_name()));
-                        app.provider_models.insert(provider, Some(models));
-                        app.providers_loading.retain(|p| *p != provider);
+                }
+            }
+        }
+        UiMode::PermissionPrompt => {
+            if let Event::Key(key) = event {
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => app.permission_up(),
+                    KeyCode::Down | KeyCode::Char('j') => app.permission_down(),
+                    KeyCode::Enter => app.select_permission(app.permission_selection),
+                    KeyCode::Char(c @ '1'..='9') => {
+                        app.select_permission(c as usize - '1' as usize);
                     }
-                    AppEvent::ProviderModelsLoadFailed { provider } => {
-                        log_debug(&format!("Failed to load models for {}", provider.display_name()));
-                        app.provider_models.insert(provider, Some(vec![]));
-                        app.providers_loading.retain(|p| *p != provider);
+                    KeyCode::Esc => app.reject_permission(),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.reject_permission();
                     }
+                    _ => {}
                 }
             }
-            Some(Ok(event)) = reader.next() => {
-                match app.ui_mode {
-                    UiMode::MainPalette => {
-                        if let Event::Key(key) = event {
-                            if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                match key.code {
-                                    KeyCode::Char('p') | KeyCode::Char('k') => app.palette_up(),
-                                    KeyCode::Char('n') | KeyCode::Char('j') => app.palette_down(),
-                                    KeyCode::Char('c') | KeyCode::Char('g') => app.close_palette(),
-                                    KeyCode::Char('o') => app.close_palette(),
-                                    KeyCode::Char('u') | KeyCode::Char('r') |
-                                    KeyCode::Char('w') | KeyCode::Char('a') | KeyCode::Char('e') |
-                                    KeyCode::Char('h') | KeyCode::Char('d') => {
-                                        app.palette_handle_input(key);
-                                    }
-                                    _ => {}
-                                }
-                            } else {
-                                match key.code {
-                                    KeyCode::Esc => app.close_palette(),
-                                    KeyCode::Up => app.palette_up(),
-                                    KeyCode::Down => app.palette_down(),
-                                    KeyCode::Enter => {
-                                        if let Some(cmd) = app.execute_main_palette_selection() {
-                                            match cmd {
-                                                PaletteCommand::ToggleDebugMode => {
-                                                    app.toggle_debug_mode();
-                                                }
-                                                PaletteCommand::SwitchProviderModel => {
-                                                    app.open_switch_palette();
-                                                }
-                                            }
-                                        }
-                                    }
-                                    _ => { app.palette_handle_input(key); }
-                                }
-                            }
-                        }
+        }
+        UiMode::ImagePreview => {
+            if let Event::Key(_) = event {
+                app.ui_mode = UiMode::Chat;
+                return EventOutcome::LeftImagePreview;
+            }
+        }
+        UiMode::MessageSelect => {
+            if let Event::Key(key) = event {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => app.exit_message_selection(),
+                    KeyCode::Up | KeyCode::Char('k') => app.move_message_selection(-1),
+                    KeyCode::Down | KeyCode::Char('j') => app.move_message_selection(1),
+                    KeyCode::Home | KeyCode::Char('g') => app.move_message_selection(isize::MIN),
+                    KeyCode::End | KeyCode::Char('G') => app.move_message_selection(isize::MAX),
+                    KeyCode::Enter | KeyCode::Char('e') => app.edit_selected_message(),
+                    _ => {}
+                }
+            }
+        }
+        UiMode::Review => match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => app.close_review(),
+                KeyCode::Up | KeyCode::Char('k') => app.move_review_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => app.move_review_selection(1),
+                KeyCode::PageUp => app.scroll_review_up(10),
+                KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_review_down(10),
+                KeyCode::Char('a') => app.accept_selected_change(),
+                KeyCode::Char('r') => app.revert_selected_change(),
+                _ => {}
+            },
+            Event::Mouse(mouse_event) => match mouse_event.kind {
+                MouseEventKind::ScrollUp => app.scroll_review_up(1),
+                MouseEventKind::ScrollDown => app.scroll_review_down(1),
+                _ => {}
+            },
+            _ => {}
+        },
+        UiMode::ToolSelect | UiMode::ToolDetail => match event {
+            Event::Key(key) => handle_tool_key(app, key),
+            Event::Mouse(mouse_event) if app.ui_mode == UiMode::ToolDetail => {
+                match mouse_event.kind {
+                    MouseEventKind::ScrollUp => app.scroll_tool_detail_up(1),
+                    MouseEventKind::ScrollDown => app.scroll_tool_detail_down(1),
+                    _ => {}
+                }
+            }
+            _ => {}
+        },
+        UiMode::Chat => {
+            match event {
+                Event::Key(key) => {
+                    match keymap().resolve(KeyContext::Chat, &mut app.pending_keys, key) {
+                        KeyResolution::Action(action) => return run_chat_action(app, action),
+                        KeyResolution::Pending => return EventOutcome::Continue,
+                        KeyResolution::Unbound => {}
                     }
-                    UiMode::SwitchPalette => {
-                        if let Event::Key(key) = event {
-                            if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                match key.code {
-                                    KeyCode::Char('p') | KeyCode::Char('k') => app.palette_up(),
-                                    KeyCode::Char('n') | KeyCode::Char('j') => app.palette_down(),
-                                    KeyCode::Char('c') | KeyCode::Char('g') => app.close_palette(),
-                                    KeyCode::Char('m') => app.close_palette(),
-                                    KeyCode::Char('u') | KeyCode::Char('r') |
-                                    KeyCode::Char('w') | KeyCode::Char('a') | KeyCode::Char('e') |
-                                    KeyCode::Char('h') | KeyCode::Char('d') => {
-                                        app.palette_handle_input(key);
-                                    }
-                                    _ => {}
-                                }
-                            } else {
-                                match key.code {
-                                    KeyCode::Esc => app.close_palette(),
-                                    KeyCode::Up => app.palette_up(),
-                                    KeyCode::Down => app.palette_down(),
-                                    KeyCode::Enter => {
-                                        app.execute_switch_palette_selection();
-                                    }
-                                    _ => { app.palette_handle_input(key); }
-                                }
+                    if key.modifiers.contains(KeyModifiers::CONTROL) {
+                        match key.code {
+                            KeyCode::Char(c @ '1'..='9') => {
+                                switch_numbered_tab(app, c);
+                            }
+                            _ => {
+                                app.textarea.input(key);
                             }
                         }
-                    }
-                    UiMode::Chat => {
-                        match event {
-                            Event::Key(key) => {
-                                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                    match key.code {
-                                        KeyCode::Char('q') | KeyCode::Char('c') | KeyCode::Char('d') => {
-                                            return Ok(());
-                                        }
-                                        KeyCode::Char('j') => { app.textarea.insert_newline(); },
-                                        KeyCode::Char('m') => { app.open_switch_palette(); },
-                                        KeyCode::Char('o') => { app.open_main_palette(); },
-                                        _ => { app.textarea.input(key); }
-                                    }
+                    } else if let (true, KeyCode::Char(c @ '1'..='9')) =
+                        (key.modifiers.contains(KeyModifiers::ALT), key.code)
+                    {
+                        switch_numbered_tab(app, c);
+                    } else {
+                        match key.code {
+                            KeyCode::Up if app.mention_popup_visible() => {
+                                app.mention_selection_up();
+                            }
+                            KeyCode::Down if app.mention_popup_visible() => {
+                                app.mention_selection_down();
+                            }
+                            KeyCode::Tab | KeyCode::Enter
+                                if !app.mention_completions().is_empty() =>
+                            {
+                                app.accept_mention();
+                            }
+                            KeyCode::Up if app.command_popup_visible() => {
+                                app.command_selection_up();
+                            }
+                            KeyCode::Down if app.command_popup_visible() => {
+                                app.command_selection_down();
+                            }
+                            KeyCode::Tab if app.command_popup_visible() => {
+                                app.accept_command_completion();
+                            }
+                            KeyCode::Up if app.textarea.is_empty() => {
+                                app.start_message_selection();
+                            }
+                            KeyCode::Esc if app.editing_from.is_some() => {
+                                app.cancel_edit();
+                            }
+                            KeyCode::Enter => {
+                                let accepted = if app.command_popup_visible() {
+                                    app.accept_command_completion()
                                 } else {
-                                    match key.code {
-                                        KeyCode::Enter => {
-                                            if app.connection_state == ConnectionState::Connected {
-                                                app.send_message().await;
-                                            }
-                                        }
-                                        KeyCode::PageUp => {
-                                            app.scroll_up(10);
-                                        }
-                                        KeyCode::PageDown => {
-                                            app.scroll_down(10);
-                                        }
-                                        KeyCode::Home => {
-                                            app.scroll_to_top();
-                                        }
-                                        KeyCode::End => {
-                                            app.scroll_to_bottom();
-                                        }
-                                        _ => {
-                                            app.textarea.input(key);
-                                        }
-                                    }
+                                    None
+                                };
+                                if accepted.is_some_and(|c| c.input_hint.is_some()) {
+                                    // Leave room for the command's input.
+                                    app.textarea.insert_char(' ');
+                                } else if let Some(command) = app.take_builtin_command() {
+                                    run_builtin_command(app, command);
+                                } else if app.connection_state == ConnectionState::Connected {
+                                    app.send_message();
                                 }
                             }
-                            Event::Paste(text) => {
-                                app.textarea.insert_str(&text);
+                            KeyCode::PageUp => {
+                                app.scroll_up(10);
                             }
-                            Event::Mouse(mouse_event) => {
-                                match mouse_event.kind {
-                                    MouseEventKind::ScrollUp => {
-                                        app.scroll_up(1);
-                                    }
-                                    MouseEventKind::ScrollDown => {
-                                        app.scroll_down(1);
-                                    }
-                                    _ => {}
-                                }
+                            KeyCode::PageDown => {
+                                app.scroll_down(10);
+                            }
+                            KeyCode::Home => {
+                                app.scroll_to_top();
+                            }
+                            KeyCode::End => {
+                                app.scroll_to_bottom();
+                            }
+                            _ => {
+                                app.textarea.input(key);
+                                app.command_selection = 0;
+                                app.mention_selection = 0;
+                                app.ensure_workspace_index();
                             }
-                            _ => {}
                         }
                     }
                 }
+                Event::Paste(text) => {
+                    app.textarea.insert_str(&text);
+                }
+                Event::Mouse(mouse_event) => match mouse_event.kind {
+                    MouseEventKind::ScrollUp => {
+                        app.scroll_up(1);
+                    }
+                    MouseEventKind::ScrollDown => {
+                        app.scroll_down(1);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+    EventOutcome::Continue
+}
+
+async fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App<'_>,
+    mut rx: mpsc::UnboundedReceiver<AppEvent>,
+) -> std::io::Result<()> {
+    let mut reader = EventStream::new();
+    // Streamed chunks mutate the last entry many times a second; batch writes.
+    let mut history_tick = tokio::time::interval(std::time::Duration::from_secs(2));
+
+    loop {
+        if let Some(path) = app.image_preview.take() {
+            show_image_preview(&path)?;
+            app.ui_mode = UiMode::ImagePreview;
+        }
+        if app.ui_mode != UiMode::ImagePreview {
+            terminal.draw(|f| ui(f, &mut app))?;
+        }
+
+        tokio::select! {
+            _ = history_tick.tick() => {
+                app.flush_all_history();
+            }
+            Some(event) = rx.recv() => {
+                handle_app_event(&mut app, event);
+            }
+            Some(Ok(event)) = reader.next() => {
+                match handle_terminal_event(&mut app, event) {
+                    EventOutcome::Continue => {}
+                    EventOutcome::Quit => return Ok(()),
+                    EventOutcome::LeftImagePreview => {
+                        // Back to the TUI; the image stays in the scrollback.
+                        execute!(std::io::stdout(), EnterAlternateScreen)?;
+                        terminal.clear()?;
+                    }
+                }
             }
         }
     }
@@ -1,23 +1,34 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use agent_client_protocol::{
-    Agent, ClientSideConnection, ContentBlock, ModelId, Plan, PromptRequest, SessionId,
-    SessionModelState, SessionNotification, SessionUpdate, SetSessionModelRequest, TextContent,
-    ToolCall, ToolCallStatus, ToolCallUpdate, ToolKind,
+    Agent, AvailableCommand, ClientSideConnection, ContentBlock, ModelId, Plan, PromptRequest,
+    RequestPermissionOutcome, SessionId, SessionModelState, SessionNotification, SessionUpdate,
+    SetSessionModelRequest, ToolCall, ToolCallContent, ToolCallLocation, ToolCallStatus,
+    ToolCallUpdate, ToolKind,
 };
 use ratatui::widgets::{Block, Borders};
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tui_textarea::TextArea;
 
+use crate::acp_client::attachments::Attachment;
 use crate::acp_client::connection::connect_to_provider;
 use crate::acp_client::events::AppEvent;
+use crate::acp_client::history::{self, SessionSummary};
+use crate::acp_client::keymap::{keymap, KeyChord};
 use crate::acp_client::markdown::normalize_code_fences;
+use crate::acp_client::mentions::{prompt_blocks, WorkspaceIndex};
+use crate::acp_client::permissions::{PendingPermission, PermissionPolicy};
 use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::review::ChangeReview;
+use crate::acp_client::tabs::{ChatTab, INITIAL_TAB_ID};
+use crate::acp_client::usage::{parse_usage, UsageStats};
 use crate::acp_client::workspace_sync::WorkspaceSyncStatus;
 use crate::palette::{fuzzy_match_str, PaletteCommand as PaletteCommandTrait};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) enum ChatEntry {
     Message {
         role: String,
@@ -29,8 +40,21 @@ pub(crate) enum ChatEntry {
         title: String,
         kind: ToolKind,
         status: ToolCallStatus,
+        #[serde(default)]
+        details: ToolCallDetails,
     },
     Plan(Plan),
+    /// An image or resource link from a message
+    Attachment(Attachment),
+}
+
+/// Everything a tool call reported beyond its title, shown in the detail view.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ToolCallDetails {
+    pub(crate) content: Vec<ToolCallContent>,
+    pub(crate) locations: Vec<ToolCallLocation>,
+    pub(crate) raw_input: Option<serde_json::Value>,
+    pub(crate) raw_output: Option<serde_json::Value>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -38,6 +62,13 @@ pub(crate) enum ConnectionState {
     Connecting,
     Connected,
     SwitchingProvider(AcpProvider),
+    /// The websocket dropped; retrying in the background
+    Reconnecting {
+        attempt: u32,
+        max: u32,
+    },
+    /// Reconnecting gave up
+    Disconnected,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -45,12 +76,37 @@ pub(crate) enum UiMode {
     Chat,
     MainPalette,
     SwitchPalette,
+    SessionPalette,
+    PermissionPrompt,
+    /// Navigating tool calls in the history
+    ToolSelect,
+    /// Showing the selected tool call's details
+    ToolDetail,
+    /// Picking one of the user's messages to edit and resend
+    MessageSelect,
+    /// Full-screen review of the files the agent changed
+    Review,
+    AttachmentPalette,
+    /// An image is shown on the main screen; any key returns
+    ImagePreview,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) enum PaletteCommand {
     ToggleDebugMode,
     SwitchProviderModel,
+    LoadPreviousSession,
+    PermissionsAlwaysAsk,
+    PermissionsAllowReads,
+    PermissionsAllowAll,
+    NewTab,
+    CloseTab,
+    ShowUsage,
+    OpenAttachment,
+    EditPreviousMessage,
+    ReviewChanges,
+    ShowKeybindings,
+    Reconnect,
 }
 
 impl PaletteCommand {
@@ -58,6 +114,18 @@ impl PaletteCommand {
         &[
             PaletteCommand::ToggleDebugMode,
             PaletteCommand::SwitchProviderModel,
+            PaletteCommand::LoadPreviousSession,
+            PaletteCommand::PermissionsAlwaysAsk,
+            PaletteCommand::PermissionsAllowReads,
+            PaletteCommand::PermissionsAllowAll,
+            PaletteCommand::NewTab,
+            PaletteCommand::CloseTab,
+            PaletteCommand::ShowUsage,
+            PaletteCommand::OpenAttachment,
+            PaletteCommand::EditPreviousMessage,
+            PaletteCommand::ReviewChanges,
+            PaletteCommand::ShowKeybindings,
+            PaletteCommand::Reconnect,
         ]
     }
 
@@ -65,6 +133,18 @@ impl PaletteCommand {
         match self {
             PaletteCommand::ToggleDebugMode => "Toggle Debug Mode",
             PaletteCommand::SwitchProviderModel => "Switch Provider / Model",
+            PaletteCommand::LoadPreviousSession => "Load Previous Session",
+            PaletteCommand::PermissionsAlwaysAsk => "Permissions: Always Ask",
+            PaletteCommand::PermissionsAllowReads => "Permissions: Allow Reads",
+            PaletteCommand::PermissionsAllowAll => "Permissions: Allow All",
+            PaletteCommand::NewTab => "New Tab",
+            PaletteCommand::CloseTab => "Close Tab",
+            PaletteCommand::ShowUsage => "Show Usage",
+            PaletteCommand::OpenAttachment => "Open Attachment",
+            PaletteCommand::EditPreviousMessage => "Edit Previous Message",
+            PaletteCommand::ReviewChanges => "Review Changes",
+            PaletteCommand::ShowKeybindings => "Show Keybindings",
+            PaletteCommand::Reconnect => "Reconnect",
         }
     }
 
@@ -72,6 +152,24 @@ impl PaletteCommand {
         match self {
             PaletteCommand::ToggleDebugMode => "Show/hide raw ACP protocol messages",
             PaletteCommand::SwitchProviderModel => "Change AI provider or model",
+            PaletteCommand::LoadPreviousSession => "Show a saved conversation for this sandbox",
+            PaletteCommand::PermissionsAlwaysAsk => "Prompt before every agent action",
+            PaletteCommand::PermissionsAllowReads => {
+                "Approve reads and searches, prompt for the rest"
+            }
+            PaletteCommand::PermissionsAllowAll => "Approve every agent action without prompting",
+            PaletteCommand::NewTab => "Start another session alongside this one",
+            PaletteCommand::CloseTab => "Close the current session tab",
+            PaletteCommand::ShowUsage => "Token and cost breakdown per turn",
+            PaletteCommand::OpenAttachment => "Open an image or linked resource from this chat",
+            PaletteCommand::EditPreviousMessage => {
+                "Change an earlier message and continue the chat from there"
+            }
+            PaletteCommand::ReviewChanges => {
+                "Diff the files the agent changed; accept or revert each"
+            }
+            PaletteCommand::ShowKeybindings => "List key bindings and where to change them",
+            PaletteCommand::Reconnect => "Reconnect to the sandbox and resume the session",
         }
     }
 
@@ -142,6 +240,61 @@ pub(crate) struct App<'a> {
     pub(crate) providers_loading: Vec<AcpProvider>,
     pub(crate) pending_model_switch: Option<ModelId>,
     pub(crate) workspace_sync_state: WorkspaceSyncState,
+    /// Root of the on-disk chat history; `None` disables persistence.
+    pub(crate) history_dir: Option<PathBuf>,
+    /// Session file the current history is written to, created on first flush.
+    pub(crate) history_file: Option<PathBuf>,
+    /// Indices of history entries changed since the last flush.
+    pub(crate) history_dirty: BTreeSet<usize>,
+    /// Sessions listed by the "Load Previous Session" palette.
+    pub(crate) saved_sessions: Vec<SessionSummary>,
+    pub(crate) permission_policy: PermissionPolicy,
+    /// Permission requests awaiting an answer; the front one is shown.
+    pub(crate) pending_permissions: VecDeque<PendingPermission>,
+    pub(crate) permission_selection: usize,
+    /// History index of the tool call selected in `ToolSelect`/`ToolDetail`.
+    pub(crate) selected_tool_call: Option<usize>,
+    pub(crate) tool_detail_scroll: u16,
+    /// History index of the user message selected in `MessageSelect`.
+    pub(crate) selected_message: Option<usize>,
+    /// History index of the message being edited; sending resends from there.
+    pub(crate) editing_from: Option<usize>,
+    /// Accept/revert decisions and position of the changes review.
+    pub(crate) review: ChangeReview,
+    /// Slash commands advertised by the agent for the current session.
+    pub(crate) available_commands: Vec<AvailableCommand>,
+    /// Selected row of the slash command popup.
+    pub(crate) command_selection: usize,
+    /// Sandbox files offered by `@` mentions, shared by every tab.
+    pub(crate) workspace_index: WorkspaceIndex,
+    /// Selected row of the `@` mention popup.
+    pub(crate) mention_selection: usize,
+    /// Chords typed so far of a multi-key binding.
+    pub(crate) pending_keys: Vec<KeyChord>,
+    /// Image waiting to be shown by the runner, which owns the terminal.
+    pub(crate) image_preview: Option<PathBuf>,
+    /// Token and cost usage of the current session.
+    pub(crate) usage: UsageStats,
+    /// Id of the active tab; its session state lives in the fields above.
+    pub(crate) tab_id: u64,
+    /// Open tabs in display order; the entry at `active_tab` is a placeholder.
+    pub(crate) tabs: Vec<ChatTab<'a>>,
+    pub(crate) active_tab: usize,
+    pub(crate) next_tab_id: u64,
+}
+
+/// The message input, styled for the chat view.
+pub(crate) fn chat_textarea<'a>() -> TextArea<'a> {
+    let mut textarea = TextArea::default();
+    textarea.set_block(
+        Block::default()
+            .borders(Borders::TOP | Borders::BOTTOM)
+            .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray)),
+    );
+    textarea.set_placeholder_text(
+        "Type a message and press Enter to send. Ctrl+J for new line, @ to mention a file.",
+    );
+    textarea
 }
 
 impl<'a> App<'a> {
@@ -151,22 +304,13 @@ impl<'a> App<'a> {
         base_url: String,
         sandbox_id: String,
     ) -> Self {
-        let mut textarea = TextArea::default();
-        textarea.set_block(
-            Block::default()
-                .borders(Borders::TOP | Borders::BOTTOM)
-                .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray)),
-        );
-        textarea
-            .set_placeholder_text("Type a message and press Enter to send. Ctrl+J for new line.");
-
         let mut palette_input = TextArea::default();
         palette_input.set_placeholder_text("Type to search...");
         palette_input.set_cursor_line_style(ratatui::style::Style::default());
 
         Self {
             history: vec![],
-            textarea,
+            textarea: chat_textarea(),
             client_connection: None,
             session_id: None,
             scroll_offset_from_bottom: 0,
@@ -186,6 +330,107 @@ impl<'a> App<'a> {
             providers_loading: vec![],
             pending_model_switch: None,
             workspace_sync_state: WorkspaceSyncState::Idle,
+            history_dir: None,
+            history_file: None,
+            history_dirty: BTreeSet::new(),
+            saved_sessions: vec![],
+            permission_policy: PermissionPolicy::default(),
+            pending_permissions: VecDeque::new(),
+            permission_selection: 0,
+            selected_tool_call: None,
+            tool_detail_scroll: 0,
+            selected_message: None,
+            editing_from: None,
+            review: ChangeReview::default(),
+            available_commands: vec![],
+            command_selection: 0,
+            workspace_index: WorkspaceIndex::NotLoaded,
+            mention_selection: 0,
+            pending_keys: vec![],
+            image_preview: None,
+            usage: UsageStats::default(),
+            tab_id: INITIAL_TAB_ID,
+            tabs: vec![ChatTab::new(INITIAL_TAB_ID, provider)],
+            active_tab: 0,
+            next_tab_id: INITIAL_TAB_ID + 1,
+        }
+    }
+
+    /// Persist history under `dir` and show the most recent session for this sandbox.
+    pub(crate) fn enable_history(&mut self, dir: PathBuf) {
+        let latest = history::list_sessions(&dir, &self.sandbox_id, 1)
+            .into_iter()
+            .next();
+        self.history_dir = Some(dir);
+        if let Some(session) = latest {
+            self.load_session(&session.path);
+        }
+    }
+
+    /// Replace the history with a saved session and continue writing to its file.
+    pub(crate) fn load_session(&mut self, path: &std::path::Path) {
+        match history::load_session(path) {
+            Ok(entries) if !entries.is_empty() => {
+                self.flush_history();
+                self.history = entries;
+                self.history_dirty.clear();
+                self.editing_from = None;
+                self.history_file = Some(path.to_path_buf());
+                self.push_entry(ChatEntry::Message {
+                    role: "System".to_string(),
+                    text: "Restored previous session. The agent starts without this context."
+                        .to_string(),
+                    normalized_markdown: None,
+                });
+                self.scroll_to_bottom();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                crate::acp_client::logging::log_debug(&format!(
+                    "Failed to load chat history {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+    }
+
+    /// Start a fresh history (and session file), e.g. after switching provider.
+    pub(crate) fn clear_history(&mut self) {
+        self.flush_history();
+        self.history.clear();
+        self.history_file = None;
+        self.editing_from = None;
+    }
+
+    pub(crate) fn push_entry(&mut self, entry: ChatEntry) {
+        self.history.push(entry);
+        self.history_dirty.insert(self.history.len() - 1);
+    }
+
+    /// Append changed entries to the session file.
+    pub(crate) fn flush_history(&mut self) {
+        let Some(ref dir) = self.history_dir else {
+            self.history_dirty.clear();
+            return;
+        };
+        if self.history_dirty.is_empty() {
+            return;
+        }
+        let path = self
+            .history_file
+            .get_or_insert_with(|| history::new_session_file(dir, &self.sandbox_id))
+            .clone();
+        let dirty = std::mem::take(&mut self.history_dirty);
+        let entries = dirty
+            .into_iter()
+            .filter_map(|i| self.history.get(i).map(|entry| (i, entry)));
+        if let Err(e) = history::append_entries(&path, entries) {
+            crate::acp_client::logging::log_debug(&format!(
+                "Failed to write chat history {}: {}",
+                path.display(),
+                e
+            ));
         }
     }
 
@@ -242,6 +487,73 @@ impl<'a> App<'a> {
         }
     }
 
+    pub(crate) fn open_session_palette(&mut self) {
+        self.flush_history();
+        self.saved_sessions = match self.history_dir {
+            Some(ref dir) => {
+                history::list_sessions(dir, &self.sandbox_id, history::RECENT_SESSION_LIMIT)
+            }
+            None => vec![],
+        };
+        self.ui_mode = UiMode::SessionPalette;
+        self.palette_selection = 0;
+        self.palette_input = TextArea::default();
+        self.palette_input
+            .set_placeholder_text("Type to filter sessions...");
+        self.palette_input
+            .set_cursor_line_style(ratatui::style::Style::default());
+    }
+
+    pub(crate) fn get_session_palette_items(&self) -> Vec<&SessionSummary> {
+        let search = self.palette_search();
+        self.saved_sessions
+            .iter()
+            .filter(|s| search.is_empty() || fuzzy_match_str(&search, &s.preview))
+            .collect()
+    }
+
+    pub(crate) fn execute_session_palette_selection(&mut self) {
+        let selected = self
+            .get_session_palette_items()
+            .get(self.palette_selection)
+            .map(|s| s.path.clone());
+        self.ui_mode = UiMode::Chat;
+        if let Some(path) = selected {
+            if self.history_file.as_ref() != Some(&path) {
+                self.load_session(&path);
+            }
+        }
+    }
+
+    pub(crate) fn open_attachment_palette(&mut self) {
+        self.ui_mode = UiMode::AttachmentPalette;
+        self.palette_selection = 0;
+        self.palette_input = TextArea::default();
+        self.palette_input
+            .set_placeholder_text("Type to filter attachments...");
+        self.palette_input
+            .set_cursor_line_style(ratatui::style::Style::default());
+    }
+
+    pub(crate) fn get_attachment_palette_items(&self) -> Vec<&Attachment> {
+        let search = self.palette_search();
+        self.attachments()
+            .into_iter()
+            .filter(|a| search.is_empty() || fuzzy_match_str(&search, &a.name))
+            .collect()
+    }
+
+    pub(crate) fn execute_attachment_palette_selection(&mut self) {
+        let selected = self
+            .get_attachment_palette_items()
+            .get(self.palette_selection)
+            .map(|a| (*a).clone());
+        self.ui_mode = UiMode::Chat;
+        if let Some(attachment) = selected {
+            self.open_attachment(&attachment);
+        }
+    }
+
     pub(crate) fn get_switch_palette_items(&self) -> Vec<SwitchPaletteItem> {
         let search = self.palette_search();
         let mut items = Vec::new();
@@ -332,7 +644,15 @@ impl<'a> App<'a> {
                 .iter()
                 .filter(|item| item.is_selectable())
                 .count(),
-            UiMode::Chat => 0,
+            UiMode::SessionPalette => self.get_session_palette_items().len(),
+            UiMode::AttachmentPalette => self.get_attachment_palette_items().len(),
+            UiMode::PermissionPrompt
+            | UiMode::ToolSelect
+            | UiMode::ToolDetail
+            | UiMode::MessageSelect
+            | UiMode::Review
+            | UiMode::ImagePreview
+            | UiMode::Chat => 0,
         }
     }
 
@@ -439,26 +759,33 @@ impl<'a> App<'a> {
         let tx = self.event_tx.clone();
         let base_url = self.base_url.clone();
         let sandbox_id = self.sandbox_id.clone();
+        let tab = self.tab_id;
 
         tokio::task::spawn_local(async move {
             match connect_to_provider(&base_url, &sandbox_id, provider, tx.clone()).await {
                 Ok((connection, session_id, model_state)) => {
-                    let _ = tx.send(AppEvent::ProviderSwitchComplete {
-                        provider,
-                        connection,
-                        session_id,
-                        model_state,
-                    });
+                    let _ = tx.send(AppEvent::for_tab(
+                        tab,
+                        AppEvent::ProviderSwitchComplete {
+                            provider,
+                            connection,
+                            session_id,
+                            model_state,
+                        },
+                    ));
                 }
                 Err(e) => {
                     crate::acp_client::logging::log_debug(&format!(
                         "Provider switch failed: {}",
                         e
                     ));
-                    let _ = tx.send(AppEvent::ProviderSwitchFailed {
-                        provider,
-                        error: e.to_string(),
-                    });
+                    let _ = tx.send(AppEvent::for_tab(
+                        tab,
+                        AppEvent::ProviderSwitchFailed {
+                            provider,
+                            error: e.to_string(),
+                        },
+                    ));
                 }
             }
         });
@@ -468,6 +795,7 @@ impl<'a> App<'a> {
         let tx = self.event_tx.clone();
         let conn = self.client_connection.clone();
         let session_id = self.session_id.clone();
+        let tab = self.tab_id;
 
         if let (Some(conn), Some(session_id)) = (conn, session_id) {
             let model_id_clone = model_id.clone();
@@ -480,24 +808,172 @@ impl<'a> App<'a> {
 
                 match Agent::set_session_model(&*conn, request).await {
                     Ok(_) => {
-                        let _ = tx.send(AppEvent::ModelSwitchComplete {
-                            model_id: model_id_clone,
-                        });
+                        let _ = tx.send(AppEvent::for_tab(
+                            tab,
+                            AppEvent::ModelSwitchComplete {
+                                model_id: model_id_clone,
+                            },
+                        ));
                     }
                     Err(e) => {
                         crate::acp_client::logging::log_debug(&format!(
                             "Model switch failed: {}",
                             e
                         ));
-                        let _ = tx.send(AppEvent::ModelSwitchFailed {
-                            error: e.to_string(),
-                        });
+                        let _ = tx.send(AppEvent::for_tab(
+                            tab,
+                            AppEvent::ModelSwitchFailed {
+                                error: e.to_string(),
+                            },
+                        ));
                     }
                 }
             });
         }
     }
 
+    pub(crate) fn on_permission_request(&mut self, pending: PendingPermission) {
+        if let Some(option_id) = self.permission_policy.auto_option(&pending.request) {
+            crate::acp_client::logging::log_debug(&format!(
+                "Auto-approved permission ({}): {}",
+                self.permission_policy.display_name(),
+                option_id
+            ));
+            pending.respond(RequestPermissionOutcome::Selected { option_id });
+            return;
+        }
+        self.pending_permissions.push_back(pending);
+        if self.ui_mode != UiMode::PermissionPrompt {
+            self.ui_mode = UiMode::PermissionPrompt;
+            self.permission_selection = 0;
+        }
+    }
+
+    pub(crate) fn permission_up(&mut self) {
+        let len = self
+            .pending_permissions
+            .front()
+            .map_or(0, |p| p.request.options.len());
+        if len > 0 {
+            self.permission_selection = (self.permission_selection + len - 1) % len;
+        }
+    }
+
+    pub(crate) fn permission_down(&mut self) {
+        let len = self
+            .pending_permissions
+            .front()
+            .map_or(0, |p| p.request.options.len());
+        if len > 0 {
+            self.permission_selection = (self.permission_selection + 1) % len;
+        }
+    }
+
+    /// Answer the shown request with the option at `index`.
+    pub(crate) fn select_permission(&mut self, index: usize) {
+        let Some(pending) = self.pending_permissions.front() else {
+            return;
+        };
+        let Some(option) = pending.request.options.get(index) else {
+            return;
+        };
+        let outcome = RequestPermissionOutcome::Selected {
+            option_id: option.id.clone(),
+        };
+        self.answer_permission(outcome);
+    }
+
+    /// Dismiss the shown request, rejecting it.
+    pub(crate) fn reject_permission(&mut self) {
+        if let Some(pending) = self.pending_permissions.front() {
+            let outcome = pending.reject_outcome();
+            self.answer_permission(outcome);
+        }
+    }
+
+    fn answer_permission(&mut self, outcome: RequestPermissionOutcome) {
+        if let Some(pending) = self.pending_permissions.pop_front() {
+            pending.respond(outcome);
+        }
+        self.permission_selection = 0;
+        if self.pending_permissions.is_empty() {
+            self.ui_mode = UiMode::Chat;
+        }
+    }
+
+    fn tool_call_indices(&self) -> Vec<usize> {
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches!(entry, ChatEntry::ToolCall { .. }))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Start navigating tool calls, beginning with the most recent one.
+    pub(crate) fn start_tool_selection(&mut self) {
+        if let Some(last) = self.tool_call_indices().last() {
+            self.selected_tool_call = Some(*last);
+            self.ui_mode = UiMode::ToolSelect;
+        }
+    }
+
+    /// Move the tool call selection; negative `delta` moves towards older calls.
+    pub(crate) fn move_tool_selection(&mut self, delta: isize) {
+        let indices = self.tool_call_indices();
+        let Some(current) = self.selected_tool_call else {
+            return;
+        };
+        let pos = indices.iter().position(|i| *i == current).unwrap_or(0);
+        let target = pos
+            .saturating_add_signed(delta)
+            .min(indices.len().saturating_sub(1));
+        self.selected_tool_call = indices.get(target).copied();
+    }
+
+    pub(crate) fn open_tool_detail(&mut self) {
+        if self.selected_tool_call.is_some() {
+            self.tool_detail_scroll = 0;
+            self.ui_mode = UiMode::ToolDetail;
+        }
+    }
+
+    pub(crate) fn close_tool_detail(&mut self) {
+        self.ui_mode = UiMode::ToolSelect;
+    }
+
+    pub(crate) fn exit_tool_selection(&mut self) {
+        self.selected_tool_call = None;
+        self.ui_mode = UiMode::Chat;
+    }
+
+    pub(crate) fn scroll_tool_detail_up(&mut self, lines: u16) {
+        self.tool_detail_scroll = self.tool_detail_scroll.saturating_sub(lines);
+    }
+
+    pub(crate) fn scroll_tool_detail_down(&mut self, lines: u16) {
+        self.tool_detail_scroll = self.tool_detail_scroll.saturating_add(lines);
+    }
+
+    /// Add the per-turn usage table to the chat.
+    pub(crate) fn show_usage(&mut self) {
+        self.push_entry(ChatEntry::Message {
+            role: "System".to_string(),
+            text: self.usage.breakdown(),
+            normalized_markdown: None,
+        });
+    }
+
+    /// Add the current key bindings to the chat.
+    pub(crate) fn show_keybindings(&mut self) {
+        self.push_entry(ChatEntry::Message {
+            role: "System".to_string(),
+            text: keymap().describe(),
+            normalized_markdown: None,
+        });
+        self.scroll_to_bottom();
+    }
+
     pub(crate) fn toggle_debug_mode(&mut self) {
         self.debug_mode = !self.debug_mode;
         if !self.debug_mode {
@@ -536,16 +1012,24 @@ impl<'a> App<'a> {
     }
 
     pub(crate) fn on_session_update(&mut self, notification: SessionNotification) {
+        // While reconnecting, `session/load` replays the conversation we already have.
+        if matches!(self.connection_state, ConnectionState::Reconnecting { .. })
+            && !matches!(
+                notification.update,
+                SessionUpdate::AvailableCommandsUpdate(_)
+            )
+        {
+            return;
+        }
+        if let Some(usage) = notification.meta.as_ref().and_then(parse_usage) {
+            self.usage.record(usage);
+        }
         match notification.update {
             SessionUpdate::UserMessageChunk(chunk) => {
-                if let ContentBlock::Text(text_content) = chunk.content {
-                    self.append_message("User", &text_content.text);
-                }
+                self.append_content("User", chunk.content);
             }
             SessionUpdate::AgentMessageChunk(chunk) => {
-                if let ContentBlock::Text(text_content) = chunk.content {
-                    self.append_message("Agent", &text_content.text);
-                }
+                self.append_content("Agent", chunk.content);
             }
             SessionUpdate::AgentThoughtChunk(chunk) => {
                 if let ContentBlock::Text(text_content) = chunk.content {
@@ -561,7 +1045,29 @@ impl<'a> App<'a> {
             SessionUpdate::Plan(plan) => {
                 self.update_plan(plan);
             }
-            SessionUpdate::AvailableCommandsUpdate(_) | SessionUpdate::CurrentModeUpdate(_) => {}
+            SessionUpdate::AvailableCommandsUpdate(update) => {
+                self.available_commands = update.available_commands;
+                self.command_selection = 0;
+            }
+            SessionUpdate::CurrentModeUpdate(_) => {}
+        }
+    }
+
+    fn append_content(&mut self, role: &str, content: ContentBlock) {
+        match content {
+            ContentBlock::Text(text_content) => self.append_message(role, &text_content.text),
+            ContentBlock::Image(image) => match Attachment::from_image(&image) {
+                Ok(attachment) => self.push_entry(ChatEntry::Attachment(attachment)),
+                Err(e) => self.push_entry(ChatEntry::Message {
+                    role: "Error".to_string(),
+                    text: format!("Failed to save image: {}", e),
+                    normalized_markdown: None,
+                }),
+            },
+            ContentBlock::ResourceLink(link) => {
+                self.push_entry(ChatEntry::Attachment(Attachment::from_resource_link(&link)));
+            }
+            ContentBlock::Audio(_) | ContentBlock::Resource(_) => {}
         }
     }
 
@@ -580,6 +1086,7 @@ impl<'a> App<'a> {
                 if matches!(role, "Agent" | "Thought") {
                     *normalized_markdown = Some(normalize_code_fences(last_text));
                 }
+                self.history_dirty.insert(self.history.len() - 1);
                 return;
             }
         }
@@ -588,7 +1095,7 @@ impl<'a> App<'a> {
         } else {
             None
         };
-        self.history.push(ChatEntry::Message {
+        self.push_entry(ChatEntry::Message {
             role: role.to_string(),
             text: text.to_string(),
             normalized_markdown,
@@ -596,22 +1103,29 @@ impl<'a> App<'a> {
     }
 
     fn add_tool_call(&mut self, tool_call: ToolCall) {
-        self.history.push(ChatEntry::ToolCall {
+        self.push_entry(ChatEntry::ToolCall {
             id: tool_call.id.to_string(),
             title: tool_call.title,
             kind: tool_call.kind,
             status: tool_call.status,
+            details: ToolCallDetails {
+                content: tool_call.content,
+                locations: tool_call.locations,
+                raw_input: tool_call.raw_input,
+                raw_output: tool_call.raw_output,
+            },
         });
     }
 
     fn update_tool_call(&mut self, update: ToolCallUpdate) {
         let id_str = update.id.to_string();
-        for entry in self.history.iter_mut().rev() {
+        for (index, entry) in self.history.iter_mut().enumerate().rev() {
             if let ChatEntry::ToolCall {
                 id,
                 title,
                 kind,
                 status,
+                details,
             } = entry
             {
                 if id == &id_str {
@@ -624,31 +1138,51 @@ impl<'a> App<'a> {
                     if let Some(new_status) = update.fields.status {
                         *status = new_status;
                     }
+                    if let Some(content) = update.fields.content {
+                        details.content = content;
+                    }
+                    if let Some(locations) = update.fields.locations {
+                        details.locations = locations;
+                    }
+                    if let Some(raw_input) = update.fields.raw_input {
+                        details.raw_input = Some(raw_input);
+                    }
+                    if let Some(raw_output) = update.fields.raw_output {
+                        details.raw_output = Some(raw_output);
+                    }
+                    self.history_dirty.insert(index);
                     return;
                 }
             }
         }
         if let Some(title) = update.fields.title {
-            self.history.push(ChatEntry::ToolCall {
+            self.push_entry(ChatEntry::ToolCall {
                 id: id_str,
                 title,
                 kind: update.fields.kind.unwrap_or_default(),
                 status: update.fields.status.unwrap_or_default(),
+                details: ToolCallDetails {
+                    content: update.fields.content.unwrap_or_default(),
+                    locations: update.fields.locations.unwrap_or_default(),
+                    raw_input: update.fields.raw_input,
+                    raw_output: update.fields.raw_output,
+                },
             });
         }
     }
 
     fn update_plan(&mut self, plan: Plan) {
-        for entry in self.history.iter_mut().rev() {
+        for (index, entry) in self.history.iter_mut().enumerate().rev() {
             if matches!(entry, ChatEntry::Plan(_)) {
                 *entry = ChatEntry::Plan(plan);
+                self.history_dirty.insert(index);
                 return;
             }
         }
-        self.history.push(ChatEntry::Plan(plan));
+        self.push_entry(ChatEntry::Plan(plan));
     }
 
-    pub(crate) async fn send_message(&mut self) {
+    pub(crate) fn send_message(&mut self) {
         let (conn, session_id, tx) =
             if let (Some(conn), Some(session_id)) = (&self.client_connection, &self.session_id) {
                 (conn.clone(), session_id.clone(), self.event_tx.clone())
@@ -662,34 +1196,48 @@ impl<'a> App<'a> {
             return;
         }
 
+        if let Some(index) = self.editing_from.take() {
+            self.resend_from(index, text);
+            return;
+        }
+
         self.append_message("User", &text);
 
-        self.textarea = TextArea::default();
-        self.textarea.set_block(
-            Block::default()
-                .borders(Borders::TOP | Borders::BOTTOM)
-                .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray)),
-        );
-        self.textarea
-            .set_placeholder_text("Type a message and press Enter to send. Ctrl+J for new line.");
+        self.textarea = chat_textarea();
+        self.usage.start_turn();
+        let tab = self.tab_id;
 
         let request = PromptRequest {
             session_id,
-            prompt: vec![ContentBlock::Text(TextContent {
-                text,
-                annotations: None,
-                meta: None,
-            })],
+            prompt: prompt_blocks(&text, self.indexed_files()),
             meta: None,
         };
 
-        tokio::task::spawn_local(async move {
-            if let Err(error) = Agent::prompt(&*conn, request).await {
-                crate::acp_client::logging::log_debug(&format!("Prompt failed: {}", error));
-                let _ = tx.send(AppEvent::RequestError {
-                    error: error.to_string(),
-                });
+        tokio::task::spawn_local(run_prompt(conn, request, tab, tx));
+    }
+}
+
+/// Send a prompt and report its usage, or the error, to `tab`.
+pub(crate) async fn run_prompt(
+    conn: Arc<ClientSideConnection>,
+    request: PromptRequest,
+    tab: u64,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) {
+    match Agent::prompt(&*conn, request).await {
+        Ok(response) => {
+            if let Some(usage) = response.meta.as_ref().and_then(parse_usage) {
+                let _ = tx.send(AppEvent::for_tab(tab, AppEvent::UsageReported(usage)));
             }
-        });
+        }
+        Err(error) => {
+            crate::acp_client::logging::log_debug(&format!("Prompt failed: {}", error));
+            let _ = tx.send(AppEvent::for_tab(
+                tab,
+                AppEvent::RequestError {
+                    error: error.to_string(),
+                },
+            ));
+        }
     }
 }
@@ -0,0 +1,302 @@
+//! Multiple chat sessions in one TUI.
+//!
+//! `App` keeps the active tab's session state in its own fields so the rest of
+//! the client can keep using them directly. Inactive tabs are parked in
+//! `App::tabs`; switching swaps the fields in and out. The slot at
+//! `App::active_tab` is an empty placeholder while its tab is active.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use agent_client_protocol::{
+    AvailableCommand, ClientSideConnection, ModelId, SessionId, SessionModelState,
+};
+use tui_textarea::TextArea;
+
+use crate::acp_client::connection::{connect_to_provider, start_session};
+use crate::acp_client::events::AppEvent;
+use crate::acp_client::provider::AcpProvider;
+use crate::acp_client::review::ChangeReview;
+use crate::acp_client::state::{chat_textarea, App, ChatEntry, ConnectionState, UiMode};
+use crate::acp_client::usage::UsageStats;
+
+/// Id of the tab created with the app.
+pub(crate) const INITIAL_TAB_ID: u64 = 0;
+/// Tabs addressable by Ctrl/Alt + number.
+pub(crate) const MAX_NUMBERED_TABS: usize = 9;
+
+/// Per-session state of a tab that isn't currently shown.
+pub(crate) struct ChatTab<'a> {
+    pub(crate) id: u64,
+    pub(crate) history: Vec<ChatEntry>,
+    pub(crate) textarea: TextArea<'a>,
+    pub(crate) client_connection: Option<Arc<ClientSideConnection>>,
+    pub(crate) session_id: Option<SessionId>,
+    pub(crate) scroll_offset_from_bottom: u16,
+    pub(crate) current_provider: AcpProvider,
+    pub(crate) connection_state: ConnectionState,
+    pub(crate) model_state: Option<SessionModelState>,
+    pub(crate) model_switching: bool,
+    pub(crate) pending_model_switch: Option<ModelId>,
+    pub(crate) history_file: Option<PathBuf>,
+    pub(crate) history_dirty: BTreeSet<usize>,
+    pub(crate) selected_tool_call: Option<usize>,
+    pub(crate) selected_message: Option<usize>,
+    pub(crate) editing_from: Option<usize>,
+    pub(crate) review: ChangeReview,
+    pub(crate) available_commands: Vec<AvailableCommand>,
+    pub(crate) usage: UsageStats,
+}
+
+impl ChatTab<'_> {
+    pub(crate) fn new(id: u64, provider: AcpProvider) -> Self {
+        Self {
+            id,
+            history: vec![],
+            textarea: chat_textarea(),
+            client_connection: None,
+            session_id: None,
+            scroll_offset_from_bottom: 0,
+            current_provider: provider,
+            connection_state: ConnectionState::Connecting,
+            model_state: None,
+            model_switching: false,
+            pending_model_switch: None,
+            history_file: None,
+            history_dirty: BTreeSet::new(),
+            selected_tool_call: None,
+            selected_message: None,
+            editing_from: None,
+            review: ChangeReview::default(),
+            available_commands: vec![],
+            usage: UsageStats::default(),
+        }
+    }
+}
+
+impl App<'_> {
+    /// Exchange the active session state with the tab parked at `index`.
+    fn swap_tab(&mut self, index: usize) {
+        let tab = &mut self.tabs[index];
+        std::mem::swap(&mut self.tab_id, &mut tab.id);
+        std::mem::swap(&mut self.history, &mut tab.history);
+        std::mem::swap(&mut self.textarea, &mut tab.textarea);
+        std::mem::swap(&mut self.client_connection, &mut tab.client_connection);
+        std::mem::swap(&mut self.session_id, &mut tab.session_id);
+        std::mem::swap(
+            &mut self.scroll_offset_from_bottom,
+            &mut tab.scroll_offset_from_bottom,
+        );
+        std::mem::swap(&mut self.current_provider, &mut tab.current_provider);
+        std::mem::swap(&mut self.connection_state, &mut tab.connection_state);
+        std::mem::swap(&mut self.model_state, &mut tab.model_state);
+        std::mem::swap(&mut self.model_switching, &mut tab.model_switching);
+        std::mem::swap(
+            &mut self.pending_model_switch,
+            &mut tab.pending_model_switch,
+        );
+        std::mem::swap(&mut self.history_file, &mut tab.history_file);
+        std::mem::swap(&mut self.history_dirty, &mut tab.history_dirty);
+        std::mem::swap(&mut self.selected_tool_call, &mut tab.selected_tool_call);
+        std::mem::swap(&mut self.selected_message, &mut tab.selected_message);
+        std::mem::swap(&mut self.editing_from, &mut tab.editing_from);
+        std::mem::swap(&mut self.review, &mut tab.review);
+        std::mem::swap(&mut self.available_commands, &mut tab.available_commands);
+        std::mem::swap(&mut self.usage, &mut tab.usage);
+    }
+
+    pub(crate) fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        let active = self.active_tab;
+        self.swap_tab(active);
+        self.active_tab = index;
+        self.swap_tab(index);
+        if matches!(
+            self.ui_mode,
+            UiMode::ToolSelect | UiMode::ToolDetail | UiMode::MessageSelect | UiMode::Review
+        ) {
+            self.ui_mode = UiMode::Chat;
+        }
+    }
+
+    /// Run `f` with tab `id` temporarily active. `None` if the tab was closed.
+    pub(crate) fn with_tab<R>(&mut self, id: u64, f: impl FnOnce(&mut Self) -> R) -> Option<R> {
+        if self.tab_id == id {
+            return Some(f(self));
+        }
+        let active = self.active_tab;
+        let index = self
+            .tabs
+            .iter()
+            .enumerate()
+            .position(|(i, tab)| i != active && tab.id == id)?;
+        self.swap_tab(active);
+        self.swap_tab(index);
+        let result = f(self);
+        self.swap_tab(index);
+        self.swap_tab(active);
+        Some(result)
+    }
+
+    pub(crate) fn tab_for_session(&self, session_id: &SessionId) -> Option<u64> {
+        if self.session_id.as_ref() == Some(session_id) {
+            return Some(self.tab_id);
+        }
+        self.tabs
+            .iter()
+            .enumerate()
+            .find(|(i, tab)| *i != self.active_tab && tab.session_id.as_ref() == Some(session_id))
+            .map(|(_, tab)| tab.id)
+    }
+
+    /// Ids of every open tab, in display order.
+    pub(crate) fn tab_ids(&self) -> Vec<u64> {
+        (0..self.tabs.len())
+            .map(|i| {
+                if i == self.active_tab {
+                    self.tab_id
+                } else {
+                    self.tabs[i].id
+                }
+            })
+            .collect()
+    }
+
+    /// `(provider, connecting)` per tab, in display order.
+    pub(crate) fn tab_labels(&self) -> Vec<(AcpProvider, bool)> {
+        (0..self.tabs.len())
+            .map(|i| {
+                let (provider, state) = if i == self.active_tab {
+                    (self.current_provider, self.connection_state)
+                } else {
+                    (self.tabs[i].current_provider, self.tabs[i].connection_state)
+                };
+                (provider, state != ConnectionState::Connected)
+            })
+            .collect()
+    }
+
+    /// Open a tab with a fresh session on the current provider. The session
+    /// shares the active tab's websocket when it has one.
+    pub(crate) fn new_tab(&mut self) {
+        let provider = self.current_provider;
+        let shared = self
+            .client_connection
+            .clone()
+            .filter(|_| self.connection_state == ConnectionState::Connected);
+
+        let id = self.next_tab_id;
+        self.next_tab_id += 1;
+        self.tabs.push(ChatTab::new(id, provider));
+        self.switch_tab(self.tabs.len() - 1);
+
+        let tx = self.event_tx.clone();
+        let base_url = self.base_url.clone();
+        let sandbox_id = self.sandbox_id.clone();
+        tokio::task::spawn_local(async move {
+            let result = match shared {
+                Some(connection) => start_session(&connection)
+                    .await
+                    .map(|(session_id, model_state)| (connection, session_id, model_state)),
+                None => connect_to_provider(&base_url, &sandbox_id, provider, tx.clone()).await,
+            };
+            let event = match result {
+                Ok((connection, session_id, model_state)) => AppEvent::ProviderSwitchComplete {
+                    provider,
+                    connection,
+                    session_id,
+                    model_state,
+                },
+                Err(e) => AppEvent::ProviderSwitchFailed {
+                    provider,
+                    error: e.to_string(),
+                },
+            };
+            let _ = tx.send(AppEvent::for_tab(id, event));
+        });
+    }
+
+    /// Close the active tab, keeping at least one open.
+    pub(crate) fn close_tab(&mut self) {
+        if self.tabs.len() <= 1 {
+            return;
+        }
+        self.flush_history();
+        let closing = self.active_tab;
+        self.swap_tab(closing);
+        self.tabs.remove(closing);
+        let next = closing.min(self.tabs.len() - 1);
+        self.active_tab = next;
+        self.swap_tab(next);
+        if matches!(
+            self.ui_mode,
+            UiMode::ToolSelect | UiMode::ToolDetail | UiMode::MessageSelect | UiMode::Review
+        ) {
+            self.ui_mode = UiMode::Chat;
+        }
+    }
+
+    pub(crate) fn flush_all_history(&mut self) {
+        for id in self.tab_ids() {
+            self.with_tab(id, |app| app.flush_history());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn message(text: &str) -> ChatEntry {
+        ChatEntry::Message {
+            role: "User".to_string(),
+            text: text.to_string(),
+            normalized_markdown: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn tabs_keep_independent_state() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (tx, _rx) = mpsc::unbounded_channel();
+                let mut app = App::new(AcpProvider::default(), tx, String::new(), String::new());
+                app.session_id = Some(SessionId("first".into()));
+                app.history.push(message("first tab"));
+                let first = app.tab_id;
+
+                app.new_tab();
+                assert_eq!(app.active_tab, 1);
+                assert!(app.history.is_empty());
+                app.session_id = Some(SessionId("second".into()));
+                app.scroll_up(5);
+                let second = app.tab_id;
+
+                // Updates for a background tab land in that tab only.
+                app.with_tab(first, |app| app.history.push(message("background")));
+                assert!(app.history.is_empty());
+                assert_eq!(app.tab_for_session(&SessionId("first".into())), Some(first));
+                assert_eq!(
+                    app.tab_for_session(&SessionId("second".into())),
+                    Some(second)
+                );
+
+                app.switch_tab(0);
+                assert_eq!(app.history.len(), 2);
+                assert_eq!(app.scroll_offset_from_bottom, 0);
+
+                app.switch_tab(1);
+                assert_eq!(app.scroll_offset_from_bottom, 5);
+                app.close_tab();
+                assert_eq!(app.tabs.len(), 1);
+                assert_eq!(app.tab_id, first);
+                assert_eq!(app.history.len(), 2);
+            })
+            .await;
+    }
+}
@@ -0,0 +1,320 @@
+//! Client-side terminals for ACP agents (`terminal/*` methods).
+//!
+//! Each terminal runs its command on a local PTY. A reader thread collects
+//! output into a bounded buffer and a waiter thread publishes the exit status,
+//! so agent requests only ever take short locks or await a watch channel.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use agent_client_protocol::{EnvVariable, TerminalExitStatus, TerminalId};
+use portable_pty::{ChildKiller, CommandBuilder, MasterPty, NativePtySystem, PtySize, PtySystem};
+use tokio::sync::watch;
+
+/// Output retained when the agent doesn't pass `outputByteLimit`.
+const DEFAULT_OUTPUT_LIMIT: usize = 1024 * 1024;
+/// How long the waiter gives the reader to drain output after the child exits.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+/// Signal `ChildKiller::kill` sends on unix.
+const KILL_SIGNAL: &str = "SIGHUP";
+
+/// Output kept from the end of a command's stream, truncated from the front.
+#[derive(Default)]
+pub(crate) struct OutputBuffer {
+    text: String,
+    /// Trailing bytes of an incomplete UTF-8 sequence from the last read.
+    pending: Vec<u8>,
+    /// The last read ended in `\r`, which may be half of a `\r\n`.
+    pending_cr: bool,
+    limit: usize,
+    truncated: bool,
+}
+
+impl OutputBuffer {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // An incomplete sequence at the end waits for the next read;
+            // anything else is invalid and decoded lossily.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        let chunk: Vec<u8> = self.pending.drain(..valid).collect();
+        let mut chunk = String::from_utf8_lossy(&chunk).into_owned();
+        if std::mem::take(&mut self.pending_cr) {
+            chunk.insert(0, '\r');
+        }
+        if chunk.ends_with('\r') {
+            chunk.pop();
+            self.pending_cr = true;
+        }
+        // PTYs translate `\n` to `\r\n`; agents expect plain newlines.
+        self.text.push_str(&chunk.replace("\r\n", "\n"));
+
+        if self.text.len() > self.limit {
+            let mut cut = self.text.len() - self.limit;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+            self.truncated = true;
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> (String, bool) {
+        let mut text = self.text.clone();
+        if self.pending_cr {
+            text.push('\r');
+        }
+        (text, self.truncated)
+    }
+}
+
+struct ManagedTerminal {
+    output: Arc<Mutex<OutputBuffer>>,
+    exit: watch::Receiver<Option<TerminalExitStatus>>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    killed: Arc<AtomicBool>,
+    // Held so the PTY stays open until the terminal is released.
+    _master: Box<dyn MasterPty + Send>,
+}
+
+/// Terminals created by the agent, keyed by id.
+#[derive(Default)]
+pub(crate) struct Terminals {
+    next_id: AtomicU64,
+    terminals: Mutex<HashMap<TerminalId, ManagedTerminal>>,
+}
+
+impl Terminals {
+    pub(crate) fn create(
+        &self,
+        command: &str,
+        args: &[String],
+        env: &[EnvVariable],
+        cwd: Option<PathBuf>,
+        output_byte_limit: Option<u64>,
+    ) -> anyhow::Result<TerminalId> {
+        let pair = NativePtySystem::default().openpty(PtySize {
+            rows: 24,
+            cols: 200,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+
+        let mut cmd = CommandBuilder::new(command);
+        cmd.args(args);
+        for var in env {
+            cmd.env(&var.name, &var.value);
+        }
+        match cwd {
+            Some(dir) => cmd.cwd(dir),
+            None => {
+                if let Ok(dir) = std::env::current_dir() {
+                    cmd.cwd(dir);
+                }
+            }
+        }
+
+        let mut child = pair.slave.spawn_command(cmd)?;
+        // Drop our copy of the slave so the reader sees EOF once the child exits.
+        drop(pair.slave);
+
+        let limit = output_byte_limit
+            .map(|l| usize::try_from(l).unwrap_or(usize::MAX))
+            .unwrap_or(DEFAULT_OUTPUT_LIMIT);
+        let output = Arc::new(Mutex::new(OutputBuffer::new(limit)));
+        let reader_done = Arc::new(AtomicBool::new(false));
+        let killed = Arc::new(AtomicBool::new(false));
+        let (exit_tx, exit_rx) = watch::channel(None);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let reader_output = output.clone();
+        let reader_flag = reader_done.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if let Ok(mut output) = reader_output.lock() {
+                            output.push(&buf[..n]);
+                        }
+                    }
+                }
+            }
+            reader_flag.store(true, Ordering::SeqCst);
+        });
+
+        let killer = child.clone_killer();
+        let waiter_killed = killed.clone();
+        std::thread::spawn(move || {
+            let status = child.wait();
+            let deadline = Instant::now() + OUTPUT_DRAIN_TIMEOUT;
+            while !reader_done.load(Ordering::SeqCst) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let exit_status = match status {
+                Ok(_) if waiter_killed.load(Ordering::SeqCst) => TerminalExitStatus {
+                    exit_code: None,
+                    signal: Some(KILL_SIGNAL.to_string()),
+                    meta: None,
+                },
+                Ok(status) => TerminalExitStatus {
+                    exit_code: Some(status.exit_code()),
+                    signal: None,
+                    meta: None,
+                },
+                Err(_) => TerminalExitStatus {
+                    exit_code: None,
+                    signal: None,
+                    meta: None,
+                },
+            };
+            let _ = exit_tx.send(Some(exit_status));
+        });
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let terminal_id = TerminalId(format!("term-{}", id).into());
+        self.lock().insert(
+            terminal_id.clone(),
+            ManagedTerminal {
+                output,
+                exit: exit_rx,
+                killer,
+                killed,
+                _master: pair.master,
+            },
+        );
+        Ok(terminal_id)
+    }
+
+    /// Current output, whether it was truncated, and the exit status if finished.
+    pub(crate) fn output(
+        &self,
+        id: &TerminalId,
+    ) -> Option<(String, bool, Option<TerminalExitStatus>)> {
+        let terminals = self.lock();
+        let terminal = terminals.get(id)?;
+        let (text, truncated) = terminal
+            .output
+            .lock()
+            .map(|o| o.snapshot())
+            .unwrap_or_default();
+        let exit_status = terminal.exit.borrow().clone();
+        Some((text, truncated, exit_status))
+    }
+
+    /// Wait for the command to exit. `None` if the terminal doesn't exist.
+    pub(crate) async fn wait_for_exit(&self, id: &TerminalId) -> Option<TerminalExitStatus> {
+        // Clone the receiver so the map isn't locked across the await.
+        let mut exit = self.lock().get(id)?.exit.clone();
+        let status = exit.wait_for(Option::is_some).await.ok()?;
+        status.clone()
+    }
+
+    /// Kill the command but keep the terminal so output can still be read.
+    pub(crate) fn kill(&self, id: &TerminalId) -> Option<std::io::Result<()>> {
+        let mut terminals = self.lock();
+        let terminal = terminals.get_mut(id)?;
+        Some(kill_terminal(terminal))
+    }
+
+    /// Kill the command if still running and forget the terminal.
+    pub(crate) fn release(&self, id: &TerminalId) -> bool {
+        let Some(mut terminal) = self.lock().remove(id) else {
+            return false;
+        };
+        let _ = kill_terminal(&mut terminal);
+        true
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<TerminalId, ManagedTerminal>> {
+        self.terminals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn kill_terminal(terminal: &mut ManagedTerminal) -> std::io::Result<()> {
+    if terminal.exit.borrow().is_some() {
+        return Ok(());
+    }
+    terminal.killed.store(true, Ordering::SeqCst);
+    terminal.killer.kill()
+}
+
+impl Drop for Terminals {
+    fn drop(&mut self) {
+        for terminal in self.lock().values_mut() {
+            let _ = kill_terminal(terminal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_buffer_truncates_at_char_boundaries() {
+        let mut buf = OutputBuffer::new(8);
+        buf.push(b"line\r\n");
+        assert_eq!(buf.snapshot(), ("line\n".to_string(), false));
+
+        // "é" split across two reads is held until complete.
+        buf.push(&[0xc3]);
+        assert_eq!(buf.snapshot().0, "line\n");
+        buf.push(&[0xa9, b'a', b'b', b'c']);
+        let (text, truncated) = buf.snapshot();
+        assert!(truncated);
+        assert!(text.len() <= 8);
+        assert!(text.ends_with("éabc"));
+    }
+
+    #[test]
+    fn output_buffer_joins_crlf_split_across_reads() {
+        let mut buf = OutputBuffer::new(64);
+        buf.push(b"a\r");
+        // A lone `\r` so far is shown as is.
+        assert_eq!(buf.snapshot().0, "a\r");
+        buf.push(b"\nb\r");
+        assert_eq!(buf.snapshot().0, "a\nb\r");
+        buf.push(b"c");
+        assert_eq!(buf.snapshot().0, "a\nb\rc");
+    }
+
+    #[tokio::test]
+    async fn runs_command_and_reports_exit() {
+        let terminals = Terminals::default();
+        let id = terminals
+            .create(
+                "sh",
+                &["-c".to_string(), "echo hello; exit 3".to_string()],
+                &[],
+                None,
+                None,
+            )
+            .unwrap();
+
+        let status = terminals.wait_for_exit(&id).await.unwrap();
+        assert_eq!(status.exit_code, Some(3));
+        let (output, truncated, exit) = terminals.output(&id).unwrap();
+        assert!(output.contains("hello"), "output: {:?}", output);
+        assert!(!truncated);
+        assert!(exit.is_some());
+
+        assert!(terminals.release(&id));
+        assert!(terminals.output(&id).is_none());
+    }
+}
@@ -6,10 +6,19 @@ use ratatui::{
 use std::sync::LazyLock;
 use tui_textarea::TextArea;
 
+use crate::acp_client::attachments::{Attachment, AttachmentKind};
+use crate::acp_client::commands::MAX_COMPLETIONS;
+use crate::acp_client::diff::diff_to_lines;
+use crate::acp_client::keymap::{keymap, Action};
 use crate::acp_client::markdown::markdown_to_lines;
+use crate::acp_client::permissions::{PendingPermission, PermissionPolicy};
+use crate::acp_client::review::{changed_files, ReviewStatus};
 use crate::acp_client::state::{
-    App, ChatEntry, ConnectionState, PaletteCommand, SwitchPaletteItem, UiMode, WorkspaceSyncState,
+    App, ChatEntry, ConnectionState, PaletteCommand, SwitchPaletteItem, ToolCallDetails, UiMode,
+    WorkspaceSyncState,
 };
+use crate::acp_client::usage::{format_cost, format_tokens};
+use crate::acp_client::wrap::{line_width, wrap_line};
 
 /// Detect if terminal is in dark mode (cached at startup)
 static IS_DARK_MODE: LazyLock<bool> = LazyLock::new(|| {
@@ -30,15 +39,22 @@ fn user_message_bg_color() -> ratatui::style::Color {
 }
 
 pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
+    render_chat(f, app, f.area());
+}
+
+/// Draw the chat view into `area`; overlays are centred on it.
+pub(crate) fn render_chat(f: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
     let line_count = app.textarea.lines().len() as u16;
     let input_height = (line_count + 2).clamp(3, 12);
     let status_height = 1u16;
     let debug_height = if app.debug_mode { 8u16 } else { 0u16 };
+    let tab_bar_height = if app.tabs.len() > 1 { 1u16 } else { 0u16 };
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
+                Constraint::Length(tab_bar_height),
                 Constraint::Min(1),
                 Constraint::Length(debug_height),
                 Constraint::Length(input_height),
@@ -46,15 +62,21 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
             ]
             .as_ref(),
         )
-        .split(f.area());
+        .split(area);
 
-    let history_area = chunks[0];
-    let debug_area = chunks[1];
-    let input_area = chunks[2];
-    let status_area = chunks[3];
+    let tab_bar_area = chunks[0];
+    let history_area = chunks[1];
+    let debug_area = chunks[2];
+    let input_area = chunks[3];
+    let status_area = chunks[4];
+
+    if tab_bar_height > 0 {
+        f.render_widget(Paragraph::new(render_tab_bar(app)), tab_bar_area);
+    }
 
     let area_width = history_area.width as usize;
     let mut lines: Vec<Line<'_>> = Vec::new();
+    let mut selected_line: Option<usize> = None;
 
     for (i, entry) in app.history.iter().enumerate() {
         if i > 0 {
@@ -66,6 +88,7 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 text,
                 normalized_markdown,
             } => {
+                let start = lines.len();
                 render_message(
                     &mut lines,
                     role,
@@ -73,6 +96,12 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
                     normalized_markdown.as_deref(),
                     area_width,
                 );
+                if app.ui_mode == UiMode::MessageSelect && app.selected_message == Some(i) {
+                    selected_line = Some(start);
+                    for line in &mut lines[start..] {
+                        line.style = line.style.add_modifier(ratatui::style::Modifier::REVERSED);
+                    }
+                }
             }
             ChatEntry::ToolCall {
                 title,
@@ -80,10 +109,17 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 status,
                 ..
             } => {
-                render_tool_call(&mut lines, title, kind, status);
+                let selected = app.selected_tool_call == Some(i);
+                if selected {
+                    selected_line = Some(lines.len());
+                }
+                render_tool_call(&mut lines, title, kind, status, selected, area_width);
             }
             ChatEntry::Plan(plan) => {
-                render_plan(&mut lines, plan);
+                render_plan(&mut lines, plan, area_width);
+            }
+            ChatEntry::Attachment(attachment) => {
+                render_attachment(&mut lines, attachment, area_width);
             }
         }
     }
@@ -92,6 +128,20 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
     let view_height = history_area.height;
     let max_scroll = total_lines.saturating_sub(view_height);
 
+    if let Some(line) = selected_line {
+        // Keep the selected tool call on screen while navigating.
+        let line = line as u16;
+        let top = max_scroll.saturating_sub(app.scroll_offset_from_bottom.min(max_scroll));
+        let top = if line < top {
+            line
+        } else if line >= top + view_height {
+            line + 1 - view_height
+        } else {
+            top
+        };
+        app.scroll_offset_from_bottom = max_scroll.saturating_sub(top);
+    }
+
     let offset_from_bottom = app.scroll_offset_from_bottom.min(max_scroll);
     app.scroll_offset_from_bottom = offset_from_bottom;
 
@@ -131,6 +181,11 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
 
     f.render_widget(&app.textarea, input_area);
 
+    if app.ui_mode == UiMode::Chat {
+        render_command_popup(f, app, input_area);
+        render_mention_popup(f, app, input_area);
+    }
+
     let provider_style = ratatui::style::Style::default()
         .fg(ratatui::style::Color::Cyan)
         .add_modifier(ratatui::style::Modifier::BOLD);
@@ -166,6 +221,15 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
         ConnectionState::SwitchingProvider(_) => {
             status_spans.push(Span::styled(" (loading...)", connecting_style));
         }
+        ConnectionState::Reconnecting { attempt, max } => {
+            status_spans.push(Span::styled(
+                format!(" (reconnecting, attempt {}/{})", attempt, max),
+                connecting_style,
+            ));
+        }
+        ConnectionState::Disconnected => {
+            status_spans.push(Span::styled(" (disconnected)", error_style));
+        }
     }
 
     match &app.workspace_sync_state {
@@ -182,7 +246,46 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
         status_spans.push(Span::styled(" [DEBUG]", debug_indicator_style));
     }
 
-    status_spans.push(Span::styled(" │ ^O: commands │ ^M: switch", hint_style));
+    if app.usage.has_usage() {
+        let total = app.usage.total();
+        let mut text = format!(" │ {} tok", format_tokens(total.total_tokens()));
+        if total.cost_usd.is_some() {
+            text.push_str(&format!(" {}", format_cost(total.cost_usd)));
+        }
+        status_spans.push(Span::styled(text, hint_style));
+    }
+
+    if app.permission_policy != PermissionPolicy::AlwaysAsk {
+        status_spans.push(Span::styled(
+            format!(" │ {}", app.permission_policy.display_name()),
+            connecting_style,
+        ));
+    }
+
+    let hint = match app.ui_mode {
+        UiMode::ToolSelect => " │ ↑↓: select tool call │ Enter: details │ Esc: back".to_string(),
+        UiMode::ToolDetail => " │ ↑↓/PgUp/PgDn: scroll │ Esc: back".to_string(),
+        UiMode::MessageSelect => " │ ↑↓: select message │ Enter: edit │ Esc: back".to_string(),
+        UiMode::Review => {
+            " │ ↑↓: file │ PgUp/PgDn: scroll │ a: accept │ r: revert │ Esc: back".to_string()
+        }
+        _ if app.editing_from.is_some() => {
+            " │ Enter: resend from here │ Esc: cancel edit".to_string()
+        }
+        _ => [
+            (Action::CommandPalette, "commands"),
+            (Action::SwitchProvider, "switch"),
+            (Action::ToolCalls, "tool calls"),
+        ]
+        .into_iter()
+        .filter_map(|(action, label)| {
+            keymap()
+                .hint(action)
+                .map(|keys| format!(" │ {}: {}", keys, label))
+        })
+        .collect(),
+    };
+    status_spans.push(Span::styled(hint, hint_style));
 
     let status_line = Line::from(status_spans);
     let status_paragraph = Paragraph::new(status_line);
@@ -193,6 +296,7 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
             let search = app.palette_search();
             render_searchable_palette(
                 f,
+                area,
                 " Commands ",
                 &app.palette_input,
                 app.palette_selection,
@@ -235,14 +339,534 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 .collect();
             render_searchable_palette(
                 f,
+                area,
                 " Switch Provider / Model ",
                 &app.palette_input,
                 app.palette_selection,
                 palette_items,
             );
         }
-        UiMode::Chat => {}
+        UiMode::SessionPalette => {
+            let items: Vec<_> = app
+                .get_session_palette_items()
+                .into_iter()
+                .map(|session| {
+                    let when: chrono::DateTime<chrono::Local> = session.modified.into();
+                    PaletteItem::Simple {
+                        label: session.preview.clone(),
+                        description: Some(when.format("%Y-%m-%d %H:%M").to_string()),
+                        is_current: app.history_file.as_ref() == Some(&session.path),
+                    }
+                })
+                .collect();
+            let items = if items.is_empty() {
+                vec![PaletteItem::Header("No saved sessions".to_string())]
+            } else {
+                items
+            };
+            render_searchable_palette(
+                f,
+                area,
+                " Load Previous Session ",
+                &app.palette_input,
+                app.palette_selection,
+                items,
+            );
+        }
+        UiMode::PermissionPrompt => {
+            if let Some(pending) = app.pending_permissions.front() {
+                render_permission_prompt(
+                    f,
+                    area,
+                    pending,
+                    app.permission_selection,
+                    app.pending_permissions.len(),
+                );
+            }
+        }
+        UiMode::ToolDetail => render_tool_detail(f, app, area),
+        UiMode::Review => render_review(f, app, area),
+        UiMode::AttachmentPalette => {
+            let items: Vec<_> = app
+                .get_attachment_palette_items()
+                .into_iter()
+                .map(|attachment| PaletteItem::Simple {
+                    label: attachment.name.clone(),
+                    description: Some(attachment.target.clone()),
+                    is_current: false,
+                })
+                .collect();
+            let items = if items.is_empty() {
+                vec![PaletteItem::Header(
+                    "No attachments in this chat".to_string(),
+                )]
+            } else {
+                items
+            };
+            render_searchable_palette(
+                f,
+                area,
+                " Open Attachment ",
+                &app.palette_input,
+                app.palette_selection,
+                items,
+            );
+        }
+        UiMode::ToolSelect | UiMode::MessageSelect | UiMode::ImagePreview | UiMode::Chat => {}
+    }
+}
+
+/// Slash command completions, drawn just above the input box.
+fn render_command_popup(f: &mut ratatui::Frame, app: &App, input_area: ratatui::layout::Rect) {
+    use ratatui::widgets::Clear;
+
+    let completions = app.command_completions();
+    if completions.is_empty() {
+        return;
+    }
+    let selection = app.command_selection.min(completions.len() - 1);
+    // Scroll the window so the selection stays visible.
+    let first = (selection + 1).saturating_sub(MAX_COMPLETIONS);
+    let visible = &completions[first..completions.len().min(first + MAX_COMPLETIONS)];
+
+    let height = (visible.len() as u16 + 2).min(input_area.y);
+    if height < 3 {
+        return;
+    }
+    let width = 70u16.min(input_area.width);
+    let popup_area = ratatui::layout::Rect::new(input_area.x, input_area.y - height, width, height);
+    f.render_widget(Clear, popup_area);
+
+    let lines: Vec<Line<'_>> = visible
+        .iter()
+        .enumerate()
+        .map(|(i, command)| {
+            let selected = first + i == selection;
+            let name_style = if selected {
+                ratatui::style::Style::default()
+                    .fg(ratatui::style::Color::Cyan)
+                    .add_modifier(ratatui::style::Modifier::BOLD)
+            } else if command.builtin {
+                ratatui::style::Style::default().fg(ratatui::style::Color::Yellow)
+            } else {
+                ratatui::style::Style::default()
+            };
+            let prefix = if selected { "▶ " } else { "  " };
+            let mut spans = vec![Span::styled(
+                format!("{}/{}", prefix, command.name),
+                name_style,
+            )];
+            if let Some(ref hint) = command.input_hint {
+                spans.push(Span::styled(
+                    format!(" <{}>", hint),
+                    ratatui::style::Style::default().fg(ratatui::style::Color::Magenta),
+                ));
+            }
+            spans.push(Span::styled(
+                format!("  {}", command.description),
+                ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+            ));
+            Line::from(spans)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(" Commands ")
+        .title_style(
+            ratatui::style::Style::default()
+                .fg(ratatui::style::Color::Cyan)
+                .add_modifier(ratatui::style::Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::Cyan));
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Workspace files for the `@` mention being typed, drawn above the input box.
+fn render_mention_popup(f: &mut ratatui::Frame, app: &App, input_area: ratatui::layout::Rect) {
+    use ratatui::widgets::Clear;
+
+    if !app.mention_popup_visible() {
+        return;
+    }
+    let completions = app.mention_completions();
+    let lines: Vec<Line<'_>> = if completions.is_empty() {
+        vec![Line::from(Span::styled(
+            "  Indexing workspace…",
+            ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+        ))]
+    } else {
+        let selection = app.mention_selection.min(completions.len() - 1);
+        completions
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                if i == selection {
+                    Line::from(Span::styled(
+                        format!("▶ @{}", path),
+                        ratatui::style::Style::default()
+                            .fg(ratatui::style::Color::Cyan)
+                            .add_modifier(ratatui::style::Modifier::BOLD),
+                    ))
+                } else {
+                    Line::from(format!("  @{}", path))
+                }
+            })
+            .collect()
+    };
+
+    let height = (lines.len() as u16 + 2).min(input_area.y);
+    if height < 3 {
+        return;
+    }
+    let width = 70u16.min(input_area.width);
+    let popup_area = ratatui::layout::Rect::new(input_area.x, input_area.y - height, width, height);
+    f.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(" Files ")
+        .title_style(
+            ratatui::style::Style::default()
+                .fg(ratatui::style::Color::Cyan)
+                .add_modifier(ratatui::style::Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::Cyan));
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// One label per tab, e.g. ` 1 Claude │ 2 Codex… `, with the active tab highlighted.
+fn render_tab_bar(app: &App) -> Line<'static> {
+    let separator_style = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+    let active_style = ratatui::style::Style::default()
+        .fg(ratatui::style::Color::Cyan)
+        .add_modifier(ratatui::style::Modifier::BOLD | ratatui::style::Modifier::REVERSED);
+    let inactive_style = ratatui::style::Style::default().fg(ratatui::style::Color::Gray);
+
+    let mut spans = Vec::new();
+    for (i, (provider, connecting)) in app.tab_labels().into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled("│", separator_style));
+        }
+        let style = if i == app.active_tab {
+            active_style
+        } else {
+            inactive_style
+        };
+        let marker = if connecting { "…" } else { "" };
+        spans.push(Span::styled(
+            format!(" {} {}{} ", i + 1, provider.display_name(), marker),
+            style,
+        ));
+    }
+    Line::from(spans)
+}
+
+/// Render the selected tool call's locations, content and output in an overlay
+fn render_tool_detail(f: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
+    use agent_client_protocol::{ContentBlock, ToolCallContent};
+    use ratatui::widgets::Clear;
+
+    let Some(ChatEntry::ToolCall {
+        title,
+        kind,
+        status,
+        details,
+        ..
+    }) = app.selected_tool_call.and_then(|i| app.history.get(i))
+    else {
+        return;
+    };
+    let ToolCallDetails {
+        content,
+        locations,
+        raw_input,
+        raw_output,
+    } = details;
+
+    let dim = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+    let section = ratatui::style::Style::default()
+        .fg(ratatui::style::Color::Magenta)
+        .add_modifier(ratatui::style::Modifier::BOLD);
+
+    let mut lines: Vec<Line<'static>> =
+        vec![Line::styled(format!("{:?} · {:?}", kind, status), dim)];
+
+    if !locations.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Locations", section));
+        for location in locations {
+            let line = location.line.map(|l| format!(":{}", l)).unwrap_or_default();
+            lines.push(Line::from(format!("  {}{}", location.path.display(), line)));
+        }
+    }
+
+    if let Some(input) = raw_input {
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Input", section));
+        let pretty = serde_json::to_string_pretty(input).unwrap_or_default();
+        lines.extend(
+            pretty
+                .lines()
+                .map(|l| Line::styled(format!("  {}", l), dim)),
+        );
+    }
+
+    for item in content {
+        lines.push(Line::from(""));
+        match item {
+            ToolCallContent::Diff { diff } => {
+                lines.push(Line::styled(
+                    format!("Diff {}", diff.path.display()),
+                    section,
+                ));
+                lines.extend(diff_to_lines(
+                    &diff.path,
+                    diff.old_text.as_deref(),
+                    &diff.new_text,
+                ));
+            }
+            ToolCallContent::Content { content } => {
+                lines.push(Line::styled("Output", section));
+                match content {
+                    ContentBlock::Text(text) => {
+                        lines.extend(text.text.lines().map(|l| Line::from(l.to_owned())));
+                    }
+                    ContentBlock::ResourceLink(link) => {
+                        lines.push(Line::from(link.uri.clone()));
+                    }
+                    _ => lines.push(Line::styled("(non-text content)", dim)),
+                }
+            }
+            ToolCallContent::Terminal { terminal_id } => {
+                lines.push(Line::styled(format!("Terminal {}", terminal_id), section));
+            }
+        }
     }
+
+    if let Some(output) = raw_output {
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Raw output", section));
+        match output.as_str() {
+            Some(text) => lines.extend(text.lines().map(|l| Line::from(l.to_owned()))),
+            None => {
+                let pretty = serde_json::to_string_pretty(output).unwrap_or_default();
+                lines.extend(pretty.lines().map(|l| Line::from(l.to_owned())));
+            }
+        }
+    }
+
+    let detail_area = ratatui::layout::Rect::new(
+        area.x + 2,
+        area.y + 1,
+        area.width.saturating_sub(4),
+        area.height.saturating_sub(3),
+    );
+    f.render_widget(Clear, detail_area);
+
+    let inner_height = detail_area.height.saturating_sub(2);
+    let max_scroll = (lines.len() as u16).saturating_sub(inner_height);
+    app.tool_detail_scroll = app.tool_detail_scroll.min(max_scroll);
+
+    let block = Block::default()
+        .title(format!(" {} ", title))
+        .title_style(
+            ratatui::style::Style::default()
+                .fg(ratatui::style::Color::Cyan)
+                .add_modifier(ratatui::style::Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::Cyan));
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .scroll((app.tool_detail_scroll, 0));
+    f.render_widget(paragraph, detail_area);
+}
+
+/// Full-screen changes review: changed files on the left, the selected
+/// file's diff on the right.
+fn render_review(f: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
+    use ratatui::widgets::{Clear, List, ListItem};
+
+    let files = changed_files(&app.history);
+    let review_area =
+        ratatui::layout::Rect::new(area.x, area.y, area.width, area.height.saturating_sub(1));
+    f.render_widget(Clear, review_area);
+    let Some(selected) = files.get(app.review.selected.min(files.len().saturating_sub(1))) else {
+        return;
+    };
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(review_area);
+
+    let border = ratatui::style::Style::default().fg(ratatui::style::Color::Cyan);
+    let items: Vec<ListItem> = files
+        .iter()
+        .map(|file| {
+            let status = app.review.status(file);
+            let color = match status {
+                ReviewStatus::Pending | ReviewStatus::Reverting => ratatui::style::Color::Yellow,
+                ReviewStatus::Accepted => ratatui::style::Color::Green,
+                ReviewStatus::Reverted => ratatui::style::Color::DarkGray,
+                ReviewStatus::Failed(_) => ratatui::style::Color::Red,
+            };
+            let name = file
+                .path
+                .strip_prefix("/workspace")
+                .unwrap_or(&file.path)
+                .display()
+                .to_string();
+            let mut style = ratatui::style::Style::default();
+            if file.path == selected.path {
+                style = style.add_modifier(ratatui::style::Modifier::REVERSED);
+            }
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", status.marker()),
+                    ratatui::style::Style::default().fg(color),
+                ),
+                Span::styled(name, style),
+            ]))
+        })
+        .collect();
+    let reviewed = files
+        .iter()
+        .filter(|file| app.review.status(file) != ReviewStatus::Pending)
+        .count();
+    f.render_widget(
+        List::new(items).block(
+            Block::default()
+                .title(format!(" Changes {}/{} ", reviewed, files.len()))
+                .borders(Borders::ALL)
+                .border_style(border),
+        ),
+        columns[0],
+    );
+
+    let mut lines = Vec::new();
+    if let ReviewStatus::Failed(error) = app.review.status(selected) {
+        lines.push(Line::styled(
+            format!("Revert failed: {}", error),
+            ratatui::style::Style::default().fg(ratatui::style::Color::Red),
+        ));
+    }
+    lines.extend(diff_to_lines(
+        &selected.path,
+        selected.old_text.as_deref(),
+        &selected.new_text,
+    ));
+    let inner_height = columns[1].height.saturating_sub(2);
+    let max_scroll = (lines.len() as u16).saturating_sub(inner_height);
+    app.review.scroll = app.review.scroll.min(max_scroll);
+    let title = if selected.old_text.is_none() {
+        format!(" {} (new file) ", selected.path.display())
+    } else {
+        format!(" {} ", selected.path.display())
+    };
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(border),
+            )
+            .scroll((app.review.scroll, 0)),
+        columns[1],
+    );
+}
+
+/// Render the modal asking the user to answer an agent permission request
+fn render_permission_prompt(
+    f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
+    pending: &PendingPermission,
+    selection: usize,
+    queued: usize,
+) {
+    use agent_client_protocol::PermissionOptionKind;
+    use ratatui::widgets::{Clear, Wrap};
+
+    let fields = &pending.request.tool_call.fields;
+    let dim = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+
+    let mut lines = vec![Line::styled(
+        fields
+            .title
+            .clone()
+            .unwrap_or_else(|| "The agent wants to run a tool".to_string()),
+        ratatui::style::Style::default().add_modifier(ratatui::style::Modifier::BOLD),
+    )];
+    if let Some(kind) = fields.kind {
+        lines.push(Line::styled(format!("Kind: {:?}", kind), dim));
+    }
+    for location in fields.locations.iter().flatten() {
+        let line = location.line.map(|l| format!(":{}", l)).unwrap_or_default();
+        lines.push(Line::styled(
+            format!("  {}{}", location.path.display(), line),
+            dim,
+        ));
+    }
+    if let Some(ref input) = fields.raw_input {
+        let input = input.to_string();
+        let input: String = input.chars().take(200).collect();
+        lines.push(Line::styled(input, dim));
+    }
+    lines.push(Line::from(""));
+
+    for (i, option) in pending.request.options.iter().enumerate() {
+        let is_selected = i == selection;
+        let color = match option.kind {
+            PermissionOptionKind::AllowOnce | PermissionOptionKind::AllowAlways => {
+                ratatui::style::Color::Green
+            }
+            PermissionOptionKind::RejectOnce | PermissionOptionKind::RejectAlways => {
+                ratatui::style::Color::Red
+            }
+        };
+        let mut style = ratatui::style::Style::default().fg(color);
+        if is_selected {
+            style = style
+                .add_modifier(ratatui::style::Modifier::BOLD | ratatui::style::Modifier::REVERSED);
+        }
+        let prefix = if is_selected { "▶ " } else { "  " };
+        lines.push(Line::styled(
+            format!("{}{}. {}", prefix, i + 1, option.name),
+            style,
+        ));
+    }
+    lines.push(Line::from(""));
+    let mut hint = "↑/↓ select · Enter confirm · 1-9 pick · Esc reject".to_string();
+    if queued > 1 {
+        hint.push_str(&format!(" · {} more waiting", queued - 1));
+    }
+    lines.push(Line::styled(hint, dim));
+
+    let width = 70u16.min(area.width.saturating_sub(4));
+    let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let modal_area = ratatui::layout::Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    f.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(" Permission Required ")
+        .title_style(
+            ratatui::style::Style::default()
+                .fg(ratatui::style::Color::Yellow)
+                .add_modifier(ratatui::style::Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::Yellow));
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, modal_area);
 }
 
 /// Item types for palette rendering
@@ -259,6 +883,7 @@ enum PaletteItem {
 /// Render a searchable palette overlay with scrolling support
 fn render_searchable_palette(
     f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
     title: &str,
     search_input: &TextArea<'_>,
     selection: usize,
@@ -266,16 +891,14 @@ fn render_searchable_palette(
 ) {
     use ratatui::widgets::Clear;
 
-    let area = f.area();
-
     let palette_width = 70u16.min(area.width.saturating_sub(4));
     let max_items_height = 15u16;
     let palette_height = (items.len() as u16 + 7)
         .min(max_items_height + 7)
         .min(area.height.saturating_sub(4));
 
-    let x = (area.width.saturating_sub(palette_width)) / 2;
-    let y = (area.height.saturating_sub(palette_height)) / 2;
+    let x = area.x + (area.width.saturating_sub(palette_width)) / 2;
+    let y = area.y + (area.height.saturating_sub(palette_height)) / 2;
 
     let palette_area = ratatui::layout::Rect::new(x, y, palette_width, palette_height);
     f.render_widget(Clear, palette_area);
@@ -444,8 +1067,12 @@ fn render_message<'a>(
             let bg_style = ratatui::style::Style::default().bg(user_message_bg_color());
             lines.push(Line::styled(" ".repeat(area_width), bg_style));
             for line in text.lines() {
-                let padded = format!("{:width$}", line, width = area_width);
-                lines.push(Line::styled(padded, bg_style));
+                for row in wrap_line(&Line::raw(line), area_width, 0) {
+                    let padding = area_width.saturating_sub(line_width(&row));
+                    let mut spans = row.spans;
+                    spans.push(Span::raw(" ".repeat(padding)));
+                    lines.push(Line::from(spans).style(bg_style));
+                }
             }
             lines.push(Line::styled(" ".repeat(area_width), bg_style));
         }
@@ -461,7 +1088,7 @@ fn render_message<'a>(
             } else {
                 Some(("Thought: ".to_string(), prefix_style))
             };
-            render_markdown_message(lines, text, normalized_markdown, prefix);
+            render_markdown_message(lines, text, normalized_markdown, prefix, area_width);
             lines.push(Line::raw(""));
         }
         "Error" => {
@@ -474,13 +1101,21 @@ fn render_message<'a>(
             let mut first = true;
             for text_line in text.lines() {
                 if first {
-                    lines.push(Line::from(vec![
-                        Span::styled(prefix.to_string(), prefix_style),
-                        Span::styled(text_line.to_owned(), text_style),
-                    ]));
+                    push_wrapped(
+                        lines,
+                        Line::from(vec![
+                            Span::styled(prefix.to_string(), prefix_style),
+                            Span::styled(text_line.to_owned(), text_style),
+                        ]),
+                        area_width,
+                    );
                     first = false;
                 } else {
-                    lines.push(Line::styled(text_line.to_owned(), text_style));
+                    push_wrapped(
+                        lines,
+                        Line::styled(text_line.to_owned(), text_style),
+                        area_width,
+                    );
                 }
             }
             if first {
@@ -501,13 +1136,21 @@ fn render_message<'a>(
             let mut first = true;
             for text_line in text.lines() {
                 if first {
-                    lines.push(Line::from(vec![
-                        Span::styled(prefix.to_string(), prefix_style),
-                        Span::styled(text_line.to_owned(), text_style),
-                    ]));
+                    push_wrapped(
+                        lines,
+                        Line::from(vec![
+                            Span::styled(prefix.to_string(), prefix_style),
+                            Span::styled(text_line.to_owned(), text_style),
+                        ]),
+                        area_width,
+                    );
                     first = false;
                 } else {
-                    lines.push(Line::styled(text_line.to_owned(), text_style));
+                    push_wrapped(
+                        lines,
+                        Line::styled(text_line.to_owned(), text_style),
+                        area_width,
+                    );
                 }
             }
             if first {
@@ -526,13 +1169,17 @@ fn render_message<'a>(
             let mut first = true;
             for text_line in text.lines() {
                 if first {
-                    lines.push(Line::from(vec![
-                        Span::styled(prefix.clone(), prefix_style),
-                        Span::raw(text_line.to_owned()),
-                    ]));
+                    push_wrapped(
+                        lines,
+                        Line::from(vec![
+                            Span::styled(prefix.clone(), prefix_style),
+                            Span::raw(text_line.to_owned()),
+                        ]),
+                        area_width,
+                    );
                     first = false;
                 } else {
-                    lines.push(Line::from(text_line.to_owned()));
+                    push_wrapped(lines, Line::from(text_line.to_owned()), area_width);
                 }
             }
             if first {
@@ -543,11 +1190,18 @@ fn render_message<'a>(
     }
 }
 
+/// Push `line` wrapped to `width`.
+fn push_wrapped(lines: &mut Vec<Line<'_>>, line: Line<'_>, width: usize) {
+    lines.extend(wrap_line(&line, width, 0));
+}
+
 fn render_tool_call<'a>(
     lines: &mut Vec<Line<'a>>,
     title: &str,
     kind: &agent_client_protocol::ToolKind,
     status: &agent_client_protocol::ToolCallStatus,
+    selected: bool,
+    width: usize,
 ) {
     let icon = match kind {
         agent_client_protocol::ToolKind::Read => "📖",
@@ -569,18 +1223,55 @@ fn render_tool_call<'a>(
         agent_client_protocol::ToolCallStatus::Failed => ("✗", ratatui::style::Color::Red),
     };
 
-    let tool_style = ratatui::style::Style::default().fg(ratatui::style::Color::Cyan);
+    let mut tool_style = ratatui::style::Style::default().fg(ratatui::style::Color::Cyan);
+    if selected {
+        tool_style = tool_style.add_modifier(ratatui::style::Modifier::REVERSED);
+    }
     let status_style = ratatui::style::Style::default().fg(status_indicator.1);
 
-    lines.push(Line::from(vec![
+    let line = Line::from(vec![
         Span::raw(format!("{} ", icon)),
         Span::styled(title.to_owned(), tool_style),
         Span::raw(" "),
         Span::styled(status_indicator.0.to_owned(), status_style),
-    ]));
+    ]);
+    lines.extend(wrap_line(&line, width, 3));
+}
+
+fn render_attachment(lines: &mut Vec<Line<'_>>, attachment: &Attachment, width: usize) {
+    let icon = match attachment.kind {
+        AttachmentKind::Image => "🖼️",
+        AttachmentKind::Resource => "🔗",
+    };
+    let name_style = ratatui::style::Style::default()
+        .fg(ratatui::style::Color::Blue)
+        .add_modifier(ratatui::style::Modifier::UNDERLINED);
+    let detail_style = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+
+    let mut spans = vec![
+        Span::raw(format!("{} ", icon)),
+        Span::styled(attachment.name.clone(), name_style),
+    ];
+    if let Some(ref mime_type) = attachment.mime_type {
+        spans.push(Span::styled(format!(" ({})", mime_type), detail_style));
+    }
+    if attachment.target != attachment.name {
+        spans.push(Span::styled(
+            format!(" {}", attachment.target),
+            detail_style,
+        ));
+    }
+    lines.extend(wrap_line(&Line::from(spans), width, 3));
+    if let Some(ref description) = attachment.description {
+        lines.extend(wrap_line(
+            &Line::styled(format!("   {}", description), detail_style),
+            width,
+            3,
+        ));
+    }
 }
 
-fn render_plan<'a>(lines: &mut Vec<Line<'a>>, plan: &agent_client_protocol::Plan) {
+fn render_plan<'a>(lines: &mut Vec<Line<'a>>, plan: &agent_client_protocol::Plan, width: usize) {
     let header_style = ratatui::style::Style::default()
         .fg(ratatui::style::Color::Magenta)
         .add_modifier(ratatui::style::Modifier::BOLD);
@@ -602,12 +1293,13 @@ fn render_plan<'a>(lines: &mut Vec<Line<'a>>, plan: &agent_client_protocol::Plan
         let status_style = ratatui::style::Style::default().fg(status_color);
         let content_style = ratatui::style::Style::default();
 
-        lines.push(Line::from(vec![
+        let line = Line::from(vec![
             Span::raw("  "),
             Span::styled(status_icon.to_owned(), status_style),
             Span::raw(" "),
             Span::styled(entry.content.clone(), content_style),
-        ]));
+        ]);
+        lines.extend(wrap_line(&line, width, 4));
     }
 }
 
@@ -616,15 +1308,18 @@ fn render_markdown_message(
     text: &str,
     normalized_markdown: Option<&str>,
     prefix: Option<(String, ratatui::style::Style)>,
+    width: usize,
 ) {
     let source = normalized_markdown.unwrap_or(text);
-    let mut result_lines = markdown_to_lines(source);
+    let mut result_lines = markdown_to_lines(source, width);
 
     if let Some((label, style)) = prefix {
         if let Some(first_line) = result_lines.first_mut() {
             let mut spans = vec![Span::styled(label, style)];
             spans.append(&mut first_line.spans);
-            *first_line = Line::from(spans);
+            // The label can push the first line past the edge; rewrap it.
+            let rows = wrap_line(&Line::from(spans), width, 0);
+            result_lines.splice(0..1, rows);
         } else {
             result_lines.push(Line::from(vec![Span::styled(label, style)]));
         }
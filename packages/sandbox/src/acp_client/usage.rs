@@ -0,0 +1,197 @@
+//! Token and cost accounting from provider-supplied `_meta`.
+//!
+//! ACP has no standard usage field, so agents that report usage put it in the
+//! `_meta` of session notifications or prompt responses. Key names differ
+//! between providers; `parse_usage` accepts the common spellings, either at
+//! the top level or under a `usage` object.
+
+use serde_json::Value;
+
+const INPUT_KEYS: &[&str] = &[
+    "inputTokens",
+    "input_tokens",
+    "promptTokens",
+    "prompt_tokens",
+];
+const OUTPUT_KEYS: &[&str] = &[
+    "outputTokens",
+    "output_tokens",
+    "completionTokens",
+    "completion_tokens",
+];
+const CACHED_KEYS: &[&str] = &[
+    "cachedReadTokens",
+    "cacheReadInputTokens",
+    "cache_read_input_tokens",
+    "cached_tokens",
+];
+const COST_KEYS: &[&str] = &[
+    "costUsd",
+    "cost_usd",
+    "totalCostUsd",
+    "total_cost_usd",
+    "cost",
+];
+
+/// Usage for one report, or the sum of a turn's reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct TurnUsage {
+    pub(crate) input_tokens: u64,
+    pub(crate) output_tokens: u64,
+    pub(crate) cached_tokens: u64,
+    /// Only set when the provider reports a cost.
+    pub(crate) cost_usd: Option<f64>,
+}
+
+impl TurnUsage {
+    pub(crate) fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    fn add(&mut self, other: &TurnUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.cost_usd = match (self.cost_usd, other.cost_usd) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        };
+    }
+}
+
+/// Extract usage from a `_meta` value, if it carries any.
+pub(crate) fn parse_usage(meta: &Value) -> Option<TurnUsage> {
+    let usage = meta.get("usage").filter(|u| u.is_object()).unwrap_or(meta);
+    let tokens = |keys: &[&str]| keys.iter().find_map(|k| usage.get(*k)?.as_u64());
+    let cost = COST_KEYS
+        .iter()
+        .find_map(|k| usage.get(*k).or_else(|| meta.get(*k))?.as_f64());
+
+    let input = tokens(INPUT_KEYS);
+    let output = tokens(OUTPUT_KEYS);
+    if input.is_none() && output.is_none() && cost.is_none() {
+        return None;
+    }
+    Some(TurnUsage {
+        input_tokens: input.unwrap_or(0),
+        output_tokens: output.unwrap_or(0),
+        cached_tokens: tokens(CACHED_KEYS).unwrap_or(0),
+        cost_usd: cost,
+    })
+}
+
+/// Usage per prompt turn for one session.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UsageStats {
+    turns: Vec<TurnUsage>,
+}
+
+impl UsageStats {
+    /// Start accounting a new prompt turn.
+    pub(crate) fn start_turn(&mut self) {
+        self.turns.push(TurnUsage::default());
+    }
+
+    /// Add a report to the current turn. Reports before the first prompt
+    /// (e.g. while loading a session) open a turn of their own.
+    pub(crate) fn record(&mut self, usage: TurnUsage) {
+        if self.turns.is_empty() {
+            self.start_turn();
+        }
+        if let Some(turn) = self.turns.last_mut() {
+            turn.add(&usage);
+        }
+    }
+
+    pub(crate) fn total(&self) -> TurnUsage {
+        let mut total = TurnUsage::default();
+        for turn in &self.turns {
+            total.add(turn);
+        }
+        total
+    }
+
+    /// Whether any report has been recorded.
+    pub(crate) fn has_usage(&self) -> bool {
+        self.turns.iter().any(|t| *t != TurnUsage::default())
+    }
+
+    /// Per-turn table for the chat history.
+    pub(crate) fn breakdown(&self) -> String {
+        if !self.has_usage() {
+            return "No usage reported by this provider yet.".to_string();
+        }
+        let mut out = String::from("Usage by turn:\n");
+        for (i, turn) in self.turns.iter().enumerate() {
+            out.push_str(&format!(
+                "  #{:<3} in {:>8}  out {:>8}  cached {:>8}  {}\n",
+                i + 1,
+                turn.input_tokens,
+                turn.output_tokens,
+                turn.cached_tokens,
+                format_cost(turn.cost_usd),
+            ));
+        }
+        let total = self.total();
+        out.push_str(&format!(
+            "  total in {:>8}  out {:>8}  cached {:>8}  {}",
+            total.input_tokens,
+            total.output_tokens,
+            total.cached_tokens,
+            format_cost(total.cost_usd),
+        ));
+        out
+    }
+}
+
+/// `12.3k`-style token count for the status bar.
+pub(crate) fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+pub(crate) fn format_cost(cost: Option<f64>) -> String {
+    match cost {
+        Some(cost) => format!("${:.4}", cost),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_common_spellings_and_sums_turns() {
+        assert_eq!(parse_usage(&json!({"traceId": "x"})), None);
+
+        let nested = parse_usage(&json!({
+            "usage": {"input_tokens": 100, "output_tokens": 20, "cache_read_input_tokens": 50},
+            "total_cost_usd": 0.01
+        }))
+        .unwrap();
+        assert_eq!(nested.total_tokens(), 120);
+        assert_eq!(nested.cached_tokens, 50);
+        assert_eq!(nested.cost_usd, Some(0.01));
+
+        let flat = parse_usage(&json!({"inputTokens": 7, "outputTokens": 3})).unwrap();
+        assert_eq!(flat.cost_usd, None);
+
+        let mut stats = UsageStats::default();
+        assert!(!stats.has_usage());
+        stats.start_turn();
+        stats.record(nested);
+        stats.record(flat);
+        stats.start_turn();
+        stats.record(flat);
+        assert_eq!(stats.turns.len(), 2);
+        assert_eq!(stats.turns[0].total_tokens(), 130);
+        assert_eq!(stats.total().total_tokens(), 140);
+        assert_eq!(stats.total().cost_usd, Some(0.01));
+        assert_eq!(format_tokens(12_345), "12.3k");
+    }
+}
@@ -0,0 +1,143 @@
+//! Width-aware line wrapping for the chat history.
+//!
+//! The history is drawn as a `Paragraph` scrolled by line count, which only
+//! lines up with what's on screen if every `Line` fits in one row. Lines are
+//! therefore wrapped here, before rendering, rather than by ratatui.
+
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthChar;
+
+/// Wrap `line` to `width` columns, breaking after whitespace where possible
+/// and mid-word otherwise. Continuation rows are indented by `indent` columns.
+pub(crate) fn wrap_line(line: &Line<'_>, width: usize, indent: usize) -> Vec<Line<'static>> {
+    let chars: Vec<(char, Style, usize)> = line
+        .spans
+        .iter()
+        .flat_map(|span| {
+            span.content
+                .chars()
+                .map(move |c| (c, span.style, c.width().unwrap_or(0)))
+        })
+        .collect();
+
+    // Leave at least one column for text on continuation rows.
+    let indent = indent.min(width.saturating_sub(1));
+    let mut rows: Vec<Line<'static>> = Vec::new();
+    let mut start = 0;
+    let mut col = 0;
+    // Index just past the last whitespace in the current row.
+    let mut break_after: Option<usize> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (c, _, w) = chars[i];
+        if width > 0 && col + w > width && i > start {
+            let end = break_after.filter(|b| *b > start).unwrap_or(i);
+            let mut trimmed = end;
+            while trimmed > start && chars[trimmed - 1].0 == ' ' {
+                trimmed -= 1;
+            }
+            rows.push(build_row(
+                line,
+                &chars[start..trimmed],
+                !rows.is_empty(),
+                indent,
+            ));
+            start = end;
+            while start < chars.len() && chars[start].0 == ' ' {
+                start += 1;
+            }
+            break_after = None;
+            i = i.max(start);
+            col = indent + chars[start..i].iter().map(|(_, _, w)| w).sum::<usize>();
+            continue;
+        }
+        if c.is_whitespace() {
+            break_after = Some(i + 1);
+        }
+        col += w;
+        i += 1;
+    }
+    if start < chars.len() || rows.is_empty() {
+        rows.push(build_row(line, &chars[start..], !rows.is_empty(), indent));
+    }
+    rows
+}
+
+fn build_row(
+    line: &Line<'_>,
+    chars: &[(char, Style, usize)],
+    continuation: bool,
+    indent: usize,
+) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    if continuation && indent > 0 {
+        spans.push(Span::raw(" ".repeat(indent)));
+    }
+    let mut text = String::new();
+    let mut style: Option<Style> = None;
+    for (c, s, _) in chars {
+        if style != Some(*s) {
+            if let Some(style) = style {
+                spans.push(Span::styled(std::mem::take(&mut text), style));
+            }
+            style = Some(*s);
+        }
+        text.push(*c);
+    }
+    if let Some(style) = style {
+        spans.push(Span::styled(text, style));
+    }
+    let mut row = Line::from(spans).style(line.style);
+    row.alignment = line.alignment;
+    row
+}
+
+/// Display width of a line.
+pub(crate) fn line_width(line: &Line<'_>) -> usize {
+    line.spans
+        .iter()
+        .flat_map(|span| span.content.chars())
+        .map(|c| c.width().unwrap_or(0))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(rows: &[Line<'_>]) -> Vec<String> {
+        rows.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn wraps_on_words_and_display_width() {
+        let line = Line::from(vec![
+            Span::raw("the quick "),
+            Span::styled("brown", Style::default().fg(ratatui::style::Color::Red)),
+            Span::raw(" fox"),
+        ]);
+        let rows = wrap_line(&line, 10, 2);
+        assert_eq!(texts(&rows), ["the quick", "  brown", "  fox"]);
+        assert!(rows.iter().all(|r| line_width(r) <= 10));
+        // Styles survive the split.
+        assert!(rows[1]
+            .spans
+            .iter()
+            .any(|s| s.content == "brown" && s.style.fg == Some(ratatui::style::Color::Red)));
+
+        // Long words are broken; wide characters count as two columns.
+        assert_eq!(
+            texts(&wrap_line(&Line::from("abcdefgh"), 3, 0)),
+            ["abc", "def", "gh"]
+        );
+        assert_eq!(
+            texts(&wrap_line(&Line::from("日本語"), 4, 0)),
+            ["日本", "語"]
+        );
+
+        assert_eq!(texts(&wrap_line(&Line::from(""), 10, 0)), [""]);
+        assert_eq!(texts(&wrap_line(&Line::from("short"), 10, 0)), ["short"]);
+    }
+}
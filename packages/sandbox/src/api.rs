@@ -1,20 +1,35 @@
+use crate::auth::{self, AuthConfig, MuxScope, Principal, SandboxOwners};
+use crate::cgroups::validate_limits;
 use crate::errors::{ErrorBody, SandboxError, SandboxResult};
+use crate::events::{self, LifecycleEvent, LifecycleEventKind};
+use crate::files;
+use crate::git;
+use crate::metrics;
 use crate::models::{
     AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, ExecRequest, ExecResponse,
-    HealthResponse, HostEvent, NotificationLevel, NotificationLogEntry, NotificationRequest,
-    OpenUrlRequest, PruneRequest, PruneResponse, PrunedItem, SandboxSummary, ServiceReadiness,
+    ForwardPortRequest, ForwardedPort, FsChangeEvent, FsChangeKind, FsEncoding, FsEntry,
+    FsEntryKind, FsReadResponse, FsWriteRequest, FsWriteResponse, GitChangeKind, GitCommit,
+    GitFileChange, GitStatus, HealthResponse, HostEvent, ListeningPort, NotificationLevel,
+    NotificationLogEntry, NotificationRequest, OpenUrlRequest, PruneRequest, PruneResponse,
+    PrunedItem, SandboxPorts, SandboxSummary, ServiceReadiness, VncClipboard, VncClipboardRequest,
+    VncClipboardSource,
 };
 use crate::notifications::NotificationStore;
+use crate::ports::{is_service_port, parse_listening_ports, proc_net_tcp_request};
 use crate::service::{AppState, GhResponseRegistry, HostEventSender, SandboxService};
-use crate::vnc_proxy::proxy_vnc_websocket;
+use crate::shutdown::ShutdownState;
+use crate::vnc_proxy::{
+    novnc_static_response, proxy_vnc_websocket, VncQuality, VncTarget, NOVNC_DIR,
+};
+use crate::vnc_recording::{recording_dir, VncRecorder};
 use axum::body::Body;
 use axum::extract::ws::WebSocketUpgrade;
-use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::extract::{DefaultBodyLimit, Extension, Path, Query, State};
 use axum::http::header::HOST;
 use axum::http::HeaderMap;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum::routing::{any, get, post};
+use axum::routing::{any, delete, get, post, put};
 use axum::{Json, Router};
 use serde::Deserialize;
 use std::net::SocketAddr;
@@ -23,16 +38,61 @@ use utoipa::OpenApi as UtoipaOpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, utoipa::IntoParams)]
 struct ProxyParams {
+    /// TCP port inside the sandbox
     port: u16,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, utoipa::IntoParams)]
+struct EventsParams {
+    /// Only send events about this sandbox
+    #[serde(default)]
+    sandbox_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct FsPathParams {
+    /// Path inside the sandbox, absolute under /workspace or relative to it
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct FsReadParams {
+    /// Path inside the sandbox, absolute under /workspace or relative to it
+    path: String,
+    #[serde(default)]
+    offset: u64,
+    /// Bytes to read (capped at 8 MiB)
+    length: Option<u64>,
+    /// Force base64 even for text
+    encoding: Option<FsEncoding>,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct GitLogParams {
+    /// Directory inside the repository, absolute under /workspace or relative to it
+    #[serde(default)]
+    path: String,
+    /// Commits to return (default 20, capped at 500)
+    #[serde(default = "default_git_log_limit")]
+    limit: usize,
+}
+
+fn default_git_log_limit() -> usize {
+    20
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
 struct AttachParams {
+    /// Initial terminal width
     cols: Option<u16>,
+    /// Initial terminal height
     rows: Option<u16>,
+    /// Shell command to run instead of the default shell
     command: Option<String>,
+    /// Allocate a PTY (default true)
     #[serde(default = "default_tty")]
     tty: bool,
 }
@@ -56,6 +116,31 @@ fn default_tty() -> bool {
         send_notification,
         prune_orphaned,
         await_ready,
+        list_ports,
+        forward_port,
+        unforward_port,
+        get_clipboard,
+        put_clipboard,
+        fs_list,
+        fs_read,
+        fs_write,
+        fs_watch,
+        git_status,
+        git_log,
+        lifecycle_events,
+        prometheus_metrics,
+        attach_sandbox,
+        proxy_sandbox,
+        mux_attach,
+        open_url,
+        pty_list_sessions,
+        pty_create_session,
+        pty_get_session,
+        pty_delete_session,
+        pty_resize_session,
+        pty_capture_session,
+        pty_attach_session,
+        pty_signal,
     ),
     components(schemas(
         CreateSandboxRequest,
@@ -64,6 +149,7 @@ fn default_tty() -> bool {
         SandboxSummary,
         crate::models::SandboxNetwork,
         crate::models::SandboxStatus,
+        crate::models::ResourceLimits,
         HealthResponse,
         ErrorBody,
         NotificationRequest,
@@ -75,18 +161,46 @@ fn default_tty() -> bool {
         PrunedItem,
         AwaitReadyRequest,
         AwaitReadyResponse,
-        ServiceReadiness
+        ServiceReadiness,
+        ForwardPortRequest,
+        ForwardedPort,
+        ListeningPort,
+        SandboxPorts,
+        LifecycleEvent,
+        LifecycleEventKind,
+        FsEntry,
+        FsEntryKind,
+        FsEncoding,
+        FsReadResponse,
+        FsWriteRequest,
+        FsWriteResponse,
+        FsChangeEvent,
+        FsChangeKind,
+        GitStatus,
+        GitCommit,
+        GitFileChange,
+        GitChangeKind,
+        VncClipboard,
+        VncClipboardSource,
+        VncClipboardRequest
     )),
-    tags((name = "sandboxes", description = "Manage bubblewrap-based sandboxes"))
+    tags(
+        (name = "sandboxes", description = "Manage bubblewrap-based sandboxes"),
+        (name = "pty", description = "Proxied to the sandbox's cmux-pty server; bodies are cmux-pty's")
+    )
 )]
 pub struct ApiDoc;
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_router(
     service: Arc<dyn SandboxService>,
     host_events: HostEventSender,
     gh_responses: GhResponseRegistry,
     gh_auth_cache: crate::service::GhAuthCache,
     notifications: NotificationStore,
+    shutdown: ShutdownState,
+    auth: AuthConfig,
+    owners: SandboxOwners,
 ) -> Router {
     let state = AppState::new(
         service,
@@ -94,6 +208,9 @@ pub fn build_router(
         gh_responses,
         gh_auth_cache,
         notifications,
+        shutdown,
+        auth,
+        owners,
     );
     let openapi = ApiDoc::openapi();
     let swagger_routes: Router<AppState> =
@@ -101,6 +218,7 @@ pub fn build_router(
 
     Router::new()
         .route("/healthz", get(health))
+        .route("/metrics", get(prometheus_metrics))
         .route("/sandboxes", get(list_sandboxes).post(create_sandbox))
         .route("/sandboxes/{id}", get(get_sandbox).delete(delete_sandbox))
         .route("/sandboxes/{id}/exec", post(exec_sandbox))
@@ -111,6 +229,19 @@ pub fn build_router(
         .route("/sandboxes/{id}/attach", any(attach_sandbox))
         .route("/sandboxes/{id}/proxy", any(proxy_sandbox))
         .route("/sandboxes/{id}/await-ready", post(await_ready))
+        .route("/sandboxes/{id}/ports", get(list_ports).post(forward_port))
+        .route("/sandboxes/{id}/ports/{port}", delete(unforward_port))
+        .route(
+            "/sandboxes/{id}/clipboard",
+            get(get_clipboard).put(put_clipboard),
+        )
+        .route("/sandboxes/{id}/fs/list", get(fs_list))
+        .route("/sandboxes/{id}/fs/read", get(fs_read))
+        .route("/sandboxes/{id}/fs/write", put(fs_write))
+        .route("/sandboxes/{id}/fs/watch", get(fs_watch))
+        .route("/sandboxes/{id}/git/status", get(git_status))
+        .route("/sandboxes/{id}/git/log", get(git_log))
+        .route("/events", get(lifecycle_events))
         // PTY proxy endpoints - direct access to sandbox's cmux-pty
         .route(
             "/sandboxes/{id}/pty/sessions",
@@ -147,18 +278,33 @@ pub fn build_router(
         .merge(swagger_routes)
         // Fallback for subdomain routing: {index}-{port}.host -> sandbox's internal port
         .fallback(subdomain_proxy)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_auth,
+        ))
         .with_state(state)
 }
 
 #[utoipa::path(
     get,
     path = "/healthz",
-    responses((status = 200, description = "Server is healthy", body = HealthResponse))
+    responses(
+        (status = 200, description = "Server is healthy", body = HealthResponse),
+        (status = 503, description = "Server is shutting down", body = HealthResponse)
+    )
 )]
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-    })
+async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let (code, status) = if state.shutdown.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    (
+        code,
+        Json(HealthResponse {
+            status: status.to_string(),
+        }),
+    )
 }
 
 #[utoipa::path(
@@ -172,9 +318,16 @@ async fn health() -> Json<HealthResponse> {
 )]
 async fn create_sandbox(
     state: axum::extract::State<AppState>,
+    Extension(principal): Extension<Principal>,
     Json(request): Json<CreateSandboxRequest>,
 ) -> SandboxResult<(StatusCode, Json<SandboxSummary>)> {
+    if let Some(limits) = &request.resources {
+        validate_limits(limits)?;
+    }
     let summary = state.service.create(request).await?;
+    if let Some(team) = principal.team() {
+        state.owners.record(summary.id, team.to_string()).await;
+    }
     Ok((StatusCode::CREATED, Json(summary)))
 }
 
@@ -185,8 +338,19 @@ async fn create_sandbox(
 )]
 async fn list_sandboxes(
     state: axum::extract::State<AppState>,
+    Extension(principal): Extension<Principal>,
 ) -> SandboxResult<Json<Vec<SandboxSummary>>> {
-    let sandboxes = state.service.list().await?;
+    let mut sandboxes = state.service.list().await?;
+    if principal.team().is_some() {
+        let mut visible = Vec::with_capacity(sandboxes.len());
+        for sandbox in sandboxes {
+            let owner = state.owners.owner(&sandbox.id).await;
+            if principal.can_access(owner.as_deref()) {
+                visible.push(sandbox);
+            }
+        }
+        sandboxes = visible;
+    }
     Ok(Json(sandboxes))
 }
 
@@ -228,8 +392,13 @@ async fn exec_sandbox(
     Path(id): Path<String>,
     Json(request): Json<ExecRequest>,
 ) -> SandboxResult<Json<ExecResponse>> {
-    let response = state.service.exec(id, request).await?;
-    Ok(Json(response))
+    let started = std::time::Instant::now();
+    let response = state.service.exec(id, request).await;
+    metrics::observe_exec(
+        started.elapsed().as_secs_f64(),
+        matches!(&response, Ok(r) if r.exit_code == 0),
+    );
+    Ok(Json(response?))
 }
 
 #[utoipa::path(
@@ -253,6 +422,16 @@ async fn upload_files(
     Ok(StatusCode::OK)
 }
 
+/// Interactive shell (or `command`) in a sandbox over WebSocket.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/attach",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        AttachParams
+    ),
+    responses((status = 101, description = "Switching protocols to WebSocket"))
+)]
 async fn attach_sandbox(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
@@ -269,6 +448,7 @@ async fn attach_sandbox(
         .map(|c| vec!["/bin/sh".to_string(), "-c".to_string(), c]);
 
     ws.on_upgrade(move |socket| async move {
+        let _connection = metrics::websocket_opened("attach");
         if let Err(e) = state
             .service
             .attach(id, socket, initial_size, command, params.tty)
@@ -279,6 +459,16 @@ async fn attach_sandbox(
     })
 }
 
+/// Raw TCP tunnel to a port inside a sandbox over WebSocket.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/proxy",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ProxyParams
+    ),
+    responses((status = 101, description = "Switching protocols to WebSocket"))
+)]
 async fn proxy_sandbox(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
@@ -286,6 +476,7 @@ async fn proxy_sandbox(
     ws: WebSocketUpgrade,
 ) -> Response {
     ws.on_upgrade(move |socket| async move {
+        let _connection = metrics::websocket_opened("proxy");
         if let Err(e) = state.service.proxy(id, params.port, socket).await {
             tracing::error!("proxy failed: {e}");
         }
@@ -325,12 +516,6 @@ async fn subdomain_proxy(
         return (StatusCode::NOT_FOUND, "Not found").into_response();
     };
 
-    // NOTE: We intentionally allow access to any port inside the sandbox.
-    // This enables users to run dev servers (e.g., vite on :5173, next on :3000)
-    // and access them via subdomain routing (e.g., 0-5173.lvh.me:46833).
-    // Security is handled at the sandbox network level - each sandbox has its own
-    // isolated network namespace and can only be reached through this proxy.
-
     // Find sandbox by index
     let sandboxes = match state.service.list().await {
         Ok(s) => s,
@@ -353,6 +538,16 @@ async fn subdomain_proxy(
             .into_response();
     };
 
+    // Dev servers (e.g. vite on :5173) are reachable once forwarded through
+    // `POST /sandboxes/{id}/ports`; built-in services always are.
+    if !is_service_port(sandbox, port) && !state.ports.is_forwarded(&sandbox.id, port).await {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("Port {port} of sandbox {index} is not forwarded"),
+        )
+            .into_response();
+    }
+
     let sandbox_ip = sandbox.network.sandbox_ip.clone();
 
     // Extract parts from request before consuming body
@@ -370,20 +565,39 @@ async fn subdomain_proxy(
         // For noVNC port (39380), use our native Rust VNC proxy with TCP_NODELAY
         // The VNC server runs on port 5900 + display_number
         if port == 39380 {
-            let vnc_port = sandbox.display.as_ref().map(|d| d.vnc_port).unwrap_or(5910); // Default to display :10
-
-            let vnc_addr: SocketAddr = format!("{}:{}", sandbox_ip, vnc_port)
-                .parse()
-                .unwrap_or_else(|_| SocketAddr::from(([10, 201, 0, 2], vnc_port)));
+            let query = parts.uri.query().unwrap_or_default();
+            let quality = match VncQuality::from_query(query) {
+                Ok(quality) => quality,
+                Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+            };
+            let vnc_target = vnc_target(sandbox);
+            let clipboard = state.clipboards.follow(sandbox.id, &vnc_target).await;
 
             tracing::info!(
                 sandbox_index = index,
-                vnc_addr = %vnc_addr,
-                "VNC WebSocket proxy (native Rust, TCP_NODELAY)"
+                vnc_target = %vnc_target,
+                quality = ?quality,
+                "VNC WebSocket proxy (native Rust)"
             );
 
             return ws.on_upgrade(move |client_socket| async move {
-                if let Err(e) = proxy_vnc_websocket(client_socket, vnc_addr).await {
+                let _connection = metrics::websocket_opened("vnc");
+                let recorder = match recording_dir() {
+                    Some(dir) => {
+                        match VncRecorder::create(&dir, &format!("sandbox-{index}")).await {
+                            Ok(recorder) => {
+                                tracing::info!(path = %recorder.path().display(), "recording VNC session");
+                                Some(recorder)
+                            }
+                            Err(e) => {
+                                tracing::warn!("failed to start VNC recording: {e}");
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                if let Err(e) = proxy_vnc_websocket(client_socket, vnc_target, recorder, Some(clipboard), quality).await {
                     tracing::error!("VNC proxy error: {e}");
                 }
             });
@@ -399,6 +613,7 @@ async fn subdomain_proxy(
         );
 
         return ws.on_upgrade(move |client_socket| async move {
+            let _connection = metrics::websocket_opened("subdomain");
             if let Err(e) = proxy_websocket(client_socket, &sandbox_ip, port, &path_and_query).await
             {
                 tracing::error!("WebSocket proxy error: {e}");
@@ -408,55 +623,12 @@ async fn subdomain_proxy(
 
     // For noVNC port (39380), serve static files from /usr/share/novnc
     if port == 39380 {
-        use std::path::Path;
-
         let path = if path_and_query == "/" || path_and_query.is_empty() {
             "/vnc.html"
         } else {
             path_and_query.split('?').next().unwrap_or(&path_and_query)
         };
-
-        // Sanitize path to prevent directory traversal attacks
-        let base_dir = Path::new("/usr/share/novnc");
-        let requested = base_dir.join(path.trim_start_matches('/'));
-        let canonical = match requested.canonicalize() {
-            Ok(p) => p,
-            Err(_) => {
-                return (StatusCode::NOT_FOUND, "File not found").into_response();
-            }
-        };
-
-        // Verify the canonical path is still under the base directory
-        if !canonical.starts_with(base_dir) {
-            tracing::warn!(path = %path, "blocked directory traversal attempt");
-            return (StatusCode::FORBIDDEN, "Forbidden").into_response();
-        }
-
-        let file_path = canonical.to_string_lossy().to_string();
-        tracing::debug!(file_path = %file_path, "serving noVNC static file");
-
-        match tokio::fs::read(&file_path).await {
-            Ok(contents) => {
-                let content_type = match file_path.rsplit('.').next() {
-                    Some("html") => "text/html; charset=utf-8",
-                    Some("js") => "application/javascript",
-                    Some("css") => "text/css",
-                    Some("png") => "image/png",
-                    Some("svg") => "image/svg+xml",
-                    Some("ico") => "image/x-icon",
-                    _ => "application/octet-stream",
-                };
-                return Response::builder()
-                    .status(StatusCode::OK)
-                    .header("Content-Type", content_type)
-                    .header("Cache-Control", "public, max-age=3600")
-                    .body(Body::from(contents))
-                    .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
-            }
-            Err(_) => {
-                return (StatusCode::NOT_FOUND, "File not found").into_response();
-            }
-        }
+        return novnc_static_response(std::path::Path::new(NOVNC_DIR), path).await;
     }
 
     // HTTP reverse proxy - collect request body
@@ -698,14 +870,25 @@ async fn proxy_websocket(
 }
 
 /// Multiplexed WebSocket endpoint - handles multiple PTY sessions over a single connection.
-async fn mux_attach(state: axum::extract::State<AppState>, ws: WebSocketUpgrade) -> Response {
+#[utoipa::path(
+    get,
+    path = "/mux/attach",
+    responses((status = 101, description = "Switching protocols to WebSocket"))
+)]
+async fn mux_attach(
+    state: axum::extract::State<AppState>,
+    Extension(principal): Extension<Principal>,
+    ws: WebSocketUpgrade,
+) -> Response {
     let host_event_rx = state.host_events.subscribe();
     let gh_responses = state.gh_responses.clone();
     let gh_auth_cache = state.gh_auth_cache.clone();
+    let scope = MuxScope::new(principal, state.owners.clone());
     ws.on_upgrade(move |socket| async move {
+        let _connection = metrics::websocket_opened("mux");
         if let Err(e) = state
             .service
-            .mux_attach(socket, host_event_rx, gh_responses, gh_auth_cache)
+            .mux_attach(socket, host_event_rx, gh_responses, gh_auth_cache, scope)
             .await
         {
             tracing::error!("mux_attach failed: {e}");
@@ -714,6 +897,16 @@ async fn mux_attach(state: axum::extract::State<AppState>, ws: WebSocketUpgrade)
 }
 
 /// Open a URL on the host machine. Used by sandboxed processes to open links.
+#[utoipa::path(
+    get,
+    path = "/open-url",
+    params(OpenUrlRequest),
+    responses(
+        (status = 200, description = "URL forwarded to host"),
+        (status = 400, description = "Invalid URL"),
+        (status = 500, description = "Failed to dispatch open-url request")
+    )
+)]
 async fn open_url(
     State(state): State<AppState>,
     Query(params): Query<OpenUrlRequest>,
@@ -831,11 +1024,410 @@ async fn delete_sandbox(
     Path(id): Path<String>,
 ) -> SandboxResult<Json<SandboxSummary>> {
     match state.service.delete(id.clone()).await? {
-        Some(summary) => Ok(Json(summary)),
+        Some(summary) => {
+            state.ports.clear(&summary.id).await;
+            state.clipboards.clear(&summary.id).await;
+            state.owners.clear(&summary.id).await;
+            Ok(Json(summary))
+        }
         None => Err(SandboxError::NotFound(Uuid::nil())), // TODO: Better error handling
     }
 }
 
+async fn require_sandbox(state: &AppState, id: String) -> SandboxResult<SandboxSummary> {
+    state
+        .service
+        .get(id)
+        .await?
+        .ok_or(SandboxError::NotFound(Uuid::nil()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/ports",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    responses(
+        (status = 200, description = "Listening and forwarded ports", body = SandboxPorts),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn list_ports(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+) -> SandboxResult<Json<SandboxPorts>> {
+    let summary = require_sandbox(&state, id.clone()).await?;
+    let output = state.service.exec(id, proc_net_tcp_request()).await?;
+    let forwarded = state.ports.list(&summary.id).await;
+    let mut listening = parse_listening_ports(&output.stdout);
+    for port in &mut listening {
+        port.forwarded = forwarded.iter().any(|f| f.port == port.port);
+    }
+    Ok(Json(SandboxPorts {
+        listening,
+        forwarded,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sandboxes/{id}/ports",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body = ForwardPortRequest,
+    responses(
+        (status = 201, description = "Port forwarded", body = ForwardedPort),
+        (status = 400, description = "Bad request", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn forward_port(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<ForwardPortRequest>,
+) -> SandboxResult<(StatusCode, Json<ForwardedPort>)> {
+    if request.port == 0 {
+        return Err(SandboxError::InvalidRequest("port must be non-zero".into()));
+    }
+    let summary = require_sandbox(&state, id).await?;
+    let subdomain = format!("{}-{}", summary.index, request.port);
+    let url = headers
+        .get(HOST)
+        .and_then(|h| h.to_str().ok())
+        .map(|host| format!("http://{subdomain}.{host}"));
+    let forward = ForwardedPort {
+        port: request.port,
+        label: request.label,
+        subdomain,
+        url,
+        created_at: chrono::Utc::now(),
+    };
+    state.ports.forward(summary.id, forward.clone()).await;
+    events::publish(LifecycleEventKind::PortForwarded {
+        sandbox_id: summary.id,
+        port: request.port,
+    });
+    Ok((StatusCode::CREATED, Json(forward)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sandboxes/{id}/ports/{port}",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("port" = u16, Path, description = "Forwarded port")
+    ),
+    responses(
+        (status = 204, description = "Forward removed"),
+        (status = 404, description = "Sandbox or forward not found", body = ErrorBody)
+    )
+)]
+async fn unforward_port(
+    state: axum::extract::State<AppState>,
+    Path((id, port)): Path<(String, u16)>,
+) -> SandboxResult<StatusCode> {
+    let summary = require_sandbox(&state, id).await?;
+    match state.ports.remove(&summary.id, port).await {
+        Some(_) => {
+            events::publish(LifecycleEventKind::PortUnforwarded {
+                sandbox_id: summary.id,
+                port,
+            });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(SandboxError::NotFound(summary.id)),
+    }
+}
+
+/// Where the sandbox's VNC server listens: its Unix socket when it has one,
+/// otherwise 5900 + display number on the sandbox IP.
+fn vnc_target(sandbox: &SandboxSummary) -> VncTarget {
+    let vnc_port = sandbox.display.as_ref().map(|d| d.vnc_port).unwrap_or(5910); // Default to display :10
+    match sandbox.display.as_ref().and_then(|d| d.vnc_socket.clone()) {
+        Some(path) => VncTarget::Unix(path.into()),
+        None => VncTarget::Tcp(
+            format!("{}:{}", sandbox.network.sandbox_ip, vnc_port)
+                .parse()
+                .unwrap_or_else(|_| SocketAddr::from(([10, 201, 0, 2], vnc_port))),
+        ),
+    }
+}
+
+async fn require_desktop(state: &AppState, id: String) -> SandboxResult<SandboxSummary> {
+    let summary = require_sandbox(state, id).await?;
+    if summary.display.is_none() {
+        return Err(SandboxError::InvalidRequest(
+            "sandbox has no desktop".into(),
+        ));
+    }
+    Ok(summary)
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/clipboard",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    responses(
+        (status = 200, description = "Latest clipboard text seen on the desktop", body = VncClipboard),
+        (status = 400, description = "Sandbox has no desktop", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn get_clipboard(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+) -> SandboxResult<Json<VncClipboard>> {
+    let summary = require_desktop(&state, id).await?;
+    let clipboard = state
+        .clipboards
+        .get(summary.id, &vnc_target(&summary))
+        .await;
+    Ok(Json(clipboard))
+}
+
+#[utoipa::path(
+    put,
+    path = "/sandboxes/{id}/clipboard",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body = VncClipboardRequest,
+    responses(
+        (status = 200, description = "Text placed on the desktop clipboard", body = VncClipboard),
+        (status = 400, description = "Sandbox has no desktop", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "Desktop VNC server unreachable", body = ErrorBody)
+    )
+)]
+async fn put_clipboard(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<VncClipboardRequest>,
+) -> SandboxResult<Json<VncClipboard>> {
+    let summary = require_desktop(&state, id).await?;
+    let clipboard = state
+        .clipboards
+        .set(summary.id, &vnc_target(&summary), &request.text)
+        .await?;
+    Ok(Json(clipboard))
+}
+
+/// Host directory backing a sandbox's /workspace.
+async fn workspace_root(state: &AppState, id: String) -> SandboxResult<std::path::PathBuf> {
+    Ok(std::path::PathBuf::from(
+        require_sandbox(state, id).await?.workspace,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/fs/list",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        FsPathParams
+    ),
+    responses(
+        (status = 200, description = "Directory entries", body = [FsEntry]),
+        (status = 400, description = "Path outside the workspace", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn fs_list(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<FsPathParams>,
+) -> SandboxResult<Json<Vec<FsEntry>>> {
+    let root = workspace_root(&state, id).await?;
+    Ok(Json(files::list_dir(&root, &params.path).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/fs/read",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        FsReadParams
+    ),
+    responses(
+        (status = 200, description = "File contents", body = FsReadResponse),
+        (status = 400, description = "Path outside the workspace", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn fs_read(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<FsReadParams>,
+) -> SandboxResult<Json<FsReadResponse>> {
+    let root = workspace_root(&state, id).await?;
+    let response = files::read_file(
+        &root,
+        &params.path,
+        params.offset,
+        params.length,
+        params.encoding,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/sandboxes/{id}/fs/write",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body = FsWriteRequest,
+    responses(
+        (status = 200, description = "File written", body = FsWriteResponse),
+        (status = 400, description = "Path outside the workspace", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn fs_write(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<FsWriteRequest>,
+) -> SandboxResult<Json<FsWriteResponse>> {
+    let root = workspace_root(&state, id).await?;
+    Ok(Json(files::write_file(&root, request).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/fs/watch",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        FsPathParams
+    ),
+    responses(
+        (status = 200, description = "Server-sent stream of FsChangeEvent", content_type = "text/event-stream"),
+        (status = 400, description = "Path outside the workspace", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn fs_watch(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<FsPathParams>,
+) -> SandboxResult<Response> {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use futures::StreamExt;
+
+    let root = workspace_root(&state, id).await?;
+    let changes = files::watch(&root, &params.path).await?;
+    let events = changes.map(|change| Event::default().json_data(change));
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/git/status",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        FsPathParams
+    ),
+    responses(
+        (status = 200, description = "Branch, upstream and changed files", body = GitStatus),
+        (status = 400, description = "Path outside the workspace or not in a repository", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn git_status(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<FsPathParams>,
+) -> SandboxResult<Json<GitStatus>> {
+    let root = workspace_root(&state, id).await?;
+    Ok(Json(git::status(&root, &params.path).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/git/log",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        GitLogParams
+    ),
+    responses(
+        (status = 200, description = "Commits reachable from HEAD, newest first", body = [GitCommit]),
+        (status = 400, description = "Path outside the workspace or not in a repository", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn git_log(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<GitLogParams>,
+) -> SandboxResult<Json<Vec<GitCommit>>> {
+    let root = workspace_root(&state, id).await?;
+    Ok(Json(git::log(&root, &params.path, params.limit).await?))
+}
+
+/// Prometheus metrics in the text exposition format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Prometheus metrics", content_type = "text/plain"))
+)]
+async fn prometheus_metrics(state: axum::extract::State<AppState>) -> SandboxResult<Response> {
+    let sandboxes = state.service.list().await?;
+    Ok((
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        metrics::render(&sandboxes),
+    )
+        .into_response())
+}
+
+/// Sandbox lifecycle events as they happen, named by their `type`.
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsParams),
+    responses(
+        (status = 200, description = "Server-sent stream of LifecycleEvent", content_type = "text/event-stream")
+    )
+)]
+async fn lifecycle_events(Query(params): Query<EventsParams>) -> Response {
+    use axum::response::sse::{Event, KeepAlive, Sse};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let rx = events::subscribe();
+    let stream = futures::stream::unfold(rx, move |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if params
+                        .sandbox_id
+                        .is_some_and(|id| id != event.kind.sandbox_id())
+                    {
+                        continue;
+                    }
+                    let sse = Event::default().event(event.kind.name()).json_data(&event);
+                    return Some((sse, rx));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "lifecycle event stream lagged");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
 #[utoipa::path(
     post,
     path = "/prune",
@@ -956,6 +1548,19 @@ async fn proxy_pty_request(
 }
 
 /// List all PTY sessions in a sandbox.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/pty/sessions",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_list_sessions(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
@@ -969,6 +1574,20 @@ async fn pty_list_sessions(
 }
 
 /// Create a new PTY session in a sandbox.
+#[utoipa::path(
+    post,
+    path = "/sandboxes/{id}/pty/sessions",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body(content_type = "application/json", description = "Forwarded to cmux-pty as-is"),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_create_session(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
@@ -990,6 +1609,20 @@ async fn pty_create_session(
 }
 
 /// Get a specific PTY session.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/pty/sessions/{session_id}",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("session_id" = String, Path, description = "cmux-pty session ID")
+    ),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_get_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
@@ -1004,6 +1637,20 @@ async fn pty_get_session(
 }
 
 /// Delete a PTY session.
+#[utoipa::path(
+    delete,
+    path = "/sandboxes/{id}/pty/sessions/{session_id}",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("session_id" = String, Path, description = "cmux-pty session ID")
+    ),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_delete_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
@@ -1018,6 +1665,21 @@ async fn pty_delete_session(
 }
 
 /// Resize a PTY session.
+#[utoipa::path(
+    post,
+    path = "/sandboxes/{id}/pty/sessions/{session_id}/resize",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("session_id" = String, Path, description = "cmux-pty session ID")
+    ),
+    request_body(content_type = "application/json", description = "Forwarded to cmux-pty as-is"),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_resize_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
@@ -1040,6 +1702,20 @@ async fn pty_resize_session(
 }
 
 /// Capture PTY session content.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/pty/sessions/{session_id}/capture",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("session_id" = String, Path, description = "cmux-pty session ID")
+    ),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_capture_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
@@ -1062,6 +1738,19 @@ async fn pty_capture_session(
 }
 
 /// WebSocket attach to a PTY session.
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/pty/sessions/{session_id}/attach",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)"),
+        ("session_id" = String, Path, description = "cmux-pty session ID")
+    ),
+    responses(
+        (status = 101, description = "Switching protocols to WebSocket"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
 async fn pty_attach_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
@@ -1075,6 +1764,7 @@ async fn pty_attach_session(
     let path = format!("/sessions/{}/attach", session_id);
 
     ws.on_upgrade(move |socket| async move {
+        let _connection = metrics::websocket_opened("pty");
         if let Err(e) = proxy_websocket(socket, &sandbox_ip, PTY_PORT, &path).await {
             tracing::error!("PTY WebSocket proxy error: {e}");
         }
@@ -1082,6 +1772,20 @@ async fn pty_attach_session(
 }
 
 /// Send a signal to PTY processes in a sandbox.
+#[utoipa::path(
+    post,
+    path = "/sandboxes/{id}/pty/signal",
+    tag = "pty",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body(content_type = "application/json", description = "Forwarded to cmux-pty as-is"),
+    responses(
+        (status = 200, description = "Response from cmux-pty"),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "cmux-pty unreachable")
+    )
+)]
 async fn pty_signal(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
@@ -1162,6 +1866,7 @@ mod tests {
             _host_event_rx: crate::service::HostEventReceiver,
             _gh_responses: crate::service::GhResponseRegistry,
             _gh_auth_cache: crate::service::GhAuthCache,
+            _scope: MuxScope,
         ) -> SandboxResult<()> {
             Ok(())
         }
@@ -1208,7 +1913,8 @@ mod tests {
     fn fake_summary(name: String) -> SandboxSummary {
         SandboxSummary {
             index: 0,
-            id: Uuid::new_v4(),
+            // Stable so state keyed by sandbox ID survives across requests.
+            id: Uuid::from_u128(1),
             name,
             created_at: Utc::now(),
             workspace: "/tmp/mock".to_string(),
@@ -1222,10 +1928,19 @@ mod tests {
             },
             display: None,
             correlation_id: None,
+            resources: None,
         }
     }
 
     fn make_test_router() -> Router {
+        make_test_router_with_shutdown(ShutdownState::new())
+    }
+
+    fn make_test_router_with_shutdown(shutdown: ShutdownState) -> Router {
+        make_test_router_with(shutdown, AuthConfig::new())
+    }
+
+    fn make_test_router_with(shutdown: ShutdownState, auth: AuthConfig) -> Router {
         use std::collections::HashMap;
         let (host_event_tx, _) = tokio::sync::broadcast::channel(16);
         let gh_responses = Arc::new(Mutex::new(HashMap::new()));
@@ -1237,9 +1952,210 @@ mod tests {
             gh_responses,
             gh_auth_cache,
             notifications,
+            shutdown,
+            auth,
+            SandboxOwners::new(),
         )
     }
 
+    fn authed(method: &str, uri: &str, token: Option<&str>, body: Body) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {token}"));
+        }
+        builder.body(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn auth_distinguishes_unauthenticated_from_forbidden() {
+        let auth = AuthConfig::new()
+            .with_admin_token("admin")
+            .with_token_spec("acme:acme-token")
+            .with_token_spec("globex:globex-token");
+        let app = make_test_router_with(ShutdownState::new(), auth);
+        let status = |request: Request<Body>| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        // Health and docs stay open.
+        let response = status(authed("GET", "/healthz", None, Body::empty())).await;
+        assert_eq!(response, StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(authed("GET", "/sandboxes", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+        let response = status(authed("GET", "/sandboxes", Some("wrong"), Body::empty())).await;
+        assert_eq!(response, StatusCode::UNAUTHORIZED);
+
+        // Admin-created sandboxes are invisible to teams.
+        let response = status(authed(
+            "GET",
+            "/sandboxes/mock",
+            Some("admin"),
+            Body::empty(),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::OK);
+        let response = status(authed(
+            "GET",
+            "/sandboxes/mock",
+            Some("acme-token"),
+            Body::empty(),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::FORBIDDEN);
+
+        let create = serde_json::to_vec(&serde_json::json!({ "name": "acme-box" })).unwrap();
+        let response = status(authed(
+            "POST",
+            "/sandboxes",
+            Some("acme-token"),
+            Body::from(create),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::CREATED);
+
+        let exec = serde_json::to_vec(&serde_json::json!({ "command": ["true"] })).unwrap();
+        let response = status(authed(
+            "POST",
+            "/sandboxes/mock/exec",
+            Some("acme-token"),
+            Body::from(exec.clone()),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::OK);
+        let response = status(authed(
+            "POST",
+            "/sandboxes/mock/exec",
+            Some("globex-token"),
+            Body::from(exec),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::FORBIDDEN);
+        let response = status(authed(
+            "GET",
+            "/sandboxes/mock/fs/read?path=a.txt",
+            Some("globex-token"),
+            Body::empty(),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::FORBIDDEN);
+
+        let list = |token: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(authed("GET", "/sandboxes", Some(token), Body::empty()))
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Vec<SandboxSummary>>(&body)
+                    .unwrap()
+                    .len()
+            }
+        };
+        assert_eq!(list("acme-token").await, 1);
+        assert_eq!(list("globex-token").await, 0);
+        assert_eq!(list("admin").await, 1);
+
+        // Host-wide routes are admin-only.
+        let response = status(authed(
+            "POST",
+            "/prune",
+            Some("acme-token"),
+            Body::from("{}"),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::FORBIDDEN);
+        let response = status(authed(
+            "GET",
+            "/notifications",
+            Some("admin"),
+            Body::empty(),
+        ))
+        .await;
+        assert_eq!(response, StatusCode::OK);
+
+        // Teams reach the mux, which scopes each message itself. Without an
+        // upgrade the handler rejects the plain GET, past the auth layer.
+        let response = status(authed("GET", "/mux/attach", None, Body::empty())).await;
+        assert_eq!(response, StatusCode::UNAUTHORIZED);
+        let response = status(authed(
+            "GET",
+            "/mux/attach",
+            Some("acme-token"),
+            Body::empty(),
+        ))
+        .await;
+        assert!(
+            response != StatusCode::FORBIDDEN && response != StatusCode::UNAUTHORIZED,
+            "{response}"
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_accepts_query_tokens_and_team_jwts() {
+        let auth = AuthConfig::new().with_jwt_secret(b"secret");
+        let app = make_test_router_with(ShutdownState::new(), auth);
+        let claims = serde_json::json!({
+            "team": "acme",
+            "exp": Utc::now().timestamp() + 600,
+        });
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(authed(
+                "GET",
+                &format!("/sandboxes?access_token={token}"),
+                None,
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(authed("GET", "/events", Some(&token), Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn healthz_reports_draining_during_shutdown() {
+        let shutdown = ShutdownState::new();
+        let app = make_test_router_with_shutdown(shutdown.clone());
+        let healthz = || {
+            Request::builder()
+                .uri("/healthz")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(healthz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        shutdown.begin();
+        let response = app.oneshot(healthz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[tokio::test]
     async fn serves_openapi_document() {
         let app = make_test_router();
@@ -1254,6 +2170,21 @@ mod tests {
             .unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let paths = doc["paths"].as_object().unwrap();
+        for path in [
+            "/sandboxes/{id}/attach",
+            "/mux/attach",
+            "/sandboxes/{id}/fs/watch",
+            "/events",
+            "/metrics",
+            "/sandboxes/{id}/pty/sessions/{session_id}/attach",
+        ] {
+            assert!(paths.contains_key(path), "missing {path}");
+        }
     }
 
     #[tokio::test]
@@ -1266,6 +2197,7 @@ mod tests {
             read_only_paths: Vec::new(),
             tmpfs: Vec::new(),
             env: Vec::new(),
+            resources: None,
         };
 
         let response = app
@@ -1282,4 +2214,142 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::CREATED);
     }
+
+    #[tokio::test]
+    async fn create_rejects_unusable_memory_limits() {
+        let app = make_test_router();
+        for memory_mb in [0, u64::MAX] {
+            let body = serde_json::json!({ "resources": { "memory_mb": memory_mb } });
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/sandboxes")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{memory_mb}");
+        }
+    }
+
+    #[tokio::test]
+    async fn ports_can_be_forwarded_listed_and_removed() {
+        let app = make_test_router();
+        let send = |method: &str, uri: &str, body: Body| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("host", "localhost:46831")
+                    .header("content-type", "application/json")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let via_subdomain = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header("host", "0-3000.localhost:46831")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = via_subdomain().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = send(
+            "POST",
+            "/sandboxes/mock/ports",
+            Body::from(r#"{"port":3000,"label":"vite"}"#),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let forward: ForwardedPort = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(forward.subdomain, "0-3000");
+        assert_eq!(
+            forward.url.as_deref(),
+            Some("http://0-3000.localhost:46831")
+        );
+
+        let response = send("GET", "/sandboxes/mock/ports", Body::empty())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let ports: SandboxPorts = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(ports.forwarded.len(), 1);
+
+        let response = send("DELETE", "/sandboxes/mock/ports/3000", Body::empty())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = via_subdomain().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = send("DELETE", "/sandboxes/mock/ports/3000", Body::empty())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error["code"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn events_stream_reports_port_forwards() {
+        use futures::StreamExt;
+
+        let app = make_test_router();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/events?sandbox_id={}", Uuid::from_u128(1)))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sandboxes/mock/ports")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"port":4321}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Other tests share the process-wide bus; skip their events.
+        let mut seen = String::new();
+        while !seen.contains(r#""port":4321"#) {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+                .await
+                .expect("event timeout")
+                .unwrap()
+                .unwrap();
+            seen.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(seen.contains("event: port_forwarded"), "{seen}");
+    }
 }
@@ -0,0 +1,479 @@
+//! Request authentication and per-team isolation for the HTTP API.
+//!
+//! With no tokens or JWT secret configured every request acts as the admin,
+//! so single-user setups keep working unchanged. Once configured, every route
+//! except `/healthz` and the API docs needs a bearer token, sent as
+//! `Authorization: Bearer <token>` or, for WebSocket clients that can't set
+//! headers, as `?access_token=<token>`.
+//!
+//! Static tokens either act as the admin or are bound to a team. JWTs are
+//! HS256-signed and name their team in the `team` claim. Team callers only
+//! see and reach sandboxes their team created, and are refused the host-wide
+//! routes (`/events`, `/prune`, the subdomain proxy, ...). `/mux/attach` is
+//! open to teams, scoped by [`MuxScope`] to their own sandboxes.
+//!
+//! Which team created each sandbox is kept in [`SandboxOwners`], saved under
+//! the data directory so it survives a daemon restart.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::errors::SandboxError;
+use crate::service::AppState;
+
+/// Query parameter carrying the token for WebSocket upgrades.
+const TOKEN_QUERY_PARAM: &str = "access_token";
+
+/// Who a request acts as. Inserted into request extensions by [`require_auth`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Principal {
+    /// Every sandbox and host-wide route.
+    Admin,
+    /// Only sandboxes created by this team.
+    Team(String),
+}
+
+impl Principal {
+    pub fn team(&self) -> Option<&str> {
+        match self {
+            Principal::Admin => None,
+            Principal::Team(team) => Some(team),
+        }
+    }
+
+    /// Whether this principal may use a sandbox owned by `owner`
+    /// (`None` for sandboxes created by the admin).
+    pub fn can_access(&self, owner: Option<&str>) -> bool {
+        match self {
+            Principal::Admin => true,
+            Principal::Team(team) => owner == Some(team.as_str()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TeamClaims {
+    team: String,
+}
+
+#[derive(Clone)]
+struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+/// Static tokens are kept as SHA-256 digests so every comparison covers the
+/// same number of bytes whatever the token's length.
+type TokenDigest = [u8; 32];
+
+fn token_digest(token: &str) -> TokenDigest {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// Compare without stopping at the first differing byte.
+fn digests_match(a: &TokenDigest, b: &TokenDigest) -> bool {
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Credentials accepted by the API. The default accepts no credentials and
+/// leaves authentication off.
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    tokens: Vec<(TokenDigest, Principal)>,
+    jwt: Option<Arc<JwtVerifier>>,
+}
+
+impl AuthConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_admin_token(self, token: impl Into<String>) -> Self {
+        self.with_static_token(token.into(), Principal::Admin)
+    }
+
+    pub fn with_team_token(self, team: impl Into<String>, token: impl Into<String>) -> Self {
+        self.with_static_token(token.into(), Principal::Team(team.into()))
+    }
+
+    fn with_static_token(mut self, token: String, principal: Principal) -> Self {
+        let digest = token_digest(&token);
+        self.tokens.retain(|(existing, _)| existing != &digest);
+        self.tokens.push((digest, principal));
+        self
+    }
+
+    /// Add a token written as `TOKEN` (admin) or `TEAM:TOKEN`.
+    pub fn with_token_spec(self, spec: &str) -> Self {
+        match spec.split_once(':') {
+            Some((team, token)) if !team.is_empty() => self.with_team_token(team, token),
+            _ => self.with_admin_token(spec),
+        }
+    }
+
+    /// Accept HS256 JWTs signed with `secret` that carry a `team` and an `exp` claim.
+    pub fn with_jwt_secret(mut self, secret: &[u8]) -> Self {
+        self.jwt = Some(Arc::new(JwtVerifier {
+            key: DecodingKey::from_secret(secret),
+            validation: Validation::new(Algorithm::HS256),
+        }));
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || self.jwt.is_some()
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<Principal> {
+        // Check every static token so the time taken doesn't reveal which matched.
+        let digest = token_digest(token);
+        let mut matched = None;
+        for (candidate, principal) in &self.tokens {
+            if digests_match(candidate, &digest) {
+                matched = Some(principal);
+            }
+        }
+        if let Some(principal) = matched {
+            return Some(principal.clone());
+        }
+        let jwt = self.jwt.as_ref()?;
+        let data = jsonwebtoken::decode::<TeamClaims>(token, &jwt.key, &jwt.validation).ok()?;
+        if data.claims.team.is_empty() {
+            return None;
+        }
+        Some(Principal::Team(data.claims.team))
+    }
+}
+
+/// Team that created each sandbox. Sandboxes created by the admin have no entry.
+#[derive(Clone, Default)]
+pub struct SandboxOwners {
+    inner: Arc<RwLock<HashMap<Uuid, String>>>,
+    path: Option<Arc<PathBuf>>,
+}
+
+impl SandboxOwners {
+    /// Owners kept in memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Owners saved as JSON at `path`, starting from what is already there.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let owners = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(io::Error::other)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Self {
+            inner: Arc::new(RwLock::new(owners)),
+            path: Some(Arc::new(path)),
+        })
+    }
+
+    pub async fn record(&self, sandbox_id: Uuid, team: String) {
+        let mut owners = self.inner.write().await;
+        owners.insert(sandbox_id, team);
+        self.save(&owners).await;
+    }
+
+    pub async fn owner(&self, sandbox_id: &Uuid) -> Option<String> {
+        self.inner.read().await.get(sandbox_id).cloned()
+    }
+
+    pub async fn clear(&self, sandbox_id: &Uuid) {
+        let mut owners = self.inner.write().await;
+        if owners.remove(sandbox_id).is_some() {
+            self.save(&owners).await;
+        }
+    }
+
+    /// Write the whole map through a temporary file so a crash never leaves
+    /// it half written. Called with the write lock held to keep saves ordered.
+    async fn save(&self, owners: &HashMap<Uuid, String>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = async {
+            let json = serde_json::to_vec(owners).map_err(io::Error::other)?;
+            let tmp = path.with_extension("json.tmp");
+            tokio::fs::write(&tmp, json).await?;
+            tokio::fs::rename(&tmp, path.as_path()).await
+        }
+        .await;
+        if let Err(error) = result {
+            tracing::warn!(%error, path = %path.display(), "failed to save sandbox owners");
+        }
+    }
+}
+
+/// What one `/mux/attach` connection may reach: every sandbox for the admin,
+/// only its own for a team.
+#[derive(Clone)]
+pub struct MuxScope {
+    principal: Principal,
+    owners: SandboxOwners,
+}
+
+impl MuxScope {
+    pub fn new(principal: Principal, owners: SandboxOwners) -> Self {
+        Self { principal, owners }
+    }
+
+    /// Unrestricted scope, for callers outside the HTTP API.
+    pub fn admin() -> Self {
+        Self::new(Principal::Admin, SandboxOwners::new())
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.principal == Principal::Admin
+    }
+
+    pub fn team(&self) -> Option<&str> {
+        self.principal.team()
+    }
+
+    pub async fn allows(&self, sandbox_id: &Uuid) -> bool {
+        if self.is_admin() {
+            return true;
+        }
+        let owner = self.owners.owner(sandbox_id).await;
+        self.principal.can_access(owner.as_deref())
+    }
+
+    /// Give a sandbox created over this connection to the caller's team.
+    pub async fn record_created(&self, sandbox_id: Uuid) {
+        if let Some(team) = self.principal.team() {
+            self.owners.record(sandbox_id, team.to_string()).await;
+        }
+    }
+}
+
+/// What part of the API a path belongs to, for team scoping.
+enum Scope<'a> {
+    Public,
+    /// `/sandboxes` itself; results are filtered by the handlers.
+    Sandboxes,
+    /// `/sandboxes/{id}` and everything below it.
+    Sandbox(&'a str),
+    /// `/mux/attach`; each message is checked against a [`MuxScope`].
+    Mux,
+    Host,
+}
+
+fn scope(path: &str) -> Scope<'_> {
+    if path == "/healthz"
+        || path == "/openapi.json"
+        || path == "/docs"
+        || path.starts_with("/docs/")
+    {
+        return Scope::Public;
+    }
+    if path == "/mux/attach" {
+        return Scope::Mux;
+    }
+    match path.strip_prefix("/sandboxes") {
+        Some("") | Some("/") => Scope::Sandboxes,
+        Some(rest) if rest.starts_with('/') => {
+            let id = rest[1..].split('/').next().unwrap_or_default();
+            Scope::Sandbox(id)
+        }
+        _ => Scope::Host,
+    }
+}
+
+fn request_token(request: &Request) -> Option<String> {
+    if let Some(value) = request.headers().get(AUTHORIZATION) {
+        let value = value.to_str().ok()?;
+        let (scheme, token) = value.split_once(' ')?;
+        return scheme
+            .eq_ignore_ascii_case("bearer")
+            .then(|| token.trim().to_string());
+    }
+    let query = request.uri().query()?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == TOKEN_QUERY_PARAM)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn unauthorized(message: &str) -> Response {
+    let mut response = SandboxError::Unauthorized(message.to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// Middleware resolving the caller's [`Principal`]: 401 without valid
+/// credentials, 403 when a team reaches outside its own sandboxes.
+pub async fn require_auth(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let scope = scope(request.uri().path());
+    if matches!(scope, Scope::Public) {
+        return next.run(request).await;
+    }
+
+    let principal = if state.auth.is_enabled() {
+        let Some(token) = request_token(&request) else {
+            return unauthorized("missing bearer token");
+        };
+        match state.auth.authenticate(&token) {
+            Some(principal) => principal,
+            None => return unauthorized("invalid or expired token"),
+        }
+    } else {
+        Principal::Admin
+    };
+
+    if let Some(team) = principal.team() {
+        match scope {
+            Scope::Public | Scope::Sandboxes | Scope::Mux => {}
+            Scope::Sandbox(id) => {
+                // Unknown sandboxes fall through so the handler reports 404.
+                if let Ok(Some(summary)) = state.service.get(id.to_string()).await {
+                    let owner = state.owners.owner(&summary.id).await;
+                    if !principal.can_access(owner.as_deref()) {
+                        let message = match owner {
+                            Some(_) => format!("sandbox {id} does not belong to team {team}"),
+                            None => format!(
+                                "sandbox {id} has no owning team; only admin tokens can use it"
+                            ),
+                        };
+                        return SandboxError::Forbidden(message).into_response();
+                    }
+                }
+            }
+            Scope::Host => {
+                return SandboxError::Forbidden(format!(
+                    "{} is only available to admin tokens",
+                    request.uri().path()
+                ))
+                .into_response();
+            }
+        }
+    }
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    fn jwt(secret: &[u8], team: &str, exp_offset: i64) -> String {
+        let claims = serde_json::json!({
+            "team": team,
+            "exp": chrono::Utc::now().timestamp() + exp_offset,
+        });
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn token_specs_bind_teams() {
+        let auth = AuthConfig::new()
+            .with_token_spec("root-token")
+            .with_token_spec("acme:acme-token");
+        assert!(auth.is_enabled());
+        assert_eq!(auth.authenticate("root-token"), Some(Principal::Admin));
+        assert_eq!(
+            auth.authenticate("acme-token"),
+            Some(Principal::Team("acme".into()))
+        );
+        assert_eq!(auth.authenticate("nope"), None);
+        assert_eq!(auth.authenticate(""), None);
+        assert!(!AuthConfig::new().is_enabled());
+    }
+
+    #[test]
+    fn reusing_a_token_rebinds_it() {
+        let auth = AuthConfig::new()
+            .with_team_token("acme", "shared")
+            .with_admin_token("shared");
+        assert_eq!(auth.authenticate("shared"), Some(Principal::Admin));
+    }
+
+    #[tokio::test]
+    async fn owners_survive_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sandbox-owners.json");
+        let kept = Uuid::new_v4();
+        let removed = Uuid::new_v4();
+
+        let owners = SandboxOwners::load(path.clone()).unwrap();
+        owners.record(kept, "acme".into()).await;
+        owners.record(removed, "acme".into()).await;
+        owners.clear(&removed).await;
+
+        let reloaded = SandboxOwners::load(path).unwrap();
+        assert_eq!(reloaded.owner(&kept).await.as_deref(), Some("acme"));
+        assert_eq!(reloaded.owner(&removed).await, None);
+    }
+
+    #[tokio::test]
+    async fn mux_scope_limits_teams_to_their_sandboxes() {
+        let owners = SandboxOwners::new();
+        let theirs = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        owners.record(other, "globex".into()).await;
+
+        let scope = MuxScope::new(Principal::Team("acme".into()), owners.clone());
+        scope.record_created(theirs).await;
+        assert!(scope.allows(&theirs).await);
+        assert!(!scope.allows(&other).await);
+        assert!(!scope.allows(&Uuid::new_v4()).await);
+
+        let admin = MuxScope::new(Principal::Admin, owners);
+        assert!(admin.allows(&theirs).await && admin.allows(&other).await);
+    }
+
+    #[test]
+    fn jwts_need_the_secret_and_an_unexpired_team_claim() {
+        let auth = AuthConfig::new().with_jwt_secret(b"secret");
+        assert_eq!(
+            auth.authenticate(&jwt(b"secret", "acme", 600)),
+            Some(Principal::Team("acme".into()))
+        );
+        assert_eq!(auth.authenticate(&jwt(b"other", "acme", 600)), None);
+        assert_eq!(auth.authenticate(&jwt(b"secret", "acme", -600)), None);
+        assert_eq!(auth.authenticate(&jwt(b"secret", "", 600)), None);
+    }
+
+    #[test]
+    fn scopes_paths() {
+        assert!(matches!(scope("/healthz"), Scope::Public));
+        assert!(matches!(scope("/docs/index.html"), Scope::Public));
+        assert!(matches!(scope("/sandboxes"), Scope::Sandboxes));
+        assert!(matches!(
+            scope("/sandboxes/abc/fs/read"),
+            Scope::Sandbox("abc")
+        ));
+        assert!(matches!(scope("/sandboxes/abc"), Scope::Sandbox("abc")));
+        assert!(matches!(scope("/sandboxesx"), Scope::Host));
+        assert!(matches!(scope("/mux/attach"), Scope::Mux));
+        assert!(matches!(scope("/events"), Scope::Host));
+        assert!(matches!(scope("/"), Scope::Host));
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::SecondsFormat;
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use cmux_sandbox::client_auth::{set_auth_token, websocket_request};
 use cmux_sandbox::models::{
     CreateSandboxRequest, EnvVar, ExecRequest, ExecResponse, NotificationLogEntry, SandboxSummary,
 };
@@ -45,9 +46,22 @@ use tokio_rustls::TlsAcceptor;
 #[command(name = "cmux", version, about = "cmux sandbox controller")]
 struct Cli {
     /// Base URL for the sandbox daemon (http or https)
-    #[arg(long, env = "CMUX_SANDBOX_URL", default_value_t = default_base_url())]
+    #[arg(long, global = true, env = "CMUX_SANDBOX_URL", default_value_t = default_base_url())]
     base_url: String,
 
+    /// Bearer token sent to the sandbox daemon
+    #[arg(
+        long,
+        global = true,
+        env = "CMUX_SANDBOX_TOKEN",
+        hide_env_values = true
+    )]
+    auth_token: Option<String>,
+
+    /// Log filter for CLI diagnostics on stderr (e.g. `debug`, `cmux_sandbox=trace`)
+    #[arg(long, global = true, env = "CMUX_LOG")]
+    log_level: Option<String>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -91,6 +105,10 @@ enum Command {
     /// Manage workspace sync via Mutagen
     Sync(SyncArgs),
 
+    /// Play back a noVNC session recorded by sandboxd (--vnc-record-dir) in the browser
+    #[command(name = "vnc-replay")]
+    VncReplay(VncReplayArgs),
+
     /// Internal helper to proxy stdin/stdout to a TCP address
     #[command(name = "_internal-proxy", hide = true)]
     InternalProxy { address: String },
@@ -123,6 +141,12 @@ enum Command {
     /// Start interactive ACP chat client
     Chat(ChatArgs),
 
+    /// Open the ACP chat client with fake conversation data (same as `chat --demo`)
+    Demo,
+
+    /// Non-interactive ACP commands for scripts and CI
+    Acp(AcpArgs),
+
     /// Manage authentication files
     Auth(AuthArgs),
 
@@ -136,6 +160,9 @@ enum Command {
     /// Check Docker setup and download sandbox image if needed
     Onboard,
 
+    /// Launch the multiplexer TUI (same as running `cmux` with no command)
+    Mux(MuxArgs),
+
     /// SSH into a sandbox (real SSH, not WebSocket attach)
     Ssh(SshArgs),
 
@@ -168,6 +195,12 @@ enum Command {
 
     /// Remove old sandboxes and optionally prune Docker resources
     Prune(PruneArgs),
+
+    /// Print a shell completion script to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
 }
 
 #[derive(Args, Debug)]
@@ -205,6 +238,21 @@ struct PruneArgs {
     all: bool,
 }
 
+#[derive(Args, Debug)]
+struct VncReplayArgs {
+    /// Recording file (.vncrec)
+    file: PathBuf,
+    /// Port to serve the player on
+    #[arg(long, default_value_t = 6080)]
+    port: u16,
+    /// Directory containing noVNC's static files
+    #[arg(long, default_value = cmux_sandbox::vnc_proxy::NOVNC_DIR)]
+    novnc_dir: PathBuf,
+    /// Playback speed multiplier (2 plays twice as fast)
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+}
+
 #[derive(Args, Debug)]
 struct BrowserArgs {
     /// Sandbox ID: c_xxx (cloud), l_xxx (local)
@@ -345,6 +393,41 @@ struct ChatArgs {
     acp: Option<AcpProvider>,
 }
 
+#[derive(Args, Debug)]
+struct AcpArgs {
+    #[command(subcommand)]
+    command: AcpCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum AcpCommand {
+    /// Send one prompt, stream the agent's output, and exit with its stop reason
+    Run(AcpRunArgs),
+}
+
+#[derive(Args, Debug)]
+struct AcpRunArgs {
+    /// Prompt to send to the agent
+    #[arg(long, short = 'p')]
+    prompt: String,
+
+    /// ACP provider to use (codex, opencode, claude, gemini). Defaults to last used provider.
+    #[arg(long, value_enum)]
+    provider: Option<AcpProvider>,
+
+    /// Print session updates as JSON lines instead of plain agent text
+    #[arg(long)]
+    json: bool,
+
+    /// Run in an existing sandbox instead of creating one from the current directory
+    #[arg(long)]
+    sandbox: Option<String>,
+
+    /// Which permission requests to approve; the rest are rejected
+    #[arg(long, default_value = "all", value_parser = ["ask", "reads", "all"])]
+    permissions: String,
+}
+
 #[derive(Args, Debug)]
 struct AuthArgs {
     #[command(subcommand)]
@@ -363,6 +446,18 @@ enum AuthCommand {
     Token,
 }
 
+#[derive(Args, Debug)]
+struct MuxArgs {
+    #[command(subcommand)]
+    command: Option<MuxCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum MuxCommand {
+    /// Restore the previous session's layout, directories and scrollback
+    Attach,
+}
+
 #[derive(Subcommand, Debug)]
 enum VmCommand {
     /// Create a new cloud VM
@@ -738,12 +833,41 @@ async fn check_server_reachable(client: &Client, base_url: &str) -> anyhow::Resu
     }
 }
 
+fn init_cli_tracing(filter: &str) -> anyhow::Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_new(filter)
+        .map_err(|e| anyhow::anyhow!("invalid --log-level '{}': {}", filter, e))?;
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .init();
+    Ok(())
+}
+
+fn default_headers(auth_token: Option<&str>) -> anyhow::Result<reqwest::header::HeaderMap> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = auth_token.filter(|t| !t.is_empty()) {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| anyhow::anyhow!("--auth-token contains invalid header characters"))?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    Ok(headers)
+}
+
 async fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    if let Some(filter) = cli.log_level.as_deref().filter(|f| !f.is_empty()) {
+        init_cli_tracing(filter)?;
+    }
     if std::env::var("CMUX_DEBUG").is_ok() {
         eprintln!("cmux base url: {}", cli.base_url);
     }
+    let headers = default_headers(cli.auth_token.as_deref())?;
+    // Websockets and the TUIs' own clients read the token from here.
+    set_auth_token(cli.auth_token.clone());
     let client = Client::builder()
+        .default_headers(headers)
         .timeout(Duration::from_secs(300))
         .no_proxy()
         .http2_keep_alive_interval(Duration::from_secs(30))
@@ -756,7 +880,7 @@ async fn run() -> anyhow::Result<()> {
             check_server_reachable(&client, &cli.base_url).await?;
             // Pass current working directory so the mux can upload it to the new sandbox
             let workspace_path = std::env::current_dir().ok();
-            cmux_sandbox::run_mux_tui(cli.base_url, workspace_path)
+            cmux_sandbox::run_mux_tui(cli.base_url, workspace_path, cli.auth_token)
                 .await
                 .map_err(|e| anyhow::anyhow!(e))?;
             return Ok(());
@@ -764,6 +888,19 @@ async fn run() -> anyhow::Result<()> {
     };
 
     match command {
+        Command::Mux(args) => {
+            check_server_reachable(&client, &cli.base_url).await?;
+            let workspace_path = std::env::current_dir().ok();
+            let attach = matches!(args.command, Some(MuxCommand::Attach));
+            cmux_sandbox::run_mux_tui_with_session(
+                cli.base_url,
+                workspace_path,
+                attach,
+                cli.auth_token,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        }
         Command::Openapi => {
             check_server_reachable(&client, &cli.base_url).await?;
             let url = format!("{}/openapi.json", cli.base_url.trim_end_matches('/'));
@@ -794,6 +931,7 @@ async fn run() -> anyhow::Result<()> {
                     read_only_paths: vec![],
                     tmpfs: vec![],
                     env: build_default_env_vars(),
+                    resources: None,
                 };
                 let url = format!("{}/sandboxes", cli.base_url.trim_end_matches('/'));
                 let response = client.post(url).json(&body).send().await?;
@@ -1069,6 +1207,21 @@ async fn run() -> anyhow::Result<()> {
             let api_url = get_cmux_api_url();
             handle_sync_command(&client, &cli.base_url, &api_url, args).await?;
         }
+        Command::VncReplay(args) => {
+            let listen = std::net::SocketAddr::from(([127, 0, 0, 1], args.port));
+            eprintln!(
+                "Replaying {} at http://{}/vnc.html?autoconnect=true (Ctrl+C to stop)",
+                args.file.display(),
+                listen
+            );
+            cmux_sandbox::vnc_recording::serve_replay(
+                listen,
+                &args.file,
+                args.novnc_dir,
+                args.speed,
+            )
+            .await?;
+        }
         Command::Start(args) => {
             handle_server_start(&args).await?;
         }
@@ -1093,6 +1246,17 @@ async fn run() -> anyhow::Result<()> {
                 print_notifications(&notifications);
             }
         }
+        Command::Demo => {
+            cmux_sandbox::run_demo_tui()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
+        }
+        Command::Completions { shell } => {
+            use clap::CommandFactory;
+            let mut cmd = Cli::command();
+            let name = if is_dmux() { "dmux" } else { "cmux" };
+            clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+        }
         Command::Chat(args) => {
             if args.demo {
                 cmux_sandbox::run_demo_tui()
@@ -1114,6 +1278,7 @@ async fn run() -> anyhow::Result<()> {
                     read_only_paths: vec![],
                     tmpfs: vec![],
                     env: build_default_env_vars(),
+                    resources: None,
                 };
                 let url = format!("{}/sandboxes", cli.base_url.trim_end_matches('/'));
                 let response = client.post(url).json(&body).send().await?;
@@ -1168,11 +1333,66 @@ async fn run() -> anyhow::Result<()> {
                     sandbox_id,
                     provider,
                     Some(workspace_status_rx),
+                    cli.auth_token,
                 )
                 .await
                 .map_err(|e| anyhow::anyhow!(e))?;
             }
         }
+        Command::Acp(args) => match args.command {
+            AcpCommand::Run(args) => {
+                check_server_reachable(&client, &cli.base_url).await?;
+                let provider = args
+                    .provider
+                    .or_else(cmux_sandbox::load_last_provider)
+                    .unwrap_or_default();
+
+                let sandbox_id = match args.sandbox {
+                    Some(id) => id,
+                    None => {
+                        let body = CreateSandboxRequest {
+                            name: Some("acp-run".into()),
+                            workspace: None,
+                            tab_id: Some(Uuid::new_v4().to_string()),
+                            read_only_paths: vec![],
+                            tmpfs: vec![],
+                            env: build_default_env_vars(),
+                            resources: None,
+                        };
+                        let url = format!("{}/sandboxes", cli.base_url.trim_end_matches('/'));
+                        let response = client.post(url).json(&body).send().await?;
+                        let summary: SandboxSummary = parse_response(response).await?;
+                        eprintln!("Created sandbox {}", summary.id);
+                        let sandbox_id = summary.id.to_string();
+
+                        // Unlike the chat, the prompt can't start until the files are there.
+                        upload_workspace_directory(
+                            &client,
+                            &cli.base_url,
+                            &sandbox_id,
+                            std::env::current_dir()?,
+                        )
+                        .await?;
+                        upload_sync_files(&client, &cli.base_url, &sandbox_id, false).await?;
+                        sandbox_id
+                    }
+                };
+
+                let stop_reason = cmux_sandbox::run_headless(
+                    cli.base_url,
+                    sandbox_id,
+                    cmux_sandbox::HeadlessOptions {
+                        prompt: args.prompt,
+                        provider,
+                        json: args.json,
+                        permissions: args.permissions,
+                        auth_token: cli.auth_token,
+                    },
+                )
+                .await?;
+                std::process::exit(cmux_sandbox::stop_reason_exit_code(stop_reason));
+            }
+        },
         Command::Auth(args) => match args.command {
             AuthCommand::Login => {
                 handle_auth_login().await?;
@@ -1266,6 +1486,7 @@ async fn run() -> anyhow::Result<()> {
                             .collect(),
                         tmpfs: args.tmpfs,
                         env: args.env,
+                        resources: None,
                     };
 
                     let url = format!("{}/sandboxes", cli.base_url.trim_end_matches('/'));
@@ -1294,6 +1515,7 @@ async fn run() -> anyhow::Result<()> {
                         read_only_paths: vec![],
                         tmpfs: vec![],
                         env: build_default_env_vars(),
+                        resources: None,
                     };
                     let url = format!("{}/sandboxes", cli.base_url.trim_end_matches('/'));
                     let response = client.post(url).json(&body).send().await?;
@@ -1445,7 +1667,7 @@ async fn handle_ssh(base_url: &str, id: &str) -> anyhow::Result<()> {
         ws_url, id, cols, rows
     );
 
-    let (ws_stream, _) = connect_async(url).await?;
+    let (ws_stream, _) = connect_async(websocket_request(&url)?).await?;
     eprintln!("Connected to sandbox shell. Press Ctrl+D to exit.");
 
     let _guard = RawModeGuard::new()?;
@@ -2917,7 +3139,7 @@ where
         .to_string();
     let url = format!("{}/sandboxes/{}/proxy?port={}", ws_url, id, port);
 
-    let (ws_stream, _) = connect_async(url).await?;
+    let (ws_stream, _) = connect_async(websocket_request(&url)?).await?;
     let (mut ws_write, mut ws_read) = ws_stream.split();
     let (mut sock_read, mut sock_write) = tokio::io::split(socket);
 
@@ -5230,6 +5452,7 @@ async fn handle_esctest(client: &Client, base_url: &str, args: EsctestArgs) -> a
         read_only_paths: vec![],
         tmpfs: vec![],
         env: build_default_env_vars(),
+        resources: None,
     };
     let url = format!("{}/sandboxes", base_url.trim_end_matches('/'));
     let response = client.post(url).json(&body).send().await?;
@@ -5336,7 +5559,7 @@ async fn run_esctest_via_attach(
         .to_string();
     let url = format!("{}/sandboxes/{}/attach?cols=80&rows=25", ws_url, sandbox_id);
 
-    let (ws_stream, _) = connect_async(&url).await?;
+    let (ws_stream, _) = connect_async(websocket_request(&url)?).await?;
     let (mut write, mut read) = ws_stream.split();
 
     // Build the esctest2 command
@@ -1,8 +1,11 @@
 use async_trait::async_trait;
 use axum::body::Body;
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use cmux_sandbox::auth::{MuxScope, SandboxOwners};
 use cmux_sandbox::bubblewrap::BubblewrapService;
 use cmux_sandbox::build_router;
+use cmux_sandbox::config::SandboxConfig;
 use cmux_sandbox::errors::{SandboxError, SandboxResult};
 use cmux_sandbox::models::{
     BridgeRequest, BridgeResponse, CreateSandboxRequest, ExecRequest, ExecResponse, GhRequest,
@@ -10,7 +13,8 @@ use cmux_sandbox::models::{
 };
 use cmux_sandbox::notifications::NotificationStore;
 use cmux_sandbox::service::{GhAuthCache, GhResponseRegistry, HostEventSender, SandboxService};
-use cmux_sandbox::DEFAULT_HTTP_PORT;
+use cmux_sandbox::shutdown::{wait_for_signal, ShutdownState};
+use std::future::IntoFuture;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -19,49 +23,161 @@ use tokio::net::{TcpSocket, UnixListener};
 use tokio::time::{sleep, Duration};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+/// Which team created each sandbox, kept in the data directory.
+const SANDBOX_OWNERS_FILE: &str = "sandbox-owners.json";
+
+/// Flags without a value are left to the config file and then to
+/// `SandboxConfig::default()`; `--print-config` shows the result.
 #[derive(Parser, Debug)]
 #[command(name = "cmux-sandboxd", author, version)]
 struct Options {
+    /// TOML file with any of the options below (underscored names); flags and
+    /// environment variables take precedence over it
+    #[arg(long, env = "CMUX_SANDBOX_CONFIG")]
+    config: Option<PathBuf>,
+    /// Print the effective configuration, with secrets redacted, and exit
+    #[arg(long)]
+    print_config: bool,
     /// Address the HTTP server binds to
-    #[arg(long, default_value = "0.0.0.0")]
-    bind: String,
+    #[arg(long)]
+    bind: Option<String>,
     /// Port for the HTTP server
-    #[arg(long, default_value_t = DEFAULT_HTTP_PORT, env = "CMUX_SANDBOX_PORT")]
-    port: u16,
+    #[arg(long, env = "CMUX_SANDBOX_PORT")]
+    port: Option<u16>,
     /// Directory used for sandbox workspaces
-    #[arg(long, default_value = "/var/lib/cmux/sandboxes")]
-    data_dir: PathBuf,
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
     /// Directory used for logs
-    #[arg(long, default_value = "/var/log/cmux", env = "CMUX_SANDBOX_LOG_DIR")]
-    log_dir: PathBuf,
+    #[arg(long, env = "CMUX_SANDBOX_LOG_DIR")]
+    log_dir: Option<PathBuf>,
     /// Path for the Unix socket used by sandboxes for bridge commands (open-url, gh, etc.)
-    #[arg(
-        long,
-        default_value = "/var/run/cmux/bridge.sock",
-        env = "CMUX_BRIDGE_SOCKET"
-    )]
-    bridge_socket: PathBuf,
+    #[arg(long, env = "CMUX_BRIDGE_SOCKET")]
+    bridge_socket: Option<PathBuf>,
     /// Enable timing instrumentation (logs to timing.log)
     #[arg(long, env = "CMUX_TIMING")]
     timing: bool,
+    /// Close noVNC sessions after this many minutes without keyboard/pointer input (0 disables)
+    #[arg(long, env = "CMUX_VNC_IDLE_TIMEOUT_MINUTES")]
+    vnc_idle_timeout_minutes: Option<u64>,
+    /// Also run Xvnc on a Unix socket and proxy noVNC through it instead of the sandbox network
+    #[arg(long, env = "CMUX_VNC_UNIX_SOCKET")]
+    vnc_unix_socket: bool,
+    /// Record noVNC sessions into this directory for later replay (`cmux vnc-replay`)
+    #[arg(long, env = "CMUX_VNC_RECORD_DIR")]
+    vnc_record_dir: Option<PathBuf>,
+    /// On shutdown, keep serving (with /healthz reporting 503) for this many
+    /// seconds before refusing new connections, so load balancers notice
+    #[arg(long, env = "CMUX_SHUTDOWN_DRAIN_SECS")]
+    shutdown_drain_secs: Option<u64>,
+    /// On shutdown, how long open connections and sandbox processes get to
+    /// finish before they're closed or killed
+    #[arg(long, env = "CMUX_SHUTDOWN_GRACE_SECS")]
+    shutdown_grace_secs: Option<u64>,
+    /// Bearer token accepted by the API, as `TOKEN` (admin) or `TEAM:TOKEN`
+    /// (scoped to that team's sandboxes). Repeatable; unset disables auth
+    #[arg(
+        long = "auth-token",
+        env = "CMUX_SANDBOX_AUTH_TOKENS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    auth_tokens: Vec<String>,
+    /// Secret for HS256 JWTs whose `team` claim scopes them to that team's sandboxes
+    #[arg(long, env = "CMUX_SANDBOX_JWT_SECRET", hide_env_values = true)]
+    jwt_secret: Option<String>,
+}
+
+/// Layer flags and environment variables that were actually given over the
+/// `--config` file (or the defaults when there is none).
+fn load_config(mut options: Options, matches: &ArgMatches) -> anyhow::Result<SandboxConfig> {
+    let mut config = match &options.config {
+        Some(path) => SandboxConfig::load(path)?,
+        None => SandboxConfig::default(),
+    };
+    options.auth_tokens.retain(|spec| !spec.is_empty());
+    options.jwt_secret = options.jwt_secret.filter(|secret| !secret.is_empty());
+    let given = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+    // Valued flags have no clap default, so they're only set when given.
+    macro_rules! overlay_values {
+        ($($field:ident),* $(,)?) => {
+            $(if let Some(value) = options.$field {
+                config.$field = value;
+            })*
+        };
+    }
+    overlay_values!(
+        bind,
+        port,
+        data_dir,
+        log_dir,
+        bridge_socket,
+        vnc_idle_timeout_minutes,
+        shutdown_drain_secs,
+        shutdown_grace_secs,
+    );
+    // Switches, lists and optional settings always have a value; an empty
+    // `CMUX_SANDBOX_JWT_SECRET` still clears the file's secret.
+    macro_rules! overlay {
+        ($($field:ident),* $(,)?) => {
+            $(if given(stringify!($field)) {
+                config.$field = options.$field;
+            })*
+        };
+    }
+    overlay!(
+        timing,
+        vnc_unix_socket,
+        vnc_record_dir,
+        auth_tokens,
+        jwt_secret,
+    );
+    config.validate()?;
+    Ok(config)
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let options = Options::parse();
-    let _guard = init_tracing(&options.log_dir);
+    let matches = Options::command().get_matches();
+    let options = Options::from_arg_matches(&matches)?;
+    let print_config = options.print_config;
+    let config = load_config(options, &matches)?;
+    if print_config {
+        print!("{}", config.redacted().to_toml()?);
+        return Ok(());
+    }
+    let _guard = init_tracing(&config.log_dir);
 
     // Enable timing if requested
-    if options.timing {
+    if config.timing {
         cmux_sandbox::timing::enable_timing();
-        let timing_log = options.log_dir.join("timing.log");
+        let timing_log = config.log_dir.join("timing.log");
         if let Some(path) = timing_log.to_str() {
             cmux_sandbox::timing::set_log_path(path);
         }
         tracing::info!("timing instrumentation enabled");
     }
 
-    run_server(options).await;
+    let vnc_idle_timeout = match config.vnc_idle_timeout_minutes {
+        0 => None,
+        minutes => Some(
+            minutes
+                .checked_mul(60)
+                .map(Duration::from_secs)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("vnc_idle_timeout_minutes is out of range: {minutes}")
+                })?,
+        ),
+    };
+    cmux_sandbox::vnc_proxy::set_idle_timeout(vnc_idle_timeout);
+    cmux_sandbox::vnc_proxy::set_use_unix_socket(config.vnc_unix_socket);
+    cmux_sandbox::vnc_recording::set_recording_dir(config.vnc_record_dir.clone());
+
+    run_server(config).await;
 
     Ok(())
 }
@@ -101,18 +217,13 @@ fn init_tracing(log_dir: &PathBuf) -> Option<tracing_appender::non_blocking::Wor
     Some(guard)
 }
 
-async fn shutdown_signal() {
-    if let Err(error) = tokio::signal::ctrl_c().await {
-        tracing::error!("failed to listen for shutdown signal: {error}");
-    }
-    tracing::info!("shutdown signal received");
-}
-
-async fn run_server(options: Options) {
+async fn run_server(config: SandboxConfig) {
     use std::collections::HashMap;
     use tokio::sync::Mutex;
 
-    let bind_ip = parse_bind_ip(&options.bind);
+    let bind_ip = config
+        .bind_ip()
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
     // Broadcast channel for host-directed events (open-url, notifications, gh)
     let (host_event_tx, _) = tokio::sync::broadcast::channel::<HostEvent>(64);
 
@@ -123,17 +234,46 @@ async fn run_server(options: Options) {
     let gh_auth_cache: GhAuthCache = Arc::new(Mutex::new(None));
     let notifications = NotificationStore::new();
 
-    let service = build_service(&options).await;
+    let shutdown = ShutdownState::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            wait_for_signal().await;
+            tracing::info!("shutdown signal received; draining");
+            shutdown.begin();
+        }
+    });
+    let drain_delay = Duration::from_secs(config.shutdown_drain_secs);
+    let grace = Duration::from_secs(config.shutdown_grace_secs);
+
+    cmux_sandbox::metrics::spawn_lifecycle_recorder();
+    let auth = config.auth();
+    if !auth.is_enabled() {
+        tracing::warn!("no --auth-token or --jwt-secret configured; API is unauthenticated");
+    }
+    let service = build_service(&config, host_event_tx.clone(), notifications.clone()).await;
+    let owners_path = config.data_dir.join(SANDBOX_OWNERS_FILE);
+    let owners = SandboxOwners::load(owners_path.clone()).unwrap_or_else(|error| {
+        tracing::error!(
+            %error,
+            path = %owners_path.display(),
+            "failed to load sandbox owners; teams lose access to sandboxes created before"
+        );
+        SandboxOwners::new()
+    });
     let app = build_router(
-        service,
+        service.clone(),
         host_event_tx.clone(),
         gh_responses.clone(),
         gh_auth_cache.clone(),
         notifications.clone(),
+        shutdown.clone(),
+        auth,
+        owners,
     );
 
     // Start the unified Unix socket listener for bridge requests from sandboxes
-    let socket_path = options.bridge_socket.clone();
+    let socket_path = config.bridge_socket.clone();
     let bridge_host_events = host_event_tx.clone();
     let bridge_gh_responses = gh_responses.clone();
     let bridge_gh_auth_cache = gh_auth_cache.clone();
@@ -152,10 +292,14 @@ async fn run_server(options: Options) {
         }
     });
 
-    let addr = SocketAddr::new(bind_ip, options.port);
+    let addr = SocketAddr::new(bind_ip, config.port);
     let retry_delay = Duration::from_secs(5);
 
     loop {
+        if shutdown.is_draining() {
+            break;
+        }
+
         // Create a TcpSocket with TCP_NODELAY enabled for low-latency connections
         let socket = match if addr.is_ipv4() {
             TcpSocket::new_v4()
@@ -192,16 +336,38 @@ async fn run_server(options: Options) {
                 tracing::info!("HTTP/1.1 and HTTP/2 are enabled");
                 tracing::info!("TCP_NODELAY enabled for low-latency connections");
 
-                match axum::serve(listener, app.clone())
-                    .with_graceful_shutdown(shutdown_signal())
-                    .await
-                {
-                    Ok(()) => {
-                        tracing::info!("server shut down gracefully");
-                        break;
+                let stop_accepting = {
+                    let shutdown = shutdown.clone();
+                    async move {
+                        shutdown.draining().await;
+                        sleep(drain_delay).await;
                     }
-                    Err(error) => {
-                        tracing::error!(?error, "server error; restarting");
+                };
+                // Long-lived WebSockets would hold graceful shutdown open forever.
+                let grace_expired = {
+                    let shutdown = shutdown.clone();
+                    async move {
+                        shutdown.draining().await;
+                        sleep(drain_delay + grace).await;
+                    }
+                };
+                let serve = axum::serve(listener, app.clone())
+                    .with_graceful_shutdown(stop_accepting)
+                    .into_future();
+
+                tokio::select! {
+                    result = serve => match result {
+                        Ok(()) => {
+                            tracing::info!("server shut down gracefully");
+                            break;
+                        }
+                        Err(error) => {
+                            tracing::error!(?error, "server error; restarting");
+                        }
+                    },
+                    _ = grace_expired => {
+                        tracing::warn!("closing connections still open after the grace period");
+                        break;
                     }
                 }
             }
@@ -214,27 +380,25 @@ async fn run_server(options: Options) {
             "retrying server startup in {} seconds",
             retry_delay.as_secs()
         );
-        sleep(retry_delay).await;
-    }
-}
-
-fn parse_bind_ip(bind: &str) -> IpAddr {
-    match bind.parse() {
-        Ok(ip) => ip,
-        Err(error) => {
-            tracing::error!(
-                ?error,
-                %bind,
-                "invalid bind address; defaulting to 0.0.0.0"
-            );
-            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        tokio::select! {
+            _ = sleep(retry_delay) => {}
+            _ = shutdown.draining() => {}
         }
     }
+
+    tracing::info!("stopping sandboxes");
+    service.shutdown(grace).await;
+    let _ = std::fs::remove_file(&config.bridge_socket);
+    tracing::info!("shutdown complete");
 }
 
-async fn build_service(options: &Options) -> Arc<dyn SandboxService> {
-    match BubblewrapService::new(options.data_dir.clone(), options.port).await {
-        Ok(service) => Arc::new(service),
+async fn build_service(
+    config: &SandboxConfig,
+    host_events: HostEventSender,
+    notifications: NotificationStore,
+) -> Arc<dyn SandboxService> {
+    match BubblewrapService::new(config.data_dir.clone(), config.port).await {
+        Ok(service) => Arc::new(service.with_notifications(host_events, notifications)),
         Err(error) => {
             tracing::error!(
                 ?error,
@@ -641,6 +805,7 @@ impl SandboxService for UnavailableSandboxService {
         _host_event_rx: cmux_sandbox::service::HostEventReceiver,
         _gh_responses: GhResponseRegistry,
         _gh_auth_cache: GhAuthCache,
+        _scope: MuxScope,
     ) -> SandboxResult<()> {
         Err(self.error("mux attach"))
     }
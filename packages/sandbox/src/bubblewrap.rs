@@ -1,21 +1,26 @@
+use crate::auth::MuxScope;
+use crate::cgroups::{command_in, pty_command_in, SandboxCgroup};
 use crate::errors::{SandboxError, SandboxResult};
+use crate::events::{self, LifecycleEventKind, ProcessScope};
 use crate::ip_pool::{IpLease, IpPool};
 use crate::models::{
     AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, EnvVar, ExecRequest, ExecResponse,
-    HostEvent, MuxClientMessage, MuxServerMessage, PruneRequest, PruneResponse, PrunedItem,
-    PtySessionId, SandboxDisplay, SandboxNetwork, SandboxStatus, SandboxSummary, ServiceReadiness,
+    HostEvent, MuxClientMessage, MuxServerMessage, NotificationLevel, NotificationRequest,
+    PruneRequest, PruneResponse, PrunedItem, PtySessionId, ResourceLimits, SandboxDisplay,
+    SandboxNetwork, SandboxStatus, SandboxSummary, ServiceReadiness,
 };
 use crate::mux::terminal::{DaFilter, VirtualTerminal};
-use crate::service::SandboxService;
+use crate::notifications::NotificationStore;
+use crate::service::{HostEventSender, SandboxService};
 use crate::timing::TimingReport;
 use async_trait::async_trait;
 use axum::body::Body;
 use axum::extract::ws::{Message, WebSocket};
 use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt};
-use portable_pty::{CommandBuilder, MasterPty, NativePtySystem, PtySize, PtySystem};
+use portable_pty::{MasterPty, NativePtySystem, PtySize, PtySystem};
 use serde::Deserialize;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
 use std::net::Ipv4Addr;
 use std::path::{Path, PathBuf};
@@ -36,7 +41,8 @@ const NETWORK_BASE: Ipv4Addr = Ipv4Addr::new(10, 201, 0, 0);
 const HOST_IF_PREFIX: &str = "vethh";
 const NS_IF_PREFIX: &str = "vethn";
 const DOCKER_CONTAINER_SOCKET: &str = "/run/docker.sock";
-const SANDBOX_WORKSPACE_MOUNT: &str = "/workspace";
+pub(crate) const SANDBOX_WORKSPACE_MOUNT: &str = "/workspace";
+const OOM_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Handle for a multiplexed PTY session.
 struct PtySessionHandle {
@@ -69,6 +75,7 @@ struct SandboxHandle {
     correlation_id: Option<String>,
     /// Display configuration for isolated X11/VNC desktop
     display: Option<SandboxDisplay>,
+    resources: Option<ResourceLimits>,
 }
 
 #[derive(Clone)]
@@ -77,6 +84,8 @@ struct SandboxEntry {
     child: Arc<Mutex<Child>>,
     inner_pid: u32,
     env: Vec<EnvVar>,
+    /// cgroup holding the sandbox's processes when resource limits were requested
+    cgroup: Option<Arc<SandboxCgroup>>,
 }
 
 #[derive(Clone)]
@@ -124,6 +133,8 @@ pub struct BubblewrapService {
     /// Service readiness tracking per sandbox.
     /// Uses watch channels so multiple waiters can subscribe efficiently.
     readiness: Mutex<HashMap<Uuid, ReadinessWatch>>,
+    /// Where sandbox alerts (e.g. OOM kills) are reported, if anywhere.
+    notifications: Option<(HostEventSender, NotificationStore)>,
 }
 
 fn nsenter_args(pid: u32, workdir: Option<&str>, command: &[String]) -> Vec<String> {
@@ -156,6 +167,7 @@ async fn start_x11_stack_background(
     inner_pid: u32,
     display_number: u16,
     vnc_port: u16,
+    vnc_socket: Option<&str>,
     cdp_port: u16,
 ) -> Result<(), String> {
     use tokio::time::timeout;
@@ -198,12 +210,15 @@ async fn start_x11_stack_background(
     }
 
     // Start Xvnc (TigerVNC)
+    let unix_socket_arg = vnc_socket
+        .map(|path| format!(" -rfbunixpath {}", path))
+        .unwrap_or_default();
     let xvnc_cmd = vec![
         "/bin/sh".to_string(),
         "-c".to_string(),
         format!(
-            "Xvnc {} -geometry 1920x1080 -depth 24 -rfbport {} -SecurityTypes None -AlwaysShared -AcceptKeyEvents -AcceptPointerEvents &",
-            x11_display, vnc_port
+            "Xvnc {} -geometry 1920x1080 -depth 24 -rfbport {}{} -SecurityTypes None -AlwaysShared -AcceptKeyEvents -AcceptPointerEvents &",
+            x11_display, vnc_port, unix_socket_arg
         ),
     ];
     run_nsenter(nsenter_path, inner_pid, &xvnc_cmd, cmd_timeout, "Xvnc").await?;
@@ -286,6 +301,7 @@ async fn start_vscode_background(
     inner_pid: u32,
     vscode_port: u16,
     workspace_path: &str,
+    cgroup: Option<&SandboxCgroup>,
 ) -> Result<(), String> {
     use tokio::time::timeout;
     let cmd_timeout = Duration::from_secs(10);
@@ -317,7 +333,7 @@ async fn start_vscode_background(
 
     let result = timeout(
         cmd_timeout,
-        Command::new(nsenter_path)
+        command_in(cgroup, nsenter_path)
             .args(nsenter_args(inner_pid, None, &vscode_cmd))
             .output(),
     )
@@ -372,6 +388,7 @@ async fn start_cmux_pty_background(
     nsenter_path: &str,
     inner_pid: u32,
     pty_port: u16,
+    cgroup: Option<&SandboxCgroup>,
 ) -> Result<(), String> {
     use tokio::time::timeout;
     let cmd_timeout = Duration::from_secs(10);
@@ -391,7 +408,7 @@ async fn start_cmux_pty_background(
 
     let result = timeout(
         cmd_timeout,
-        Command::new(nsenter_path)
+        command_in(cgroup, nsenter_path)
             .args(nsenter_args(inner_pid, None, &pty_cmd))
             .output(),
     )
@@ -460,12 +477,67 @@ impl BubblewrapService {
             next_index: AtomicUsize::new(0),
             docker,
             readiness: Mutex::new(HashMap::new()),
+            notifications: None,
         };
 
         service.setup_host_network().await?;
         Ok(service)
     }
 
+    /// Report sandbox alerts, such as OOM kills under resource limits, as
+    /// notifications.
+    pub fn with_notifications(
+        mut self,
+        host_events: HostEventSender,
+        notifications: NotificationStore,
+    ) -> Self {
+        self.notifications = Some((host_events, notifications));
+        self
+    }
+
+    /// Poll the cgroup's OOM kill counter until the sandbox is gone, reporting
+    /// each new kill.
+    fn watch_oom_kills(&self, id: Uuid, cgroup: Arc<SandboxCgroup>) {
+        let notifications = self.notifications.clone();
+        tokio::spawn(async move {
+            let mut reported = 0;
+            loop {
+                sleep(OOM_POLL_INTERVAL).await;
+                // The cgroup disappears when the sandbox is deleted.
+                let Ok(kills) = cgroup.oom_kills().await else {
+                    return;
+                };
+                if kills <= reported {
+                    continue;
+                }
+                let message = format!(
+                    "Sandbox {id} hit its memory limit; {} process(es) were OOM-killed",
+                    kills - reported
+                );
+                reported = kills;
+                warn!("{message}");
+                if let Some((host_events, store)) = &notifications {
+                    store
+                        .record(
+                            message.clone(),
+                            NotificationLevel::Error,
+                            Some(id.to_string()),
+                            None,
+                            None,
+                        )
+                        .await;
+                    let _ = host_events.send(HostEvent::Notification(NotificationRequest {
+                        message,
+                        level: NotificationLevel::Error,
+                        sandbox_id: Some(id.to_string()),
+                        tab_id: None,
+                        pane_id: None,
+                    }));
+                }
+            }
+        });
+    }
+
     async fn setup_host_network(&self) -> SandboxResult<()> {
         // Enable IP forwarding
         if let Err(e) = run_command("sysctl", &["-w", "net.ipv4.ip_forward=1"]).await {
@@ -585,6 +657,26 @@ impl BubblewrapService {
         matched.ok_or_else(|| SandboxError::InvalidRequest(format!("sandbox not found: {id_str}")))
     }
 
+    /// Whether a mux client in `scope` should see `event`. Teams only get
+    /// events from their own sandboxes, never host-wide ones.
+    async fn mux_event_visible(&self, scope: &MuxScope, event: &HostEvent) -> bool {
+        if scope.is_admin() {
+            return true;
+        }
+        let sandbox_id = match event {
+            HostEvent::OpenUrl(request) => request.sandbox_id.as_deref(),
+            HostEvent::Notification(notification) => notification.sandbox_id.as_deref(),
+            HostEvent::GhRequest(request) => request.sandbox_id.as_deref(),
+        };
+        let Some(sandbox_id) = sandbox_id else {
+            return false;
+        };
+        match self.resolve_id(sandbox_id).await {
+            Ok(id) => scope.allows(&id).await,
+            Err(_) => false,
+        }
+    }
+
     async fn setup_dns(&self, etc_merged: &Path) -> SandboxResult<()> {
         let resolv_conf_path = etc_merged.join("resolv.conf");
 
@@ -832,6 +924,7 @@ fi
         _id: &Uuid,
         _lease: &IpLease,
         index: usize,
+        cgroup: Option<&SandboxCgroup>,
     ) -> SandboxResult<(Child, u32)> {
         // Prepare system directories for the sandbox
         fs::create_dir_all(system_dir).await?;
@@ -874,7 +967,7 @@ fi
 
         let docker_socket_host = path_to_string(self.docker.host_socket(), "docker socket")?;
 
-        let mut command = Command::new(&self.bubblewrap_path);
+        let mut command = command_in(cgroup, &self.bubblewrap_path);
         command.kill_on_drop(true);
         command.stdout(Stdio::piped());
         command.args([
@@ -1084,6 +1177,42 @@ fi
         })
     }
 
+    /// SIGTERM everything in a sandbox except its init, give it `grace` to
+    /// exit, then kill the sandbox and release its network and cgroup.
+    async fn stop_for_shutdown(&self, id: Uuid, entry: SandboxEntry, grace: Duration) {
+        // Inside the PID namespace `kill -1` reaches every process but init and
+        // the caller, and `kill -0 -1` succeeds while any of them are left.
+        let in_sandbox = |script: &str| {
+            let script = ["/bin/sh".to_string(), "-c".to_string(), script.to_string()];
+            let mut command = Command::new(&self.nsenter_path);
+            command
+                .args(nsenter_args(entry.inner_pid, None, &script))
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            command
+        };
+
+        let _ = in_sandbox("kill -TERM -1").status().await;
+        let deadline = tokio::time::Instant::now() + grace;
+        while tokio::time::Instant::now() < deadline {
+            match in_sandbox("kill -0 -1").status().await {
+                Ok(status) if status.success() => sleep(Duration::from_millis(200)).await,
+                _ => break,
+            }
+        }
+
+        {
+            let mut child = entry.child.lock().await;
+            let _ = child.kill().await;
+        }
+        self.ip_pool.lock().await.release(&entry.handle.lease);
+        self.teardown_network(&entry.handle.network).await;
+        if let Some(cgroup) = &entry.cgroup {
+            cgroup.remove().await;
+        }
+        info!("stopped sandbox {id} for shutdown");
+    }
+
     async fn teardown_network(&self, network: &SandboxNetwork) {
         let delete_result =
             run_command(&self.ip_path, &["link", "del", &network.host_interface]).await;
@@ -1119,6 +1248,7 @@ fi
         &self,
         session_id: PtySessionId,
         inner_pid: u32,
+        cgroup: Option<&SandboxCgroup>,
         command: Vec<String>,
         cols: u16,
         rows: u16,
@@ -1137,7 +1267,7 @@ fi
             })
             .map_err(|e| SandboxError::Internal(format!("failed to open pty: {e}")))?;
 
-        let mut cmd = CommandBuilder::new(&self.nsenter_path);
+        let mut cmd = pty_command_in(cgroup, &self.nsenter_path);
         cmd.args(nsenter_args(inner_pid, None, &command));
         cmd.env("HOME", "/root");
         cmd.env("SHELL", "/bin/zsh");
@@ -1380,11 +1510,24 @@ impl SandboxService for BubblewrapService {
 
         let system_dir = self.workspace_root.join(id.to_string()).join("system");
 
+        let cgroup = match request.resources {
+            Some(ref limits) => Some(Arc::new(SandboxCgroup::create(&id, limits).await?)),
+            None => None,
+        };
+
         // Phase: IP allocation
         let ip_timer = crate::timing::Timer::new("ip_allocation");
         let lease = {
             let mut pool = self.ip_pool.lock().await;
-            pool.allocate()?
+            match pool.allocate() {
+                Ok(lease) => lease,
+                Err(error) => {
+                    if let Some(cgroup) = &cgroup {
+                        cgroup.remove().await;
+                    }
+                    return Err(error);
+                }
+            }
         };
         timing.record_timer("ip_allocation", ip_timer);
 
@@ -1411,6 +1554,7 @@ impl SandboxService for BubblewrapService {
             &id,
             &lease,
             index,
+            cgroup.as_deref(),
         );
 
         let net_prepare_fut = self.prepare_network_host_side(&host_if, &ns_if, &host_cidr);
@@ -1424,6 +1568,9 @@ impl SandboxService for BubblewrapService {
                 // Clean up network if it was created
                 let _ = run_command(&self.ip_path, &["link", "del", &host_if]).await;
                 cleanup_overlays(&system_dir).await;
+                if let Some(cgroup) = &cgroup {
+                    cgroup.remove().await;
+                }
                 let mut pool = self.ip_pool.lock().await;
                 pool.release(&lease);
                 return Err(error);
@@ -1436,6 +1583,9 @@ impl SandboxService for BubblewrapService {
             // Clean up veth pair if it was partially created
             let _ = run_command(&self.ip_path, &["link", "del", &host_if]).await;
             cleanup_overlays(&system_dir).await;
+            if let Some(cgroup) = &cgroup {
+                cgroup.remove().await;
+            }
             let mut pool = self.ip_pool.lock().await;
             pool.release(&lease);
             return Err(error);
@@ -1454,6 +1604,9 @@ impl SandboxService for BubblewrapService {
                 let _ = child.kill().await;
                 let _ = run_command(&self.ip_path, &["link", "del", &host_if]).await;
                 cleanup_overlays(&system_dir).await;
+                if let Some(cgroup) = &cgroup {
+                    cgroup.remove().await;
+                }
                 {
                     let mut pool = self.ip_pool.lock().await;
                     pool.release(&lease);
@@ -1473,6 +1626,13 @@ impl SandboxService for BubblewrapService {
         let cdp_port = 39381_u16; // Fixed port, accessed via subdomain routing
         let vscode_port = 39378_u16; // Fixed port for cmux-code
         let pty_port = 39383_u16; // Fixed port for cmux-pty
+                                  // Xvnc's Unix socket inside the sandbox, reached from here through the
+                                  // sandbox's root in /proc
+        let vnc_socket_inner = crate::vnc_proxy::use_unix_socket()
+            .then(|| format!("/tmp/.cmux-vnc-{}.sock", display_number));
+        let vnc_socket = vnc_socket_inner
+            .as_ref()
+            .map(|path| format!("/proc/{}/root{}", inner_pid, path));
 
         // Display config is set immediately (ports are known upfront)
         // Services start in background - use await_services_ready to wait for VNC/VS Code/PTY
@@ -1483,6 +1643,7 @@ impl SandboxService for BubblewrapService {
             cdp_port,
             vscode_port,
             pty_port,
+            vnc_socket,
         });
 
         // Create readiness watch channel for this sandbox
@@ -1498,6 +1659,7 @@ impl SandboxService for BubblewrapService {
             let nsenter_path = self.nsenter_path.clone();
             let sandbox_id = id;
             let readiness = self.readiness.lock().await.get(&id).cloned();
+            let services_cgroup = cgroup.clone();
             let workspace_path = SANDBOX_WORKSPACE_MOUNT;
 
             tokio::spawn(async move {
@@ -1507,6 +1669,7 @@ impl SandboxService for BubblewrapService {
                     inner_pid,
                     display_number,
                     vnc_port,
+                    vnc_socket_inner.as_deref(),
                     cdp_port,
                 )
                 .await;
@@ -1541,8 +1704,13 @@ impl SandboxService for BubblewrapService {
                 }
 
                 // Start cmux-pty FIRST (PTY server) - must be ready before VS Code extension activates
-                let pty_result =
-                    start_cmux_pty_background(&nsenter_path, inner_pid, pty_port).await;
+                let pty_result = start_cmux_pty_background(
+                    &nsenter_path,
+                    inner_pid,
+                    pty_port,
+                    services_cgroup.as_deref(),
+                )
+                .await;
 
                 let pty_ready = match pty_result {
                     Ok(()) => {
@@ -1573,9 +1741,14 @@ impl SandboxService for BubblewrapService {
                 }
 
                 // Start cmux-code (VS Code server) AFTER cmux-pty is ready
-                let vscode_result =
-                    start_vscode_background(&nsenter_path, inner_pid, vscode_port, workspace_path)
-                        .await;
+                let vscode_result = start_vscode_background(
+                    &nsenter_path,
+                    inner_pid,
+                    vscode_port,
+                    workspace_path,
+                    services_cgroup.as_deref(),
+                )
+                .await;
 
                 let vscode_ready = match vscode_result {
                     Ok(()) => {
@@ -1617,13 +1790,19 @@ impl SandboxService for BubblewrapService {
             lease,
             correlation_id: request.tab_id.clone(),
             display,
+            resources: request.resources.clone(),
         };
 
+        if let Some(cgroup) = &cgroup {
+            self.watch_oom_kills(id, cgroup.clone());
+        }
+
         let entry = SandboxEntry {
             handle,
             child: Arc::new(Mutex::new(child)),
             inner_pid,
             env: effective_env,
+            cgroup,
         };
 
         // Phase: finalize
@@ -1636,6 +1815,10 @@ impl SandboxService for BubblewrapService {
         let mut sandboxes = self.sandboxes.lock().await;
         sandboxes.insert(id, entry);
         timing.record_timer("finalize", finalize_timer);
+        events::publish(LifecycleEventKind::SandboxCreated {
+            sandbox_id: id,
+            name: summary.name.clone(),
+        });
 
         info!("created sandbox {id}");
         timing.finish();
@@ -1695,7 +1878,7 @@ impl SandboxService for BubblewrapService {
         }
         .ok_or(SandboxError::NotFound(id))?;
 
-        let mut command = Command::new(&self.nsenter_path);
+        let mut command = command_in(entry.cgroup.as_deref(), &self.nsenter_path);
         for env in &entry.env {
             command.env(&env.key, &env.value);
         }
@@ -1711,7 +1894,17 @@ impl SandboxService for BubblewrapService {
         ));
 
         command.kill_on_drop(true);
-        let output = command.output().await?;
+        events::publish(LifecycleEventKind::ProcessSpawned {
+            sandbox_id: id,
+            command: exec.command.clone(),
+        });
+        let output = command.output().await;
+        events::publish(LifecycleEventKind::ProcessExited {
+            sandbox_id: id,
+            command: exec.command,
+            exit_code: output.as_ref().ok().and_then(|o| o.status.code()),
+        });
+        let output = output?;
         let exit_code = output.status.code().unwrap_or_default();
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -1744,10 +1937,11 @@ impl SandboxService for BubblewrapService {
             "attaching to sandbox {} with command: {:?} (tty={})",
             id_str, target_command, tty
         );
+        let _process = ProcessScope::start(id, target_command.clone());
 
         if !tty {
             // Non-PTY path: Use standard pipes
-            let mut cmd = Command::new(&self.nsenter_path);
+            let mut cmd = command_in(entry.cgroup.as_deref(), &self.nsenter_path);
             cmd.args(nsenter_args(entry.inner_pid, None, &target_command));
 
             for env in &entry.env {
@@ -1934,7 +2128,7 @@ impl SandboxService for BubblewrapService {
             })
             .map_err(|e| SandboxError::Internal(format!("failed to open pty: {e}")))?;
 
-        let mut cmd = CommandBuilder::new(&self.nsenter_path);
+        let mut cmd = pty_command_in(entry.cgroup.as_deref(), &self.nsenter_path);
 
         cmd.args(nsenter_args(entry.inner_pid, None, &target_command));
         cmd.env("HOME", "/root");
@@ -2163,8 +2357,15 @@ impl SandboxService for BubblewrapService {
         mut host_event_rx: crate::service::HostEventReceiver,
         gh_responses: crate::service::GhResponseRegistry,
         gh_auth_cache: crate::service::GhAuthCache,
+        scope: MuxScope,
     ) -> SandboxResult<()> {
-        info!("mux_attach: new multiplexed connection");
+        info!(
+            "mux_attach: new multiplexed connection team={:?}",
+            scope.team()
+        );
+
+        // gh requests forwarded to a team client; it may only answer those.
+        let mut forwarded_gh_requests: HashSet<String> = HashSet::new();
 
         // Channel for PTY output from all sessions -> WebSocket
         let (output_tx, mut output_rx) = mpsc::unbounded_channel::<MuxServerMessage>();
@@ -2197,6 +2398,9 @@ impl SandboxService for BubblewrapService {
                 msg = ws_read.next() => msg,
                 event = host_event_rx.recv() => {
                     if let Ok(event) = event {
+                        if !self.mux_event_visible(&scope, &event).await {
+                            continue;
+                        }
                         match event {
                             HostEvent::OpenUrl(request) => {
                                 let _ = output_tx.send(MuxServerMessage::OpenUrl {
@@ -2215,6 +2419,9 @@ impl SandboxService for BubblewrapService {
                                 });
                             }
                             HostEvent::GhRequest(request) => {
+                                if !scope.is_admin() {
+                                    forwarded_gh_requests.insert(request.request_id.clone());
+                                }
                                 let _ = output_tx.send(MuxServerMessage::GhRequest {
                                     request_id: request.request_id,
                                     args: request.args,
@@ -2252,10 +2459,12 @@ impl SandboxService for BubblewrapService {
                                     read_only_paths: vec![],
                                     tmpfs: vec![],
                                     env,
+                                    resources: None,
                                 })
                                 .await
                             {
                                 Ok(summary) => {
+                                    scope.record_created(summary.id).await;
                                     let _ =
                                         output_tx.send(MuxServerMessage::SandboxCreated(summary));
                                 }
@@ -2271,7 +2480,13 @@ impl SandboxService for BubblewrapService {
                         MuxClientMessage::ListSandboxes => {
                             debug!("mux_attach: list sandboxes request");
                             match self.list().await {
-                                Ok(sandboxes) => {
+                                Ok(all) => {
+                                    let mut sandboxes = Vec::with_capacity(all.len());
+                                    for sandbox in all {
+                                        if scope.allows(&sandbox.id).await {
+                                            sandboxes.push(sandbox);
+                                        }
+                                    }
                                     let _ =
                                         output_tx.send(MuxServerMessage::SandboxList { sandboxes });
                                 }
@@ -2301,6 +2516,16 @@ impl SandboxService for BubblewrapService {
 
                             // Resolve sandbox
                             let entry = match self.resolve_id(&sandbox_id).await {
+                                Ok(id) if !scope.allows(&id).await => {
+                                    let _ = output_tx.send(MuxServerMessage::Error {
+                                        session_id: Some(session_id),
+                                        message: format!(
+                                            "Sandbox {sandbox_id} does not belong to team {}",
+                                            scope.team().unwrap_or_default()
+                                        ),
+                                    });
+                                    continue;
+                                }
                                 Ok(id) => {
                                     let sandboxes = self.sandboxes.lock().await;
                                     sandboxes.get(&id).cloned()
@@ -2343,6 +2568,7 @@ impl SandboxService for BubblewrapService {
                                 .spawn_mux_pty_session(
                                     session_id.clone(),
                                     entry.inner_pid,
+                                    entry.cgroup.as_deref(),
                                     target_command,
                                     cols,
                                     rows,
@@ -2422,6 +2648,13 @@ impl SandboxService for BubblewrapService {
                                 "mux_attach: gh response request_id={} exit_code={}",
                                 request_id, exit_code
                             );
+                            if !scope.is_admin() && !forwarded_gh_requests.remove(&request_id) {
+                                warn!(
+                                    "mux_attach: ignoring gh response for request_id={} not sent to this client",
+                                    request_id
+                                );
+                                continue;
+                            }
                             // Look up the pending request and send the response
                             let mut registry = gh_responses.lock().await;
                             if let Some(sender) = registry.remove(&request_id) {
@@ -2450,6 +2683,11 @@ impl SandboxService for BubblewrapService {
                             stdout,
                             stderr,
                         } => {
+                            if !scope.is_admin() {
+                                // The cache answers `gh auth status` for every sandbox.
+                                debug!("mux_attach: ignoring gh auth status from team client");
+                                continue;
+                            }
                             debug!("mux_attach: caching gh auth status exit_code={}", exit_code);
                             let mut cache = gh_auth_cache.lock().await;
                             *cache = Some(crate::service::CachedGhAuth {
@@ -2596,6 +2834,11 @@ impl SandboxService for BubblewrapService {
             };
 
             let summary = entry.handle.to_summary(observed_status);
+            events::publish(LifecycleEventKind::SandboxDeleted { sandbox_id: id });
+
+            if let Some(cgroup) = &entry.cgroup {
+                cgroup.remove().await;
+            }
 
             let system_dir = self.workspace_root.join(id.to_string()).join("system");
             cleanup_overlays(&system_dir).await;
@@ -2895,6 +3138,17 @@ impl SandboxService for BubblewrapService {
             }
         }
     }
+
+    async fn shutdown(&self, grace: Duration) {
+        let entries: Vec<(Uuid, SandboxEntry)> = self.sandboxes.lock().await.drain().collect();
+        self.readiness.lock().await.clear();
+        futures::future::join_all(
+            entries
+                .into_iter()
+                .map(|(id, entry)| self.stop_for_shutdown(id, entry, grace)),
+        )
+        .await;
+    }
 }
 
 impl SandboxHandle {
@@ -2909,6 +3163,7 @@ impl SandboxHandle {
             network: self.network.clone(),
             display: self.display.clone(),
             correlation_id: self.correlation_id.clone(),
+            resources: self.resources.clone(),
         }
     }
 }
@@ -0,0 +1,243 @@
+//! cgroup v2 resource limits for sandboxes.
+//!
+//! Each sandbox that asks for limits gets its own cgroup under
+//! `CMUX_CGROUP_ROOT` (default `/sys/fs/cgroup/cmux-sandboxes`). The bubblewrap
+//! process and everything launched into the sandbox through nsenter (exec,
+//! attach, mux terminals, cmux-pty and VS Code) join it before they exec, so
+//! every descendant is accounted for.
+
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use tokio::fs;
+use tokio::process::Command;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::errors::{SandboxError, SandboxResult};
+use crate::models::ResourceLimits;
+
+const CGROUP_ROOT_ENV: &str = "CMUX_CGROUP_ROOT";
+const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup/cmux-sandboxes";
+/// cpu.max period; quotas are expressed against it.
+const CPU_PERIOD_US: u64 = 100_000;
+const CONTROLLERS: &str = "+cpu +memory +pids";
+
+/// Moves the shell into the cgroup whose `cgroup.procs` is `$0`, then execs
+/// the real program so it (and its children) start inside the cgroup.
+const JOIN_SCRIPT: &str = "echo $$ > \"$0\" && exec \"$@\"";
+
+fn cgroup_root() -> PathBuf {
+    std::env::var_os(CGROUP_ROOT_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CGROUP_ROOT))
+}
+
+fn cpu_max(cpu_millis: u32) -> String {
+    let quota = u64::from(cpu_millis) * CPU_PERIOD_US / 1000;
+    format!("{} {}", quota.max(1000), CPU_PERIOD_US)
+}
+
+/// `memory.max` for a limit in MiB. Zero would OOM-kill the sandbox at once.
+fn memory_max(memory_mb: u64) -> SandboxResult<String> {
+    match memory_mb.checked_mul(1024 * 1024) {
+        Some(bytes) if bytes > 0 => Ok(bytes.to_string()),
+        Some(_) => Err(SandboxError::InvalidRequest(
+            "resources.memory_mb must be greater than zero".into(),
+        )),
+        None => Err(SandboxError::InvalidRequest(format!(
+            "resources.memory_mb {memory_mb} is too large"
+        ))),
+    }
+}
+
+/// Reject limits no sandbox could run under, before anything is created.
+pub fn validate_limits(limits: &ResourceLimits) -> SandboxResult<()> {
+    if let Some(memory_mb) = limits.memory_mb {
+        memory_max(memory_mb)?;
+    }
+    if limits.pids == Some(0) {
+        return Err(SandboxError::InvalidRequest(
+            "resources.pids must be greater than zero".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// The `oom_kill` counter from a `memory.events` file.
+fn parse_oom_kills(memory_events: &str) -> Option<u64> {
+    memory_events.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        (key == "oom_kill").then(|| value.trim().parse().ok())?
+    })
+}
+
+/// A sandbox's cgroup. Removed explicitly with [`SandboxCgroup::remove`].
+#[derive(Debug)]
+pub struct SandboxCgroup {
+    path: PathBuf,
+}
+
+impl SandboxCgroup {
+    /// Create the cgroup for sandbox `id` and apply `limits`.
+    pub async fn create(id: &Uuid, limits: &ResourceLimits) -> SandboxResult<Self> {
+        let root = cgroup_root();
+        let unavailable = |e: std::io::Error| {
+            SandboxError::InvalidRequest(format!(
+                "resource limits are unavailable ({}): {e}",
+                root.display()
+            ))
+        };
+        fs::create_dir_all(&root).await.map_err(unavailable)?;
+        // Controllers have to be enabled on the parent before children can use them.
+        fs::write(root.join("cgroup.subtree_control"), CONTROLLERS)
+            .await
+            .map_err(unavailable)?;
+
+        let cgroup = Self {
+            path: root.join(id.to_string()),
+        };
+        fs::create_dir_all(&cgroup.path).await?;
+        if let Err(error) = cgroup.apply(limits).await {
+            cgroup.remove().await;
+            return Err(error);
+        }
+        Ok(cgroup)
+    }
+
+    async fn apply(&self, limits: &ResourceLimits) -> SandboxResult<()> {
+        if let Some(cpu_millis) = limits.cpu_millis {
+            self.write("cpu.max", &cpu_max(cpu_millis)).await?;
+        }
+        if let Some(memory_mb) = limits.memory_mb {
+            self.write("memory.max", &memory_max(memory_mb)?).await?;
+            // Without swap limits the memory limit is easy to sidestep.
+            let _ = self.write("memory.swap.max", "0").await;
+        }
+        if let Some(pids) = limits.pids {
+            self.write("pids.max", &pids.to_string()).await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&self, file: &str, value: &str) -> SandboxResult<()> {
+        fs::write(self.path.join(file), value)
+            .await
+            .map_err(|e| SandboxError::Internal(format!("failed to set {file}={value}: {e}")))
+    }
+
+    pub fn procs_path(&self) -> PathBuf {
+        self.path.join("cgroup.procs")
+    }
+
+    /// Number of processes the kernel has OOM-killed in this cgroup so far.
+    /// Errors once the cgroup is gone.
+    pub async fn oom_kills(&self) -> SandboxResult<u64> {
+        let events = fs::read_to_string(self.path.join("memory.events")).await?;
+        Ok(parse_oom_kills(&events).unwrap_or(0))
+    }
+
+    /// Kill anything left in the cgroup and remove it. Best-effort.
+    pub async fn remove(&self) {
+        let _ = fs::write(self.path.join("cgroup.kill"), "1").await;
+        // Killed processes take a moment to leave the cgroup.
+        for _ in 0..10 {
+            match fs::remove_dir(&self.path).await {
+                Ok(()) => return,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(50)).await,
+            }
+        }
+        warn!("failed to remove cgroup {}", self.path.display());
+    }
+}
+
+fn join_args(cgroup: &SandboxCgroup, program: &OsStr) -> [std::ffi::OsString; 4] {
+    [
+        "-c".into(),
+        JOIN_SCRIPT.into(),
+        cgroup.procs_path().into_os_string(),
+        program.to_os_string(),
+    ]
+}
+
+/// A command for `program` that starts inside `cgroup`, if there is one.
+/// Arguments added afterwards are passed to `program`.
+pub fn command_in(cgroup: Option<&SandboxCgroup>, program: impl AsRef<OsStr>) -> Command {
+    match cgroup {
+        Some(cgroup) => {
+            let mut command = Command::new("/bin/sh");
+            command.args(join_args(cgroup, program.as_ref()));
+            command
+        }
+        None => Command::new(program),
+    }
+}
+
+/// [`command_in`] for PTY commands.
+pub fn pty_command_in(
+    cgroup: Option<&SandboxCgroup>,
+    program: impl AsRef<OsStr>,
+) -> portable_pty::CommandBuilder {
+    match cgroup {
+        Some(cgroup) => {
+            let mut command = portable_pty::CommandBuilder::new("/bin/sh");
+            command.args(join_args(cgroup, program.as_ref()));
+            command
+        }
+        None => portable_pty::CommandBuilder::new(program),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_and_events_use_cgroup_v2_formats() {
+        assert_eq!(cpu_max(1500), "150000 100000");
+        assert_eq!(cpu_max(1), "1000 100000");
+        assert_eq!(memory_max(512).unwrap(), "536870912");
+
+        let events = "low 0\nhigh 0\nmax 12\noom 3\noom_kill 2\noom_group_kill 0\n";
+        assert_eq!(parse_oom_kills(events), Some(2));
+        assert_eq!(parse_oom_kills("low 0\n"), None);
+    }
+
+    #[test]
+    fn unusable_limits_are_rejected() {
+        let limits = |memory_mb, pids| ResourceLimits {
+            cpu_millis: None,
+            memory_mb,
+            pids,
+        };
+        assert!(validate_limits(&limits(Some(512), Some(64))).is_ok());
+        assert!(validate_limits(&limits(None, None)).is_ok());
+        for bad in [
+            limits(Some(0), None),
+            limits(Some(u64::MAX / 1024), None),
+            limits(None, Some(0)),
+        ] {
+            assert!(matches!(
+                validate_limits(&bad),
+                Err(SandboxError::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn commands_join_the_cgroup_before_exec() {
+        let dir = tempfile::tempdir().unwrap();
+        let cgroup = SandboxCgroup {
+            path: dir.path().to_path_buf(),
+        };
+        let output = command_in(Some(&cgroup), "echo")
+            .arg("hello")
+            .output()
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello\n");
+        let pid = std::fs::read_to_string(cgroup.procs_path()).unwrap();
+        assert!(pid.trim().parse::<u32>().is_ok());
+    }
+}
@@ -0,0 +1,84 @@
+//! Credentials the in-process clients send to the sandbox daemon.
+//!
+//! The mux and chat TUIs reach the daemon from many places (sidebar
+//! refreshes, exec calls, the attach WebSocket, ...). Their entry points
+//! store the CLI's `--auth-token` here once, and every client built through
+//! [`http_client`] or [`websocket_request`] sends it as
+//! `Authorization: Bearer <token>`.
+
+use std::sync::RwLock;
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+
+/// Environment variable the CLI reads its token from; also passed to the
+/// `cmux` processes the TUIs spawn.
+pub const AUTH_TOKEN_ENV: &str = "CMUX_SANDBOX_TOKEN";
+
+static AUTH_TOKEN: RwLock<Option<String>> = RwLock::new(None);
+
+/// Use `token` for every later request; `None` or an empty token sends none.
+pub fn set_auth_token(token: Option<String>) {
+    *AUTH_TOKEN.write().unwrap_or_else(|e| e.into_inner()) = token.filter(|t| !t.is_empty());
+}
+
+pub fn auth_token() -> Option<String> {
+    AUTH_TOKEN.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// `Bearer <token>` for the current token. Tokens that aren't valid header
+/// values are skipped; the CLI rejects them before they get here.
+pub fn authorization() -> Option<HeaderValue> {
+    let token = auth_token()?;
+    let mut value = HeaderValue::from_str(&format!("Bearer {token}")).ok()?;
+    value.set_sensitive(true);
+    Some(value)
+}
+
+pub fn default_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = authorization() {
+        headers.insert(AUTHORIZATION, value);
+    }
+    headers
+}
+
+/// `reqwest::Client::builder()` with the token as a default header.
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().default_headers(default_headers())
+}
+
+/// Drop-in for `reqwest::Client::new()` that sends the token.
+pub fn http_client() -> reqwest::Client {
+    http_client_builder()
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+/// WebSocket handshake request for `url` carrying the token.
+pub fn websocket_request(url: &str) -> anyhow::Result<Request> {
+    let mut request = url.into_client_request()?;
+    if let Some(value) = authorization() {
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_carry_the_token() {
+        set_auth_token(Some("secret".into()));
+        let request = websocket_request("ws://127.0.0.1:1/mux/attach").unwrap();
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer secret");
+        assert_eq!(default_headers()[AUTHORIZATION], "Bearer secret");
+
+        set_auth_token(Some(String::new()));
+        let request = websocket_request("ws://127.0.0.1:1/mux/attach").unwrap();
+        assert!(request.headers().get(AUTHORIZATION).is_none());
+        assert!(default_headers().is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+//! Startup configuration for `cmux-sandboxd`.
+//!
+//! Values come from, in increasing precedence: built-in defaults, a TOML file
+//! (`--config`), `CMUX_*` environment variables, and command-line flags. The
+//! file uses the long flag names with underscores:
+//!
+//! ```toml
+//! port = 46831
+//! data_dir = "/var/lib/cmux/sandboxes"
+//! auth_tokens = ["admin-token", "acme:acme-token"]
+//! ```
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::auth::AuthConfig;
+use crate::DEFAULT_HTTP_PORT;
+
+const REDACTED: &str = "<redacted>";
+
+/// Shortest JWT secret accepted; HS256 keys shorter than the hash are weak.
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// Longest noVNC idle timeout accepted: one week.
+const MAX_VNC_IDLE_TIMEOUT_MINUTES: u64 = 7 * 24 * 60;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("failed to serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("invalid `{field}`: {message}")]
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn invalid(field: &'static str, message: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Address the HTTP server binds to.
+    pub bind: String,
+    /// Port for the HTTP server.
+    pub port: u16,
+    /// Directory used for sandbox workspaces.
+    pub data_dir: PathBuf,
+    /// Directory used for logs.
+    pub log_dir: PathBuf,
+    /// Unix socket sandboxes use for bridge commands (open-url, gh, ...).
+    pub bridge_socket: PathBuf,
+    /// Log timing instrumentation to `timing.log`.
+    pub timing: bool,
+    /// Minutes without input before a noVNC session closes; 0 disables.
+    pub vnc_idle_timeout_minutes: u64,
+    /// Proxy noVNC through a Unix socket instead of the sandbox network.
+    pub vnc_unix_socket: bool,
+    /// Record noVNC sessions into this directory.
+    pub vnc_record_dir: Option<PathBuf>,
+    /// Seconds to keep serving with a draining /healthz after a shutdown signal.
+    pub shutdown_drain_secs: u64,
+    /// Seconds connections and sandbox processes get to finish on shutdown.
+    pub shutdown_grace_secs: u64,
+    /// Bearer tokens, as `TOKEN` (admin) or `TEAM:TOKEN`.
+    pub auth_tokens: Vec<String>,
+    /// Secret for HS256 team JWTs.
+    pub jwt_secret: Option<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: DEFAULT_HTTP_PORT,
+            data_dir: PathBuf::from("/var/lib/cmux/sandboxes"),
+            log_dir: PathBuf::from("/var/log/cmux"),
+            bridge_socket: PathBuf::from("/var/run/cmux/bridge.sock"),
+            timing: false,
+            vnc_idle_timeout_minutes: 60,
+            vnc_unix_socket: false,
+            vnc_record_dir: None,
+            shutdown_drain_secs: 0,
+            shutdown_grace_secs: 10,
+            auth_tokens: Vec::new(),
+            jwt_secret: None,
+        }
+    }
+}
+
+impl SandboxConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&text)
+    }
+
+    /// Check values that would otherwise only fail once the server is running.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.bind_ip()?;
+        if self.port == 0 {
+            return Err(ConfigError::invalid("port", "must be between 1 and 65535"));
+        }
+        for (field, path) in [
+            ("data_dir", &self.data_dir),
+            ("log_dir", &self.log_dir),
+            ("bridge_socket", &self.bridge_socket),
+        ] {
+            if !path.is_absolute() {
+                return Err(ConfigError::invalid(
+                    field,
+                    format!("must be an absolute path, got {}", path.display()),
+                ));
+            }
+        }
+        if self.vnc_idle_timeout_minutes > MAX_VNC_IDLE_TIMEOUT_MINUTES {
+            return Err(ConfigError::invalid(
+                "vnc_idle_timeout_minutes",
+                format!("must be at most {MAX_VNC_IDLE_TIMEOUT_MINUTES}"),
+            ));
+        }
+        if let Some(dir) = &self.vnc_record_dir {
+            if dir.as_os_str().is_empty() {
+                return Err(ConfigError::invalid("vnc_record_dir", "must not be empty"));
+            }
+        }
+        for spec in &self.auth_tokens {
+            let token = match spec.split_once(':') {
+                // `:TOKEN` would otherwise be taken as an admin token.
+                Some(("", _)) => {
+                    return Err(ConfigError::invalid(
+                        "auth_tokens",
+                        "team name before `:` must not be empty",
+                    ));
+                }
+                Some((_, token)) => token,
+                None => spec.as_str(),
+            };
+            if token.is_empty() {
+                return Err(ConfigError::invalid(
+                    "auth_tokens",
+                    "entries must be `TOKEN` or `TEAM:TOKEN` with a non-empty token",
+                ));
+            }
+        }
+        if let Some(secret) = &self.jwt_secret {
+            if secret.len() < MIN_JWT_SECRET_LEN {
+                return Err(ConfigError::invalid(
+                    "jwt_secret",
+                    format!("must be at least {MIN_JWT_SECRET_LEN} bytes"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn bind_ip(&self) -> Result<IpAddr, ConfigError> {
+        self.bind
+            .parse()
+            .map_err(|_| ConfigError::invalid("bind", format!("not an IP address: {}", self.bind)))
+    }
+
+    pub fn auth(&self) -> AuthConfig {
+        let mut auth = AuthConfig::new();
+        for spec in &self.auth_tokens {
+            auth = auth.with_token_spec(spec);
+        }
+        if let Some(secret) = &self.jwt_secret {
+            auth = auth.with_jwt_secret(secret.as_bytes());
+        }
+        auth
+    }
+
+    /// Copy with secrets replaced, for printing. Team names stay visible.
+    pub fn redacted(&self) -> Self {
+        let mut out = self.clone();
+        out.auth_tokens = self
+            .auth_tokens
+            .iter()
+            .map(|spec| match spec.split_once(':') {
+                Some((team, _)) if !team.is_empty() => format!("{team}:{REDACTED}"),
+                _ => REDACTED.to_string(),
+            })
+            .collect();
+        out.jwt_secret = self.jwt_secret.as_ref().map(|_| REDACTED.to_string());
+        out
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_values_fill_in_defaults() {
+        let config = SandboxConfig::from_toml(
+            r#"
+            port = 5000
+            auth_tokens = ["acme:secret"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 5000);
+        assert_eq!(config.bind, "0.0.0.0");
+        assert_eq!(config.shutdown_grace_secs, 10);
+        assert!(config.validate().is_ok());
+        assert!(config.auth().is_enabled());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = SandboxConfig::from_toml("prot = 5000").unwrap_err();
+        assert!(err.to_string().contains("prot"), "{err}");
+    }
+
+    #[test]
+    fn validation_names_the_field() {
+        let cases: [(&str, SandboxConfig); 6] = [
+            (
+                "bind",
+                SandboxConfig {
+                    bind: "localhost:80".into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "data_dir",
+                SandboxConfig {
+                    data_dir: "relative/dir".into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "vnc_idle_timeout_minutes",
+                SandboxConfig {
+                    vnc_idle_timeout_minutes: u64::MAX,
+                    ..Default::default()
+                },
+            ),
+            (
+                "auth_tokens",
+                SandboxConfig {
+                    auth_tokens: vec!["acme:".into()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "auth_tokens",
+                SandboxConfig {
+                    auth_tokens: vec![":abc".into()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "jwt_secret",
+                SandboxConfig {
+                    jwt_secret: Some("short".into()),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (field, config) in cases {
+            match config.validate() {
+                Err(ConfigError::Invalid { field: got, .. }) => assert_eq!(got, field),
+                other => panic!("expected {field} to be invalid, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn redaction_hides_secrets_but_keeps_teams() {
+        let config = SandboxConfig {
+            auth_tokens: vec!["root".into(), "acme:abc".into()],
+            jwt_secret: Some("x".repeat(40)),
+            ..Default::default()
+        };
+        let printed = config.redacted().to_toml().unwrap();
+        assert!(!printed.contains("root") && !printed.contains("abc"));
+        assert!(!printed.contains("xxxx"));
+        assert!(printed.contains("acme:<redacted>"));
+        assert_eq!(
+            SandboxConfig::from_toml(&printed).unwrap().port,
+            config.port
+        );
+    }
+}
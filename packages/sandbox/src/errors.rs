@@ -18,6 +18,10 @@ pub enum SandboxError {
     IpPoolExhausted,
     #[error("invalid request: {0}")]
     InvalidRequest(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
     #[error("process failed to start")]
     ProcessNotStarted,
     #[error("internal error: {0}")]
@@ -40,6 +44,8 @@ impl IntoResponse for SandboxError {
             SandboxError::CommandFailed { .. } => StatusCode::BAD_GATEWAY,
             SandboxError::IpPoolExhausted => StatusCode::INSUFFICIENT_STORAGE,
             SandboxError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            SandboxError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            SandboxError::Forbidden(_) => StatusCode::FORBIDDEN,
             SandboxError::ProcessNotStarted => StatusCode::INTERNAL_SERVER_ERROR,
             SandboxError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             SandboxError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -47,6 +53,8 @@ impl IntoResponse for SandboxError {
 
         let code = match status.as_u16() {
             400 => "bad_request",
+            401 => "unauthorized",
+            403 => "forbidden",
             404 => "not_found",
             500 => "internal_error",
             507 => "ip_pool_exhausted",
@@ -0,0 +1,167 @@
+//! Sandbox lifecycle events.
+//!
+//! Subsystems publish what happened to a process-wide broadcast bus instead of
+//! calling each other: the service announces sandboxes and processes, the API
+//! announces port forwards, and anything interested (the `/events` SSE stream,
+//! metrics) subscribes. Publishing never blocks; subscribers that fall behind
+//! miss events and see a `Lagged` error from the receiver.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Events buffered per subscriber before it starts lagging.
+const BUS_CAPACITY: usize = 256;
+
+static BUS: LazyLock<broadcast::Sender<LifecycleEvent>> =
+    LazyLock::new(|| broadcast::channel(BUS_CAPACITY).0);
+
+/// Something that happened to a sandbox, with when it happened.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct LifecycleEvent {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: LifecycleEventKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    SandboxCreated {
+        sandbox_id: Uuid,
+        name: String,
+    },
+    SandboxDeleted {
+        sandbox_id: Uuid,
+    },
+    /// An exec or attach started a process in the sandbox.
+    ProcessSpawned {
+        sandbox_id: Uuid,
+        command: Vec<String>,
+    },
+    /// `exit_code` is `None` for attach sessions, which don't report one.
+    ProcessExited {
+        sandbox_id: Uuid,
+        command: Vec<String>,
+        exit_code: Option<i32>,
+    },
+    PortForwarded {
+        sandbox_id: Uuid,
+        port: u16,
+    },
+    PortUnforwarded {
+        sandbox_id: Uuid,
+        port: u16,
+    },
+}
+
+impl LifecycleEventKind {
+    /// The `type` tag, also used as the SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SandboxCreated { .. } => "sandbox_created",
+            Self::SandboxDeleted { .. } => "sandbox_deleted",
+            Self::ProcessSpawned { .. } => "process_spawned",
+            Self::ProcessExited { .. } => "process_exited",
+            Self::PortForwarded { .. } => "port_forwarded",
+            Self::PortUnforwarded { .. } => "port_unforwarded",
+        }
+    }
+
+    pub fn sandbox_id(&self) -> Uuid {
+        match self {
+            Self::SandboxCreated { sandbox_id, .. }
+            | Self::SandboxDeleted { sandbox_id }
+            | Self::ProcessSpawned { sandbox_id, .. }
+            | Self::ProcessExited { sandbox_id, .. }
+            | Self::PortForwarded { sandbox_id, .. }
+            | Self::PortUnforwarded { sandbox_id, .. } => *sandbox_id,
+        }
+    }
+}
+
+/// Announce `kind` to current subscribers.
+pub fn publish(kind: LifecycleEventKind) {
+    // No subscribers is fine; the event just isn't needed.
+    let _ = BUS.send(LifecycleEvent {
+        at: Utc::now(),
+        kind,
+    });
+}
+
+/// Receive every event published from now on.
+pub fn subscribe() -> broadcast::Receiver<LifecycleEvent> {
+    BUS.subscribe()
+}
+
+/// Publishes `ProcessSpawned` now and `ProcessExited` (without an exit code)
+/// when dropped, for processes whose lifetime is a scope with many exits.
+pub struct ProcessScope {
+    sandbox_id: Uuid,
+    command: Vec<String>,
+}
+
+impl ProcessScope {
+    pub fn start(sandbox_id: Uuid, command: Vec<String>) -> Self {
+        publish(LifecycleEventKind::ProcessSpawned {
+            sandbox_id,
+            command: command.clone(),
+        });
+        Self {
+            sandbox_id,
+            command,
+        }
+    }
+}
+
+impl Drop for ProcessScope {
+    fn drop(&mut self) {
+        publish(LifecycleEventKind::ProcessExited {
+            sandbox_id: self.sandbox_id,
+            command: std::mem::take(&mut self.command),
+            exit_code: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_flat_with_a_type_tag() {
+        let event = LifecycleEvent {
+            at: DateTime::from_timestamp(0, 0).unwrap(),
+            kind: LifecycleEventKind::PortForwarded {
+                sandbox_id: Uuid::nil(),
+                port: 3000,
+            },
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.kind.name());
+        assert_eq!(json["port"], 3000);
+        assert_eq!(json["at"], "1970-01-01T00:00:00Z");
+        let back: LifecycleEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(back, event);
+    }
+
+    #[tokio::test]
+    async fn process_scope_publishes_spawn_and_exit() {
+        let sandbox_id = Uuid::new_v4();
+        let mut rx = subscribe();
+        drop(ProcessScope::start(sandbox_id, vec!["zsh".into()]));
+
+        let mut names = Vec::new();
+        while names.len() < 2 {
+            let event = rx.recv().await.unwrap();
+            if event.kind.sandbox_id() == sandbox_id {
+                names.push(event.kind.name());
+            }
+        }
+        assert_eq!(names, ["process_spawned", "process_exited"]);
+    }
+}
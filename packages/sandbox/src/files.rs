@@ -0,0 +1,322 @@
+//! Filesystem access to a sandbox's workspace for the REST API.
+//!
+//! Paths are given as the sandbox sees them (`/workspace/...`, or relative to
+//! `/workspace`) and resolved against the workspace directory on the host.
+//! Resolution never leaves the workspace: `..` can't climb above it and
+//! symlinks are checked after canonicalizing.
+
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::bubblewrap::SANDBOX_WORKSPACE_MOUNT;
+use crate::errors::{SandboxError, SandboxResult};
+use crate::models::{
+    FsChangeEvent, FsChangeKind, FsEncoding, FsEntry, FsEntryKind, FsReadResponse, FsWriteRequest,
+    FsWriteResponse,
+};
+
+/// Largest range returned by a single read.
+pub const MAX_READ_BYTES: u64 = 8 * 1024 * 1024;
+
+fn outside(requested: &str) -> SandboxError {
+    SandboxError::InvalidRequest(format!("path {requested:?} is outside the workspace"))
+}
+
+/// The path inside the sandbox for a host path under `root`.
+pub(crate) fn sandbox_path(root: &Path, host_path: &Path) -> String {
+    let relative = host_path.strip_prefix(root).unwrap_or(host_path);
+    if relative.as_os_str().is_empty() {
+        return SANDBOX_WORKSPACE_MOUNT.to_string();
+    }
+    format!("{}/{}", SANDBOX_WORKSPACE_MOUNT, relative.display())
+}
+
+/// Resolve a sandbox path to a host path under `root`.
+pub async fn resolve_path(root: &Path, requested: &str) -> SandboxResult<PathBuf> {
+    let relative = requested
+        .strip_prefix(SANDBOX_WORKSPACE_MOUNT)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .unwrap_or(requested);
+
+    let mut resolved = root.to_path_buf();
+    let mut depth = 0usize;
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                depth += 1;
+            }
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir => {
+                if depth == 0 {
+                    return Err(outside(requested));
+                }
+                resolved.pop();
+                depth -= 1;
+            }
+            Component::Prefix(_) => return Err(outside(requested)),
+        }
+    }
+
+    // Symlinks can still point outside; check where the deepest existing
+    // ancestor really is. A missing tail is fine (it's about to be written).
+    let canonical_root = fs::canonicalize(root).await?;
+    let mut existing = resolved.as_path();
+    while fs::symlink_metadata(existing).await.is_err() {
+        existing = existing.parent().ok_or_else(|| outside(requested))?;
+    }
+    let canonical = fs::canonicalize(existing)
+        .await
+        .map_err(|_| outside(requested))?;
+    if !canonical.starts_with(&canonical_root) {
+        return Err(outside(requested));
+    }
+    Ok(resolved)
+}
+
+fn entry_kind(file_type: std::fs::FileType) -> FsEntryKind {
+    if file_type.is_symlink() {
+        FsEntryKind::Symlink
+    } else if file_type.is_dir() {
+        FsEntryKind::Directory
+    } else if file_type.is_file() {
+        FsEntryKind::File
+    } else {
+        FsEntryKind::Other
+    }
+}
+
+/// List a directory, directories first, then by name.
+pub async fn list_dir(root: &Path, requested: &str) -> SandboxResult<Vec<FsEntry>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = resolve_path(root, requested).await?;
+    let mut reader = fs::read_dir(&dir).await?;
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        entries.push(FsEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path: sandbox_path(root, &entry.path()),
+            kind: entry_kind(entry.file_type().await?),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            mode: metadata.permissions().mode() & 0o7777,
+        });
+    }
+    entries.sort_by(|a, b| {
+        (a.kind != FsEntryKind::Directory, &a.name)
+            .cmp(&(b.kind != FsEntryKind::Directory, &b.name))
+    });
+    Ok(entries)
+}
+
+/// Read up to `length` bytes (capped at [`MAX_READ_BYTES`]) from `offset`.
+/// Text comes back as UTF-8 unless `encoding` asks for base64.
+pub async fn read_file(
+    root: &Path,
+    requested: &str,
+    offset: u64,
+    length: Option<u64>,
+    encoding: Option<FsEncoding>,
+) -> SandboxResult<FsReadResponse> {
+    let path = resolve_path(root, requested).await?;
+    let mut file = fs::File::open(&path).await?;
+    let size = file.metadata().await?.len();
+    let length = length.unwrap_or(MAX_READ_BYTES).min(MAX_READ_BYTES);
+
+    let mut bytes = Vec::new();
+    if offset < size {
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.take(length).read_to_end(&mut bytes).await?;
+    }
+    let truncated = offset + (bytes.len() as u64) < size;
+
+    let (content, encoding) = match encoding {
+        Some(FsEncoding::Base64) => (encode_base64(&bytes), FsEncoding::Base64),
+        _ => match String::from_utf8(bytes) {
+            Ok(text) => (text, FsEncoding::Utf8),
+            Err(e) => (encode_base64(e.as_bytes()), FsEncoding::Base64),
+        },
+    };
+    Ok(FsReadResponse {
+        path: sandbox_path(root, &path),
+        size,
+        offset,
+        content,
+        encoding,
+        truncated,
+    })
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Replace a file, or patch it in place when the request has an offset.
+pub async fn write_file(root: &Path, request: FsWriteRequest) -> SandboxResult<FsWriteResponse> {
+    let path = resolve_path(root, &request.path).await?;
+    let bytes = match request.encoding {
+        FsEncoding::Utf8 => request.content.into_bytes(),
+        FsEncoding::Base64 => base64::engine::general_purpose::STANDARD
+            .decode(request.content.trim())
+            .map_err(|e| SandboxError::InvalidRequest(format!("invalid base64 content: {e}")))?,
+    };
+    if request.create_parents {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+    }
+
+    match request.offset {
+        Some(offset) => {
+            let mut file = fs::OpenOptions::new().write(true).open(&path).await?;
+            file.seek(SeekFrom::Start(offset)).await?;
+            file.write_all(&bytes).await?;
+            file.flush().await?;
+        }
+        None => fs::write(&path, &bytes).await?,
+    }
+    let size = fs::metadata(&path).await?.len();
+    Ok(FsWriteResponse {
+        path: sandbox_path(root, &path),
+        size,
+    })
+}
+
+fn change_event(root: &Path, event: notify::Event) -> Option<FsChangeEvent> {
+    let kind = match event.kind {
+        EventKind::Create(_) => FsChangeKind::Created,
+        EventKind::Modify(_) => FsChangeKind::Modified,
+        EventKind::Remove(_) => FsChangeKind::Removed,
+        EventKind::Access(_) => return None,
+        EventKind::Any | EventKind::Other => FsChangeKind::Other,
+    };
+    Some(FsChangeEvent {
+        kind,
+        paths: event
+            .paths
+            .iter()
+            .map(|path| sandbox_path(root, path))
+            .collect(),
+    })
+}
+
+/// Watch a path recursively. The watch stops when the stream is dropped.
+pub async fn watch(
+    root: &Path,
+    requested: &str,
+) -> SandboxResult<impl Stream<Item = FsChangeEvent>> {
+    let path = resolve_path(root, requested).await?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| SandboxError::Internal(format!("failed to start watcher: {e}")))?;
+    watcher
+        .watch(&path, RecursiveMode::Recursive)
+        .map_err(|e| SandboxError::Internal(format!("failed to watch {}: {e}", path.display())))?;
+
+    let root = root.to_path_buf();
+    Ok(futures::stream::unfold(
+        (rx, watcher, root),
+        |(mut rx, watcher, root)| async move {
+            loop {
+                if let Ok(event) = rx.recv().await? {
+                    if let Some(change) = change_event(&root, event) {
+                        return Some((change, (rx, watcher, root)));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn paths_stay_inside_the_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), root.join("escape")).unwrap();
+
+        assert_eq!(
+            resolve_path(&root, "/workspace/src/main.rs").await.unwrap(),
+            root.join("src/main.rs")
+        );
+        assert_eq!(
+            resolve_path(&root, "src/../new.txt").await.unwrap(),
+            root.join("new.txt")
+        );
+        assert!(resolve_path(&root, "../etc/passwd").await.is_err());
+        assert!(resolve_path(&root, "/workspace/../etc").await.is_err());
+        assert!(resolve_path(&root, "escape/secret").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn files_can_be_written_patched_read_and_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |path: &str, content: &str, offset: Option<u64>| FsWriteRequest {
+            path: path.to_string(),
+            content: content.to_string(),
+            encoding: FsEncoding::Utf8,
+            offset,
+            create_parents: true,
+        };
+
+        write_file(root, write("notes/a.txt", "hello world", None))
+            .await
+            .unwrap();
+        let written = write_file(root, write("notes/a.txt", "WORLD", Some(6)))
+            .await
+            .unwrap();
+        assert_eq!(written.path, "/workspace/notes/a.txt");
+        assert_eq!(written.size, 11);
+
+        let read = read_file(root, "notes/a.txt", 0, Some(5), None)
+            .await
+            .unwrap();
+        assert_eq!(read.content, "hello");
+        assert!(read.truncated);
+        let read = read_file(root, "notes/a.txt", 6, None, Some(FsEncoding::Base64))
+            .await
+            .unwrap();
+        assert_eq!(read.content, encode_base64(b"WORLD"));
+
+        std::fs::write(root.join("binary"), [0xff, 0xfe]).unwrap();
+        let read = read_file(root, "binary", 0, None, None).await.unwrap();
+        assert_eq!(read.encoding, FsEncoding::Base64);
+
+        let entries = list_dir(root, "/workspace").await.unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["notes", "binary"]);
+        assert_eq!(entries[0].kind, FsEntryKind::Directory);
+    }
+
+    #[tokio::test]
+    async fn watch_reports_changes_as_sandbox_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut changes = Box::pin(watch(&root, "/workspace").await.unwrap());
+        std::fs::write(root.join("new.txt"), "x").unwrap();
+
+        let change = tokio::time::timeout(std::time::Duration::from_secs(5), changes.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.paths, ["/workspace/new.txt"]);
+    }
+}
@@ -0,0 +1,433 @@
+//! Git state of a sandbox's workspace for the REST API.
+//!
+//! Read natively with gix rather than by running git, so the UI can show a
+//! repository's status next to the conversation without an agent round trip.
+//! Staged changes compare HEAD's tree with the index; unstaged ones come from
+//! gix's index/worktree status. Repositories are opened with reduced trust:
+//! the workspace is writable by the sandbox, so its `.git/config` must not be
+//! able to make the host run filter drivers or other programs.
+
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use gix::bstr::ByteSlice;
+use gix::hash::ObjectId;
+
+use crate::errors::{SandboxError, SandboxResult};
+use crate::files::{resolve_path, sandbox_path};
+use crate::models::{GitChangeKind, GitCommit, GitFileChange, GitStatus};
+
+/// Most commits returned by a single log request.
+pub const MAX_LOG_COMMITS: usize = 500;
+
+fn git_error(error: impl std::fmt::Display) -> SandboxError {
+    SandboxError::Internal(format!("git: {error}"))
+}
+
+/// Canonical workspace root and working tree, for turning repository paths
+/// into sandbox paths.
+struct WorkTree {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl WorkTree {
+    fn sandbox_path(&self, rela_path: &str) -> String {
+        if rela_path.is_empty() {
+            return sandbox_path(&self.root, &self.dir);
+        }
+        sandbox_path(&self.root, &self.dir.join(rela_path))
+    }
+}
+
+/// Repository whose working tree contains `dir`, if that working tree is
+/// inside the workspace at `root`.
+fn open_repo(
+    root: &Path,
+    dir: &Path,
+    requested: &str,
+) -> SandboxResult<(gix::Repository, WorkTree)> {
+    let not_a_repo =
+        || SandboxError::InvalidRequest(format!("{requested:?} is not in a git repository"));
+    let options = gix::open::Options::isolated().with(gix::sec::Trust::Reduced);
+    let repo = gix::ThreadSafeRepository::discover_opts(
+        dir,
+        Default::default(),
+        gix::sec::trust::Mapping {
+            full: options.clone(),
+            reduced: options,
+        },
+    )
+    .map_err(|_| not_a_repo())?
+    .to_thread_local();
+    // Bare repositories have no working tree to report on.
+    let work_tree = WorkTree {
+        root: std::fs::canonicalize(root)?,
+        dir: std::fs::canonicalize(repo.work_dir().ok_or_else(not_a_repo)?)?,
+    };
+    if !work_tree.dir.starts_with(&work_tree.root) {
+        return Err(not_a_repo());
+    }
+    Ok((repo, work_tree))
+}
+
+fn commit_info(commit: &gix::Commit<'_>) -> SandboxResult<GitCommit> {
+    let author = commit.author().map_err(git_error)?;
+    let summary = commit
+        .message()
+        .map_err(git_error)?
+        .summary()
+        .to_str_lossy()
+        .into_owned();
+    let seconds = commit.time().map_err(git_error)?.seconds;
+    Ok(GitCommit {
+        id: commit.id.to_string(),
+        summary,
+        author_name: author.name.to_str_lossy().into_owned(),
+        author_email: author.email.to_str_lossy().into_owned(),
+        time: DateTime::<Utc>::from_timestamp(seconds, 0).unwrap_or_default(),
+    })
+}
+
+/// Commits reachable from `ours` but not `theirs`, and the other way round.
+/// Walks both histories newest first and stops once only shared commits
+/// are left, the way git does it.
+fn ahead_behind(
+    repo: &gix::Repository,
+    ours: ObjectId,
+    theirs: ObjectId,
+) -> SandboxResult<(u32, u32)> {
+    const OURS: u8 = 1;
+    const THEIRS: u8 = 2;
+    const BOTH: u8 = OURS | THEIRS;
+
+    let commit_time = |id: ObjectId| -> SandboxResult<i64> {
+        let commit = repo
+            .find_object(id)
+            .map_err(git_error)?
+            .try_into_commit()
+            .map_err(git_error)?;
+        Ok(commit.time().map_err(git_error)?.seconds)
+    };
+
+    let mut flags: HashMap<ObjectId, u8> = HashMap::new();
+    // (commit time, id, flags when queued); stale entries are skipped.
+    let mut queue = BinaryHeap::new();
+    // Queued entries that aren't known to be shared yet.
+    let mut interesting = 0usize;
+    for (id, flag) in [(ours, OURS), (theirs, THEIRS)] {
+        let merged = *flags.entry(id).and_modify(|f| *f |= flag).or_insert(flag);
+        queue.push((commit_time(id)?, id, merged));
+        interesting += 1;
+    }
+
+    while interesting > 0 {
+        let Some((_, id, queued)) = queue.pop() else {
+            break;
+        };
+        if queued != BOTH {
+            interesting -= 1;
+        }
+        let flag = flags[&id];
+        if flag != queued {
+            continue;
+        }
+        let commit = repo
+            .find_object(id)
+            .map_err(git_error)?
+            .try_into_commit()
+            .map_err(git_error)?;
+        for parent in commit.parent_ids() {
+            let parent = parent.detach();
+            let entry = flags.entry(parent).or_insert(0);
+            let merged = *entry | flag;
+            if merged == *entry {
+                continue;
+            }
+            *entry = merged;
+            queue.push((commit_time(parent)?, parent, merged));
+            if merged != BOTH {
+                interesting += 1;
+            }
+        }
+    }
+
+    let count = |wanted: u8| flags.values().filter(|f| **f == wanted).count() as u32;
+    Ok((count(OURS), count(THEIRS)))
+}
+
+/// Staged changes: HEAD's tree against the index.
+fn staged_changes(
+    repo: &gix::Repository,
+    index: &gix::index::File,
+) -> SandboxResult<BTreeMap<String, GitChangeKind>> {
+    let mut head_files: HashMap<String, ObjectId> = HashMap::new();
+    if let Ok(commit) = repo.head_commit() {
+        let tree = commit.tree().map_err(git_error)?;
+        let mut recorder = gix::traverse::tree::Recorder::default();
+        tree.traverse()
+            .breadthfirst(&mut recorder)
+            .map_err(git_error)?;
+        for entry in recorder.records {
+            if !entry.mode.is_tree() {
+                head_files.insert(entry.filepath.to_str_lossy().into_owned(), entry.oid);
+            }
+        }
+    }
+
+    let mut changes = BTreeMap::new();
+    for entry in index.entries() {
+        let path = entry.path(index).to_str_lossy().into_owned();
+        if entry.stage() != gix::index::entry::Stage::Unconflicted {
+            changes.insert(path, GitChangeKind::Conflicted);
+            continue;
+        }
+        match head_files.remove(&path) {
+            None => {
+                changes.insert(path, GitChangeKind::Added);
+            }
+            Some(id) if id != entry.id => {
+                changes.insert(path, GitChangeKind::Modified);
+            }
+            Some(_) => {}
+        }
+    }
+    for path in head_files.into_keys() {
+        changes.entry(path).or_insert(GitChangeKind::Deleted);
+    }
+    Ok(changes)
+}
+
+/// Unstaged changes and untracked files: the index against the working tree.
+fn unstaged_changes(repo: &gix::Repository) -> SandboxResult<BTreeMap<String, GitChangeKind>> {
+    use gix::status::index_worktree::iter::Summary;
+
+    let iter = repo
+        .status(gix::progress::Discard)
+        .map_err(git_error)?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .index_worktree_rewrites(None)
+        .into_index_worktree_iter(Vec::new())
+        .map_err(git_error)?;
+    let mut changes = BTreeMap::new();
+    for item in iter {
+        let item = item.map_err(git_error)?;
+        let kind = match item.summary() {
+            Some(Summary::Added) => GitChangeKind::Untracked,
+            Some(Summary::Removed) => GitChangeKind::Deleted,
+            Some(Summary::Modified) => GitChangeKind::Modified,
+            Some(Summary::TypeChange) => GitChangeKind::TypeChange,
+            Some(Summary::Conflict) => GitChangeKind::Conflicted,
+            Some(Summary::IntentToAdd | Summary::Renamed | Summary::Copied) => GitChangeKind::Added,
+            None => continue,
+        };
+        changes.insert(item.rela_path().to_str_lossy().into_owned(), kind);
+    }
+    Ok(changes)
+}
+
+fn read_status(repo: &gix::Repository, work_tree: &WorkTree) -> SandboxResult<GitStatus> {
+    let head_name = repo.head_name().map_err(git_error)?;
+    let head = repo.head_commit().ok();
+
+    let mut upstream = None;
+    let mut ahead = None;
+    let mut behind = None;
+    if let (Some(name), Some(head)) = (&head_name, &head) {
+        let tracking = repo
+            .branch_remote_tracking_ref_name(name.as_ref(), gix::remote::Direction::Fetch)
+            .and_then(Result::ok);
+        if let Some(tracking) = tracking {
+            upstream = Some(tracking.shorten().to_str_lossy().into_owned());
+            let tracking_id = repo
+                .find_reference(tracking.as_ref())
+                .ok()
+                .and_then(|mut r| r.peel_to_id_in_place().ok())
+                .map(|id| id.detach());
+            if let Some(tracking_id) = tracking_id {
+                let (a, b) = ahead_behind(repo, head.id, tracking_id)?;
+                ahead = Some(a);
+                behind = Some(b);
+            }
+        }
+    }
+
+    let index = repo.index_or_empty().map_err(git_error)?;
+    let staged = staged_changes(repo, &index)?;
+    let mut unstaged = unstaged_changes(repo)?;
+    let mut changes = Vec::new();
+    for (path, kind) in staged {
+        let unstaged = unstaged.remove(&path);
+        changes.push((path, Some(kind), unstaged));
+    }
+    changes.extend(
+        unstaged
+            .into_iter()
+            .map(|(path, kind)| (path, None, Some(kind))),
+    );
+    let mut changes: Vec<GitFileChange> = changes
+        .into_iter()
+        .map(|(path, staged, unstaged)| GitFileChange {
+            path: work_tree.sandbox_path(&path),
+            staged,
+            unstaged,
+        })
+        .collect();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(GitStatus {
+        root: work_tree.sandbox_path(""),
+        branch: head_name.map(|name| name.shorten().to_str_lossy().into_owned()),
+        upstream,
+        ahead,
+        behind,
+        head: head.as_ref().map(commit_info).transpose()?,
+        dirty: !changes.is_empty(),
+        changes,
+    })
+}
+
+/// Branch, upstream, HEAD commit and changed files of the repository
+/// containing `requested`.
+pub async fn status(root: &Path, requested: &str) -> SandboxResult<GitStatus> {
+    let dir = resolve_path(root, requested).await?;
+    let root = root.to_path_buf();
+    let requested = requested.to_string();
+    tokio::task::spawn_blocking(move || {
+        let (repo, work_tree) = open_repo(&root, &dir, &requested)?;
+        read_status(&repo, &work_tree)
+    })
+    .await
+    .map_err(git_error)?
+}
+
+/// Up to `limit` commits reachable from HEAD, newest first.
+pub async fn log(root: &Path, requested: &str, limit: usize) -> SandboxResult<Vec<GitCommit>> {
+    let dir = resolve_path(root, requested).await?;
+    let root = root.to_path_buf();
+    let requested = requested.to_string();
+    let limit = limit.min(MAX_LOG_COMMITS);
+    tokio::task::spawn_blocking(move || {
+        let (repo, _) = open_repo(&root, &dir, &requested)?;
+        let Ok(head) = repo.head_commit() else {
+            return Ok(Vec::new());
+        };
+        let walk = head
+            .ancestors()
+            .sorting(gix::traverse::commit::simple::Sorting::ByCommitTimeNewestFirst)
+            .all()
+            .map_err(git_error)?;
+        let mut commits = Vec::new();
+        for info in walk.take(limit) {
+            let info = info.map_err(git_error)?;
+            commits.push(commit_info(&info.object().map_err(git_error)?)?);
+        }
+        Ok(commits)
+    })
+    .await
+    .map_err(git_error)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    fn kinds(status: &GitStatus) -> Vec<(&str, Option<GitChangeKind>, Option<GitChangeKind>)> {
+        status
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.staged, c.unstaged))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reports_branch_upstream_and_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        let repo = root.join("app");
+        std::fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q", "-b", "main"]);
+        for (file, message) in [("a.txt", "first"), ("b.txt", "second")] {
+            std::fs::write(repo.join(file), file).unwrap();
+            git(&repo, &["add", file]);
+            git(&repo, &["commit", "-q", "-m", message]);
+        }
+        // The upstream is one commit behind and has one of its own.
+        git(&repo, &["update-ref", "refs/remotes/origin/main", "HEAD~1"]);
+        git(&repo, &["config", "branch.main.remote", "origin"]);
+        git(&repo, &["config", "branch.main.merge", "refs/heads/main"]);
+        git(
+            &repo,
+            &["config", "remote.origin.url", "https://example.com/app.git"],
+        );
+        git(
+            &repo,
+            &[
+                "config",
+                "remote.origin.fetch",
+                "+refs/heads/*:refs/remotes/origin/*",
+            ],
+        );
+
+        std::fs::write(repo.join("a.txt"), "changed").unwrap();
+        std::fs::write(repo.join("c.txt"), "staged").unwrap();
+        git(&repo, &["add", "c.txt"]);
+        std::fs::write(repo.join("c.txt"), "staged, then edited").unwrap();
+        git(&repo, &["rm", "-q", "b.txt"]);
+        std::fs::write(repo.join("new.txt"), "untracked").unwrap();
+
+        let status = status(&root, "app").await.unwrap();
+        assert_eq!(status.root, "/workspace/app");
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (Some(1), Some(0)));
+        assert_eq!(status.head.as_ref().unwrap().summary, "second");
+        assert!(status.dirty);
+        assert_eq!(
+            kinds(&status),
+            vec![
+                ("/workspace/app/a.txt", None, Some(GitChangeKind::Modified)),
+                ("/workspace/app/b.txt", Some(GitChangeKind::Deleted), None),
+                (
+                    "/workspace/app/c.txt",
+                    Some(GitChangeKind::Added),
+                    Some(GitChangeKind::Modified)
+                ),
+                (
+                    "/workspace/app/new.txt",
+                    None,
+                    Some(GitChangeKind::Untracked)
+                ),
+            ]
+        );
+
+        let commits = log(&root, "/workspace/app", 10).await.unwrap();
+        let summaries: Vec<_> = commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, ["second", "first"]);
+        assert_eq!(log(&root, "app", 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_directories_outside_a_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        std::fs::create_dir_all(root.join("plain")).unwrap();
+        assert!(matches!(
+            status(&root, "plain").await,
+            Err(SandboxError::InvalidRequest(_))
+        ));
+        assert!(log(&root, "../", 10).await.is_err());
+    }
+}
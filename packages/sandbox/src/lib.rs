@@ -1,24 +1,36 @@
 pub mod acp_client;
 pub mod api;
+pub mod auth;
 pub mod bubblewrap;
+pub mod cgroups;
+pub mod client_auth;
+pub mod config;
 pub mod errors;
+pub mod events;
+pub mod files;
+pub mod git;
 pub mod ip_pool;
 pub mod keyring;
+pub mod metrics;
 pub mod models;
 pub mod mux;
 pub mod notifications;
 pub mod palette;
+pub mod ports;
 pub mod sandbox_handle;
 pub mod service;
 pub mod settings;
+pub mod shutdown;
 pub mod sync_files;
 pub mod terminal_guard;
 pub mod timing;
+pub mod vnc_clipboard;
 pub mod vnc_proxy;
+pub mod vnc_recording;
 
 pub use acp_client::{
     load_last_provider, run_chat_tui, run_chat_tui_with_workspace_status, run_demo_tui,
-    AcpProvider, WorkspaceSyncStatus,
+    run_headless, stop_reason_exit_code, AcpProvider, HeadlessOptions, WorkspaceSyncStatus,
 };
 pub use api::build_router;
 pub use bubblewrap::BubblewrapService;
@@ -28,7 +40,7 @@ pub use keyring::{
     get_claude_token, get_default_team, get_stack_refresh_token, set_default_team,
     store_claude_token, store_stack_refresh_token,
 };
-pub use mux::run_mux_tui;
+pub use mux::{run_mux_tui, run_mux_tui_with_session};
 pub use sandbox_handle::{ExecOutput, SandboxBuilder, SandboxHandle, SandboxPool};
 
 pub const DEFAULT_HTTP_PORT: u16 = 46831;
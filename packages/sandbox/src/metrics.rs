@@ -0,0 +1,225 @@
+//! Prometheus metrics for sandboxd, served at `/metrics`.
+//!
+//! Process and port counts come from the lifecycle event bus
+//! ([`spawn_lifecycle_recorder`]); websocket counts from [`websocket_opened`]
+//! guards held for the life of each connection. Sandbox counts are read from
+//! the service when scraped so they are right even for sandboxes created
+//! before the recorder started.
+
+use std::sync::LazyLock;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::{self, LifecycleEventKind};
+use crate::models::{SandboxStatus, SandboxSummary};
+
+struct Metrics {
+    registry: Registry,
+    sandboxes: IntGaugeVec,
+    processes_running: IntGauge,
+    processes_spawned: IntCounter,
+    process_exits: IntCounterVec,
+    ports_forwarded: IntGauge,
+    websockets: IntGaugeVec,
+    exec_duration: HistogramVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("sandboxd".into()), None).expect("valid registry prefix");
+        let sandboxes = IntGaugeVec::new(
+            Opts::new("sandboxes", "Sandboxes known to the service, by status"),
+            &["status"],
+        )
+        .unwrap();
+        let processes_running = IntGauge::new(
+            "processes_running",
+            "Exec and attach processes currently running",
+        )
+        .unwrap();
+        let processes_spawned = IntCounter::new(
+            "processes_spawned_total",
+            "Exec and attach processes started",
+        )
+        .unwrap();
+        let process_exits = IntCounterVec::new(
+            Opts::new(
+                "process_exits_total",
+                "Processes that exited, by result (success, failure, unknown)",
+            ),
+            &["result"],
+        )
+        .unwrap();
+        let ports_forwarded =
+            IntGauge::new("ports_forwarded", "Ports currently forwarded").unwrap();
+        let websockets = IntGaugeVec::new(
+            Opts::new(
+                "websocket_connections",
+                "Open websocket connections, by endpoint",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+        let exec_duration = HistogramVec::new(
+            HistogramOpts::new("exec_duration_seconds", "Time to run an exec request")
+                .buckets(vec![0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0, 120.0]),
+            &["result"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(sandboxes.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(processes_running.clone()),
+            Box::new(processes_spawned.clone()),
+            Box::new(process_exits.clone()),
+            Box::new(ports_forwarded.clone()),
+            Box::new(websockets.clone()),
+            Box::new(exec_duration.clone()),
+        ] {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            sandboxes,
+            processes_running,
+            processes_spawned,
+            process_exits,
+            ports_forwarded,
+            websockets,
+            exec_duration,
+        }
+    }
+
+    fn record(&self, event: &LifecycleEventKind) {
+        match event {
+            LifecycleEventKind::ProcessSpawned { .. } => {
+                self.processes_spawned.inc();
+                self.processes_running.inc();
+            }
+            LifecycleEventKind::ProcessExited { exit_code, .. } => {
+                self.processes_running.dec();
+                let result = match exit_code {
+                    Some(0) => "success",
+                    Some(_) => "failure",
+                    None => "unknown",
+                };
+                self.process_exits.with_label_values(&[result]).inc();
+            }
+            LifecycleEventKind::PortForwarded { .. } => self.ports_forwarded.inc(),
+            LifecycleEventKind::PortUnforwarded { .. } => self.ports_forwarded.dec(),
+            LifecycleEventKind::SandboxCreated { .. }
+            | LifecycleEventKind::SandboxDeleted { .. } => {}
+        }
+    }
+}
+
+/// Keep the process and port metrics up to date from the lifecycle bus.
+/// Call once from within the runtime.
+pub fn spawn_lifecycle_recorder() -> tokio::task::JoinHandle<()> {
+    let mut rx = events::subscribe();
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => METRICS.record(&event.kind),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "metrics missed lifecycle events");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Decrements the connection gauge for its endpoint when dropped.
+pub struct WebsocketGuard(IntGauge);
+
+impl Drop for WebsocketGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Count a websocket on `endpoint` as open until the guard is dropped.
+pub fn websocket_opened(endpoint: &str) -> WebsocketGuard {
+    let gauge = METRICS.websockets.with_label_values(&[endpoint]);
+    gauge.inc();
+    WebsocketGuard(gauge)
+}
+
+/// Record how long an exec request took and whether it succeeded.
+pub fn observe_exec(seconds: f64, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    METRICS
+        .exec_duration
+        .with_label_values(&[result])
+        .observe(seconds);
+}
+
+/// Render every metric in the Prometheus text format, with sandbox counts
+/// taken from `sandboxes`.
+pub fn render(sandboxes: &[SandboxSummary]) -> String {
+    let metrics = &*METRICS;
+    for status in [
+        SandboxStatus::Creating,
+        SandboxStatus::Running,
+        SandboxStatus::Exited,
+        SandboxStatus::Failed,
+        SandboxStatus::Unknown,
+    ] {
+        let count = sandboxes.iter().filter(|s| s.status == status).count();
+        metrics
+            .sandboxes
+            .with_label_values(&[status_label(&status)])
+            .set(count as i64);
+    }
+    let mut out = Vec::new();
+    TextEncoder::new()
+        .encode(&metrics.registry.gather(), &mut out)
+        .expect("text encoding of gathered metrics");
+    String::from_utf8(out).expect("text encoder writes UTF-8")
+}
+
+fn status_label(status: &SandboxStatus) -> &'static str {
+    match status {
+        SandboxStatus::Creating => "creating",
+        SandboxStatus::Running => "running",
+        SandboxStatus::Exited => "exited",
+        SandboxStatus::Failed => "failed",
+        SandboxStatus::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_includes_every_family() {
+        let _ws = websocket_opened("attach");
+        observe_exec(0.2, true);
+        METRICS.record(&LifecycleEventKind::ProcessExited {
+            sandbox_id: uuid::Uuid::nil(),
+            command: vec!["true".into()],
+            exit_code: Some(0),
+        });
+
+        let text = render(&[]);
+        for family in [
+            "sandboxd_sandboxes{status=\"running\"} 0",
+            "sandboxd_websocket_connections{endpoint=\"attach\"}",
+            "sandboxd_exec_duration_seconds_bucket",
+            "sandboxd_process_exits_total{result=\"success\"}",
+            "sandboxd_ports_forwarded",
+        ] {
+            assert!(text.contains(family), "missing {family} in:\n{text}");
+        }
+    }
+}
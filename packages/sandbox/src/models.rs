@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
@@ -24,6 +24,22 @@ pub struct CreateSandboxRequest {
     pub tmpfs: Vec<String>,
     #[serde(default)]
     pub env: Vec<EnvVar>,
+    /// cgroup v2 limits for everything running in the sandbox
+    #[serde(default)]
+    pub resources: Option<ResourceLimits>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// CPU time in thousandths of a core (1500 = 1.5 cores)
+    #[serde(default)]
+    pub cpu_millis: Option<u32>,
+    /// Memory limit in MiB; swap is disabled when set
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    /// Maximum number of processes and threads
+    #[serde(default)]
+    pub pids: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
@@ -60,6 +76,9 @@ pub struct SandboxDisplay {
     pub vscode_port: u16,
     /// cmux-pty port (always 39383 inside sandbox, accessed via subdomain routing)
     pub pty_port: u16,
+    /// Host path of Xvnc's Unix socket; noVNC is proxied through it instead of `vnc_port` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vnc_socket: Option<String>,
 }
 
 /// Readiness status for sandbox services.
@@ -122,6 +141,9 @@ pub struct SandboxSummary {
     /// the server responds with the real sandbox.
     #[serde(default)]
     pub correlation_id: Option<String>,
+    /// Resource limits applied to the sandbox, if any.
+    #[serde(default)]
+    pub resources: Option<ResourceLimits>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
@@ -135,6 +157,205 @@ pub struct ExecRequest {
     pub env: Vec<EnvVar>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsEntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct FsEntry {
+    pub name: String,
+    /// Path inside the sandbox
+    #[schema(example = "/workspace/src/main.rs")]
+    pub path: String,
+    pub kind: FsEntryKind,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// Unix permission bits
+    pub mode: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsEncoding {
+    #[default]
+    Utf8,
+    Base64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct FsReadResponse {
+    pub path: String,
+    /// Total file size in bytes
+    pub size: u64,
+    pub offset: u64,
+    pub content: String,
+    /// UTF-8 when the range is valid text, base64 otherwise
+    pub encoding: FsEncoding,
+    /// Whether the file continues past the returned range
+    pub truncated: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct FsWriteRequest {
+    /// Path inside the sandbox, absolute under /workspace or relative to it
+    pub path: String,
+    pub content: String,
+    #[serde(default)]
+    pub encoding: FsEncoding,
+    /// Overwrite bytes starting here instead of replacing the file
+    #[serde(default)]
+    pub offset: Option<u64>,
+    /// Create missing parent directories
+    #[serde(default)]
+    pub create_parents: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct FsWriteResponse {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Other,
+}
+
+/// A filesystem change, sent as an SSE event by the watch endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct FsChangeEvent {
+    pub kind: FsChangeKind,
+    pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct GitCommit {
+    pub id: String,
+    /// First line of the message
+    pub summary: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GitChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    TypeChange,
+    Untracked,
+    Conflicted,
+}
+
+/// A changed file, like a line of `git status --porcelain`.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct GitFileChange {
+    /// Path inside the sandbox
+    #[schema(example = "/workspace/src/main.rs")]
+    pub path: String,
+    /// Change staged in the index relative to HEAD
+    pub staged: Option<GitChangeKind>,
+    /// Change in the working tree relative to the index
+    pub unstaged: Option<GitChangeKind>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct GitStatus {
+    /// Working tree root inside the sandbox
+    #[schema(example = "/workspace")]
+    pub root: String,
+    /// Checked-out branch, `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// Upstream tracking branch, such as `origin/main`
+    pub upstream: Option<String>,
+    /// Commits on HEAD that the upstream doesn't have
+    pub ahead: Option<u32>,
+    /// Commits on the upstream that HEAD doesn't have
+    pub behind: Option<u32>,
+    /// `None` before the first commit
+    pub head: Option<GitCommit>,
+    pub dirty: bool,
+    pub changes: Vec<GitFileChange>,
+}
+
+/// Where the clipboard text last came from.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VncClipboardSource {
+    /// Copied inside the remote desktop
+    Desktop,
+    /// Pasted into a noVNC viewer's clipboard
+    Viewer,
+    /// Pushed through the REST API
+    Api,
+}
+
+/// Clipboard of a sandbox's remote desktop.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct VncClipboard {
+    /// Latest clipboard text; `None` until something is copied or pushed
+    /// after sandboxd starts following the desktop's clipboard
+    pub text: Option<String>,
+    pub source: Option<VncClipboardSource>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct VncClipboardRequest {
+    /// Text to place on the desktop's clipboard. RFB carries Latin-1, so
+    /// other characters arrive as `?`.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ForwardPortRequest {
+    /// Port the service listens on inside the sandbox
+    #[schema(example = 3000)]
+    pub port: u16,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ForwardedPort {
+    pub port: u16,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Subdomain the proxy routes to this port (`{index}-{port}`)
+    #[schema(example = "0-3000")]
+    pub subdomain: String,
+    /// URL through the host proxy, when the request's host was known
+    #[serde(default)]
+    pub url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ListeningPort {
+    pub port: u16,
+    /// Only bound to loopback, so the proxy can't reach it
+    pub loopback_only: bool,
+    pub forwarded: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SandboxPorts {
+    /// TCP ports with a listener inside the sandbox
+    pub listening: Vec<ListeningPort>,
+    pub forwarded: Vec<ForwardedPort>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ExecResponse {
     pub exit_code: i32,
@@ -171,7 +392,7 @@ pub struct NotificationRequest {
     pub pane_id: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema, IntoParams)]
 pub struct OpenUrlRequest {
     pub url: String,
     #[serde(default)]
@@ -114,6 +114,59 @@ impl CharacterStyles {
         self.modifiers = self.modifiers.difference(modifier);
         self
     }
+
+    /// SGR escape sequence that resets attributes and then applies these
+    /// styles, e.g. `\x1b[0;1;31m`.
+    pub fn to_sgr(&self) -> String {
+        let mut params = vec!["0".to_string()];
+        for (modifier, code) in [
+            (Modifier::BOLD, "1"),
+            (Modifier::DIM, "2"),
+            (Modifier::ITALIC, "3"),
+            (Modifier::UNDERLINED, "4"),
+            (Modifier::SLOW_BLINK, "5"),
+            (Modifier::RAPID_BLINK, "6"),
+            (Modifier::REVERSED, "7"),
+            (Modifier::HIDDEN, "8"),
+            (Modifier::CROSSED_OUT, "9"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                params.push(code.to_string());
+            }
+        }
+        params.extend(self.foreground.and_then(|c| sgr_color(c, false)));
+        params.extend(self.background.and_then(|c| sgr_color(c, true)));
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+/// SGR parameters selecting `color` as the foreground (or background).
+/// `None` for `Color::Reset`, which the reset at the start already covers.
+fn sgr_color(color: Color, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    let extended = if background { 48 } else { 38 };
+    let basic = |code: u8| Some((code + offset).to_string());
+    match color {
+        Color::Reset => None,
+        Color::Black => basic(30),
+        Color::Red => basic(31),
+        Color::Green => basic(32),
+        Color::Yellow => basic(33),
+        Color::Blue => basic(34),
+        Color::Magenta => basic(35),
+        Color::Cyan => basic(36),
+        Color::Gray => basic(37),
+        Color::DarkGray => basic(90),
+        Color::LightRed => basic(91),
+        Color::LightGreen => basic(92),
+        Color::LightYellow => basic(93),
+        Color::LightBlue => basic(94),
+        Color::LightMagenta => basic(95),
+        Color::LightCyan => basic(96),
+        Color::White => basic(97),
+        Color::Indexed(index) => Some(format!("{extended};5;{index}")),
+        Color::Rgb(r, g, b) => Some(format!("{extended};2;{r};{g};{b}")),
+    }
 }
 
 /// A single character in the terminal grid.
@@ -517,6 +570,49 @@ impl Row {
         self.columns.iter().map(|c| c.character).collect()
     }
 
+    /// Cells up to the last one that shows something: a non-blank character
+    /// or a blank with a background or reverse video.
+    fn trimmed_columns(&self) -> impl Iterator<Item = &TerminalCharacter> {
+        let end = self
+            .columns
+            .iter()
+            .rposition(|c| {
+                let styles = c.styles.get();
+                c.character != ' '
+                    || styles.background.is_some()
+                    || styles.modifiers.contains(Modifier::REVERSED)
+            })
+            .map_or(0, |index| index + 1);
+        self.columns.iter().take(end).filter(|c| !c.wide_spacer)
+    }
+
+    /// The row's text without trailing blanks, as it reads on screen.
+    pub fn to_plain_string(&self) -> String {
+        self.trimmed_columns()
+            .map(|c| c.character)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    /// The row's text with SGR escapes for its colors and attributes. The
+    /// result always ends with attributes reset.
+    pub fn to_ansi_string(&self) -> String {
+        let mut out = String::new();
+        let mut current = SharedStyles::Default;
+        for c in self.trimmed_columns() {
+            if c.styles != current {
+                out.push_str(&c.styles.get().to_sgr());
+                current = c.styles.clone();
+            }
+            out.push(c.character);
+        }
+        if !current.is_default() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+
     /// Convert row contents to a ratatui Line for rendering.
     pub fn to_ratatui_line(&self) -> ratatui::text::Line<'static> {
         self.to_ratatui_line_with_defaults(None, None)
@@ -605,6 +701,30 @@ mod tests {
         assert!(c.styles.is_default());
     }
 
+    #[test]
+    fn test_row_to_ansi_string() {
+        let red = SharedStyles::new(
+            CharacterStyles::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut row = Row::filled(10);
+        row.set(0, TerminalCharacter::new('o', red.clone()));
+        row.set(1, TerminalCharacter::new('k', red));
+        row.set(2, TerminalCharacter::new('!', SharedStyles::Default));
+        row.add_character_at(TerminalCharacter::new('中', SharedStyles::Default), 3);
+
+        assert_eq!(row.to_plain_string(), "ok!中");
+        assert_eq!(row.to_ansi_string(), "\x1b[0;1;31mok\x1b[0m!中");
+        assert_eq!(
+            CharacterStyles::default()
+                .fg(Color::Indexed(208))
+                .bg(Color::Rgb(1, 2, 3))
+                .to_sgr(),
+            "\x1b[0;38;5;208;48;2;1;2;3m"
+        );
+    }
+
     #[test]
     fn test_terminal_character_width() {
         // ASCII character
@@ -30,8 +30,11 @@ pub enum MuxCommand {
     // Pane management
     SplitHorizontal,
     SplitVertical,
+    OpenAgentChat,
     ClosePane,
     ToggleZoom,
+    EqualizePanes,
+    ToggleBroadcast,
     SwapPaneLeft,
     SwapPaneRight,
     SwapPaneUp,
@@ -68,6 +71,7 @@ pub enum MuxCommand {
     OpenCommandPalette,
     ToggleHelp,
     ShowNotifications,
+    SelectTheme,
     Quit,
     ScrollUp,
     ScrollDown,
@@ -80,6 +84,16 @@ pub enum MuxCommand {
     EnableDeltaPager,
     DisableDeltaPager,
     CopyScrollback,
+    CopyScrollbackAnsi,
+    ExportScrollback,
+    ExportScrollbackAnsi,
+    TogglePipePane,
+    SearchScrollback,
+    SearchAllPanes,
+    SearchCommandHistory,
+    RerunLastCommand,
+    EnterCopyMode,
+    PasteClipboard,
 
     // External tools
     OpenEditor,
@@ -130,8 +144,11 @@ impl MuxCommand {
             // Pane management
             MuxCommand::SplitHorizontal,
             MuxCommand::SplitVertical,
+            MuxCommand::OpenAgentChat,
             MuxCommand::ClosePane,
             MuxCommand::ToggleZoom,
+            MuxCommand::EqualizePanes,
+            MuxCommand::ToggleBroadcast,
             MuxCommand::SwapPaneLeft,
             MuxCommand::SwapPaneRight,
             MuxCommand::SwapPaneUp,
@@ -163,6 +180,7 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette,
             MuxCommand::ToggleHelp,
             MuxCommand::ShowNotifications,
+            MuxCommand::SelectTheme,
             MuxCommand::Quit,
             MuxCommand::ScrollUp,
             MuxCommand::ScrollDown,
@@ -174,6 +192,16 @@ impl MuxCommand {
             MuxCommand::EnableDeltaPager,
             MuxCommand::DisableDeltaPager,
             MuxCommand::CopyScrollback,
+            MuxCommand::CopyScrollbackAnsi,
+            MuxCommand::ExportScrollback,
+            MuxCommand::ExportScrollbackAnsi,
+            MuxCommand::TogglePipePane,
+            MuxCommand::SearchScrollback,
+            MuxCommand::SearchAllPanes,
+            MuxCommand::SearchCommandHistory,
+            MuxCommand::RerunLastCommand,
+            MuxCommand::EnterCopyMode,
+            MuxCommand::PasteClipboard,
             // External tools
             MuxCommand::OpenEditor,
             MuxCommand::OpenWith,
@@ -214,8 +242,11 @@ impl MuxCommand {
             MuxCommand::GoToTab9 => "Go to Tab 9",
             MuxCommand::SplitHorizontal => "Split Horizontal",
             MuxCommand::SplitVertical => "Split Vertical",
+            MuxCommand::OpenAgentChat => "Open Agent Chat",
             MuxCommand::ClosePane => "Close Pane",
             MuxCommand::ToggleZoom => "Toggle Zoom",
+            MuxCommand::EqualizePanes => "Equalize Panes",
+            MuxCommand::ToggleBroadcast => "Toggle Broadcast Input",
             MuxCommand::SwapPaneLeft => "Swap Pane Left",
             MuxCommand::SwapPaneRight => "Swap Pane Right",
             MuxCommand::SwapPaneUp => "Swap Pane Up",
@@ -242,6 +273,7 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette => "Command Palette",
             MuxCommand::ToggleHelp => "Toggle Help",
             MuxCommand::ShowNotifications => "Show Notifications",
+            MuxCommand::SelectTheme => "Change Theme",
             MuxCommand::Quit => "Quit",
             MuxCommand::ScrollUp => "Scroll Up",
             MuxCommand::ScrollDown => "Scroll Down",
@@ -252,6 +284,16 @@ impl MuxCommand {
             MuxCommand::EnableDeltaPager => "Enable Delta Pager",
             MuxCommand::DisableDeltaPager => "Disable Delta Pager",
             MuxCommand::CopyScrollback => "Copy Scrollback",
+            MuxCommand::CopyScrollbackAnsi => "Copy Scrollback with Colors",
+            MuxCommand::ExportScrollback => "Export Scrollback to File",
+            MuxCommand::ExportScrollbackAnsi => "Export Scrollback to File with Colors",
+            MuxCommand::TogglePipePane => "Toggle Pipe Pane to File",
+            MuxCommand::SearchScrollback => "Search Scrollback",
+            MuxCommand::SearchAllPanes => "Search All Panes",
+            MuxCommand::SearchCommandHistory => "Search Command History",
+            MuxCommand::RerunLastCommand => "Rerun Last Command",
+            MuxCommand::EnterCopyMode => "Copy Mode",
+            MuxCommand::PasteClipboard => "Paste",
             MuxCommand::OpenEditor => "Open Editor",
             MuxCommand::OpenWith => "Open With...",
             MuxCommand::OpenWithVSCode => "VS Code",
@@ -275,7 +317,12 @@ impl MuxCommand {
             MuxCommand::CloseTab => &["delete", "remove", "kill", "destroy", "close"],
             MuxCommand::SplitHorizontal => &["divide", "new pane", "hsplit"],
             MuxCommand::SplitVertical => &["divide", "new pane", "vsplit"],
+            MuxCommand::OpenAgentChat => &["acp", "agent", "ai", "assistant", "claude", "codex"],
             MuxCommand::ToggleZoom => &["maximize", "fullscreen", "expand"],
+            MuxCommand::EqualizePanes => &["balance", "even", "reset size"],
+            MuxCommand::ToggleBroadcast => {
+                &["synchronize", "sync panes", "type everywhere", "multiplex"]
+            }
             MuxCommand::FocusLeft => &["move left", "navigate left", "go left"],
             MuxCommand::FocusRight => &["move right", "navigate right", "go right"],
             MuxCommand::FocusUp => &["move up", "navigate up", "go up"],
@@ -287,6 +334,7 @@ impl MuxCommand {
             MuxCommand::ToggleHelp => {
                 &["shortcuts", "keybindings", "keyboard shortcuts", "hotkeys"]
             }
+            MuxCommand::SelectTheme => &["colors", "color scheme", "appearance", "dark", "light"],
             MuxCommand::Quit => &["exit", "close", "terminate"],
             MuxCommand::RenameTab => &["edit tab name", "change tab name"],
             MuxCommand::ToggleSidebar => &["show sidebar", "hide sidebar", "sidebar"],
@@ -298,6 +346,18 @@ impl MuxCommand {
             MuxCommand::EnableDeltaPager => &["git diff", "syntax highlighting", "pretty diff"],
             MuxCommand::DisableDeltaPager => &["git diff", "plain diff", "default pager"],
             MuxCommand::CopyScrollback => &["copy", "clipboard", "terminal output", "history"],
+            MuxCommand::CopyScrollbackAnsi => &["copy", "clipboard", "ansi", "escape codes"],
+            MuxCommand::ExportScrollback => &["save", "capture", "dump", "terminal output"],
+            MuxCommand::ExportScrollbackAnsi => &["save", "capture", "dump", "ansi"],
+            MuxCommand::TogglePipePane => &["pipe", "tee", "log", "record output"],
+            MuxCommand::SearchScrollback => &["find", "grep", "history", "terminal output"],
+            MuxCommand::SearchAllPanes => &["find", "grep", "everywhere", "global search"],
+            MuxCommand::SearchCommandHistory => {
+                &["history", "ctrl-r", "previous commands", "replay"]
+            }
+            MuxCommand::RerunLastCommand => &["again", "repeat", "replay", "!!"],
+            MuxCommand::EnterCopyMode => &["select", "copy", "yank", "visual"],
+            MuxCommand::PasteClipboard => &["paste", "clipboard", "insert"],
             MuxCommand::OpenEditor => &["editor", "ide", "code", "remote", "ssh"],
             MuxCommand::OpenWith => &["editor", "ide", "code", "remote", "ssh", "choose"],
             MuxCommand::OpenWithVSCode => &["vscode", "code", "remote", "editor", "ide"],
@@ -338,8 +398,11 @@ impl MuxCommand {
             MuxCommand::GoToTab9 => "Switch to tab 9",
             MuxCommand::SplitHorizontal => "Split the current pane horizontally",
             MuxCommand::SplitVertical => "Split the current pane vertically",
+            MuxCommand::OpenAgentChat => "Chat with a coding agent next to the current pane",
             MuxCommand::ClosePane => "Close the current pane",
             MuxCommand::ToggleZoom => "Toggle zoom on the current pane",
+            MuxCommand::EqualizePanes => "Give all panes in the tab equal space",
+            MuxCommand::ToggleBroadcast => "Send keystrokes to every terminal pane in the tab",
             MuxCommand::SwapPaneLeft => "Swap current pane with the one on the left",
             MuxCommand::SwapPaneRight => "Swap current pane with the one on the right",
             MuxCommand::SwapPaneUp => "Swap current pane with the one above",
@@ -366,6 +429,7 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette => "Open the command palette",
             MuxCommand::ToggleHelp => "Show or hide help overlay",
             MuxCommand::ShowNotifications => "Show notifications panel",
+            MuxCommand::SelectTheme => "Preview and switch the color theme",
             MuxCommand::Quit => "Exit the multiplexer",
             MuxCommand::ScrollUp => "Scroll up one line",
             MuxCommand::ScrollDown => "Scroll down one line",
@@ -376,6 +440,26 @@ impl MuxCommand {
             MuxCommand::EnableDeltaPager => "Use delta for syntax-highlighted git diffs",
             MuxCommand::DisableDeltaPager => "Use default pager for git diffs",
             MuxCommand::CopyScrollback => "Copy entire terminal scrollback to clipboard",
+            MuxCommand::CopyScrollbackAnsi => {
+                "Copy scrollback to clipboard, keeping colors as ANSI escapes"
+            }
+            MuxCommand::ExportScrollback => "Save the active pane's scrollback as a text file",
+            MuxCommand::ExportScrollbackAnsi => {
+                "Save the active pane's scrollback with colors as ANSI escapes"
+            }
+            MuxCommand::TogglePipePane => {
+                "Start or stop appending the active pane's live output to a file"
+            }
+            MuxCommand::SearchScrollback => "Search the active pane's scrollback (n/N to navigate)",
+            MuxCommand::SearchAllPanes => "Search scrollback in every pane and jump to a match",
+            MuxCommand::SearchCommandHistory => {
+                "Search commands run in any pane and replay one in the active pane"
+            }
+            MuxCommand::RerunLastCommand => "Run the active pane's previous command again",
+            MuxCommand::EnterCopyMode => {
+                "Select text in the active pane and copy it to the clipboard"
+            }
+            MuxCommand::PasteClipboard => "Paste the clipboard into the active pane",
             MuxCommand::OpenEditor => "Open default editor connected to sandbox via SSH",
             MuxCommand::OpenWith => "Choose editor to open sandbox with",
             MuxCommand::OpenWithVSCode => "Open VS Code connected to sandbox via SSH",
@@ -418,8 +502,11 @@ impl MuxCommand {
 
             MuxCommand::SplitHorizontal
             | MuxCommand::SplitVertical
+            | MuxCommand::OpenAgentChat
             | MuxCommand::ClosePane
             | MuxCommand::ToggleZoom
+            | MuxCommand::EqualizePanes
+            | MuxCommand::ToggleBroadcast
             | MuxCommand::SwapPaneLeft
             | MuxCommand::SwapPaneRight
             | MuxCommand::SwapPaneUp
@@ -451,6 +538,7 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette
             | MuxCommand::ToggleHelp
             | MuxCommand::ShowNotifications
+            | MuxCommand::SelectTheme
             | MuxCommand::Quit
             | MuxCommand::ScrollUp
             | MuxCommand::ScrollDown
@@ -461,7 +549,17 @@ impl MuxCommand {
 
             MuxCommand::EnableDeltaPager
             | MuxCommand::DisableDeltaPager
-            | MuxCommand::CopyScrollback => "Terminal",
+            | MuxCommand::CopyScrollback
+            | MuxCommand::CopyScrollbackAnsi
+            | MuxCommand::ExportScrollback
+            | MuxCommand::ExportScrollbackAnsi
+            | MuxCommand::TogglePipePane
+            | MuxCommand::SearchScrollback
+            | MuxCommand::SearchAllPanes
+            | MuxCommand::SearchCommandHistory
+            | MuxCommand::RerunLastCommand
+            | MuxCommand::EnterCopyMode
+            | MuxCommand::PasteClipboard => "Terminal",
 
             MuxCommand::OpenEditor
             | MuxCommand::OpenWith
@@ -524,8 +622,13 @@ impl MuxCommand {
             // Pane management - use Alt for pane operations
             MuxCommand::SplitHorizontal => Some((KeyModifiers::ALT, KeyCode::Char('-'))),
             MuxCommand::SplitVertical => Some((KeyModifiers::ALT, KeyCode::Char('\\'))),
+            MuxCommand::OpenAgentChat => None, // Access via command palette
             MuxCommand::ClosePane => Some((KeyModifiers::ALT, KeyCode::Char('w'))),
             MuxCommand::ToggleZoom => Some((KeyModifiers::ALT, KeyCode::Char('z'))),
+            MuxCommand::EqualizePanes => Some((KeyModifiers::ALT, KeyCode::Char('='))),
+            MuxCommand::ToggleBroadcast => {
+                Some((KeyModifiers::ALT | KeyModifiers::SHIFT, KeyCode::Char('B')))
+            }
 
             // Swap panes
             MuxCommand::SwapPaneLeft => {
@@ -590,6 +693,7 @@ impl MuxCommand {
             MuxCommand::ShowNotifications => {
                 Some((KeyModifiers::ALT | KeyModifiers::SHIFT, KeyCode::Char('n')))
             }
+            MuxCommand::SelectTheme => None, // Access via command palette
             MuxCommand::Quit => Some((KeyModifiers::CONTROL, KeyCode::Char('q'))),
             // Scroll - only when NOT focused on terminal (handled separately)
             MuxCommand::ScrollUp => None,
@@ -603,6 +707,16 @@ impl MuxCommand {
             MuxCommand::EnableDeltaPager => None,
             MuxCommand::DisableDeltaPager => None,
             MuxCommand::CopyScrollback => None,
+            MuxCommand::CopyScrollbackAnsi => None,
+            MuxCommand::ExportScrollback => None,
+            MuxCommand::ExportScrollbackAnsi => None,
+            MuxCommand::TogglePipePane => None,
+            MuxCommand::SearchScrollback => Some((KeyModifiers::ALT, KeyCode::Char('/'))),
+            MuxCommand::SearchAllPanes => None,
+            MuxCommand::SearchCommandHistory => Some((KeyModifiers::ALT, KeyCode::Char('y'))),
+            MuxCommand::RerunLastCommand => Some((KeyModifiers::ALT, KeyCode::Char('g'))),
+            MuxCommand::EnterCopyMode => Some((KeyModifiers::ALT, KeyCode::Char('c'))),
+            MuxCommand::PasteClipboard => Some((KeyModifiers::ALT, KeyCode::Char('v'))),
 
             // External tools
             MuxCommand::OpenEditor => Some((KeyModifiers::ALT, KeyCode::Char('e'))),
@@ -0,0 +1,214 @@
+//! Copy mode for the multiplexer: keyboard or mouse selection over a pane's
+//! grid, copied to the system clipboard.
+//!
+//! Positions use the same absolute line addressing as scrollback search: line 0
+//! is the oldest scrollback row. Copies go out three ways so at least one
+//! reaches the user: an OSC 52 sequence to the outer terminal (which works over
+//! SSH), the local clipboard, and a fallback file in the config directory.
+
+use base64::Engine;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::mux::layout::PaneId;
+
+const APP_NAME: &str = "cmux";
+const CLIPBOARD_FILE: &str = "clipboard.txt";
+
+/// A cell position in a pane's scrollback + viewport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CopyPos {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// An active copy-mode session on one pane.
+#[derive(Debug, Clone)]
+pub struct CopyState {
+    pub pane_id: PaneId,
+    pub cursor: CopyPos,
+    /// Start of the selection; `None` until selection begins (`v`/Space or mouse down).
+    pub anchor: Option<CopyPos>,
+    /// Started by a mouse drag, so releasing the button copies and exits.
+    pub mouse: bool,
+}
+
+impl CopyState {
+    pub fn new(pane_id: PaneId, cursor: CopyPos) -> Self {
+        Self {
+            pane_id,
+            cursor,
+            anchor: None,
+            mouse: false,
+        }
+    }
+
+    /// Selected range in reading order, inclusive of both ends.
+    pub fn range(&self) -> Option<(CopyPos, CopyPos)> {
+        let anchor = self.anchor?;
+        Some(if anchor <= self.cursor {
+            (anchor, self.cursor)
+        } else {
+            (self.cursor, anchor)
+        })
+    }
+
+    /// Highlighted `(line, col, len)` spans for lines `first..first + height`,
+    /// where `width` bounds full-line spans. Without a selection the cursor cell
+    /// is highlighted.
+    pub fn spans(&self, first: usize, height: usize, width: usize) -> Vec<(usize, usize, usize)> {
+        let (start, end) = self.range().unwrap_or((self.cursor, self.cursor));
+        (start.line.max(first)..=end.line.min((first + height).saturating_sub(1)))
+            .filter(|_| height > 0)
+            .map(|line| {
+                let from = if line == start.line { start.col } else { 0 };
+                let to = if line == end.line { end.col + 1 } else { width };
+                (line, from, to.saturating_sub(from))
+            })
+            .collect()
+    }
+}
+
+/// Extract the selected text. `line_text` returns the trimmed text of an
+/// absolute line; trailing whitespace is dropped and lines are joined with `\n`.
+pub fn selected_text(
+    start: CopyPos,
+    end: CopyPos,
+    line_text: impl Fn(usize) -> Option<String>,
+) -> String {
+    let mut out = Vec::new();
+    for line in start.line..=end.line {
+        let chars: Vec<char> = line_text(line).unwrap_or_default().chars().collect();
+        let from = if line == start.line { start.col } else { 0 };
+        let to = if line == end.line {
+            (end.col + 1).min(chars.len())
+        } else {
+            chars.len()
+        };
+        let text: String = chars
+            .get(from..to)
+            .map(|c| c.iter().collect())
+            .unwrap_or_default();
+        out.push(text.trim_end().to_string());
+    }
+    out.join("\n")
+}
+
+/// OSC 52 sequence that sets the outer terminal's clipboard. Inside tmux the
+/// sequence is wrapped in a DCS passthrough so it reaches the real terminal.
+pub fn osc52_sequence(text: &str) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let osc = format!("\x1b]52;c;{}\x07", encoded);
+    if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else {
+        osc
+    }
+}
+
+/// Decode the payload of an OSC 52 set request (`c;<base64>`). Queries (`?`)
+/// are not answered since they would let pane programs read the clipboard.
+pub fn decode_osc52(selection: &[u8], data: &[u8]) -> Option<String> {
+    if data == b"?" || selection.contains(&b'?') {
+        return None;
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Path of the fallback clipboard file.
+pub fn clipboard_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_NAME).join(CLIPBOARD_FILE))
+}
+
+/// Copy `text` everywhere we can reach: OSC 52 on `out`, the local clipboard,
+/// and the fallback file. Returns a short status for the user.
+pub fn copy_text(out: &mut impl Write, text: &str) -> String {
+    let _ = out.write_all(osc52_sequence(text).as_bytes());
+    let _ = out.flush();
+
+    let local = arboard::Clipboard::new().and_then(|mut c| c.set_text(text));
+
+    if let Some(path) = clipboard_file() {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = std::fs::write(&path, text) {
+            tracing::warn!("Failed to write clipboard file: {}", e);
+        }
+    }
+
+    let lines = text.lines().count().max(1);
+    match local {
+        Ok(()) => format!("Copied {} line(s)", lines),
+        // OSC 52 is fire-and-forget; mention the file in case the terminal ignores it.
+        Err(_) => match clipboard_file() {
+            Some(path) => format!(
+                "Copied {} line(s) via OSC 52 (also {})",
+                lines,
+                path.display()
+            ),
+            None => format!("Copied {} line(s) via OSC 52", lines),
+        },
+    }
+}
+
+/// Text to paste: the local clipboard, falling back to the clipboard file.
+pub fn paste_text() -> Option<String> {
+    if let Ok(text) = arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+        if !text.is_empty() {
+            return Some(text);
+        }
+    }
+    std::fs::read_to_string(clipboard_file()?)
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize, col: usize) -> CopyPos {
+        CopyPos { line, col }
+    }
+
+    #[test]
+    fn selection_text_spans_lines_in_either_direction() {
+        let lines = ["hello world", "second line", "third"];
+        let get = |l: usize| lines.get(l).map(|s| s.to_string());
+
+        assert_eq!(selected_text(pos(0, 6), pos(0, 10), get), "world");
+        assert_eq!(
+            selected_text(pos(0, 6), pos(2, 2), get),
+            "world\nsecond line\nthi"
+        );
+
+        let mut state = CopyState::new(PaneId::new(), pos(0, 6));
+        state.anchor = Some(pos(2, 2));
+        assert_eq!(state.range(), Some((pos(0, 6), pos(2, 2))));
+        assert_eq!(
+            state.spans(1, 5, 20),
+            vec![(1, 0, 20), (2, 0, 3)],
+            "spans are clipped to the visible lines"
+        );
+    }
+
+    #[test]
+    fn osc52_round_trips() {
+        let seq = osc52_sequence("make: *** [all] Error 2");
+        let payload = seq
+            .rsplit(';')
+            .next()
+            .unwrap()
+            .trim_end_matches("\x1b\\")
+            .trim_end_matches('\x07');
+        assert_eq!(
+            decode_osc52(b"c", payload.as_bytes()).as_deref(),
+            Some("make: *** [all] Error 2")
+        );
+        assert_eq!(decode_osc52(b"c", b"?"), None);
+    }
+}
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use crate::models::{NotificationLevel, SandboxSummary};
 use crate::mux::colors::TerminalColors;
+use crate::mux::history::ShellEvent;
 use crate::mux::layout::PaneId;
 use crate::mux::onboard::OnboardEvent;
 
@@ -19,7 +20,7 @@ pub enum MuxEvent {
     SandboxRefreshFailed(String),
     /// A sandbox was created (includes tab_id for correlation with placeholder).
     SandboxCreated {
-        sandbox: SandboxSummary,
+        sandbox: Box<SandboxSummary>,
         tab_id: Option<String>,
     },
     /// Attach tab metadata to a sandbox (used to keep CMUX_TAB_ID aligned with UI tab).
@@ -30,6 +31,10 @@ pub enum MuxEvent {
     SandboxConnectionChanged { sandbox_id: String, connected: bool },
     /// Terminal output received.
     TerminalOutput { pane_id: crate::mux::layout::PaneId },
+    /// A pane's shell ran or finished a command (OSC 133 shell integration).
+    ShellCommand { pane_id: PaneId, event: ShellEvent },
+    /// Put text on the clipboard (copy mode or a pane program's OSC 52).
+    CopyToClipboard { text: String },
     /// An error occurred.
     Error(String),
     /// A system notification to display.
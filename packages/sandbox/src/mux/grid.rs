@@ -14,6 +14,15 @@ use super::character::{CharacterStyles, Row, SharedStyles, TerminalCharacter};
 /// Maximum number of lines to keep in scrollback.
 const MAX_SCROLLBACK_LINES: usize = 10_000;
 
+/// How captured pane contents are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// Text only.
+    Plain,
+    /// Text with SGR escapes, so `cat`/`less -R` show the original colors.
+    Ansi,
+}
+
 /// Terminal grid with tripartite design for efficient scrolling.
 #[derive(Clone, Debug)]
 pub struct Grid {
@@ -555,6 +564,32 @@ impl Grid {
     pub fn viewport_iter(&self) -> impl Iterator<Item = &Row> {
         self.viewport.iter()
     }
+
+    /// Scrollback and screen contents as text, one line per logical line:
+    /// rows the terminal wrapped are joined back together. Trailing blank
+    /// lines are dropped.
+    pub fn capture(&self, format: CaptureFormat) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let rows = self
+            .lines_above
+            .iter()
+            .chain(self.viewport.iter())
+            .chain(self.lines_below.iter());
+        for row in rows {
+            let text = match format {
+                CaptureFormat::Plain => row.to_plain_string(),
+                CaptureFormat::Ansi => row.to_ansi_string(),
+            };
+            match lines.last_mut() {
+                Some(last) if !row.is_canonical => last.push_str(&text),
+                _ => lines.push(text),
+            }
+        }
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
 }
 
 #[cfg(test)]
@@ -614,6 +649,26 @@ mod tests {
         assert_eq!(grid.lines_above[0].columns[0].character, '1');
     }
 
+    #[test]
+    fn test_grid_capture() {
+        let mut grid = Grid::new(3, 4);
+        for (i, line) in ["one", "twox", "yz"].iter().enumerate() {
+            if i > 0 {
+                grid.newline();
+                grid.cursor_col = 0;
+            }
+            for c in line.chars() {
+                grid.put_char(c);
+            }
+        }
+        // Pretend the terminal wrapped "twox" onto "yz".
+        grid.viewport[2].is_canonical = false;
+        grid.newline();
+
+        assert_eq!(grid.lines_above.len(), 1);
+        assert_eq!(grid.capture(CaptureFormat::Plain), "one\ntwoxyz");
+    }
+
     #[test]
     fn test_grid_changed_lines() {
         let mut grid = Grid::new(24, 80);
@@ -0,0 +1,229 @@
+//! Shell command history for the multiplexer.
+//!
+//! Shells with OSC 133 integration mark where the prompt ends (`B`), where the
+//! typed command starts running (`C`) and when it finishes (`D;<exit>`). The
+//! terminal reads the command line off the grid between `B` and `C`; each pane
+//! keeps its own history, and the history view searches all of them at once.
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use crate::mux::layout::PaneId;
+use crate::palette::fuzzy_score;
+
+/// Commands kept per pane; older ones are dropped first.
+pub const MAX_COMMANDS_PER_PANE: usize = 500;
+
+/// Maximum number of entries shown by the history view.
+pub const MAX_HISTORY_RESULTS: usize = 200;
+
+/// Shell-integration events a terminal reports after processing output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellEvent {
+    /// The user ran a command (OSC 133 `C`).
+    CommandStarted(String),
+    /// The running command finished (OSC 133 `D`), with its exit status if given.
+    CommandFinished(Option<i32>),
+}
+
+/// A command that ran in a pane.
+#[derive(Debug, Clone)]
+pub struct CommandRecord {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub ran_at: DateTime<Utc>,
+    /// Position in the overall order commands ran in, across panes.
+    seq: u64,
+}
+
+/// Command history for every pane.
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    panes: HashMap<PaneId, VecDeque<CommandRecord>>,
+    next_seq: u64,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a shell-integration event from `pane_id`.
+    pub fn apply(&mut self, pane_id: PaneId, event: ShellEvent) {
+        match event {
+            ShellEvent::CommandStarted(command) => self.record(pane_id, command),
+            ShellEvent::CommandFinished(exit_code) => {
+                if let Some(last) = self.panes.get_mut(&pane_id).and_then(|h| h.back_mut()) {
+                    last.exit_code = exit_code;
+                }
+            }
+        }
+    }
+
+    /// Record a command. Repeating the previous command only refreshes it.
+    pub fn record(&mut self, pane_id: PaneId, command: String) {
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            return;
+        }
+        let history = self.panes.entry(pane_id).or_default();
+        if history.back().is_some_and(|last| last.command == command) {
+            history.pop_back();
+        }
+        if history.len() >= MAX_COMMANDS_PER_PANE {
+            history.pop_front();
+        }
+        history.push_back(CommandRecord {
+            command,
+            exit_code: None,
+            ran_at: Utc::now(),
+            seq: self.next_seq,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Commands run in a pane, oldest first.
+    pub fn for_pane(&self, pane_id: PaneId) -> impl Iterator<Item = &CommandRecord> {
+        self.panes.get(&pane_id).into_iter().flatten()
+    }
+
+    /// The most recent command run in a pane.
+    pub fn last(&self, pane_id: PaneId) -> Option<&CommandRecord> {
+        self.panes.get(&pane_id)?.back()
+    }
+
+    /// Commands across all panes that fuzzy-match `query`, best match first
+    /// and newest first among equals. Each command line appears once, under
+    /// the pane that ran it most recently.
+    pub fn search(&self, query: &str) -> Vec<(PaneId, &CommandRecord)> {
+        let mut newest: HashMap<&str, (PaneId, &CommandRecord)> = HashMap::new();
+        for (pane_id, history) in &self.panes {
+            for record in history {
+                let entry = newest
+                    .entry(record.command.as_str())
+                    .or_insert((*pane_id, record));
+                if record.seq > entry.1.seq {
+                    *entry = (*pane_id, record);
+                }
+            }
+        }
+
+        let mut scored: Vec<(i64, PaneId, &CommandRecord)> = newest
+            .into_values()
+            .filter_map(|(pane_id, record)| {
+                let score = if query.is_empty() {
+                    0
+                } else {
+                    fuzzy_score(query, &record.command)?
+                };
+                Some((score, pane_id, record))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(b.2.seq.cmp(&a.2.seq)));
+        scored
+            .into_iter()
+            .take(MAX_HISTORY_RESULTS)
+            .map(|(_, pane_id, record)| (pane_id, record))
+            .collect()
+    }
+}
+
+/// An entry in the history view.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub pane_label: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+}
+
+/// State of the open history view.
+pub struct HistoryView<'a> {
+    pub input: tui_textarea::TextArea<'a>,
+    pub results: Vec<HistoryEntry>,
+    /// Index into `results` of the current selection.
+    pub current: usize,
+}
+
+impl Default for HistoryView<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> HistoryView<'a> {
+    pub fn new() -> Self {
+        Self {
+            input: tui_textarea::TextArea::default(),
+            results: Vec::new(),
+            current: 0,
+        }
+    }
+
+    pub fn query(&self) -> String {
+        self.input.lines().join("")
+    }
+
+    pub fn selected(&self) -> Option<&HistoryEntry> {
+        self.results.get(self.current)
+    }
+
+    pub fn select_up(&mut self) {
+        let len = self.results.len();
+        if len > 0 {
+            self.current = (self.current + len - 1) % len;
+        }
+    }
+
+    pub fn select_down(&mut self) {
+        let len = self.results.len();
+        if len > 0 {
+            self.current = (self.current + 1) % len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_per_pane_and_attaches_exit_codes() {
+        let (a, b) = (PaneId::new(), PaneId::new());
+        let mut history = CommandHistory::new();
+        history.apply(a, ShellEvent::CommandStarted("cargo test ".to_string()));
+        history.apply(a, ShellEvent::CommandFinished(Some(101)));
+        history.apply(b, ShellEvent::CommandStarted("ls".to_string()));
+        history.apply(a, ShellEvent::CommandStarted("   ".to_string()));
+
+        let last = history.last(a).unwrap();
+        assert_eq!(last.command, "cargo test");
+        assert_eq!(last.exit_code, Some(101));
+        assert_eq!(history.for_pane(b).count(), 1);
+
+        history.record(a, "cargo test".to_string());
+        assert_eq!(history.for_pane(a).count(), 1);
+        assert_eq!(history.last(a).unwrap().exit_code, None);
+    }
+
+    #[test]
+    fn search_is_fuzzy_and_deduplicated_across_panes() {
+        let (a, b) = (PaneId::new(), PaneId::new());
+        let mut history = CommandHistory::new();
+        history.record(a, "git status".to_string());
+        history.record(a, "cargo build".to_string());
+        history.record(b, "git status".to_string());
+
+        let all = history.search("");
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].0, b);
+        assert_eq!(all[0].1.command, "git status");
+
+        let matches: Vec<&str> = history
+            .search("cbld")
+            .into_iter()
+            .map(|(_, record)| record.command.as_str())
+            .collect();
+        assert_eq!(matches, ["cargo build"]);
+    }
+}
@@ -1,4 +1,5 @@
 use ratatui::layout::Rect;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Unique identifier for a sandbox.
@@ -84,7 +85,7 @@ impl std::fmt::Display for TabId {
 }
 
 /// Direction for splitting panes or navigation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     Horizontal,
     Vertical,
@@ -100,7 +101,7 @@ pub enum NavDirection {
 }
 
 /// Content that can be displayed in a pane.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum PaneContent {
     /// An empty placeholder pane
     #[default]
@@ -454,60 +455,116 @@ impl LayoutNode {
         best_candidate.map(|(id, _)| id)
     }
 
-    /// Resize the split containing the given pane in the specified direction.
+    /// Resize the pane by moving the border of its nearest enclosing split that
+    /// runs across `direction`: Left/Up move the border left/up, Right/Down move
+    /// it right/down. Nested splits are resized independently.
     pub fn resize_pane(&mut self, pane_id: PaneId, direction: NavDirection, delta: f32) {
         self.resize_pane_internal(pane_id, direction, delta);
     }
 
-    fn resize_pane_internal(&mut self, pane_id: PaneId, direction: NavDirection, delta: f32) {
+    fn resize_pane_internal(
+        &mut self,
+        pane_id: PaneId,
+        direction: NavDirection,
+        delta: f32,
+    ) -> bool {
         match self {
-            LayoutNode::Pane(_) => {}
+            LayoutNode::Pane(_) => false,
             LayoutNode::Split {
                 direction: split_dir,
                 ratio,
                 first,
                 second,
             } => {
-                let first_contains = first.contains_pane(pane_id);
-                let second_contains = second.contains_pane(pane_id);
-
-                // Check if this split is relevant to the resize direction
-                let is_relevant = matches!(
-                    (split_dir, direction),
-                    (
-                        Direction::Vertical,
-                        NavDirection::Left | NavDirection::Right
-                    ) | (Direction::Horizontal, NavDirection::Up | NavDirection::Down)
-                );
-
-                if is_relevant && (first_contains || second_contains) {
-                    let adjustment = match direction {
-                        NavDirection::Left | NavDirection::Up => {
-                            if first_contains {
-                                -delta
-                            } else {
-                                delta
-                            }
-                        }
-                        NavDirection::Right | NavDirection::Down => {
-                            if first_contains {
-                                delta
-                            } else {
-                                -delta
-                            }
-                        }
-                    };
-
-                    *ratio = (*ratio + adjustment).clamp(0.1, 0.9);
+                // Prefer the innermost split so nested layouts resize locally
+                let handled = if first.contains_pane(pane_id) {
+                    first.resize_pane_internal(pane_id, direction, delta)
+                } else if second.contains_pane(pane_id) {
+                    second.resize_pane_internal(pane_id, direction, delta)
                 } else {
-                    // Recurse into the appropriate child
-                    if first_contains {
-                        first.resize_pane_internal(pane_id, direction, delta);
-                    } else if second_contains {
-                        second.resize_pane_internal(pane_id, direction, delta);
-                    }
+                    return false;
+                };
+                if handled {
+                    return true;
                 }
+
+                let adjustment = match (*split_dir, direction) {
+                    (Direction::Vertical, NavDirection::Left)
+                    | (Direction::Horizontal, NavDirection::Up) => -delta,
+                    (Direction::Vertical, NavDirection::Right)
+                    | (Direction::Horizontal, NavDirection::Down) => delta,
+                    _ => return false,
+                };
+                *ratio = (*ratio + adjustment).clamp(0.1, 0.9);
+                true
+            }
+        }
+    }
+
+    /// Swap the positions of two panes, keeping their IDs and content together.
+    pub fn swap_panes(&mut self, a: PaneId, b: PaneId) -> bool {
+        if a == b {
+            return false;
+        }
+        let (Some(pane_a), Some(pane_b)) = (self.find_pane(a).cloned(), self.find_pane(b).cloned())
+        else {
+            return false;
+        };
+        // Areas belong to the slot, not the pane, so keep them in place.
+        self.for_each_pane_mut(&mut |pane| {
+            let replacement = if pane.id == a {
+                &pane_b
+            } else if pane.id == b {
+                &pane_a
+            } else {
+                return;
+            };
+            let area = pane.area;
+            *pane = replacement.clone();
+            pane.area = area;
+        });
+        true
+    }
+
+    fn for_each_pane_mut(&mut self, f: &mut impl FnMut(&mut Pane)) {
+        match self {
+            LayoutNode::Pane(pane) => f(pane),
+            LayoutNode::Split { first, second, .. } => {
+                first.for_each_pane_mut(f);
+                second.for_each_pane_mut(f);
+            }
+        }
+    }
+
+    /// Reset split ratios so panes sharing a row or column get equal space.
+    pub fn equalize(&mut self) {
+        if let LayoutNode::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            let a = first.units_along(*direction) as f32;
+            let b = second.units_along(*direction) as f32;
+            *ratio = a / (a + b);
+            first.equalize();
+            second.equalize();
+        }
+    }
+
+    /// Number of panes laid out side by side along `direction`.
+    fn units_along(&self, direction: Direction) -> usize {
+        match self {
+            LayoutNode::Split {
+                direction: split_dir,
+                first,
+                second,
+                ..
+            } if *split_dir == direction => {
+                first.units_along(direction) + second.units_along(direction)
             }
+            _ => 1,
         }
     }
 }
@@ -671,6 +728,23 @@ impl Tab {
         };
         self.layout.resize_pane(active_id, direction, delta);
     }
+
+    /// Swap the active pane with its neighbor in the given direction.
+    /// Focus follows the moved pane.
+    pub fn swap(&mut self, direction: NavDirection) -> bool {
+        let Some(active_id) = self.active_pane else {
+            return false;
+        };
+        let Some(neighbor_id) = self.layout.find_neighbor(active_id, direction) else {
+            return false;
+        };
+        self.layout.swap_panes(active_id, neighbor_id)
+    }
+
+    /// Give every pane in the tab an equal share of its row or column.
+    pub fn equalize(&mut self) {
+        self.layout.equalize();
+    }
 }
 
 /// A workspace for a single sandbox containing all its tabs/splits.
@@ -1023,6 +1097,14 @@ impl WorkspaceManager {
         self.workspaces.get_mut(&sandbox_id)
     }
 
+    /// All panes in every workspace and tab.
+    pub fn panes(&self) -> impl Iterator<Item = &Pane> {
+        self.workspaces
+            .values()
+            .flat_map(|workspace| &workspace.tabs)
+            .flat_map(|tab| tab.layout.panes())
+    }
+
     /// Get all sandbox IDs in order.
     pub fn sandbox_ids(&self) -> &[SandboxId] {
         &self.sandbox_order
@@ -1380,4 +1462,68 @@ mod tests {
         assert_eq!(tab.layout.pane_count(), 1);
         assert!(tab.contains_pane(tab.active_pane.expect("active pane should exist")));
     }
+
+    fn split_ratios(node: &LayoutNode) -> Vec<f32> {
+        match node {
+            LayoutNode::Pane(_) => Vec::new(),
+            LayoutNode::Split {
+                ratio,
+                first,
+                second,
+                ..
+            } => {
+                let mut out = vec![*ratio];
+                out.extend(split_ratios(first));
+                out.extend(split_ratios(second));
+                out
+            }
+        }
+    }
+
+    #[test]
+    fn resize_targets_nearest_matching_split() {
+        // A | (B / C)
+        let mut tab = Tab::new("t");
+        tab.split(Direction::Vertical, Pane::empty());
+        tab.split(Direction::Horizontal, Pane::empty());
+
+        tab.resize(NavDirection::Up, 0.1);
+        assert_eq!(split_ratios(&tab.layout), vec![0.5, 0.4]);
+
+        // No horizontal-axis split inside the right column, so the outer one moves.
+        tab.resize(NavDirection::Left, 0.1);
+        assert_eq!(split_ratios(&tab.layout), vec![0.4, 0.4]);
+
+        for _ in 0..10 {
+            tab.resize(NavDirection::Right, 0.1);
+        }
+        assert_eq!(split_ratios(&tab.layout)[0], 0.9);
+    }
+
+    #[test]
+    fn swap_moves_pane_and_keeps_focus_on_it() {
+        let mut tab = Tab::new("t");
+        let left = tab.active_pane.unwrap();
+        tab.split(Direction::Vertical, Pane::empty());
+        let right = tab.active_pane.unwrap();
+        tab.layout.calculate_areas(Rect::new(0, 0, 80, 24));
+
+        assert!(tab.swap(NavDirection::Left));
+        assert_eq!(tab.layout.pane_ids(), vec![right, left]);
+        assert_eq!(tab.active_pane, Some(right));
+        assert!(!tab.swap(NavDirection::Left));
+    }
+
+    #[test]
+    fn equalize_splits_rows_evenly() {
+        // A | B | C built as A | (B | C)
+        let mut tab = Tab::new("t");
+        tab.split(Direction::Vertical, Pane::empty());
+        tab.split(Direction::Vertical, Pane::empty());
+        tab.resize(NavDirection::Left, 0.2);
+        tab.equalize();
+        let ratios = split_ratios(&tab.layout);
+        assert!((ratios[0] - 1.0 / 3.0).abs() < f32::EPSILON);
+        assert_eq!(ratios[1], 0.5);
+    }
 }
@@ -1,19 +1,24 @@
 pub mod character;
 pub mod colors;
 pub mod commands;
+pub mod copy;
 pub mod events;
 pub mod grid;
+pub mod history;
 pub mod layout;
 pub mod onboard;
 pub mod palette;
 pub mod runner;
+pub mod search;
+pub mod session;
 pub mod sidebar;
 pub mod state;
 pub mod terminal;
+pub mod theme;
 pub mod ui;
 
 pub use colors::{
     get_outer_bg, get_outer_fg, query_outer_terminal_colors, spawn_theme_change_listener,
     TerminalColors, ThemeChangeEvent,
 };
-pub use runner::run_mux_tui;
+pub use runner::{run_mux_tui, run_mux_tui_with_session};
@@ -3,27 +3,33 @@ use crossterm::{
     cursor::SetCursorStyle,
     event::{
         DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event, EventStream, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
-        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::StreamExt;
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::time::MissedTickBehavior;
 
+use crate::acp_client::{AcpProvider, ChatPane};
+use crate::client_auth::http_client;
 use crate::mux::colors::{query_outer_terminal_colors, spawn_theme_change_listener};
 use crate::mux::commands::MuxCommand;
+use crate::mux::copy::copy_text;
 use crate::mux::events::MuxEvent;
-use crate::mux::layout::{ClosedTabInfo, PaneContent, PaneExitOutcome, SandboxId, TabId};
+use crate::mux::layout::{ClosedTabInfo, PaneContent, PaneExitOutcome, PaneId, SandboxId, TabId};
 use crate::mux::onboard::{
     pull_image_with_progress, run_onboard_check, OnboardEvent, OnboardPhase, OnboardState,
 };
+use crate::mux::search::SearchScope;
+use crate::mux::session::SessionSnapshot;
 use crate::mux::state::{FocusArea, MuxApp};
 use crate::mux::terminal::{
     connect_to_sandbox, create_terminal_manager, invalidate_all_render_caches,
@@ -37,7 +43,29 @@ use crate::terminal_guard;
 ///
 /// If `workspace_path` is provided, sandboxes created during the session will upload
 /// that directory (defaulting to the current working directory).
-pub async fn run_mux_tui(base_url: String, workspace_path: Option<PathBuf>) -> Result<()> {
+pub async fn run_mux_tui(
+    base_url: String,
+    workspace_path: Option<PathBuf>,
+    auth_token: Option<String>,
+) -> Result<()> {
+    run_mux_tui_with_session(base_url, workspace_path, false, auth_token).await
+}
+
+/// Run the multiplexer TUI, optionally restoring the last saved session.
+///
+/// With `attach` set, the layout, working directories and scrollback saved by a
+/// previous run are restored instead of creating a new sandbox. If no session
+/// was saved, this behaves like [`run_mux_tui`].
+///
+/// `auth_token` is sent on every request to the daemon at `base_url`.
+pub async fn run_mux_tui_with_session(
+    base_url: String,
+    workspace_path: Option<PathBuf>,
+    attach: bool,
+    auth_token: Option<String>,
+) -> Result<()> {
+    crate::client_auth::set_auth_token(auth_token);
+
     // Query outer terminal colors BEFORE entering alternate screen
     // This allows us to inherit the host terminal's theme
     let _outer_colors = query_outer_terminal_colors();
@@ -58,7 +86,15 @@ pub async fn run_mux_tui(base_url: String, workspace_path: Option<PathBuf>) -> R
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_main_loop(&mut terminal, base_url, workspace_path).await;
+    // Chat panes spawn their connections with `spawn_local`.
+    let result = tokio::task::LocalSet::new()
+        .run_until(run_main_loop(
+            &mut terminal,
+            base_url,
+            workspace_path,
+            attach,
+        ))
+        .await;
 
     // Cleanup must happen in reverse order, and PopKeyboardEnhancementFlags
     // must be sent BEFORE LeaveAlternateScreen to properly restore terminal state.
@@ -98,6 +134,7 @@ async fn run_main_loop<B: ratatui::backend::Backend + std::io::Write>(
     terminal: &mut Terminal<B>,
     base_url: String,
     workspace_path: Option<PathBuf>,
+    attach: bool,
 ) -> Result<()> {
     let workspace = workspace_path
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
@@ -126,7 +163,17 @@ async fn run_main_loop<B: ratatui::backend::Backend + std::io::Write>(
         refresh_sandboxes_periodically(refresh_url, refresh_tx).await;
     });
 
-    // Always create a new sandbox on startup with the current working directory
+    // When attaching, restore the saved session once the sandbox list arrives
+    if attach {
+        app.pending_session = SessionSnapshot::load();
+        if app.pending_session.is_none() {
+            app.set_status("No saved session found; starting a new one");
+        }
+    }
+    let restoring = app.pending_session.is_some();
+
+    // Create a new sandbox on startup with the current working directory,
+    // unless a saved session is being restored
     let init_tx = event_tx.clone();
     let init_url = base_url.clone();
     let initial_workspace = workspace.clone();
@@ -134,6 +181,9 @@ async fn run_main_loop<B: ratatui::backend::Backend + std::io::Write>(
         // First refresh to populate sidebar
         let _ = refresh_sandboxes(&init_url, &init_tx).await;
 
+        if restoring {
+            return;
+        }
         let _ = init_tx.send(MuxEvent::CreateSandboxWithWorkspace {
             workspace_path: initial_workspace,
             tab_id: Some(TabId::new().to_string()),
@@ -189,9 +239,24 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
     let mut status_tick = tokio::time::interval(Duration::from_millis(33));
     let mut render_tick = tokio::time::interval(Duration::from_millis(8));
     render_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // Save the session regularly so it survives a dropped SSH connection
+    let mut session_tick = tokio::time::interval(Duration::from_secs(15));
+    session_tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
     loop {
+        sync_chat_panes(&mut app);
         tokio::select! {
+            _ = session_tick.tick() => {
+                if let Ok(manager) = terminal_manager.try_lock() {
+                    save_session(&app, &manager);
+                }
+                for chat in app.chats.values_mut() {
+                    chat.flush_history();
+                }
+            }
+            _ = update_chats(&mut app.chats) => {
+                redraw_needed = true;
+            }
             _ = status_tick.tick() => {
                 let had_status = app.status_message.is_some();
                 app.clear_expired_status();
@@ -317,6 +382,10 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
                         let event_tx_for_handler = app.event_tx.clone();
                         handle_onboard_event(&mut app, onboard_event.clone(), &event_tx_for_handler);
                     }
+                    MuxEvent::CopyToClipboard { text } => {
+                        let status = copy_text(terminal.backend_mut(), text);
+                        app.set_status(status);
+                    }
                     MuxEvent::SendTerminalInput { pane_id, input } => {
                         if let Ok(mut manager) = terminal_manager.try_lock() {
                             if !manager.send_input(*pane_id, input.clone()) {
@@ -330,7 +399,7 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
                         let sandbox_id = sandbox_id.clone();
                         let command = command.clone();
                         tokio::spawn(async move {
-                            let client = reqwest::Client::new();
+                            let client = http_client();
                             let url = format!(
                                 "{}/sandboxes/{}/exec",
                                 base_url.trim_end_matches('/'),
@@ -369,9 +438,76 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
         }
     }
 
+    save_session(&app, &*terminal_manager.lock().await);
+    for chat in app.chats.values_mut() {
+        chat.flush_history();
+    }
+
     Ok(())
 }
 
+/// Start chats for new chat panes and drop the ones whose pane has closed.
+fn sync_chat_panes(app: &mut MuxApp<'_>) {
+    let mut open = HashSet::new();
+    for pane in app.workspace_manager.panes() {
+        let PaneContent::Chat {
+            sandbox_id,
+            provider,
+        } = &pane.content
+        else {
+            continue;
+        };
+        open.insert(pane.id);
+        if !app.chats.contains_key(&pane.id) {
+            let provider = AcpProvider::from_short_name(provider).unwrap_or_default();
+            let chat = ChatPane::start(app.base_url.clone(), sandbox_id.clone(), provider);
+            app.chats.insert(pane.id, chat);
+        }
+    }
+    app.chats.retain(|pane_id, chat| {
+        if !open.contains(pane_id) {
+            chat.flush_history();
+        }
+        open.contains(pane_id)
+    });
+}
+
+/// Apply the next event from any running chat. Pends while there are none.
+async fn update_chats(chats: &mut HashMap<PaneId, ChatPane>) {
+    if chats.is_empty() {
+        return std::future::pending().await;
+    }
+    futures::future::select_all(chats.values_mut().map(|chat| Box::pin(chat.update()))).await;
+}
+
+/// Hand input to the active pane's chat, if it is one. A chat the user quits
+/// closes its pane. Returns false when the active pane isn't a chat.
+fn forward_to_chat(app: &mut MuxApp<'_>, event: Event) -> bool {
+    let Some(pane_id) = app.active_pane_id() else {
+        return false;
+    };
+    let Some(chat) = app.chats.get_mut(&pane_id) else {
+        return false;
+    };
+    if !chat.handle_input(event) {
+        app.workspace_manager.handle_pane_exit(pane_id);
+        app.set_status("Chat closed");
+    }
+    true
+}
+
+/// Write the current layout and scrollback for `cmux mux attach`.
+fn save_session(app: &MuxApp<'_>, manager: &crate::mux::terminal::TerminalManager) {
+    // Don't clobber a saved session before it has been restored, or with an empty one.
+    if app.pending_session.is_some() || app.workspace_manager.sandbox_count() == 0 {
+        return;
+    }
+    let snapshot = SessionSnapshot::capture_with_terminals(&app.workspace_manager, manager);
+    if let Err(e) = snapshot.save() {
+        tracing::warn!("Failed to save mux session: {}", e);
+    }
+}
+
 fn fallback_terminal_size() -> (u16, u16) {
     let (fallback_cols, fallback_rows) = crossterm::terminal::size().unwrap_or((80, 24));
     (fallback_rows, fallback_cols)
@@ -383,6 +519,34 @@ fn try_consume_pending_connection(
     app: &mut MuxApp<'_>,
     terminal_manager: &crate::mux::terminal::SharedTerminalManager,
 ) {
+    // Reconnect panes restored from a saved session
+    for restore in app.pending_pane_connects.drain(..) {
+        let manager = terminal_manager.clone();
+        let event_tx = app.event_tx.clone();
+        let (rows, cols) = fallback_terminal_size();
+        tokio::spawn(async move {
+            manager
+                .lock()
+                .await
+                .restore_pane(restore.pane_id, restore.restored);
+            if let Err(e) = connect_to_sandbox(
+                manager,
+                restore.pane_id,
+                restore.sandbox_id,
+                Some(restore.tab_id),
+                cols,
+                rows,
+            )
+            .await
+            {
+                let _ = event_tx.send(MuxEvent::Error(format!(
+                    "Failed to reconnect restored pane: {}",
+                    e
+                )));
+            }
+        });
+    }
+
     // Process all pending connections in the queue
     while let Some(sandbox_id) = app.pending_connects.pop_front() {
         // Verify the sandbox exists in sidebar
@@ -425,6 +589,15 @@ fn connect_sandbox_terminal(
     let Some(pane_id) = pane_id else {
         return;
     };
+    let is_terminal = app
+        .workspace_manager
+        .get_workspace(sandbox_layout_id)
+        .and_then(|ws| ws.active_tab())
+        .and_then(|tab| tab.layout.find_pane(pane_id))
+        .is_some_and(|pane| matches!(pane.content, PaneContent::Terminal { .. }));
+    if !is_terminal {
+        return;
+    }
 
     // Update the pane's sandbox_id in the workspace
     if let Some(ws) = app.workspace_manager.get_workspace_mut(sandbox_layout_id) {
@@ -735,6 +908,30 @@ fn handle_input(
                 return false;
             }
 
+            // Handle scrollback search prompt
+            if app.focus == FocusArea::Search {
+                handle_search_key(app, key);
+                return false;
+            }
+
+            // Handle command history view
+            if app.focus == FocusArea::History {
+                handle_history_key(app, key);
+                return false;
+            }
+
+            // Handle theme picker
+            if app.focus == FocusArea::ThemePicker {
+                handle_theme_picker_key(app, key);
+                return false;
+            }
+
+            // Handle copy-mode cursor and selection keys
+            if app.focus == FocusArea::Copy {
+                handle_copy_key(app, key);
+                return false;
+            }
+
             // Handle command palette mode
             if app.focus == FocusArea::CommandPalette {
                 match key.code {
@@ -848,6 +1045,9 @@ fn handle_input(
                     }
                 }
                 FocusArea::MainArea => {
+                    if forward_to_chat(app, Event::Key(key)) {
+                        return false;
+                    }
                     // Check if we should forward input to the terminal
                     let should_forward = if let Some(pane_id) = app.active_pane_id() {
                         let guard = terminal_manager.try_lock();
@@ -857,12 +1057,12 @@ fn handle_input(
                     };
 
                     if should_forward {
-                        // Forward input to terminal
-                        if let Some(pane_id) = app.active_pane_id() {
-                            let input = key_to_terminal_input(key.modifiers, key.code);
-                            if !input.is_empty() {
-                                if let Ok(mut guard) = terminal_manager.try_lock() {
-                                    guard.send_input(pane_id, input);
+                        // Forward input to terminal (every pane in the tab while broadcasting)
+                        let input = key_to_terminal_input(key.modifiers, key.code);
+                        if !input.is_empty() {
+                            if let Ok(mut guard) = terminal_manager.try_lock() {
+                                for pane_id in app.input_targets() {
+                                    guard.send_input(pane_id, input.clone());
                                 }
                             }
                         }
@@ -902,6 +1102,9 @@ fn handle_input(
                                     app.focus = FocusArea::Sidebar;
                                 }
                             }
+                            KeyCode::Char('/') => {
+                                app.open_search(SearchScope::ActivePane);
+                            }
                             _ => {}
                         }
                     }
@@ -915,12 +1118,31 @@ fn handle_input(
                 FocusArea::Onboard => {
                     // Onboard overlay is handled before focus-specific input.
                 }
+                FocusArea::Search
+                | FocusArea::Copy
+                | FocusArea::History
+                | FocusArea::ThemePicker => {
+                    // Search prompt, copy mode, history and the theme picker are
+                    // handled before focus-specific input.
+                }
             }
         }
         Event::Mouse(mouse_event) => {
             // Handle mouse events (scrolling, clicking)
             use crossterm::event::{MouseButton, MouseEventKind};
 
+            // Chats scroll their own transcript
+            if app.focus == FocusArea::MainArea
+                && matches!(
+                    mouse_event.kind,
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                )
+                && active_pane_cell(app, mouse_event.column, mouse_event.row, false).is_some()
+                && forward_to_chat(app, Event::Mouse(mouse_event))
+            {
+                return false;
+            }
+
             // First check if terminal has mouse mode enabled and we should forward
             if let Some(pane_id) = app.active_pane_id() {
                 if app.focus == FocusArea::MainArea {
@@ -1034,6 +1256,24 @@ fn handle_input(
 
             // Handle locally if not forwarded to terminal
             match mouse_event.kind {
+                // Drag to select and copy, like a regular terminal
+                MouseEventKind::Down(MouseButton::Left) if app.focus == FocusArea::MainArea => {
+                    if let Some((pane_id, row, col)) =
+                        active_pane_cell(app, mouse_event.column, mouse_event.row, false)
+                    {
+                        app.copy_mouse_down(pane_id, row, col);
+                    }
+                }
+                MouseEventKind::Drag(MouseButton::Left) if app.focus == FocusArea::Copy => {
+                    if let Some((_, row, col)) =
+                        active_pane_cell(app, mouse_event.column, mouse_event.row, true)
+                    {
+                        app.copy_mouse_drag(row, col);
+                    }
+                }
+                MouseEventKind::Up(MouseButton::Left) if app.focus == FocusArea::Copy => {
+                    app.copy_mouse_up();
+                }
                 MouseEventKind::ScrollUp => {
                     if let Some(pane_id) = app.active_pane_id() {
                         if let Ok(mut guard) = terminal_manager.try_lock() {
@@ -1056,10 +1296,14 @@ fn handle_input(
             }
         }
         Event::Paste(text) => {
-            // Forward paste to active terminal
-            if let Some(pane_id) = app.active_pane_id() {
-                if let Ok(mut guard) = terminal_manager.try_lock() {
-                    guard.send_input(pane_id, text.into_bytes());
+            if app.focus == FocusArea::MainArea && forward_to_chat(app, Event::Paste(text.clone()))
+            {
+                return false;
+            }
+            // Forward paste to the active terminal (or all panes while broadcasting)
+            if let Ok(mut guard) = terminal_manager.try_lock() {
+                for pane_id in app.input_targets() {
+                    guard.send_input(pane_id, text.clone().into_bytes());
                 }
             }
         }
@@ -1069,6 +1313,167 @@ fn handle_input(
     false
 }
 
+/// Map a screen cell to `(pane, row, col)` inside the active pane's content.
+/// With `clamp`, positions outside the pane snap to its nearest edge.
+fn active_pane_cell(
+    app: &MuxApp<'_>,
+    column: u16,
+    row: u16,
+    clamp: bool,
+) -> Option<(crate::mux::layout::PaneId, usize, usize)> {
+    let pane_id = app.active_pane_id()?;
+    let area = app.active_tab()?.layout.find_pane(pane_id)?.area?;
+    let (x, y) = (area.x + 1, area.y + 1);
+    let (w, h) = (area.width.saturating_sub(2), area.height.saturating_sub(2));
+    if w == 0 || h == 0 {
+        return None;
+    }
+    let inside = column >= x && column < x + w && row >= y && row < y + h;
+    if !inside && !clamp {
+        return None;
+    }
+    let col = column.clamp(x, x + w - 1) - x;
+    let row = row.clamp(y, y + h - 1) - y;
+    Some((pane_id, row as usize, col as usize))
+}
+
+/// Handle a key press in copy mode: vi-style motions, `v` to select, `y` to copy.
+fn handle_copy_key(app: &mut MuxApp<'_>, key: KeyEvent) {
+    let Some(copy) = app.copy.as_ref() else {
+        app.focus = FocusArea::MainArea;
+        return;
+    };
+    let cursor = copy.cursor;
+    let page = app
+        .active_tab()
+        .and_then(|tab| tab.layout.find_pane(copy.pane_id))
+        .and_then(pane_content_dimensions)
+        .map(|(rows, _)| rows as isize)
+        .unwrap_or(10);
+    let line = cursor.line as isize;
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.exit_copy_mode(),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => app.exit_copy_mode(),
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.copy_move(-page / 2, 0)
+        }
+        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.copy_move(page / 2, 0)
+        }
+        KeyCode::Left | KeyCode::Char('h') => app.copy_move(0, -1),
+        KeyCode::Right | KeyCode::Char('l') => app.copy_move(0, 1),
+        KeyCode::Up | KeyCode::Char('k') => app.copy_move(-1, 0),
+        KeyCode::Down | KeyCode::Char('j') => app.copy_move(1, 0),
+        KeyCode::PageUp => app.copy_move(-page, 0),
+        KeyCode::PageDown => app.copy_move(page, 0),
+        KeyCode::Home | KeyCode::Char('0') => app.copy_move_to(line, 0),
+        KeyCode::End | KeyCode::Char('$') => app.copy_move_to(line, isize::MAX),
+        KeyCode::Char('g') => app.copy_move_to(0, 0),
+        KeyCode::Char('G') => app.copy_move_to(isize::MAX, 0),
+        KeyCode::Char('v') | KeyCode::Char(' ') => app.copy_toggle_selection(),
+        KeyCode::Char('y') | KeyCode::Enter => app.copy_yank(),
+        _ => {}
+    }
+}
+
+/// Handle a key press while the scrollback search prompt has focus.
+fn handle_search_key(app: &mut MuxApp<'_>, key: KeyEvent) {
+    let Some(search) = app.search.as_mut() else {
+        app.focus = FocusArea::MainArea;
+        return;
+    };
+
+    if !search.editing {
+        // Navigating a confirmed query, like `n`/`N` in less or vim
+        match key.code {
+            KeyCode::Char('n') => app.search_step(true),
+            KeyCode::Char('N') => app.search_step(false),
+            KeyCode::Char('/') => search.editing = true,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => app.close_search(),
+            _ => {}
+        }
+        return;
+    }
+
+    match key.code {
+        KeyCode::Esc => app.close_search(),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => app.close_search(),
+        KeyCode::Enter => match search.scope {
+            SearchScope::AllPanes => app.open_search_result(),
+            SearchScope::ActivePane if search.is_empty() => {
+                let query = search.query();
+                app.close_search();
+                if !query.is_empty() {
+                    app.set_status(format!("No matches for \"{}\"", query));
+                }
+            }
+            SearchScope::ActivePane => search.editing = false,
+        },
+        KeyCode::Up => app.search_step(true),
+        KeyCode::Down => app.search_step(false),
+        _ => {
+            search.input.input(key);
+            app.refresh_search();
+        }
+    }
+}
+
+/// Handle a key press while the command history view has focus.
+fn handle_history_key(app: &mut MuxApp<'_>, key: KeyEvent) {
+    let Some(history) = app.history.as_mut() else {
+        app.focus = FocusArea::MainArea;
+        return;
+    };
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.close_history(),
+        KeyCode::Char('c') if ctrl => app.close_history(),
+        KeyCode::Enter => app.replay_history_selection(true),
+        KeyCode::Tab => app.replay_history_selection(false),
+        KeyCode::Up => history.select_up(),
+        KeyCode::Char('p') if ctrl => history.select_up(),
+        KeyCode::Down => history.select_down(),
+        KeyCode::Char('n') if ctrl => history.select_down(),
+        _ => {
+            history.input.input(key);
+            app.refresh_history();
+        }
+    }
+}
+
+fn handle_theme_picker_key(app: &mut MuxApp<'_>, key: KeyEvent) {
+    let Some(picker) = app.theme_picker.as_mut() else {
+        app.focus = FocusArea::MainArea;
+        return;
+    };
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.close_theme_picker(false),
+        KeyCode::Char('c') if ctrl => app.close_theme_picker(false),
+        KeyCode::Enter => app.close_theme_picker(true),
+        KeyCode::Up | KeyCode::Char('k') => {
+            picker.select_up();
+            app.preview_theme_selection();
+        }
+        KeyCode::Char('p') if ctrl => {
+            picker.select_up();
+            app.preview_theme_selection();
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            picker.select_down();
+            app.preview_theme_selection();
+        }
+        KeyCode::Char('n') if ctrl => {
+            picker.select_down();
+            app.preview_theme_selection();
+        }
+        _ => {}
+    }
+}
+
 /// Select the currently highlighted sandbox in the sidebar and switch to its workspace.
 fn select_sidebar_sandbox(app: &mut MuxApp<'_>) {
     if let Some(sandbox) = app.sidebar.selected_sandbox() {
@@ -1087,7 +1492,7 @@ async fn delete_sidebar_sandbox(
     sandbox_id: String,
     event_tx: mpsc::UnboundedSender<MuxEvent>,
 ) {
-    let client = reqwest::Client::new();
+    let client = http_client();
     let url = format!(
         "{}/sandboxes/{}",
         base_url.trim_end_matches('/'),
@@ -1238,7 +1643,7 @@ async fn create_sandbox_with_workspace(
     tab_id: Option<String>,
     event_tx: mpsc::UnboundedSender<MuxEvent>,
 ) -> Result<(), anyhow::Error> {
-    let client = reqwest::Client::new();
+    let client = http_client();
     let trimmed_base = base_url.trim_end_matches('/').to_string();
     let tab_id = tab_id.unwrap_or_else(|| TabId::new().to_string());
 
@@ -1259,6 +1664,7 @@ async fn create_sandbox_with_workspace(
         read_only_paths: vec![],
         tmpfs: vec![],
         env: crate::keyring::build_default_env_vars(),
+        resources: None,
     };
 
     let response = client
@@ -1284,7 +1690,7 @@ async fn create_sandbox_with_workspace(
 
     // Send creation events and connect IMMEDIATELY - don't wait for uploads
     let _ = event_tx.send(MuxEvent::SandboxCreated {
-        sandbox: summary.clone(),
+        sandbox: Box::new(summary.clone()),
         tab_id: Some(tab_id.clone()),
     });
     let _ = event_tx.send(MuxEvent::StatusMessage {
@@ -1367,7 +1773,7 @@ async fn refresh_sandboxes(
     base_url: &str,
     tx: &mpsc::UnboundedSender<MuxEvent>,
 ) -> Result<Vec<crate::models::SandboxSummary>, anyhow::Error> {
-    let client = reqwest::Client::new();
+    let client = http_client();
     let url = format!("{}/sandboxes", base_url.trim_end_matches('/'));
 
     let response = client
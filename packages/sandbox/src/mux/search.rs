@@ -0,0 +1,164 @@
+//! Scrollback search state for the multiplexer.
+//!
+//! Lines are addressed absolutely: line 0 is the oldest scrollback row and the
+//! viewport follows the scrollback. Columns are grid columns, so a match maps
+//! directly onto cells when highlighting.
+
+use crate::mux::layout::PaneId;
+
+/// Maximum number of results collected when searching across all panes.
+pub const MAX_ALL_PANES_RESULTS: usize = 500;
+
+/// A single match inside one terminal buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+/// A match tagged with the pane it came from, used by the all-panes list.
+#[derive(Debug, Clone)]
+pub struct PaneMatch {
+    pub pane_id: PaneId,
+    pub pane_label: String,
+    pub line_text: String,
+    pub at: SearchMatch,
+}
+
+/// Whether the search targets the active pane or every pane in the workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    ActivePane,
+    AllPanes,
+}
+
+/// State of an open search prompt.
+pub struct SearchState<'a> {
+    pub scope: SearchScope,
+    pub input: tui_textarea::TextArea<'a>,
+    /// True while typing the query; false once confirmed and navigating with n/N.
+    pub editing: bool,
+    /// Pane being searched (active-pane scope only).
+    pub pane_id: Option<PaneId>,
+    /// Matches in the searched pane, ordered top to bottom.
+    pub matches: Vec<SearchMatch>,
+    /// Index into `matches` (or `results`) of the current selection.
+    pub current: usize,
+    /// Matches across all panes (all-panes scope only).
+    pub results: Vec<PaneMatch>,
+}
+
+impl<'a> SearchState<'a> {
+    pub fn new(scope: SearchScope, pane_id: Option<PaneId>) -> Self {
+        Self {
+            scope,
+            input: tui_textarea::TextArea::default(),
+            editing: true,
+            pane_id,
+            matches: Vec::new(),
+            current: 0,
+            results: Vec::new(),
+        }
+    }
+
+    pub fn query(&self) -> String {
+        self.input.lines().join("")
+    }
+
+    /// Number of entries the current selection can move between.
+    pub fn len(&self) -> usize {
+        match self.scope {
+            SearchScope::ActivePane => self.matches.len(),
+            SearchScope::AllPanes => self.results.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Move towards older output (up the scrollback), wrapping around.
+    pub fn select_older(&mut self) {
+        let len = self.len();
+        if len > 0 {
+            self.current = (self.current + len - 1) % len;
+        }
+    }
+
+    /// Move towards newer output (down the scrollback), wrapping around.
+    pub fn select_newer(&mut self) {
+        let len = self.len();
+        if len > 0 {
+            self.current = (self.current + 1) % len;
+        }
+    }
+
+    pub fn current_match(&self) -> Option<SearchMatch> {
+        match self.scope {
+            SearchScope::ActivePane => self.matches.get(self.current).copied(),
+            SearchScope::AllPanes => self.results.get(self.current).map(|r| r.at),
+        }
+    }
+}
+
+/// Find non-overlapping occurrences of `query` in `line`, returning
+/// `(column, length)` pairs in columns. Matching is smart-case: case-insensitive
+/// unless the query contains an uppercase character.
+pub fn find_in_line(line: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let hay: Vec<char> = line.chars().map(fold).collect();
+    let needle: Vec<char> = query.chars().map(fold).collect();
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= hay.len() {
+        if hay[i..i + needle.len()] == needle[..] {
+            out.push((i, needle.len()));
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_case_matching() {
+        assert_eq!(find_in_line("Error: error", "error"), vec![(0, 5), (7, 5)]);
+        assert_eq!(find_in_line("Error: error", "Error"), vec![(0, 5)]);
+        assert_eq!(find_in_line("aaaa", "aa"), vec![(0, 2), (2, 2)]);
+        assert!(find_in_line("abc", "").is_empty());
+    }
+
+    #[test]
+    fn selection_wraps_in_both_directions() {
+        let mut state = SearchState::new(SearchScope::ActivePane, None);
+        state.matches = (0..3)
+            .map(|line| SearchMatch {
+                line,
+                col: 0,
+                len: 1,
+            })
+            .collect();
+        state.current = 0;
+        state.select_older();
+        assert_eq!(state.current, 2);
+        state.select_newer();
+        assert_eq!(state.current, 0);
+    }
+}
@@ -0,0 +1,384 @@
+//! Session persistence for the multiplexer.
+//!
+//! The layout of every sandbox workspace, along with each pane's working
+//! directory and recent scrollback, is written to
+//! `<config dir>/cmux/mux-session.json` while the TUI runs. `cmux mux attach`
+//! loads it back so the previous session survives the TUI exiting or an SSH
+//! connection dropping.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::mux::layout::{
+    Direction, LayoutNode, Pane, PaneContent, PaneId, SandboxId, Tab, WorkspaceManager,
+};
+use crate::mux::terminal::TerminalManager;
+
+const APP_NAME: &str = "cmux";
+const SESSION_FILE: &str = "mux-session.json";
+const SESSION_VERSION: u32 = 1;
+
+/// Maximum number of scrollback lines saved per pane.
+pub const MAX_SAVED_SCROLLBACK: usize = 2000;
+
+/// Saved state replayed into a pane when it reconnects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoredPane {
+    pub scrollback: Vec<String>,
+    pub cwd: Option<String>,
+}
+
+/// A serialized layout tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LayoutSnapshot {
+    Pane {
+        content: PaneContent,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default)]
+        scrollback: Vec<String>,
+    },
+    Split {
+        direction: Direction,
+        ratio: f32,
+        first: Box<LayoutSnapshot>,
+        second: Box<LayoutSnapshot>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabSnapshot {
+    pub name: String,
+    /// Index of the active pane in layout order.
+    #[serde(default)]
+    pub active_pane: Option<usize>,
+    pub layout: LayoutSnapshot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub sandbox_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub active_tab_index: usize,
+    pub tabs: Vec<TabSnapshot>,
+}
+
+/// Everything needed to rebuild the multiplexer after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub version: u32,
+    pub saved_at: String,
+    #[serde(default)]
+    pub active_sandbox_id: Option<String>,
+    pub workspaces: Vec<WorkspaceSnapshot>,
+}
+
+/// A pane rebuilt from a snapshot that should be reconnected to its sandbox.
+#[derive(Debug, Clone)]
+pub struct RestoredConnect {
+    pub pane_id: PaneId,
+    pub tab_id: crate::mux::layout::TabId,
+    pub sandbox_id: String,
+    pub restored: RestoredPane,
+}
+
+impl LayoutSnapshot {
+    fn capture(node: &LayoutNode, pane_state: &impl Fn(PaneId) -> RestoredPane) -> Self {
+        match node {
+            LayoutNode::Pane(pane) => {
+                let state = pane_state(pane.id);
+                LayoutSnapshot::Pane {
+                    content: pane.content.clone(),
+                    cwd: state.cwd,
+                    scrollback: state.scrollback,
+                }
+            }
+            LayoutNode::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => LayoutSnapshot::Split {
+                direction: *direction,
+                ratio: *ratio,
+                first: Box::new(Self::capture(first, pane_state)),
+                second: Box::new(Self::capture(second, pane_state)),
+            },
+        }
+    }
+
+    /// Rebuild the layout tree with fresh pane IDs, collecting the saved state
+    /// of each pane in layout order.
+    pub fn rebuild(self, panes: &mut Vec<(Pane, RestoredPane)>) -> LayoutNode {
+        match self {
+            LayoutSnapshot::Pane {
+                content,
+                cwd,
+                scrollback,
+            } => {
+                let pane = Pane::new(content);
+                panes.push((pane.clone(), RestoredPane { scrollback, cwd }));
+                LayoutNode::Pane(pane)
+            }
+            LayoutSnapshot::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => LayoutNode::Split {
+                direction,
+                ratio: ratio.clamp(0.1, 0.9),
+                first: Box::new(first.rebuild(panes)),
+                second: Box::new(second.rebuild(panes)),
+            },
+        }
+    }
+}
+
+impl TabSnapshot {
+    fn capture(tab: &Tab, pane_state: &impl Fn(PaneId) -> RestoredPane) -> Self {
+        Self {
+            name: tab.name.clone(),
+            active_pane: tab
+                .active_pane
+                .and_then(|id| tab.layout.pane_ids().iter().position(|p| *p == id)),
+            layout: LayoutSnapshot::capture(&tab.layout, pane_state),
+        }
+    }
+
+    /// Rebuild a tab, returning it with its panes' saved state.
+    pub fn rebuild(self) -> (Tab, Vec<(Pane, RestoredPane)>) {
+        let mut panes = Vec::new();
+        let mut tab = Tab::new(self.name);
+        tab.layout = self.layout.rebuild(&mut panes);
+        let ids = tab.layout.pane_ids();
+        tab.active_pane = self
+            .active_pane
+            .and_then(|i| ids.get(i).copied())
+            .or_else(|| ids.first().copied());
+        (tab, panes)
+    }
+}
+
+impl SessionSnapshot {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_NAME).join(SESSION_FILE))
+    }
+
+    /// Capture the current layout, using `pane_state` to look up each pane's
+    /// scrollback and working directory.
+    pub fn capture(
+        workspaces: &WorkspaceManager,
+        pane_state: impl Fn(PaneId) -> RestoredPane,
+    ) -> Self {
+        let workspaces = workspaces
+            .sandbox_ids()
+            .iter()
+            .filter_map(|id| workspaces.get_workspace(*id))
+            .map(|ws| WorkspaceSnapshot {
+                sandbox_id: ws.sandbox_id.to_string(),
+                name: ws.name.clone(),
+                active_tab_index: ws.active_tab_index,
+                tabs: ws
+                    .tabs
+                    .iter()
+                    .map(|tab| TabSnapshot::capture(tab, &pane_state))
+                    .collect(),
+            })
+            .collect();
+        Self {
+            version: SESSION_VERSION,
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            active_sandbox_id: None,
+            workspaces,
+        }
+    }
+
+    /// Capture the session using the scrollback held by the terminal manager.
+    pub fn capture_with_terminals(
+        workspaces: &WorkspaceManager,
+        manager: &TerminalManager,
+    ) -> Self {
+        let mut snapshot = Self::capture(workspaces, |pane_id| {
+            let Some(buffer) = manager.get_buffer(pane_id) else {
+                return RestoredPane::default();
+            };
+            let text = buffer.get_all_text();
+            let lines: Vec<&str> = text.lines().collect();
+            let start = lines.len().saturating_sub(MAX_SAVED_SCROLLBACK);
+            RestoredPane {
+                scrollback: lines[start..].iter().map(|l| l.to_string()).collect(),
+                cwd: buffer.terminal.current_dir.clone(),
+            }
+        });
+        snapshot.active_sandbox_id = workspaces.active_sandbox_id.map(|id| id.to_string());
+        snapshot
+    }
+
+    /// Load the saved session, if one exists and was written by a compatible version.
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(Self::path()?).ok()?;
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(snapshot) if snapshot.version == SESSION_VERSION => Some(snapshot),
+            Ok(snapshot) => {
+                tracing::warn!("Ignoring mux session with version {}", snapshot.version);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Failed to parse mux session file: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Save the session to disk.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = Self::path() else {
+            return Err("Could not determine config directory".to_string());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let contents = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize session: {}", e))?;
+
+        // Write via a temp file so a crash mid-write never leaves a truncated session.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, contents).map_err(|e| format!("Failed to write session file: {}", e))?;
+        fs::rename(&tmp, &path).map_err(|e| format!("Failed to write session file: {}", e))?;
+        Ok(())
+    }
+
+    /// Apply the snapshot to workspaces that still exist, replacing their tabs.
+    /// Returns the panes that should be reconnected to their sandboxes.
+    pub fn restore_into(self, workspaces: &mut WorkspaceManager) -> Vec<RestoredConnect> {
+        let mut connects = Vec::new();
+        for saved in self.workspaces {
+            let Ok(sandbox_id) = saved.sandbox_id.parse::<SandboxId>() else {
+                continue;
+            };
+            let Some(ws) = workspaces.get_workspace_mut(sandbox_id) else {
+                continue;
+            };
+            if saved.tabs.is_empty() {
+                continue;
+            }
+            ws.tabs.clear();
+            for tab_snapshot in saved.tabs {
+                let (tab, panes) = tab_snapshot.rebuild();
+                for (pane, restored) in panes {
+                    if let PaneContent::Terminal {
+                        sandbox_id: Some(pane_sandbox),
+                        ..
+                    } = pane.content
+                    {
+                        connects.push(RestoredConnect {
+                            pane_id: pane.id,
+                            tab_id: tab.id,
+                            sandbox_id: pane_sandbox,
+                            restored,
+                        });
+                    }
+                }
+                ws.tabs.push(tab);
+            }
+            ws.active_tab_index = saved.active_tab_index.min(ws.tabs.len() - 1);
+        }
+        if let Some(active) = self
+            .active_sandbox_id
+            .and_then(|id| id.parse::<SandboxId>().ok())
+        {
+            workspaces.select_sandbox(active);
+        }
+        connects
+    }
+}
+
+/// Command that starts the user's login shell in `dir`, falling back to the
+/// default directory if it no longer exists.
+pub fn shell_in_dir(dir: &str) -> Vec<String> {
+    let quoted = format!("'{}'", dir.replace('\'', "'\\''"));
+    vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        format!(
+            "cd {} 2>/dev/null; exec \"${{SHELL:-/bin/bash}}\" -l",
+            quoted
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trips_and_rebuilds_layout() {
+        let sandbox_id = SandboxId::new();
+        let mut workspaces = WorkspaceManager::new();
+        workspaces.add_sandbox(sandbox_id, "dev");
+        {
+            let ws = workspaces.get_workspace_mut(sandbox_id).unwrap();
+            let tab = ws.active_tab_mut().unwrap();
+            if let LayoutNode::Pane(pane) = &mut tab.layout {
+                pane.content = PaneContent::Terminal {
+                    sandbox_id: Some(sandbox_id.to_string()),
+                    title: "Terminal".to_string(),
+                };
+            }
+            tab.split(
+                Direction::Vertical,
+                Pane::terminal(Some(sandbox_id.to_string()), "Terminal"),
+            );
+            ws.new_tab();
+            ws.active_tab_index = 0;
+        }
+        let second_pane = workspaces.active_tab().unwrap().layout.pane_ids()[1];
+        workspaces.active_tab_mut().unwrap().active_pane = Some(second_pane);
+
+        let snapshot = SessionSnapshot::capture(&workspaces, |pane_id| RestoredPane {
+            scrollback: vec![format!("output of {}", pane_id)],
+            cwd: Some("/root/it's here".to_string()),
+        });
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: SessionSnapshot = serde_json::from_str(&json).unwrap();
+
+        let mut fresh = WorkspaceManager::new();
+        fresh.add_sandbox(sandbox_id, "dev");
+        let connects = parsed.restore_into(&mut fresh);
+
+        let ws = fresh.get_workspace(sandbox_id).unwrap();
+        assert_eq!(ws.tabs.len(), 2);
+        assert_eq!(ws.active_tab_index, 0);
+        let tab = &ws.tabs[0];
+        assert!(matches!(
+            tab.layout,
+            LayoutNode::Split {
+                direction: Direction::Vertical,
+                ..
+            }
+        ));
+        let ids = tab.layout.pane_ids();
+        assert_eq!(tab.active_pane, Some(ids[1]));
+        assert!(!ids.contains(&second_pane), "panes get fresh IDs");
+
+        // Only panes attached to a sandbox are reconnected.
+        assert_eq!(connects.len(), 2);
+        assert_eq!(connects[0].pane_id, ids[0]);
+        assert_eq!(connects[0].restored.cwd.as_deref(), Some("/root/it's here"));
+        assert_eq!(connects[0].restored.scrollback.len(), 1);
+    }
+
+    #[test]
+    fn shell_in_dir_quotes_path() {
+        let cmd = shell_in_dir("/tmp/it's");
+        assert_eq!(cmd[0], "/bin/sh");
+        assert!(cmd[2].starts_with("cd '/tmp/it'\\''s' 2>/dev/null;"));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::{SandboxStatus, SandboxSummary};
+use crate::mux::theme::ThemeColors;
 use uuid::Uuid;
 
 /// State for the sidebar showing sandbox list.
@@ -141,13 +142,13 @@ impl Sidebar {
     }
 
     /// Get status color for a sandbox.
-    pub fn status_color(status: &SandboxStatus) -> ratatui::style::Color {
+    pub fn status_color(status: &SandboxStatus, colors: &ThemeColors) -> ratatui::style::Color {
         match status {
-            SandboxStatus::Creating => ratatui::style::Color::Cyan,
-            SandboxStatus::Running => ratatui::style::Color::Green,
-            SandboxStatus::Exited => ratatui::style::Color::DarkGray,
-            SandboxStatus::Failed => ratatui::style::Color::Red,
-            SandboxStatus::Unknown => ratatui::style::Color::Yellow,
+            SandboxStatus::Creating => colors.accent,
+            SandboxStatus::Running => colors.success,
+            SandboxStatus::Exited => colors.muted,
+            SandboxStatus::Failed => colors.error,
+            SandboxStatus::Unknown => colors.warning,
         }
     }
 
@@ -196,6 +197,7 @@ mod tests {
             },
             display: None,
             correlation_id: None,
+            resources: None,
         }
     }
 
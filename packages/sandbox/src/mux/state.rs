@@ -1,17 +1,26 @@
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use tokio::sync::mpsc;
 
+use crate::acp_client::{load_last_provider, ChatPane};
 use crate::models::{NotificationLevel, SandboxNetwork, SandboxStatus, SandboxSummary};
 use crate::mux::commands::MuxCommand;
+use crate::mux::copy::{selected_text, CopyPos, CopyState};
 use crate::mux::events::MuxEvent;
-use crate::mux::layout::{Direction, NavDirection, Pane, PaneId, SandboxId, WorkspaceManager};
+use crate::mux::grid::CaptureFormat;
+use crate::mux::history::{CommandHistory, HistoryEntry, HistoryView};
+use crate::mux::layout::{
+    Direction, NavDirection, Pane, PaneContent, PaneId, SandboxId, WorkspaceManager,
+};
 use crate::mux::onboard::OnboardState;
 use crate::mux::palette::CommandPalette;
+use crate::mux::search::{PaneMatch, SearchScope, SearchState, MAX_ALL_PANES_RESULTS};
+use crate::mux::session::{RestoredConnect, SessionSnapshot};
 use crate::mux::sidebar::Sidebar;
 use crate::mux::terminal::{SharedTerminalManager, TerminalRenderView};
+use crate::mux::theme::{available_themes, load_theme, Theme, ThemePicker};
 use crate::settings::{EditorChoice, Settings};
 use uuid::Uuid;
 
@@ -175,6 +184,10 @@ pub enum FocusArea {
     CommandPalette,
     Notifications,
     Onboard,
+    Search,
+    Copy,
+    History,
+    ThemePicker,
 }
 
 #[derive(Debug, Clone)]
@@ -335,6 +348,19 @@ pub struct MuxApp<'a> {
     pub renaming_tab: bool,
     pub rename_input: Option<tui_textarea::TextArea<'a>>,
 
+    // Scrollback search state
+    pub search: Option<SearchState<'a>>,
+
+    // Copy-mode selection state
+    pub copy: Option<CopyState>,
+
+    // Commands run in each pane (OSC 133) and the open history view
+    pub command_history: CommandHistory,
+    pub history: Option<HistoryView<'a>>,
+
+    // Send terminal input to every terminal pane in the active tab
+    pub broadcast_input: bool,
+
     // Terminal manager for handling sandbox connections
     pub terminal_manager: Option<SharedTerminalManager>,
 
@@ -344,6 +370,12 @@ pub struct MuxApp<'a> {
     // Flag to indicate we need to create a sandbox on startup
     pub needs_initial_sandbox: bool,
 
+    // Saved session to restore once the sandbox list arrives (`cmux mux attach`)
+    pub pending_session: Option<SessionSnapshot>,
+
+    // Restored panes waiting to be reconnected to their sandboxes
+    pub pending_pane_connects: Vec<RestoredConnect>,
+
     // Last rendered terminal views per pane for damage-aware drawing
     pub last_terminal_views:
         std::collections::HashMap<PaneId, crate::mux::terminal::TerminalRenderView>,
@@ -369,6 +401,13 @@ pub struct MuxApp<'a> {
 
     /// Persistent settings (editor choice, etc.)
     pub settings: Settings,
+
+    /// Colors for the mux chrome, and the open theme picker
+    pub theme: Theme,
+    pub theme_picker: Option<ThemePicker>,
+
+    /// Running agent chats, keyed by the chat pane showing them
+    pub(crate) chats: std::collections::HashMap<PaneId, ChatPane>,
 }
 
 impl<'a> MuxApp<'a> {
@@ -381,6 +420,9 @@ impl<'a> MuxApp<'a> {
         // This ensures VS Code SSH works without additional setup
         let _ = ensure_ssh_config_for_sandboxes(&base_url);
 
+        let settings = Settings::load();
+        let theme = load_theme(settings.theme.as_deref());
+
         Self {
             workspace_manager: WorkspaceManager::new(),
             sidebar: Sidebar::new(),
@@ -395,9 +437,16 @@ impl<'a> MuxApp<'a> {
             status_message: None,
             renaming_tab: false,
             rename_input: None,
+            search: None,
+            copy: None,
+            command_history: CommandHistory::new(),
+            history: None,
+            broadcast_input: false,
             terminal_manager: None,
             pending_connects: std::collections::VecDeque::new(),
             needs_initial_sandbox: false,
+            pending_session: None,
+            pending_pane_connects: Vec::new(),
             last_terminal_views: std::collections::HashMap::new(),
             cursor_blink: true,
             cursor_color: None,
@@ -405,7 +454,10 @@ impl<'a> MuxApp<'a> {
             delta_enabled_sandboxes: HashSet::new(),
             pending_creation_tab_ids: HashSet::new(),
             most_recent_creation_tab_id: None,
-            settings: Settings::load(),
+            theme,
+            theme_picker: None,
+            settings,
+            chats: std::collections::HashMap::new(),
         }
     }
 
@@ -439,6 +491,27 @@ impl<'a> MuxApp<'a> {
         }
     }
 
+    /// Rebuild workspaces from the saved session, queueing their panes for
+    /// reconnection. Returns true if anything was restored.
+    fn apply_pending_session(&mut self) -> bool {
+        let Some(snapshot) = self.pending_session.take() else {
+            return false;
+        };
+        let connects = snapshot.restore_into(&mut self.workspace_manager);
+        if connects.is_empty() {
+            return false;
+        }
+        if let Some(active) = self.workspace_manager.active_sandbox_id {
+            self.sidebar.select_by_id(active.0);
+        }
+        self.set_status(format!(
+            "Restored {} pane(s) from saved session",
+            connects.len()
+        ));
+        self.pending_pane_connects.extend(connects);
+        true
+    }
+
     /// Set the terminal manager
     pub fn set_terminal_manager(&mut self, manager: SharedTerminalManager) {
         self.terminal_manager = Some(manager);
@@ -453,6 +526,31 @@ impl<'a> MuxApp<'a> {
         Some(buffer.render_view(height))
     }
 
+    /// The file a pane's output is being piped to, if any.
+    pub fn pipe_path(&self, pane_id: PaneId) -> Option<PathBuf> {
+        let manager = self.terminal_manager.as_ref()?;
+        let guard = manager.try_lock().ok()?;
+        guard.pipe_path(pane_id).map(Path::to_path_buf)
+    }
+
+    /// Panes that terminal input should go to: the active pane, or every
+    /// terminal pane in the active tab while broadcasting.
+    pub fn input_targets(&self) -> Vec<PaneId> {
+        if !self.broadcast_input {
+            return self.active_pane_id().into_iter().collect();
+        }
+        self.active_tab()
+            .map(|tab| {
+                tab.layout
+                    .panes()
+                    .into_iter()
+                    .filter(|pane| matches!(pane.content, PaneContent::Terminal { .. }))
+                    .map(|pane| pane.id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Get the active pane ID from the active workspace.
     pub fn active_pane_id(&self) -> Option<PaneId> {
         self.workspace_manager
@@ -613,6 +711,31 @@ impl<'a> MuxApp<'a> {
 
     /// Execute a command.
     pub fn execute_command(&mut self, cmd: MuxCommand) {
+        // Changing the layout or moving focus while zoomed restores the full layout.
+        if self.zoomed_pane.is_some()
+            && matches!(
+                cmd,
+                MuxCommand::FocusLeft
+                    | MuxCommand::FocusRight
+                    | MuxCommand::FocusUp
+                    | MuxCommand::FocusDown
+                    | MuxCommand::SplitHorizontal
+                    | MuxCommand::SplitVertical
+                    | MuxCommand::OpenAgentChat
+                    | MuxCommand::SwapPaneLeft
+                    | MuxCommand::SwapPaneRight
+                    | MuxCommand::SwapPaneUp
+                    | MuxCommand::SwapPaneDown
+                    | MuxCommand::ResizeLeft
+                    | MuxCommand::ResizeRight
+                    | MuxCommand::ResizeUp
+                    | MuxCommand::ResizeDown
+                    | MuxCommand::EqualizePanes
+            )
+        {
+            self.zoomed_pane = None;
+        }
+
         match cmd {
             // Navigation
             MuxCommand::FocusLeft => {
@@ -694,6 +817,21 @@ impl<'a> MuxApp<'a> {
                     let _ = self.event_tx.send(MuxEvent::ConnectActivePaneToSandbox);
                 }
             }
+            MuxCommand::OpenAgentChat => {
+                let Some(sandbox_id) = self.selected_sandbox_id_string() else {
+                    self.set_status("No sandbox selected");
+                    return;
+                };
+                let provider = load_last_provider().unwrap_or_default();
+                if let Some(tab) = self.active_tab_mut() {
+                    // The runner starts the chat once it sees the new pane.
+                    tab.split(
+                        Direction::Vertical,
+                        Pane::chat(sandbox_id, provider.short_name()),
+                    );
+                    self.set_status(format!("Opened {} chat", provider.display_name()));
+                }
+            }
             MuxCommand::ClosePane => {
                 if let Some(tab) = self.active_tab_mut() {
                     if tab.close_active_pane() {
@@ -716,7 +854,32 @@ impl<'a> MuxApp<'a> {
             | MuxCommand::SwapPaneRight
             | MuxCommand::SwapPaneUp
             | MuxCommand::SwapPaneDown => {
-                self.set_status("Pane swapping not yet implemented");
+                let direction = match cmd {
+                    MuxCommand::SwapPaneLeft => NavDirection::Left,
+                    MuxCommand::SwapPaneRight => NavDirection::Right,
+                    MuxCommand::SwapPaneUp => NavDirection::Up,
+                    _ => NavDirection::Down,
+                };
+                if let Some(tab) = self.active_tab_mut() {
+                    if tab.swap(direction) {
+                        // Pane contents moved between slots; repaint from scratch.
+                        self.last_terminal_views.clear();
+                    }
+                }
+            }
+            MuxCommand::ToggleBroadcast => {
+                self.broadcast_input = !self.broadcast_input;
+                self.set_status(if self.broadcast_input {
+                    "Broadcast on: input goes to every pane in this tab"
+                } else {
+                    "Broadcast off"
+                });
+            }
+            MuxCommand::EqualizePanes => {
+                if let Some(tab) = self.active_tab_mut() {
+                    tab.equalize();
+                    self.set_status("Panes equalized");
+                }
             }
             MuxCommand::ResizeLeft => {
                 if let Some(tab) = self.active_tab_mut() {
@@ -926,47 +1089,50 @@ impl<'a> MuxApp<'a> {
                     self.set_status("Delta pager disabled");
                 }
             }
-            MuxCommand::CopyScrollback => {
-                // Get the active pane's terminal content and copy to clipboard
-                // Extract the text first to avoid borrow conflicts with set_status
-                let result: Result<String, &'static str> = (|| {
-                    let pane_id = self.active_pane_id().ok_or("No active pane")?;
-                    let manager = self
-                        .terminal_manager
-                        .as_ref()
-                        .ok_or("Terminal manager not available")?;
-                    let mut guard = manager
-                        .try_lock()
-                        .map_err(|_| "Could not access terminal")?;
-                    let buffer = guard
-                        .get_buffer_mut(pane_id)
-                        .ok_or("No terminal in active pane")?;
-                    Ok(buffer.get_all_text())
-                })();
-
-                match result {
-                    Ok(text) if text.is_empty() => {
-                        self.set_status("Terminal is empty");
-                    }
-                    Ok(text) => match arboard::Clipboard::new() {
-                        Ok(mut clipboard) => match clipboard.set_text(&text) {
-                            Ok(()) => {
-                                let lines = text.lines().count();
-                                self.set_status(format!("Copied {} lines to clipboard", lines));
-                            }
-                            Err(e) => {
-                                self.set_status(format!("Failed to copy: {}", e));
-                            }
-                        },
-                        Err(e) => {
-                            self.set_status(format!("Clipboard not available: {}", e));
-                        }
-                    },
-                    Err(msg) => {
-                        self.set_status(msg);
-                    }
+            MuxCommand::SearchScrollback => {
+                self.open_search(SearchScope::ActivePane);
+            }
+            MuxCommand::SearchAllPanes => {
+                self.open_search(SearchScope::AllPanes);
+            }
+            MuxCommand::SearchCommandHistory => {
+                self.open_history();
+            }
+            MuxCommand::SelectTheme => {
+                self.open_theme_picker();
+            }
+            MuxCommand::RerunLastCommand => {
+                let last = self
+                    .active_pane_id()
+                    .and_then(|pane_id| self.command_history.last(pane_id))
+                    .map(|record| record.command.clone());
+                match last {
+                    Some(command) => self.replay_command(&command, true),
+                    None => self.set_status("No commands recorded in this pane"),
                 }
             }
+            MuxCommand::EnterCopyMode => {
+                self.enter_copy_mode();
+            }
+            MuxCommand::PasteClipboard => match crate::mux::copy::paste_text() {
+                Some(text) => self.paste_into_active_pane(text),
+                None => self.set_status("Clipboard is empty"),
+            },
+            MuxCommand::CopyScrollback => {
+                self.copy_scrollback(CaptureFormat::Plain);
+            }
+            MuxCommand::CopyScrollbackAnsi => {
+                self.copy_scrollback(CaptureFormat::Ansi);
+            }
+            MuxCommand::ExportScrollback => {
+                self.export_scrollback(CaptureFormat::Plain);
+            }
+            MuxCommand::ExportScrollbackAnsi => {
+                self.export_scrollback(CaptureFormat::Ansi);
+            }
+            MuxCommand::TogglePipePane => {
+                self.toggle_pipe_pane();
+            }
             MuxCommand::OpenWith => {
                 // This normally opens a submenu in the palette, but if executed directly:
                 self.set_status("Use command palette to choose an editor");
@@ -1046,6 +1212,10 @@ impl<'a> MuxApp<'a> {
                     match std::process::Command::new(&binary_name)
                         .args(["browser", &sandbox_id.to_string()])
                         .env("CMUX_SANDBOX_URL", &self.base_url)
+                        .envs(
+                            crate::client_auth::auth_token()
+                                .map(|token| (crate::client_auth::AUTH_TOKEN_ENV, token)),
+                        )
                         .stdout(std::process::Stdio::null())
                         .stderr(std::process::Stdio::null())
                         .spawn()
@@ -1073,6 +1243,637 @@ impl<'a> MuxApp<'a> {
         self.focus = FocusArea::MainArea;
     }
 
+    /// Open the scrollback search prompt.
+    pub fn open_search(&mut self, scope: SearchScope) {
+        let pane_id = self.active_pane_id();
+        if scope == SearchScope::ActivePane && pane_id.is_none() {
+            self.set_status("No active pane");
+            return;
+        }
+        self.close_search();
+        self.search = Some(SearchState::new(scope, pane_id));
+        self.focus = FocusArea::Search;
+    }
+
+    /// Close the search prompt and drop its highlights.
+    pub fn close_search(&mut self) {
+        if let Some(pane_id) = self.search.take().and_then(|s| s.pane_id) {
+            // Force a full repaint so highlighted cells are redrawn plainly.
+            self.last_terminal_views.remove(&pane_id);
+        }
+        if self.focus == FocusArea::Search {
+            self.focus = FocusArea::MainArea;
+        }
+    }
+
+    /// Recompute matches for the current query and reveal the newest one.
+    pub fn refresh_search(&mut self) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        let Some(manager) = self.terminal_manager.clone() else {
+            return;
+        };
+        let Ok(guard) = manager.try_lock() else {
+            return;
+        };
+        let query = search.query();
+        match search.scope {
+            SearchScope::ActivePane => {
+                search.matches = search
+                    .pane_id
+                    .and_then(|pane_id| guard.get_buffer(pane_id))
+                    .map(|buffer| buffer.search(&query))
+                    .unwrap_or_default();
+                search.current = search.matches.len().saturating_sub(1);
+            }
+            SearchScope::AllPanes => {
+                let mut results = Vec::new();
+                if !query.is_empty() {
+                    let tabs = self
+                        .workspace_manager
+                        .active_workspace()
+                        .map(|ws| ws.tabs.as_slice())
+                        .unwrap_or_default();
+                    'panes: for tab in tabs {
+                        for pane in tab.layout.panes() {
+                            let Some(buffer) = guard.get_buffer(pane.id) else {
+                                continue;
+                            };
+                            // Newest matches first within each pane.
+                            for at in buffer.search(&query).into_iter().rev() {
+                                if results.len() >= MAX_ALL_PANES_RESULTS {
+                                    break 'panes;
+                                }
+                                results.push(PaneMatch {
+                                    pane_id: pane.id,
+                                    pane_label: format!("{} · {}", tab.name, pane.title()),
+                                    line_text: buffer.line_text(at.line).unwrap_or_default(),
+                                    at,
+                                });
+                            }
+                        }
+                    }
+                }
+                search.results = results;
+                search.current = 0;
+            }
+        }
+        drop(guard);
+        if let Some(pane_id) = search.pane_id {
+            self.last_terminal_views.remove(&pane_id);
+        }
+        self.reveal_search_match();
+    }
+
+    /// Move to the next older (`older = true`) or newer match.
+    pub fn search_step(&mut self, older: bool) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        if older {
+            search.select_older();
+        } else {
+            search.select_newer();
+        }
+        self.reveal_search_match();
+    }
+
+    /// Scroll the searched pane so the current match is visible.
+    fn reveal_search_match(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        if search.scope != SearchScope::ActivePane {
+            return;
+        }
+        let (Some(pane_id), Some(at)) = (search.pane_id, search.current_match()) else {
+            return;
+        };
+        if let Some(manager) = &self.terminal_manager {
+            if let Ok(mut guard) = manager.try_lock() {
+                if let Some(buffer) = guard.get_buffer_mut(pane_id) {
+                    buffer.scroll_to_line(at.line);
+                }
+            }
+        }
+        self.last_terminal_views.remove(&pane_id);
+    }
+
+    /// Enter copy mode on the active pane with the cursor at the bottom of the view.
+    pub fn enter_copy_mode(&mut self) {
+        let Some(pane_id) = self.active_pane_id() else {
+            self.set_status("No active pane");
+            return;
+        };
+        let line = self
+            .terminal_manager
+            .as_ref()
+            .and_then(|manager| manager.try_lock().ok())
+            .and_then(|guard| {
+                guard
+                    .get_buffer(pane_id)
+                    .map(|b| b.view_start_line() + b.rows().saturating_sub(1))
+            });
+        let Some(line) = line else {
+            self.set_status("No terminal in active pane");
+            return;
+        };
+        self.close_search();
+        self.copy = Some(CopyState::new(pane_id, CopyPos { line, col: 0 }));
+        self.focus = FocusArea::Copy;
+        self.set_status("Copy mode: move with arrows/hjkl, v to select, y to copy, q to exit");
+    }
+
+    /// Leave copy mode and drop its highlight.
+    pub fn exit_copy_mode(&mut self) {
+        if let Some(copy) = self.copy.take() {
+            self.last_terminal_views.remove(&copy.pane_id);
+        }
+        if self.focus == FocusArea::Copy {
+            self.focus = FocusArea::MainArea;
+        }
+    }
+
+    /// Move the copy-mode cursor, clamped to the pane's lines, scrolling to keep it visible.
+    pub fn copy_move_to(&mut self, line: isize, col: isize) {
+        let Some(copy) = self.copy.as_mut() else {
+            return;
+        };
+        let Some(manager) = self.terminal_manager.clone() else {
+            return;
+        };
+        let Ok(mut guard) = manager.try_lock() else {
+            return;
+        };
+        let Some(buffer) = guard.get_buffer_mut(copy.pane_id) else {
+            return;
+        };
+        let total = buffer.terminal.scrollback_len() + buffer.rows();
+        let line = line.clamp(0, total.saturating_sub(1) as isize) as usize;
+        let width = buffer.terminal.cols().max(1);
+        copy.cursor = CopyPos {
+            line,
+            col: col.clamp(0, width as isize - 1) as usize,
+        };
+        let start = buffer.view_start_line();
+        if line < start || line >= start + buffer.rows() {
+            buffer.scroll_to_line(line);
+        }
+        self.last_terminal_views.remove(&copy.pane_id);
+    }
+
+    /// Move the copy-mode cursor relative to its current position.
+    pub fn copy_move(&mut self, lines: isize, cols: isize) {
+        if let Some(cursor) = self.copy.as_ref().map(|c| c.cursor) {
+            self.copy_move_to(cursor.line as isize + lines, cursor.col as isize + cols);
+        }
+    }
+
+    /// Start a selection at the cursor, or clear the current one.
+    pub fn copy_toggle_selection(&mut self) {
+        if let Some(copy) = self.copy.as_mut() {
+            copy.anchor = match copy.anchor {
+                Some(_) => None,
+                None => Some(copy.cursor),
+            };
+            self.last_terminal_views.remove(&copy.pane_id);
+        }
+    }
+
+    /// Copy the selection (or the cursor line if nothing is selected) and leave copy mode.
+    pub fn copy_yank(&mut self) {
+        let Some(copy) = self.copy.clone() else {
+            return;
+        };
+        let (start, end) = copy.range().unwrap_or((
+            CopyPos {
+                line: copy.cursor.line,
+                col: 0,
+            },
+            CopyPos {
+                line: copy.cursor.line,
+                col: usize::MAX - 1,
+            },
+        ));
+        let text = self
+            .terminal_manager
+            .as_ref()
+            .and_then(|manager| manager.try_lock().ok())
+            .and_then(|guard| {
+                let buffer = guard.get_buffer(copy.pane_id)?;
+                Some(selected_text(start, end, |line| buffer.line_text(line)))
+            });
+        self.exit_copy_mode();
+        match text {
+            Some(text) if !text.trim().is_empty() => {
+                let _ = self.event_tx.send(MuxEvent::CopyToClipboard { text });
+            }
+            _ => self.set_status("Nothing selected"),
+        }
+    }
+
+    /// Absolute position of a cell in the top row `row` of a pane's current view.
+    fn pane_view_pos(&self, pane_id: PaneId, row: usize, col: usize) -> Option<CopyPos> {
+        let manager = self.terminal_manager.as_ref()?;
+        let guard = manager.try_lock().ok()?;
+        let start = guard.get_buffer(pane_id)?.view_start_line();
+        Some(CopyPos {
+            line: start + row,
+            col,
+        })
+    }
+
+    /// Begin a mouse selection at a cell in the pane's view.
+    pub fn copy_mouse_down(&mut self, pane_id: PaneId, row: usize, col: usize) {
+        let Some(pos) = self.pane_view_pos(pane_id, row, col) else {
+            return;
+        };
+        self.close_search();
+        let mut state = CopyState::new(pane_id, pos);
+        state.anchor = Some(pos);
+        state.mouse = true;
+        self.copy = Some(state);
+        self.focus = FocusArea::Copy;
+        self.last_terminal_views.remove(&pane_id);
+    }
+
+    /// Extend a mouse selection.
+    pub fn copy_mouse_drag(&mut self, row: usize, col: usize) {
+        let Some(pane_id) = self.copy.as_ref().filter(|c| c.mouse).map(|c| c.pane_id) else {
+            return;
+        };
+        if let Some(pos) = self.pane_view_pos(pane_id, row, col) {
+            self.copy_move_to(pos.line as isize, pos.col as isize);
+        }
+    }
+
+    /// Finish a mouse selection: copy it unless it was a plain click.
+    pub fn copy_mouse_up(&mut self) {
+        match &self.copy {
+            Some(copy) if copy.mouse && copy.anchor != Some(copy.cursor) => self.copy_yank(),
+            Some(copy) if copy.mouse => self.exit_copy_mode(),
+            _ => {}
+        }
+    }
+
+    /// `(row, col, len)` cells to highlight for copy mode in a pane's view.
+    pub fn copy_highlights(
+        &self,
+        pane_id: PaneId,
+        height: usize,
+        width: usize,
+    ) -> Vec<(usize, usize, usize)> {
+        let Some(copy) = self.copy.as_ref().filter(|c| c.pane_id == pane_id) else {
+            return Vec::new();
+        };
+        let Some(start) = self
+            .terminal_manager
+            .as_ref()
+            .and_then(|manager| manager.try_lock().ok())
+            .and_then(|guard| guard.get_buffer(pane_id).map(|b| b.view_start_line()))
+        else {
+            return Vec::new();
+        };
+        copy.spans(start, height, width)
+            .into_iter()
+            .map(|(line, col, len)| (line - start, col, len))
+            .collect()
+    }
+
+    /// Send text to the input targets as a paste, bracketed if the program asked for it.
+    pub fn paste_into_active_pane(&mut self, text: String) {
+        let Some(manager) = self.terminal_manager.clone() else {
+            return;
+        };
+        let Ok(mut guard) = manager.try_lock() else {
+            return;
+        };
+        for pane_id in self.input_targets() {
+            let bracketed = guard
+                .get_buffer(pane_id)
+                .is_some_and(|b| b.terminal.bracketed_paste);
+            let data = if bracketed {
+                format!("\x1b[200~{}\x1b[201~", text)
+            } else {
+                text.clone()
+            };
+            guard.send_input(pane_id, data.into_bytes());
+        }
+    }
+
+    /// The active pane's scrollback and screen, encoded as `format`.
+    fn capture_active_pane(&self, format: CaptureFormat) -> Result<String, &'static str> {
+        let pane_id = self.active_pane_id().ok_or("No active pane")?;
+        let manager = self
+            .terminal_manager
+            .as_ref()
+            .ok_or("Terminal manager not available")?;
+        let mut guard = manager
+            .try_lock()
+            .map_err(|_| "Could not access terminal")?;
+        let buffer = guard
+            .get_buffer_mut(pane_id)
+            .ok_or("No terminal in active pane")?;
+        Ok(buffer.capture(format))
+    }
+
+    /// Copy the active pane's scrollback to the clipboard.
+    fn copy_scrollback(&mut self, format: CaptureFormat) {
+        match self.capture_active_pane(format) {
+            Ok(text) if text.is_empty() => {
+                self.set_status("Terminal is empty");
+            }
+            Ok(text) => match arboard::Clipboard::new() {
+                Ok(mut clipboard) => match clipboard.set_text(&text) {
+                    Ok(()) => {
+                        let lines = text.lines().count();
+                        self.set_status(format!("Copied {} lines to clipboard", lines));
+                    }
+                    Err(e) => {
+                        self.set_status(format!("Failed to copy: {}", e));
+                    }
+                },
+                Err(e) => {
+                    self.set_status(format!("Clipboard not available: {}", e));
+                }
+            },
+            Err(msg) => {
+                self.set_status(msg);
+            }
+        }
+    }
+
+    /// Save the active pane's scrollback to a new file in the captures directory.
+    fn export_scrollback(&mut self, format: CaptureFormat) {
+        let text = match self.capture_active_pane(format) {
+            Ok(text) if text.is_empty() => {
+                self.set_status("Terminal is empty");
+                return;
+            }
+            Ok(text) => text,
+            Err(msg) => {
+                self.set_status(msg);
+                return;
+            }
+        };
+        let Some(pane_id) = self.active_pane_id() else {
+            return;
+        };
+        let extension = match format {
+            CaptureFormat::Plain => "txt",
+            CaptureFormat::Ansi => "ansi",
+        };
+        let path = capture_path(pane_id, "scrollback", extension);
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, format!("{}\n", text)));
+        match result {
+            Ok(()) => self.set_status(format!(
+                "Saved {} lines to {}",
+                text.lines().count(),
+                path.display()
+            )),
+            Err(e) => self.set_status(format!("Failed to save scrollback: {}", e)),
+        }
+    }
+
+    /// Start teeing the active pane's output to a file, or stop if it already is.
+    fn toggle_pipe_pane(&mut self) {
+        let result: Result<String, String> = (|| {
+            let pane_id = self.active_pane_id().ok_or("No active pane")?;
+            let manager = self
+                .terminal_manager
+                .as_ref()
+                .ok_or("Terminal manager not available")?;
+            let mut guard = manager
+                .try_lock()
+                .map_err(|_| "Could not access terminal")?;
+            guard
+                .get_buffer_mut(pane_id)
+                .ok_or("No terminal in active pane")?;
+            if let Some(path) = guard.stop_pipe(pane_id) {
+                return Ok(format!("Stopped piping output to {}", path.display()));
+            }
+            let path = capture_path(pane_id, "pipe", "log");
+            path.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| guard.start_pipe(pane_id, path.clone()))
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            Ok(format!("Piping output to {}", path.display()))
+        })();
+        match result {
+            Ok(msg) | Err(msg) => self.set_status(msg),
+        }
+    }
+
+    /// Open the command history view.
+    pub fn open_history(&mut self) {
+        self.history = Some(HistoryView::new());
+        self.focus = FocusArea::History;
+        self.refresh_history();
+    }
+
+    pub fn close_history(&mut self) {
+        self.history = None;
+        if self.focus == FocusArea::History {
+            self.focus = FocusArea::MainArea;
+        }
+    }
+
+    /// Recompute history matches for the current query.
+    pub fn refresh_history(&mut self) {
+        let Some(view) = self.history.as_ref() else {
+            return;
+        };
+        let results = self
+            .command_history
+            .search(&view.query())
+            .into_iter()
+            .map(|(pane_id, record)| HistoryEntry {
+                pane_label: self.pane_label(pane_id),
+                command: record.command.clone(),
+                exit_code: record.exit_code,
+            })
+            .collect();
+        if let Some(view) = self.history.as_mut() {
+            view.results = results;
+            view.current = 0;
+        }
+    }
+
+    /// Close the history view and type the selected command into the active
+    /// pane, pressing Enter too when `run` is set.
+    pub fn replay_history_selection(&mut self, run: bool) {
+        let command = self
+            .history
+            .as_ref()
+            .and_then(|view| view.selected())
+            .map(|entry| entry.command.clone());
+        self.close_history();
+        if let Some(command) = command {
+            self.replay_command(&command, run);
+        }
+    }
+
+    /// Send a command line to the active terminal pane.
+    pub fn replay_command(&mut self, command: &str, run: bool) {
+        let Some(pane_id) = self.active_pane_id() else {
+            self.set_status("No active pane");
+            return;
+        };
+        let is_terminal = self
+            .active_tab()
+            .and_then(|tab| tab.layout.find_pane(pane_id))
+            .is_some_and(|pane| matches!(pane.content, PaneContent::Terminal { .. }));
+        if !is_terminal {
+            self.set_status("The active pane isn't a terminal");
+            return;
+        }
+        let mut input = command.replace('\n', "\r");
+        if run {
+            input.push('\r');
+        }
+        let _ = self.event_tx.send(MuxEvent::SendTerminalInput {
+            pane_id,
+            input: input.into_bytes(),
+        });
+    }
+
+    /// Open the theme picker with the current theme selected.
+    pub fn open_theme_picker(&mut self) {
+        self.theme_picker = Some(ThemePicker::new(available_themes(), self.theme.clone()));
+        self.focus = FocusArea::ThemePicker;
+    }
+
+    /// Preview the theme under the picker's selection.
+    pub fn preview_theme_selection(&mut self) {
+        if let Some(theme) = self
+            .theme_picker
+            .as_ref()
+            .and_then(|picker| picker.selected_theme())
+        {
+            self.apply_theme(theme.clone());
+        }
+    }
+
+    /// Close the picker, keeping the selected theme and saving it when `keep`
+    /// is set, or going back to the theme it was opened with otherwise.
+    pub fn close_theme_picker(&mut self, keep: bool) {
+        let Some(picker) = self.theme_picker.take() else {
+            return;
+        };
+        if self.focus == FocusArea::ThemePicker {
+            self.focus = FocusArea::MainArea;
+        }
+        if !keep {
+            self.apply_theme(picker.original);
+            return;
+        }
+        let Some(theme) = picker.selected_theme().cloned() else {
+            return;
+        };
+        let name = theme.name.clone();
+        self.apply_theme(theme);
+        self.settings.theme = Some(name.clone());
+        if let Err(e) = self.settings.save() {
+            self.set_status(format!(
+                "Theme set to {} (warning: failed to save: {})",
+                name, e
+            ));
+        } else {
+            self.set_status(format!("Theme set to {}", name));
+        }
+    }
+
+    fn apply_theme(&mut self, theme: Theme) {
+        if self.theme != theme {
+            self.theme = theme;
+            // Search and selection highlights are part of the cached views.
+            self.last_terminal_views.clear();
+        }
+    }
+
+    /// "Tab · pane" label for a pane in any workspace.
+    fn pane_label(&self, pane_id: PaneId) -> String {
+        self.workspace_manager
+            .sandbox_ids()
+            .iter()
+            .filter_map(|id| self.workspace_manager.get_workspace(*id))
+            .flat_map(|ws| &ws.tabs)
+            .find_map(|tab| {
+                let pane = tab.layout.find_pane(pane_id)?;
+                Some(format!("{} · {}", tab.name, pane.title()))
+            })
+            .unwrap_or_else(|| "closed pane".to_string())
+    }
+
+    /// Jump to the selected all-panes result and keep searching in that pane.
+    pub fn open_search_result(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        let Some(result) = search.results.get(search.current).cloned() else {
+            self.search = Some(search);
+            return;
+        };
+        if let Some(workspace) = self.workspace_manager.active_workspace_mut() {
+            if let Some(index) = workspace
+                .tabs
+                .iter()
+                .position(|tab| tab.contains_pane(result.pane_id))
+            {
+                workspace.go_to_tab(index);
+                workspace.tabs[index].active_pane = Some(result.pane_id);
+            }
+        }
+        if self.zoomed_pane.is_some_and(|id| id != result.pane_id) {
+            self.zoomed_pane = None;
+        }
+
+        let mut state = SearchState::new(SearchScope::ActivePane, Some(result.pane_id));
+        state.input = search.input;
+        state.editing = false;
+        self.search = Some(state);
+        self.refresh_search();
+        if let Some(search) = self.search.as_mut() {
+            if let Some(index) = search.matches.iter().position(|m| *m == result.at) {
+                search.current = index;
+            }
+        }
+        self.reveal_search_match();
+    }
+
+    /// Highlights visible in a pane as `(row, col, len, is_current)`.
+    pub fn search_highlights(
+        &self,
+        pane_id: PaneId,
+        height: usize,
+    ) -> Vec<(usize, usize, usize, bool)> {
+        let Some(search) = &self.search else {
+            return Vec::new();
+        };
+        if search.scope != SearchScope::ActivePane || search.pane_id != Some(pane_id) {
+            return Vec::new();
+        }
+        let Some(start) = self
+            .terminal_manager
+            .as_ref()
+            .and_then(|manager| manager.try_lock().ok())
+            .and_then(|guard| guard.get_buffer(pane_id).map(|b| b.view_start_line()))
+        else {
+            return Vec::new();
+        };
+        let current = search.current_match();
+        search
+            .matches
+            .iter()
+            .filter(|m| m.line >= start && m.line < start + height)
+            .map(|m| (m.line - start, m.col, m.len, Some(*m) == current))
+            .collect()
+    }
+
     /// Start tab rename mode.
     fn start_tab_rename(&mut self) {
         if let Some(tab) = self.active_tab() {
@@ -1174,8 +1975,10 @@ impl<'a> MuxApp<'a> {
                     self.add_sandbox(&sandbox_id_str, &sandbox.name);
                 }
 
+                let restored = self.apply_pending_session();
+
                 // Only add to pending_connects on first load (when we had no active sandbox)
-                if !had_active && self.pending_connects.is_empty() {
+                if !had_active && !restored && self.pending_connects.is_empty() {
                     if let Some(first) = self.sidebar.sandboxes.first() {
                         let first_id = first.id.to_string();
                         self.pending_connects.push_back(first_id);
@@ -1239,7 +2042,7 @@ impl<'a> MuxApp<'a> {
                     self.sidebar
                         .sandboxes
                         .retain(|existing| existing.id != sandbox.id);
-                    self.sidebar.sandboxes.push(sandbox.clone());
+                    self.sidebar.sandboxes.push((*sandbox).clone());
                     self.add_sandbox(&sandbox_id_str, &sandbox.name);
                 }
 
@@ -1308,6 +2111,9 @@ impl<'a> MuxApp<'a> {
             MuxEvent::TerminalOutput { .. } => {
                 // TODO: Forward to appropriate pane
             }
+            MuxEvent::ShellCommand { pane_id, event } => {
+                self.command_history.apply(pane_id, event);
+            }
             MuxEvent::Error(msg) => {
                 self.set_status(format!("Error: {}", msg));
             }
@@ -1346,6 +2152,9 @@ impl<'a> MuxApp<'a> {
             MuxEvent::ExecInSandbox { .. } => {
                 // Exec requests are handled in the runner
             }
+            MuxEvent::CopyToClipboard { .. } => {
+                // Clipboard writes need the outer terminal and are handled in the runner
+            }
         }
     }
 
@@ -1377,6 +2186,7 @@ impl<'a> MuxApp<'a> {
             },
             display: None, // Will be populated when sandbox is actually created
             correlation_id: tab_id_str.clone(), // Stored on sandbox itself - single source of truth
+            resources: None,
         };
 
         self.sidebar.sandboxes.push(summary);
@@ -1443,6 +2253,23 @@ impl<'a> MuxApp<'a> {
     }
 }
 
+/// Where scrollback exports and pipe-pane logs go:
+/// `<data dir>/cmux/captures/<kind>-<time>-<pane>.<extension>`.
+fn capture_path(pane_id: PaneId, kind: &str, extension: &str) -> PathBuf {
+    let dir = dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("cmux")
+        .join("captures");
+    let pane = pane_id.to_string();
+    dir.join(format!(
+        "{}-{}-{}.{}",
+        kind,
+        Local::now().format("%Y%m%d-%H%M%S"),
+        &pane[..8],
+        extension
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1462,7 +2289,7 @@ mod tests {
         app.most_recent_creation_tab_id = Some(tab_id.clone());
 
         app.handle_event(MuxEvent::SandboxCreated {
-            sandbox: sandbox.clone(),
+            sandbox: Box::new(sandbox.clone()),
             tab_id: Some(tab_id),
         });
 
@@ -1494,7 +2321,7 @@ mod tests {
         app.most_recent_creation_tab_id = Some(initiated_tab_id);
 
         app.handle_event(MuxEvent::SandboxCreated {
-            sandbox: sandbox.clone(),
+            sandbox: Box::new(sandbox.clone()),
             tab_id: Some(Uuid::new_v4().to_string()), // Different tab_id
         });
 
@@ -1524,6 +2351,118 @@ mod tests {
         );
     }
 
+    #[test]
+    fn layout_changes_leave_zoom() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = MuxApp::new("http://localhost".to_string(), tx, PathBuf::from("."));
+        app.handle_event(MuxEvent::SandboxesRefreshed(vec![sample_sandbox("demo")]));
+
+        app.execute_command(MuxCommand::SplitVertical);
+        app.execute_command(MuxCommand::ToggleZoom);
+        assert_eq!(app.zoomed_pane, app.active_pane_id());
+
+        app.execute_command(MuxCommand::ResizeLeft);
+        assert!(app.zoomed_pane.is_none());
+    }
+
+    #[test]
+    fn open_agent_chat_splits_in_a_chat_pane_for_the_sandbox() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = MuxApp::new("http://localhost".to_string(), tx, PathBuf::from("."));
+        app.execute_command(MuxCommand::OpenAgentChat);
+        assert!(app.active_tab().is_none());
+
+        let sandbox = sample_sandbox("demo");
+        app.handle_event(MuxEvent::SandboxesRefreshed(vec![sandbox.clone()]));
+        app.execute_command(MuxCommand::OpenAgentChat);
+
+        let tab = app.active_tab().unwrap();
+        assert_eq!(tab.layout.panes().len(), 2);
+        let active = tab.layout.find_pane(tab.active_pane.unwrap()).unwrap();
+        assert!(matches!(
+            &active.content,
+            PaneContent::Chat { sandbox_id, .. } if *sandbox_id == sandbox.id.to_string()
+        ));
+    }
+
+    #[test]
+    fn theme_picker_previews_and_cancel_restores_the_theme() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = MuxApp::new("http://localhost".to_string(), tx, PathBuf::from("."));
+        let original = app.theme.clone();
+
+        app.execute_command(MuxCommand::SelectTheme);
+        assert_eq!(app.focus, FocusArea::ThemePicker);
+        let picker = app.theme_picker.as_mut().unwrap();
+        assert!(picker.themes.iter().any(|t| t.name == "light"));
+        picker.select_down();
+        app.preview_theme_selection();
+        assert_ne!(app.theme.name, original.name);
+
+        app.close_theme_picker(false);
+        assert_eq!(app.theme, original);
+        assert_eq!(app.focus, FocusArea::MainArea);
+        assert!(app.theme_picker.is_none());
+    }
+
+    #[test]
+    fn shell_commands_are_searchable_and_replay_into_the_active_pane() {
+        use crate::mux::history::ShellEvent;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = MuxApp::new("http://localhost".to_string(), tx, PathBuf::from("."));
+        app.handle_event(MuxEvent::SandboxesRefreshed(vec![sample_sandbox("demo")]));
+        let pane_id = app.active_pane_id().unwrap();
+        for command in ["cargo build", "git status"] {
+            app.handle_event(MuxEvent::ShellCommand {
+                pane_id,
+                event: ShellEvent::CommandStarted(command.to_string()),
+            });
+        }
+        while rx.try_recv().is_ok() {}
+
+        app.execute_command(MuxCommand::RerunLastCommand);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(MuxEvent::SendTerminalInput { pane_id: id, input })
+                if id == pane_id && input == b"git status\r"
+        ));
+
+        app.execute_command(MuxCommand::SearchCommandHistory);
+        assert_eq!(app.focus, FocusArea::History);
+        for ch in "cargo".chars() {
+            app.history.as_mut().unwrap().input.insert_char(ch);
+        }
+        app.refresh_history();
+        assert_eq!(app.history.as_ref().unwrap().results.len(), 1);
+        app.replay_history_selection(false);
+        assert_eq!(app.focus, FocusArea::MainArea);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(MuxEvent::SendTerminalInput { input, .. }) if input == b"cargo build"
+        ));
+    }
+
+    #[test]
+    fn broadcast_targets_every_terminal_pane_in_tab() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut app = MuxApp::new("http://localhost".to_string(), tx, PathBuf::from("."));
+        app.handle_event(MuxEvent::SandboxesRefreshed(vec![sample_sandbox("demo")]));
+        app.execute_command(MuxCommand::SplitVertical);
+        app.execute_command(MuxCommand::SplitHorizontal);
+
+        assert_eq!(
+            app.input_targets(),
+            app.active_pane_id().into_iter().collect::<Vec<_>>()
+        );
+
+        app.execute_command(MuxCommand::ToggleBroadcast);
+        assert_eq!(app.input_targets().len(), 3);
+
+        app.execute_command(MuxCommand::ToggleBroadcast);
+        assert_eq!(app.input_targets().len(), 1);
+    }
+
     #[test]
     fn notifications_track_read_state() {
         let mut notifications = NotificationsState::new();
@@ -1572,6 +2511,7 @@ mod tests {
             },
             display: None,
             correlation_id: None,
+            resources: None,
         }
     }
 }
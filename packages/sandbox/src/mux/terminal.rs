@@ -7,12 +7,17 @@ use tokio::sync::Mutex;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use vte::{Params, Parser, Perform};
 
+use crate::client_auth::websocket_request;
 use crate::models::{MuxClientMessage, MuxServerMessage, PtySessionId};
 use crate::mux::character::{CharacterStyles, Row, TerminalCharacter};
 use crate::mux::colors::{get_outer_bg, get_outer_fg};
+use crate::mux::copy::decode_osc52;
 use crate::mux::events::MuxEvent;
-use crate::mux::grid::Grid;
+use crate::mux::grid::{CaptureFormat, Grid};
+use crate::mux::history::ShellEvent;
 use crate::mux::layout::{PaneId, TabId};
+use crate::mux::search::{find_in_line, SearchMatch};
+use crate::mux::session::{shell_in_dir, RestoredPane};
 
 /// A single cell in the terminal grid (legacy compatibility type).
 /// This is used for backward compatibility with existing tests and APIs.
@@ -44,6 +49,28 @@ impl From<&TerminalCharacter> for Cell {
     }
 }
 
+/// Extract the path from an OSC 7 `file://host/path` URI, percent-decoding it.
+fn parse_osc7_path(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
 /// Line drawing character mapping (DEC Special Graphics)
 fn line_drawing_char(c: char) -> char {
     match c {
@@ -725,6 +752,14 @@ pub struct VirtualTerminal {
     pub bell_pending: bool,
     /// Window title (set via OSC)
     pub title: Option<String>,
+    /// Working directory reported by the shell (OSC 7)
+    pub current_dir: Option<String>,
+    /// Text a program asked to put on the clipboard (OSC 52), not yet forwarded
+    pub clipboard_request: Option<String>,
+    /// Where the shell's command input began (OSC 133 B), as (absolute line, col)
+    command_start: Option<(usize, usize)>,
+    /// Commands run and finished since last drained (OSC 133 C and D)
+    pub shell_events: Vec<ShellEvent>,
     /// Last printed character (for REP - repeat)
     last_printed_char: Option<char>,
     /// Pending responses to send back to the PTY (e.g., DSR cursor position report)
@@ -824,6 +859,10 @@ impl VirtualTerminal {
             sgr_mouse_mode: false,
             bell_pending: false,
             title: None,
+            current_dir: None,
+            clipboard_request: None,
+            command_start: None,
+            shell_events: Vec::new(),
             last_printed_char: None,
             pending_responses: Vec::new(),
             default_fg_color: None,     // Use terminal's native color
@@ -866,6 +905,47 @@ impl VirtualTerminal {
         self.internal_grid.cursor_col
     }
 
+    /// Cursor position as (absolute line, col), scrollback first.
+    fn absolute_cursor(&self) -> (usize, usize) {
+        let grid = &self.internal_grid;
+        (grid.lines_above.len() + grid.cursor_row, grid.cursor_col)
+    }
+
+    /// Text from `start` up to the cursor. Rows are joined with newlines
+    /// unless the previous one was filled to the last column (auto-wrapped).
+    fn text_from(&self, start: (usize, usize)) -> String {
+        let grid = &self.internal_grid;
+        let (end_line, end_col) = self.absolute_cursor();
+        let row_at = |line: usize| {
+            grid.lines_above
+                .get(line)
+                .or_else(|| grid.viewport.get(line.checked_sub(grid.lines_above.len())?))
+        };
+        let mut text = String::new();
+        let mut wrapped = false;
+        for line in start.0..=end_line {
+            let Some(row) = row_at(line) else {
+                continue;
+            };
+            let chars: Vec<char> = row.as_string().chars().collect();
+            if line > start.0 && !wrapped {
+                text.truncate(text.trim_end().len());
+                text.push('\n');
+            }
+            wrapped = chars.len() >= grid.cols && chars.last().is_some_and(|c| *c != ' ');
+            let from = if line == start.0 { start.1 } else { 0 };
+            let to = if line == end_line {
+                end_col.min(chars.len())
+            } else {
+                chars.len()
+            };
+            if from < to {
+                text.extend(&chars[from..to]);
+            }
+        }
+        text.trim().to_string()
+    }
+
     /// Set cursor row
     #[inline]
     pub fn set_cursor_row(&mut self, row: usize) {
@@ -1771,6 +1851,43 @@ impl Perform for VirtualTerminal {
                         }
                     }
                 }
+                // OSC 7 - Current working directory (file://host/path)
+                "7" if params.len() > 1 => {
+                    let uri = params[1..]
+                        .iter()
+                        .map(|p| String::from_utf8_lossy(p))
+                        .collect::<Vec<_>>()
+                        .join(";");
+                    if let Some(dir) = parse_osc7_path(&uri) {
+                        self.current_dir = Some(dir);
+                    }
+                }
+                // OSC 133 - Shell integration prompt/command marks
+                "133" if params.len() > 1 && self.alternate_screen.is_none() => match params[1] {
+                    b"A" => self.command_start = None,
+                    b"B" => self.command_start = Some(self.absolute_cursor()),
+                    b"C" => {
+                        if let Some(start) = self.command_start.take() {
+                            let command = self.text_from(start);
+                            self.shell_events.push(ShellEvent::CommandStarted(command));
+                        }
+                    }
+                    b"D" => {
+                        let exit_code = params
+                            .get(2)
+                            .and_then(|code| std::str::from_utf8(code).ok())
+                            .and_then(|code| code.parse().ok());
+                        self.shell_events
+                            .push(ShellEvent::CommandFinished(exit_code));
+                    }
+                    _ => {}
+                },
+                // OSC 52 - Set clipboard; forwarded to the outer terminal
+                "52" if params.len() > 2 => {
+                    if let Some(text) = decode_osc52(params[1], params[2]) {
+                        self.clipboard_request = Some(text);
+                    }
+                }
                 // OSC 4 - Query/Set indexed color (256-color palette)
                 // Format: OSC 4 ; index ; colorspec ST or OSC 4 ; index ; ? ST
                 "4" => {
@@ -3186,6 +3303,62 @@ impl TerminalBuffer {
         lines.join("\n")
     }
 
+    /// Scrollback and screen contents for export; see [`Grid::capture`].
+    pub fn capture(&self, format: CaptureFormat) -> String {
+        self.terminal.internal_grid.capture(format)
+    }
+
+    /// Find all matches for `query` across scrollback and viewport.
+    pub fn search(&self, query: &str) -> Vec<SearchMatch> {
+        let grid = &self.terminal.internal_grid;
+        grid.lines_above
+            .iter()
+            .chain(grid.viewport.iter())
+            .enumerate()
+            .flat_map(|(line, row)| {
+                find_in_line(&row.as_string(), query)
+                    .into_iter()
+                    .map(move |(col, len)| SearchMatch { line, col, len })
+            })
+            .collect()
+    }
+
+    /// Text of an absolute line (scrollback first, then viewport), trimmed.
+    pub fn line_text(&self, line: usize) -> Option<String> {
+        let grid = &self.terminal.internal_grid;
+        let row = if line < grid.lines_above.len() {
+            grid.lines_above.get(line)
+        } else {
+            grid.viewport.get(line - grid.lines_above.len())
+        }?;
+        Some(row.as_string().trim_end().to_string())
+    }
+
+    /// Absolute line shown in the top row of the pane at the current scroll offset.
+    pub fn view_start_line(&self) -> usize {
+        let total = self.terminal.scrollback_len() + self.terminal.internal_grid.viewport.len();
+        total
+            .saturating_sub(self.scroll_offset)
+            .saturating_sub(self.terminal.rows())
+    }
+
+    /// Scroll so that an absolute line is visible, roughly centred when it
+    /// lives in scrollback.
+    pub fn scroll_to_line(&mut self, line: usize) {
+        let scrollback = self.terminal.scrollback_len();
+        let offset = if line >= scrollback {
+            0
+        } else {
+            let total = scrollback + self.terminal.internal_grid.viewport.len();
+            let end = (line + self.terminal.rows() / 2 + 1).min(total);
+            (total - end).min(scrollback)
+        };
+        if offset != self.scroll_offset {
+            self.scroll_offset = offset;
+            self.mark_dirty();
+        }
+    }
+
     /// Try to extract a URL at the given row and column (0-indexed).
     pub fn url_at_position(&self, row: usize, col: usize) -> Option<String> {
         if self.scroll_offset != 0 {
@@ -3356,6 +3529,16 @@ pub struct TerminalManager {
     mux_sender: Option<MuxConnectionSender>,
     /// Flag indicating if connection is being established
     connecting: bool,
+    /// Saved scrollback and working directory for panes restored from a session
+    restored: HashMap<PaneId, RestoredPane>,
+    /// Files that panes' raw output is being teed to ("pipe pane")
+    pipes: HashMap<PaneId, PanePipe>,
+}
+
+/// A file receiving a pane's output as it arrives.
+struct PanePipe {
+    path: std::path::PathBuf,
+    file: std::fs::File,
 }
 
 impl TerminalManager {
@@ -3369,6 +3552,8 @@ impl TerminalManager {
             event_tx,
             mux_sender: None,
             connecting: false,
+            restored: HashMap::new(),
+            pipes: HashMap::new(),
         }
     }
 
@@ -3472,12 +3657,64 @@ impl TerminalManager {
         data: Vec<u8>,
     ) -> Option<PaneId> {
         let pane_id = *self.session_to_pane.get(session_id)?;
+        self.write_to_pipe(pane_id, &data);
         // Process the output (this updates terminal state and may generate responses,
         // but we discard them since the sandbox server handles responses)
         let _responses = self.handle_output(pane_id, data);
+        if let Some(text) = self
+            .buffers
+            .get_mut(&pane_id)
+            .and_then(|b| b.terminal.clipboard_request.take())
+        {
+            let _ = self.event_tx.send(MuxEvent::CopyToClipboard { text });
+        }
+        if let Some(buffer) = self.buffers.get_mut(&pane_id) {
+            for event in buffer.terminal.shell_events.drain(..) {
+                let _ = self
+                    .event_tx
+                    .send(MuxEvent::ShellCommand { pane_id, event });
+            }
+        }
         Some(pane_id)
     }
 
+    /// Start appending everything `pane_id` outputs, escape sequences
+    /// included, to the file at `path`.
+    pub fn start_pipe(&mut self, pane_id: PaneId, path: std::path::PathBuf) -> std::io::Result<()> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        self.pipes.insert(pane_id, PanePipe { path, file });
+        Ok(())
+    }
+
+    /// Stop piping a pane's output. Returns the file it was going to.
+    pub fn stop_pipe(&mut self, pane_id: PaneId) -> Option<std::path::PathBuf> {
+        self.pipes.remove(&pane_id).map(|pipe| pipe.path)
+    }
+
+    /// The file a pane's output is being piped to, if any.
+    pub fn pipe_path(&self, pane_id: PaneId) -> Option<&std::path::Path> {
+        self.pipes.get(&pane_id).map(|pipe| pipe.path.as_path())
+    }
+
+    fn write_to_pipe(&mut self, pane_id: PaneId, data: &[u8]) {
+        use std::io::Write;
+
+        let Some(pipe) = self.pipes.get_mut(&pane_id) else {
+            return;
+        };
+        if let Err(e) = pipe.file.write_all(data) {
+            let path = pipe.path.display().to_string();
+            self.pipes.remove(&pane_id);
+            let _ = self.event_tx.send(MuxEvent::Error(format!(
+                "Stopped piping pane output to {}: {}",
+                path, e
+            )));
+        }
+    }
+
     /// Get pane ID for a session ID
     pub fn get_pane_for_session(&self, session_id: &PtySessionId) -> Option<PaneId> {
         self.session_to_pane.get(session_id).copied()
@@ -3510,6 +3747,8 @@ impl TerminalManager {
         }
         self.last_sizes.remove(&pane_id);
         self.buffers.remove(&pane_id);
+        self.restored.remove(&pane_id);
+        self.pipes.remove(&pane_id);
     }
 
     /// Clear a terminal buffer
@@ -3553,11 +3792,31 @@ impl TerminalManager {
 
     /// Initialize a buffer with specific size
     pub fn init_buffer(&mut self, pane_id: PaneId, rows: usize, cols: usize) {
-        self.buffers
-            .insert(pane_id, TerminalBuffer::with_size(rows.max(1), cols.max(1)));
+        let mut buffer = TerminalBuffer::with_size(rows.max(1), cols.max(1));
+        if let Some(restored) = self.restored.get_mut(&pane_id) {
+            // Replay the saved output so the pane picks up where it left off.
+            for line in restored.scrollback.drain(..) {
+                buffer.process(line.as_bytes());
+                buffer.process(b"\r\n");
+            }
+            buffer.process(b"\x1b[2m-- session restored --\x1b[0m\r\n");
+        }
+        self.buffers.insert(pane_id, buffer);
         self.last_sizes.insert(pane_id, (rows as u16, cols as u16));
     }
 
+    /// Stash saved state for a pane so the next connect replays its scrollback
+    /// and starts the shell in its previous working directory.
+    pub fn restore_pane(&mut self, pane_id: PaneId, restored: RestoredPane) {
+        self.restored.insert(pane_id, restored);
+    }
+
+    /// Take the shell command that reopens a restored pane in its saved directory.
+    fn take_restore_command(&mut self, pane_id: PaneId) -> Option<Vec<String>> {
+        let restored = self.restored.remove(&pane_id)?;
+        restored.cwd.as_deref().map(shell_in_dir)
+    }
+
     /// Register a new session for a pane (called after receiving Attached message)
     pub fn register_session(
         &mut self,
@@ -3622,7 +3881,8 @@ pub async fn establish_mux_connection(manager: SharedTerminalManager) -> anyhow:
 
     let url = format!("{}/mux/attach", ws_url);
 
-    let (ws_stream, _) = match connect_async(&url).await {
+    let request = websocket_request(&url)?;
+    let (ws_stream, _) = match connect_async(request).await {
         Ok(stream) => stream,
         Err(e) => {
             let mut mgr = manager.lock().await;
@@ -3681,7 +3941,7 @@ pub async fn establish_mux_connection(manager: SharedTerminalManager) -> anyhow:
                                 MuxServerMessage::SandboxCreated(summary) => {
                                     // No tab_id for server-broadcast events (created by other clients)
                                     let _ = event_tx_clone.send(MuxEvent::SandboxCreated {
-                                        sandbox: summary,
+                                        sandbox: Box::new(summary),
                                         tab_id: None,
                                     });
                                 }
@@ -3832,6 +4092,7 @@ pub async fn connect_to_sandbox(
 
         // Register the session (optimistically - server will confirm)
         mgr.register_session(pane_id, session_id.clone(), sandbox_id.clone());
+        let command = mgr.take_restore_command(pane_id);
 
         // Send attach message
         if let Some(sender) = mgr.get_mux_sender() {
@@ -3840,7 +4101,7 @@ pub async fn connect_to_sandbox(
                 sandbox_id: sandbox_id.clone(),
                 cols,
                 rows,
-                command: None,
+                command,
                 tty: true,
                 tab_id: tab_id_string,
                 pane_id: Some(pane_id_string),
@@ -4645,6 +4906,100 @@ mod tests {
         assert_eq!(term.get_palette_color(235), (53, 55, 49));
     }
 
+    #[test]
+    fn osc7_sets_current_dir() {
+        let mut buffer = TerminalBuffer::with_size(5, 40);
+        buffer.process(b"\x1b]7;file://host/home/user/my%20project\x07");
+        assert_eq!(
+            buffer.terminal.current_dir.as_deref(),
+            Some("/home/user/my project")
+        );
+        buffer.process(b"\x1b]7;not-a-uri\x07");
+        assert_eq!(
+            buffer.terminal.current_dir.as_deref(),
+            Some("/home/user/my project")
+        );
+    }
+
+    #[test]
+    fn osc133_records_commands_and_exit_codes() {
+        let mut buffer = TerminalBuffer::with_size(5, 20);
+        buffer.process(b"\x1b]133;A\x07$ \x1b]133;B\x07echo hello world again\r\n");
+        buffer.process(b"\x1b]133;C\x07hello world again\r\n\x1b]133;D;0\x07");
+        buffer.process(b"\x1b]133;A\x07$ \x1b]133;B\x07false\r\n\x1b]133;C\x07\x1b]133;D;1\x07");
+        // Output without a prompt mark in front isn't a command.
+        buffer.process(b"\x1b]133;C\x07");
+        assert_eq!(
+            std::mem::take(&mut buffer.terminal.shell_events),
+            [
+                ShellEvent::CommandStarted("echo hello world again".to_string()),
+                ShellEvent::CommandFinished(Some(0)),
+                ShellEvent::CommandStarted("false".to_string()),
+                ShellEvent::CommandFinished(Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipe_pane_tees_raw_output_until_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pane.log");
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut manager = TerminalManager::new("http://localhost".to_string(), tx);
+        let pane_id = PaneId::new();
+        manager.register_session(pane_id, "s1".to_string(), "sandbox".to_string());
+
+        manager.start_pipe(pane_id, path.clone()).unwrap();
+        manager.handle_output_by_session(&"s1".to_string(), b"\x1b[31mred\x1b[0m\r\n".to_vec());
+        assert_eq!(manager.stop_pipe(pane_id), Some(path.clone()));
+        manager.handle_output_by_session(&"s1".to_string(), b"after\r\n".to_vec());
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"\x1b[31mred\x1b[0m\r\n");
+        let buffer = manager.get_buffer_mut(pane_id).unwrap();
+        assert_eq!(buffer.capture(CaptureFormat::Plain), "red\nafter");
+        assert_eq!(
+            buffer.capture(CaptureFormat::Ansi),
+            "\x1b[0;31mred\x1b[0m\nafter"
+        );
+    }
+
+    #[test]
+    fn osc52_records_clipboard_request() {
+        let mut buffer = TerminalBuffer::with_size(5, 40);
+        buffer.process(b"\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(
+            buffer.terminal.clipboard_request.take().as_deref(),
+            Some("hello")
+        );
+        buffer.process(b"\x1b]52;c;?\x07");
+        assert!(buffer.terminal.clipboard_request.is_none());
+    }
+
+    #[test]
+    fn buffer_search_spans_scrollback_and_scrolls_to_match() {
+        let mut buffer = TerminalBuffer::with_size(5, 40);
+        for i in 0..20 {
+            buffer.process(format!("line {i}\r\n").as_bytes());
+        }
+        buffer.process(b"error: boom");
+
+        let matches = buffer.search("line 3");
+        assert_eq!(matches.len(), 1);
+        let at = matches[0];
+        assert_eq!(buffer.line_text(at.line).as_deref(), Some("line 3"));
+        assert_eq!(at.col, 0);
+
+        buffer.scroll_to_line(at.line);
+        let start = buffer.view_start_line();
+        assert!(at.line >= start && at.line < start + buffer.rows());
+
+        let last = buffer.search("ERROR");
+        assert!(last.is_empty(), "uppercase query is case-sensitive");
+        let last = buffer.search("error");
+        buffer.scroll_to_line(last[0].line);
+        assert_eq!(buffer.scroll_offset(), 0);
+    }
+
     #[test]
     fn osc4_palette_color_stays_indexed_in_render() {
         use crate::mux::terminal::TerminalBuffer;
@@ -0,0 +1,423 @@
+//! Color themes for the multiplexer chrome (sidebar, tabs, borders, palette
+//! and overlays). Pane contents keep the colors their programs ask for.
+//!
+//! Themes are TOML files (see `themes/dark.toml` for the format). The
+//! built-in `dark` and `light` themes are always available; files in
+//! `<config dir>/cmux/themes/` are added to them, replacing a built-in of the
+//! same name.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+const APP_NAME: &str = "cmux";
+const THEMES_DIR: &str = "themes";
+
+/// Theme used when none is configured or the configured one is missing.
+pub const DEFAULT_THEME: &str = "dark";
+
+const BUILTIN_THEMES: [&str; 2] = [
+    include_str!("themes/dark.toml"),
+    include_str!("themes/light.toml"),
+];
+
+/// How many colors the outer terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guess from `COLORTERM` and `TERM`, the way most terminal programs do.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        Self::from_env(&colorterm, &term)
+    }
+
+    fn from_env(colorterm: &str, term: &str) -> Self {
+        if matches!(colorterm, "truecolor" | "24bit") || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// The colors each part of the UI is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThemeColors {
+    /// Focused borders, titles, the selected item
+    pub accent: Color,
+    /// Regular text and unfocused titles
+    pub text: Color,
+    /// Hints, unfocused borders, secondary details
+    pub muted: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+    /// Text drawn on an accent, warning or error background
+    pub on_accent: Color,
+    /// Section headings in lists
+    pub heading: Color,
+    /// Background of search matches
+    pub search_match: Color,
+    /// Background of the current search match
+    pub search_current: Color,
+    /// Background of the copy-mode selection
+    pub selection: Color,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            accent: Color::Cyan,
+            text: Color::White,
+            muted: Color::DarkGray,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+            on_accent: Color::Black,
+            heading: Color::Magenta,
+            search_match: Color::Blue,
+            search_current: Color::Yellow,
+            selection: Color::LightBlue,
+        }
+    }
+}
+
+/// A named set of colors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub colors: ThemeColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            colors: ThemeColors::default(),
+        }
+    }
+}
+
+/// A color as written in a theme file: a name, `#rrggbb`, or a 256-color index.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ColorSpec {
+    Index(u8),
+    Name(String),
+}
+
+impl ColorSpec {
+    fn to_color(&self) -> Result<Color, String> {
+        match self {
+            ColorSpec::Index(index) => Ok(Color::Indexed(*index)),
+            ColorSpec::Name(name) => {
+                Color::from_str(name).map_err(|_| format!("unknown color {name:?}"))
+            }
+        }
+    }
+}
+
+/// One color-depth section of a theme file. Unset roles are inherited.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorOverrides {
+    accent: Option<ColorSpec>,
+    text: Option<ColorSpec>,
+    muted: Option<ColorSpec>,
+    success: Option<ColorSpec>,
+    warning: Option<ColorSpec>,
+    error: Option<ColorSpec>,
+    on_accent: Option<ColorSpec>,
+    heading: Option<ColorSpec>,
+    search_match: Option<ColorSpec>,
+    search_current: Option<ColorSpec>,
+    selection: Option<ColorSpec>,
+}
+
+impl ColorOverrides {
+    fn apply(&self, colors: &mut ThemeColors) -> Result<(), String> {
+        let roles = [
+            (&self.accent, &mut colors.accent),
+            (&self.text, &mut colors.text),
+            (&self.muted, &mut colors.muted),
+            (&self.success, &mut colors.success),
+            (&self.warning, &mut colors.warning),
+            (&self.error, &mut colors.error),
+            (&self.on_accent, &mut colors.on_accent),
+            (&self.heading, &mut colors.heading),
+            (&self.search_match, &mut colors.search_match),
+            (&self.search_current, &mut colors.search_current),
+            (&self.selection, &mut colors.selection),
+        ];
+        for (spec, color) in roles {
+            if let Some(spec) = spec {
+                *color = spec.to_color()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A theme file as written on disk.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    /// Built-in theme to take unset roles from (default: `dark`)
+    base: Option<String>,
+    #[serde(default)]
+    ansi16: ColorOverrides,
+    #[serde(default)]
+    ansi256: ColorOverrides,
+    #[serde(default)]
+    truecolor: ColorOverrides,
+}
+
+impl ThemeFile {
+    fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Resolve to concrete colors for a terminal with `depth` colors.
+    fn resolve(&self, depth: ColorDepth) -> Result<Theme, String> {
+        let mut colors = match self.base.as_deref() {
+            Some(base) if base != self.name => {
+                builtin_theme(base, depth)
+                    .ok_or_else(|| format!("unknown base theme {base:?}"))?
+                    .colors
+            }
+            _ => ThemeColors::default(),
+        };
+        self.ansi16.apply(&mut colors)?;
+        if depth >= ColorDepth::Ansi256 {
+            self.ansi256.apply(&mut colors)?;
+        }
+        if depth >= ColorDepth::TrueColor {
+            self.truecolor.apply(&mut colors)?;
+        }
+        Ok(Theme {
+            name: self.name.clone(),
+            colors,
+        })
+    }
+}
+
+fn builtin_theme(name: &str, depth: ColorDepth) -> Option<Theme> {
+    BUILTIN_THEMES
+        .iter()
+        .filter_map(|contents| ThemeFile::parse(contents).ok())
+        .find(|file| file.name == name)
+        .and_then(|file| file.resolve(depth).ok())
+}
+
+fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_NAME).join(THEMES_DIR))
+}
+
+/// Load every `*.toml` theme in `dir`. Broken files are skipped with a warning.
+fn load_dir(dir: &Path, depth: ColorDepth) -> Vec<Theme> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let loaded = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| ThemeFile::parse(&contents))
+                .and_then(|file| file.resolve(depth));
+            match loaded {
+                Ok(theme) => Some(theme),
+                Err(e) => {
+                    tracing::warn!("Skipping theme {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn merge_themes(builtin: Vec<Theme>, user: Vec<Theme>) -> Vec<Theme> {
+    let mut themes = builtin;
+    for theme in user {
+        match themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => themes.push(theme),
+        }
+    }
+    themes
+}
+
+/// Built-in themes followed by the user's, resolved for this terminal.
+pub fn available_themes() -> Vec<Theme> {
+    let depth = ColorDepth::detect();
+    let builtin = BUILTIN_THEMES
+        .iter()
+        .filter_map(|contents| ThemeFile::parse(contents).ok())
+        .filter_map(|file| file.resolve(depth).ok())
+        .collect();
+    let user = themes_dir()
+        .map(|dir| load_dir(&dir, depth))
+        .unwrap_or_default();
+    merge_themes(builtin, user)
+}
+
+/// The theme called `name`, or the default theme if there is none.
+pub fn load_theme(name: Option<&str>) -> Theme {
+    let name = name.unwrap_or(DEFAULT_THEME);
+    let mut themes = available_themes();
+    match themes.iter().position(|theme| theme.name == name) {
+        Some(index) => themes.swap_remove(index),
+        None => {
+            tracing::warn!("Theme {:?} not found, using {}", name, DEFAULT_THEME);
+            builtin_theme(DEFAULT_THEME, ColorDepth::detect()).unwrap_or_default()
+        }
+    }
+}
+
+/// The theme picker. Moving the selection previews a theme; the previous one
+/// is kept so cancelling can restore it.
+#[derive(Debug, Clone)]
+pub struct ThemePicker {
+    pub themes: Vec<Theme>,
+    pub selected: usize,
+    pub original: Theme,
+}
+
+impl ThemePicker {
+    pub fn new(themes: Vec<Theme>, current: Theme) -> Self {
+        let selected = themes
+            .iter()
+            .position(|theme| theme.name == current.name)
+            .unwrap_or(0);
+        Self {
+            themes,
+            selected,
+            original: current,
+        }
+    }
+
+    pub fn selected_theme(&self) -> Option<&Theme> {
+        self.themes.get(self.selected)
+    }
+
+    pub fn select_up(&mut self) {
+        let len = self.themes.len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    pub fn select_down(&mut self) {
+        let len = self.themes.len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_dark_matches_the_default_colors() {
+        for depth in [
+            ColorDepth::Ansi16,
+            ColorDepth::Ansi256,
+            ColorDepth::TrueColor,
+        ] {
+            let dark = builtin_theme("dark", depth).unwrap();
+            assert_eq!(dark.colors, ThemeColors::default());
+        }
+        assert!(builtin_theme("light", ColorDepth::Ansi16).is_some());
+    }
+
+    #[test]
+    fn deeper_sections_apply_only_when_the_terminal_supports_them() {
+        let file = ThemeFile::parse(
+            r##"
+            name = "ocean"
+            base = "light"
+
+            [ansi16]
+            accent = "dark-gray"
+
+            [ansi256]
+            accent = 37
+
+            [truecolor]
+            accent = "#2aa198"
+            "##,
+        )
+        .unwrap();
+
+        let accent = |depth| file.resolve(depth).unwrap().colors.accent;
+        assert_eq!(accent(ColorDepth::Ansi16), Color::DarkGray);
+        assert_eq!(accent(ColorDepth::Ansi256), Color::Indexed(37));
+        assert_eq!(accent(ColorDepth::TrueColor), Color::Rgb(0x2a, 0xa1, 0x98));
+        // Unset roles come from the base theme.
+        let light = builtin_theme("light", ColorDepth::Ansi16).unwrap();
+        assert_eq!(
+            file.resolve(ColorDepth::Ansi16).unwrap().colors.text,
+            light.colors.text
+        );
+    }
+
+    #[test]
+    fn invalid_theme_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("good.toml"), "name = \"good\"\n").unwrap();
+        std::fs::write(
+            dir.path().join("bad-color.toml"),
+            "name = \"bad\"\n[ansi16]\naccent = \"plaid\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "name = \"ignored\"\n").unwrap();
+
+        let themes = load_dir(dir.path(), ColorDepth::Ansi16);
+        let names: Vec<&str> = themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["good"]);
+
+        let merged = merge_themes(
+            vec![Theme::default()],
+            vec![Theme {
+                name: "dark".to_string(),
+                colors: ThemeColors {
+                    accent: Color::Green,
+                    ..ThemeColors::default()
+                },
+            }],
+        );
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].colors.accent, Color::Green);
+    }
+
+    #[test]
+    fn color_depth_detection() {
+        assert_eq!(
+            ColorDepth::from_env("truecolor", "xterm-256color"),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env("", "xterm-256color"),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::from_env("", "xterm"), ColorDepth::Ansi16);
+    }
+}
@@ -12,6 +12,7 @@ use crate::mux::commands::MuxCommand;
 use crate::mux::layout::LayoutNode;
 use crate::mux::onboard::OnboardPhase;
 use crate::mux::palette::PaletteItem;
+use crate::mux::search::SearchScope;
 use crate::mux::sidebar::Sidebar;
 use crate::mux::state::{FocusArea, MuxApp};
 use crate::settings::EditorChoice;
@@ -53,7 +54,10 @@ pub fn ui(f: &mut Frame, app: &mut MuxApp) {
 
     render_tab_bar(f, app, workspace_chunks[0]);
     render_workspace(f, app, workspace_chunks[1]);
-    render_status_bar(f, app, workspace_chunks[2]);
+    match app.search.as_ref().map(|s| s.scope) {
+        Some(SearchScope::ActivePane) => render_search_bar(f, app, workspace_chunks[2]),
+        _ => render_status_bar(f, app, workspace_chunks[2]),
+    }
 
     // Render overlays
     if app.command_palette.is_visible() {
@@ -72,6 +76,22 @@ pub fn ui(f: &mut Frame, app: &mut MuxApp) {
         render_rename_dialog(f, app);
     }
 
+    if app
+        .search
+        .as_ref()
+        .is_some_and(|s| s.scope == SearchScope::AllPanes)
+    {
+        render_search_results(f, app);
+    }
+
+    if app.history.is_some() {
+        render_history(f, app);
+    }
+
+    if app.theme_picker.is_some() {
+        render_theme_picker(f, app);
+    }
+
     // Onboard overlay (highest priority - blocks other interactions during setup)
     if let Some(onboard) = &app.onboard {
         if onboard.is_visible {
@@ -82,22 +102,23 @@ pub fn ui(f: &mut Frame, app: &mut MuxApp) {
 
 /// Render the sidebar with sandbox list.
 fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
+    let colors = app.theme.colors;
     let is_focused = app.focus == FocusArea::Sidebar;
 
     let border_style = if is_focused {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(colors.accent)
     } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(colors.muted)
     };
 
     let block = Block::default()
         .title(" Sandboxes ")
         .title_style(if is_focused {
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(colors.text)
         })
         .borders(Borders::ALL)
         .border_style(border_style);
@@ -106,20 +127,20 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
     f.render_widget(block, area);
 
     if app.sidebar.is_loading {
-        let loading = Paragraph::new("Loading...").style(Style::default().fg(Color::Yellow));
+        let loading = Paragraph::new("Loading...").style(Style::default().fg(colors.warning));
         f.render_widget(loading, inner_area);
         return;
     }
 
     if let Some(error) = &app.sidebar.last_error {
         let error_text =
-            Paragraph::new(format!("Error: {}", error)).style(Style::default().fg(Color::Red));
+            Paragraph::new(format!("Error: {}", error)).style(Style::default().fg(colors.error));
         f.render_widget(error_text, inner_area);
         return;
     }
 
     if app.sidebar.sandboxes.is_empty() {
-        let empty_text = Paragraph::new("No sandboxes").style(Style::default().fg(Color::DarkGray));
+        let empty_text = Paragraph::new("No sandboxes").style(Style::default().fg(colors.muted));
         f.render_widget(empty_text, inner_area);
         return;
     }
@@ -129,7 +150,7 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
         let is_selected = idx == app.sidebar.selected_index();
 
         let status_icon = Sidebar::status_icon(&sandbox.status);
-        let status_color = Sidebar::status_color(&sandbox.status);
+        let status_color = Sidebar::status_color(&sandbox.status, &colors);
 
         let prefix = if is_selected { "▶ " } else { "  " };
         let max_name_width = inner_area.width.saturating_sub(6) as usize;
@@ -137,7 +158,7 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
 
         let style = if is_selected {
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -157,18 +178,19 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
 
 /// Render the tab bar.
 fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
+    let colors = app.theme.colors;
     // Get workspace from workspace manager
     let Some(workspace) = app.workspace_manager.active_workspace() else {
         // No active sandbox, show placeholder
         let placeholder =
-            Paragraph::new(" No sandbox selected ").style(Style::default().fg(Color::DarkGray));
+            Paragraph::new(" No sandbox selected ").style(Style::default().fg(colors.muted));
         f.render_widget(placeholder, area);
         return;
     };
 
     if workspace.tabs.is_empty() {
         let placeholder = Paragraph::new(" No tabs ")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(colors.muted))
             .alignment(Alignment::Left);
         f.render_widget(placeholder, area);
         return;
@@ -181,10 +203,10 @@ fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
         .map(|(idx, tab)| {
             let style = if idx == workspace.active_tab_index {
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(colors.accent)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(colors.muted)
             };
             Line::styled(format!(" {} ", tab.name), style)
         })
@@ -195,7 +217,7 @@ fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
         .divider(Span::raw("│"))
         .highlight_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD),
         );
 
@@ -204,6 +226,7 @@ fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
 
 /// Render the main workspace area with panes.
 fn render_workspace(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let colors = app.theme.colors;
     // First, calculate areas (needs mutable borrow)
     // Capture zoomed_pane first to avoid borrow issues
     let zoomed_pane = app.zoomed_pane;
@@ -227,7 +250,7 @@ fn render_workspace(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     let Some(layout) = layout_snapshot else {
         // No active tab, show placeholder
         let placeholder = Paragraph::new("Select a sandbox from the sidebar (Tab to switch)")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(colors.muted))
             .alignment(Alignment::Center);
         f.render_widget(placeholder, area);
         return;
@@ -278,24 +301,42 @@ fn render_pane(
     is_main_focused: bool,
     app: &mut MuxApp,
 ) {
+    let colors = app.theme.colors;
+    let broadcasting = app.broadcast_input
+        && matches!(
+            pane.content,
+            crate::mux::layout::PaneContent::Terminal { .. }
+        );
     let border_style = if is_active && is_main_focused {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(colors.accent)
+    } else if broadcasting {
+        // Mark every pane that receives broadcast input
+        Style::default().fg(colors.error)
     } else if is_active {
-        Style::default().fg(Color::White)
+        Style::default().fg(colors.text)
     } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(colors.muted)
     };
 
     let title_style = if is_active && is_main_focused {
         Style::default()
-            .fg(Color::Cyan)
+            .fg(colors.accent)
             .add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::White)
+        Style::default().fg(colors.text)
     };
 
     let block = Block::default()
-        .title(format!(" {} ", pane.title()))
+        .title({
+            let mut title = format!(" {} ", pane.title());
+            if app.zoomed_pane == Some(pane.id) {
+                title.push_str("[zoomed] ");
+            }
+            if app.pipe_path(pane.id).is_some() {
+                title.push_str("[piping] ");
+            }
+            title
+        })
         .title_style(title_style)
         .borders(Borders::ALL)
         .border_style(border_style);
@@ -307,7 +348,7 @@ fn render_pane(
     match &pane.content {
         crate::mux::layout::PaneContent::Empty => {
             let text = Paragraph::new("Empty pane\n\nUse Alt+- or Alt+\\ to split")
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(colors.muted))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -338,9 +379,15 @@ fn render_pane(
                     let visible_rows = height.min(view.lines.len());
 
                     let changed = view.changed_lines.as_ref();
+                    let highlights = app.search_highlights(pane.id, visible_rows);
+                    let selection =
+                        app.copy_highlights(pane.id, visible_rows, inner_area.width as usize);
 
                     for row in 0..visible_rows {
-                        let row_changed = previous.is_none() || changed.binary_search(&row).is_ok();
+                        let row_changed = previous.is_none()
+                            || !highlights.is_empty()
+                            || !selection.is_empty()
+                            || changed.binary_search(&row).is_ok();
                         if !row_changed {
                             continue;
                         }
@@ -390,6 +437,41 @@ fn render_pane(
                         }
                     }
 
+                    // Paint search matches over the rendered rows
+                    for &(row, col, len, is_current) in &highlights {
+                        let style = if is_current {
+                            Style::default()
+                                .bg(colors.search_current)
+                                .fg(colors.on_accent)
+                        } else {
+                            Style::default().bg(colors.search_match).fg(colors.text)
+                        };
+                        let y = inner_area.y + row as u16;
+                        let start = inner_area.x + col as u16;
+                        let end = (start + len as u16).min(inner_area.x + inner_area.width);
+                        for x in start..end {
+                            if let Some(cell) = buf.cell_mut((x, y)) {
+                                cell.set_style(style);
+                            }
+                        }
+                    }
+
+                    // Paint the copy-mode selection (or cursor) on top
+                    for &(row, col, len) in &selection {
+                        let y = inner_area.y + row as u16;
+                        let start = inner_area.x + col.min(inner_area.width as usize) as u16;
+                        let end = (start as usize + len)
+                            .min((inner_area.x + inner_area.width) as usize)
+                            as u16;
+                        for x in start..end {
+                            if let Some(cell) = buf.cell_mut((x, y)) {
+                                cell.set_style(
+                                    Style::default().bg(colors.selection).fg(colors.on_accent),
+                                );
+                            }
+                        }
+                    }
+
                     app.last_terminal_views.insert(pane.id, view.clone());
 
                     // Set cursor position only if:
@@ -465,7 +547,7 @@ fn render_pane(
             };
 
             let text = Paragraph::new(format!("Terminal\n\n{}\n\n{}", sandbox_info, help_text))
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(colors.muted))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -473,11 +555,15 @@ fn render_pane(
             sandbox_id,
             provider,
         } => {
+            if let Some(chat) = app.chats.get_mut(&pane.id) {
+                chat.render(f, inner_area);
+                return;
+            }
             let text = Paragraph::new(format!(
-                "Chat with {}\n\nSandbox: {}\n\n(Chat integration coming soon)",
+                "Chat with {}\n\nSandbox: {}\n\nStarting chat...",
                 provider, sandbox_id
             ))
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(colors.muted))
             .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -486,6 +572,7 @@ fn render_pane(
 
 /// Render the status bar.
 fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let colors = app.theme.colors;
     app.clear_expired_status();
 
     let mut spans = Vec::new();
@@ -497,24 +584,39 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         FocusArea::CommandPalette => "COMMAND",
         FocusArea::Notifications => "NOTIFS",
         FocusArea::Onboard => "SETUP",
+        FocusArea::Search => "SEARCH",
+        FocusArea::Copy => "COPY",
+        FocusArea::History => "HISTORY",
+        FocusArea::ThemePicker => "THEME",
     };
     spans.push(Span::styled(
         format!(" {} ", mode),
         Style::default()
-            .fg(Color::Black)
-            .bg(Color::Cyan)
+            .fg(colors.on_accent)
+            .bg(colors.accent)
             .add_modifier(Modifier::BOLD),
     ));
     spans.push(Span::raw(" "));
 
+    if app.broadcast_input {
+        spans.push(Span::styled(
+            " BROADCAST ",
+            Style::default()
+                .fg(colors.on_accent)
+                .bg(colors.error)
+                .add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" "));
+    }
+
     // Debug build indicator (only in debug builds)
     #[cfg(debug_assertions)]
     {
         spans.push(Span::styled(
             "[debug build]",
             Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
+                .fg(colors.on_accent)
+                .bg(colors.warning)
                 .add_modifier(Modifier::BOLD),
         ));
         spans.push(Span::raw(" "));
@@ -524,7 +626,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     if let Some(workspace) = app.workspace_manager.active_workspace() {
         spans.push(Span::styled(
             format!("[{}] ", workspace.name),
-            Style::default().fg(Color::Green),
+            Style::default().fg(colors.success),
         ));
     }
 
@@ -537,7 +639,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
                 pane_count,
                 if pane_count == 1 { "" } else { "s" }
             ),
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(colors.muted),
         ));
         spans.push(Span::raw(" │ "));
     }
@@ -550,12 +652,12 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
                 workspace.active_tab_index + 1,
                 workspace.tabs.len()
             ),
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(colors.muted),
         ));
     } else {
         spans.push(Span::styled(
             "No sandbox",
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(colors.muted),
         ));
     }
 
@@ -564,7 +666,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         spans.push(Span::raw(" │ "));
         spans.push(Span::styled(
             msg.clone(),
-            Style::default().fg(Color::Yellow),
+            Style::default().fg(colors.warning),
         ));
     }
 
@@ -575,10 +677,10 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         format!("Notifs: {}", unread),
         if unread > 0 {
             Style::default()
-                .fg(Color::Yellow)
+                .fg(colors.warning)
                 .add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::DarkGray)
+            Style::default().fg(colors.muted)
         },
     ));
 
@@ -589,7 +691,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     let padding = area.width.saturating_sub(left_width + hints_width);
 
     spans.push(Span::raw(" ".repeat(padding as usize)));
-    spans.push(Span::styled(hints, Style::default().fg(Color::DarkGray)));
+    spans.push(Span::styled(hints, Style::default().fg(colors.muted)));
 
     let line = Line::from(spans);
     let paragraph = Paragraph::new(line);
@@ -598,6 +700,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
 
 /// Render the command palette overlay.
 fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
+    let colors = app.theme.colors;
     let area = f.area();
 
     let palette_width = 70u16.min(area.width.saturating_sub(4));
@@ -621,18 +724,18 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
         .title(title)
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(colors.accent));
 
     let inner_area = block.inner(palette_area);
     f.render_widget(block, palette_area);
 
     // Search input
     let search_area = Rect::new(inner_area.x, inner_area.y, inner_area.width, 1);
-    let search_prefix = Paragraph::new(Span::styled(">", Style::default().fg(Color::Cyan)));
+    let search_prefix = Paragraph::new(Span::styled(">", Style::default().fg(colors.accent)));
     f.render_widget(search_prefix, Rect::new(search_area.x, search_area.y, 2, 1));
     f.render_widget(
         app.command_palette.search_input(),
@@ -679,7 +782,7 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
                     lines.push(Line::styled(
                         format!("─ {} ─", text),
                         Style::default()
-                            .fg(Color::DarkGray)
+                            .fg(colors.muted)
                             .add_modifier(Modifier::BOLD),
                     ));
                 }
@@ -694,13 +797,13 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
 
                 let style = if is_highlighted {
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(colors.accent)
                         .add_modifier(Modifier::BOLD)
                 } else {
                     Style::default()
                 };
 
-                let kb_style = Style::default().fg(Color::Yellow);
+                let kb_style = Style::default().fg(colors.warning);
 
                 // Calculate padding for right-aligned keybinding
                 let label = command.label();
@@ -732,7 +835,7 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
                 if is_default {
                     label_spans.push(Span::styled(
                         default_suffix,
-                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(colors.muted),
                     ));
                 }
 
@@ -776,14 +879,12 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
     } else {
         "↑↓: navigate │ Enter: execute │ Esc: cancel"
     };
-    let help = Paragraph::new(Line::styled(
-        help_text,
-        Style::default().fg(Color::DarkGray),
-    ));
+    let help = Paragraph::new(Line::styled(help_text, Style::default().fg(colors.muted)));
     f.render_widget(help, help_area);
 }
 
 fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
+    let colors = app.theme.colors;
     let area = f.area();
     let overlay_width = 80u16.min(area.width.saturating_sub(4));
     let overlay_height = 24u16.min(area.height.saturating_sub(4));
@@ -801,11 +902,11 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
         .title(title)
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(colors.accent));
 
     let inner_area = block.inner(overlay_area);
     f.render_widget(block, overlay_area);
@@ -813,7 +914,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
     if app.notifications.items.is_empty() {
         let empty = Paragraph::new("No notifications yet")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(colors.muted));
         f.render_widget(empty, inner_area);
         return;
     }
@@ -860,7 +961,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
         lines.push(Line::styled(
             format!(" {header} "),
             Style::default()
-                .fg(Color::Magenta)
+                .fg(colors.heading)
                 .add_modifier(Modifier::BOLD),
         ));
 
@@ -875,9 +976,9 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             selection_counter += 1;
 
             let level_style = match item.level {
-                crate::models::NotificationLevel::Info => Style::default().fg(Color::White),
-                crate::models::NotificationLevel::Warning => Style::default().fg(Color::Yellow),
-                crate::models::NotificationLevel::Error => Style::default().fg(Color::Red),
+                crate::models::NotificationLevel::Info => Style::default().fg(colors.text),
+                crate::models::NotificationLevel::Warning => Style::default().fg(colors.warning),
+                crate::models::NotificationLevel::Error => Style::default().fg(colors.error),
             };
 
             let mut spans = Vec::new();
@@ -885,10 +986,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             spans.push(Span::styled(state_prefix, level_style));
             spans.push(Span::raw(" "));
             let time_label = relative_time_string(item.sent_at);
-            spans.push(Span::styled(
-                time_label,
-                Style::default().fg(Color::DarkGray),
-            ));
+            spans.push(Span::styled(time_label, Style::default().fg(colors.muted)));
             spans.push(Span::raw(" "));
             spans.push(Span::styled(item.message.clone(), level_style));
             if let Some(sandbox_id) = &item.sandbox_id {
@@ -896,7 +994,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
                     spans.push(Span::raw("  "));
                     spans.push(Span::styled(
                         format!("sandbox {}", label),
-                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(colors.muted),
                     ));
                 }
             }
@@ -904,14 +1002,14 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
                 spans.push(Span::raw("  "));
                 spans.push(Span::styled(
                     format!("tab: {}", tab_id),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(colors.muted),
                 ));
             }
             if let Some(read_at) = item.read_at {
                 spans.push(Span::raw("  "));
                 spans.push(Span::styled(
                     format!("read {}", relative_time_string(read_at)),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(colors.muted),
                 ));
             }
 
@@ -919,7 +1017,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             if is_selected {
                 line = line.style(
                     Style::default()
-                        .bg(Color::DarkGray)
+                        .bg(colors.muted)
                         .add_modifier(Modifier::BOLD),
                 );
             }
@@ -946,7 +1044,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
     );
     let help = Paragraph::new(Line::styled(
         "↑↓: navigate │ Enter: open target │ Space/r: mark read │ u: mark unread │ Esc: close",
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(colors.muted),
     ));
     f.render_widget(help, help_area);
 }
@@ -1026,7 +1124,8 @@ fn sandbox_short_label(app: &MuxApp, sandbox_id: &str) -> Option<String> {
 }
 
 /// Render help overlay showing all keybindings.
-fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
+fn render_help_overlay(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
     let area = f.area();
 
     let help_width = 60u16.min(area.width.saturating_sub(4));
@@ -1042,11 +1141,11 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
         .title(" Keyboard Shortcuts ")
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(colors.accent));
 
     let inner_area = block.inner(help_area);
     f.render_widget(block, help_area);
@@ -1063,7 +1162,7 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
             lines.push(Line::styled(
                 category,
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(colors.warning)
                     .add_modifier(Modifier::BOLD),
             ));
             current_category = Some(category);
@@ -1076,7 +1175,7 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
                 Span::raw("  "),
                 Span::styled(
                     format!("{:<width$}", keybinding, width = kb_width),
-                    Style::default().fg(Color::Cyan),
+                    Style::default().fg(colors.accent),
                 ),
                 Span::raw(cmd.label()),
             ]));
@@ -1087,8 +1186,363 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
     f.render_widget(paragraph, inner_area);
 }
 
+/// Render the active-pane search prompt in place of the status bar.
+fn render_search_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
+    let colors = app.theme.colors;
+    let Some(search) = &app.search else {
+        return;
+    };
+
+    let counter = if search.is_empty() {
+        if search.query().is_empty() {
+            String::new()
+        } else {
+            " no matches ".to_string()
+        }
+    } else {
+        // Number matches from the bottom, so the newest one is 1
+        format!(" {}/{} ", search.len() - search.current, search.len())
+    };
+    let hints = if search.editing {
+        " Enter: confirm │ ↑↓: prev/next │ Esc: cancel "
+    } else {
+        " n: older │ N: newer │ /: edit │ Esc: close "
+    };
+
+    let prefix_width = 2u16;
+    let right_width = (counter.chars().count() + hints.chars().count()) as u16;
+    let input_width = area
+        .width
+        .saturating_sub(prefix_width)
+        .saturating_sub(right_width);
+
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "/ ",
+            Style::default()
+                .fg(colors.on_accent)
+                .bg(colors.accent)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Rect::new(area.x, area.y, prefix_width.min(area.width), 1),
+    );
+    f.render_widget(
+        &search.input,
+        Rect::new(area.x + prefix_width, area.y, input_width, 1),
+    );
+    let right = Line::from(vec![
+        Span::styled(counter, Style::default().fg(colors.warning)),
+        Span::styled(hints, Style::default().fg(colors.muted)),
+    ]);
+    f.render_widget(
+        Paragraph::new(right),
+        Rect::new(
+            area.x + prefix_width + input_width,
+            area.y,
+            right_width.min(area.width),
+            1,
+        ),
+    );
+}
+
+/// Render the all-panes search overlay listing matches from every pane.
+fn render_search_results(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
+    let Some(search) = &app.search else {
+        return;
+    };
+    let area = f.area();
+
+    let overlay_width = 90u16.min(area.width.saturating_sub(4));
+    let overlay_height = 24u16.min(area.height.saturating_sub(4));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = area.height / 6;
+
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+    f.render_widget(Clear, overlay_area);
+
+    let block = Block::default()
+        .title(format!(" Search All Panes ({}) ", search.results.len()))
+        .title_style(
+            Style::default()
+                .fg(colors.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent));
+
+    let inner_area = block.inner(overlay_area);
+    f.render_widget(block, overlay_area);
+
+    f.render_widget(
+        Paragraph::new(Span::styled("/", Style::default().fg(colors.accent))),
+        Rect::new(inner_area.x, inner_area.y, 2, 1),
+    );
+    f.render_widget(
+        &search.input,
+        Rect::new(
+            inner_area.x + 2,
+            inner_area.y,
+            inner_area.width.saturating_sub(2),
+            1,
+        ),
+    );
+
+    let items_area = Rect::new(
+        inner_area.x,
+        inner_area.y + 2,
+        inner_area.width,
+        inner_area.height.saturating_sub(4),
+    );
+    let visible = items_area.height as usize;
+    let scroll = search.current.saturating_sub(visible.saturating_sub(1));
+
+    let lines: Vec<Line<'_>> = search
+        .results
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible)
+        .map(|(index, result)| {
+            let selected = index == search.current;
+            let style = if selected {
+                Style::default()
+                    .fg(colors.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, style),
+                Span::styled(
+                    format!("{}:{} ", result.pane_label, result.at.line + 1),
+                    Style::default().fg(colors.muted),
+                ),
+                Span::styled(result.line_text.trim().to_string(), style),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), items_area);
+
+    let help_area = Rect::new(
+        inner_area.x,
+        inner_area.y + inner_area.height.saturating_sub(1),
+        inner_area.width,
+        1,
+    );
+    f.render_widget(
+        Paragraph::new(Line::styled(
+            "↑↓: navigate │ Enter: jump to match │ Esc: cancel",
+            Style::default().fg(colors.muted),
+        )),
+        help_area,
+    );
+}
+
+/// Render the command history view.
+fn render_history(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
+    let Some(history) = &app.history else {
+        return;
+    };
+    let area = f.area();
+
+    let overlay_width = 90u16.min(area.width.saturating_sub(4));
+    let overlay_height = 24u16.min(area.height.saturating_sub(4));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = area.height / 6;
+
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+    f.render_widget(Clear, overlay_area);
+
+    let block = Block::default()
+        .title(format!(" Command History ({}) ", history.results.len()))
+        .title_style(
+            Style::default()
+                .fg(colors.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent));
+
+    let inner_area = block.inner(overlay_area);
+    f.render_widget(block, overlay_area);
+
+    f.render_widget(
+        Paragraph::new(Span::styled("$", Style::default().fg(colors.accent))),
+        Rect::new(inner_area.x, inner_area.y, 2, 1),
+    );
+    f.render_widget(
+        &history.input,
+        Rect::new(
+            inner_area.x + 2,
+            inner_area.y,
+            inner_area.width.saturating_sub(2),
+            1,
+        ),
+    );
+
+    let items_area = Rect::new(
+        inner_area.x,
+        inner_area.y + 2,
+        inner_area.width,
+        inner_area.height.saturating_sub(4),
+    );
+    if history.results.is_empty() {
+        f.render_widget(
+            Paragraph::new(Line::styled(
+                "No commands yet (needs a shell with OSC 133 integration)",
+                Style::default().fg(colors.muted),
+            )),
+            items_area,
+        );
+    }
+    let visible = items_area.height as usize;
+    let scroll = history.current.saturating_sub(visible.saturating_sub(1));
+
+    let lines: Vec<Line<'_>> = history
+        .results
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible)
+        .map(|(index, entry)| {
+            let selected = index == history.current;
+            let style = if selected {
+                Style::default()
+                    .fg(colors.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let status = match entry.exit_code {
+                Some(0) => Span::styled("✓ ", Style::default().fg(colors.success)),
+                Some(_) => Span::styled("✗ ", Style::default().fg(colors.error)),
+                None => Span::raw("  "),
+            };
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, style),
+                status,
+                Span::styled(entry.command.replace('\n', " ⏎ "), style),
+                Span::styled(
+                    format!("  {}", entry.pane_label),
+                    Style::default().fg(colors.muted),
+                ),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), items_area);
+
+    let help_area = Rect::new(
+        inner_area.x,
+        inner_area.y + inner_area.height.saturating_sub(1),
+        inner_area.width,
+        1,
+    );
+    f.render_widget(
+        Paragraph::new(Line::styled(
+            "↑↓: navigate │ Enter: run in active pane │ Tab: insert │ Esc: cancel",
+            Style::default().fg(colors.muted),
+        )),
+        help_area,
+    );
+}
+
+/// Render the theme picker. The rest of the UI already shows the selected
+/// theme, so the list only needs each theme's swatches.
+fn render_theme_picker(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
+    let Some(picker) = &app.theme_picker else {
+        return;
+    };
+    let area = f.area();
+
+    let overlay_width = 60u16.min(area.width.saturating_sub(4));
+    let overlay_height = (picker.themes.len() as u16 + 4).min(area.height.saturating_sub(4));
+    let x = (area.width.saturating_sub(overlay_width)) / 2;
+    let y = area.height / 6;
+
+    let overlay_area = Rect::new(x, y, overlay_width, overlay_height);
+    f.render_widget(Clear, overlay_area);
+
+    let block = Block::default()
+        .title(" Theme ")
+        .title_style(
+            Style::default()
+                .fg(colors.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(colors.accent));
+
+    let inner_area = block.inner(overlay_area);
+    f.render_widget(block, overlay_area);
+
+    let items_area = Rect::new(
+        inner_area.x,
+        inner_area.y,
+        inner_area.width,
+        inner_area.height.saturating_sub(2),
+    );
+    let visible = items_area.height as usize;
+    let scroll = picker.selected.saturating_sub(visible.saturating_sub(1));
+
+    let lines: Vec<Line<'_>> = picker
+        .themes
+        .iter()
+        .enumerate()
+        .skip(scroll)
+        .take(visible)
+        .map(|(index, theme)| {
+            let selected = index == picker.selected;
+            let style = if selected {
+                Style::default()
+                    .fg(colors.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(colors.text)
+            };
+            let marker = if theme.name == picker.original.name {
+                " (current)"
+            } else {
+                ""
+            };
+            let label = format!("{}{}", theme.name, marker);
+            let swatch = |color| Span::styled("██", Style::default().fg(color));
+            let theme_colors = theme.colors;
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, style),
+                Span::styled(format!("{:<24}", label), style),
+                swatch(theme_colors.accent),
+                swatch(theme_colors.text),
+                swatch(theme_colors.muted),
+                swatch(theme_colors.success),
+                swatch(theme_colors.warning),
+                swatch(theme_colors.error),
+                swatch(theme_colors.heading),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), items_area);
+
+    let help_area = Rect::new(
+        inner_area.x,
+        inner_area.y + inner_area.height.saturating_sub(1),
+        inner_area.width,
+        1,
+    );
+    f.render_widget(
+        Paragraph::new(Line::styled(
+            "↑↓: preview │ Enter: apply │ Esc: cancel",
+            Style::default().fg(colors.muted),
+        )),
+        help_area,
+    );
+}
+
 /// Render tab rename dialog.
 fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
     let area = f.area();
 
     let dialog_width = 40u16.min(area.width.saturating_sub(4));
@@ -1104,11 +1558,11 @@ fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
         .title(" Rename Tab ")
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(colors.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(colors.accent));
 
     let inner_area = block.inner(dialog_area);
     f.render_widget(block, dialog_area);
@@ -1126,13 +1580,14 @@ fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
     );
     let help = Paragraph::new(Line::styled(
         "Enter: confirm │ Esc: cancel",
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(colors.muted),
     ));
     f.render_widget(help, help_area);
 }
 
 /// Render the onboarding overlay for Docker image setup.
 fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
+    let colors = app.theme.colors;
     let Some(onboard) = &app.onboard else {
         return;
     };
@@ -1163,9 +1618,9 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
     };
 
     let border_color = match onboard.phase {
-        OnboardPhase::Error => Color::Red,
-        OnboardPhase::DownloadComplete | OnboardPhase::ImageExists => Color::Green,
-        _ => Color::Cyan,
+        OnboardPhase::Error => colors.error,
+        OnboardPhase::DownloadComplete | OnboardPhase::ImageExists => colors.success,
+        _ => colors.accent,
     };
 
     let block = Block::default()
@@ -1192,7 +1647,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 % spinner.len();
 
             let text = Paragraph::new(Line::from(vec![
-                Span::styled(spinner[idx], Style::default().fg(Color::Cyan)),
+                Span::styled(spinner[idx], Style::default().fg(colors.accent)),
                 Span::raw(" Checking Docker image..."),
             ]))
             .alignment(Alignment::Center);
@@ -1203,16 +1658,16 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 Line::raw(""),
                 Line::styled(
                     "The sandbox Docker image is not installed.",
-                    Style::default().fg(Color::Yellow),
+                    Style::default().fg(colors.warning),
                 ),
                 Line::raw(""),
                 Line::from(vec![
                     Span::raw("Image: "),
-                    Span::styled(&onboard.image_name, Style::default().fg(Color::Cyan)),
+                    Span::styled(&onboard.image_name, Style::default().fg(colors.accent)),
                 ]),
                 Line::from(vec![
                     Span::raw("Size:  "),
-                    Span::styled(onboard.format_size(), Style::default().fg(Color::Cyan)),
+                    Span::styled(onboard.format_size(), Style::default().fg(colors.accent)),
                 ]),
                 Line::raw(""),
                 Line::styled("Would you like to download it now?", Style::default()),
@@ -1222,20 +1677,20 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             // Render buttons
             let download_style = if onboard.is_download_selected() {
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Cyan)
+                    .fg(colors.on_accent)
+                    .bg(colors.accent)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::Cyan)
+                Style::default().fg(colors.accent)
             };
 
             let cancel_style = if !onboard.is_download_selected() {
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::DarkGray)
+                    .fg(colors.on_accent)
+                    .bg(colors.muted)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(colors.muted)
             };
 
             lines.push(Line::from(vec![
@@ -1257,7 +1712,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             );
             let help = Paragraph::new(Line::styled(
                 "Tab/←→: switch │ Enter: confirm │ Esc: cancel",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(colors.muted),
             ))
             .alignment(Alignment::Center);
             f.render_widget(help, help_area);
@@ -1268,7 +1723,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 &onboard.download_status,
-                Style::default().fg(Color::White),
+                Style::default().fg(colors.text),
             ));
             lines.push(Line::raw(""));
 
@@ -1284,7 +1739,10 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 onboard.download_progress * 100.0
             );
 
-            lines.push(Line::styled(progress_bar, Style::default().fg(Color::Cyan)));
+            lines.push(Line::styled(
+                progress_bar,
+                Style::default().fg(colors.accent),
+            ));
             lines.push(Line::raw(""));
 
             // Layer progress
@@ -1294,7 +1752,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                         "Layers: {}/{}",
                         onboard.layers_downloaded, onboard.layers_total
                     ),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(colors.muted),
                 ));
             }
 
@@ -1308,7 +1766,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 "Docker image ready! Starting cmux..."
             };
 
-            let text = Paragraph::new(Line::styled(message, Style::default().fg(Color::Green)))
+            let text = Paragraph::new(Line::styled(message, Style::default().fg(colors.success)))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -1318,7 +1776,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 "Failed to set up Docker image:",
-                Style::default().fg(Color::Red),
+                Style::default().fg(colors.error),
             ));
             lines.push(Line::raw(""));
 
@@ -1328,7 +1786,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 for chunk in error.chars().collect::<Vec<_>>().chunks(max_width) {
                     lines.push(Line::styled(
                         chunk.iter().collect::<String>(),
-                        Style::default().fg(Color::White),
+                        Style::default().fg(colors.text),
                     ));
                 }
             }
@@ -1336,7 +1794,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 "Press Esc to exit or Enter to retry",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(colors.muted),
             ));
 
             let text = Paragraph::new(lines).alignment(Alignment::Center);
@@ -289,6 +289,12 @@ pub fn fuzzy_match_str(query: &str, label: &str) -> bool {
     fuzzy_match_label(query, label).is_some()
 }
 
+/// Score of a fuzzy match of `query` against `label` (higher is better), or
+/// None if it doesn't match. For ranking items that don't implement PaletteCommand.
+pub fn fuzzy_score(query: &str, label: &str) -> Option<i64> {
+    fuzzy_match_label(query, label).map(|(score, _)| score)
+}
+
 /// Core fuzzy matching logic that works on raw strings.
 /// Returns Some((score, indices)) if matched, None otherwise.
 fn fuzzy_match_label(query: &str, label: &str) -> Option<(i64, Vec<usize>)> {
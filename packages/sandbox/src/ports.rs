@@ -0,0 +1,179 @@
+//! Port forwarding for sandboxes.
+//!
+//! The subdomain proxy (`{index}-{port}.<host>`) serves a sandbox's built-in
+//! services (noVNC, VS Code, cmux-pty, DevTools) and the ports forwarded
+//! here; removing a forward stops the proxy from serving it. Listening ports
+//! are detected from the sandbox's `/proc/net/tcp{,6}` so the UI can offer to
+//! forward a dev server as soon as it starts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::models::{ExecRequest, ForwardedPort, ListeningPort, SandboxSummary};
+
+/// TCP socket state for LISTEN in /proc/net/tcp.
+const TCP_LISTEN: &str = "0A";
+
+/// Prints the sandbox's TCP socket tables. Run inside the sandbox so the
+/// tables are those of its network namespace.
+pub fn proc_net_tcp_request() -> ExecRequest {
+    ExecRequest {
+        command: vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "cat /proc/net/tcp /proc/net/tcp6 2>/dev/null".to_string(),
+        ],
+        workdir: None,
+        env: Vec::new(),
+    }
+}
+
+/// Whether a /proc/net/tcp local address (hex, kernel byte order) is loopback.
+fn is_loopback(address: &str) -> bool {
+    let words: Option<Vec<u32>> = (0..address.len() / 8)
+        .map(|i| u32::from_str_radix(&address[i * 8..i * 8 + 8], 16).ok())
+        .collect();
+    match words.as_deref() {
+        Some([v4]) => Ipv4Addr::from(v4.to_le_bytes()).is_loopback(),
+        Some(words @ [_, _, _, _]) => {
+            let mut bytes = [0u8; 16];
+            for (chunk, word) in bytes.chunks_mut(4).zip(words) {
+                chunk.copy_from_slice(&word.to_le_bytes());
+            }
+            let ip = Ipv6Addr::from(bytes);
+            ip.is_loopback() || ip.to_ipv4_mapped().is_some_and(|v4| v4.is_loopback())
+        }
+        _ => false,
+    }
+}
+
+/// Listening TCP ports from the contents of /proc/net/tcp and /proc/net/tcp6,
+/// sorted by port. `forwarded` is left false.
+pub fn parse_listening_ports(proc_net_tcp: &str) -> Vec<ListeningPort> {
+    // Port -> whether every listener on it is loopback-only.
+    let mut ports: BTreeMap<u16, bool> = BTreeMap::new();
+    for line in proc_net_tcp.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // sl local_address rem_address st ...; header lines fail the state check.
+        let (Some(local), Some(state)) = (fields.get(1), fields.get(3)) else {
+            continue;
+        };
+        if *state != TCP_LISTEN {
+            continue;
+        }
+        let Some((address, port)) = local.split_once(':') else {
+            continue;
+        };
+        let Ok(port) = u16::from_str_radix(port, 16) else {
+            continue;
+        };
+        let loopback = is_loopback(address);
+        ports
+            .entry(port)
+            .and_modify(|only| *only &= loopback)
+            .or_insert(loopback);
+    }
+    ports
+        .into_iter()
+        .map(|(port, loopback_only)| ListeningPort {
+            port,
+            loopback_only,
+            forwarded: false,
+        })
+        .collect()
+}
+
+/// Whether `port` is one of the sandbox's built-in services, which the
+/// subdomain proxy serves without a forward.
+pub fn is_service_port(sandbox: &SandboxSummary, port: u16) -> bool {
+    sandbox.display.as_ref().is_some_and(|display| {
+        [
+            display.novnc_port,
+            display.vscode_port,
+            display.pty_port,
+            display.cdp_port,
+        ]
+        .contains(&port)
+    })
+}
+
+/// Forwarded ports per sandbox.
+#[derive(Clone, Default)]
+pub struct PortForwards {
+    inner: Arc<RwLock<HashMap<Uuid, BTreeMap<u16, ForwardedPort>>>>,
+}
+
+impl PortForwards {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a forward, replacing any existing one for the same port.
+    pub async fn forward(&self, sandbox_id: Uuid, forward: ForwardedPort) {
+        let mut guard = self.inner.write().await;
+        guard
+            .entry(sandbox_id)
+            .or_default()
+            .insert(forward.port, forward);
+    }
+
+    pub async fn list(&self, sandbox_id: &Uuid) -> Vec<ForwardedPort> {
+        let guard = self.inner.read().await;
+        guard
+            .get(sandbox_id)
+            .map(|ports| ports.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub async fn is_forwarded(&self, sandbox_id: &Uuid, port: u16) -> bool {
+        let guard = self.inner.read().await;
+        guard
+            .get(sandbox_id)
+            .is_some_and(|ports| ports.contains_key(&port))
+    }
+
+    pub async fn remove(&self, sandbox_id: &Uuid, port: u16) -> Option<ForwardedPort> {
+        let mut guard = self.inner.write().await;
+        let ports = guard.get_mut(sandbox_id)?;
+        let removed = ports.remove(&port);
+        if ports.is_empty() {
+            guard.remove(sandbox_id);
+        }
+        removed
+    }
+
+    /// Drop every forward for a sandbox that's gone.
+    pub async fn clear(&self, sandbox_id: &Uuid) {
+        self.inner.write().await.remove(sandbox_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_listeners_from_proc_net_tcp() {
+        let tcp = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1 1
+   1: 0100007F:1538 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2 1
+   2: 0201C90A:0BB8 0101C90A:D431 01 00000000:00000000 00:00000000 00000000     0        0 3 1
+";
+        let tcp6 = "\
+  sl  local_address                         remote_address                        st
+   0: 00000000000000000000000001000000:1F90 00000000000000000000000000000000:0000 0A
+   1: 0000000000000000FFFF00000100007F:1538 00000000000000000000000000000000:0000 0A
+   2: 00000000000000000000000000000000:1538 00000000000000000000000000000000:0000 0A
+";
+        let ports = parse_listening_ports(&format!("{tcp}{tcp6}"));
+        let summary: Vec<(u16, bool)> = ports.iter().map(|p| (p.port, p.loopback_only)).collect();
+        // 3000 on all interfaces; 5432 on loopback in v4 but all interfaces in v6;
+        // 8080 on ::1 only. The established connection on 3000 is ignored.
+        assert_eq!(summary, [(3000, false), (5432, false), (8080, true)]);
+    }
+}
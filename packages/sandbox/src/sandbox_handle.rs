@@ -32,7 +32,7 @@
 
 use crate::bubblewrap::BubblewrapService;
 use crate::errors::SandboxResult;
-use crate::models::{CreateSandboxRequest, EnvVar, ExecRequest, SandboxSummary};
+use crate::models::{CreateSandboxRequest, EnvVar, ExecRequest, ResourceLimits, SandboxSummary};
 use crate::service::SandboxService;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -225,6 +225,7 @@ pub struct SandboxBuilder {
     env: Vec<EnvVar>,
     read_only_paths: Vec<String>,
     tmpfs: Vec<String>,
+    resources: Option<ResourceLimits>,
 }
 
 impl Default for SandboxBuilder {
@@ -244,6 +245,7 @@ impl SandboxBuilder {
             env: Vec::new(),
             read_only_paths: Vec::new(),
             tmpfs: Vec::new(),
+            resources: None,
         }
     }
 
@@ -292,6 +294,12 @@ impl SandboxBuilder {
         self
     }
 
+    /// Limit CPU, memory and process count with a cgroup.
+    pub fn resources(mut self, limits: ResourceLimits) -> Self {
+        self.resources = Some(limits);
+        self
+    }
+
     /// Build the sandbox and return a handle.
     ///
     /// This creates a new isolated sandbox using bubblewrap with its own
@@ -311,6 +319,7 @@ impl SandboxBuilder {
             read_only_paths: self.read_only_paths,
             tmpfs: self.tmpfs,
             env: self.env.clone(),
+            resources: self.resources,
         };
 
         let summary = service.create(request).await?;
@@ -371,6 +380,7 @@ impl SandboxPool {
             read_only_paths: Vec::new(),
             tmpfs: Vec::new(),
             env: self.default_env.clone(),
+            resources: None,
         };
 
         let summary = self.service.create(request).await?;
@@ -1,14 +1,19 @@
+use crate::auth::{AuthConfig, MuxScope, SandboxOwners};
 use crate::errors::SandboxResult;
 use crate::models::{
     AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, ExecRequest, ExecResponse,
     GhResponse, HostEvent, PruneRequest, PruneResponse, SandboxSummary,
 };
 use crate::notifications::NotificationStore;
+use crate::ports::PortForwards;
+use crate::shutdown::ShutdownState;
+use crate::vnc_clipboard::VncClipboards;
 use async_trait::async_trait;
 use axum::body::Body;
 use axum::extract::ws::WebSocket;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{broadcast, oneshot, Mutex};
 
 /// Broadcast channel for host-directed events (open-url, notifications, etc.).
@@ -46,12 +51,14 @@ pub trait SandboxService: Send + Sync + 'static {
         tty: bool,
     ) -> SandboxResult<()>;
     /// Multiplexed attach - handles multiple PTY sessions over a single WebSocket.
+    /// Sandboxes and host events outside `scope` are hidden from the client.
     async fn mux_attach(
         &self,
         socket: WebSocket,
         host_event_rx: HostEventReceiver,
         gh_responses: GhResponseRegistry,
         gh_auth_cache: GhAuthCache,
+        scope: MuxScope,
     ) -> SandboxResult<()>;
     async fn proxy(&self, id: String, port: u16, socket: WebSocket) -> SandboxResult<()>;
     async fn upload_archive(&self, id: String, archive: Body) -> SandboxResult<()>;
@@ -64,6 +71,9 @@ pub trait SandboxService: Send + Sync + 'static {
         id: String,
         request: AwaitReadyRequest,
     ) -> SandboxResult<AwaitReadyResponse>;
+    /// Stop every sandbox for server shutdown. Processes get SIGTERM and up to
+    /// `grace` to exit before they're killed; workspaces are kept.
+    async fn shutdown(&self, _grace: Duration) {}
 }
 
 #[derive(Clone)]
@@ -73,15 +83,24 @@ pub struct AppState {
     pub gh_responses: GhResponseRegistry,
     pub gh_auth_cache: GhAuthCache,
     pub notifications: NotificationStore,
+    pub ports: PortForwards,
+    pub clipboards: VncClipboards,
+    pub shutdown: ShutdownState,
+    pub auth: AuthConfig,
+    pub owners: SandboxOwners,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         service: Arc<dyn SandboxService>,
         host_events: HostEventSender,
         gh_responses: GhResponseRegistry,
         gh_auth_cache: GhAuthCache,
         notifications: NotificationStore,
+        shutdown: ShutdownState,
+        auth: AuthConfig,
+        owners: SandboxOwners,
     ) -> Self {
         Self {
             service,
@@ -89,6 +108,11 @@ impl AppState {
             gh_responses,
             gh_auth_cache,
             notifications,
+            ports: PortForwards::new(),
+            clipboards: VncClipboards::new(),
+            shutdown,
+            auth,
+            owners,
         }
     }
 }
@@ -133,6 +133,9 @@ pub struct Settings {
     /// Default editor for opening sandboxes.
     #[serde(default)]
     pub default_editor: EditorChoice,
+    /// Name of the mux color theme (built-in or from the themes directory).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
 }
 
 impl Settings {
@@ -142,7 +145,8 @@ impl Settings {
     }
 
     /// Load settings from disk, falling back to defaults if not found or invalid.
-    /// Also checks DMUX_EDITOR env var which takes precedence over saved settings.
+    /// Also checks the DMUX_EDITOR and DMUX_THEME env vars, which take
+    /// precedence over saved settings.
     pub fn load() -> Self {
         // First, try loading from file
         let mut settings = Self::load_from_file();
@@ -151,6 +155,9 @@ impl Settings {
         if let Ok(val) = std::env::var("DMUX_EDITOR") {
             settings.default_editor = EditorChoice::from_str_loose(&val);
         }
+        if let Ok(val) = std::env::var("DMUX_THEME") {
+            settings.theme = Some(val);
+        }
 
         settings
     }
@@ -223,12 +230,14 @@ mod tests {
     fn settings_serialization() {
         let settings = Settings {
             default_editor: EditorChoice::Zed,
+            theme: Some("light".to_string()),
         };
 
         let json = serde_json::to_string_pretty(&settings).unwrap();
         let parsed: Settings = serde_json::from_str(&json).unwrap();
 
         assert_eq!(settings.default_editor, parsed.default_editor);
+        assert_eq!(settings.theme, parsed.theme);
     }
 
     #[test]
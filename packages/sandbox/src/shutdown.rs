@@ -0,0 +1,97 @@
+//! Coordinated shutdown for the sandbox server.
+//!
+//! On SIGTERM or Ctrl-C the server starts draining: `/healthz` answers 503 so
+//! orchestrators stop routing to it, the listener stops accepting, open
+//! connections get a grace period, and then sandboxes are stopped through
+//! [`SandboxService::shutdown`](crate::service::SandboxService::shutdown).
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Whether the server is draining. Cheap to clone; all clones share state.
+#[derive(Clone)]
+pub struct ShutdownState {
+    draining: Arc<watch::Sender<bool>>,
+}
+
+impl Default for ShutdownState {
+    fn default() -> Self {
+        Self {
+            draining: Arc::new(watch::channel(false).0),
+        }
+    }
+}
+
+impl ShutdownState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Start draining. Idempotent.
+    pub fn begin(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Resolves once [`ShutdownState::begin`] has been called.
+    pub async fn draining(&self) {
+        let mut rx = self.draining.subscribe();
+        let _ = rx.wait_for(|draining| *draining).await;
+    }
+}
+
+/// Wait for SIGTERM or Ctrl-C.
+pub async fn wait_for_signal() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                tracing::error!("failed to listen for SIGTERM: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            if let Err(error) = result {
+                tracing::error!("failed to listen for shutdown signal: {error}");
+                std::future::pending::<()>().await;
+            }
+        }
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn begin_wakes_waiters_and_sticks() {
+        let state = ShutdownState::new();
+        assert!(!state.is_draining());
+
+        let waiter = tokio::spawn({
+            let state = state.clone();
+            async move { state.draining().await }
+        });
+        state.begin();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(state.is_draining());
+        // Late waiters return immediately.
+        tokio::time::timeout(Duration::from_secs(1), state.draining())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+//! Clipboard bridging between the REST API and sandbox desktops.
+//!
+//! Each sandbox whose clipboard is in use gets a background RFB connection to
+//! its VNC server. The connection never asks for framebuffer updates, so all
+//! the server sends it are a few small messages, and ServerCutText among them
+//! tracks what is copied inside the desktop. Text pushed through the API goes
+//! out on the same connection as ClientCutText, and ClientCutText from noVNC
+//! viewers is picked up by the websocket relay. Xvnc has to accept shared
+//! connections without authentication, which is how sandboxes start it.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::errors::{SandboxError, SandboxResult};
+use crate::models::{VncClipboard, VncClipboardSource};
+use crate::vnc_proxy::VncTarget;
+
+/// Largest cut text accepted from the API or kept from the desktop.
+pub const MAX_CUT_TEXT: usize = 1024 * 1024;
+
+const SECURITY_NONE: u8 = 1;
+const CLIENT_CUT_TEXT: u8 = 6;
+
+/// Text pushed to the desktop and where to report the outcome.
+type Push = (Vec<u8>, oneshot::Sender<io::Result<()>>);
+
+/// Latest clipboard of one sandbox, shared by its bridge and noVNC relays.
+#[derive(Clone, Default)]
+pub struct ClipboardState(Arc<Mutex<VncClipboard>>);
+
+impl ClipboardState {
+    pub fn record(&self, text: String, source: VncClipboardSource) {
+        *self.0.lock().unwrap() = VncClipboard {
+            text: Some(text),
+            source: Some(source),
+            updated_at: Some(Utc::now()),
+        };
+    }
+
+    pub fn snapshot(&self) -> VncClipboard {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+struct Bridge {
+    state: ClipboardState,
+    pushes: mpsc::Sender<Push>,
+    task: JoinHandle<()>,
+}
+
+/// Clipboard bridges per sandbox.
+#[derive(Clone, Default)]
+pub struct VncClipboards {
+    inner: Arc<tokio::sync::Mutex<HashMap<Uuid, Bridge>>>,
+}
+
+impl VncClipboards {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clipboard state of a sandbox, (re)connecting its bridge to `target`
+    /// if it isn't running.
+    pub async fn follow(&self, sandbox_id: Uuid, target: &VncTarget) -> ClipboardState {
+        self.bridge(sandbox_id, target).await.0
+    }
+
+    async fn bridge(
+        &self,
+        sandbox_id: Uuid,
+        target: &VncTarget,
+    ) -> (ClipboardState, mpsc::Sender<Push>) {
+        let mut guard = self.inner.lock().await;
+        if let Some(bridge) = guard.get(&sandbox_id) {
+            if !bridge.task.is_finished() {
+                return (bridge.state.clone(), bridge.pushes.clone());
+            }
+        }
+        // Keep what was known across reconnects.
+        let state = guard
+            .get(&sandbox_id)
+            .map(|bridge| bridge.state.clone())
+            .unwrap_or_default();
+        let (pushes, rx) = mpsc::channel(8);
+        let task = tokio::spawn(run_bridge(target.clone(), state.clone(), rx));
+        guard.insert(
+            sandbox_id,
+            Bridge {
+                state: state.clone(),
+                pushes: pushes.clone(),
+                task,
+            },
+        );
+        (state, pushes)
+    }
+
+    pub async fn get(&self, sandbox_id: Uuid, target: &VncTarget) -> VncClipboard {
+        self.follow(sandbox_id, target).await.snapshot()
+    }
+
+    /// Put `text` on the desktop's clipboard.
+    pub async fn set(
+        &self,
+        sandbox_id: Uuid,
+        target: &VncTarget,
+        text: &str,
+    ) -> SandboxResult<VncClipboard> {
+        let latin1 = encode_latin1(text);
+        if latin1.len() > MAX_CUT_TEXT {
+            return Err(SandboxError::InvalidRequest(format!(
+                "clipboard text is larger than {MAX_CUT_TEXT} bytes"
+            )));
+        }
+        let closed = || SandboxError::Internal("VNC clipboard connection closed".into());
+        let (state, pushes) = self.bridge(sandbox_id, target).await;
+        let (done, result) = oneshot::channel();
+        pushes.send((latin1, done)).await.map_err(|_| closed())?;
+        result.await.map_err(|_| closed())??;
+        Ok(state.snapshot())
+    }
+
+    /// Disconnect a deleted sandbox's bridge.
+    pub async fn clear(&self, sandbox_id: &Uuid) {
+        if let Some(bridge) = self.inner.lock().await.remove(sandbox_id) {
+            bridge.task.abort();
+        }
+    }
+}
+
+async fn run_bridge(target: VncTarget, state: ClipboardState, mut pushes: mpsc::Receiver<Push>) {
+    let result = match &target {
+        VncTarget::Tcp(addr) => match TcpStream::connect(addr).await {
+            Ok(stream) => {
+                let _ = stream.set_nodelay(true);
+                follow(stream, &state, &mut pushes).await
+            }
+            Err(e) => Err(e),
+        },
+        VncTarget::Unix(path) => match UnixStream::connect(path).await {
+            Ok(stream) => follow(stream, &state, &mut pushes).await,
+            Err(e) => Err(e),
+        },
+    };
+    match result {
+        Ok(()) => debug!(vnc_target = %target, "VNC clipboard bridge closed"),
+        Err(e) => {
+            warn!(vnc_target = %target, "VNC clipboard bridge stopped: {e}");
+            // Fail pushes queued behind the error rather than dropping them.
+            pushes.close();
+            while let Ok((_, done)) = pushes.try_recv() {
+                let _ = done.send(Err(io::Error::new(e.kind(), e.to_string())));
+            }
+        }
+    }
+}
+
+async fn follow<S>(
+    stream: S,
+    state: &ClipboardState,
+    pushes: &mut mpsc::Receiver<Push>,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (read, mut write) = tokio::io::split(stream);
+    let mut read = BufReader::new(read);
+    handshake(&mut read, &mut write).await?;
+    debug!("VNC clipboard bridge connected");
+
+    let reader = read_cut_texts(&mut read, state);
+    let writer = async {
+        while let Some((latin1, done)) = pushes.recv().await {
+            let result = write.write_all(&client_cut_text(&latin1)).await;
+            let error = result
+                .as_ref()
+                .err()
+                .map(|e| io::Error::new(e.kind(), e.to_string()));
+            if result.is_ok() {
+                state.record(decode_latin1(&latin1), VncClipboardSource::Api);
+            }
+            let _ = done.send(result);
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+        Ok(())
+    };
+    tokio::select! {
+        result = reader => result,
+        result = writer => result,
+    }
+}
+
+fn protocol_error(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reason string following a failed handshake step.
+async fn read_reason<R: AsyncRead + Unpin>(read: &mut R) -> String {
+    let Ok(len) = read.read_u32().await else {
+        return "no reason given".into();
+    };
+    let mut reason = Vec::new();
+    let _ = read
+        .take(u64::from(len.min(4096)))
+        .read_to_end(&mut reason)
+        .await;
+    String::from_utf8_lossy(&reason).into_owned()
+}
+
+/// Client side of the RFB handshake with security type None, asking to
+/// share the desktop with other viewers.
+async fn handshake<R, W>(read: &mut R, write: &mut W) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut version = [0u8; 12];
+    read.read_exact(&mut version).await?;
+    let minor = version
+        .strip_prefix(b"RFB 003.")
+        .and_then(|rest| std::str::from_utf8(&rest[..3]).ok())
+        .and_then(|minor| minor.parse::<u32>().ok())
+        .ok_or_else(|| protocol_error("not an RFB server"))?;
+    let minor = match minor {
+        8.. => 8,
+        7 => 7,
+        _ => 3,
+    };
+    write
+        .write_all(format!("RFB 003.{minor:03}\n").as_bytes())
+        .await?;
+
+    if minor == 3 {
+        match read.read_u32().await? {
+            1 => {}
+            0 => return Err(protocol_error(read_reason(read).await)),
+            other => {
+                return Err(protocol_error(format!(
+                    "VNC server requires security type {other}"
+                )))
+            }
+        }
+    } else {
+        let count = read.read_u8().await?;
+        if count == 0 {
+            return Err(protocol_error(read_reason(read).await));
+        }
+        let mut types = vec![0u8; count as usize];
+        read.read_exact(&mut types).await?;
+        if !types.contains(&SECURITY_NONE) {
+            return Err(protocol_error("VNC server requires authentication"));
+        }
+        write.write_all(&[SECURITY_NONE]).await?;
+        if minor == 8 && read.read_u32().await? != 0 {
+            return Err(protocol_error(read_reason(read).await));
+        }
+    }
+
+    // ClientInit: shared session, so viewers stay connected.
+    write.write_all(&[1]).await?;
+    write.flush().await?;
+    // ServerInit: size, pixel format, then the desktop name.
+    let mut init = [0u8; 24];
+    read.read_exact(&mut init).await?;
+    let name_len = u32::from_be_bytes([init[20], init[21], init[22], init[23]]);
+    skip(read, u64::from(name_len)).await
+}
+
+async fn skip<R: AsyncRead + Unpin>(read: &mut R, len: u64) -> io::Result<()> {
+    let skipped = tokio::io::copy(&mut read.take(len), &mut tokio::io::sink()).await?;
+    if skipped < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Record every ServerCutText until the connection fails. Without
+/// framebuffer update requests the server only sends these, bells and
+/// colour map changes.
+async fn read_cut_texts<R: AsyncRead + Unpin>(
+    read: &mut R,
+    state: &ClipboardState,
+) -> io::Result<()> {
+    loop {
+        match read.read_u8().await? {
+            // SetColourMapEntries: padding, first colour, count, 6 bytes each
+            1 => {
+                let mut header = [0u8; 5];
+                read.read_exact(&mut header).await?;
+                let count = u16::from_be_bytes([header[3], header[4]]);
+                skip(read, 6 * u64::from(count)).await?;
+            }
+            // Bell
+            2 => {}
+            // ServerCutText: padding, then a length-prefixed Latin-1 string
+            3 => {
+                skip(read, 3).await?;
+                let len = read.read_i32().await?;
+                // Negative lengths are extended clipboard messages, which
+                // this connection never negotiates.
+                let extended = len < 0;
+                let len = u64::from(len.unsigned_abs());
+                if extended || len > MAX_CUT_TEXT as u64 {
+                    skip(read, len).await?;
+                    continue;
+                }
+                let mut text = vec![0u8; len as usize];
+                read.read_exact(&mut text).await?;
+                state.record(decode_latin1(&text), VncClipboardSource::Desktop);
+            }
+            other => {
+                return Err(protocol_error(format!(
+                    "unexpected RFB server message {other}"
+                )))
+            }
+        }
+    }
+}
+
+/// ClientCutText messages in a noVNC client→server frame, decoded. Walks the
+/// frame message by message and stops at anything it can't size.
+pub fn client_cut_texts(data: &[u8]) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut rest = data;
+    while let Some(&msg_type) = rest.first() {
+        let len = match msg_type {
+            // SetPixelFormat
+            0 => 20,
+            // SetEncodings: type, padding, u16 count, then i32 per encoding
+            2 => match rest.get(2..4) {
+                Some(n) => 4 + 4 * u16::from_be_bytes([n[0], n[1]]) as usize,
+                None => break,
+            },
+            // FramebufferUpdateRequest
+            3 => 10,
+            // KeyEvent
+            4 => 8,
+            // PointerEvent
+            5 => 6,
+            CLIENT_CUT_TEXT => match rest.get(4..8) {
+                Some(n) => {
+                    let text_len = i32::from_be_bytes([n[0], n[1], n[2], n[3]]);
+                    // Extended clipboard messages carry zlib data; skip them.
+                    let Ok(text_len) = usize::try_from(text_len) else {
+                        break;
+                    };
+                    if let Some(text) = rest.get(8..8 + text_len) {
+                        texts.push(decode_latin1(text));
+                    }
+                    8 + text_len
+                }
+                None => break,
+            },
+            // Handshake or unknown messages
+            _ => break,
+        };
+        if rest.len() < len {
+            break;
+        }
+        rest = &rest[len..];
+    }
+    texts
+}
+
+/// RFB cut text is Latin-1 with bare `\n` line endings.
+fn encode_latin1(text: &str) -> Vec<u8> {
+    text.replace("\r\n", "\n")
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+fn client_cut_text(latin1: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(8 + latin1.len());
+    message.extend_from_slice(&[CLIENT_CUT_TEXT, 0, 0, 0]);
+    message.extend_from_slice(&(latin1.len() as u32).to_be_bytes());
+    message.extend_from_slice(latin1);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+
+    /// Fake Xvnc: RFB 3.8 with security None. Sends `server_text` as
+    /// ServerCutText after the handshake and returns the first ClientCutText
+    /// it gets.
+    async fn fake_vnc_server(server_text: &'static [u8]) -> (SocketAddr, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"RFB 003.008\n").await.unwrap();
+            let mut version = [0u8; 12];
+            stream.read_exact(&mut version).await.unwrap();
+            assert_eq!(&version, b"RFB 003.008\n");
+            stream.write_all(&[2, 2, SECURITY_NONE]).await.unwrap();
+            assert_eq!(stream.read_u8().await.unwrap(), SECURITY_NONE);
+            stream.write_all(&0u32.to_be_bytes()).await.unwrap();
+            assert_eq!(stream.read_u8().await.unwrap(), 1, "shared ClientInit");
+            let mut init = vec![0u8; 20];
+            init.extend_from_slice(&4u32.to_be_bytes());
+            init.extend_from_slice(b"desk");
+            stream.write_all(&init).await.unwrap();
+
+            // A bell, then the clipboard.
+            let mut cut = vec![2u8, 3, 0, 0, 0];
+            cut.extend_from_slice(&(server_text.len() as u32).to_be_bytes());
+            cut.extend_from_slice(server_text);
+            stream.write_all(&cut).await.unwrap();
+
+            let mut header = [0u8; 8];
+            stream.read_exact(&mut header).await.unwrap();
+            assert_eq!(header[0], CLIENT_CUT_TEXT);
+            let len = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+            let mut text = vec![0u8; len as usize];
+            stream.read_exact(&mut text).await.unwrap();
+            text
+        });
+        (addr, task)
+    }
+
+    #[tokio::test]
+    async fn bridges_clipboard_both_ways() {
+        let (addr, server) = fake_vnc_server(b"caf\xe9").await;
+        let clipboards = VncClipboards::new();
+        let sandbox = Uuid::new_v4();
+        let target = VncTarget::Tcp(addr);
+
+        let state = clipboards.follow(sandbox, &target).await;
+        for _ in 0..100 {
+            if state.snapshot().text.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let copied = clipboards.get(sandbox, &target).await;
+        assert_eq!(copied.text.as_deref(), Some("café"));
+        assert_eq!(copied.source, Some(VncClipboardSource::Desktop));
+
+        let pushed = clipboards
+            .set(sandbox, &target, "naïve\r\n☃")
+            .await
+            .unwrap();
+        assert_eq!(pushed.text.as_deref(), Some("naïve\n?"));
+        assert_eq!(pushed.source, Some(VncClipboardSource::Api));
+        assert_eq!(server.await.unwrap(), b"na\xefve\n?");
+
+        clipboards.clear(&sandbox).await;
+    }
+
+    #[tokio::test]
+    async fn unreachable_desktops_fail_pushes() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let clipboards = VncClipboards::new();
+        let result = clipboards
+            .set(Uuid::new_v4(), &VncTarget::Tcp(addr), "text")
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn finds_cut_text_among_client_messages() {
+        let mut frame = vec![3u8, 1, 0, 0, 0, 0, 4, 0, 3, 0];
+        frame.extend_from_slice(&[5, 0, 0, 10, 0, 20]);
+        frame.extend_from_slice(&[CLIENT_CUT_TEXT, 0, 0, 0, 0, 0, 0, 2, b'h', b'i']);
+        assert_eq!(client_cut_texts(&frame), ["hi"]);
+
+        // Extended clipboard and handshake bytes are skipped.
+        let mut extended = vec![CLIENT_CUT_TEXT, 0, 0, 0];
+        extended.extend_from_slice(&(-4i32).to_be_bytes());
+        extended.extend_from_slice(&[0, 0, 0, 1]);
+        assert!(client_cut_texts(&extended).is_empty());
+        assert!(client_cut_texts(b"RFB 003.008\n").is_empty());
+    }
+}
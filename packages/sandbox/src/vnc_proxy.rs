@@ -1,46 +1,352 @@
-//! WebSocket-to-VNC TCP proxy.
+//! WebSocket-to-VNC proxy.
 //!
-//! Proxies WebSocket connections from noVNC clients to VNC servers over TCP.
+//! Proxies WebSocket connections from noVNC clients to VNC servers over TCP, or
+//! over a Unix socket when Xvnc was started with `-rfbunixpath`.
 //! Runs in the same process as sandboxd for minimal latency.
+//!
+//! Sessions without user input (keyboard, pointer, clipboard) for longer than the
+//! configured idle timeout are closed so abandoned desktops stop holding X servers busy.
+//!
+//! A `?quality=low|medium|high` on the websocket URL rewrites the client's
+//! SetEncodings so the server uses the matching JPEG quality and compression
+//! levels, whatever the noVNC bundle asked for.
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::body::Body;
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use futures::{SinkExt, StreamExt};
+use std::fmt;
 use std::net::SocketAddr;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
-use tracing::{debug, error};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixStream};
+use tracing::{debug, error, info, warn};
+
+use crate::models::VncClipboardSource;
+use crate::vnc_clipboard::{client_cut_texts, ClipboardState};
+use crate::vnc_recording::VncRecorder;
+
+/// Default idle timeout applied when none is configured.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Idle timeout in seconds. Zero disables idle detection.
+static IDLE_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_IDLE_TIMEOUT.as_secs());
+
+/// How often the relay checks for idle sessions.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Whether new sandboxes start Xvnc with a Unix socket for the proxy.
+static USE_UNIX_SOCKET: AtomicBool = AtomicBool::new(false);
+
+/// Where a sandbox's VNC server accepts connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VncTarget {
+    Tcp(SocketAddr),
+    /// Xvnc's `-rfbunixpath` socket, as a path reachable from sandboxd.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for VncTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VncTarget::Tcp(addr) => write!(f, "{addr}"),
+            VncTarget::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Have Xvnc in new sandboxes also listen on a Unix socket, and proxy noVNC
+/// sessions through it instead of the sandbox network.
+pub fn set_use_unix_socket(enabled: bool) {
+    USE_UNIX_SOCKET.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether new sandboxes get a Unix socket VNC target.
+pub fn use_unix_socket() -> bool {
+    USE_UNIX_SOCKET.load(Ordering::SeqCst)
+}
+
+/// Set the idle timeout for VNC sessions. `None` disables the timeout.
+pub fn set_idle_timeout(timeout: Option<Duration>) {
+    let secs = timeout.map(|t| t.as_secs().max(1)).unwrap_or(0);
+    IDLE_TIMEOUT_SECS.store(secs, Ordering::SeqCst);
+}
+
+/// Get the configured idle timeout, if enabled.
+pub fn idle_timeout() -> Option<Duration> {
+    match IDLE_TIMEOUT_SECS.load(Ordering::SeqCst) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Bandwidth/fidelity trade-off requested through `?quality=` on the websocket URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VncQuality {
+    Low,
+    Medium,
+    High,
+}
+
+/// Tight JPEG quality levels 0-9 are pseudo-encodings -32..=-23.
+const QUALITY_LEVEL_BASE: i32 = -32;
+/// Compression levels 0-9 are pseudo-encodings -256..=-247.
+const COMPRESS_LEVEL_BASE: i32 = -256;
 
-/// Proxy a WebSocket connection to a VNC server over TCP.
+impl VncQuality {
+    /// Read `quality` from a websocket URL query string. Absent means the
+    /// client's own encodings are left alone.
+    pub fn from_query(query: &str) -> Result<Option<Self>, String> {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "quality")
+            .map(|(_, value)| value.parse())
+            .transpose()
+    }
+
+    /// (JPEG quality level, compression level), both 0-9.
+    fn levels(self) -> (i32, i32) {
+        match self {
+            VncQuality::Low => (2, 9),
+            VncQuality::Medium => (5, 6),
+            VncQuality::High => (8, 2),
+        }
+    }
+
+    /// The SetEncodings list to send instead of `encodings`: the client's
+    /// quality and compression pseudo-encodings are replaced with ours.
+    fn rewrite_encodings(self, encodings: &[i32]) -> Vec<i32> {
+        let (quality, compress) = self.levels();
+        let mut rewritten: Vec<i32> = encodings
+            .iter()
+            .copied()
+            .filter(|e| {
+                !(QUALITY_LEVEL_BASE..QUALITY_LEVEL_BASE + 10).contains(e)
+                    && !(COMPRESS_LEVEL_BASE..COMPRESS_LEVEL_BASE + 10).contains(e)
+            })
+            .collect();
+        rewritten.push(QUALITY_LEVEL_BASE + quality);
+        rewritten.push(COMPRESS_LEVEL_BASE + compress);
+        rewritten
+    }
+}
+
+impl FromStr for VncQuality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(VncQuality::Low),
+            "medium" => Ok(VncQuality::Medium),
+            "high" => Ok(VncQuality::High),
+            other => Err(format!(
+                "unknown quality {other:?}, expected low, medium or high"
+            )),
+        }
+    }
+}
+
+/// Rewrite any SetEncodings in a client→server payload for `quality`.
+///
+/// Returns `None` when the payload has no SetEncodings, or contains something
+/// we cannot walk (handshake bytes, truncated or unknown messages), in which
+/// case it is forwarded untouched.
+fn apply_quality(data: &[u8], quality: VncQuality) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() + 8);
+    let mut rewrote = false;
+    let mut rest = data;
+    while let Some(&msg_type) = rest.first() {
+        let len = match msg_type {
+            // SetPixelFormat
+            0 => 20,
+            2 => {
+                let count = u16::from_be_bytes(rest.get(2..4)?.try_into().ok()?) as usize;
+                let list = rest.get(4..4 + 4 * count)?;
+                let encodings: Vec<i32> = list
+                    .chunks_exact(4)
+                    .map(|e| i32::from_be_bytes([e[0], e[1], e[2], e[3]]))
+                    .collect();
+                let encodings = quality.rewrite_encodings(&encodings);
+                out.extend_from_slice(&[2, 0]);
+                out.extend_from_slice(&(encodings.len() as u16).to_be_bytes());
+                for encoding in encodings {
+                    out.extend_from_slice(&encoding.to_be_bytes());
+                }
+                rewrote = true;
+                rest = &rest[4 + 4 * count..];
+                continue;
+            }
+            // FramebufferUpdateRequest
+            3 => 10,
+            // KeyEvent
+            4 => 8,
+            // PointerEvent
+            5 => 6,
+            // ClientCutText; a negative length is the extended-clipboard form
+            6 => {
+                let n = i32::from_be_bytes(rest.get(4..8)?.try_into().ok()?);
+                8 + n.unsigned_abs() as usize
+            }
+            _ => return None,
+        };
+        out.extend_from_slice(rest.get(..len)?);
+        rest = &rest[len..];
+    }
+    rewrote.then_some(out)
+}
+
+/// Returns true if a client→server payload contains user input.
+///
+/// noVNC continuously sends FramebufferUpdateRequests even when nobody is at the
+/// keyboard, so raw traffic is not a useful activity signal. We walk the RFB client
+/// messages in the frame and only count KeyEvent, PointerEvent and ClientCutText.
+/// Anything we cannot parse (handshake, unknown extensions) counts as activity.
+fn contains_input_event(data: &[u8]) -> bool {
+    let mut rest = data;
+    while let Some(&msg_type) = rest.first() {
+        let len = match msg_type {
+            // SetPixelFormat
+            0 => 20,
+            // SetEncodings: type, padding, u16 count, then i32 per encoding
+            2 => match rest.get(2..4) {
+                Some(n) => 4 + 4 * u16::from_be_bytes([n[0], n[1]]) as usize,
+                None => return true,
+            },
+            // FramebufferUpdateRequest
+            3 => 10,
+            // KeyEvent, PointerEvent, ClientCutText, or handshake/unknown bytes
+            _ => return true,
+        };
+        if rest.len() < len {
+            return true;
+        }
+        rest = &rest[len..];
+    }
+    false
+}
+
+/// Milliseconds elapsed since `start`, used for lock-free activity timestamps.
+fn millis_since(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Where noVNC's static files are installed.
+pub const NOVNC_DIR: &str = "/usr/share/novnc";
+
+/// Serve a noVNC static file at `path` from `base_dir`.
+pub async fn novnc_static_response(base_dir: &Path, path: &str) -> Response {
+    // Sanitize path to prevent directory traversal attacks
+    let requested = base_dir.join(path.trim_start_matches('/'));
+    let (Ok(canonical), Ok(base_dir)) = (requested.canonicalize(), base_dir.canonicalize()) else {
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    };
+
+    // Verify the canonical path is still under the base directory
+    if !canonical.starts_with(&base_dir) {
+        warn!(path = %path, "blocked directory traversal attempt");
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+
+    let file_path = canonical.to_string_lossy().to_string();
+    debug!(file_path = %file_path, "serving noVNC static file");
+
+    match tokio::fs::read(&file_path).await {
+        Ok(contents) => {
+            let content_type = match file_path.rsplit('.').next() {
+                Some("html") => "text/html; charset=utf-8",
+                Some("js") => "application/javascript",
+                Some("css") => "text/css",
+                Some("png") => "image/png",
+                Some("svg") => "image/svg+xml",
+                Some("ico") => "image/x-icon",
+                _ => "application/octet-stream",
+            };
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", content_type)
+                .header("Cache-Control", "public, max-age=3600")
+                .body(Body::from(contents))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+        Err(_) => (StatusCode::NOT_FOUND, "File not found").into_response(),
+    }
+}
+
+/// Proxy a WebSocket connection to a VNC server.
 ///
 /// This function handles the bidirectional relay between a noVNC WebSocket client
 /// and a VNC server (e.g., TigerVNC's Xvnc). The RFB protocol uses binary frames.
 ///
 /// # Arguments
 /// * `client_socket` - The WebSocket connection from the noVNC client
-/// * `vnc_addr` - The address of the VNC server (e.g., "10.201.0.2:5910")
+/// * `target` - The VNC server, e.g. `10.201.0.2:5910` or Xvnc's Unix socket
+/// * `recorder` - Records the server→client stream for later replay
+/// * `clipboard` - Updated with text the viewer pastes into its clipboard
+/// * `quality` - Overrides the quality and compression levels the client asks for
 pub async fn proxy_vnc_websocket(
     client_socket: WebSocket,
-    vnc_addr: SocketAddr,
+    target: VncTarget,
+    recorder: Option<VncRecorder>,
+    clipboard: Option<ClipboardState>,
+    quality: Option<VncQuality>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    debug!("Connecting to VNC server at {}", vnc_addr);
-
-    // Connect to VNC server
-    let stream = TcpStream::connect(vnc_addr).await?;
+    debug!("Connecting to VNC server at {}", target);
 
-    // Enable TCP_NODELAY for low-latency interactive sessions
-    stream.set_nodelay(true)?;
-
-    debug!("Connected to VNC server, TCP_NODELAY enabled");
+    match &target {
+        VncTarget::Tcp(addr) => {
+            let stream = TcpStream::connect(addr).await?;
+            // Enable TCP_NODELAY for low-latency interactive sessions
+            stream.set_nodelay(true)?;
+            debug!("Connected to VNC server, TCP_NODELAY enabled");
+            relay(client_socket, stream, &target, recorder, clipboard, quality).await
+        }
+        VncTarget::Unix(path) => {
+            let stream = UnixStream::connect(path).await?;
+            debug!("Connected to VNC server over Unix socket");
+            relay(client_socket, stream, &target, recorder, clipboard, quality).await
+        }
+    }
+}
 
-    let (mut tcp_read, mut tcp_write) = stream.into_split();
+async fn relay<S>(
+    client_socket: WebSocket,
+    stream: S,
+    target: &VncTarget,
+    mut recorder: Option<VncRecorder>,
+    clipboard: Option<ClipboardState>,
+    quality: Option<VncQuality>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut vnc_read, mut vnc_write) = tokio::io::split(stream);
     let (mut ws_sink, mut ws_stream) = client_socket.split();
 
-    // Spawn task to forward WebSocket -> TCP
-    let ws_to_tcp = tokio::spawn(async move {
+    let idle_timeout = idle_timeout();
+    let started = Instant::now();
+    let last_input = Arc::new(AtomicU64::new(0));
+    let last_input_writer = last_input.clone();
+
+    // Spawn task to forward WebSocket -> VNC
+    let ws_to_vnc = tokio::spawn(async move {
         while let Some(msg_result) = ws_stream.next().await {
             match msg_result {
                 Ok(Message::Binary(data)) => {
-                    if tcp_write.write_all(&data).await.is_err() {
+                    if contains_input_event(&data) {
+                        last_input_writer.store(millis_since(started), Ordering::Relaxed);
+                        if let Some(clipboard) = &clipboard {
+                            for text in client_cut_texts(&data) {
+                                clipboard.record(text, VncClipboardSource::Viewer);
+                            }
+                        }
+                    }
+                    let rewritten = quality.and_then(|q| apply_quality(&data, q));
+                    let data = rewritten.as_deref().unwrap_or(&data);
+                    if vnc_write.write_all(data).await.is_err() {
                         break;
                     }
                 }
@@ -61,37 +367,167 @@ pub async fn proxy_vnc_websocket(
                 }
             }
         }
-        debug!("WebSocket -> TCP relay ended");
+        debug!("WebSocket -> VNC relay ended");
     });
 
-    // Forward TCP -> WebSocket in main task
+    // Forward VNC -> WebSocket in main task
     let mut buf = vec![0u8; 16384]; // 16KB buffer for VNC framebuffer data
+    let mut idle_check = tokio::time::interval(IDLE_CHECK_INTERVAL);
+    let mut idle_close = None;
     loop {
-        match tcp_read.read(&mut buf).await {
-            Ok(0) => {
-                debug!("VNC server closed connection");
-                break;
-            }
-            Ok(n) => {
-                if ws_sink
-                    .send(Message::Binary(buf[..n].to_vec().into()))
-                    .await
-                    .is_err()
-                {
+        tokio::select! {
+            read = vnc_read.read(&mut buf) => match read {
+                Ok(0) => {
+                    debug!("VNC server closed connection");
+                    break;
+                }
+                Ok(n) => {
+                    if let Some(rec) = recorder.as_mut() {
+                        if let Err(e) = rec.record(&buf[..n]).await {
+                            warn!("VNC recording stopped: {}", e);
+                            recorder = None;
+                        }
+                    }
+                    if ws_sink
+                        .send(Message::Binary(buf[..n].to_vec().into()))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!("VNC read error: {}", e);
+                    break;
+                }
+            },
+            _ = idle_check.tick(), if idle_timeout.is_some() => {
+                let Some(timeout) = idle_timeout else { continue };
+                let idle_for = Duration::from_millis(
+                    millis_since(started).saturating_sub(last_input.load(Ordering::Relaxed)),
+                );
+                if idle_for >= timeout {
+                    info!(
+                        vnc_target = %target,
+                        idle_secs = idle_for.as_secs(),
+                        "closing idle VNC session"
+                    );
+                    idle_close = Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: format!("idle for {} minutes", idle_for.as_secs() / 60).into(),
+                    });
                     break;
                 }
-            }
-            Err(e) => {
-                error!("TCP read error: {}", e);
-                break;
             }
         }
     }
 
-    // Clean up
-    ws_to_tcp.abort();
+    // Clean up: aborting the relay task drops the write half, tearing down the VNC side.
+    ws_to_vnc.abort();
+    drop(vnc_read);
+    if let Some(frame) = idle_close {
+        let _ = ws_sink.send(Message::Close(Some(frame))).await;
+    }
     let _ = ws_sink.close().await;
+    if let Some(rec) = recorder {
+        if let Err(e) = rec.finish().await {
+            warn!("failed to flush VNC recording: {}", e);
+        }
+    }
 
     debug!("VNC proxy session ended");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framebuffer_update_requests_are_not_input() {
+        let mut data = vec![3u8, 1, 0, 0, 0, 0, 4, 0, 3, 0];
+        data.extend_from_slice(&[3u8, 1, 0, 0, 0, 0, 4, 0, 3, 0]);
+        assert!(!contains_input_event(&data));
+    }
+
+    #[test]
+    fn set_encodings_is_skipped_by_length() {
+        // SetEncodings with two encodings followed by a FramebufferUpdateRequest
+        let mut data = vec![2u8, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1];
+        data.extend_from_slice(&[3u8, 1, 0, 0, 0, 0, 4, 0, 3, 0]);
+        assert!(!contains_input_event(&data));
+    }
+
+    #[test]
+    fn key_and_pointer_events_are_input() {
+        assert!(contains_input_event(&[4, 1, 0, 0, 0, 0, 0, 0x61]));
+        assert!(contains_input_event(&[5, 0, 0, 10, 0, 20]));
+        let mut data = vec![3u8, 1, 0, 0, 0, 0, 4, 0, 3, 0];
+        data.extend_from_slice(&[5, 1, 0, 10, 0, 20]);
+        assert!(contains_input_event(&data));
+    }
+
+    #[test]
+    fn handshake_and_truncated_messages_count_as_activity() {
+        assert!(contains_input_event(b"RFB 003.008\n"));
+        assert!(contains_input_event(&[3, 1, 0]));
+    }
+
+    fn set_encodings(encodings: &[i32]) -> Vec<u8> {
+        let mut data = vec![2u8, 0];
+        data.extend_from_slice(&(encodings.len() as u16).to_be_bytes());
+        for e in encodings {
+            data.extend_from_slice(&e.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn quality_replaces_client_levels() {
+        // Tight, ZRLE, Raw, quality 6, compress 2, DesktopSize
+        let mut data = set_encodings(&[7, 16, 0, -26, -254, -223]);
+        data.extend_from_slice(&[3u8, 0, 0, 0, 0, 0, 4, 0, 3, 0]);
+
+        let mut expected = set_encodings(&[7, 16, 0, -223, -30, -247]);
+        expected.extend_from_slice(&[3u8, 0, 0, 0, 0, 0, 4, 0, 3, 0]);
+        assert_eq!(apply_quality(&data, VncQuality::Low), Some(expected));
+
+        let high = apply_quality(&set_encodings(&[7]), VncQuality::High);
+        assert_eq!(high, Some(set_encodings(&[7, -24, -254])));
+    }
+
+    #[test]
+    fn payloads_without_set_encodings_pass_through() {
+        assert_eq!(apply_quality(b"RFB 003.008\n", VncQuality::Low), None);
+        assert_eq!(apply_quality(&[5, 0, 0, 10, 0, 20], VncQuality::Low), None);
+        // Truncated SetEncodings
+        assert_eq!(apply_quality(&[2, 0, 0, 2, 0, 0], VncQuality::Low), None);
+    }
+
+    #[test]
+    fn quality_is_read_from_the_query() {
+        assert_eq!(VncQuality::from_query(""), Ok(None));
+        assert_eq!(
+            VncQuality::from_query("token=x&quality=medium"),
+            Ok(Some(VncQuality::Medium))
+        );
+        assert!(VncQuality::from_query("quality=ultra").is_err());
+    }
+
+    #[test]
+    fn targets_display_as_address_or_socket_path() {
+        let tcp = VncTarget::Tcp(SocketAddr::from(([10, 201, 0, 2], 5910)));
+        assert_eq!(tcp.to_string(), "10.201.0.2:5910");
+        let unix = VncTarget::Unix("/proc/42/root/tmp/.cmux-vnc-10.sock".into());
+        assert_eq!(unix.to_string(), "unix:/proc/42/root/tmp/.cmux-vnc-10.sock");
+    }
+
+    #[test]
+    fn idle_timeout_can_be_disabled() {
+        set_idle_timeout(None);
+        assert_eq!(idle_timeout(), None);
+        set_idle_timeout(Some(Duration::from_secs(120)));
+        assert_eq!(idle_timeout(), Some(Duration::from_secs(120)));
+        set_idle_timeout(Some(DEFAULT_IDLE_TIMEOUT));
+    }
+}
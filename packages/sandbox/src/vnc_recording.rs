@@ -0,0 +1,242 @@
+//! Recording and replay of noVNC sessions.
+//!
+//! When a recording directory is configured, the VNC proxy writes everything the
+//! VNC server sends to the browser into a `.vncrec` file: a magic header followed
+//! by chunks of `[u64 millis since start][u32 length][bytes]` (big-endian). The
+//! server→client RFB stream alone is enough for noVNC to redraw the session, so
+//! [`serve_replay`] plays a file back to a browser with the original timing.
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Request, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::Router;
+use futures::{SinkExt, StreamExt};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use tracing::{debug, info};
+
+use crate::vnc_proxy::novnc_static_response;
+
+/// First bytes of every recording.
+const MAGIC: &[u8; 8] = b"CMUXVNC1";
+
+/// Chunks larger than this are treated as corruption rather than allocated.
+const MAX_CHUNK_LEN: u32 = 64 * 1024 * 1024;
+
+/// Directory new sessions are recorded to. `None` disables recording.
+static RECORDING_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Record new noVNC sessions into `dir`, or stop recording with `None`.
+pub fn set_recording_dir(dir: Option<PathBuf>) {
+    *RECORDING_DIR.lock().unwrap() = dir;
+}
+
+/// Where new sessions are recorded, if recording is enabled.
+pub fn recording_dir() -> Option<PathBuf> {
+    RECORDING_DIR.lock().unwrap().clone()
+}
+
+/// A chunk of server→client bytes and when it was sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedChunk {
+    pub at: Duration,
+    pub data: Vec<u8>,
+}
+
+/// Writes one session's server→client stream to disk.
+pub struct VncRecorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+impl VncRecorder {
+    /// Start a recording named after `label` and the current time in `dir`.
+    pub async fn create(dir: &Path, label: &str) -> io::Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+        let path = dir.join(format!("{label}-{stamp}.vncrec"));
+        let mut file = BufWriter::new(File::create(&path).await?);
+        file.write_all(MAGIC).await?;
+        Ok(Self {
+            path,
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append bytes the VNC server just sent.
+    pub async fn record(&mut self, data: &[u8]) -> io::Result<()> {
+        let at = self.started.elapsed().as_millis() as u64;
+        self.file.write_all(&at.to_be_bytes()).await?;
+        self.file
+            .write_all(&(data.len() as u32).to_be_bytes())
+            .await?;
+        self.file.write_all(data).await
+    }
+
+    /// Flush buffered chunks to disk.
+    pub async fn finish(mut self) -> io::Result<()> {
+        self.file.flush().await
+    }
+}
+
+/// Read every chunk of a recording. A chunk cut short (the proxy was killed
+/// mid-write) ends the recording instead of failing it.
+pub async fn read_recording(path: &Path) -> io::Result<Vec<RecordedChunk>> {
+    let mut bytes = Vec::new();
+    File::open(path).await?.read_to_end(&mut bytes).await?;
+    parse_recording(&bytes)
+}
+
+fn parse_recording(bytes: &[u8]) -> io::Result<Vec<RecordedChunk>> {
+    let mut rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a VNC recording"))?;
+    let mut chunks = Vec::new();
+    while rest.len() >= 12 {
+        let at = u64::from_be_bytes(rest[..8].try_into().unwrap());
+        let len = u32::from_be_bytes(rest[8..12].try_into().unwrap());
+        if len > MAX_CHUNK_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chunk of {len} bytes is too large"),
+            ));
+        }
+        let Some(data) = rest.get(12..12 + len as usize) else {
+            break;
+        };
+        chunks.push(RecordedChunk {
+            at: Duration::from_millis(at),
+            data: data.to_vec(),
+        });
+        rest = &rest[12 + len as usize..];
+    }
+    Ok(chunks)
+}
+
+struct ReplayState {
+    chunks: Arc<Vec<RecordedChunk>>,
+    novnc_dir: PathBuf,
+    speed: f64,
+}
+
+/// Serve noVNC from `novnc_dir` on `listen` and play `recording` to every
+/// browser that connects, `speed` times faster than it was recorded.
+pub async fn serve_replay(
+    listen: SocketAddr,
+    recording: &Path,
+    novnc_dir: PathBuf,
+    speed: f64,
+) -> io::Result<()> {
+    let chunks = read_recording(recording).await?;
+    let length = chunks.last().map(|c| c.at).unwrap_or_default();
+    let state = Arc::new(ReplayState {
+        chunks: Arc::new(chunks),
+        novnc_dir,
+        speed: if speed > 0.0 { speed } else { 1.0 },
+    });
+    let app = Router::new()
+        .fallback(any(replay_handler))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!(
+        addr = %listener.local_addr()?,
+        recording = %recording.display(),
+        length_secs = length.as_secs(),
+        "serving VNC recording"
+    );
+    axum::serve(listener, app).await
+}
+
+async fn replay_handler(
+    State(state): State<Arc<ReplayState>>,
+    ws: Result<WebSocketUpgrade, axum::extract::ws::rejection::WebSocketUpgradeRejection>,
+    req: Request,
+) -> Response {
+    if let Ok(ws) = ws {
+        let chunks = state.chunks.clone();
+        let speed = state.speed;
+        return ws.on_upgrade(move |socket| replay_session(socket, chunks, speed));
+    }
+    let path = match req.uri().path() {
+        "/" | "" => "/vnc.html",
+        path => path,
+    };
+    novnc_static_response(&state.novnc_dir, path)
+        .await
+        .into_response()
+}
+
+/// Send the recorded server stream with its original pacing. Anything the
+/// browser sends is read and dropped so its handshake doesn't stall.
+async fn replay_session(socket: WebSocket, chunks: Arc<Vec<RecordedChunk>>, speed: f64) {
+    let (mut sink, mut stream) = socket.split();
+    let drain = tokio::spawn(async move { while let Some(Ok(_)) = stream.next().await {} });
+    let started = tokio::time::Instant::now();
+    for chunk in chunks.iter() {
+        tokio::time::sleep_until(started + chunk.at.div_f64(speed)).await;
+        if sink
+            .send(Message::Binary(chunk.data.clone().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    debug!("VNC replay finished");
+    // Leave the last frame on screen until the viewer closes the tab.
+    let _ = drain.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn recordings_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut recorder = VncRecorder::create(dir.path(), "sandbox-0").await.unwrap();
+        recorder.record(b"RFB 003.008\n").await.unwrap();
+        recorder.record(&[0, 0, 0, 1]).await.unwrap();
+        let path = recorder.path().to_path_buf();
+        recorder.finish().await.unwrap();
+
+        let chunks = read_recording(&path).await.unwrap();
+        let data: Vec<&[u8]> = chunks.iter().map(|c| c.data.as_slice()).collect();
+        assert_eq!(data, [b"RFB 003.008\n".as_slice(), &[0, 0, 0, 1]]);
+        assert!(chunks[0].at <= chunks[1].at);
+    }
+
+    #[test]
+    fn truncated_tail_is_dropped_and_garbage_rejected() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&5u64.to_be_bytes());
+        bytes.extend_from_slice(&2u32.to_be_bytes());
+        bytes.extend_from_slice(b"ok");
+        bytes.extend_from_slice(&9u64.to_be_bytes());
+        bytes.extend_from_slice(&100u32.to_be_bytes());
+        bytes.extend_from_slice(b"cut");
+        let chunks = parse_recording(&bytes).unwrap();
+        assert_eq!(
+            chunks,
+            [RecordedChunk {
+                at: Duration::from_millis(5),
+                data: b"ok".to_vec()
+            }]
+        );
+
+        assert!(parse_recording(b"RFB 003.008\n").is_err());
+    }
+}
@@ -47,6 +47,7 @@ async fn test_agent_config_files_exist_in_sandbox() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary = service.create(req).await.expect("Failed to create sandbox");
 
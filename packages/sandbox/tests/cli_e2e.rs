@@ -1,12 +1,14 @@
 use assert_cmd::Command;
 use axum::body::Body;
 use axum::Router;
+use cmux_sandbox::auth::{AuthConfig, MuxScope, SandboxOwners};
 use cmux_sandbox::build_router;
 use cmux_sandbox::models::{
     CreateSandboxRequest, ExecRequest, ExecResponse, SandboxNetwork, SandboxStatus, SandboxSummary,
 };
 use cmux_sandbox::notifications::NotificationStore;
 use cmux_sandbox::service::SandboxService;
+use cmux_sandbox::shutdown::ShutdownState;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -26,6 +28,9 @@ fn make_test_router(service: Arc<MockService>) -> Router {
         gh_responses,
         gh_auth_cache,
         notifications,
+        ShutdownState::new(),
+        AuthConfig::new(),
+        SandboxOwners::new(),
     )
 }
 
@@ -74,6 +79,7 @@ impl SandboxService for MockService {
             },
             display: None,
             correlation_id: None,
+            resources: None,
         };
         let mut guard = self.sandboxes.lock().await;
         guard.push(summary.clone());
@@ -122,6 +128,7 @@ impl SandboxService for MockService {
         _host_event_rx: cmux_sandbox::service::HostEventReceiver,
         _gh_responses: cmux_sandbox::service::GhResponseRegistry,
         _gh_auth_cache: cmux_sandbox::service::GhAuthCache,
+        _scope: MuxScope,
     ) -> cmux_sandbox::errors::SandboxResult<()> {
         Ok(())
     }
@@ -248,6 +255,7 @@ async fn cli_can_list_and_create_via_http() {
         read_only_paths: Vec::new(),
         tmpfs: Vec::new(),
         env: Vec::new(),
+        resources: None,
     })
     .unwrap();
     let created = client
@@ -307,6 +315,16 @@ fn cli_help_exits_quickly() {
         .stdout(predicates::str::contains("cmux sandbox controller"));
 }
 
+#[test]
+fn cli_generates_shell_completions() {
+    Command::new(assert_cmd::cargo::cargo_bin!("cmux"))
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("_cmux()"))
+        .stdout(predicates::str::contains("--auth-token"));
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn cli_exec_shorthand() {
     let service = Arc::new(MockService::new());
@@ -36,6 +36,7 @@ async fn test_filesystem_isolation() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary_a = service
         .create(req_a)
@@ -66,6 +67,7 @@ async fn test_filesystem_isolation() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary_b = service
         .create(req_b)
@@ -24,6 +24,7 @@ async fn test_global_binaries_in_sandbox() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
 
     let resp = client
@@ -39,6 +39,7 @@ async fn test_network_connectivity_apt_update() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary = service.create(req).await.expect("Failed to create sandbox");
 
@@ -39,6 +39,7 @@ async fn test_package_isolation() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary_a = service.create(req_a).await.expect("Failed to create A");
 
@@ -104,6 +105,7 @@ async fn test_package_isolation() {
         read_only_paths: vec![],
         tmpfs: vec![],
         env: vec![],
+        resources: None,
     };
     let summary_b = service.create(req_b).await.expect("Failed to create B");
 
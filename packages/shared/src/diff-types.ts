@@ -14,5 +14,19 @@ export interface ReplaceDiffEntry {
   oldSize?: number;
   newSize?: number;
   patchSize?: number;
+  /** Set when the entry is a submodule pointer rather than a file. */
+  submodule?: {
+    oldSha?: string;
+    newSha?: string;
+  };
+  /** Blob ids of each side, for fetching full contents with `gitFileAtRef`. */
+  oldSha?: string;
+  newSha?: string;
+  /** Set when `oldContent`/`newContent` hold only the head and tail of the file. */
+  truncation?: {
+    windowBytes: number;
+    oldTruncated: boolean;
+    newTruncated: boolean;
+  };
 }
 